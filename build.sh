@@ -10,12 +10,14 @@ if cargo --version &>/dev/null; then
   mkdir -p bin
   mv ./target/release/oxidate bin/
   mv ./target/release/ignite bin/
+  mv ./target/release/bcdiff bin/
   echo "Build complete. Executables are in the bin directory."
 
   echo "Adding temporary aliases for executables..."
   CWD=$(pwd)
   alias oxidate="$CWD/bin/oxidate"
   alias ignite="$CWD/bin/ignite"
+  alias bcdiff="$CWD/bin/bcdiff"
 
   echo "To use the executables, run the following commands:"
   echo "oxidate --help"
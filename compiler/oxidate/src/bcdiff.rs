@@ -0,0 +1,519 @@
+use bytecode::ByteCode;
+
+const TOP_LEVEL: &str = "top level";
+
+/// An instruction that differs between the old and new bytecode, with its address in each.
+#[derive(Debug, PartialEq)]
+pub enum Change {
+    Added {
+        new_addr: usize,
+        instr: ByteCode,
+    },
+    Removed {
+        old_addr: usize,
+        instr: ByteCode,
+    },
+    Changed {
+        old_addr: usize,
+        new_addr: usize,
+        old: ByteCode,
+        new: ByteCode,
+    },
+}
+
+/// The difference in one function, or in the code outside of every function.
+#[derive(Debug, PartialEq)]
+pub enum SegmentDiff {
+    Added {
+        name: String,
+        start: usize,
+        len: usize,
+    },
+    Removed {
+        name: String,
+        start: usize,
+        len: usize,
+    },
+    Changed {
+        name: String,
+        changes: Vec<Change>,
+    },
+}
+
+// The instructions that belong to a fn and not to a fn nested in it. Top level code is a segment too
+struct Segment {
+    name: String,
+    addrs: Vec<usize>,
+}
+
+// Where every instruction of a program belongs
+struct Layout {
+    segments: Vec<Segment>,
+    // (first address of the body, segment) for every fn
+    fn_starts: Vec<(usize, usize)>,
+}
+
+impl Layout {
+    // A fn compiles to LDF(start), GOTO(end), the body from start up to end, then ASSIGN(name) at end. Nested fns
+    // of position independent code use the relative forms
+    fn new(code: &[ByteCode]) -> Layout {
+        let mut fns: Vec<(usize, usize, usize, String)> = vec![]; // (ldf addr, start, end, name)
+        for (i, instr) in code.iter().enumerate() {
+            let (ByteCode::LDF(..) | ByteCode::LDFR(..)) = instr else {
+                continue;
+            };
+
+            let goto = code.get(i + 1);
+            let Some(ByteCode::GOTO(_) | ByteCode::GOTOR(_)) = goto else {
+                continue;
+            };
+
+            let (Some(start), Some(end)) = (instr.target(i), goto.and_then(|g| g.target(i + 1)))
+            else {
+                continue;
+            };
+
+            if start != i + 2 || end < start || end > code.len() {
+                continue;
+            }
+
+            let name = match code.get(end) {
+                Some(ByteCode::ASSIGN(name)) => name.to_owned(),
+                _ => format!("<anonymous@{}>", start),
+            };
+            fns.push((i, start, end, name));
+        }
+
+        // innermost fn containing addr, fns are in order of their start so the last match is the innermost
+        let innermost = |addr: usize| {
+            fns.iter()
+                .rposition(|(_, start, end, _)| *start <= addr && addr < *end)
+        };
+
+        let mut segments = vec![Segment {
+            name: TOP_LEVEL.to_string(),
+            addrs: vec![],
+        }];
+        let mut fn_starts = vec![];
+        for (ldf, start, _, name) in fns.iter() {
+            let mut path = match innermost(*ldf) {
+                Some(parent) => format!("{}::{}", segments[parent + 1].name, name),
+                None => name.to_owned(),
+            };
+
+            // a fn declared again with the same name
+            let count = segments
+                .iter()
+                .filter(|seg| seg.name == path || seg.name.starts_with(&format!("{}#", path)))
+                .count();
+            if count > 0 {
+                path = format!("{}#{}", path, count + 1);
+            }
+
+            fn_starts.push((*start, segments.len()));
+            segments.push(Segment {
+                name: path,
+                addrs: vec![],
+            });
+        }
+
+        for addr in 0..code.len() {
+            let seg = innermost(addr).map(|f| f + 1).unwrap_or(0);
+            segments[seg].addrs.push(addr);
+        }
+
+        Layout {
+            segments,
+            fn_starts,
+        }
+    }
+
+    fn fn_name(&self, addr: usize) -> Option<&str> {
+        self.fn_starts
+            .iter()
+            .find(|(start, _)| *start == addr)
+            .map(|(_, seg)| self.segments[*seg].name.as_str())
+    }
+
+    // What an instruction means independent of its address, used to align the old and new code. Fns are loaded
+    // by name, other jump targets are left out and checked once the code is aligned
+    fn key(&self, addr: usize, instr: &ByteCode) -> String {
+        match (instr, instr.target(addr)) {
+            (ByteCode::LDF(_, prms) | ByteCode::LDFR(_, prms), Some(start)) => {
+                match self.fn_name(start) {
+                    Some(name) => format!("{}(fn {}, {:?})", instr.opcode(), name, prms),
+                    None => format!("{}({:?})", instr.opcode(), prms),
+                }
+            }
+            (_, Some(_)) => instr.opcode().to_string(),
+            (_, None) => format!("{:?}", instr),
+        }
+    }
+}
+
+/// Diff two compiled programs function by function. Functions are matched by name, nested ones by their path
+/// e.g `outer::inner`, and jump targets are compared by where they land within their function, so a change in one
+/// function doesn't show up as changed addresses in every function after it.
+pub fn diff_bytecode(old: &[ByteCode], new: &[ByteCode]) -> Vec<SegmentDiff> {
+    let old_layout = Layout::new(old);
+    let new_layout = Layout::new(new);
+
+    // align each fn in both programs, remembering where every kept or changed instruction went
+    let mut aligned: Vec<(&Segment, &Segment, Vec<Edit>)> = vec![];
+    let mut moved_to: Vec<Option<usize>> = vec![None; old.len() + 1];
+    moved_to[old.len()] = Some(new.len()); // jumping to the end
+    for old_seg in old_layout.segments.iter() {
+        let Some(new_seg) = new_layout
+            .segments
+            .iter()
+            .find(|seg| seg.name == old_seg.name)
+        else {
+            continue;
+        };
+
+        let old_keys: Vec<String> = old_seg
+            .addrs
+            .iter()
+            .map(|addr| old_layout.key(*addr, &old[*addr]))
+            .collect();
+        let new_keys: Vec<String> = new_seg
+            .addrs
+            .iter()
+            .map(|addr| new_layout.key(*addr, &new[*addr]))
+            .collect();
+
+        let edits = diff_keys(&old_keys, &new_keys);
+        for edit in edits.iter() {
+            if let Edit::Same(i, j) | Edit::Changed(i, j) = edit {
+                moved_to[old_seg.addrs[*i]] = Some(new_seg.addrs[*j]);
+            }
+        }
+        aligned.push((old_seg, new_seg, edits));
+    }
+
+    let mut diffs = vec![];
+    for old_seg in old_layout.segments.iter() {
+        let Some((_, new_seg, edits)) = aligned.iter().find(|(seg, _, _)| seg.name == old_seg.name)
+        else {
+            diffs.push(SegmentDiff::Removed {
+                name: old_seg.name.to_owned(),
+                start: old_seg.addrs.first().copied().unwrap_or(0),
+                len: old_seg.addrs.len(),
+            });
+            continue;
+        };
+
+        let changes: Vec<Change> = edits
+            .iter()
+            .filter_map(|edit| {
+                let (i, j) = match *edit {
+                    Edit::Same(i, j) | Edit::Changed(i, j) => (i, j),
+                    Edit::Added(j) => {
+                        let new_addr = new_seg.addrs[j];
+                        return Some(Change::Added {
+                            new_addr,
+                            instr: new[new_addr].clone(),
+                        });
+                    }
+                    Edit::Removed(i) => {
+                        let old_addr = old_seg.addrs[i];
+                        return Some(Change::Removed {
+                            old_addr,
+                            instr: old[old_addr].clone(),
+                        });
+                    }
+                };
+
+                let (old_addr, new_addr) = (old_seg.addrs[i], new_seg.addrs[j]);
+                // a kept jump has changed if it no longer lands on where its old target went. Fns were
+                // already matched by name
+                let same_target = match (
+                    old[old_addr].target(old_addr),
+                    new[new_addr].target(new_addr),
+                ) {
+                    (Some(old_target), Some(new_target))
+                        if old_layout.fn_name(old_target).is_none() =>
+                    {
+                        moved_to.get(old_target).copied().flatten() == Some(new_target)
+                    }
+                    _ => true,
+                };
+
+                if matches!(edit, Edit::Same(..)) && same_target {
+                    return None;
+                }
+
+                Some(Change::Changed {
+                    old_addr,
+                    new_addr,
+                    old: old[old_addr].clone(),
+                    new: new[new_addr].clone(),
+                })
+            })
+            .collect();
+
+        if !changes.is_empty() {
+            diffs.push(SegmentDiff::Changed {
+                name: old_seg.name.to_owned(),
+                changes,
+            });
+        }
+    }
+
+    for new_seg in new_layout.segments.iter() {
+        if !old_layout
+            .segments
+            .iter()
+            .any(|seg| seg.name == new_seg.name)
+        {
+            diffs.push(SegmentDiff::Added {
+                name: new_seg.name.to_owned(),
+                start: new_seg.addrs.first().copied().unwrap_or(0),
+                len: new_seg.addrs.len(),
+            });
+        }
+    }
+
+    diffs
+}
+
+// Index into the old keys, the new keys or both
+#[derive(Debug, PartialEq)]
+enum Edit {
+    Same(usize, usize),
+    Added(usize),
+    Removed(usize),
+    Changed(usize, usize),
+}
+
+// Longest common subsequence. Between two runs of common keys, removed and added keys are paired up as changes
+fn diff_keys(old: &[String], new: &[String]) -> Vec<Edit> {
+    let (n, m) = (old.len(), new.len());
+    // lcs[i][j] is the length of the LCS of old[i..] and new[j..]
+    let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut changes = vec![];
+    let mut removed = vec![];
+    let mut added = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && old[i] == new[j] {
+            flush_run(&mut changes, &mut removed, &mut added);
+            changes.push(Edit::Same(i, j));
+            i += 1;
+            j += 1;
+        } else if j < m && (i == n || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            added.push(j);
+            j += 1;
+        } else {
+            removed.push(i);
+            i += 1;
+        }
+    }
+    flush_run(&mut changes, &mut removed, &mut added);
+
+    changes
+}
+
+fn flush_run(edits: &mut Vec<Edit>, removed: &mut Vec<usize>, added: &mut Vec<usize>) {
+    let paired = removed.len().min(added.len());
+    for k in 0..paired {
+        edits.push(Edit::Changed(removed[k], added[k]));
+    }
+    edits.extend(removed.drain(..).skip(paired).map(Edit::Removed));
+    edits.extend(added.drain(..).skip(paired).map(Edit::Added));
+}
+
+/// Display a diff, one line per changed instruction under the function it is in.
+pub fn fmt_bytecode_diff(diffs: &[SegmentDiff]) -> String {
+    if diffs.is_empty() {
+        return "No differences".to_string();
+    }
+
+    let (mut added, mut removed, mut changed) = (0, 0, 0);
+    let mut out = String::new();
+
+    for diff in diffs {
+        match diff {
+            SegmentDiff::Added { name, start, len } => {
+                added += len;
+                out.push_str(&format!(
+                    "+ {}: added, {} instructions from new {}\n",
+                    name, len, start
+                ));
+            }
+            SegmentDiff::Removed { name, start, len } => {
+                removed += len;
+                out.push_str(&format!(
+                    "- {}: removed, {} instructions from old {}\n",
+                    name, len, start
+                ));
+            }
+            SegmentDiff::Changed { name, changes } => {
+                out.push_str(&format!("{}:\n", name));
+                for change in changes {
+                    let line = match change {
+                        Change::Added { new_addr, instr } => {
+                            added += 1;
+                            format!("  + [new {}] {:?}", new_addr, instr)
+                        }
+                        Change::Removed { old_addr, instr } => {
+                            removed += 1;
+                            format!("  - [old {}] {:?}", old_addr, instr)
+                        }
+                        Change::Changed {
+                            old_addr,
+                            new_addr,
+                            old,
+                            new,
+                        } => {
+                            changed += 1;
+                            format!(
+                                "  ~ [old {}, new {}] {:?} => {:?}",
+                                old_addr, new_addr, old, new
+                            )
+                        }
+                    };
+                    out.push_str(&line);
+                    out.push('\n');
+                }
+            }
+        }
+    }
+
+    out.push_str(&format!(
+        "{} added, {} removed, {} changed",
+        added, removed, changed
+    ));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use bytecode::ByteCode;
+
+    use crate::compiler::compile_from_string;
+
+    use super::*;
+
+    fn compile(inp: &str) -> Vec<ByteCode> {
+        compile_from_string(inp, true).expect("Should compile")
+    }
+
+    #[test]
+    fn test_bcdiff_same() {
+        let t = "fn f(x: int) -> int { if x > 0 { x } else { 0 } } f(2)";
+        assert!(diff_bytecode(&compile(t), &compile(t)).is_empty());
+        assert_eq!(fmt_bytecode_diff(&[]), "No differences");
+    }
+
+    #[test]
+    fn test_bcdiff_fns_aligned() {
+        // g moves down, but only the changed instruction in f is reported
+        let old = compile(
+            r"
+            fn f(x: int) -> int { if x > 0 { x } else { 0 } }
+            fn g() -> int { 2 }
+            f(g())
+            ",
+        );
+        let new = compile(
+            r"
+            fn f(x: int) -> int { if x > 0 { x + 1 } else { 0 } }
+            fn g() -> int { 2 }
+            f(g())
+            ",
+        );
+
+        let diffs = diff_bytecode(&old, &new);
+        assert_eq!(
+            diffs,
+            vec![SegmentDiff::Changed {
+                name: "f".to_string(),
+                changes: vec![
+                    Change::Added {
+                        new_addr: 8,
+                        instr: ByteCode::ldc(1),
+                    },
+                    Change::Added {
+                        new_addr: 9,
+                        instr: ByteCode::BINOP(bytecode::BinOp::Add),
+                    },
+                ],
+            }]
+        );
+        assert_eq!(
+            fmt_bytecode_diff(&diffs),
+            "f:\n  + [new 8] LDC(1)\n  + [new 9] BINOP(Add)\n2 added, 0 removed, 0 changed"
+        );
+    }
+
+    #[test]
+    fn test_bcdiff_changed_added_removed() {
+        let old = compile("fn f() -> int { 1 } fn h() { fn inner() {} } f()");
+        let new = compile("fn f() -> int { 2 } fn g() {} f()");
+
+        let diffs = diff_bytecode(&old, &new);
+        assert_eq!(
+            diffs,
+            vec![
+                SegmentDiff::Changed {
+                    name: "top level".to_string(),
+                    changes: vec![
+                        Change::Changed {
+                            old_addr: 0,
+                            new_addr: 0,
+                            old: ByteCode::enterscope(vec!["f", "h"]),
+                            new: ByteCode::enterscope(vec!["f", "g"]),
+                        },
+                        Change::Changed {
+                            old_addr: 8,
+                            new_addr: 8,
+                            old: ByteCode::ldf(10, Vec::<String>::new()),
+                            new: ByteCode::ldf(10, Vec::<String>::new()),
+                        },
+                        Change::Changed {
+                            old_addr: 21,
+                            new_addr: 12,
+                            old: ByteCode::assign("h"),
+                            new: ByteCode::assign("g"),
+                        },
+                    ],
+                },
+                SegmentDiff::Changed {
+                    name: "f".to_string(),
+                    changes: vec![Change::Changed {
+                        old_addr: 3,
+                        new_addr: 3,
+                        old: ByteCode::ldc(1),
+                        new: ByteCode::ldc(2),
+                    }],
+                },
+                SegmentDiff::Removed {
+                    name: "h".to_string(),
+                    start: 10,
+                    len: 9,
+                },
+                SegmentDiff::Removed {
+                    name: "h::inner".to_string(),
+                    start: 13,
+                    len: 2,
+                },
+                SegmentDiff::Added {
+                    name: "g".to_string(),
+                    start: 10,
+                    len: 2,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,26 @@
+use anyhow::Result;
+use bytecode::read_from_file;
+use clap::Parser;
+use compiler::bcdiff::{diff_bytecode, fmt_bytecode_diff};
+
+#[derive(clap::Parser, Debug)]
+#[command(name = "bcdiff")]
+#[command(version = "0.1.0")]
+#[command(about = "Diff two compiled RustScript programs function by function", long_about = None)]
+struct Args {
+    /// Old .o2 file
+    old: String,
+
+    /// New .o2 file
+    new: String,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let old = read_from_file(&args.old)?;
+    let new = read_from_file(&args.new)?;
+
+    println!("{}", fmt_bytecode_diff(&diff_bytecode(&old, &new)));
+    Ok(())
+}
@@ -1,50 +1,178 @@
 use anyhow::Result;
-use std::{fmt::Display, rc::Rc, vec};
+use parser::cfg::apply_cfg;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    rc::Rc,
+    vec,
+};
+use types::check_attrs::{ATTR_DEPRECATED, ATTR_TEST};
+use types::check_match::Coverage;
 use types::type_checker::TypeChecker;
 
-use bytecode::{BinOp, ByteCode, Value};
+use crate::optimize::{const_value, peephole};
+use crate::reachability::decl_diverges;
+use crate::relocate::make_position_independent;
+
+use bytecode::{
+    BinOp, ByteCode, LineTable, Value, ERR_SYM, NONE_SYM, OK_SYM, OPTION_SYM, RESULT_SYM, SOME_SYM,
+};
+use diagnostics::{message, Code, Message};
 use parser::structs::{
-    BinOpType, BlockSeq, Decl, Expr, FnCallData, FnDeclData, IfElseData, LoopData, UnOpType,
+    BinOpType, BlockSeq, Decl, Expr, FnCallData, FnDeclData, ForData, ForIter, IfElseData,
+    LambdaData, LockData, LoopData, MatchArm, MatchData, Pattern, SpawnData, UnOpType,
 };
 
 pub struct Compiler {
     program: BlockSeq,
-    // Tracks idx in bytecode for any nested break stmts compiled for that loop. Stack of vecs since we can have nested loops
-    // and break should only break the closest enclosing loop
-    loop_stack: Vec<Vec<usize>>,
+    // Stack since we can have nested loops and break should only break the closest enclosing loop
+    loop_stack: Vec<LoopCtx>,
+    // Number of ENTERSCOPEs we are currently inside of, so break knows how many scopes to exit
+    scope_depth: usize,
+    // Mutexes held by the lock blocks we are inside of in the current fn, so break and return can release them
+    held_locks: Vec<HeldLock>,
+    // Dead code found so far, which is left out of the bytecode, uses of deprecated names, and lets that shadow a
+    // name in strict mode
+    warnings: Vec<CompileWarning>,
+    // Whether to fold constant exprs and clean up the bytecode, see compile_optimized
+    optimize: bool,
+    // Whether to give the addresses in fn bodies as offsets, see compile_position_independent
+    position_independent: bool,
+    // Whether to warn about lets that shadow a name and deny warnings, see compile_strict
+    strict: bool,
+    // Names declared by the params and lets of the scopes we are inside of so far, innermost last. Only added to in
+    // strict mode
+    declared: Vec<Vec<String>>,
+    // The names bound by the scopes we are inside of, innermost last, with the note of the #[deprecated] on the fn
+    // or let that declared them if it has one
+    bound: Vec<HashMap<String, Option<String>>>,
+    // The line each instruction is from, and the line of the decl being compiled
+    lines: LineTable,
+    line: Option<usize>,
+    // The enum each variant of the enums declared at the top level is of, by variant name
+    variants: HashMap<String, String>,
+}
+
+struct LoopCtx {
+    // idx in bytecode of the GOTO for any nested break stmts compiled for this loop
+    breaks: Vec<usize>,
+    // scope_depth outside of the loop body
+    scope_depth: usize,
+}
+
+struct HeldLock {
+    // hidden symbol the mutex is stored in
+    sym: String,
+    // loop_stack length when the lock was taken, a break only releases locks taken inside its loop
+    loop_depth: usize,
 }
 
 #[derive(Debug, PartialEq)]
 pub struct CompileError {
+    code: Code,
     msg: String,
 }
 
 impl CompileError {
-    pub fn new(err: &str) -> CompileError {
+    pub fn new(msg: Message) -> CompileError {
         CompileError {
-            msg: err.to_owned(),
+            code: msg.code,
+            msg: msg.text,
         }
     }
+
+    /// The code of the error, see [`diagnostics`].
+    pub fn code(&self) -> Code {
+        self.code
+    }
 }
 
 impl Display for CompileError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "[CompileError] -  {}", self.msg)
+        write!(f, "[CompileError {}] -  {}", self.code, self.msg)
     }
 }
 
 impl std::error::Error for CompileError {}
 
+/// Something wrong with the program that doesn't stop it compiling, like code that can never run.
+#[derive(Debug, PartialEq)]
+pub struct CompileWarning {
+    msg: String,
+}
+
+impl CompileWarning {
+    pub fn new(warning: &str) -> CompileWarning {
+        CompileWarning {
+            msg: warning.to_owned(),
+        }
+    }
+}
+
+impl Display for CompileWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[Warning] - {}", self.msg)
+    }
+}
+
+// Hidden symbols for the state of a for loop. '$' can't start an identifier so these never clash with user code
+const FOR_IDX_SYM: &str = "$idx";
+const FOR_END_SYM: &str = "$end";
+const FOR_ARR_SYM: &str = "$arr";
+// Hidden symbol for the value being matched, so it is only evaluated once
+const MATCH_SYM: &str = "$match";
+// Hidden symbol for the option on the left of ??, which is tested and then unwrapped
+const COALESCE_SYM: &str = "$coalesce";
+// Prefix of the hidden symbol for the mutex of a lock block, suffixed with the nesting depth so an inner lock
+// doesn't shadow an outer one that a break or return still has to release
+const LOCK_SYM: &str = "$lock";
+// Prefix of the hidden symbols the args of a spawn are evaluated into, suffixed with the index of the arg
+const SPAWN_ARG_SYM: &str = "$spawn";
+
 // Workaround to ensure builtins that dont pop produce Unit when compiling fn call
 // Because user functions even if empty will produce unit (everything is value producing), so
 // this issue only applies to builtins with no value pushed
-const BUILTINS_WITH_NO_VAL: [&str; 3] = ["println", "print", "sem_set"];
+const BUILTINS_WITH_NO_VAL: [&str; 7] = [
+    "println", "print", "sem_set", "close", "set_byte", "detach", "assert",
+];
+
+// Channel operations have their own instructions since recv may block the thread, like wait
+const SEND_SYM: &str = "send";
+const RECV_SYM: &str = "recv";
+
+// format takes any number of args after the format string, which are passed to the builtin in an array
+const FORMAT_SYM: &str = "format";
 
 impl Compiler {
     pub fn new(program: BlockSeq) -> Compiler {
+        let variants = program
+            .decls
+            .iter()
+            .filter_map(|decl| match decl {
+                Decl::EnumDeclStmt(data) => Some(data),
+                _ => None,
+            })
+            .flat_map(|data| {
+                data.variants
+                    .iter()
+                    .map(|(name, _)| (name.to_owned(), data.name.to_owned()))
+            })
+            .collect();
+
         Compiler {
             program,
             loop_stack: vec![],
+            scope_depth: 0,
+            held_locks: vec![],
+            warnings: vec![],
+            optimize: false,
+            position_independent: false,
+            strict: false,
+            declared: vec![],
+            bound: vec![],
+            lines: LineTable::new(),
+            line: None,
+            variants,
         }
     }
 
@@ -78,12 +206,14 @@ impl Compiler {
                     decls: vec![],
                     last_expr: Some(Rc::new(rhs.clone())),
                     symbols: vec![],
+                    spans: vec![],
                 };
 
                 let else_blk = BlockSeq {
                     decls: vec![],
                     last_expr: Some(Rc::new(Expr::Bool(false))),
                     symbols: vec![],
+                    spans: vec![],
                 };
 
                 let stmt = IfElseData {
@@ -101,12 +231,14 @@ impl Compiler {
                     decls: vec![],
                     last_expr: Some(Rc::new(Expr::Bool(true))),
                     symbols: vec![],
+                    spans: vec![],
                 };
 
                 let else_blk = BlockSeq {
                     decls: vec![],
                     last_expr: Some(Rc::new(rhs.clone())),
                     symbols: vec![],
+                    spans: vec![],
                 };
 
                 let stmt = IfElseData {
@@ -143,6 +275,7 @@ impl Compiler {
             BinOpType::Add => arr.push(ByteCode::BINOP(bytecode::BinOp::Add)),
             BinOpType::Mul => arr.push(ByteCode::BINOP(bytecode::BinOp::Mul)),
             BinOpType::Div => arr.push(ByteCode::BINOP(bytecode::BinOp::Div)),
+            BinOpType::Mod => arr.push(ByteCode::BINOP(bytecode::BinOp::Mod)),
             BinOpType::Sub => arr.push(ByteCode::BINOP(bytecode::BinOp::Sub)),
             BinOpType::Gt => arr.push(ByteCode::BINOP(BinOp::Gt)),
             BinOpType::Lt => arr.push(ByteCode::BINOP(BinOp::Lt)),
@@ -154,6 +287,12 @@ impl Compiler {
         Ok(())
     }
 
+    /// Compile an expression, leaving its value on top of the operand stack.
+    ///
+    /// Sub-expressions are evaluated left to right, each one completely before the next starts: the operands
+    /// of a binop (the right of && and || only if it is needed), the callee and then the args of a call, the
+    /// elements of an array and the fields of a struct as they are written. Their side effects happen in that
+    /// order, so any pass that moves or removes code has to keep it.
     pub fn compile_expr(
         &mut self,
         expr: &Expr,
@@ -163,15 +302,29 @@ impl Compiler {
             Expr::Integer(val) => arr.push(ByteCode::ldc(*val)),
             Expr::Float(val) => arr.push(ByteCode::ldc(*val)),
             Expr::Bool(val) => arr.push(ByteCode::ldc(*val)),
-            Expr::StringLiteral(str) => arr.push(ByteCode::LDC(Value::String(str.to_owned()))),
+            Expr::StringLiteral(str) => arr.push(ByteCode::LDC(Value::String(str.as_str().into()))),
+            Expr::Char(c) => arr.push(ByteCode::LDC(Value::Char(*c))),
+            Expr::BinOpExpr(..) | Expr::UnOpExpr(..)
+                if self.optimize && const_value(expr).is_some() =>
+            {
+                arr.push(ByteCode::LDC(const_value(expr).expect("checked in guard")));
+            }
             Expr::BinOpExpr(op, lhs, rhs) => {
                 self.compile_binop(op, lhs, rhs, arr)?;
             }
             Expr::UnOpExpr(op, expr) => {
                 self.compile_unop(op, expr, arr)?;
             }
+            Expr::Symbol(sym) if sym == NONE_SYM => {
+                arr.push(ByteCode::VARIANT(OPTION_SYM.into(), NONE_SYM.into(), 0));
+            }
+            Expr::Symbol(sym) if self.variants.contains_key(sym) => {
+                let enum_name = self.variants[sym].to_owned();
+                arr.push(ByteCode::VARIANT(enum_name, sym.to_owned(), 0));
+            }
             // Load symbol
             Expr::Symbol(sym) => {
+                self.warn_deprecated(sym);
                 arr.push(ByteCode::LD(sym.to_string()));
             }
             Expr::BlockExpr(blk) => {
@@ -179,38 +332,152 @@ impl Compiler {
             }
             Expr::IfElseExpr(if_else) => self.compile_if_else(if_else, arr)?,
             Expr::FnCallExpr(fn_call) => self.compile_fn_call(fn_call, arr)?,
-            Expr::SpawnExpr(fn_call) => self.compile_spawn(fn_call, arr)?,
+            Expr::SpawnExpr(data) => self.compile_spawn(data, arr)?,
             Expr::JoinExpr(id) => {
                 arr.push(ByteCode::ld(id));
                 arr.push(ByteCode::JOIN);
             }
+            // try_join doesn't wait, so it is join_timeout with a timeout of 0
+            Expr::TryJoinExpr(data) => {
+                arr.push(ByteCode::ld(&data.tid));
+                match &data.timeout {
+                    Some(ms) => self.compile_expr(ms, arr)?,
+                    None => arr.push(ByteCode::ldc(0)),
+                }
+                arr.push(ByteCode::TRYJOIN);
+            }
+            Expr::ArrayExpr(elems) => {
+                for elem in elems.iter() {
+                    self.compile_expr(elem, arr)?;
+                }
+                arr.push(ByteCode::ARRAY(elems.len()));
+            }
+            Expr::TupleExpr(elems) => {
+                for elem in elems.iter() {
+                    self.compile_expr(elem, arr)?;
+                }
+                arr.push(ByteCode::TUPLE(elems.len()));
+            }
+            Expr::TupleIndexExpr(tuple, idx) => {
+                self.compile_expr(tuple, arr)?;
+                arr.push(ByteCode::LDTUPLE(*idx));
+            }
+            Expr::MapExpr(entries) => {
+                for (key, val) in entries.iter() {
+                    self.compile_expr(key, arr)?;
+                    self.compile_expr(val, arr)?;
+                }
+                arr.push(ByteCode::MAP(entries.len()));
+            }
+            Expr::ArrayFillExpr(val, len) => {
+                self.compile_expr(val, arr)?;
+                arr.push(ByteCode::ARRAYFILL(*len));
+            }
+            Expr::IndexExpr(array, index) => {
+                self.compile_expr(array, arr)?;
+                self.compile_expr(index, arr)?;
+                arr.push(ByteCode::LDIDX);
+            }
+            Expr::SliceExpr(slice) => {
+                self.compile_expr(&slice.arr, arr)?;
+
+                match &slice.start {
+                    Some(start) => self.compile_expr(start, arr)?,
+                    None => arr.push(ByteCode::ldc(0)),
+                }
+
+                // Unit end means slice to the end, which is only known at runtime
+                match &slice.end {
+                    Some(end) => {
+                        self.compile_expr(end, arr)?;
+                        if slice.inclusive {
+                            arr.push(ByteCode::ldc(1));
+                            arr.push(ByteCode::BINOP(BinOp::Add));
+                        }
+                    }
+                    None => arr.push(ByteCode::ldc(Value::Unit)),
+                }
+
+                arr.push(ByteCode::SLICE);
+            }
+            // fields are evaluated in the order they are written, not the order they were declared in
+            Expr::StructExpr(data) => {
+                for (_, expr) in data.fields.iter() {
+                    self.compile_expr(expr, arr)?;
+                }
+                let fields = data
+                    .fields
+                    .iter()
+                    .map(|(field, _)| field.to_owned())
+                    .collect();
+                arr.push(ByteCode::STRUCT(data.name.to_owned(), fields));
+            }
+            Expr::FieldAccessExpr(obj, field) => {
+                self.compile_expr(obj, arr)?;
+                arr.push(ByteCode::LDFIELD(field.to_owned()));
+            }
+            Expr::MatchExpr(data) => self.compile_match(data, arr)?,
+            Expr::CoalesceExpr(opt, default) => self.compile_coalesce(opt, default, arr)?,
+            Expr::LockExpr(data) => self.compile_lock(data, arr)?,
+            Expr::LambdaExpr(data) => self.compile_lambda(data, arr)?,
+            // parser expands macros before returning the program
+            Expr::MacroCallExpr(call) => {
+                let e = message!(C001, "Macro '{}!' was not expanded", call.name);
+                return Err(CompileError::new(e));
+            }
         }
 
         Ok(())
     }
 
+    // spawn f(a, b) evaluates the args in the parent, into hidden symbols of a scope of their own that the child
+    // starts in, so the child gets their values at the spawn whatever the parent does after. The scope is
+    // only left by the parent, the child keeps it as its environment. f itself is looked up by the child.
+    // spawn isolate f(a, b) is the same, except that the child keeps a copy of the environment
     fn compile_spawn(
         &mut self,
-        fn_call: &FnCallData,
+        data: &SpawnData,
         arr: &mut Vec<ByteCode>,
     ) -> Result<(), CompileError> {
-        // dbg!("SPAWN COMPILE:", _fn_call);
+        let fn_call = &data.call;
+        let arg_syms: Vec<String> = (0..fn_call.args.len())
+            .map(|idx| format!("{}{}", SPAWN_ARG_SYM, idx))
+            .collect();
+
+        if !arg_syms.is_empty() {
+            arr.push(ByteCode::ENTERSCOPE(arg_syms.clone()));
+            self.scope_depth += 1;
+        }
+
+        for (arg, sym) in fn_call.args.iter().zip(arg_syms.iter()) {
+            self.compile_expr(arg, arr)?;
+            arr.push(ByteCode::assign(sym.as_str()));
+        }
+
         let spawn_idx = arr.len();
-        arr.push(ByteCode::SPAWN(0));
+        if data.isolate {
+            arr.push(ByteCode::SPAWNISO(0));
+        } else {
+            arr.push(ByteCode::SPAWN(0));
+        }
 
         let goto_idx = arr.len();
         arr.push(ByteCode::GOTO(0));
 
         // spawn jumps to POP which is added after this
         let spawn_jmp = arr.len();
-        if let Some(ByteCode::SPAWN(jmp)) = arr.get_mut(spawn_idx) {
+        if let Some(ByteCode::SPAWN(jmp) | ByteCode::SPAWNISO(jmp)) = arr.get_mut(spawn_idx) {
             *jmp = spawn_jmp;
         }
 
         // child pops value on its stack
         arr.push(ByteCode::POP);
 
-        self.compile_fn_call(fn_call, arr)?;
+        let child_call = FnCallData {
+            name: fn_call.name.clone(),
+            args: arg_syms.iter().cloned().map(Expr::Symbol).collect(),
+        };
+        self.compile_fn_call(&child_call, arr)?;
         arr.push(ByteCode::DONE); // child thread finishes
 
         let goto_jmp = arr.len();
@@ -220,6 +487,11 @@ impl Compiler {
             *jmp = goto_jmp;
         }
 
+        if !arg_syms.is_empty() {
+            arr.push(ByteCode::EXITSCOPE);
+            self.scope_depth -= 1;
+        }
+
         Ok(())
     }
 
@@ -247,29 +519,166 @@ impl Compiler {
         blk: &BlockSeq,
         arr: &mut Vec<ByteCode>,
     ) -> Result<(), CompileError> {
-        let decls = &blk.decls;
         let syms = &blk.symbols;
 
         if !syms.is_empty() {
             arr.push(ByteCode::ENTERSCOPE(syms.clone()));
+            self.scope_depth += 1;
         }
 
-        for decl in decls {
+        self.declared.push(vec![]);
+        self.bound.push(Compiler::block_names(blk));
+        let res = self.compile_block_decls(blk, arr);
+        self.bound.pop();
+        self.declared.pop();
+
+        if !syms.is_empty() {
+            arr.push(ByteCode::EXITSCOPE);
+            self.scope_depth -= 1;
+        }
+
+        res
+    }
+
+    fn compile_block_decls(
+        &mut self,
+        blk: &BlockSeq,
+        arr: &mut Vec<ByteCode>,
+    ) -> Result<(), CompileError> {
+        let outer_line = self.line;
+
+        for (idx, decl) in blk.decls.iter().enumerate() {
+            self.mark_line(blk.decl_span(idx).map(|span| span.line), arr);
+            if let Decl::LetStmt(stmt) = decl {
+                self.declare(&stmt.ident, blk.decl_span(idx));
+            }
+            if let Decl::LetTupleStmt(stmt) = decl {
+                for ident in stmt.idents.iter().filter(|ident| *ident != "_") {
+                    self.declare(ident, blk.decl_span(idx));
+                }
+            }
             self.compile_decl(decl, arr)?;
             // pop result of statements - need to ensure all stmts produce something (either Unit or something else)
             arr.push(ByteCode::POP);
+
+            // nothing after a decl that never finishes can run, so don't compile it
+            if let Some(cause) = decl_diverges(decl) {
+                self.warn_unreachable(blk, idx + 1, cause);
+                return Ok(());
+            }
         }
 
         // Handle expr
         if let Some(expr) = &blk.last_expr {
+            self.mark_line(blk.last_expr_span().map(|span| span.line), arr);
             self.compile_expr(expr.as_ref(), arr)?;
         }
 
-        if !syms.is_empty() {
-            arr.push(ByteCode::EXITSCOPE);
+        // the rest of the decl the block is in is from its line
+        self.mark_line(outer_line, arr);
+        Ok(())
+    }
+
+    // The instructions compiled from here on are from line, if it is known
+    fn mark_line(&mut self, line: Option<usize>, arr: &[ByteCode]) {
+        if let Some(line) = line {
+            self.lines.push(arr.len(), line);
+            self.line = Some(line);
         }
+    }
 
-        Ok(())
+    // In strict mode, warn if a let declares a name that a let or param before it in the same or an enclosing scope
+    // already has, since the earlier one can't be used after it
+    fn declare(&mut self, name: &str, span: Option<impl Display>) {
+        if !self.strict {
+            return;
+        }
+
+        if self.declared.iter().flatten().any(|prev| prev == name) {
+            let mut msg = format!("'{}' shadows a name declared before it", name);
+            if let Some(span) = span {
+                msg.push_str(&format!(" at {}", span));
+            }
+            self.warnings.push(CompileWarning::new(&msg));
+        }
+
+        if let Some(scope) = self.declared.last_mut() {
+            scope.push(name.to_string());
+        }
+    }
+
+    // The names blk binds, with the note of the #[deprecated] on the fn or let that declares them if it has one,
+    // empty if the attribute has no message
+    fn block_names(blk: &BlockSeq) -> HashMap<String, Option<String>> {
+        let mut names: HashMap<String, Option<String>> = blk
+            .symbols
+            .iter()
+            .map(|sym| (sym.to_owned(), None))
+            .collect();
+
+        for decl in blk.decls.iter() {
+            let (name, attrs) = match decl {
+                Decl::FnDeclStmt(data) => (&data.name, &data.attrs),
+                Decl::LetStmt(data) => (&data.ident, &data.attrs),
+                _ => continue,
+            };
+
+            if let Some(attr) = attrs.iter().find(|attr| attr.name == ATTR_DEPRECATED) {
+                let note = match attr.args.first() {
+                    Some(Expr::StringLiteral(msg)) => msg.to_owned(),
+                    _ => String::new(),
+                };
+                names.insert(name.to_owned(), Some(note));
+            }
+        }
+
+        names
+    }
+
+    // Bind names that aren't deprecated in a new scope, like params and loop vars, which bound.pop() leaves
+    fn enter_names<'a>(&mut self, names: impl IntoIterator<Item = &'a String>) {
+        let scope = names
+            .into_iter()
+            .map(|name| (name.to_owned(), None))
+            .collect();
+        self.bound.push(scope);
+    }
+
+    // Warn about a use of a name whose declaration is #[deprecated], unless a name in a scope closer in hides it
+    fn warn_deprecated(&mut self, sym: &str) {
+        let note = self.bound.iter().rev().find_map(|scope| scope.get(sym));
+        let Some(Some(note)) = note else {
+            return;
+        };
+
+        let mut msg = format!("'{}' is deprecated", sym);
+        if !note.is_empty() {
+            msg.push_str(&format!(": {}", note));
+        }
+        if let Some(line) = self.line {
+            msg.push_str(&format!(" at line {}", line));
+        }
+        self.warnings.push(CompileWarning::new(&msg));
+    }
+
+    // Warn that the decls of blk from idx on, and its last expr, are unreachable. Nested blocks in the dead code
+    // aren't compiled, so there is one warning for all of it
+    fn warn_unreachable(&mut self, blk: &BlockSeq, idx: usize, cause: &str) {
+        if idx == blk.decls.len() && blk.last_expr.is_none() {
+            return;
+        }
+
+        let span = if idx < blk.decls.len() {
+            blk.decl_span(idx)
+        } else {
+            blk.last_expr_span()
+        };
+
+        let mut msg = format!("Unreachable code after {}", cause);
+        if let Some(span) = span {
+            msg.push_str(&format!(" at {}", span));
+        }
+        self.warnings.push(CompileWarning::new(&msg));
     }
 
     /// Compile block appropriately based on whether it is none-like
@@ -302,17 +711,56 @@ impl Compiler {
             Decl::LetStmt(stmt) => {
                 self.compile_assign(&stmt.ident, &stmt.expr, arr)?;
             }
+            // the values are unpacked with the last one on top, so they are assigned from the last name
+            Decl::LetTupleStmt(stmt) => {
+                self.compile_expr(&stmt.expr, arr)?;
+                arr.push(ByteCode::UNPACK(stmt.idents.len()));
+                for ident in stmt.idents.iter().rev() {
+                    match ident.as_str() {
+                        "_" => arr.push(ByteCode::POP),
+                        _ => arr.push(ByteCode::ASSIGN(ident.to_owned())),
+                    }
+                }
+                arr.push(ByteCode::LDC(Value::Unit));
+            }
             Decl::AssignStmt(stmt) => {
                 self.compile_assign(&stmt.ident, &stmt.expr, arr)?;
             }
+            Decl::IndexAssignStmt(stmt) => {
+                self.compile_expr(&stmt.arr, arr)?;
+                self.compile_expr(&stmt.index, arr)?;
+                self.compile_expr(&stmt.expr, arr)?;
+                arr.push(ByteCode::ASSIGNIDX);
+                arr.push(ByteCode::LDC(Value::Unit));
+            }
+            Decl::FieldAssignStmt(stmt) => {
+                self.compile_expr(&stmt.obj, arr)?;
+                self.compile_expr(&stmt.expr, arr)?;
+                arr.push(ByteCode::ASSIGNFIELD(stmt.field.to_owned()));
+                arr.push(ByteCode::LDC(Value::Unit));
+            }
+            // structs and enums only exist at compile time, instances carry their own field and variant names
+            Decl::StructDeclStmt(_) | Decl::EnumDeclStmt(_) => arr.push(ByteCode::ldc(Value::Unit)),
+            Decl::MacroDeclStmt(data) => {
+                let e = message!(C001, "Macro '{}' was not expanded", data.name);
+                return Err(CompileError::new(e));
+            }
             Decl::IfOnlyStmt(if_else) => self.compile_if_else(if_else, arr)?,
             Decl::LoopStmt(lp) => self.compile_loop(lp, arr)?,
-            // push GOTO, push idx of this break in arr onto loop stack
+            Decl::ForStmt(for_data) => self.compile_for(for_data, arr)?,
+            // exit the scopes entered since the start of the loop body, then push GOTO and push idx of this break
+            // in arr onto loop stack. The GOTO skips the EXITSCOPEs at the end of those blocks
             Decl::BreakStmt => {
-                let break_idx = arr.len();
-                arr.push(ByteCode::GOTO(0));
-                if let Some(breaks) = self.loop_stack.last_mut() {
-                    breaks.push(break_idx);
+                let loop_depth = self.loop_stack.len();
+                self.release_locks(|lock| lock.loop_depth >= loop_depth, arr);
+
+                if let Some(lp) = self.loop_stack.last_mut() {
+                    for _ in lp.scope_depth..self.scope_depth {
+                        arr.push(ByteCode::EXITSCOPE);
+                    }
+
+                    lp.breaks.push(arr.len());
+                    arr.push(ByteCode::GOTO(0));
                 }
             }
             Decl::FnDeclStmt(fn_decl) => self.compile_fn_decl(fn_decl, arr)?,
@@ -324,6 +772,8 @@ impl Compiler {
                     arr.push(ByteCode::ldc(Value::Unit));
                 }
 
+                self.release_locks(|_| true, arr);
+
                 // push RESET
                 arr.push(ByteCode::RESET(bytecode::FrameType::CallFrame))
             }
@@ -357,7 +807,7 @@ impl Compiler {
 
         let param_strs: Vec<String> = fn_decl.params.iter().map(|x| x.name.to_string()).collect();
 
-        arr.push(ByteCode::ldf(fn_start_idx, param_strs));
+        arr.push(ByteCode::ldf(fn_start_idx, param_strs.clone()));
 
         // push GOTO for skipping fn compile
         let goto_idx = arr.len();
@@ -370,7 +820,15 @@ impl Compiler {
 
         // compile the augmented blk
 
-        self.compile_block(&fn_decl.body, arr)?;
+        // locks held outside the fn are not released by a return inside it
+        let outer_locks = std::mem::take(&mut self.held_locks);
+        self.enter_names(&param_strs);
+        self.declared.push(param_strs);
+        let res = self.compile_block(&fn_decl.body, arr);
+        self.declared.pop();
+        self.bound.pop();
+        self.held_locks = outer_locks;
+        res?;
         // self.compile_block(&fn_blk, arr)?;
 
         // push reset to return last value produced by blk, in case no return was there
@@ -389,13 +847,96 @@ impl Compiler {
         Ok(())
     }
 
+    /// Lambda expression e.g |x: int| x + 1. Compiled like a fn decl, but the closure is left on the stack
+    /// instead of being assigned to a name
+    fn compile_lambda(
+        &mut self,
+        lambda: &LambdaData,
+        arr: &mut Vec<ByteCode>,
+    ) -> Result<(), CompileError> {
+        let fn_start_idx = arr.len() + 2;
+        let param_strs: Vec<String> = lambda.params.iter().map(|x| x.name.to_string()).collect();
+        arr.push(ByteCode::ldf(fn_start_idx, param_strs.clone()));
+
+        let goto_idx = arr.len();
+        arr.push(ByteCode::GOTO(0));
+
+        let outer_locks = std::mem::take(&mut self.held_locks);
+        self.enter_names(&param_strs);
+        self.declared.push(param_strs);
+        let res = self.compile_expr(&lambda.body, arr);
+        self.declared.pop();
+        self.bound.pop();
+        self.held_locks = outer_locks;
+        res?;
+
+        arr.push(ByteCode::RESET(bytecode::FrameType::CallFrame));
+
+        // skip the body, leaving the closure from LDF as the value of the expression
+        let goto_addr = arr.len();
+        if let Some(ByteCode::GOTO(idx)) = arr.get_mut(goto_idx) {
+            *idx = goto_addr;
+        }
+
+        Ok(())
+    }
+
     /// Function call expression e.g println(2,3)
     fn compile_fn_call(
         &mut self,
         fn_call: &FnCallData,
         arr: &mut Vec<ByteCode>,
     ) -> Result<(), CompileError> {
+        match (fn_call.name.as_str(), fn_call.args.as_slice()) {
+            (SEND_SYM, [ch, val]) => {
+                self.compile_expr(ch, arr)?;
+                self.compile_expr(val, arr)?;
+                arr.push(ByteCode::SEND);
+                arr.push(ByteCode::ldc(Value::Unit));
+                return Ok(());
+            }
+            (RECV_SYM, [ch]) => {
+                self.compile_expr(ch, arr)?;
+                arr.push(ByteCode::RECV);
+                return Ok(());
+            }
+            // the constructors of options and results make the variant in place, there is no fn to call
+            (SOME_SYM | OK_SYM | ERR_SYM, [val]) => {
+                let enum_name = match fn_call.name.as_str() {
+                    SOME_SYM => OPTION_SYM,
+                    _ => RESULT_SYM,
+                };
+                self.compile_expr(val, arr)?;
+                arr.push(ByteCode::VARIANT(
+                    enum_name.into(),
+                    fn_call.name.to_owned(),
+                    1,
+                ));
+                return Ok(());
+            }
+            (name, args) if self.variants.contains_key(name) => {
+                let enum_name = self.variants[name].to_owned();
+                for arg in args.iter() {
+                    self.compile_expr(arg, arr)?;
+                }
+                arr.push(ByteCode::VARIANT(enum_name, name.to_owned(), args.len()));
+                return Ok(());
+            }
+            (FORMAT_SYM, [fmt, args @ ..]) => {
+                self.compile_expr(&Expr::Symbol(fn_call.name.clone()), arr)?;
+                self.compile_expr(fmt, arr)?;
+                for arg in args.iter() {
+                    self.compile_expr(arg, arr)?;
+                }
+                arr.push(ByteCode::ARRAY(args.len()));
+                arr.push(ByteCode::CALL(2));
+                return Ok(());
+            }
+            _ => (),
+        }
+
         // TODO: change to accept arbitary expr for fn
+        // the callee is loaded before any arg runs, so an arg that reassigns it doesn't change what is called
         self.compile_expr(&Expr::Symbol(fn_call.name.clone()), arr)?;
 
         for arg in fn_call.args.iter() {
@@ -449,6 +990,327 @@ impl Compiler {
         Ok(())
     }
 
+    // The mutex is stored in a hidden symbol so the release at the end, or at a break or return in the body,
+    // posts the same mutex that was waited on even if the mutex expr would evaluate differently
+    fn compile_lock(
+        &mut self,
+        data: &LockData,
+        arr: &mut Vec<ByteCode>,
+    ) -> Result<(), CompileError> {
+        let sym = format!("{}{}", LOCK_SYM, self.held_locks.len());
+        arr.push(ByteCode::ENTERSCOPE(vec![sym.to_owned()]));
+        self.scope_depth += 1;
+
+        self.compile_expr(&data.mutex, arr)?;
+        arr.push(ByteCode::assign(&sym));
+        arr.push(ByteCode::ld(&sym));
+        arr.push(ByteCode::WAIT);
+
+        self.held_locks.push(HeldLock {
+            sym: sym.to_owned(),
+            loop_depth: self.loop_stack.len(),
+        });
+        let res = self.compile_block(&data.body, arr);
+        self.held_locks.pop();
+        self.scope_depth -= 1;
+        res?;
+
+        // the value of the body stays on the stack
+        arr.push(ByteCode::ld(&sym));
+        arr.push(ByteCode::POST);
+        arr.push(ByteCode::EXITSCOPE);
+        Ok(())
+    }
+
+    // Post the held mutexes that satisfy the predicate, innermost first
+    fn release_locks(&self, pred: impl Fn(&HeldLock) -> bool, arr: &mut Vec<ByteCode>) {
+        for lock in self.held_locks.iter().rev().filter(|lock| pred(lock)) {
+            arr.push(ByteCode::ld(&lock.sym));
+            arr.push(ByteCode::POST);
+        }
+    }
+
+    // Each arm compares the matched value with its pattern and jumps on false to the next arm. An arm that runs
+    // jumps to the end after its body. Falling off the last arm produces Unit, for matches without '_'
+    fn compile_match(
+        &mut self,
+        data: &MatchData,
+        arr: &mut Vec<ByteCode>,
+    ) -> Result<(), CompileError> {
+        arr.push(ByteCode::ENTERSCOPE(vec![MATCH_SYM.to_owned()]));
+        self.scope_depth += 1;
+
+        let res = self.compile_match_arms(data, arr);
+        self.scope_depth -= 1;
+        res?;
+
+        arr.push(ByteCode::EXITSCOPE);
+        Ok(())
+    }
+
+    // opt ?? default is the test and branch of a match with a Some arm and a '_' arm: the option is assigned to a
+    // hidden symbol in its own scope, what it holds is loaded if it is Some, otherwise default is evaluated
+    fn compile_coalesce(
+        &mut self,
+        opt: &Expr,
+        default: &Expr,
+        arr: &mut Vec<ByteCode>,
+    ) -> Result<(), CompileError> {
+        arr.push(ByteCode::ENTERSCOPE(vec![COALESCE_SYM.to_owned()]));
+        self.scope_depth += 1;
+
+        let res = self.compile_coalesce_branches(opt, default, arr);
+        self.scope_depth -= 1;
+        res?;
+
+        arr.push(ByteCode::EXITSCOPE);
+        Ok(())
+    }
+
+    fn compile_coalesce_branches(
+        &mut self,
+        opt: &Expr,
+        default: &Expr,
+        arr: &mut Vec<ByteCode>,
+    ) -> Result<(), CompileError> {
+        self.compile_expr(opt, arr)?;
+        arr.push(ByteCode::assign(COALESCE_SYM));
+
+        arr.push(ByteCode::ld(COALESCE_SYM));
+        arr.push(ByteCode::ISVARIANT(SOME_SYM.to_owned()));
+        let jof_idx = arr.len();
+        arr.push(ByteCode::JOF(0));
+
+        arr.push(ByteCode::ld(COALESCE_SYM));
+        arr.push(ByteCode::LDVARIANT(0));
+        let goto_idx = arr.len();
+        arr.push(ByteCode::GOTO(0));
+
+        let len = arr.len();
+        if let Some(ByteCode::JOF(idx)) = arr.get_mut(jof_idx) {
+            *idx = len;
+        }
+        self.compile_expr(default, arr)?;
+
+        let len = arr.len();
+        if let Some(ByteCode::GOTO(idx)) = arr.get_mut(goto_idx) {
+            *idx = len;
+        }
+
+        Ok(())
+    }
+
+    fn compile_match_arms(
+        &mut self,
+        data: &MatchData,
+        arr: &mut Vec<ByteCode>,
+    ) -> Result<(), CompileError> {
+        self.compile_expr(&data.subject, arr)?;
+        arr.push(ByteCode::assign(MATCH_SYM));
+
+        let mut goto_idxs: Vec<usize> = vec![];
+        let mut has_wildcard = false;
+        let mut coverage = Coverage::new(self.match_variants(data));
+
+        for arm in data.arms.iter() {
+            // the type checker rejects an arm the earlier arms cover, unchecked it is left out with a warning
+            if coverage.covers(&arm.pat) {
+                let mut msg = format!(
+                    "Unreachable match arm '{}', the arms before it match every value it does",
+                    arm.pat
+                );
+                if let Some(line) = self.line {
+                    msg.push_str(&format!(" at line {}", line));
+                }
+                self.warnings.push(CompileWarning::new(&msg));
+                continue;
+            }
+            if arm.guard.is_none() {
+                coverage.add(&arm.pat);
+            }
+
+            let test = Compiler::pattern_test(&arm.pat);
+
+            let mut jof_idx = None;
+            if !test.is_empty() {
+                arr.extend(test);
+                jof_idx.replace(arr.len());
+                arr.push(ByteCode::JOF(0));
+            }
+
+            let guard_idx = self.compile_match_arm(arm, arr)?;
+            if jof_idx.is_none() && guard_idx.is_none() {
+                has_wildcard = true;
+                break;
+            }
+
+            goto_idxs.push(arr.len());
+            arr.push(ByteCode::GOTO(0));
+
+            // a false guard leaves the scope of the names the pattern bound, then tries the next arm
+            if let Some(guard_idx) = guard_idx {
+                let len = arr.len();
+                if let Some(ByteCode::JOF(idx)) = arr.get_mut(guard_idx) {
+                    *idx = len;
+                }
+                if !arm.pat.binds().is_empty() {
+                    arr.push(ByteCode::EXITSCOPE);
+                }
+            }
+
+            if let Some(jof_idx) = jof_idx {
+                let len = arr.len();
+                if let Some(ByteCode::JOF(idx)) = arr.get_mut(jof_idx) {
+                    *idx = len;
+                }
+            }
+        }
+
+        if !has_wildcard {
+            arr.push(ByteCode::ldc(Value::Unit));
+        }
+
+        let len = arr.len();
+        for goto_idx in goto_idxs.into_iter() {
+            if let Some(ByteCode::GOTO(idx)) = arr.get_mut(goto_idx) {
+                *idx = len;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Leaves whether the matched value matches pat on the stack, empty for patterns that match every value
+    fn pattern_test(pat: &Pattern) -> Vec<ByteCode> {
+        let ld = ByteCode::ld(MATCH_SYM);
+        match *pat {
+            Pattern::Int(val) => vec![ld, ByteCode::ldc(val), ByteCode::BINOP(BinOp::Eq)],
+            Pattern::Bool(val) => vec![ld, ByteCode::ldc(val), ByteCode::BINOP(BinOp::Eq)],
+            Pattern::Variant(ref name, _) => vec![ld, ByteCode::ISVARIANT(name.to_owned())],
+            // !($match < lo) && !($match > hi)
+            Pattern::Range { .. } => match pat.int_range() {
+                Some((lo, hi)) => vec![
+                    ld.clone(),
+                    ByteCode::ldc(lo),
+                    ByteCode::BINOP(BinOp::Lt),
+                    ByteCode::UNOP(bytecode::UnOp::Not),
+                    ld,
+                    ByteCode::ldc(hi),
+                    ByteCode::BINOP(BinOp::Gt),
+                    ByteCode::UNOP(bytecode::UnOp::Not),
+                    ByteCode::BINOP(BinOp::And),
+                ],
+                None => vec![ByteCode::ldc(false)],
+            },
+            // the test of each pattern, or-ed together
+            Pattern::Or(ref alts) => {
+                let mut test = vec![];
+                for (i, alt) in alts.iter().enumerate() {
+                    match Compiler::pattern_test(alt) {
+                        alt_test if alt_test.is_empty() => test.push(ByteCode::ldc(true)),
+                        alt_test => test.extend(alt_test),
+                    }
+                    if i > 0 {
+                        test.push(ByteCode::BINOP(BinOp::Or));
+                    }
+                }
+                test
+            }
+            // Like '_', a struct pattern matches every value of its type
+            Pattern::Wildcard | Pattern::Struct { .. } => vec![],
+        }
+    }
+
+    // The names of the variants of the type a match is on, found from the first variant pattern of its arms
+    fn match_variants(&self, data: &MatchData) -> Vec<String> {
+        fn variant_name(pat: &Pattern) -> Option<&String> {
+            match pat {
+                Pattern::Variant(name, _) => Some(name),
+                Pattern::Or(alts) => alts.iter().find_map(variant_name),
+                _ => None,
+            }
+        }
+
+        let Some(name) = data.arms.iter().find_map(|arm| variant_name(&arm.pat)) else {
+            return vec![];
+        };
+        match name.as_str() {
+            SOME_SYM | NONE_SYM => vec![SOME_SYM.to_string(), NONE_SYM.to_string()],
+            OK_SYM | ERR_SYM => vec![OK_SYM.to_string(), ERR_SYM.to_string()],
+            _ => match self.variants.get(name) {
+                Some(enum_name) => self
+                    .variants
+                    .iter()
+                    .filter(|(_, of)| *of == enum_name)
+                    .map(|(variant, _)| variant.to_owned())
+                    .collect(),
+                None => vec![],
+            },
+        }
+    }
+
+    // The names a variant or struct pattern binds get their own scope around the guard and body of the arm,
+    // assigned the values the matched variant holds or the fields of the matched struct.
+    // Returns the index of the JOF taken when the guard is false, still inside that scope
+    fn compile_match_arm(
+        &mut self,
+        arm: &MatchArm,
+        arr: &mut Vec<ByteCode>,
+    ) -> Result<Option<usize>, CompileError> {
+        let binds: Vec<(ByteCode, &String)> = match arm.pat {
+            Pattern::Variant(_, ref binds) => binds
+                .iter()
+                .enumerate()
+                .filter(|(_, bind)| *bind != "_")
+                .map(|(idx, bind)| (ByteCode::LDVARIANT(idx), bind))
+                .collect(),
+            Pattern::Struct { ref fields, .. } => fields
+                .iter()
+                .filter(|(_, bind)| bind != "_")
+                .map(|(field, bind)| (ByteCode::LDFIELD(field.to_owned()), bind))
+                .collect(),
+            _ => vec![],
+        };
+        if binds.is_empty() {
+            return self.compile_guarded_body(arm, arr);
+        }
+
+        let syms = binds.iter().map(|(_, bind)| bind.to_string()).collect();
+        arr.push(ByteCode::ENTERSCOPE(syms));
+        self.scope_depth += 1;
+
+        for (load, bind) in binds.iter() {
+            arr.push(ByteCode::ld(MATCH_SYM));
+            arr.push(load.to_owned());
+            arr.push(ByteCode::assign(*bind));
+        }
+        self.enter_names(binds.iter().map(|(_, bind)| *bind));
+        let res = self.compile_guarded_body(arm, arr);
+        self.bound.pop();
+        self.scope_depth -= 1;
+        let guard_idx = res?;
+
+        arr.push(ByteCode::EXITSCOPE);
+        Ok(guard_idx)
+    }
+
+    // guard; JOF; body
+    fn compile_guarded_body(
+        &mut self,
+        arm: &MatchArm,
+        arr: &mut Vec<ByteCode>,
+    ) -> Result<Option<usize>, CompileError> {
+        let mut guard_idx = None;
+        if let Some(guard) = &arm.guard {
+            self.compile_expr(guard, arr)?;
+            guard_idx.replace(arr.len());
+            arr.push(ByteCode::JOF(0));
+        }
+
+        self.compile_expr(&arm.body, arr)?;
+        Ok(guard_idx)
+    }
+
     /*Assumptions:
     1. Before entering a statement, op_stack length  is 0
     2. Upon jump on false, op stack length is 0
@@ -494,53 +1356,381 @@ impl Compiler {
         loop_data: &LoopData,
         arr: &mut Vec<ByteCode>,
     ) -> Result<(), CompileError> {
-        self.loop_stack.push(vec![]);
+        self.loop_stack.push(LoopCtx {
+            breaks: vec![],
+            scope_depth: self.scope_depth,
+        });
         let end_idx = self.compile_loop_inner(loop_data, arr);
 
+        let lp = self
+            .loop_stack
+            .pop()
+            .expect("Loop stack should be present since pushed earlier");
         let end_idx = end_idx?;
 
         // patch all the break stmts
-        let breaks = self
-            .loop_stack
-            .last()
-            .expect("Loop stack should be present since pushed earlier");
+        let breaks = lp.breaks;
 
         // Later: can use this to detect infinite loops
         // if breaks.len() == 0 && loop_data.cond.is_none() {
         //     dbg!("[WARNING] Breaks was empty: loop has no break");
         // }
 
-        for idx in breaks.iter() {
-            let idx = idx.to_owned();
+        for idx in breaks.into_iter() {
+            if let Some(ByteCode::GOTO(break_idx)) = arr.get_mut(idx) {
+                *break_idx = end_idx;
+            }
+        }
 
+        Ok(())
+    }
+
+    /*
+    for i in start..end { body } is compiled as (for arrays the element at $idx is loaded into i instead):
+        ENTERSCOPE [i, $idx, $end]
+        start, ASSIGN $idx, end, ASSIGN $end
+    loop_start:
+        LD $idx, LD $end, BINOP <, JOF loop_end
+        LD $idx, ASSIGN i
+        body, POP
+        $idx = $idx + 1
+        GOTO loop_start
+    loop_end:
+        EXITSCOPE, LDC Unit
+
+    The bounds are evaluated once, and assigning to i in the body doesn't change the iteration.
+    */
+    fn compile_for_inner(
+        &mut self,
+        for_data: &ForData,
+        arr: &mut Vec<ByteCode>,
+    ) -> Result<usize, CompileError> {
+        match &for_data.iter {
+            ForIter::Range {
+                start,
+                end,
+                inclusive,
+            } => {
+                self.compile_expr(start, arr)?;
+                arr.push(ByteCode::assign(FOR_IDX_SYM));
+
+                self.compile_expr(end, arr)?;
+                if *inclusive {
+                    arr.push(ByteCode::ldc(1));
+                    arr.push(ByteCode::BINOP(BinOp::Add));
+                }
+                arr.push(ByteCode::assign(FOR_END_SYM));
+            }
+            ForIter::Elems(elems) => {
+                self.compile_expr(elems, arr)?;
+                arr.push(ByteCode::assign(FOR_ARR_SYM));
+                arr.push(ByteCode::ldc(0));
+                arr.push(ByteCode::assign(FOR_IDX_SYM));
+                arr.push(ByteCode::ld(FOR_ARR_SYM));
+                arr.push(ByteCode::LEN);
+                arr.push(ByteCode::assign(FOR_END_SYM));
+            }
+        }
+
+        let loop_start = arr.len();
+        arr.push(ByteCode::ld(FOR_IDX_SYM));
+        arr.push(ByteCode::ld(FOR_END_SYM));
+        arr.push(ByteCode::BINOP(BinOp::Lt));
+        let jof_idx = arr.len();
+        arr.push(ByteCode::JOF(0));
+
+        if let ForIter::Elems(_) = for_data.iter {
+            arr.push(ByteCode::ld(FOR_ARR_SYM));
+            arr.push(ByteCode::ld(FOR_IDX_SYM));
+            arr.push(ByteCode::LDIDX);
+        } else {
+            arr.push(ByteCode::ld(FOR_IDX_SYM));
+        }
+        arr.push(ByteCode::assign(&for_data.var));
+
+        self.compile_block(&for_data.body, arr)?;
+        arr.push(ByteCode::POP);
+
+        arr.push(ByteCode::ld(FOR_IDX_SYM));
+        arr.push(ByteCode::ldc(1));
+        arr.push(ByteCode::BINOP(BinOp::Add));
+        arr.push(ByteCode::assign(FOR_IDX_SYM));
+        arr.push(ByteCode::GOTO(loop_start));
+
+        // JOF and break must jump to EXITSCOPE for the loop var scope
+        let loop_end_idx = arr.len();
+        if let Some(ByteCode::JOF(jmp_idx)) = arr.get_mut(jof_idx) {
+            *jmp_idx = loop_end_idx;
+        }
+
+        Ok(loop_end_idx)
+    }
+
+    // The loop var and hidden state get their own scope, which breaks leave through loop_end
+    fn compile_for(
+        &mut self,
+        for_data: &ForData,
+        arr: &mut Vec<ByteCode>,
+    ) -> Result<(), CompileError> {
+        let mut syms = vec![
+            for_data.var.to_owned(),
+            FOR_IDX_SYM.to_owned(),
+            FOR_END_SYM.to_owned(),
+        ];
+        if let ForIter::Elems(_) = for_data.iter {
+            syms.push(FOR_ARR_SYM.to_owned());
+        }
+
+        arr.push(ByteCode::ENTERSCOPE(syms));
+        self.scope_depth += 1;
+        self.loop_stack.push(LoopCtx {
+            breaks: vec![],
+            scope_depth: self.scope_depth,
+        });
+
+        self.enter_names([&for_data.var]);
+        let end_idx = self.compile_for_inner(for_data, arr);
+        self.bound.pop();
+
+        let lp = self
+            .loop_stack
+            .pop()
+            .expect("Loop stack should be present since pushed earlier");
+        self.scope_depth -= 1;
+        let end_idx = end_idx?;
+
+        arr.push(ByteCode::EXITSCOPE);
+        arr.push(ByteCode::ldc(Value::Unit));
+
+        for idx in lp.breaks.into_iter() {
             if let Some(ByteCode::GOTO(break_idx)) = arr.get_mut(idx) {
                 *break_idx = end_idx;
             }
         }
 
-        self.loop_stack.pop();
         Ok(())
     }
 
-    pub fn compile(mut self) -> anyhow::Result<Vec<ByteCode>, CompileError> {
+    pub fn compile(self) -> anyhow::Result<Vec<ByteCode>, CompileError> {
+        Ok(self.compile_with_warnings()?.0)
+    }
+
+    /// Compile the program, along with warnings for the code that was left out because it can never run.
+    pub fn compile_with_warnings(
+        self,
+    ) -> anyhow::Result<(Vec<ByteCode>, Vec<CompileWarning>), CompileError> {
+        let (bytecode, warnings, _) = self.compile_with_lines()?;
+        Ok((bytecode, warnings))
+    }
+
+    /// Like compile_with_warnings, also returning the source line each instruction was compiled from. The table
+    /// is empty if the program was optimized, since the peephole pass moves instructions around.
+    pub fn compile_with_lines(
+        mut self,
+    ) -> anyhow::Result<(Vec<ByteCode>, Vec<CompileWarning>, LineTable), CompileError> {
         let mut bytecode: Vec<ByteCode> = vec![];
         let prog = self.program.clone();
         self.compile_block_body(&prog, &mut bytecode)?;
         bytecode.push(ByteCode::DONE);
 
+        if self.optimize {
+            bytecode = peephole(bytecode);
+            self.lines = LineTable::new();
+        }
+
+        if self.position_independent {
+            bytecode = make_position_independent(bytecode);
+        }
+
+        Ok((bytecode, self.warnings, self.lines))
+    }
+
+    /// Compile the program as a line of a REPL session, onto the end of the bytecode of the lines before it. The
+    /// names it declares are bound in a scope that is never left, so the next line, compiled the same way and run
+    /// from where this one ends, can still use them. Jumps are to where the code ends up, so the result runs as it
+    /// is from the start of the line.
+    pub fn compile_line(
+        mut self,
+        mut bytecode: Vec<ByteCode>,
+    ) -> anyhow::Result<Vec<ByteCode>, CompileError> {
+        let prog = self.program.clone();
+        if !prog.symbols.is_empty() {
+            bytecode.push(ByteCode::ENTERSCOPE(prog.symbols.clone()));
+            self.scope_depth += 1;
+        }
+
+        self.bound.push(Compiler::block_names(&prog));
+        self.compile_block_decls(&prog, &mut bytecode)?;
+        bytecode.push(ByteCode::DONE);
         Ok(bytecode)
     }
+
+    /// Compile the program, folding exprs that only operate on literals into the constant they evaluate to
+    /// (`2+3*4` compiles to `LDC 14`), removing values that are popped right after being loaded and scopes that
+    /// are left right after being entered, and making jumps to a GOTO jump straight to where it goes.
+    pub fn compile_optimized(mut self) -> anyhow::Result<Vec<ByteCode>, CompileError> {
+        self.optimize = true;
+        self.compile()
+    }
+
+    /// Compile the program with fn and lambda bodies that are position independent: the jumps, spawns and LDFs in
+    /// a body that land in the same body use their relative forms, like GOTOR, so a linker can place the body
+    /// anywhere by rewriting only the LDF and GOTO that load it.
+    pub fn compile_position_independent(mut self) -> anyhow::Result<Vec<ByteCode>, CompileError> {
+        self.position_independent = true;
+        self.compile()
+    }
+
+    /// Compile the program for strict mode, where a let that shadows a name declared before it is warned about
+    /// and any warning is an error, so a program only compiles if it has no warnings at all.
+    pub fn compile_strict(mut self) -> anyhow::Result<(Vec<ByteCode>, LineTable), CompileError> {
+        self.strict = true;
+        let (bytecode, warnings, lines) = self.compile_with_lines()?;
+
+        match warnings.first() {
+            Some(warning) => Err(CompileError::new(message!(
+                C004,
+                "{}, and --strict makes warnings errors",
+                warning.msg
+            ))),
+            None => Ok((bytecode, lines)),
+        }
+    }
 }
 
 /// Takes in a string and returns compiled bytecode or errors
 pub fn compile_from_string(inp: &str, type_check: bool) -> Result<Vec<ByteCode>> {
-    let parser = parser::Parser::new_from_string(inp);
-    let program = parser.parse()?;
+    compile_with_defines(inp, type_check, &HashSet::new())
+}
+
+/// Like compile_from_string, with the flags that are defined for #[cfg(flag)] and cfg(flag)
+pub fn compile_with_defines(
+    inp: &str,
+    type_check: bool,
+    defines: &HashSet<String>,
+) -> Result<Vec<ByteCode>> {
+    Ok(compile_with_warnings(inp, type_check, defines, false)?.0)
+}
+
+/// Like compile_with_defines, also returning warnings for the code that can never run. If optimize is set,
+/// the program is compiled like Compiler::compile_optimized
+pub fn compile_with_warnings(
+    inp: &str,
+    type_check: bool,
+    defines: &HashSet<String>,
+    optimize: bool,
+) -> Result<(Vec<ByteCode>, Vec<CompileWarning>)> {
+    let program = desugar_with_defines(inp, defines)?;
+
+    if type_check {
+        TypeChecker::new(&program).type_check()?;
+    }
+
+    let mut compiler = Compiler::new(program);
+    compiler.optimize = optimize;
+    Ok(compiler.compile_with_warnings()?)
+}
+
+/// Like compile_with_defines, also returning warnings and the source line each instruction was compiled from,
+/// see Compiler::compile_with_lines
+pub fn compile_with_lines(
+    inp: &str,
+    type_check: bool,
+    defines: &HashSet<String>,
+) -> Result<(Vec<ByteCode>, Vec<CompileWarning>, LineTable)> {
+    let program = desugar_with_defines(inp, defines)?;
 
     if type_check {
         TypeChecker::new(&program).type_check()?;
     }
 
-    let compiler = Compiler::new(program);
-    Ok(compiler.compile()?)
+    Ok(Compiler::new(program).compile_with_lines()?)
+}
+
+/// Type check and compile the program in strict mode, see Compiler::compile_strict. The type checker already
+/// rejects mixing int and float without a conversion and fn params without a type annotation, and the VM checks
+/// every arithmetic op for overflow, so strict mode only adds the shadowing warning and denies warnings.
+pub fn compile_strict(inp: &str, defines: &HashSet<String>) -> Result<(Vec<ByteCode>, LineTable)> {
+    let program = desugar_with_defines(inp, defines)?;
+    TypeChecker::new(&program).type_check()?;
+    Ok(Compiler::new(program).compile_strict()?)
+}
+
+/// A #[test] fn and the program that runs it, see compile_tests.
+pub struct TestProgram {
+    pub name: String,
+    pub bytecode: Vec<ByteCode>,
+    pub lines: LineTable,
+}
+
+/// Type check the program and compile it once for each #[test] fn declared at its top level, with a call to the
+/// fn in place of the last expression of the program, like a main that isn't run. A test passes if its program
+/// runs to the end, and fails if the top level code or the fn stop with an error, like a false assert. Also
+/// returns the warnings for the program as it is.
+pub fn compile_tests(
+    inp: &str,
+    defines: &HashSet<String>,
+) -> Result<(Vec<TestProgram>, Vec<CompileWarning>)> {
+    let program = desugar_with_defines(inp, defines)?;
+    TypeChecker::new(&program).type_check()?;
+
+    let names: Vec<(usize, String)> = program
+        .decls
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, decl)| match decl {
+            Decl::FnDeclStmt(data) if data.attrs.iter().any(|attr| attr.name == ATTR_TEST) => {
+                Some((idx, data.name.to_owned()))
+            }
+            _ => None,
+        })
+        .collect();
+
+    let mut tests = vec![];
+    for (idx, name) in names {
+        let mut test = program.clone();
+        test.last_expr = None;
+        // the call is put where the fn is declared, so a backtrace shows which test it is from
+        test.spans.truncate(test.decls.len());
+        match program.decl_span(idx) {
+            Some(span) if test.spans.len() == test.decls.len() => test.spans.push(span),
+            _ => (),
+        }
+        test.decls.push(Decl::ExprStmt(Expr::FnCallExpr(FnCallData {
+            name: name.to_owned(),
+            args: vec![],
+        })));
+
+        let (bytecode, _, lines) = Compiler::new(test).compile_with_lines()?;
+        tests.push(TestProgram {
+            name,
+            bytecode,
+            lines,
+        });
+    }
+
+    let (_, warnings) = Compiler::new(program).compile_with_warnings()?;
+    Ok((tests, warnings))
+}
+
+/// Parse the input and apply every source to source pass that runs before type checking: macro expansion
+/// and cfg flags. The result is the program that is actually type checked and compiled.
+pub fn desugar_with_defines(inp: &str, defines: &HashSet<String>) -> Result<BlockSeq> {
+    let parser = parser::Parser::new_from_string(inp);
+    Ok(apply_cfg(parser.parse()?, defines)?)
+}
+
+/// Display a desugared program with each top level declaration on its own line.
+pub fn fmt_desugared(program: &BlockSeq) -> String {
+    let mut lines: Vec<String> = program
+        .decls
+        .iter()
+        .map(|decl| format!("{};", decl))
+        .collect();
+
+    if let Some(expr) = &program.last_expr {
+        lines.push(expr.to_string());
+    }
+
+    lines.join("\n")
 }
@@ -0,0 +1,156 @@
+use parser::structs::{
+    BlockSeq, Decl, EnumDeclData, FnDeclData, LetStmtData, StructDeclData, Type,
+};
+
+/// Generate Markdown docs for the top-level declarations of a program: every struct, enum and fn, and every let
+/// with a doc comment.
+pub fn generate_docs(program: &BlockSeq, title: &str) -> String {
+    let mut structs: Vec<&StructDeclData> = vec![];
+    let mut enums: Vec<&EnumDeclData> = vec![];
+    let mut fns: Vec<&FnDeclData> = vec![];
+    let mut lets: Vec<&LetStmtData> = vec![];
+
+    for decl in program.decls.iter() {
+        match decl {
+            Decl::StructDeclStmt(data) => structs.push(data),
+            Decl::EnumDeclStmt(data) => enums.push(data),
+            Decl::FnDeclStmt(data) => fns.push(data),
+            Decl::LetStmt(data) if data.doc.is_some() => lets.push(data),
+            _ => (),
+        }
+    }
+
+    let mut out = format!("# {}\n", title);
+
+    if !structs.is_empty() {
+        out.push_str("\n## Structs\n");
+        for data in structs {
+            push_item(&mut out, &data.to_string(), &data.doc);
+        }
+    }
+
+    if !enums.is_empty() {
+        out.push_str("\n## Enums\n");
+        for data in enums {
+            push_item(&mut out, &data.to_string(), &data.doc);
+        }
+    }
+
+    if !fns.is_empty() {
+        out.push_str("\n## Functions\n");
+        for data in fns {
+            push_item(&mut out, &fn_signature(data), &data.doc);
+        }
+    }
+
+    if !lets.is_empty() {
+        out.push_str("\n## Variables\n");
+        for data in lets {
+            push_item(&mut out, &let_signature(data), &data.doc);
+        }
+    }
+
+    out
+}
+
+fn push_item(out: &mut String, signature: &str, doc: &Option<String>) {
+    out.push_str(&format!("\n### `{}`\n", signature));
+    if let Some(doc) = doc {
+        out.push_str(&format!("\n{}\n", doc));
+    }
+}
+
+fn fn_signature(data: &FnDeclData) -> String {
+    let params: Vec<String> = data
+        .params
+        .iter()
+        .map(|param| match &param.type_ann {
+            Some(ty) => format!("{}: {}", param.name, ty),
+            None => param.name.to_string(),
+        })
+        .collect();
+
+    let ret = match &data.ret_type {
+        Some(ty) if *ty != Type::Unit => format!(" -> {}", ty),
+        _ => String::new(),
+    };
+
+    format!("fn {}({}){}", data.name, params.join(", "), ret)
+}
+
+fn let_signature(data: &LetStmtData) -> String {
+    match &data.type_ann {
+        Some(ty) => format!("let {}: {}", data.ident, ty),
+        None => format!("let {}", data.ident),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::generate_docs;
+
+    fn docs(inp: &str) -> String {
+        let prog = parser::Parser::new_from_string(inp)
+            .parse()
+            .expect("Should parse");
+        generate_docs(&prog, "math")
+    }
+
+    #[test]
+    fn test_generate_docs() {
+        let t = r"
+        /// Adds two ints.
+        ///
+        /// Overflow panics.
+        fn add(x: int, y: int) -> int {
+            x + y
+        }
+
+        fn log(msg: str) {
+            /// not top-level, so not documented
+            let y = 2;
+            println(msg);
+        }
+
+        /// Scale factor
+        let factor: float = 2.0;
+        let undocumented = 3;
+
+        /// A point in the plane.
+        struct Point { x: float, y: float }
+
+        enum Sign { Neg, Zero, Pos(int) }
+        ";
+        let exp = "# math
+
+## Structs
+
+### `struct Point { x: float, y: float }`
+
+A point in the plane.
+
+## Enums
+
+### `enum Sign { Neg, Zero, Pos(int) }`
+
+## Functions
+
+### `fn add(x: int, y: int) -> int`
+
+Adds two ints.
+
+Overflow panics.
+
+### `fn log(msg: str)`
+
+## Variables
+
+### `let factor: float`
+
+Scale factor
+";
+        assert_eq!(docs(t), exp);
+
+        assert_eq!(docs("2 + 3"), "# math\n");
+    }
+}
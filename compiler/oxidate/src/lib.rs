@@ -1,2 +1,7 @@
+pub mod bcdiff;
 pub mod compiler;
+pub mod doc;
+mod optimize;
+mod reachability;
+mod relocate;
 pub mod tests;
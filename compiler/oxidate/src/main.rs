@@ -1,14 +1,28 @@
 pub mod compiler;
+pub mod doc;
+mod optimize;
+mod reachability;
+mod relocate;
 
 use anyhow::{Error, Result};
-use bytecode::write_bytecode;
+use bytecode::write_to_file;
 use clap::Parser;
+use diagnostics::message;
 use std::{io::Read, path::Path};
 
-use crate::compiler::{compile_from_string, CompileError};
+use crate::compiler::{compile_with_warnings, desugar_with_defines, fmt_desugared, CompileError};
+use crate::doc::generate_docs;
+use crate::relocate::make_position_independent;
 
 const RST: &str = "rst";
 
+/// What to print instead of compiling
+#[derive(clap::ValueEnum, Clone, Debug)]
+enum Emit {
+    /// The program after macro expansion and cfg flags, as it is type checked and compiled
+    Desugared,
+}
+
 #[derive(clap::Parser, Debug)]
 #[command(name = "Oxidate")]
 #[command(version = "0.1.0")]
@@ -24,6 +38,26 @@ struct Args {
     /// If present, does not type check
     #[arg(short)]
     notype: bool,
+
+    /// Define a flag for #[cfg(flag)] and cfg(flag). Can be repeated
+    #[arg(long = "define", value_name = "FLAG")]
+    defines: Vec<String>,
+
+    /// Write Markdown docs for the file's top-level declarations to <out>.md instead of compiling
+    #[arg(long)]
+    doc: bool,
+
+    /// Fold constant expressions and clean up the bytecode
+    #[arg(short = 'O', long)]
+    optimize: bool,
+
+    /// Give the addresses in fn bodies as offsets from where they are, so a linker can place the bodies anywhere
+    #[arg(long)]
+    pic: bool,
+
+    /// Print an intermediate form of the program to stdout instead of compiling
+    #[arg(long, value_name = "STAGE")]
+    emit: Option<Emit>,
 }
 
 fn main() -> Result<()> {
@@ -32,20 +66,20 @@ fn main() -> Result<()> {
     let path = Path::new(&file);
 
     if !path.exists() {
-        let err = format!("File '{}' does not exist", file);
-        return Err(CompileError::new(&err).into());
+        let err = message!(C002, "File '{}' does not exist", file);
+        return Err(CompileError::new(err).into());
     }
 
     match path.extension() {
         Some(ext) => {
             if ext != RST {
-                let err = format!("File {} does not have extension .{RST}", file);
-                return Err(CompileError::new(&err).into());
+                let err = message!(C003, "File {} does not have extension .{}", file, RST);
+                return Err(CompileError::new(err).into());
             }
         }
         None => {
-            let err = format!("File {} does not have extension .{RST}", file);
-            return Err(CompileError::new(&err).into());
+            let err = message!(C003, "File {} does not have extension .{}", file, RST);
+            return Err(CompileError::new(err).into());
         }
     }
 
@@ -54,14 +88,6 @@ fn main() -> Result<()> {
         .expect("File should exist")
         .read_to_string(&mut code)?;
 
-    let bytecode = match compile_from_string(&code, !args.notype) {
-        Ok(bc) => bc,
-        Err(err) => {
-            let e = format!("\n{}", err);
-            return Err(Error::msg(e));
-        }
-    };
-
     let out_name;
     if let Some(name) = args.out {
         out_name = name;
@@ -74,10 +100,57 @@ fn main() -> Result<()> {
             .expect("File name should be valid string");
     }
 
+    if args.doc {
+        let program = match parser::Parser::new_from_string(&code).parse() {
+            Ok(program) => program,
+            Err(err) => {
+                let e = format!("\n{}", err);
+                return Err(Error::msg(e));
+            }
+        };
+
+        let doc_name = format!("{}.md", out_name);
+        std::fs::write(&doc_name, generate_docs(&program, &out_name))?;
+
+        println!("Generated docs in {}", doc_name);
+        return Ok(());
+    }
+
+    let defines = args.defines.into_iter().collect();
+
+    if let Some(Emit::Desugared) = args.emit {
+        let program = match desugar_with_defines(&code, &defines) {
+            Ok(program) => program,
+            Err(err) => {
+                let e = format!("\n{}", err);
+                return Err(Error::msg(e));
+            }
+        };
+
+        println!("{}", fmt_desugared(&program));
+        return Ok(());
+    }
+
+    let mut bytecode = match compile_with_warnings(&code, !args.notype, &defines, args.optimize) {
+        Ok((bc, warnings)) => {
+            for warning in warnings.iter() {
+                eprintln!("{}", warning);
+            }
+            bc
+        }
+        Err(err) => {
+            let e = format!("\n{}", err);
+            return Err(Error::msg(e));
+        }
+    };
+
+    if args.pic {
+        bytecode = make_position_independent(bytecode);
+    }
+
     // Write to .o2 file
     let bc_name = format!("{}.o2", out_name);
-    let mut bc_file = std::fs::File::create(&bc_name).unwrap();
-    write_bytecode(&bytecode, &mut bc_file)?;
+    write_to_file(&bytecode, &bc_name)?;
 
     println!("Compiled successfully to {}", bc_name);
 
@@ -0,0 +1,271 @@
+use bytecode::{ByteCode, Value};
+use parser::structs::{BinOpType, Expr, UnOpType};
+
+/// The value of expr if it only operates on literals, worked out the way the VM would. Anything the VM would
+/// fail on, like an overflow, a division by zero or mismatched types, is left for it to fail on at runtime.
+pub(crate) fn const_value(expr: &Expr) -> Option<Value> {
+    match expr {
+        Expr::Integer(val) => Some(Value::Int(*val)),
+        Expr::Float(val) => Some(Value::Float(*val)),
+        Expr::Bool(val) => Some(Value::Bool(*val)),
+        Expr::StringLiteral(val) => Some(Value::String(val.as_str().into())),
+        Expr::Char(c) => Some(Value::Char(*c)),
+        Expr::UnOpExpr(op, expr) => match (op, const_value(expr)?) {
+            (UnOpType::Negate, Value::Int(val)) => val.checked_neg().map(Value::Int),
+            (UnOpType::Negate, Value::Float(val)) => Some(Value::Float(-val)),
+            (UnOpType::Not, Value::Int(val)) => Some(Value::Int(!val)),
+            (UnOpType::Not, Value::Bool(val)) => Some(Value::Bool(!val)),
+            _ => None,
+        },
+        Expr::BinOpExpr(op, lhs, rhs) => const_binop(op, const_value(lhs)?, const_value(rhs)?),
+        _ => None,
+    }
+}
+
+fn const_binop(op: &BinOpType, lhs: Value, rhs: Value) -> Option<Value> {
+    let val = match (lhs, rhs) {
+        (Value::Int(lhs), Value::Int(rhs)) => match op {
+            BinOpType::Add => Value::Int(lhs.checked_add(rhs)?),
+            BinOpType::Sub => Value::Int(lhs.checked_sub(rhs)?),
+            BinOpType::Mul => Value::Int(lhs.checked_mul(rhs)?),
+            BinOpType::Div => Value::Int(lhs.checked_div(rhs)?),
+            BinOpType::Mod => Value::Int(lhs.checked_rem(rhs)?),
+            BinOpType::Gt => Value::Bool(lhs > rhs),
+            BinOpType::Lt => Value::Bool(lhs < rhs),
+            BinOpType::LogicalEq => Value::Bool(lhs == rhs),
+            BinOpType::LogicalAnd | BinOpType::LogicalOr => return None,
+        },
+        (Value::Float(lhs), Value::Float(rhs)) => match op {
+            BinOpType::Add => Value::Float(lhs + rhs),
+            BinOpType::Sub => Value::Float(lhs - rhs),
+            BinOpType::Mul => Value::Float(lhs * rhs),
+            BinOpType::Div => Value::Float(lhs / rhs),
+            BinOpType::Gt => Value::Bool(lhs > rhs),
+            BinOpType::Lt => Value::Bool(lhs < rhs),
+            BinOpType::LogicalEq => Value::Bool(lhs == rhs),
+            BinOpType::Mod | BinOpType::LogicalAnd | BinOpType::LogicalOr => return None,
+        },
+        (Value::Bool(lhs), Value::Bool(rhs)) => match op {
+            BinOpType::LogicalAnd => Value::Bool(lhs && rhs),
+            BinOpType::LogicalOr => Value::Bool(lhs || rhs),
+            BinOpType::LogicalEq => Value::Bool(lhs == rhs),
+            _ => return None,
+        },
+        (Value::String(lhs), Value::String(rhs)) => match op {
+            BinOpType::Add => Value::String(format!("{}{}", lhs, rhs).into()),
+            BinOpType::LogicalEq => Value::Bool(lhs == rhs),
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    Some(val)
+}
+
+/// Clean up compiled bytecode: remove values that are popped right after being loaded and scopes that are left
+/// right after being entered, and make jumps to a GOTO go straight to where it goes.
+pub(crate) fn peephole(code: Vec<ByteCode>) -> Vec<ByteCode> {
+    let code = collapse_jumps(code);
+    remove_no_ops(code)
+}
+
+// The address an instruction jumps to or starts code at, if it has one
+fn target(instr: &ByteCode) -> Option<usize> {
+    match instr {
+        ByteCode::GOTO(addr)
+        | ByteCode::JOF(addr)
+        | ByteCode::SPAWN(addr)
+        | ByteCode::SPAWNISO(addr)
+        | ByteCode::LDF(addr, _) => Some(*addr),
+        _ => None,
+    }
+}
+
+fn target_mut(instr: &mut ByteCode) -> Option<&mut usize> {
+    match instr {
+        ByteCode::GOTO(addr)
+        | ByteCode::JOF(addr)
+        | ByteCode::SPAWN(addr)
+        | ByteCode::SPAWNISO(addr)
+        | ByteCode::LDF(addr, _) => Some(addr),
+        _ => None,
+    }
+}
+
+fn collapse_jumps(mut code: Vec<ByteCode>) -> Vec<ByteCode> {
+    for idx in 0..code.len() {
+        let (ByteCode::GOTO(addr) | ByteCode::JOF(addr)) = code[idx] else {
+            continue;
+        };
+
+        // at most one step per instruction, so a loop of GOTOs can't hang
+        let mut dest = addr;
+        for _ in 0..code.len() {
+            match code.get(dest) {
+                Some(ByteCode::GOTO(next)) if *next != dest => dest = *next,
+                _ => break,
+            }
+        }
+
+        if let ByteCode::GOTO(addr) | ByteCode::JOF(addr) = &mut code[idx] {
+            *addr = dest;
+        }
+    }
+
+    code
+}
+
+// Whether running a then b, with nothing jumping to b, does nothing. Loading a symbol can only fail if it is
+// not declared, which the type checker rules out
+fn is_no_op(a: &ByteCode, b: &ByteCode) -> bool {
+    matches!(
+        (a, b),
+        (ByteCode::LDC(_) | ByteCode::LD(_), ByteCode::POP)
+            | (ByteCode::ENTERSCOPE(_), ByteCode::EXITSCOPE)
+    )
+}
+
+// Removing a pair can make another, e.g a scope holding only a popped value, so this goes until none are left
+fn remove_no_ops(mut code: Vec<ByteCode>) -> Vec<ByteCode> {
+    loop {
+        let mut targets = vec![false; code.len() + 1];
+        for instr in code.iter() {
+            if let Some(addr) = target(instr) {
+                targets[addr.min(code.len())] = true;
+            }
+        }
+
+        // a pair can't go if something jumps to its second instruction, since that would skip the first
+        let mut remove = vec![false; code.len()];
+        let mut removed = false;
+        let mut idx = 0;
+        while idx + 1 < code.len() {
+            if is_no_op(&code[idx], &code[idx + 1]) && !targets[idx + 1] {
+                remove[idx] = true;
+                remove[idx + 1] = true;
+                removed = true;
+                idx += 2;
+            } else {
+                idx += 1;
+            }
+        }
+
+        if !removed {
+            return code;
+        }
+        code = remove_instrs(code, &remove);
+    }
+}
+
+/// Remove the instructions marked in remove, fixing up every address to where the instruction it pointed to
+/// went. An address of a removed instruction points to the next one that is kept.
+fn remove_instrs(code: Vec<ByteCode>, remove: &[bool]) -> Vec<ByteCode> {
+    // new_addr[i] is the number of instructions kept before i
+    let mut new_addr = Vec::with_capacity(code.len() + 1);
+    let mut kept = 0;
+    for removed in remove.iter() {
+        new_addr.push(kept);
+        if !removed {
+            kept += 1;
+        }
+    }
+    new_addr.push(kept);
+
+    code.into_iter()
+        .zip(remove)
+        .filter(|(_, removed)| !**removed)
+        .map(|(mut instr, _)| {
+            if let Some(addr) = target_mut(&mut instr) {
+                *addr = new_addr[(*addr).min(new_addr.len() - 1)];
+            }
+            instr
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use bytecode::ByteCode::*;
+    use bytecode::{ByteCode, Value};
+
+    use super::peephole;
+
+    #[test]
+    fn test_peephole_unit_pops() {
+        // the jump to the removed pair goes to what comes after it
+        let code = vec![
+            ByteCode::ldc(true),
+            JOF(4),
+            LDC(Value::Unit),
+            POP,
+            LDC(Value::Unit),
+            POP,
+            DONE,
+        ];
+        assert_eq!(peephole(code), vec![ByteCode::ldc(true), JOF(2), DONE]);
+
+        // a jump to the POP keeps the pair
+        let code = vec![GOTO(2), LDC(Value::Unit), POP, DONE];
+        assert_eq!(peephole(code.clone()), code);
+    }
+
+    #[test]
+    fn test_peephole_loads_and_scopes() {
+        let code = vec![
+            ByteCode::ld("x"),
+            POP,
+            ByteCode::ldc(1),
+            POP,
+            ByteCode::ld("x"),
+            DONE,
+        ];
+        assert_eq!(peephole(code), vec![ByteCode::ld("x"), DONE]);
+
+        // the scope is empty once the popped value inside it is gone
+        let code = vec![
+            ByteCode::enterscope(vec!["x"]),
+            LDC(Value::Unit),
+            POP,
+            EXITSCOPE,
+            GOTO(0),
+        ];
+        assert_eq!(peephole(code), vec![GOTO(0)]);
+
+        // a scope that binds something is kept
+        let code = vec![
+            ByteCode::enterscope(vec!["x"]),
+            ByteCode::ldc(1),
+            ByteCode::assign("x"),
+            EXITSCOPE,
+            DONE,
+        ];
+        assert_eq!(peephole(code.clone()), code);
+    }
+
+    #[test]
+    fn test_peephole_jump_chains() {
+        let code = vec![
+            ByteCode::ldc(false),
+            JOF(3),
+            GOTO(4),
+            GOTO(5),
+            GOTO(3),
+            DONE,
+        ];
+        assert_eq!(
+            peephole(code),
+            vec![
+                ByteCode::ldc(false),
+                JOF(5),
+                GOTO(5),
+                GOTO(5),
+                GOTO(5),
+                DONE
+            ]
+        );
+
+        // a loop of GOTOs stays a loop
+        let code = vec![GOTO(1), GOTO(0), DONE];
+        assert_eq!(peephole(code.clone()).len(), 3);
+    }
+}
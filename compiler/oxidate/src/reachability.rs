@@ -0,0 +1,131 @@
+use parser::structs::{BlockSeq, Decl, Expr, ForIter};
+
+// Whether control can never get past a decl or expr, and what stops it. Conservative: anything not known to
+// diverge is taken to carry on, so only code that is certainly dead is reported and left out of the bytecode
+
+/// What stops control getting past decl, if it never does.
+pub(crate) fn decl_diverges(decl: &Decl) -> Option<&'static str> {
+    match decl {
+        Decl::ReturnStmt(_) => Some("return"),
+        Decl::BreakStmt => Some("break"),
+        Decl::LoopStmt(lp) if lp.cond.is_none() && !blk_breaks(&lp.body) => Some("infinite loop"),
+        Decl::ExprStmt(expr) => expr_diverges(expr),
+        Decl::LetStmt(stmt) => expr_diverges(&stmt.expr),
+        Decl::LetTupleStmt(stmt) => expr_diverges(&stmt.expr),
+        _ => None,
+    }
+}
+
+/// What stops control getting past blk, if some decl in it or its last expr never finishes.
+fn blk_diverges(blk: &BlockSeq) -> Option<&'static str> {
+    blk.decls
+        .iter()
+        .find_map(decl_diverges)
+        .or_else(|| blk.last_expr.as_ref().and_then(|expr| expr_diverges(expr)))
+}
+
+fn expr_diverges(expr: &Expr) -> Option<&'static str> {
+    match expr {
+        Expr::BlockExpr(blk) => blk_diverges(blk),
+        Expr::LockExpr(data) => blk_diverges(&data.body),
+        // every branch has to diverge
+        Expr::IfElseExpr(if_else) => {
+            let if_blk = blk_diverges(&if_else.if_blk)?;
+            if_else.else_blk.as_ref().and_then(blk_diverges)?;
+            Some(if_blk)
+        }
+        Expr::MatchExpr(data) => {
+            let mut arms = data.arms.iter().map(|arm| expr_diverges(&arm.body));
+            let first = arms.next()??;
+            arms.all(|arm| arm.is_some()).then_some(first)
+        }
+        _ => None,
+    }
+}
+
+// Whether a break in blk leaves the loop blk is the body of. Breaks in nested loops leave those instead, and fns
+// and lambdas can't break out at all
+fn blk_breaks(blk: &BlockSeq) -> bool {
+    blk.decls.iter().any(decl_breaks)
+        || blk.last_expr.as_ref().is_some_and(|expr| expr_breaks(expr))
+}
+
+fn decl_breaks(decl: &Decl) -> bool {
+    match decl {
+        Decl::BreakStmt => true,
+        Decl::LetStmt(stmt) => expr_breaks(&stmt.expr),
+        Decl::LetTupleStmt(stmt) => expr_breaks(&stmt.expr),
+        Decl::AssignStmt(stmt) => expr_breaks(&stmt.expr),
+        Decl::IndexAssignStmt(stmt) => {
+            expr_breaks(&stmt.arr) || expr_breaks(&stmt.index) || expr_breaks(&stmt.expr)
+        }
+        Decl::FieldAssignStmt(stmt) => expr_breaks(&stmt.obj) || expr_breaks(&stmt.expr),
+        Decl::ExprStmt(expr) => expr_breaks(expr),
+        Decl::ReturnStmt(expr) => expr.as_ref().is_some_and(expr_breaks),
+        Decl::IfOnlyStmt(if_else) => {
+            expr_breaks(&if_else.cond)
+                || blk_breaks(&if_else.if_blk)
+                || if_else.else_blk.as_ref().is_some_and(blk_breaks)
+        }
+        // the cond of a nested loop is still in this loop, the body isn't
+        Decl::LoopStmt(lp) => lp.cond.as_ref().is_some_and(expr_breaks),
+        Decl::ForStmt(lp) => match &lp.iter {
+            ForIter::Range { start, end, .. } => expr_breaks(start) || expr_breaks(end),
+            ForIter::Elems(expr) => expr_breaks(expr),
+        },
+        Decl::FnDeclStmt(_)
+        | Decl::StructDeclStmt(_)
+        | Decl::EnumDeclStmt(_)
+        | Decl::MacroDeclStmt(_)
+        | Decl::WaitStmt(_)
+        | Decl::PostStmt(_)
+        | Decl::YieldStmt => false,
+    }
+}
+
+// No wildcard arm, so a new kind of expr has to decide whether it can hold a break
+fn expr_breaks(expr: &Expr) -> bool {
+    match expr {
+        Expr::BlockExpr(blk) => blk_breaks(blk),
+        Expr::IfElseExpr(if_else) => {
+            expr_breaks(&if_else.cond)
+                || blk_breaks(&if_else.if_blk)
+                || if_else.else_blk.as_ref().is_some_and(blk_breaks)
+        }
+        Expr::LockExpr(data) => expr_breaks(&data.mutex) || blk_breaks(&data.body),
+        Expr::MatchExpr(data) => {
+            expr_breaks(&data.subject)
+                || data.arms.iter().any(|arm| {
+                    arm.guard.as_ref().is_some_and(expr_breaks) || expr_breaks(&arm.body)
+                })
+        }
+        Expr::UnOpExpr(_, expr)
+        | Expr::FieldAccessExpr(expr, _)
+        | Expr::TupleIndexExpr(expr, _)
+        | Expr::ArrayFillExpr(expr, _) => expr_breaks(expr),
+        Expr::BinOpExpr(_, lhs, rhs) | Expr::IndexExpr(lhs, rhs) | Expr::CoalesceExpr(lhs, rhs) => {
+            expr_breaks(lhs) || expr_breaks(rhs)
+        }
+        Expr::SliceExpr(slice) => {
+            expr_breaks(&slice.arr)
+                || slice.start.as_ref().is_some_and(expr_breaks)
+                || slice.end.as_ref().is_some_and(expr_breaks)
+        }
+        Expr::FnCallExpr(call) | Expr::MacroCallExpr(call) => call.args.iter().any(expr_breaks),
+        Expr::SpawnExpr(data) => data.call.args.iter().any(expr_breaks),
+        Expr::TryJoinExpr(data) => data.timeout.as_ref().is_some_and(expr_breaks),
+        Expr::ArrayExpr(elems) | Expr::TupleExpr(elems) => elems.iter().any(expr_breaks),
+        Expr::StructExpr(data) => data.fields.iter().any(|(_, expr)| expr_breaks(expr)),
+        Expr::MapExpr(entries) => entries
+            .iter()
+            .any(|(key, val)| expr_breaks(key) || expr_breaks(val)),
+        Expr::LambdaExpr(_)
+        | Expr::JoinExpr(_)
+        | Expr::Symbol(_)
+        | Expr::Integer(_)
+        | Expr::Float(_)
+        | Expr::Bool(_)
+        | Expr::StringLiteral(_)
+        | Expr::Char(_) => false,
+    }
+}
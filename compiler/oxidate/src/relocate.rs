@@ -0,0 +1,145 @@
+use bytecode::ByteCode;
+
+/// Make the bodies of fns and lambdas position independent: every jump, spawn and LDF inside a body that lands
+/// inside the same body is given as an offset from where it is, so the body can be placed anywhere by moving it
+/// and rewriting only the LDF and GOTO that load it. Top level code keeps its absolute addresses, since that is
+/// what a linker places bodies around.
+///
+/// A fn compiles to LDF(start), GOTO(end) and the body from start up to end, so the bodies are found the same way
+/// bcdiff finds them. No instruction is added or moved, so the line table of the program still holds.
+pub(crate) fn make_position_independent(mut code: Vec<ByteCode>) -> Vec<ByteCode> {
+    for (start, end) in outermost_bodies(&code) {
+        for (pc, instr) in code.iter_mut().enumerate().take(end).skip(start) {
+            let in_body = instr
+                .target(pc)
+                .is_some_and(|addr| start <= addr && addr < end);
+            if !in_body {
+                continue;
+            }
+
+            if let Some(relative) = instr.to_relative(pc) {
+                *instr = relative;
+            }
+        }
+    }
+
+    code
+}
+
+// The (start, end) of the bodies that aren't nested in another body, in order
+fn outermost_bodies(code: &[ByteCode]) -> Vec<(usize, usize)> {
+    let mut bodies: Vec<(usize, usize)> = vec![];
+    for (i, instr) in code.iter().enumerate() {
+        let (ByteCode::LDF(start, _), Some(ByteCode::GOTO(end))) = (instr, code.get(i + 1)) else {
+            continue;
+        };
+
+        if *start != i + 2 || end < start || *end > code.len() {
+            continue;
+        }
+
+        match bodies.last() {
+            Some((_, outer_end)) if i < *outer_end => (),
+            _ => bodies.push((*start, *end)),
+        }
+    }
+
+    bodies
+}
+
+#[cfg(test)]
+mod tests {
+    use bytecode::{BinOp, FrameType, Value};
+
+    use super::*;
+    use crate::compiler::Compiler;
+
+    fn compile_pic(inp: &str) -> Vec<ByteCode> {
+        let parser = parser::Parser::new_from_string(inp);
+        let program = parser.parse().expect("Should parse");
+        Compiler::new(program)
+            .compile_position_independent()
+            .expect("Should compile")
+    }
+
+    #[test]
+    fn test_position_independent_fn() {
+        use ByteCode::*;
+
+        let code = compile_pic("fn f(x: int) -> int { if x > 0 { 1 } else { 2 } } f(3)");
+        let exp = vec![
+            ENTERSCOPE(vec!["f".to_string()]),
+            ByteCode::ldf(3, vec!["x"]),
+            GOTO(11),
+            // body, from 3 up to 11
+            ByteCode::ld("x"),
+            ByteCode::ldc(0),
+            BINOP(BinOp::Gt),
+            JOFR(3),
+            ByteCode::ldc(1),
+            GOTOR(2),
+            ByteCode::ldc(2),
+            RESET(FrameType::CallFrame),
+            // back at the top level
+            ByteCode::assign("f"),
+            LDC(Value::Unit),
+            POP,
+            ByteCode::ld("f"),
+            ByteCode::ldc(3),
+            CALL(1),
+            EXITSCOPE,
+            DONE,
+        ];
+        assert_eq!(code, exp);
+    }
+
+    #[test]
+    fn test_position_independent_nested() {
+        // the lambda and the spawn inside f are relative, the loop at the top level is not
+        let code = compile_pic(
+            r"
+            fn f() {
+                let g = |y: int| y;
+                spawn g(1);
+            }
+            let i = 0;
+            loop i < 3 { i = i + 1; }
+            ",
+        );
+
+        let body = 3..code
+            .iter()
+            .position(|instr| *instr == ByteCode::assign("f"))
+            .expect("f is assigned");
+        assert!(matches!(code[1], ByteCode::LDF(3, _)));
+        assert!(matches!(code[2], ByteCode::GOTO(_)));
+        for pc in body.clone() {
+            assert!(
+                !matches!(
+                    code[pc],
+                    ByteCode::LDF(..)
+                        | ByteCode::GOTO(_)
+                        | ByteCode::JOF(_)
+                        | ByteCode::SPAWN(_)
+                        | ByteCode::SPAWNISO(_)
+                ),
+                "{:?} at {} is absolute",
+                code[pc],
+                pc
+            );
+        }
+        assert!(code[body.clone()]
+            .iter()
+            .any(|instr| matches!(instr, ByteCode::LDFR(..))));
+        assert!(code[body.clone()]
+            .iter()
+            .any(|instr| matches!(instr, ByteCode::SPAWNR(_))));
+
+        assert!(code[body.end..]
+            .iter()
+            .any(|instr| matches!(instr, ByteCode::JOF(_))));
+        assert!(!code[body.end..]
+            .iter()
+            .any(|instr| matches!(instr, ByteCode::JOFR(_) | ByteCode::GOTOR(_))));
+    }
+}
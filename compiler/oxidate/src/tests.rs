@@ -1,4 +1,5 @@
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests {
 
     use std::vec;
@@ -6,9 +7,14 @@ mod tests {
     use bytecode::ByteCode;
     use bytecode::ByteCode::*;
     use bytecode::Value::*;
+    use diagnostics::Code;
     use parser::Parser;
 
-    use crate::compiler::Compiler;
+    use std::collections::HashSet;
+
+    use crate::compiler::{
+        compile_tests, desugar_with_defines, fmt_desugared, CompileWarning, Compiler,
+    };
 
     fn exp_compile_str(inp: &str) -> Vec<ByteCode> {
         let parser = Parser::new_from_string(inp);
@@ -91,6 +97,18 @@ mod tests {
         ];
 
         assert_eq!(res, exp);
+
+        let res = exp_compile_str("1+7%3");
+        let exp = [
+            LDC(Int(1)),
+            LDC(Int(7)),
+            LDC(Int(3)),
+            BINOP(bytecode::BinOp::Mod),
+            BINOP(bytecode::BinOp::Add),
+            DONE,
+        ];
+
+        assert_eq!(res, exp);
     }
 
     #[test]
@@ -1028,6 +1046,59 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_compile_break_exits_scopes() {
+        // break skips the EXITSCOPE at the end of the body, so it has to exit the scopes itself
+        let t = r"
+        let x = 0;
+        while true {
+            let y = 1;
+            {
+                let z = 2;
+                break;
+            }
+        }
+        x
+        ";
+
+        test_comp(
+            t,
+            vec![
+                ENTERSCOPE(vec!["x".to_string()]),
+                LDC(Int(0)),
+                ByteCode::assign("x"),
+                LDC(Unit),
+                POP,
+                LDC(Bool(true)), // 5 - loop cond (start)
+                JOF(26),
+                ENTERSCOPE(vec!["y".to_string()]),
+                LDC(Int(1)),
+                ByteCode::assign("y"),
+                LDC(Unit),
+                POP,
+                ENTERSCOPE(vec!["z".to_string()]),
+                LDC(Int(2)),
+                ByteCode::assign("z"),
+                LDC(Unit),
+                POP,
+                EXITSCOPE,
+                EXITSCOPE,
+                GOTO(26),
+                POP,
+                EXITSCOPE, // end of inner blk
+                LDC(Unit),
+                EXITSCOPE, // end of loop body
+                POP,
+                GOTO(5),
+                LDC(Unit), // 26 - loop end
+                POP,
+                ByteCode::ld("x"),
+                EXITSCOPE,
+                DONE,
+            ],
+        );
+    }
+
     #[test]
     fn test_compile_fn_call() {
         let t = "print(2, 3)";
@@ -1149,19 +1220,53 @@ mod tests {
             vec![
                 ByteCode::ldc(2),
                 POP,
-                SPAWN(4),
-                GOTO(9),
+                ENTERSCOPE(vec!["$spawn0".to_string()]),
+                ByteCode::ldc(1),
+                ByteCode::assign("$spawn0"),
+                SPAWN(7),
+                GOTO(12),
                 POP,
                 LD("func".to_string()),
-                ByteCode::ldc(1),
+                ByteCode::ld("$spawn0"),
                 CALL(1),
                 DONE,
+                EXITSCOPE,
                 POP,
                 ByteCode::ldc(3),
                 POP,
                 DONE,
             ],
         );
+
+        // no args, no scope to hold them
+        test_comp(
+            "spawn func();",
+            vec![
+                SPAWN(2),
+                GOTO(6),
+                POP,
+                LD("func".to_string()),
+                CALL(0),
+                DONE,
+                POP,
+                DONE,
+            ],
+        );
+
+        // the same code, but the child gets a copy of the environment
+        test_comp(
+            "spawn isolate func();",
+            vec![
+                SPAWNISO(2),
+                GOTO(6),
+                POP,
+                LD("func".to_string()),
+                CALL(0),
+                DONE,
+                POP,
+                DONE,
+            ],
+        );
     }
 
     #[test]
@@ -1188,4 +1293,1135 @@ mod tests {
             ],
         );
     }
+
+    #[test]
+    fn test_compile_array() {
+        let t = r"
+        let xs = [1, 2];
+        xs[0] = [0; 2][1];
+        xs[1]
+        ";
+        test_comp(
+            t,
+            vec![
+                ENTERSCOPE(vec!["xs".to_string()]),
+                ByteCode::ldc(1),
+                ByteCode::ldc(2),
+                ARRAY(2),
+                ByteCode::assign("xs"),
+                LDC(Unit),
+                POP,
+                ByteCode::ld("xs"),
+                ByteCode::ldc(0),
+                ByteCode::ldc(0),
+                ARRAYFILL(2),
+                ByteCode::ldc(1),
+                LDIDX,
+                ASSIGNIDX,
+                LDC(Unit),
+                POP,
+                ByteCode::ld("xs"),
+                ByteCode::ldc(1),
+                LDIDX,
+                EXITSCOPE,
+                DONE,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_compile_slice() {
+        let t = r"
+        let xs = [1, 2, 3];
+        xs[1..=2];
+        xs[..]
+        ";
+        test_comp(
+            t,
+            vec![
+                ENTERSCOPE(vec!["xs".to_string()]),
+                ByteCode::ldc(1),
+                ByteCode::ldc(2),
+                ByteCode::ldc(3),
+                ARRAY(3),
+                ByteCode::assign("xs"),
+                LDC(Unit),
+                POP,
+                ByteCode::ld("xs"),
+                ByteCode::ldc(1),
+                ByteCode::ldc(2),
+                ByteCode::ldc(1),
+                BINOP(bytecode::BinOp::Add),
+                SLICE,
+                POP,
+                ByteCode::ld("xs"),
+                ByteCode::ldc(0),
+                LDC(Unit),
+                SLICE,
+                EXITSCOPE,
+                DONE,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_compile_struct() {
+        let t = r"
+        struct Point { x: int, y: float }
+        let p = Point { y: 2.0, x: 1 };
+        p.x = p.x + 1;
+        p.y
+        ";
+        test_comp(
+            t,
+            vec![
+                ENTERSCOPE(vec!["p".to_string()]),
+                LDC(Unit),
+                POP,
+                ByteCode::ldc(2.0),
+                ByteCode::ldc(1),
+                STRUCT("Point".to_string(), vec!["y".to_string(), "x".to_string()]),
+                ByteCode::assign("p"),
+                LDC(Unit),
+                POP,
+                ByteCode::ld("p"),
+                ByteCode::ld("p"),
+                LDFIELD("x".to_string()),
+                ByteCode::ldc(1),
+                BINOP(bytecode::BinOp::Add),
+                ASSIGNFIELD("x".to_string()),
+                LDC(Unit),
+                POP,
+                ByteCode::ld("p"),
+                LDFIELD("y".to_string()),
+                EXITSCOPE,
+                DONE,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_compile_match() {
+        // the matched value is evaluated once, each arm jumps to the next on false
+        let t = r"
+        let x = 2;
+        match x {
+            1 => 10,
+            -1 => 20,
+            _ => 30,
+        }
+        ";
+        test_comp(
+            t,
+            vec![
+                ENTERSCOPE(vec!["x".to_string()]),
+                ByteCode::ldc(2),
+                ByteCode::assign("x"),
+                LDC(Unit),
+                POP,
+                ENTERSCOPE(vec!["$match".to_string()]),
+                ByteCode::ld("x"),
+                ByteCode::assign("$match"),
+                ByteCode::ld("$match"),
+                ByteCode::ldc(1),
+                BINOP(bytecode::BinOp::Eq),
+                JOF(14),
+                ByteCode::ldc(10),
+                GOTO(21),
+                ByteCode::ld("$match"),
+                ByteCode::ldc(-1),
+                BINOP(bytecode::BinOp::Eq),
+                JOF(20),
+                ByteCode::ldc(20),
+                GOTO(21),
+                ByteCode::ldc(30),
+                EXITSCOPE,
+                EXITSCOPE,
+                DONE,
+            ],
+        );
+
+        // no '_': falling off the last arm produces Unit
+        test_comp(
+            "match true { true => 1, false => 2 }",
+            vec![
+                ENTERSCOPE(vec!["$match".to_string()]),
+                ByteCode::ldc(true),
+                ByteCode::assign("$match"),
+                ByteCode::ld("$match"),
+                ByteCode::ldc(true),
+                BINOP(bytecode::BinOp::Eq),
+                JOF(9),
+                ByteCode::ldc(1),
+                GOTO(16),
+                ByteCode::ld("$match"),
+                ByteCode::ldc(false),
+                BINOP(bytecode::BinOp::Eq),
+                JOF(15),
+                ByteCode::ldc(2),
+                GOTO(16),
+                LDC(Unit),
+                EXITSCOPE,
+                DONE,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_compile_match_variant() {
+        // the names a pattern binds get a scope around the body of the arm, '_' binds nothing
+        let t = r"
+        match Some(1) {
+            Some(x) => x,
+            None => 0,
+        }
+        ";
+        test_comp(
+            t,
+            vec![
+                ENTERSCOPE(vec!["$match".to_string()]),
+                ByteCode::ldc(1),
+                VARIANT("Option".to_string(), "Some".to_string(), 1),
+                ByteCode::assign("$match"),
+                ByteCode::ld("$match"),
+                ISVARIANT("Some".to_string()),
+                JOF(14),
+                ENTERSCOPE(vec!["x".to_string()]),
+                ByteCode::ld("$match"),
+                LDVARIANT(0),
+                ByteCode::assign("x"),
+                ByteCode::ld("x"),
+                EXITSCOPE,
+                GOTO(20),
+                ByteCode::ld("$match"),
+                ISVARIANT("None".to_string()),
+                JOF(19),
+                ByteCode::ldc(0),
+                GOTO(20),
+                LDC(Unit),
+                EXITSCOPE,
+                DONE,
+            ],
+        );
+
+        test_comp(
+            "match Err(None) { Err(_) => 1, _ => 2 }",
+            vec![
+                ENTERSCOPE(vec!["$match".to_string()]),
+                VARIANT("Option".to_string(), "None".to_string(), 0),
+                VARIANT("Result".to_string(), "Err".to_string(), 1),
+                ByteCode::assign("$match"),
+                ByteCode::ld("$match"),
+                ISVARIANT("Err".to_string()),
+                JOF(9),
+                ByteCode::ldc(1),
+                GOTO(10),
+                ByteCode::ldc(2),
+                EXITSCOPE,
+                DONE,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_compile_coalesce() {
+        // the option is evaluated once, default only if it is None
+        test_comp(
+            "None ?? 2",
+            vec![
+                ENTERSCOPE(vec!["$coalesce".to_string()]),
+                VARIANT("Option".to_string(), "None".to_string(), 0),
+                ByteCode::assign("$coalesce"),
+                ByteCode::ld("$coalesce"),
+                ISVARIANT("Some".to_string()),
+                JOF(9),
+                ByteCode::ld("$coalesce"),
+                LDVARIANT(0),
+                GOTO(10),
+                ByteCode::ldc(2),
+                EXITSCOPE,
+                DONE,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_compile_match_or_range() {
+        // each pattern leaves a bool, the alternatives of an or-pattern are or-ed together
+        test_comp(
+            "match 3 { 1 | 2 => 1, 0..=9 => 2, _ => 3 }",
+            vec![
+                ENTERSCOPE(vec!["$match".to_string()]),
+                ByteCode::ldc(3),
+                ByteCode::assign("$match"),
+                ByteCode::ld("$match"),
+                ByteCode::ldc(1),
+                BINOP(bytecode::BinOp::Eq),
+                ByteCode::ld("$match"),
+                ByteCode::ldc(2),
+                BINOP(bytecode::BinOp::Eq),
+                BINOP(bytecode::BinOp::Or),
+                JOF(13),
+                ByteCode::ldc(1),
+                GOTO(26),
+                ByteCode::ld("$match"),
+                ByteCode::ldc(0),
+                BINOP(bytecode::BinOp::Lt),
+                UNOP(bytecode::UnOp::Not),
+                ByteCode::ld("$match"),
+                ByteCode::ldc(9),
+                BINOP(bytecode::BinOp::Gt),
+                UNOP(bytecode::UnOp::Not),
+                BINOP(bytecode::BinOp::And),
+                JOF(25),
+                ByteCode::ldc(2),
+                GOTO(26),
+                ByteCode::ldc(3),
+                EXITSCOPE,
+                DONE,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_compile_match_unreachable_arm() {
+        // an arm the arms before it cover is left out, so it compiles the same as without it
+        let cases = [
+            (
+                "match true { true => 1, false => 2, _ => 3 }",
+                "match true { true => 1, false => 2 }",
+                "_",
+            ),
+            (
+                "match 5 { 0..=9 => 1, 3 => 2, _ => 3 }",
+                "match 5 { 0..=9 => 1, _ => 3 }",
+                "3",
+            ),
+            (
+                "match 5 { 0..5 | 5..10 => 1, 2..=7 | 9 => 2, _ => 3 }",
+                "match 5 { 0..5 | 5..10 => 1, _ => 3 }",
+                "2..=7 | 9",
+            ),
+            (
+                "match None { Some(_) | None => 1, None => 2 }",
+                "match None { Some(_) | None => 1 }",
+                "None",
+            ),
+            (
+                "enum E { A, B } match A { A => 1, B => 2, _ => 3 }",
+                "enum E { A, B } match A { A => 1, B => 2 }",
+                "_",
+            ),
+        ];
+        for (dead, live, arm) in cases {
+            let (bytecode, warnings) = compile_warnings(dead);
+            assert_eq!(bytecode, compile_warnings(live).0, "{}", dead);
+            assert_eq!(
+                warnings,
+                vec![CompileWarning::new(&format!(
+                    "Unreachable match arm '{}', the arms before it match every value it does at line 1",
+                    arm
+                ))]
+            );
+        }
+
+        // a guarded arm may not be taken, so it doesn't cover the arms after it
+        let live = [
+            "match true { true => 1, false if 1 > 2 => 2, _ => 3 }",
+            "match 5 { 0..=9 if false => 1, 3 => 2, _ => 3 }",
+            "match 5 { 0..=9 => 1, 10 => 2, _ => 3 }",
+        ];
+        for inp in live {
+            assert!(compile_warnings(inp).1.is_empty(), "{}", inp);
+        }
+    }
+
+    #[test]
+    fn test_compile_match_guard() {
+        // a false guard leaves the scope of the binds and tries the next arm
+        let t = r"
+        match Some(1) {
+            Some(x) if x > 0 => x,
+            _ if false => 1,
+            _ => 0,
+        }
+        ";
+        test_comp(
+            t,
+            vec![
+                ENTERSCOPE(vec!["$match".to_string()]),
+                ByteCode::ldc(1),
+                VARIANT("Option".to_string(), "Some".to_string(), 1),
+                ByteCode::assign("$match"),
+                ByteCode::ld("$match"),
+                ISVARIANT("Some".to_string()),
+                JOF(19),
+                ENTERSCOPE(vec!["x".to_string()]),
+                ByteCode::ld("$match"),
+                LDVARIANT(0),
+                ByteCode::assign("x"),
+                ByteCode::ld("x"),
+                ByteCode::ldc(0),
+                BINOP(bytecode::BinOp::Gt),
+                JOF(18),
+                ByteCode::ld("x"),
+                EXITSCOPE,
+                GOTO(24),
+                EXITSCOPE,
+                ByteCode::ldc(false),
+                JOF(23),
+                ByteCode::ldc(1),
+                GOTO(24),
+                ByteCode::ldc(0),
+                EXITSCOPE,
+                DONE,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_compile_enum() {
+        // the declaration leaves nothing behind, variants are made in place like Some and None
+        let t = r"
+        enum Shape { Circle(float), Rect(float, float), Empty }
+        let s = Rect(1.0, 2.0);
+        s == Empty
+        ";
+        test_comp(
+            t,
+            vec![
+                ENTERSCOPE(vec!["s".to_string()]),
+                LDC(Unit),
+                POP,
+                ByteCode::ldc(1.0),
+                ByteCode::ldc(2.0),
+                VARIANT("Shape".to_string(), "Rect".to_string(), 2),
+                ByteCode::assign("s"),
+                LDC(Unit),
+                POP,
+                ByteCode::ld("s"),
+                VARIANT("Shape".to_string(), "Empty".to_string(), 0),
+                BINOP(bytecode::BinOp::Eq),
+                EXITSCOPE,
+                DONE,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_compile_tuple() {
+        // destructuring unpacks with the last value on top, so the names are assigned from the last one
+        let t = r"
+        let (a, _, c) = (1, true, 2.0);
+        (a, c).1
+        ";
+        test_comp(
+            t,
+            vec![
+                ENTERSCOPE(vec!["a".to_string(), "c".to_string()]),
+                ByteCode::ldc(1),
+                ByteCode::ldc(true),
+                ByteCode::ldc(2.0),
+                TUPLE(3),
+                UNPACK(3),
+                ByteCode::assign("c"),
+                POP,
+                ByteCode::assign("a"),
+                LDC(Unit),
+                POP,
+                ByteCode::ld("a"),
+                ByteCode::ld("c"),
+                TUPLE(2),
+                LDTUPLE(1),
+                EXITSCOPE,
+                DONE,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_compile_for() {
+        // break jumps to the EXITSCOPE for the loop var scope
+        let t = r"
+        for i in 0..=2 {
+            if i == 1 {
+                break;
+            }
+        }
+        ";
+        test_comp(
+            t,
+            vec![
+                ByteCode::enterscope(vec!["i", "$idx", "$end"]),
+                ByteCode::ldc(0),
+                ByteCode::assign("$idx"),
+                ByteCode::ldc(2),
+                ByteCode::ldc(1),
+                BINOP(bytecode::BinOp::Add),
+                ByteCode::assign("$end"),
+                ByteCode::ld("$idx"), // 7 - loop start
+                ByteCode::ld("$end"),
+                BINOP(bytecode::BinOp::Lt),
+                JOF(30),
+                ByteCode::ld("$idx"),
+                ByteCode::assign("i"),
+                ByteCode::ld("i"),
+                ByteCode::ldc(1),
+                BINOP(bytecode::BinOp::Eq),
+                JOF(21),
+                GOTO(30),
+                POP,
+                LDC(Unit),
+                GOTO(22),
+                LDC(Unit),
+                POP,
+                LDC(Unit),
+                POP,
+                ByteCode::ld("$idx"),
+                ByteCode::ldc(1),
+                BINOP(bytecode::BinOp::Add),
+                ByteCode::assign("$idx"),
+                GOTO(7),
+                EXITSCOPE, // 30 - loop end
+                LDC(Unit),
+                POP,
+                DONE,
+            ],
+        );
+
+        let t = "for x in [1] { }";
+        test_comp(
+            t,
+            vec![
+                ByteCode::enterscope(vec!["x", "$idx", "$end", "$arr"]),
+                ByteCode::ldc(1),
+                ARRAY(1),
+                ByteCode::assign("$arr"),
+                ByteCode::ldc(0),
+                ByteCode::assign("$idx"),
+                ByteCode::ld("$arr"),
+                LEN,
+                ByteCode::assign("$end"),
+                ByteCode::ld("$idx"), // 9 - loop start
+                ByteCode::ld("$end"),
+                BINOP(bytecode::BinOp::Lt),
+                JOF(24),
+                ByteCode::ld("$arr"),
+                ByteCode::ld("$idx"),
+                LDIDX,
+                ByteCode::assign("x"),
+                LDC(Unit),
+                POP,
+                ByteCode::ld("$idx"),
+                ByteCode::ldc(1),
+                BINOP(bytecode::BinOp::Add),
+                ByteCode::assign("$idx"),
+                GOTO(9),
+                EXITSCOPE, // 24 - loop end
+                LDC(Unit),
+                POP,
+                DONE,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_compile_channels() {
+        // send and recv have their own instructions instead of a CALL
+        let t = "let c : chan[int] = chan(); send(c, 1); recv(c)";
+        test_comp(
+            t,
+            vec![
+                ENTERSCOPE(vec!["c".to_string()]),
+                ByteCode::ld("chan"),
+                CALL(0),
+                ByteCode::assign("c"),
+                LDC(Unit),
+                POP,
+                ByteCode::ld("c"),
+                ByteCode::ldc(1),
+                SEND,
+                LDC(Unit),
+                POP,
+                ByteCode::ld("c"),
+                RECV,
+                EXITSCOPE,
+                DONE,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_desugared() {
+        let t = r"
+        macro double($a) { $a * 2 }
+        #[cfg(debug)]
+        let y = 1;
+        let x = double!(3);
+        if cfg(debug) { x } else { 0 }
+        ";
+        let program = desugar_with_defines(t, &HashSet::new()).expect("Should desugar");
+        assert_eq!(
+            fmt_desugared(&program),
+            "let x = { (3*2) };\nif false { x } else { 0 }"
+        );
+
+        let defines = HashSet::from(["debug".to_string()]);
+        let program = desugar_with_defines(t, &defines).expect("Should desugar");
+        assert_eq!(
+            fmt_desugared(&program),
+            "let y = 1;\nlet x = { (3*2) };\nif true { x } else { 0 }"
+        );
+    }
+
+    #[test]
+    fn test_desugared_reparses() {
+        // the output is source: reading it back gives the same program
+        let t = r#"
+        macro swap($a, $b) { let t = $a; $a = $b; $b = t; }
+        macro apply($f, $x) { let g = |t: int| $f(t) + t; match Some(g($x)) { Some(v) => v, None => 0 } }
+        struct Point { x: int, y: float }
+        enum Shape { Circle(float), Dot }
+        fn inc(n: int) -> int { n + 1 }
+        let x = 1;
+        let t = 2;
+        let t_1 = 3;
+        swap!(x, t);
+        let s = "say \"hi\"\n\t{x} \\ \u{1b} 'q'";
+        let c = '\'';
+        let p = Point { x: 1, y: 2.0 };
+        let f = 1.5 + p.y;
+        let m = #{"k": [1, 2], "v": [3; 2]};
+        let (a, _) = (apply!(inc, x), 'z');
+        let sh = Circle(1.0);
+        for i in 0..=3 { println(format("{} {}", i, s)); }
+        match sh { Circle(r) => r, Dot => 0.0 };
+        a + t + t_1
+        "#;
+
+        let program = desugar_with_defines(t, &HashSet::new()).expect("Should desugar");
+        let emitted = fmt_desugared(&program);
+        // t_1 is taken by the program, so the swap's t gets another name
+        assert!(emitted.contains("{ let t_1_1 = x;x = t;t = t_1_1; };"));
+        let reparsed = desugar_with_defines(&emitted, &HashSet::new())
+            .unwrap_or_else(|e| panic!("Should reparse {}: {}", emitted, e));
+        assert_eq!(fmt_desugared(&reparsed), emitted);
+        types::type_checker::TypeChecker::new(&reparsed)
+            .type_check()
+            .expect("Should type check");
+    }
+
+    #[test]
+    fn test_compile_reproducible() {
+        // ENTERSCOPE lists names in the order they are declared
+        let t = "let z = 1; fn b() {} let a = 2; let z = 3; a";
+        let res = exp_compile_str(t);
+        assert_eq!(
+            res.first(),
+            Some(&ENTERSCOPE(vec![
+                "z".to_string(),
+                "b".to_string(),
+                "a".to_string()
+            ]))
+        );
+
+        // the same input serializes to the same bytes every time
+        let t = r"
+        macro swap($a, $b) { let t = $a; $a = $b; $b = t; }
+        struct P { x: int, y: int }
+        let q = 1;
+        let p = P { y: 2, x: 1 };
+        fn f(c: int, b: int, a: int) -> int {
+            let z = a; let y = b; let x = c;
+            swap!(x, z);
+            x + y + z
+        }
+        for i in 0..3 {
+            let w = i;
+        }
+        match q { 1 => f(p.x, p.y, 3), _ => 0 }
+        ";
+        let serialize = || {
+            let bytecode = crate::compiler::compile_from_string(t, true).expect("Should compile");
+            let mut bytes: Vec<u8> = vec![];
+            bytecode::write_bytecode(&bytecode, &mut bytes).expect("Should serialize");
+            bytes
+        };
+        let first = serialize();
+        for _ in 0..10 {
+            assert_eq!(serialize(), first);
+        }
+    }
+
+    #[test]
+    fn test_compile_lock() {
+        // the value of the body is left under the release
+        let t = "let m = mutex(); lock m { 1 }";
+        test_comp(
+            t,
+            vec![
+                ENTERSCOPE(vec!["m".to_string()]),
+                ByteCode::ld("mutex"),
+                CALL(0),
+                ByteCode::assign("m"),
+                LDC(Unit),
+                POP,
+                ENTERSCOPE(vec!["$lock0".to_string()]),
+                ByteCode::ld("m"),
+                ByteCode::assign("$lock0"),
+                ByteCode::ld("$lock0"),
+                WAIT,
+                ByteCode::ldc(1),
+                ByteCode::ld("$lock0"),
+                POST,
+                EXITSCOPE,
+                EXITSCOPE,
+                DONE,
+            ],
+        );
+
+        // break releases the locks taken inside the loop, innermost first, before exiting their scopes
+        let t = "lock a { loop { lock b { break; } } }";
+        test_comp(
+            t,
+            vec![
+                ENTERSCOPE(vec!["$lock0".to_string()]),
+                ByteCode::ld("a"),
+                ByteCode::assign("$lock0"),
+                ByteCode::ld("$lock0"),
+                WAIT,
+                ENTERSCOPE(vec!["$lock1".to_string()]),
+                ByteCode::ld("b"),
+                ByteCode::assign("$lock1"),
+                ByteCode::ld("$lock1"),
+                WAIT,
+                ByteCode::ld("$lock1"),
+                POST,
+                EXITSCOPE,
+                GOTO(21),
+                POP,
+                LDC(Unit),
+                ByteCode::ld("$lock1"),
+                POST,
+                EXITSCOPE,
+                POP,
+                GOTO(5),
+                LDC(Unit),
+                POP,
+                LDC(Unit),
+                ByteCode::ld("$lock0"),
+                POST,
+                EXITSCOPE,
+                DONE,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_compile_lambda() {
+        // the closure is left on the stack for the let instead of being assigned by name
+        let t = "let f = |x: int| x + 1; f(2)";
+        test_comp(
+            t,
+            vec![
+                ENTERSCOPE(vec!["f".to_string()]),
+                LDF(3, vec!["x".to_string()]),
+                GOTO(7),
+                ByteCode::ld("x"),
+                ByteCode::ldc(1),
+                BINOP(bytecode::BinOp::Add),
+                RESET(bytecode::FrameType::CallFrame),
+                ByteCode::assign("f"),
+                LDC(Unit),
+                POP,
+                ByteCode::ld("f"),
+                ByteCode::ldc(2),
+                CALL(1),
+                EXITSCOPE,
+                DONE,
+            ],
+        );
+
+        // as an argument
+        let t = "apply(|| 2)";
+        test_comp(
+            t,
+            vec![
+                ByteCode::ld("apply"),
+                LDF(3, vec![]),
+                GOTO(5),
+                ByteCode::ldc(2),
+                RESET(bytecode::FrameType::CallFrame),
+                CALL(1),
+                DONE,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_compile_eval_order() {
+        // operands and args are emitted left to right, each call complete before the next starts
+        let t = "f(g(1), h(2)) * k()";
+        test_comp(
+            t,
+            vec![
+                ByteCode::ld("f"),
+                ByteCode::ld("g"),
+                ByteCode::ldc(1),
+                CALL(1),
+                ByteCode::ld("h"),
+                ByteCode::ldc(2),
+                CALL(1),
+                CALL(2),
+                ByteCode::ld("k"),
+                CALL(0),
+                BINOP(bytecode::BinOp::Mul),
+                DONE,
+            ],
+        );
+
+        // the array, then the index, then the value
+        let t = "xs[i()] = [a(), b()];";
+        test_comp(
+            t,
+            vec![
+                ByteCode::ld("xs"),
+                ByteCode::ld("i"),
+                CALL(0),
+                ByteCode::ld("a"),
+                CALL(0),
+                ByteCode::ld("b"),
+                CALL(0),
+                ARRAY(2),
+                ASSIGNIDX,
+                LDC(Unit),
+                POP,
+                DONE,
+            ],
+        );
+
+        // struct fields in the order written, not the order declared
+        let t = "struct P { x: int, y: int } P { y: a(), x: b() }";
+        test_comp(
+            t,
+            vec![
+                LDC(Unit),
+                POP,
+                ByteCode::ld("a"),
+                CALL(0),
+                ByteCode::ld("b"),
+                CALL(0),
+                STRUCT("P".to_string(), vec!["y".to_string(), "x".to_string()]),
+                DONE,
+            ],
+        );
+    }
+
+    fn compile_warnings(inp: &str) -> (Vec<ByteCode>, Vec<CompileWarning>) {
+        let parsed = Parser::new_from_string(inp).parse().expect("Should parse");
+        Compiler::new(parsed)
+            .compile_with_warnings()
+            .expect("Should compile")
+    }
+
+    #[test]
+    fn test_compile_unreachable() {
+        // dead code is left out, so it compiles the same as without it
+        let cases = [
+            (
+                "fn f() { return 1; g(); h(); }",
+                "fn f() { return 1; }",
+                "return at line 1, column 20",
+            ),
+            (
+                "loop { break; g(); }",
+                "loop { break; }",
+                "break at line 1, column 15",
+            ),
+            (
+                "loop { yield; }\ng();",
+                "loop { yield; }",
+                "infinite loop at line 2, column 1",
+            ),
+            (
+                "fn f(x: int) { if x > 2 { return 1; } else { return 2; } g(); }",
+                "fn f(x: int) { if x > 2 { return 1; } else { return 2; }; }",
+                "return at line 1, column 58",
+            ),
+            (
+                "loop { { break; } g(); }",
+                "loop { { break; }; }",
+                "break at line 1, column 19",
+            ),
+            (
+                "loop { loop { break; } } g();",
+                "loop { loop { break; } }",
+                "infinite loop at line 1, column 26",
+            ),
+        ];
+
+        for (dead, live, warning) in cases {
+            let (bytecode, warnings) = compile_warnings(dead);
+            assert_eq!(bytecode, compile_warnings(live).0, "{}", dead);
+            let exp = format!("Unreachable code after {}", warning);
+            assert_eq!(warnings, vec![CompileWarning::new(&exp)]);
+        }
+
+        let (_, warnings) = compile_warnings("fn f() { return; 2 }");
+        assert_eq!(
+            warnings[0].to_string(),
+            "[Warning] - Unreachable code after return at line 1, column 18"
+        );
+
+        // each of these can carry on
+        let live = [
+            "fn f(x: int) { if x > 2 { return 1; } g(); }",
+            "loop { if x { break; } } g();",
+            "fn f() { loop { if x { return; } else { break; } } g(); }",
+            "while x { } g();",
+            "fn f() { return 1; }",
+            "loop { match x { _ if { break; } => 1, _ => 2 }; } g();",
+        ];
+        for inp in live {
+            assert!(compile_warnings(inp).1.is_empty(), "{}", inp);
+        }
+    }
+
+    #[test]
+    fn test_compile_strict() {
+        let strict = |inp: &str| {
+            let parsed = Parser::new_from_string(inp).parse().expect("Should parse");
+            Compiler::new(parsed).compile_strict()
+        };
+
+        let shadowed = [
+            (
+                "let x = 1; let x = 2;",
+                "'x' shadows a name declared before it at line 1, column 12",
+            ),
+            (
+                "let x = 1; { let x = 2; }",
+                "'x' shadows a name declared before it at line 1, column 14",
+            ),
+            (
+                "fn f(x: int) { let x = 2; }",
+                "'x' shadows a name declared before it at line 1, column 16",
+            ),
+            (
+                "let f = |x: int| { let x = 2; x };",
+                "'x' shadows a name declared before it at line 1, column 20",
+            ),
+            (
+                "fn f() { return; 2 }",
+                "Unreachable code after return at line 1, column 18",
+            ),
+        ];
+        for (inp, warning) in shadowed {
+            let err = strict(inp).expect_err("Should be denied");
+            assert_eq!(err.code(), Code::C004, "{}", inp);
+            assert_eq!(
+                err.to_string(),
+                format!(
+                    "[CompileError C004] -  {}, and --strict makes warnings errors",
+                    warning
+                )
+            );
+        }
+
+        // names in sibling scopes, and lets after a block that declared them, don't shadow anything in scope
+        let ok = [
+            "{ let x = 1; } { let x = 2; }",
+            "{ let x = 1; } let x = 2;",
+            "fn f(x: int) { x } fn g(x: int) { x }",
+            "let x = 1; x = 2;",
+        ];
+        for inp in ok {
+            assert!(strict(inp).is_ok(), "{}", inp);
+        }
+
+        // only strict mode warns about shadowing
+        assert!(compile_warnings("let x = 1; let x = 2;").1.is_empty());
+    }
+
+    #[test]
+    fn test_compile_deprecated() {
+        let t = r#"
+        #[deprecated("use g")]
+        fn f() -> int { 1 }
+        #[deprecated]
+        let y = 2;
+        fn g() -> int { 3 }
+        f() + y
+        "#;
+        assert_eq!(
+            compile_warnings(t).1,
+            vec![
+                CompileWarning::new("'f' is deprecated: use g at line 7"),
+                CompileWarning::new("'y' is deprecated at line 7"),
+            ]
+        );
+
+        // a param, loop var or let closer in with the same name hides the deprecated one
+        let hidden = [
+            "#[deprecated] let x = 1; fn f(x: int) -> int { x }",
+            "#[deprecated] let x = 1; let f = |x: int| x;",
+            "#[deprecated] let x = 1; for x in 0..2 { x; }",
+            "#[deprecated] let x = 1; { let x = 2; x }",
+            "#[deprecated] let x = 1; match Some(2) { Some(x) => x, None => 0 }",
+        ];
+        for inp in hidden {
+            assert!(compile_warnings(inp).1.is_empty(), "{}", inp);
+        }
+    }
+
+    #[test]
+    fn test_compile_tests() {
+        let t = r#"
+        fn double(x: int) -> int { x + x }
+        #[test]
+        fn test_a() { assert(double(2) == 4, "a"); }
+        #[test]
+        fn test_b() { assert(double(3) == 6, "b"); }
+        fn helper() {}
+        double(1)
+        "#;
+        let (tests, warnings) = compile_tests(t, &HashSet::new()).expect("Should compile");
+        assert!(warnings.is_empty());
+
+        let names: Vec<&str> = tests.iter().map(|test| test.name.as_str()).collect();
+        assert_eq!(names, vec!["test_a", "test_b"]);
+
+        // the last expr is left out and the test is called after the rest of the program
+        let (bytecode, _) = compile_warnings(&t.replace("double(1)", "test_b();"));
+        assert_eq!(tests[1].bytecode, bytecode);
+
+        // the program is type checked before any test is compiled
+        assert!(compile_tests("#[test] fn t() { assert(1, \"x\"); }", &HashSet::new()).is_err());
+    }
+
+    fn test_comp_optimized(inp: &str, exp: Vec<ByteCode>) {
+        let parsed = Parser::new_from_string(inp).parse().expect("Should parse");
+        let res = Compiler::new(parsed)
+            .compile_optimized()
+            .expect("Should compile");
+        assert_eq!(res, exp, "{}", inp);
+    }
+
+    #[test]
+    fn test_compile_optimized() {
+        test_comp_optimized("2+3*4", vec![ByteCode::ldc(14), DONE]);
+        test_comp_optimized("-(2.5 * 2.0) < 1.0", vec![ByteCode::ldc(true), DONE]);
+        test_comp_optimized(r#""a" + "b""#, vec![ByteCode::ldc("ab"), DONE]);
+        test_comp_optimized("!(true && false)", vec![ByteCode::ldc(true), DONE]);
+
+        // only the constant part folds
+        test_comp_optimized(
+            "x + 2 * 3",
+            vec![
+                ByteCode::ld("x"),
+                ByteCode::ldc(6),
+                BINOP(bytecode::BinOp::Add),
+                DONE,
+            ],
+        );
+
+        // left for the VM to fail on
+        test_comp_optimized(
+            "1 / 0",
+            vec![
+                ByteCode::ldc(1),
+                ByteCode::ldc(0),
+                BINOP(bytecode::BinOp::Div),
+                DONE,
+            ],
+        );
+        test_comp_optimized(
+            "9223372036854775807 + 1",
+            vec![
+                ByteCode::ldc(i64::MAX),
+                ByteCode::ldc(1),
+                BINOP(bytecode::BinOp::Add),
+                DONE,
+            ],
+        );
+
+        // the Unit for each statement isn't pushed just to be popped
+        test_comp_optimized(
+            "let x = 1; x = 2;",
+            vec![
+                ENTERSCOPE(vec!["x".to_string()]),
+                ByteCode::ldc(1),
+                ByteCode::assign("x"),
+                ByteCode::ldc(2),
+                ByteCode::assign("x"),
+                EXITSCOPE,
+                DONE,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_compile_line() {
+        let line = |inp: &str, prev: Vec<ByteCode>| {
+            let program = desugar_with_defines(inp, &HashSet::new()).expect("Should parse");
+            Compiler::new(program)
+                .compile_line(prev)
+                .expect("Should compile")
+        };
+
+        // the scope stays open for the next line
+        let code = line("let x = 2;", vec![]);
+        assert_eq!(
+            code,
+            vec![
+                ENTERSCOPE(vec!["x".to_string()]),
+                ByteCode::ldc(2),
+                ByteCode::assign("x"),
+                LDC(Unit),
+                POP,
+                DONE,
+            ]
+        );
+
+        // jumps are to where the line is in the whole
+        let code = line("if x > 1 { 3 } else { 4 }", code);
+        assert_eq!(
+            code[6..],
+            vec![
+                ByteCode::ld("x"),
+                ByteCode::ldc(1),
+                BINOP(bytecode::BinOp::Gt),
+                JOF(12),
+                ByteCode::ldc(3),
+                GOTO(13),
+                ByteCode::ldc(4),
+                DONE,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compile_lines() {
+        let inp =
+            "let x = 2;\nfn f(n: int) -> int {\n    let y = n + 1;\n    y * 2\n}\nlet z = f(x);\nz";
+        let program = desugar_with_defines(inp, &HashSet::new()).expect("Should parse");
+        let (code, _, lines) = Compiler::new(program)
+            .compile_with_lines()
+            .expect("Should compile");
+
+        let lines: Vec<_> = (0..code.len()).map(|pc| lines.line(pc)).collect();
+        // the body of f is compiled in the middle of its decl, and the decl goes on after it
+        let exp: Vec<Option<usize>> = [
+            vec![None],
+            vec![Some(1); 4],
+            vec![Some(2); 3],
+            vec![Some(3); 6],
+            vec![Some(4); 3],
+            vec![Some(2); 5],
+            vec![Some(6); 6],
+            vec![Some(7); 3],
+        ]
+        .concat();
+        assert_eq!(lines, exp);
+    }
 }
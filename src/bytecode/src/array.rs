@@ -0,0 +1,124 @@
+use std::{cell::RefCell, fmt::Debug, rc::Rc};
+
+use crate::{Value, W};
+
+/// Arrays have reference semantics: cloning the value shares the backing storage,
+/// so an update through one handle is seen through every other.
+pub type Array = W<Rc<RefCell<Vec<Value>>>>;
+
+impl Array {
+    pub fn new(vals: Vec<Value>) -> Self {
+        Self(Rc::new(RefCell::new(vals)))
+    }
+
+    /// Copy the backing storage, including that of nested arrays and structs, so the result shares nothing with self.
+    pub fn deep_clone(&self) -> Self {
+        let vals = self
+            .borrow()
+            .iter()
+            .map(|val| match val {
+                Value::Array(arr) => Value::Array(arr.deep_clone()),
+                Value::Struct(s) => Value::Struct(s.deep_clone()),
+                Value::Variant(v) => Value::Variant(v.deep_clone()),
+                Value::Tuple(t) => Value::Tuple(t.deep_clone()),
+                Value::Map(m) => Value::Map(m.deep_clone()),
+                _ => val.clone(),
+            })
+            .collect();
+
+        Self::new(vals)
+    }
+
+    pub fn len(&self) -> usize {
+        self.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.borrow().is_empty()
+    }
+}
+
+/// Arrays are equal if their elements are, whether or not they share storage.
+impl PartialEq for Array {
+    fn eq(&self, other: &Self) -> bool {
+        *self.borrow() == *other.borrow()
+    }
+}
+
+impl Clone for Array {
+    fn clone(&self) -> Self {
+        Self(Rc::clone(&self.0))
+    }
+}
+
+impl Debug for Array {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.borrow())
+    }
+}
+
+/// A view of `len` elements of an array starting at `offset`. Slicing never copies, so writes through a slice
+/// are seen through the array and every other slice of it.
+#[derive(Clone, PartialEq)]
+pub struct Slice {
+    pub arr: Array,
+    pub offset: usize,
+    pub len: usize,
+}
+
+impl Slice {
+    /// Create a view of `arr[offset..offset + len]`. The caller is responsible for the bounds.
+    pub fn new(arr: Array, offset: usize, len: usize) -> Self {
+        Slice { arr, offset, len }
+    }
+
+    /// Copy out the elements in view.
+    pub fn to_vec(&self) -> Vec<Value> {
+        self.arr.borrow()[self.offset..self.offset + self.len].to_vec()
+    }
+}
+
+impl Debug for Slice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_array_shares_storage() {
+        let arr = Array::new(vec![Value::Int(1), Value::Int(2)]);
+        let alias = arr.clone();
+        alias.borrow_mut()[0] = Value::Int(42);
+
+        assert_eq!(arr.borrow()[0], Value::Int(42));
+        assert_eq!(arr, alias);
+    }
+
+    #[test]
+    fn test_array_deep_clone() {
+        let inner = Array::new(vec![Value::Int(1)]);
+        let outer = Array::new(vec![Value::Array(inner.clone())]);
+        let copy = outer.deep_clone();
+        inner.borrow_mut()[0] = Value::Int(2);
+
+        assert_eq!(
+            copy.borrow()[0],
+            Value::Array(Array::new(vec![Value::Int(1)]))
+        );
+        assert_ne!(copy, outer);
+    }
+
+    #[test]
+    fn test_slice_is_a_view() {
+        let arr = Array::new(vec![Value::Int(1), Value::Int(2), Value::Int(3)]);
+        let slice = Slice::new(arr.clone(), 1, 2);
+        assert_eq!(slice.to_vec(), vec![Value::Int(2), Value::Int(3)]);
+
+        arr.borrow_mut()[2] = Value::Int(42);
+        assert_eq!(slice.to_vec(), vec![Value::Int(2), Value::Int(42)]);
+    }
+}
@@ -0,0 +1,54 @@
+use std::rc::Weak;
+
+use anyhow::Result;
+
+use crate::{type_of, Array, ByteCodeError, FnType, Slice, Value, W};
+
+use super::map::elems_of;
+
+pub const FILTER_SYM: &str = "filter";
+
+pub fn filter() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: FILTER_SYM.into(),
+        prms: vec!["xs".into(), "f".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// Keep the elements of an array or slice that `f` returns true for, in order. The result is a slice over a
+/// new array, since its length is only known once every element has been tested.
+///
+/// `call` runs `f` as for [`super::map_impl`].
+pub fn filter_impl<S>(
+    state: S,
+    xs: &Value,
+    f: &Value,
+    mut call: impl FnMut(S, &Value, Vec<Value>) -> Result<(S, Value)>,
+) -> Result<(S, Value)> {
+    let mut state = state;
+    let mut vals = vec![];
+
+    for x in elems_of(xs)? {
+        let (next, keep) = call(state, f, vec![x.clone()])?;
+        state = next;
+
+        match keep {
+            Value::Bool(true) => vals.push(x),
+            Value::Bool(false) => (),
+            _ => {
+                return Err(ByteCodeError::TypeMismatch {
+                    expected: "Bool".to_string(),
+                    found: type_of(&keep).to_string(),
+                }
+                .into())
+            }
+        }
+    }
+
+    let arr = Array::new(vals);
+    let len = arr.len();
+    Ok((state, Value::Slice(Slice::new(arr, 0, len))))
+}
@@ -0,0 +1,42 @@
+use std::rc::Weak;
+
+use anyhow::Result;
+
+use crate::{FnType, Value, W};
+
+use super::map::elems_of;
+
+pub const FOLD_SYM: &str = "fold";
+
+pub fn fold() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: FOLD_SYM.into(),
+        prms: vec!["xs".into(), "init".into(), "f".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// Combine the elements of an array or slice from left to right, starting from `init`: each step calls
+/// `f(acc, x)` and the result is the new `acc`.
+///
+/// `call` runs `f` as for [`super::map_impl`].
+pub fn fold_impl<S>(
+    state: S,
+    xs: &Value,
+    init: Value,
+    f: &Value,
+    mut call: impl FnMut(S, &Value, Vec<Value>) -> Result<(S, Value)>,
+) -> Result<(S, Value)> {
+    let mut state = state;
+    let mut acc = init;
+
+    for x in elems_of(xs)? {
+        let (next, val) = call(state, f, vec![acc, x])?;
+        state = next;
+        acc = val;
+    }
+
+    Ok((state, acc))
+}
@@ -0,0 +1,59 @@
+use std::rc::Weak;
+
+use anyhow::Result;
+
+use crate::{Array, ByteCodeError, FnType, Slice, Value, W};
+
+pub const MAP_SYM: &str = "map";
+
+pub fn map() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: MAP_SYM.into(),
+        prms: vec!["xs".into(), "f".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// Apply `f` to each element of an array or slice, in order, collecting the results into a new array of the
+/// same length. Slices give a slice over the new array.
+///
+/// Only the VM can run a closure, so `call` runs `f` with the given args. It is passed the VM state `S` and
+/// hands it back along with the result, so the state is threaded through every call.
+pub fn map_impl<S>(
+    state: S,
+    xs: &Value,
+    f: &Value,
+    mut call: impl FnMut(S, &Value, Vec<Value>) -> Result<(S, Value)>,
+) -> Result<(S, Value)> {
+    let mut state = state;
+    let mut vals = vec![];
+
+    for x in elems_of(xs)? {
+        let (next, val) = call(state, f, vec![x])?;
+        state = next;
+        vals.push(val);
+    }
+
+    let arr = Array::new(vals);
+    let res = match xs {
+        Value::Slice(_) => Value::Slice(Slice::new(arr.clone(), 0, arr.len())),
+        _ => Value::Array(arr),
+    };
+
+    Ok((state, res))
+}
+
+/// Copy out the elements of an array or slice, so `f` can write to them while they are being visited.
+pub(super) fn elems_of(xs: &Value) -> Result<Vec<Value>> {
+    match xs {
+        Value::Array(arr) => Ok(arr.borrow().clone()),
+        Value::Slice(slice) => Ok(slice.to_vec()),
+        _ => Err(ByteCodeError::TypeMismatch {
+            expected: "Array or Slice".to_string(),
+            found: format!("{:?}", xs),
+        }
+        .into()),
+    }
+}
@@ -0,0 +1,51 @@
+use std::{cmp::Ordering, rc::Weak};
+
+use anyhow::Result;
+
+use crate::{ByteCodeError, FnType, Value, W};
+
+use super::{map::elems_of, sort_by::compare};
+
+pub const MAX_BY_SYM: &str = "max_by";
+
+pub fn max_by() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: MAX_BY_SYM.into(),
+        prms: vec!["xs".into(), "cmp".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// The greatest element of an array or slice by `cmp`, which compares as for [`super::sort_by_impl`]. Of
+/// elements that are equally greatest, the last is returned, so `max_by` and `min_by` of two equal elements
+/// give different ones, as a stable sort would put them.
+///
+/// `call` runs `cmp` as for [`super::map_impl`].
+///
+/// # Errors
+///
+/// If `xs` is empty, or `cmp` doesn't return an int.
+pub fn max_by_impl<S>(
+    state: S,
+    xs: &Value,
+    cmp: &Value,
+    mut call: impl FnMut(S, &Value, Vec<Value>) -> Result<(S, Value)>,
+) -> Result<(S, Value)> {
+    let mut vals = elems_of(xs)?.into_iter();
+    let Some(mut best) = vals.next() else {
+        return Err(ByteCodeError::EmptyArray(MAX_BY_SYM.to_string()).into());
+    };
+
+    let mut state = state;
+    for x in vals {
+        let (next, ord) = compare(state, cmp, &x, &best, &mut call)?;
+        state = next;
+        if matches!(ord, Ordering::Greater | Ordering::Equal) {
+            best = x;
+        }
+    }
+
+    Ok((state, best))
+}
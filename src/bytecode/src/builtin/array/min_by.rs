@@ -0,0 +1,50 @@
+use std::{cmp::Ordering, rc::Weak};
+
+use anyhow::Result;
+
+use crate::{ByteCodeError, FnType, Value, W};
+
+use super::{map::elems_of, sort_by::compare};
+
+pub const MIN_BY_SYM: &str = "min_by";
+
+pub fn min_by() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: MIN_BY_SYM.into(),
+        prms: vec!["xs".into(), "cmp".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// The least element of an array or slice by `cmp`, which compares as for [`super::sort_by_impl`]. Of elements
+/// that are equally least, the first is returned.
+///
+/// `call` runs `cmp` as for [`super::map_impl`].
+///
+/// # Errors
+///
+/// If `xs` is empty, or `cmp` doesn't return an int.
+pub fn min_by_impl<S>(
+    state: S,
+    xs: &Value,
+    cmp: &Value,
+    mut call: impl FnMut(S, &Value, Vec<Value>) -> Result<(S, Value)>,
+) -> Result<(S, Value)> {
+    let mut vals = elems_of(xs)?.into_iter();
+    let Some(mut best) = vals.next() else {
+        return Err(ByteCodeError::EmptyArray(MIN_BY_SYM.to_string()).into());
+    };
+
+    let mut state = state;
+    for x in vals {
+        let (next, ord) = compare(state, cmp, &x, &best, &mut call)?;
+        state = next;
+        if matches!(ord, Ordering::Less) {
+            best = x;
+        }
+    }
+
+    Ok((state, best))
+}
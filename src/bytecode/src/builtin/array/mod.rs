@@ -0,0 +1,15 @@
+pub use filter::*;
+pub use fold::*;
+pub use map::*;
+pub use max_by::*;
+pub use min_by::*;
+pub use slice_len::*;
+pub use sort_by::*;
+
+mod filter;
+mod fold;
+mod map;
+mod max_by;
+mod min_by;
+mod slice_len;
+mod sort_by;
@@ -0,0 +1,30 @@
+use std::rc::Weak;
+
+use anyhow::Result;
+
+use crate::{ByteCodeError, FnType, Value, W};
+
+pub const SLICE_LEN_SYM: &str = "slice_len";
+
+pub fn slice_len() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: SLICE_LEN_SYM.into(),
+        prms: vec!["xs".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// Number of elements in view for a slice, or the length of an array.
+pub fn slice_len_impl(xs: &Value) -> Result<usize> {
+    match xs {
+        Value::Slice(slice) => Ok(slice.len),
+        Value::Array(arr) => Ok(arr.len()),
+        _ => Err(ByteCodeError::TypeMismatch {
+            expected: "Slice".to_string(),
+            found: format!("{:?}", xs),
+        }
+        .into()),
+    }
+}
@@ -0,0 +1,90 @@
+use std::{cmp::Ordering, rc::Weak};
+
+use anyhow::Result;
+
+use crate::{type_of, Array, ByteCodeError, FnType, Slice, Value, W};
+
+use super::map::elems_of;
+
+pub const SORT_BY_SYM: &str = "sort_by";
+
+pub fn sort_by() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: SORT_BY_SYM.into(),
+        prms: vec!["xs".into(), "cmp".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// Sort the elements of an array or slice into a new array of the same length, leaving `xs` as it was. Slices
+/// give a slice over the new array. `cmp(a, b)` returns a negative int if `a` goes before `b`, a positive one if
+/// it goes after, and 0 if either order will do.
+///
+/// The sort is stable: elements that `cmp` says are equal keep the order they had in `xs`. It is a merge sort, so
+/// `cmp` is called O(n log n) times.
+///
+/// `call` runs `cmp` as for [`super::map_impl`].
+pub fn sort_by_impl<S>(
+    state: S,
+    xs: &Value,
+    cmp: &Value,
+    mut call: impl FnMut(S, &Value, Vec<Value>) -> Result<(S, Value)>,
+) -> Result<(S, Value)> {
+    let mut state = state;
+    let mut vals = elems_of(xs)?;
+
+    // Merge runs of width 1, 2, 4, ... until one run is the whole array
+    let mut width = 1;
+    while width < vals.len() {
+        let mut merged = Vec::with_capacity(vals.len());
+        for run in vals.chunks(2 * width) {
+            let (left, right) = run.split_at(width.min(run.len()));
+            let (mut i, mut j) = (0, 0);
+            while i < left.len() && j < right.len() {
+                let (next, ord) = compare(state, cmp, &left[i], &right[j], &mut call)?;
+                state = next;
+                // Only take from the right when it is strictly less, so equal elements keep their order
+                if ord == Ordering::Greater {
+                    merged.push(right[j].clone());
+                    j += 1;
+                } else {
+                    merged.push(left[i].clone());
+                    i += 1;
+                }
+            }
+            merged.extend_from_slice(&left[i..]);
+            merged.extend_from_slice(&right[j..]);
+        }
+        vals = merged;
+        width *= 2;
+    }
+
+    let arr = Array::new(vals);
+    let res = match xs {
+        Value::Slice(_) => Value::Slice(Slice::new(arr.clone(), 0, arr.len())),
+        _ => Value::Array(arr),
+    };
+
+    Ok((state, res))
+}
+
+/// Call `cmp(a, b)` and turn the int it returns into an ordering.
+pub(super) fn compare<S>(
+    state: S,
+    cmp: &Value,
+    a: &Value,
+    b: &Value,
+    call: &mut impl FnMut(S, &Value, Vec<Value>) -> Result<(S, Value)>,
+) -> Result<(S, Ordering)> {
+    let (state, ord) = call(state, cmp, vec![a.clone(), b.clone()])?;
+    match ord {
+        Value::Int(ord) => Ok((state, ord.cmp(&0))),
+        _ => Err(ByteCodeError::TypeMismatch {
+            expected: "Int".to_string(),
+            found: type_of(&ord).to_string(),
+        }
+        .into()),
+    }
+}
@@ -0,0 +1,27 @@
+use std::rc::Weak;
+
+use anyhow::Result;
+
+use crate::{ByteCodeError, Bytes, FnType, Value, W};
+
+pub const BYTES_SYM: &str = "bytes";
+
+pub fn bytes() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: BYTES_SYM.into(),
+        prms: vec!["len".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// A buffer of len bytes, all 0.
+pub fn bytes_impl(len: &Value) -> Result<Value> {
+    let len: i64 = len.clone().try_into()?;
+    let len = usize::try_from(len).map_err(|_| ByteCodeError::BadType {
+        expected: "a length of 0 or more".to_string(),
+        found: len.to_string(),
+    })?;
+    Ok(Bytes::new(vec![0; len]).into())
+}
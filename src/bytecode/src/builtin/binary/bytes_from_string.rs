@@ -0,0 +1,23 @@
+use std::rc::Weak;
+
+use anyhow::Result;
+
+use crate::{Bytes, FnType, Value, W};
+
+pub const BYTES_FROM_STRING_SYM: &str = "bytes_from_string";
+
+pub fn bytes_from_string() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: BYTES_FROM_STRING_SYM.into(),
+        prms: vec!["s".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// The utf-8 bytes of s.
+pub fn bytes_from_string_impl(s: &Value) -> Result<Value> {
+    let s: String = s.clone().try_into()?;
+    Ok(Bytes::new(s.into_bytes()).into())
+}
@@ -0,0 +1,22 @@
+use std::rc::Weak;
+
+use anyhow::Result;
+
+use crate::{Bytes, FnType, Value, W};
+
+pub const BYTES_LEN_SYM: &str = "bytes_len";
+
+pub fn bytes_len() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: BYTES_LEN_SYM.into(),
+        prms: vec!["b".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+pub fn bytes_len_impl(b: &Value) -> Result<usize> {
+    let b: Bytes = b.clone().try_into()?;
+    Ok(b.len())
+}
@@ -0,0 +1,24 @@
+use std::rc::Weak;
+
+use anyhow::Result;
+
+use crate::{Bytes, FnType, Value, W};
+
+pub const GET_BYTE_SYM: &str = "get_byte";
+
+pub fn get_byte() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: GET_BYTE_SYM.into(),
+        prms: vec!["b".into(), "idx".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// The byte at idx, from 0 to 255.
+pub fn get_byte_impl(b: &Value, idx: &Value) -> Result<Value> {
+    let b: Bytes = b.clone().try_into()?;
+    let idx: i64 = idx.clone().try_into()?;
+    Ok(Value::Int(b.get(idx)?.into()))
+}
@@ -0,0 +1,17 @@
+pub use bytes::*;
+pub use bytes_from_string::*;
+pub use bytes_len::*;
+pub use get_byte::*;
+pub use read_bytes::*;
+pub use set_byte::*;
+pub use string_from_bytes::*;
+pub use write_bytes::*;
+
+mod bytes;
+mod bytes_from_string;
+mod bytes_len;
+mod get_byte;
+mod read_bytes;
+mod set_byte;
+mod string_from_bytes;
+mod write_bytes;
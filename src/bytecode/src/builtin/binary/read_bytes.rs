@@ -0,0 +1,27 @@
+use std::{fs, rc::Weak};
+
+use anyhow::Result;
+
+use crate::{Bytes, FnType, Value, Variant, W};
+
+pub const READ_BYTES_SYM: &str = "read_bytes";
+
+pub fn read_bytes() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: READ_BYTES_SYM.into(),
+        prms: vec!["path".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// Ok with the contents of the file at path, or Err with why it couldn't be read.
+pub fn read_bytes_impl(path: &Value) -> Result<Value> {
+    let path: String = path.clone().try_into()?;
+    let res = match fs::read(path) {
+        Ok(bytes) => Variant::ok(Bytes::new(bytes)),
+        Err(e) => Variant::err(e.to_string()),
+    };
+    Ok(res.into())
+}
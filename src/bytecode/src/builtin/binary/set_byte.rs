@@ -0,0 +1,25 @@
+use std::rc::Weak;
+
+use anyhow::Result;
+
+use crate::{Bytes, FnType, Value, W};
+
+pub const SET_BYTE_SYM: &str = "set_byte";
+
+pub fn set_byte() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: SET_BYTE_SYM.into(),
+        prms: vec!["b".into(), "idx".into(), "byte".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// Set the byte at idx. The buffer is shared, so the change is seen through every handle to it.
+pub fn set_byte_impl(b: &Value, idx: &Value, byte: &Value) -> Result<()> {
+    let b: Bytes = b.clone().try_into()?;
+    let idx: i64 = idx.clone().try_into()?;
+    let byte: i64 = byte.clone().try_into()?;
+    b.set(idx, byte)
+}
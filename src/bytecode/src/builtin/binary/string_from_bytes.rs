@@ -0,0 +1,65 @@
+use std::rc::Weak;
+
+use anyhow::Result;
+
+use crate::{ByteCodeError, Bytes, FnType, Value, W};
+
+pub const STRING_FROM_BYTES_SYM: &str = "string_from_bytes";
+
+pub fn string_from_bytes() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: STRING_FROM_BYTES_SYM.into(),
+        prms: vec!["b".into(), "encoding".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// Decode b as a string in the encoding: utf-8, ascii or latin-1, ignoring case and dashes.
+pub fn string_from_bytes_impl(b: &Value, encoding: &Value) -> Result<Value> {
+    let b: Bytes = b.clone().try_into()?;
+    let encoding: String = encoding.clone().try_into()?;
+    let bytes = b.borrow();
+
+    let s = match encoding.to_lowercase().replace('-', "").as_str() {
+        "utf8" => String::from_utf8(bytes.clone())?,
+        "ascii" => {
+            if let Some(b) = bytes.iter().find(|b| !b.is_ascii()) {
+                return Err(ByteCodeError::BadType {
+                    expected: "ascii".to_string(),
+                    found: format!("byte {}", b),
+                }
+                .into());
+            }
+            bytes.iter().map(|&b| char::from(b)).collect()
+        }
+        // Each byte is the code point of the same number
+        "latin1" => bytes.iter().map(|&b| char::from(b)).collect(),
+        _ => return Err(ByteCodeError::UnknownEncoding(encoding).into()),
+    };
+
+    Ok(s.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_string_from_bytes() -> Result<()> {
+        let decode = |bytes: &[u8], encoding: &str| {
+            string_from_bytes_impl(&Bytes::new(bytes.to_vec()).into(), &encoding.into())
+        };
+
+        assert_eq!(decode("héllo".as_bytes(), "utf-8")?, "héllo".into());
+        assert_eq!(decode(b"hi", "ASCII")?, "hi".into());
+        assert_eq!(decode(&[0x63, 0x61, 0x66, 0xe9], "latin-1")?, "café".into());
+
+        assert!(decode(&[0xff], "utf-8").is_err());
+        assert!(decode(&[0xe9], "ascii").is_err());
+        assert!(decode(b"hi", "ebcdic")
+            .is_err_and(|e| e.to_string().contains("Unknown encoding ebcdic")));
+        Ok(())
+    }
+}
@@ -0,0 +1,28 @@
+use std::{fs, rc::Weak};
+
+use anyhow::Result;
+
+use crate::{Bytes, FnType, Value, Variant, W};
+
+pub const WRITE_BYTES_SYM: &str = "write_bytes";
+
+pub fn write_bytes() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: WRITE_BYTES_SYM.into(),
+        prms: vec!["path".into(), "b".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// Write b to the file at path, replacing what was there. Ok with unit, or Err with why it couldn't be written.
+pub fn write_bytes_impl(path: &Value, b: &Value) -> Result<Value> {
+    let path: String = path.clone().try_into()?;
+    let b: Bytes = b.clone().try_into()?;
+    let res = match fs::write(path, &*b.borrow()) {
+        Ok(()) => Variant::ok(()),
+        Err(e) => Variant::err(e.to_string()),
+    };
+    Ok(res.into())
+}
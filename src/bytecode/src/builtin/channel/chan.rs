@@ -0,0 +1,19 @@
+use std::rc::Weak;
+
+use crate::{Channel, FnType, Value, W};
+
+pub const CHAN_SYM: &str = "chan";
+
+pub fn chan() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: CHAN_SYM.into(),
+        prms: vec![].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+pub fn chan_impl() -> Value {
+    Channel::new().into()
+}
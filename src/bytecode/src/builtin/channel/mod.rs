@@ -0,0 +1,5 @@
+pub use chan::*;
+pub use try_recv::*;
+
+mod chan;
+mod try_recv;
@@ -0,0 +1,27 @@
+use std::rc::Weak;
+
+use anyhow::Result;
+
+use crate::{Channel, FnType, Value, Variant, W};
+
+pub const TRY_RECV_SYM: &str = "try_recv";
+
+pub fn try_recv() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: TRY_RECV_SYM.into(),
+        prms: vec!["c".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// Some with the next value waiting in the channel, or None without blocking if nothing is.
+pub fn try_recv_impl(c: &Value) -> Result<Value> {
+    let c: Channel = c.clone().try_into()?;
+    let res = match c.recv() {
+        Some(val) => Variant::some(val),
+        None => Variant::none(),
+    };
+    Ok(res.into())
+}
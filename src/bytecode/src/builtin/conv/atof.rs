@@ -0,0 +1,28 @@
+use std::rc::Weak;
+
+use anyhow::Result;
+
+use crate::{FnType, Value, Variant, W};
+
+pub const ATOF_SYM: &str = "atof";
+
+pub fn atof() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: ATOF_SYM.into(),
+        prms: vec!["s".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// atof(s): Ok with the float written in s, like "2.5", "-1e3" or "inf", or Err with why it isn't one.
+/// Whitespace around it is ignored.
+pub fn atof_impl(s: &Value) -> Result<Value> {
+    let s: String = s.clone().try_into()?;
+    let res = match s.trim().parse::<f64>() {
+        Ok(f) => Variant::ok(f),
+        Err(e) => Variant::err(e.to_string()),
+    };
+    Ok(res.into())
+}
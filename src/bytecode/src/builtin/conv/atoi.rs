@@ -2,7 +2,7 @@ use std::rc::Weak;
 
 use anyhow::Result;
 
-use crate::{FnType, Value, W};
+use crate::{FnType, Value, Variant, W};
 
 pub const ATOI_SYM: &str = "atoi";
 
@@ -10,14 +10,18 @@ pub fn atoi() -> Value {
     Value::Closure {
         fn_type: FnType::Builtin,
         sym: ATOI_SYM.into(),
-        prms: vec!["s".into()],
+        prms: vec!["s".into()].into(),
         addr: 0,
         env: W(Weak::new()),
     }
 }
 
+/// Ok with the int the string is, or Err with why it isn't one.
 pub fn atoi_impl(s: &Value) -> Result<Value> {
     let s: String = s.clone().try_into()?;
-    let n: i64 = s.parse()?;
-    Ok(Value::Int(n))
+    let res = match s.parse::<i64>() {
+        Ok(n) => Variant::ok(n),
+        Err(e) => Variant::err(e.to_string()),
+    };
+    Ok(res.into())
 }
@@ -10,7 +10,7 @@ pub fn float_to_int() -> Value {
     Value::Closure {
         fn_type: FnType::Builtin,
         sym: FLOAT_TO_INT_SYM.into(),
-        prms: vec!["x".into()],
+        prms: vec!["x".into()].into(),
         addr: 0,
         env: W(Weak::new()),
     }
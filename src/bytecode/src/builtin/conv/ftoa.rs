@@ -0,0 +1,30 @@
+use std::rc::Weak;
+
+use anyhow::Result;
+
+use crate::{ByteCodeError, FnType, Value, W};
+
+pub const FTOA_SYM: &str = "ftoa";
+
+pub fn ftoa() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: FTOA_SYM.into(),
+        prms: vec!["f".into(), "precision".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// ftoa(f, precision): f with precision digits after the point, rounded, e.g. ftoa(2.345, 2) is "2.35"
+pub fn ftoa_impl(f: &Value, precision: &Value) -> Result<Value> {
+    let f: f64 = f.clone().try_into()?;
+    let precision: i64 = precision.clone().try_into()?;
+    if precision < 0 {
+        return Err(ByteCodeError::NegativePrecision(precision).into());
+    }
+
+    Ok(Value::String(
+        format!("{:.*}", precision as usize, f).into(),
+    ))
+}
@@ -9,7 +9,7 @@ pub fn int_to_float() -> Value {
     Value::Closure {
         fn_type: FnType::Builtin,
         sym: INT_TO_FLOAT_SYM.into(),
-        prms: vec!["x".into()],
+        prms: vec!["x".into()].into(),
         addr: 0,
         env: W(Weak::new()),
     }
@@ -10,7 +10,7 @@ pub fn itoa() -> Value {
     Value::Closure {
         fn_type: FnType::Builtin,
         sym: ITOA_SYM.into(),
-        prms: vec!["i".into()],
+        prms: vec!["i".into()].into(),
         addr: 0,
         env: W(Weak::new()),
     }
@@ -18,5 +18,5 @@ pub fn itoa() -> Value {
 
 pub fn itoa_impl(i: &Value) -> Result<Value> {
     let i: i64 = i.clone().try_into()?;
-    Ok(Value::String(i.to_string()))
+    Ok(Value::String(i.to_string().into()))
 }
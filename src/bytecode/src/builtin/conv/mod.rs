@@ -1,9 +1,13 @@
+pub use atof::*;
 pub use atoi::*;
 pub use float_to_int::*;
+pub use ftoa::*;
 pub use int_to_float::*;
 pub use itoa::*;
 
+mod atof;
 mod atoi;
 mod float_to_int;
+mod ftoa;
 mod int_to_float;
 mod itoa;
@@ -0,0 +1,431 @@
+use std::rc::{Rc, Weak};
+
+use anyhow::Result;
+
+use crate::{type_of, ByteCodeError, Bytes, FnType, Value, W};
+
+pub const HASH_SYM: &str = "hash";
+pub const CRC32_SYM: &str = "crc32";
+pub const SHA256_SYM: &str = "sha256";
+
+pub fn hash() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: HASH_SYM.into(),
+        prms: vec!["value".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+pub fn crc32() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: CRC32_SYM.into(),
+        prms: vec!["b".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+pub fn sha256() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: SHA256_SYM.into(),
+        prms: vec!["b".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// A hash of the contents of the value, the same on every run and machine: 64 bit FNV-1a over the type and
+/// contents of the value. Arrays, structs and bytes are hashed by what they hold, so equal values hash the same. A
+/// value that holds itself is hashed as a reference back to itself where it comes back.
+///
+/// # Errors
+///
+/// If the value is or holds something with no contents to hash, like a closure, channel or socket.
+pub fn hash_impl(value: &Value) -> Result<i64> {
+    let mut hasher = Fnv::new();
+    hash_value(&mut hasher, value, &mut Vec::new())?;
+    Ok(hasher.0 as i64)
+}
+
+/// The CRC-32 of the bytes, as used by zip, png and ethernet.
+pub fn crc32_impl(b: &Value) -> Result<i64> {
+    let b: Bytes = b.clone().try_into()?;
+    let crc = b.borrow().iter().fold(!0u32, |crc, &byte| {
+        (0..8).fold(crc ^ byte as u32, |crc, _| {
+            // 0xEDB88320 is the CRC-32 polynomial with its bits reversed
+            (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg())
+        })
+    });
+    Ok((!crc).into())
+}
+
+/// The SHA-256 digest of the bytes, as 64 lowercase hex digits.
+pub fn sha256_impl(b: &Value) -> Result<Value> {
+    let b: Bytes = b.clone().try_into()?;
+    let digest = sha256_digest(&b.borrow());
+    let hex: String = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+    Ok(hex.into())
+}
+
+struct Fnv(u64);
+
+impl Fnv {
+    fn new() -> Self {
+        Fnv(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+// Each value is written as a tag for its type then its contents, with lengths before anything of variable length,
+// so values of different types or shapes don't run together into the same bytes.
+//
+// Arrays, structs and maps can be changed to hold themselves, so ancestors has the storage of each one being hashed
+// around this value. One that comes back is written as a tag and how many levels up it is, instead of its contents.
+fn hash_value(hasher: &mut Fnv, value: &Value, ancestors: &mut Vec<*const ()>) -> Result<()> {
+    let ptr = match value {
+        Value::Array(arr) => Some(Rc::as_ptr(arr) as *const ()),
+        Value::Slice(slice) => Some(Rc::as_ptr(&slice.arr) as *const ()),
+        Value::Struct(s) => Some(s.as_ptr()),
+        Value::Map(m) => Some(m.as_ptr()),
+        _ => None,
+    };
+    let Some(ptr) = ptr else {
+        return hash_contents(hasher, value, ancestors);
+    };
+
+    if let Some(idx) = ancestors.iter().rposition(|&ancestor| ancestor == ptr) {
+        hasher.write(&[14]);
+        hasher.write(&(ancestors.len() - idx).to_le_bytes());
+        return Ok(());
+    }
+
+    ancestors.push(ptr);
+    let res = hash_contents(hasher, value, ancestors);
+    ancestors.pop();
+    res
+}
+
+fn hash_contents(hasher: &mut Fnv, value: &Value, ancestors: &mut Vec<*const ()>) -> Result<()> {
+    match value {
+        Value::Unit => hasher.write(&[0]),
+        Value::Int(i) => {
+            hasher.write(&[1]);
+            hasher.write(&i.to_le_bytes());
+        }
+        Value::Float(f) => {
+            hasher.write(&[2]);
+            hasher.write(&f.to_bits().to_le_bytes());
+        }
+        Value::Bool(b) => hasher.write(&[3, *b as u8]),
+        Value::String(s) => {
+            hasher.write(&[4]);
+            hasher.write(&s.len().to_le_bytes());
+            hasher.write(s.as_bytes());
+        }
+        Value::Bytes(b) => {
+            let b = b.borrow();
+            hasher.write(&[5]);
+            hasher.write(&b.len().to_le_bytes());
+            hasher.write(&b);
+        }
+        // A slice hashes the same as an array of what it views
+        Value::Array(_) | Value::Slice(_) => {
+            let vals = match value {
+                Value::Array(arr) => arr.borrow().clone(),
+                Value::Slice(slice) => slice.to_vec(),
+                _ => unreachable!(),
+            };
+            hasher.write(&[6]);
+            hasher.write(&vals.len().to_le_bytes());
+            for val in vals.iter() {
+                hash_value(hasher, val, ancestors)?;
+            }
+        }
+        Value::Struct(s) => {
+            let fields = s.fields();
+            hasher.write(&[7]);
+            hasher.write(&s.name.len().to_le_bytes());
+            hasher.write(s.name.as_bytes());
+            for (field, val) in fields.iter() {
+                hasher.write(&field.len().to_le_bytes());
+                hasher.write(field.as_bytes());
+                hash_value(hasher, val, ancestors)?;
+            }
+        }
+        Value::PVec(pvec) => {
+            hasher.write(&[8]);
+            hasher.write(&pvec.len().to_le_bytes());
+            for val in pvec.to_vec().iter() {
+                hash_value(hasher, val, ancestors)?;
+            }
+        }
+        // Entries are visited in key order, so maps with the same entries hash the same however they were built
+        Value::PMap(pmap) => {
+            hasher.write(&[9]);
+            hasher.write(&pmap.len().to_le_bytes());
+            for (key, val) in pmap.entries().iter() {
+                hash_value(hasher, key, ancestors)?;
+                hash_value(hasher, val, ancestors)?;
+            }
+        }
+        Value::Char(c) => {
+            hasher.write(&[10]);
+            hasher.write(&u32::from(*c).to_le_bytes());
+        }
+        Value::Variant(v) => {
+            hasher.write(&[11]);
+            for name in [&v.enum_name, &v.name] {
+                hasher.write(&name.len().to_le_bytes());
+                hasher.write(name.as_bytes());
+            }
+            hasher.write(&v.payload.len().to_le_bytes());
+            for val in v.payload.iter() {
+                hash_value(hasher, val, ancestors)?;
+            }
+        }
+        Value::Tuple(t) => {
+            hasher.write(&[12]);
+            hasher.write(&t.len().to_le_bytes());
+            for val in t.iter() {
+                hash_value(hasher, val, ancestors)?;
+            }
+        }
+        Value::Map(m) => {
+            hasher.write(&[13]);
+            hasher.write(&m.len().to_le_bytes());
+            for (key, val) in m.entries().iter() {
+                hash_value(hasher, key, ancestors)?;
+                hash_value(hasher, val, ancestors)?;
+            }
+        }
+        Value::Unitialized
+        | Value::Semaphore(_)
+        | Value::Channel(_)
+        | Value::Socket(_)
+        | Value::Closure { .. } => {
+            return Err(ByteCodeError::BadType {
+                expected: "a value with contents to hash".to_string(),
+                found: type_of(value).to_string(),
+            }
+            .into())
+        }
+    }
+
+    Ok(())
+}
+
+// The first 32 bits of the fractional parts of the cube roots of the first 64 primes
+const SHA256_K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+// SHA-256 as in FIPS 180-4
+fn sha256_digest(data: &[u8]) -> [u8; 32] {
+    let mut h: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+        0x5be0cd19,
+    ];
+
+    // Pad with a 1 bit, then 0s up to 8 bytes short of a block, then the length in bits
+    let mut msg = data.to_vec();
+    msg.push(0x80);
+    while msg.len() % 64 != 56 {
+        msg.push(0);
+    }
+    msg.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for block in msg.chunks(64) {
+        let mut w = [0u32; 64];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut hh] = h;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = hh
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(SHA256_K[i])
+                .wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+
+            hh = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+
+        for (h, v) in h.iter_mut().zip([a, b, c, d, e, f, g, hh]) {
+            *h = h.wrapping_add(v);
+        }
+    }
+
+    let mut digest = [0; 32];
+    for (chunk, word) in digest.chunks_mut(4).zip(h) {
+        chunk.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Array, Struct};
+
+    use super::*;
+
+    fn bytes(b: &[u8]) -> Value {
+        Bytes::new(b.to_vec()).into()
+    }
+
+    #[test]
+    fn test_hash() -> Result<()> {
+        // Fixed across runs, so it can be relied on as a key
+        assert_eq!(hash_impl(&Value::Int(42))?, hash_impl(&Value::Int(42))?);
+        assert_eq!(hash_impl(&Value::Unit)?, 0xaf63bd4c8601b7df_u64 as i64);
+
+        assert_ne!(hash_impl(&Value::Int(1))?, hash_impl(&Value::Bool(true))?);
+        assert_ne!(hash_impl(&"ab".into())?, hash_impl(&"ba".into())?);
+        let pair = |a: &str, b: &str| -> Value { vec![a.into(), b.into()].into() };
+        assert_ne!(hash_impl(&pair("a", "bc"))?, hash_impl(&pair("ab", "c"))?);
+
+        // Equal arrays and structs hash the same, whether or not they share storage
+        let arr: Value = vec![Value::Int(1), Value::Int(2)].into();
+        let Value::Array(shared) = arr.clone() else {
+            unreachable!()
+        };
+        assert_eq!(hash_impl(&arr)?, hash_impl(&shared.deep_clone().into())?);
+        let slice: Value = crate::Slice::new(
+            Array::new(vec![Value::Int(0), Value::Int(1), Value::Int(2)]),
+            1,
+            2,
+        )
+        .into();
+        assert_eq!(hash_impl(&arr)?, hash_impl(&slice)?);
+
+        let point = |x: i64| -> Value {
+            Struct::new("Point".into(), vec![("x".into(), Value::Int(x))]).into()
+        };
+        assert_eq!(hash_impl(&point(1))?, hash_impl(&point(1))?);
+        assert_ne!(hash_impl(&point(1))?, hash_impl(&point(2))?);
+
+        let some = |v: i64| -> Value { crate::Variant::some(v).into() };
+        assert_eq!(hash_impl(&some(1))?, hash_impl(&some(1))?);
+        assert_ne!(
+            hash_impl(&some(1))?,
+            hash_impl(&crate::Variant::ok(1).into())?
+        );
+
+        let pair = |a: i64, b: i64| -> Value {
+            crate::Tuple::new(vec![Value::Int(a), Value::Int(b)]).into()
+        };
+        assert_eq!(hash_impl(&pair(1, 2))?, hash_impl(&pair(1, 2))?);
+        assert_ne!(hash_impl(&pair(1, 2))?, hash_impl(&pair(2, 1))?);
+
+        // like a pmap, the same entries hash the same in any order, but a map is not a pmap
+        let (m1, m2) = (crate::Map::new(), crate::Map::new());
+        m1.insert(Value::Int(1), Value::Int(2))?;
+        m1.insert(Value::Int(3), Value::Int(4))?;
+        m2.insert(Value::Int(3), Value::Int(4))?;
+        m2.insert(Value::Int(1), Value::Int(2))?;
+        assert_eq!(hash_impl(&m1.clone().into())?, hash_impl(&m2.into())?);
+        let pmap = crate::PMap::new()
+            .insert(Value::Int(1), Value::Int(2))?
+            .insert(Value::Int(3), Value::Int(4))?;
+        assert_ne!(hash_impl(&m1.into())?, hash_impl(&pmap.into())?);
+
+        assert!(hash_impl(&crate::builtin::sha256()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_hash_cycle() -> Result<()> {
+        // a node whose map of kids holds the node itself
+        let node = |v: i64| -> Result<Value> {
+            let kids = crate::Map::new();
+            let node = Struct::new(
+                "N".into(),
+                vec![
+                    ("v".into(), Value::Int(v)),
+                    ("kids".into(), kids.clone().into()),
+                ],
+            );
+            kids.insert("self".into(), node.clone().into())?;
+            Ok(node.into())
+        };
+        assert_eq!(hash_impl(&node(1)?)?, hash_impl(&node(1)?)?);
+        assert_ne!(hash_impl(&node(1)?)?, hash_impl(&node(2)?)?);
+
+        // an array holding itself is not the same as one holding an empty array
+        let Value::Array(arr) = Value::from(vec![Value::Int(1)]) else {
+            unreachable!()
+        };
+        arr.borrow_mut().push(Value::Array(arr.clone()));
+        let flat: Value = vec![Value::Int(1), Vec::new().into()].into();
+        assert_ne!(hash_impl(&arr.into())?, hash_impl(&flat)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_crc32() -> Result<()> {
+        assert_eq!(crc32_impl(&bytes(b""))?, 0);
+        assert_eq!(crc32_impl(&bytes(b"123456789"))?, 0xcbf43926);
+        assert_eq!(
+            crc32_impl(&bytes(b"The quick brown fox jumps over the lazy dog"))?,
+            0x414fa339
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_sha256() -> Result<()> {
+        assert_eq!(
+            sha256_impl(&bytes(b""))?,
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855".into()
+        );
+        assert_eq!(
+            sha256_impl(&bytes(b"abc"))?,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad".into()
+        );
+        // Two blocks once padded
+        assert_eq!(
+            sha256_impl(&bytes(
+                b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"
+            ))?,
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1".into()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,31 @@
+use std::rc::Weak;
+
+use anyhow::Result;
+
+use crate::{FnType, Map, Value, Variant, W};
+
+pub const MAP_GET_SYM: &str = "map_get";
+
+pub fn map_get() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: MAP_GET_SYM.into(),
+        prms: vec!["m".into(), "key".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// Some of the value of the key, or None if the map doesn't have it.
+///
+/// # Errors
+///
+/// If m is not a map or the key is not an int, string or bool.
+pub fn map_get_impl(m: &Value, key: &Value) -> Result<Value> {
+    let m: Map = m.clone().try_into()?;
+    let val = match m.get(key)? {
+        Some(val) => Variant::some(val),
+        None => Variant::none(),
+    };
+    Ok(val.into())
+}
@@ -0,0 +1,28 @@
+use std::rc::Weak;
+
+use anyhow::Result;
+
+use crate::{FnType, Map, Value, W};
+
+pub const MAP_INSERT_SYM: &str = "map_insert";
+
+pub fn map_insert() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: MAP_INSERT_SYM.into(),
+        prms: vec!["m".into(), "key".into(), "val".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// Set the value of a key in the map, in place.
+///
+/// # Errors
+///
+/// If m is not a map or the key is not an int, string or bool.
+pub fn map_insert_impl(m: &Value, key: &Value, val: &Value) -> Result<Value> {
+    let m: Map = m.clone().try_into()?;
+    m.insert(key.clone(), val.clone())?;
+    Ok(Value::Unit)
+}
@@ -0,0 +1,29 @@
+use std::rc::Weak;
+
+use anyhow::Result;
+
+use crate::{Array, FnType, Map, Slice, Value, W};
+
+pub const MAP_KEYS_SYM: &str = "map_keys";
+
+pub fn map_keys() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: MAP_KEYS_SYM.into(),
+        prms: vec!["m".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// A slice of the keys in the map, ints then strings then bools, each in order.
+///
+/// # Errors
+///
+/// If m is not a map.
+pub fn map_keys_impl(m: &Value) -> Result<Value> {
+    let m: Map = m.clone().try_into()?;
+    let keys = m.keys();
+    let len = keys.len();
+    Ok(Value::Slice(Slice::new(Array::new(keys), 0, len)))
+}
@@ -0,0 +1,27 @@
+use std::rc::Weak;
+
+use anyhow::Result;
+
+use crate::{FnType, Map, Value, W};
+
+pub const MAP_LEN_SYM: &str = "map_len";
+
+pub fn map_len() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: MAP_LEN_SYM.into(),
+        prms: vec!["m".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// The number of keys in the map.
+///
+/// # Errors
+///
+/// If m is not a map.
+pub fn map_len_impl(m: &Value) -> Result<Value> {
+    let m: Map = m.clone().try_into()?;
+    Ok(Value::Int(m.len() as i64))
+}
@@ -0,0 +1,20 @@
+use std::rc::Weak;
+
+use crate::{FnType, Map, Value, W};
+
+pub const MAP_NEW_SYM: &str = "map_new";
+
+pub fn map_new() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: MAP_NEW_SYM.into(),
+        prms: vec![].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// An empty map.
+pub fn map_new_impl() -> Value {
+    Map::new().into()
+}
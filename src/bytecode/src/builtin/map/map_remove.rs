@@ -0,0 +1,31 @@
+use std::rc::Weak;
+
+use anyhow::Result;
+
+use crate::{FnType, Map, Value, Variant, W};
+
+pub const MAP_REMOVE_SYM: &str = "map_remove";
+
+pub fn map_remove() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: MAP_REMOVE_SYM.into(),
+        prms: vec!["m".into(), "key".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// Remove the key from the map, in place. Some of the value it had, or None if the map didn't have it.
+///
+/// # Errors
+///
+/// If m is not a map or the key is not an int, string or bool.
+pub fn map_remove_impl(m: &Value, key: &Value) -> Result<Value> {
+    let m: Map = m.clone().try_into()?;
+    let val = match m.remove(key)? {
+        Some(val) => Variant::some(val),
+        None => Variant::none(),
+    };
+    Ok(val.into())
+}
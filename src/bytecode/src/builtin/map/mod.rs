@@ -0,0 +1,13 @@
+pub use map_get::*;
+pub use map_insert::*;
+pub use map_keys::*;
+pub use map_len::*;
+pub use map_new::*;
+pub use map_remove::*;
+
+mod map_get;
+mod map_insert;
+mod map_keys;
+mod map_len;
+mod map_new;
+mod map_remove;
@@ -0,0 +1,49 @@
+use std::rc::Weak;
+
+use anyhow::Result;
+
+use crate::{ByteCodeError, FnType, Value, W};
+
+pub const DIV_EUCLID_SYM: &str = "div_euclid";
+
+pub fn div_euclid() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: DIV_EUCLID_SYM.into(),
+        prms: vec!["a".into(), "b".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// The quotient of a by b, rounded so that rem_euclid(a, b) is never negative: div_euclid(-7, 2) is -4 where
+/// -7 / 2 is -3.
+pub fn div_euclid_impl(a: &Value, b: &Value) -> Result<Value> {
+    let a: i64 = a.clone().try_into()?;
+    let b: i64 = b.clone().try_into()?;
+    if b == 0 {
+        return Err(ByteCodeError::DivisionByZero(DIV_EUCLID_SYM.to_string()).into());
+    }
+
+    let q = a
+        .checked_div_euclid(b)
+        .ok_or_else(|| ByteCodeError::IntegerOverflow(DIV_EUCLID_SYM.to_string()))?;
+    Ok(Value::Int(q))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_div_euclid() {
+        let div = |a: i64, b: i64| div_euclid_impl(&Value::Int(a), &Value::Int(b));
+        assert_eq!(div(7, 2).unwrap(), Value::Int(3));
+        assert_eq!(div(-7, 2).unwrap(), Value::Int(-4));
+        assert_eq!(div(7, -2).unwrap(), Value::Int(-3));
+        assert_eq!(div(-7, -2).unwrap(), Value::Int(4));
+
+        assert!(div(1, 0).is_err());
+        assert!(div(i64::MIN, -1).is_err());
+    }
+}
@@ -0,0 +1,49 @@
+use std::rc::Weak;
+
+use anyhow::Result;
+
+use crate::{ByteCodeError, FnType, Tuple, Value, W};
+
+pub const DIVMOD_SYM: &str = "divmod";
+
+pub fn divmod() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: DIVMOD_SYM.into(),
+        prms: vec!["a".into(), "b".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// (a / b, a % b) with the same truncating division as the operators, so the quotient rounds towards zero and
+/// the remainder has the sign of a. Either way q * b + r is a.
+pub fn divmod_impl(a: &Value, b: &Value) -> Result<Value> {
+    let a: i64 = a.clone().try_into()?;
+    let b: i64 = b.clone().try_into()?;
+    if b == 0 {
+        return Err(ByteCodeError::DivisionByZero(DIVMOD_SYM.to_string()).into());
+    }
+
+    let overflow = || ByteCodeError::IntegerOverflow(DIVMOD_SYM.to_string());
+    let q = a.checked_div(b).ok_or_else(overflow)?;
+    let r = a.checked_rem(b).ok_or_else(overflow)?;
+    Ok(Tuple::new(vec![Value::Int(q), Value::Int(r)]).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_divmod() {
+        let pair = |q: i64, r: i64| Value::from(Tuple::new(vec![Value::Int(q), Value::Int(r)]));
+        let divmod = |a: i64, b: i64| divmod_impl(&Value::Int(a), &Value::Int(b));
+        assert_eq!(divmod(17, 5).unwrap(), pair(3, 2));
+        assert_eq!(divmod(-17, 5).unwrap(), pair(-3, -2));
+        assert_eq!(divmod(17, -5).unwrap(), pair(-3, 2));
+
+        assert!(divmod(1, 0).is_err());
+        assert!(divmod(i64::MIN, -1).is_err());
+    }
+}
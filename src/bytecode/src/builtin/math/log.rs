@@ -10,7 +10,7 @@ pub fn log() -> Value {
     Value::Closure {
         fn_type: FnType::Builtin,
         sym: LOG_SYM.into(),
-        prms: vec!["x".into()],
+        prms: vec!["x".into()].into(),
         addr: 0,
         env: W(Weak::new()),
     }
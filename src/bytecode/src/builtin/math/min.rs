@@ -10,7 +10,7 @@ pub fn min() -> Value {
     Value::Closure {
         fn_type: FnType::Builtin,
         sym: MIN_SYM.into(),
-        prms: vec!["v1".into(), "v2".into()],
+        prms: vec!["v1".into(), "v2".into()].into(),
         addr: 0,
         env: W(Weak::new()),
     }
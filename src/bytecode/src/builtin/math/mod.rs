@@ -1,19 +1,25 @@
 pub use abs::*;
 pub use cos::*;
+pub use div_euclid::*;
+pub use divmod::*;
 pub use log::*;
 pub use max::*;
 pub use min::*;
 pub use pow::*;
+pub use rem_euclid::*;
 pub use sin::*;
 pub use sqrt::*;
 pub use tan::*;
 
 mod abs;
 mod cos;
+mod div_euclid;
+mod divmod;
 mod log;
 mod max;
 mod min;
 mod pow;
+mod rem_euclid;
 mod sin;
 mod sqrt;
 mod tan;
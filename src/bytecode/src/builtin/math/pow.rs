@@ -10,7 +10,7 @@ pub fn pow() -> Value {
     Value::Closure {
         fn_type: FnType::Builtin,
         sym: POW_SYM.into(),
-        prms: vec!["base".into(), "exp".into()],
+        prms: vec!["base".into(), "exp".into()].into(),
         addr: 0,
         env: W(Weak::new()),
     }
@@ -0,0 +1,49 @@
+use std::rc::Weak;
+
+use anyhow::Result;
+
+use crate::{ByteCodeError, FnType, Value, W};
+
+pub const REM_EUCLID_SYM: &str = "rem_euclid";
+
+pub fn rem_euclid() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: REM_EUCLID_SYM.into(),
+        prms: vec!["a".into(), "b".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// The remainder of a by b, from 0 up to |b|: rem_euclid(-7, 2) is 1 where -7 % 2 is -1. This is the one to
+/// wrap an index around an array with.
+pub fn rem_euclid_impl(a: &Value, b: &Value) -> Result<Value> {
+    let a: i64 = a.clone().try_into()?;
+    let b: i64 = b.clone().try_into()?;
+    if b == 0 {
+        return Err(ByteCodeError::DivisionByZero(REM_EUCLID_SYM.to_string()).into());
+    }
+
+    let r = a
+        .checked_rem_euclid(b)
+        .ok_or_else(|| ByteCodeError::IntegerOverflow(REM_EUCLID_SYM.to_string()))?;
+    Ok(Value::Int(r))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rem_euclid() {
+        let rem = |a: i64, b: i64| rem_euclid_impl(&Value::Int(a), &Value::Int(b));
+        assert_eq!(rem(7, 2).unwrap(), Value::Int(1));
+        assert_eq!(rem(-7, 2).unwrap(), Value::Int(1));
+        assert_eq!(rem(7, -2).unwrap(), Value::Int(1));
+        assert_eq!(rem(-7, -2).unwrap(), Value::Int(1));
+
+        assert!(rem(1, 0).is_err());
+        assert!(rem_euclid_impl(&Value::Int(1), &Value::Float(2.0)).is_err());
+    }
+}
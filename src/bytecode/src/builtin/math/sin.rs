@@ -10,7 +10,7 @@ pub fn sin() -> Value {
     Value::Closure {
         fn_type: FnType::Builtin,
         sym: SIN_SYM.into(),
-        prms: vec!["x".into()],
+        prms: vec!["x".into()].into(),
         addr: 0,
         env: W(Weak::new()),
     }
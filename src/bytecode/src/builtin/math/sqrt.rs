@@ -10,7 +10,7 @@ pub fn sqrt() -> Value {
     Value::Closure {
         fn_type: FnType::Builtin,
         sym: SQRT_SYM.into(),
-        prms: vec!["x".into()],
+        prms: vec!["x".into()].into(),
         addr: 0,
         env: W(Weak::new()),
     }
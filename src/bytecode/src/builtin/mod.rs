@@ -1,17 +1,52 @@
+pub use array::*;
+pub use binary::*;
+pub use channel::*;
 pub use constants::*;
 pub use conv::*;
+pub use hash::*;
+pub use map::*;
 pub use math::*;
+pub use net::*;
+pub use persist::*;
+pub use process::*;
 pub use semaphore::*;
 pub use stdin::*;
 pub use stdout::*;
 pub use string::*;
+pub use thread::*;
+pub use time::*;
 
+mod array;
+mod binary;
+mod channel;
 mod constants;
 mod conv;
+mod hash;
+mod map;
 mod math;
+mod net;
+mod persist;
+mod process;
 mod semaphore;
 mod stdin;
 mod stdout;
 mod string;
+mod thread;
+mod time;
 
 pub const BUILTIN_SYM: &str = "BUILTIN";
+
+/// Sort the errors of a builtin that does IO. The ones the program can't rule out, like a refused connection or a
+/// command that isn't installed, are given to it as the message of an Err to handle. The ones it made, like a
+/// closed socket or a bad url, stay errors of the VM.
+///
+/// # Errors
+///
+/// If the builtin failed for a reason other than IO.
+pub fn io_result<T>(res: anyhow::Result<T>) -> anyhow::Result<Result<T, String>> {
+    match res {
+        Ok(val) => Ok(Ok(val)),
+        Err(e) if e.downcast_ref::<std::io::Error>().is_some() => Ok(Err(format!("{:#}", e))),
+        Err(e) => Err(e),
+    }
+}
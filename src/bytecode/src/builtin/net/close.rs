@@ -0,0 +1,24 @@
+use std::rc::Weak;
+
+use anyhow::Result;
+
+use crate::Socket;
+use crate::{FnType, Value, W};
+
+pub const CLOSE_SYM: &str = "close";
+
+pub fn close() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: CLOSE_SYM.into(),
+        prms: vec!["sock".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+pub fn close_impl(sock: &Value) -> Result<()> {
+    let sock: Socket = sock.clone().try_into()?;
+    sock.close();
+    Ok(())
+}
@@ -0,0 +1,213 @@
+use std::{
+    io::{Read, Write},
+    net::TcpStream,
+};
+
+use anyhow::{Error, Result};
+
+use crate::{Struct, Value};
+
+/// The name of the struct http_get and http_post give in their Ok, with the fields `status: int` and `body: string`.
+pub const HTTP_RESPONSE_STRUCT: &str = "HttpResponse";
+
+/// The status and body of an HTTP response. Made into an HttpResponse struct on the thread of the runtime, since
+/// values can't be sent between threads.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpResponse {
+    pub status: i64,
+    pub body: String,
+}
+
+impl From<HttpResponse> for Value {
+    fn from(res: HttpResponse) -> Self {
+        Value::Struct(Struct::new(
+            HTTP_RESPONSE_STRUCT.into(),
+            vec![
+                ("status".into(), Value::Int(res.status)),
+                ("body".into(), Value::String(res.body.into())),
+            ],
+        ))
+    }
+}
+
+/// Send a request over plain HTTP/1.1 and wait for the whole response. Only http:// urls are supported.
+pub(crate) fn request(method: &str, url: &str, body: Option<&str>) -> Result<HttpResponse> {
+    let (host, path) = parse_url(url)?;
+    let mut stream = TcpStream::connect(&host)?;
+
+    let mut req = format!(
+        "{} {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: rustscript\r\nConnection: close\r\n",
+        method, path, host
+    );
+    if let Some(body) = body {
+        req.push_str("Content-Type: text/plain; charset=utf-8\r\n");
+        req.push_str(&format!("Content-Length: {}\r\n", body.len()));
+    }
+    req.push_str("\r\n");
+    req.push_str(body.unwrap_or(""));
+    stream.write_all(req.as_bytes())?;
+
+    // The server closes the connection once the response is sent
+    let mut res = vec![];
+    stream.read_to_end(&mut res)?;
+    parse_response(&res)
+}
+
+// http://host[:port][/path] to host:port and path
+fn parse_url(url: &str) -> Result<(String, String)> {
+    let Some(rest) = url.strip_prefix("http://") else {
+        return Err(Error::msg(format!(
+            "Only http:// urls are supported, got '{}'",
+            url
+        )));
+    };
+
+    let (host, path) = match rest.find('/') {
+        Some(idx) => rest.split_at(idx),
+        None => (rest, "/"),
+    };
+
+    if host.is_empty() {
+        return Err(Error::msg(format!("No host in url '{}'", url)));
+    }
+
+    let host = if host.contains(':') {
+        host.to_string()
+    } else {
+        format!("{}:80", host)
+    };
+    Ok((host, path.to_string()))
+}
+
+fn parse_response(res: &[u8]) -> Result<HttpResponse> {
+    let malformed = || Error::msg("Malformed HTTP response");
+
+    let head_end = res
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .ok_or_else(malformed)?;
+    let head = String::from_utf8_lossy(&res[..head_end]);
+    let mut body = &res[head_end + 4..];
+
+    let mut lines = head.split("\r\n");
+    let status = lines
+        .next()
+        .and_then(|line| line.split(' ').nth(1))
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(malformed)?;
+
+    let mut chunked = false;
+    for line in lines {
+        let Some((name, val)) = line.split_once(':') else {
+            continue;
+        };
+        let val = val.trim();
+        if name.eq_ignore_ascii_case("content-length") {
+            let len: usize = val.parse().map_err(|_| malformed())?;
+            body = body.get(..len).ok_or_else(malformed)?;
+        } else if name.eq_ignore_ascii_case("transfer-encoding") {
+            chunked = val.eq_ignore_ascii_case("chunked");
+        }
+    }
+
+    let body = if chunked {
+        dechunk(body).ok_or_else(malformed)?
+    } else {
+        body.to_vec()
+    };
+
+    Ok(HttpResponse {
+        status,
+        body: String::from_utf8_lossy(&body).into_owned(),
+    })
+}
+
+// Join the chunks of a chunked body: each is its length in hex, then the data, ending with a chunk of length 0
+fn dechunk(mut body: &[u8]) -> Option<Vec<u8>> {
+    let mut out = vec![];
+    loop {
+        let line_end = body.windows(2).position(|w| w == b"\r\n")?;
+        let size = std::str::from_utf8(&body[..line_end]).ok()?;
+        // chunk extensions after ';' are ignored
+        let size = size.split(';').next()?.trim();
+        let size = usize::from_str_radix(size, 16).ok()?;
+        body = &body[line_end + 2..];
+
+        if size == 0 {
+            return Some(out);
+        }
+
+        out.extend_from_slice(body.get(..size)?);
+        body = body.get(size + 2..)?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::TcpListener, thread};
+
+    use super::*;
+
+    #[test]
+    fn test_parse_url() -> Result<()> {
+        assert_eq!(
+            parse_url("http://example.com")?,
+            ("example.com:80".to_string(), "/".to_string())
+        );
+        assert_eq!(
+            parse_url("http://127.0.0.1:8080/a/b?c=1")?,
+            ("127.0.0.1:8080".to_string(), "/a/b?c=1".to_string())
+        );
+        assert!(parse_url("https://example.com").is_err());
+        assert!(parse_url("http:///path").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_response() -> Result<()> {
+        let res = parse_response(b"HTTP/1.1 404 Not Found\r\nContent-Length: 4\r\n\r\nnopeextra")?;
+        assert_eq!(
+            res,
+            HttpResponse {
+                status: 404,
+                body: "nope".to_string()
+            }
+        );
+
+        let res = parse_response(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n7;x=y\r\n, world\r\n0\r\n\r\n",
+        )?;
+        assert_eq!(res.body, "hello, world");
+
+        // without a length the body is the rest of the response
+        assert_eq!(parse_response(b"HTTP/1.0 200 OK\r\n\r\nall")?.body, "all");
+
+        assert!(parse_response(b"HTTP/1.1 200 OK\r\n").is_err());
+        assert!(parse_response(b"HTTP/1.1 OK\r\n\r\n").is_err());
+        assert!(parse_response(b"HTTP/1.1 200 OK\r\nContent-Length: 9\r\n\r\nshort").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_request() -> Result<()> {
+        let server = TcpListener::bind("127.0.0.1:0")?;
+        let url = format!("http://{}/echo", server.local_addr()?);
+        let handle = thread::spawn(move || -> Result<String> {
+            let (mut stream, _) = server.accept()?;
+            let mut buf = [0; 1024];
+            let n = stream.read(&mut buf)?;
+            stream.write_all(b"HTTP/1.1 201 Created\r\nContent-Length: 2\r\n\r\nok")?;
+            Ok(String::from_utf8_lossy(&buf[..n]).into_owned())
+        });
+
+        let res = request("POST", &url, Some("data"))?;
+        assert_eq!(res.status, 201);
+        assert_eq!(res.body, "ok");
+
+        let req = handle.join().unwrap()?;
+        assert!(req.starts_with("POST /echo HTTP/1.1\r\n"));
+        assert!(req.contains("Content-Length: 4\r\n"));
+        assert!(req.ends_with("\r\n\r\ndata"));
+        Ok(())
+    }
+}
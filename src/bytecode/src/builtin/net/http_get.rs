@@ -0,0 +1,24 @@
+use std::rc::Weak;
+
+use anyhow::Result;
+
+use super::http::{request, HttpResponse};
+use crate::{FnType, Value, W};
+
+pub const HTTP_GET_SYM: &str = "http_get";
+
+pub fn http_get() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: HTTP_GET_SYM.into(),
+        prms: vec!["url".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// GET the url, like "http://127.0.0.1:8080/path", and wait for the response. A response with an error status
+/// is returned like any other.
+pub fn http_get_impl(url: &str) -> Result<HttpResponse> {
+    request("GET", url, None)
+}
@@ -0,0 +1,24 @@
+use std::rc::Weak;
+
+use anyhow::Result;
+
+use super::http::{request, HttpResponse};
+use crate::{FnType, Value, W};
+
+pub const HTTP_POST_SYM: &str = "http_post";
+
+pub fn http_post() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: HTTP_POST_SYM.into(),
+        prms: vec!["url".into(), "body".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// POST body to the url as text and wait for the response. A response with an error status is returned like
+/// any other.
+pub fn http_post_impl(url: &str, body: &str) -> Result<HttpResponse> {
+    request("POST", url, Some(body))
+}
@@ -0,0 +1,40 @@
+pub use close::*;
+pub use http::{HttpResponse, HTTP_RESPONSE_STRUCT};
+pub use http_get::*;
+pub use http_post::*;
+pub use tcp_accept::*;
+pub use tcp_connect::*;
+pub use tcp_listen::*;
+pub use tcp_recv::*;
+pub use tcp_send::*;
+pub use udp_bind::*;
+pub use udp_recv::*;
+pub use udp_send_to::*;
+
+mod close;
+mod http;
+mod http_get;
+mod http_post;
+mod tcp_accept;
+mod tcp_connect;
+mod tcp_listen;
+mod tcp_recv;
+mod tcp_send;
+mod udp_bind;
+mod udp_recv;
+mod udp_send_to;
+
+/// The builtins that use the network, which a program can only call if the runtime allows it.
+pub const NET_SYMS: [&str; 11] = [
+    TCP_CONNECT_SYM,
+    TCP_LISTEN_SYM,
+    TCP_ACCEPT_SYM,
+    TCP_SEND_SYM,
+    TCP_RECV_SYM,
+    UDP_BIND_SYM,
+    UDP_SEND_TO_SYM,
+    UDP_RECV_SYM,
+    CLOSE_SYM,
+    HTTP_GET_SYM,
+    HTTP_POST_SYM,
+];
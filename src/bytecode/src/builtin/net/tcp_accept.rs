@@ -0,0 +1,32 @@
+use std::rc::Weak;
+
+use anyhow::Result;
+
+use crate::{ByteCodeError, SocketKind};
+use crate::{FnType, Value, W};
+
+pub const TCP_ACCEPT_SYM: &str = "tcp_accept";
+
+pub fn tcp_accept() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: TCP_ACCEPT_SYM.into(),
+        prms: vec!["listener".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// Wait for the next connection to a listener.
+pub fn tcp_accept_impl(listener: &SocketKind) -> Result<SocketKind> {
+    let SocketKind::Listener(listener) = listener else {
+        return Err(ByteCodeError::BadType {
+            expected: "TCP listener".to_string(),
+            found: listener.name().to_string(),
+        }
+        .into());
+    };
+
+    let (stream, _) = listener.accept()?;
+    Ok(SocketKind::Stream(stream))
+}
@@ -0,0 +1,25 @@
+use std::rc::Weak;
+
+use std::net::TcpStream;
+
+use anyhow::Result;
+
+use crate::SocketKind;
+use crate::{FnType, Value, W};
+
+pub const TCP_CONNECT_SYM: &str = "tcp_connect";
+
+pub fn tcp_connect() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: TCP_CONNECT_SYM.into(),
+        prms: vec!["addr".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// Connect to a TCP server at addr, like "127.0.0.1:8080".
+pub fn tcp_connect_impl(addr: &str) -> Result<SocketKind> {
+    Ok(SocketKind::Stream(TcpStream::connect(addr)?))
+}
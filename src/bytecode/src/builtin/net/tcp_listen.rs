@@ -0,0 +1,25 @@
+use std::rc::Weak;
+
+use std::net::TcpListener;
+
+use anyhow::Result;
+
+use crate::SocketKind;
+use crate::{FnType, Value, W};
+
+pub const TCP_LISTEN_SYM: &str = "tcp_listen";
+
+pub fn tcp_listen() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: TCP_LISTEN_SYM.into(),
+        prms: vec!["addr".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// Listen for TCP connections on addr, like "127.0.0.1:8080".
+pub fn tcp_listen_impl(addr: &str) -> Result<SocketKind> {
+    Ok(SocketKind::Listener(TcpListener::bind(addr)?))
+}
@@ -0,0 +1,40 @@
+use std::rc::Weak;
+
+use std::io::Read;
+
+use anyhow::Result;
+
+use crate::{ByteCodeError, SocketKind};
+use crate::{FnType, Value, W};
+
+pub const TCP_RECV_SYM: &str = "tcp_recv";
+
+pub fn tcp_recv() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: TCP_RECV_SYM.into(),
+        prms: vec!["conn".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// The most bytes one recv reads.
+pub const RECV_BUF_SIZE: usize = 4096;
+
+/// Wait for data on a connection and return what arrived, up to RECV_BUF_SIZE bytes. Returns "" once the other
+/// end has closed the connection.
+pub fn tcp_recv_impl(conn: &SocketKind) -> Result<String> {
+    let SocketKind::Stream(stream) = conn else {
+        return Err(ByteCodeError::BadType {
+            expected: "TCP connection".to_string(),
+            found: conn.name().to_string(),
+        }
+        .into());
+    };
+
+    let mut stream = stream;
+    let mut buf = [0; RECV_BUF_SIZE];
+    let n = stream.read(&mut buf)?;
+    Ok(String::from_utf8_lossy(&buf[..n]).into_owned())
+}
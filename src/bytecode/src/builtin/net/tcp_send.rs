@@ -0,0 +1,35 @@
+use std::rc::Weak;
+
+use std::io::Write;
+
+use anyhow::Result;
+
+use crate::{ByteCodeError, SocketKind};
+use crate::{FnType, Value, W};
+
+pub const TCP_SEND_SYM: &str = "tcp_send";
+
+pub fn tcp_send() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: TCP_SEND_SYM.into(),
+        prms: vec!["conn".into(), "data".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// Write all of data to a connection, returning how many bytes were sent.
+pub fn tcp_send_impl(conn: &SocketKind, data: &str) -> Result<usize> {
+    let SocketKind::Stream(stream) = conn else {
+        return Err(ByteCodeError::BadType {
+            expected: "TCP connection".to_string(),
+            found: conn.name().to_string(),
+        }
+        .into());
+    };
+
+    let mut stream = stream;
+    stream.write_all(data.as_bytes())?;
+    Ok(data.len())
+}
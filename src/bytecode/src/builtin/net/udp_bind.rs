@@ -0,0 +1,25 @@
+use std::rc::Weak;
+
+use std::net::UdpSocket;
+
+use anyhow::Result;
+
+use crate::SocketKind;
+use crate::{FnType, Value, W};
+
+pub const UDP_BIND_SYM: &str = "udp_bind";
+
+pub fn udp_bind() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: UDP_BIND_SYM.into(),
+        prms: vec!["addr".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// Open a UDP socket on addr, like "127.0.0.1:8080".
+pub fn udp_bind_impl(addr: &str) -> Result<SocketKind> {
+    Ok(SocketKind::Udp(UdpSocket::bind(addr)?))
+}
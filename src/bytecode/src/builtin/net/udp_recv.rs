@@ -0,0 +1,33 @@
+use std::rc::Weak;
+
+use anyhow::Result;
+
+use crate::{ByteCodeError, SocketKind};
+use crate::{FnType, Value, W};
+
+pub const UDP_RECV_SYM: &str = "udp_recv";
+
+pub fn udp_recv() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: UDP_RECV_SYM.into(),
+        prms: vec!["sock".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// Wait for a datagram and return it, cut to RECV_BUF_SIZE bytes.
+pub fn udp_recv_impl(sock: &SocketKind) -> Result<String> {
+    let SocketKind::Udp(sock) = sock else {
+        return Err(ByteCodeError::BadType {
+            expected: "UDP socket".to_string(),
+            found: sock.name().to_string(),
+        }
+        .into());
+    };
+
+    let mut buf = [0; super::RECV_BUF_SIZE];
+    let (n, _) = sock.recv_from(&mut buf)?;
+    Ok(String::from_utf8_lossy(&buf[..n]).into_owned())
+}
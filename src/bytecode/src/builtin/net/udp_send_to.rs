@@ -0,0 +1,31 @@
+use std::rc::Weak;
+
+use anyhow::Result;
+
+use crate::{ByteCodeError, SocketKind};
+use crate::{FnType, Value, W};
+
+pub const UDP_SEND_TO_SYM: &str = "udp_send_to";
+
+pub fn udp_send_to() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: UDP_SEND_TO_SYM.into(),
+        prms: vec!["sock".into(), "addr".into(), "data".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// Send data in one datagram to addr, returning how many bytes were sent.
+pub fn udp_send_to_impl(sock: &SocketKind, addr: &str, data: &str) -> Result<usize> {
+    let SocketKind::Udp(sock) = sock else {
+        return Err(ByteCodeError::BadType {
+            expected: "UDP socket".to_string(),
+            found: sock.name().to_string(),
+        }
+        .into());
+    };
+
+    Ok(sock.send_to(data.as_bytes(), addr)?)
+}
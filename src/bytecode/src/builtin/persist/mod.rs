@@ -0,0 +1,19 @@
+pub use persist_contains::*;
+pub use persist_get::*;
+pub use persist_insert::*;
+pub use persist_len::*;
+pub use persist_push::*;
+pub use persist_remove::*;
+pub use persist_set::*;
+pub use pmap::*;
+pub use pvec::*;
+
+mod persist_contains;
+mod persist_get;
+mod persist_insert;
+mod persist_len;
+mod persist_push;
+mod persist_remove;
+mod persist_set;
+mod pmap;
+mod pvec;
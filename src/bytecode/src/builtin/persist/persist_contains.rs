@@ -0,0 +1,23 @@
+use std::rc::Weak;
+
+use anyhow::Result;
+
+use crate::{FnType, PMap, Value, W};
+
+pub const PERSIST_CONTAINS_SYM: &str = "persist_contains";
+
+pub fn persist_contains() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: PERSIST_CONTAINS_SYM.into(),
+        prms: vec!["m".into(), "key".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// If the key is in the map.
+pub fn persist_contains_impl(m: &Value, key: &Value) -> Result<Value> {
+    let m: PMap = m.clone().try_into()?;
+    Ok(m.get(key)?.is_some().into())
+}
@@ -0,0 +1,39 @@
+use std::rc::Weak;
+
+use anyhow::Result;
+
+use crate::{type_of, ByteCodeError, FnType, Value, W};
+
+pub const PERSIST_GET_SYM: &str = "persist_get";
+
+pub fn persist_get() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: PERSIST_GET_SYM.into(),
+        prms: vec!["c".into(), "key".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// The element of a persistent vector at an index, or the value of a key in a persistent map.
+///
+/// # Errors
+///
+/// If the index is out of bounds or the key isn't in the map.
+pub fn persist_get_impl(c: &Value, key: &Value) -> Result<Value> {
+    match c {
+        Value::PVec(v) => {
+            let idx: i64 = key.clone().try_into()?;
+            v.get(idx)
+        }
+        Value::PMap(m) => m
+            .get(key)?
+            .ok_or_else(|| ByteCodeError::KeyNotFound(key.to_string()).into()),
+        _ => Err(ByteCodeError::BadType {
+            expected: "PVec or PMap".to_string(),
+            found: type_of(c).to_string(),
+        }
+        .into()),
+    }
+}
@@ -0,0 +1,23 @@
+use std::rc::Weak;
+
+use anyhow::Result;
+
+use crate::{FnType, PMap, Value, W};
+
+pub const PERSIST_INSERT_SYM: &str = "persist_insert";
+
+pub fn persist_insert() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: PERSIST_INSERT_SYM.into(),
+        prms: vec!["m".into(), "key".into(), "val".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// A map with the key set to val. m is left as it was.
+pub fn persist_insert_impl(m: &Value, key: &Value, val: &Value) -> Result<Value> {
+    let m: PMap = m.clone().try_into()?;
+    Ok(m.insert(key.clone(), val.clone())?.into())
+}
@@ -0,0 +1,33 @@
+use std::rc::Weak;
+
+use anyhow::Result;
+
+use crate::{type_of, ByteCodeError, FnType, Value, W};
+
+pub const PERSIST_LEN_SYM: &str = "persist_len";
+
+pub fn persist_len() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: PERSIST_LEN_SYM.into(),
+        prms: vec!["c".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// The number of elements of a persistent vector or entries of a persistent map.
+pub fn persist_len_impl(c: &Value) -> Result<Value> {
+    let len = match c {
+        Value::PVec(v) => v.len(),
+        Value::PMap(m) => m.len(),
+        _ => {
+            return Err(ByteCodeError::BadType {
+                expected: "PVec or PMap".to_string(),
+                found: type_of(c).to_string(),
+            }
+            .into())
+        }
+    };
+    Ok(Value::Int(len as i64))
+}
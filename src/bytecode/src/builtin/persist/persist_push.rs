@@ -0,0 +1,24 @@
+use std::rc::Weak;
+
+use anyhow::Result;
+
+use crate::{FnType, PVec, Value, W};
+
+pub const PERSIST_PUSH_SYM: &str = "persist_push";
+
+pub fn persist_push() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: PERSIST_PUSH_SYM.into(),
+        prms: vec!["v".into(), "x".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// A vector with x after the elements of v. v is left as it was, and shares all but the last few nodes with the
+/// new vector.
+pub fn persist_push_impl(v: &Value, x: &Value) -> Result<Value> {
+    let v: PVec = v.clone().try_into()?;
+    Ok(v.push(x.clone()).into())
+}
@@ -0,0 +1,23 @@
+use std::rc::Weak;
+
+use anyhow::Result;
+
+use crate::{FnType, PMap, Value, W};
+
+pub const PERSIST_REMOVE_SYM: &str = "persist_remove";
+
+pub fn persist_remove() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: PERSIST_REMOVE_SYM.into(),
+        prms: vec!["m".into(), "key".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// A map without the key. m is left as it was.
+pub fn persist_remove_impl(m: &Value, key: &Value) -> Result<Value> {
+    let m: PMap = m.clone().try_into()?;
+    Ok(m.remove(key)?.into())
+}
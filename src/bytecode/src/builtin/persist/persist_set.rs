@@ -0,0 +1,24 @@
+use std::rc::Weak;
+
+use anyhow::Result;
+
+use crate::{FnType, PVec, Value, W};
+
+pub const PERSIST_SET_SYM: &str = "persist_set";
+
+pub fn persist_set() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: PERSIST_SET_SYM.into(),
+        prms: vec!["v".into(), "idx".into(), "x".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// A vector with the element at idx replaced by x. v is left as it was.
+pub fn persist_set_impl(v: &Value, idx: &Value, x: &Value) -> Result<Value> {
+    let v: PVec = v.clone().try_into()?;
+    let idx: i64 = idx.clone().try_into()?;
+    Ok(v.set(idx, x.clone())?.into())
+}
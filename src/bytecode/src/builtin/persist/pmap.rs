@@ -0,0 +1,20 @@
+use std::rc::Weak;
+
+use crate::{FnType, PMap, Value, W};
+
+pub const PMAP_SYM: &str = "pmap";
+
+pub fn pmap() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: PMAP_SYM.into(),
+        prms: vec![].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// An empty persistent map.
+pub fn pmap_impl() -> Value {
+    PMap::new().into()
+}
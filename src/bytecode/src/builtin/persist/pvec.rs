@@ -0,0 +1,20 @@
+use std::rc::Weak;
+
+use crate::{FnType, PVec, Value, W};
+
+pub const PVEC_SYM: &str = "pvec";
+
+pub fn pvec() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: PVEC_SYM.into(),
+        prms: vec![].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// An empty persistent vector.
+pub fn pvec_impl() -> Value {
+    PVec::new().into()
+}
@@ -0,0 +1,22 @@
+use std::rc::Weak;
+
+use crate::{Array, FnType, Slice, Value, W};
+
+pub const ARGS_SYM: &str = "args";
+
+pub fn args() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: ARGS_SYM.into(),
+        prms: vec![].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// args(): the arguments the program was run with, as a slice of strings. Each call gives a new slice, so
+/// changing one doesn't change what the next call returns.
+pub fn args_impl(main_args: &[Value]) -> Value {
+    let len = main_args.len();
+    Value::Slice(Slice::new(Array::new(main_args.to_vec()), 0, len))
+}
@@ -0,0 +1,28 @@
+use std::rc::Weak;
+
+use anyhow::Result;
+
+use crate::{ByteCodeError, FnType, Value, W};
+
+pub const ASSERT_SYM: &str = "assert";
+
+pub fn assert() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: ASSERT_SYM.into(),
+        prms: vec!["cond".into(), "msg".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// assert(cond, msg): stop the program with msg if cond is false. This is how a #[test] fn fails.
+pub fn assert_impl(cond: &Value, msg: &Value) -> Result<()> {
+    let cond: bool = cond.clone().try_into()?;
+    let msg: String = msg.clone().try_into()?;
+    if cond {
+        Ok(())
+    } else {
+        Err(ByteCodeError::AssertionFailed(msg).into())
+    }
+}
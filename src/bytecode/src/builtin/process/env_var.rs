@@ -0,0 +1,25 @@
+use std::rc::Weak;
+
+use anyhow::Result;
+
+use crate::{FnType, Value, W};
+
+pub const ENV_VAR_SYM: &str = "env_var";
+
+pub fn env_var() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: ENV_VAR_SYM.into(),
+        prms: vec!["name".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// env_var(name): the value of the environment variable of the process, or an empty string if it isn't set or
+/// isn't valid unicode, like an unset variable in a shell.
+pub fn env_var_impl(name: &Value) -> Result<Value> {
+    let name: String = name.clone().try_into()?;
+    let val = std::env::var(name).unwrap_or_default();
+    Ok(Value::String(val.into()))
+}
@@ -0,0 +1,9 @@
+pub use args::*;
+pub use assert::*;
+pub use env_var::*;
+pub use run_command::*;
+
+mod args;
+mod assert;
+mod env_var;
+mod run_command;
@@ -0,0 +1,188 @@
+use std::{
+    io::Read,
+    process::{Command, Stdio},
+    rc::Weak,
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+
+use crate::{ByteCodeError, FnType, Struct, Value, W};
+
+pub const RUN_COMMAND_SYM: &str = "run_command";
+
+/// The name of the struct run_command gives in its Ok, with the fields `code: int`, `stdout: string` and
+/// `stderr: string`.
+pub const COMMAND_OUTPUT_STRUCT: &str = "CommandOutput";
+
+// How often a command with a timeout is checked on
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+pub fn run_command() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: RUN_COMMAND_SYM.into(),
+        prms: vec!["cmd".into(), "args".into(), "timeout_ms".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// The exit code and output of a command. Made into a CommandOutput struct on the thread of the runtime, since
+/// values can't be sent between threads.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandOutput {
+    /// -1 if the command was killed, by the timeout or a signal.
+    pub code: i64,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl From<CommandOutput> for Value {
+    fn from(output: CommandOutput) -> Self {
+        Value::Struct(Struct::new(
+            COMMAND_OUTPUT_STRUCT.into(),
+            vec![
+                ("code".into(), Value::Int(output.code)),
+                ("stdout".into(), Value::String(output.stdout.into())),
+                ("stderr".into(), Value::String(output.stderr.into())),
+            ],
+        ))
+    }
+}
+
+/// The args of run_command, from an array or slice of strings.
+pub fn command_args(args: &Value) -> Result<Vec<String>> {
+    let vals = match args {
+        Value::Array(arr) => arr.borrow().clone(),
+        Value::Slice(slice) => slice.to_vec(),
+        _ => {
+            return Err(ByteCodeError::TypeMismatch {
+                expected: "Array".to_string(),
+                found: format!("{:?}", args),
+            }
+            .into())
+        }
+    };
+
+    let args: Result<Vec<String>, _> = vals.into_iter().map(String::try_from).collect();
+    Ok(args?)
+}
+
+/// The timeout of run_command, from a number of ms. A timeout of 0 or less waits for as long as the command runs.
+pub fn command_timeout(timeout_ms: &Value) -> Result<Option<Duration>> {
+    let timeout_ms: i64 = timeout_ms.clone().try_into()?;
+    Ok((timeout_ms > 0).then(|| Duration::from_millis(timeout_ms as u64)))
+}
+
+/// Run the program cmd with args and wait for it to exit, collecting what it writes. The command is killed if it
+/// runs for longer than the timeout, with what it wrote until then returned. Without a timeout it can run for as
+/// long as it takes.
+pub fn run_command_impl(
+    cmd: &str,
+    args: &[String],
+    timeout: Option<Duration>,
+) -> Result<CommandOutput> {
+    let mut child = Command::new(cmd)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run '{}'", cmd))?;
+
+    // Read both pipes as the command runs, so it never blocks on a full pipe
+    let stdout = read_pipe(child.stdout.take());
+    let stderr = read_pipe(child.stderr.take());
+
+    let status = match timeout {
+        None => child.wait()?,
+        Some(timeout) => {
+            let start = Instant::now();
+            loop {
+                if let Some(status) = child.try_wait()? {
+                    break status;
+                }
+
+                if start.elapsed() >= timeout {
+                    child.kill()?;
+                    break child.wait()?;
+                }
+                thread::sleep(POLL_INTERVAL);
+            }
+        }
+    };
+
+    Ok(CommandOutput {
+        code: status.code().map_or(-1, i64::from),
+        stdout: stdout.join().expect("reader thread should not panic"),
+        stderr: stderr.join().expect("reader thread should not panic"),
+    })
+}
+
+fn read_pipe(pipe: Option<impl Read + Send + 'static>) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut buf = vec![];
+        if let Some(mut pipe) = pipe {
+            // A read error ends the output early, like the command exiting
+            let _ = pipe.read_to_end(&mut buf);
+        }
+        String::from_utf8_lossy(&buf).into_owned()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_command() -> Result<()> {
+        let out = run_command_impl(
+            "sh",
+            &[
+                "-c".to_string(),
+                "echo out; echo err >&2; exit 3".to_string(),
+            ],
+            None,
+        )?;
+        assert_eq!(
+            out,
+            CommandOutput {
+                code: 3,
+                stdout: "out\n".to_string(),
+                stderr: "err\n".to_string(),
+            }
+        );
+
+        // Killed when the timeout is up, with what it wrote before then
+        let start = Instant::now();
+        let out = run_command_impl(
+            "sh",
+            &["-c".to_string(), "echo started; exec sleep 5".to_string()],
+            Some(Duration::from_millis(100)),
+        )?;
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert_eq!(out.code, -1);
+        assert_eq!(out.stdout, "started\n");
+
+        assert!(run_command_impl("no-such-command-rustscript", &[], None).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_command_args() -> Result<()> {
+        let arr = Value::Array(crate::Array::new(vec!["-l".into(), "/".into()]));
+        assert_eq!(command_args(&arr)?, vec!["-l", "/"]);
+        assert!(command_args(&Value::Array(crate::Array::new(vec![Value::Int(1)]))).is_err());
+        assert!(command_args(&"-l".into()).is_err());
+
+        assert_eq!(
+            command_timeout(&Value::Int(250))?,
+            Some(Duration::from_millis(250))
+        );
+        assert_eq!(command_timeout(&Value::Int(0))?, None);
+        assert_eq!(command_timeout(&Value::Int(-1))?, None);
+        Ok(())
+    }
+}
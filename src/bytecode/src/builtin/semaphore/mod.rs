@@ -1,5 +1,7 @@
+pub use mutex::*;
 pub use sem_create::*;
 pub use sem_set::*;
 
+mod mutex;
 mod sem_create;
 mod sem_set;
@@ -0,0 +1,20 @@
+use std::rc::Weak;
+
+use crate::{FnType, Semaphore, Value, W};
+
+pub const MUTEX_SYM: &str = "mutex";
+
+pub fn mutex() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: MUTEX_SYM.into(),
+        prms: vec![].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// A mutex is a semaphore that starts with one permit, held with `lock` instead of `wait` and `post`.
+pub fn mutex_impl() -> Value {
+    Semaphore::new(1).into()
+}
@@ -8,7 +8,7 @@ pub fn sem_create() -> Value {
     Value::Closure {
         fn_type: FnType::Builtin,
         sym: SEM_CREATE_SYM.into(),
-        prms: vec![],
+        prms: vec![].into(),
         addr: 0,
         env: W(Weak::new()),
     }
@@ -10,7 +10,7 @@ pub fn sem_set() -> Value {
     Value::Closure {
         fn_type: FnType::Builtin,
         sym: SEM_SET_SYM.into(),
-        prms: vec![],
+        prms: vec![].into(),
         addr: 2,
         env: W(Weak::new()),
     }
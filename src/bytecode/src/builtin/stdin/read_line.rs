@@ -10,7 +10,7 @@ pub fn read_line() -> Value {
     Value::Closure {
         fn_type: FnType::Builtin,
         sym: READ_LINE_SYM.into(),
-        prms: vec![],
+        prms: vec![].into(),
         addr: 0,
         env: W(Weak::new()),
     }
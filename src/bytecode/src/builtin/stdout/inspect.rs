@@ -0,0 +1,273 @@
+use std::rc::{Rc, Weak};
+
+use anyhow::Result;
+
+use crate::{FnType, Value, W};
+
+pub const INSPECT_SYM: &str = "inspect";
+pub const INSPECT_DEPTH_SYM: &str = "inspect_depth";
+
+/// How deep inspect goes into nested arrays and structs before it leaves out what is inside them.
+pub const DEFAULT_INSPECT_DEPTH: usize = 8;
+
+pub fn inspect() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: INSPECT_SYM.into(),
+        prms: vec!["value".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+pub fn inspect_depth() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: INSPECT_DEPTH_SYM.into(),
+        prms: vec!["value".into(), "max_depth".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// inspect_depth(value, max_depth): like inspect, with the depth to go to instead of the default. A depth of 0 or
+/// less only shows the outermost value.
+pub fn inspect_depth_impl(value: &Value, max_depth: &Value) -> Result<Value> {
+    let max_depth: i64 = max_depth.clone().try_into()?;
+    Ok(inspect_impl(value, max_depth.max(0) as usize).into())
+}
+
+/// Render the value for debugging: every element of an array and field of a struct on its own line, indented by
+/// how deeply it is nested, with strings quoted so they can be told apart from the other values.
+///
+/// Arrays and structs nested deeper than `max_depth` are shown as `[...]` and `Name { ... }`. An array or struct
+/// that holds itself is shown as `<cycle>` where it appears inside itself, instead of forever.
+pub fn inspect_impl(value: &Value, max_depth: usize) -> String {
+    let mut inspector = Inspector {
+        out: String::new(),
+        max_depth,
+        ancestors: vec![],
+    };
+    inspector.value(value, 0);
+    inspector.out
+}
+
+struct Inspector {
+    out: String,
+    max_depth: usize,
+    // The storage of the arrays, structs and maps being rendered, outermost first
+    ancestors: Vec<*const ()>,
+}
+
+impl Inspector {
+    fn value(&mut self, value: &Value, depth: usize) {
+        match value {
+            Value::Unitialized => self.out.push_str("uninitialized"),
+            Value::Unit => self.out.push_str("()"),
+            Value::Int(i) => self.out.push_str(&i.to_string()),
+            // Debug keeps the .0 so floats aren't mistaken for ints
+            Value::Float(f) => self.out.push_str(&format!("{:?}", f)),
+            Value::Bool(b) => self.out.push_str(&b.to_string()),
+            Value::String(s) => self.out.push_str(&format!("{:?}", s)),
+            Value::Char(c) => self.out.push_str(&format!("{:?}", c)),
+            Value::Bytes(_) => self.out.push_str(&value.to_string()),
+            Value::Semaphore(_) => self.out.push_str("semaphore"),
+            Value::Channel(_) => self.out.push_str("channel"),
+            Value::Socket(_) => self.out.push_str("socket"),
+            Value::Closure {
+                fn_type,
+                sym,
+                prms,
+                addr,
+                ..
+            } => {
+                let prms = prms.join(", ");
+                let closure = match fn_type {
+                    FnType::User => format!("fn {}({}) at {}", sym, prms, addr),
+                    FnType::Builtin => format!("builtin {}({})", sym, prms),
+                };
+                self.out.push_str(&closure);
+            }
+            Value::Array(arr) => {
+                let elems = arr.borrow().iter().map(|v| (None, v.clone())).collect();
+                self.nested(Rc::as_ptr(arr) as *const (), "[", "]", elems, depth);
+            }
+            Value::Slice(slice) => {
+                let elems = slice.to_vec().into_iter().map(|v| (None, v)).collect();
+                self.nested(Rc::as_ptr(&slice.arr) as *const (), "[", "]", elems, depth);
+            }
+            Value::Struct(s) => {
+                let fields = s
+                    .fields()
+                    .into_iter()
+                    .map(|(field, v)| (Some(field), v))
+                    .collect();
+                let open = format!("{} {{", s.name);
+                self.nested(s.as_ptr(), &open, "}", fields, depth);
+            }
+            // Variants can't be changed to hold themselves, so they are shown on one line like Some(1)
+            Value::Variant(v) => {
+                self.out.push_str(&v.name);
+                if v.payload.is_empty() {
+                    return;
+                }
+
+                self.out.push('(');
+                for (i, val) in v.payload.iter().enumerate() {
+                    if i > 0 {
+                        self.out.push_str(", ");
+                    }
+                    self.value(val, depth);
+                }
+                self.out.push(')');
+            }
+            // Tuples can't be changed to hold themselves either, so they are shown on one line like (1, true)
+            Value::Tuple(t) => {
+                self.out.push('(');
+                for (i, val) in t.iter().enumerate() {
+                    if i > 0 {
+                        self.out.push_str(", ");
+                    }
+                    self.value(val, depth);
+                }
+                if t.len() == 1 {
+                    self.out.push(',');
+                }
+                self.out.push(')');
+            }
+            Value::Map(m) => {
+                let entries = m
+                    .entries()
+                    .into_iter()
+                    .map(|(key, v)| (Some(inspect_impl(&key, 0)), v))
+                    .collect();
+                self.nested(m.as_ptr(), "#{", "}", entries, depth);
+            }
+            // Persistent collections can't be changed to hold themselves, so only the arrays and structs inside
+            // them need checking for cycles
+            Value::PVec(pvec) => {
+                let elems = pvec.to_vec().into_iter().map(|v| (None, v)).collect();
+                self.nested(pvec as *const _ as *const (), "[", "]", elems, depth);
+            }
+            Value::PMap(pmap) => {
+                let entries = pmap
+                    .entries()
+                    .into_iter()
+                    .map(|(key, v)| (Some(inspect_impl(&key, 0)), v))
+                    .collect();
+                self.nested(pmap as *const _ as *const (), "{", "}", entries, depth);
+            }
+        }
+    }
+
+    // The elements or fields of an array or struct, each on its own line and labelled with its field if it has one
+    fn nested(
+        &mut self,
+        ptr: *const (),
+        open: &str,
+        close: &str,
+        items: Vec<(Option<String>, Value)>,
+        depth: usize,
+    ) {
+        if self.ancestors.contains(&ptr) {
+            self.out.push_str("<cycle>");
+            return;
+        }
+
+        self.out.push_str(open);
+        if items.is_empty() {
+            self.out.push_str(close);
+            return;
+        }
+        if depth >= self.max_depth {
+            let elided = if close == "]" { "..." } else { " ... " };
+            self.out.push_str(elided);
+            self.out.push_str(close);
+            return;
+        }
+
+        self.ancestors.push(ptr);
+        self.out.push('\n');
+        for (label, val) in items.iter() {
+            self.indent(depth + 1);
+            if let Some(label) = label {
+                self.out.push_str(&format!("{}: ", label));
+            }
+            self.value(val, depth + 1);
+            self.out.push_str(",\n");
+        }
+        self.indent(depth);
+        self.out.push_str(close);
+        self.ancestors.pop();
+    }
+
+    fn indent(&mut self, depth: usize) {
+        self.out.push_str(&"  ".repeat(depth));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Array, Struct};
+
+    use super::*;
+
+    fn point(x: Value, y: Value) -> Value {
+        Struct::new("Point".into(), vec![("x".into(), x), ("y".into(), y)]).into()
+    }
+
+    #[test]
+    fn test_inspect() {
+        assert_eq!(inspect_impl(&Value::Float(1.0), 8), "1.0");
+        assert_eq!(inspect_impl(&"a\"b\n".into(), 8), r#""a\"b\n""#);
+        assert_eq!(inspect_impl(&Value::Array(Array::new(vec![])), 8), "[]");
+
+        let value: Value = vec![
+            Value::Int(1),
+            point(Value::Int(2), vec!["three".into()].into()),
+        ]
+        .into();
+        assert_eq!(
+            inspect_impl(&value, 8),
+            "[\n  1,\n  Point {\n    x: 2,\n    y: [\n      \"three\",\n    ],\n  },\n]"
+        );
+
+        // Past the max depth only the outside of arrays and structs is shown
+        assert_eq!(inspect_impl(&value, 1), "[\n  1,\n  Point { ... },\n]");
+        assert_eq!(inspect_impl(&value, 0), "[...]");
+
+        let ok: Value = crate::Variant::ok("done").into();
+        assert_eq!(inspect_impl(&ok, 8), r#"Ok("done")"#);
+        let pair: Value = crate::Tuple::new(vec![Value::Int(1), "a".into()]).into();
+        assert_eq!(inspect_impl(&pair, 8), r#"(1, "a")"#);
+
+        assert_eq!(
+            inspect_impl(&crate::builtin::inspect(), 8),
+            "builtin inspect(value)"
+        );
+    }
+
+    #[test]
+    fn test_inspect_cycle() {
+        let arr = Array::new(vec![Value::Int(1)]);
+        let s = point(Value::Array(arr.clone()), Value::Unit);
+        arr.borrow_mut().push(s.clone());
+
+        assert_eq!(
+            inspect_impl(&s, 8),
+            "Point {\n  x: [\n    1,\n    <cycle>,\n  ],\n  y: (),\n}"
+        );
+
+        // The same array twice side by side isn't a cycle
+        let inner = Value::Array(Array::new(vec![Value::Int(1)]));
+        let twice: Value = vec![inner.clone(), inner].into();
+        assert_eq!(
+            inspect_impl(&twice, 8),
+            "[\n  [\n    1,\n  ],\n  [\n    1,\n  ],\n]"
+        );
+
+        let m = crate::Map::new();
+        m.insert("self".into(), m.clone().into()).unwrap();
+        assert_eq!(inspect_impl(&m.into(), 8), "#{\n  \"self\": <cycle>,\n}");
+    }
+}
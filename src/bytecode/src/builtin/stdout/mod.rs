@@ -1,5 +1,7 @@
+pub use inspect::*;
 pub use print::*;
 pub use println::*;
 
+mod inspect;
 mod print;
 mod println;
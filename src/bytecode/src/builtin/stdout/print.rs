@@ -8,7 +8,7 @@ pub fn print() -> Value {
     Value::Closure {
         fn_type: FnType::Builtin,
         sym: PRINT_SYM.into(),
-        prms: vec!["s".into()],
+        prms: vec!["s".into()].into(),
         addr: 0,
         env: W(Weak::new()),
     }
@@ -19,10 +19,17 @@ pub fn print_impl(v: &Value) {
         Value::Unitialized => print!("uninitialized"),
         Value::Unit => print!("()"),
         Value::String(s) => print!("{}", s),
+        Value::Char(c) => print!("{}", c),
         Value::Bool(b) => print!("{}", b),
         Value::Int(i) => print!("{}", i),
         Value::Float(f) => print!("{}", f),
         Value::Semaphore(_) => print!("semaphore"),
+        Value::Channel(_) => print!("channel"),
+        Value::Socket(_) => print!("socket"),
+        Value::Bytes(_) => print!("{}", v),
+        Value::Array(_) | Value::Slice(_) | Value::Struct(_) => print!("{}", v),
+        Value::Variant(_) | Value::Tuple(_) | Value::Map(_) => print!("{}", v),
+        Value::PVec(_) | Value::PMap(_) => print!("{}", v),
         Value::Closure { .. } => print!("closure"),
     }
 }
@@ -8,7 +8,7 @@ pub fn println() -> Value {
     Value::Closure {
         fn_type: FnType::Builtin,
         sym: PRINTLN_SYM.into(),
-        prms: vec!["s".into()],
+        prms: vec!["s".into()].into(),
         addr: 0,
         env: W(Weak::new()),
     }
@@ -0,0 +1,59 @@
+use std::rc::Weak;
+
+use anyhow::Result;
+
+use crate::{type_of, ByteCodeError, FnType, Value, Variant, W};
+
+pub const CHAR_AT_SYM: &str = "char_at";
+
+pub fn char_at() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: CHAR_AT_SYM.into(),
+        prms: vec!["s".into(), "idx".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// char_at(s, idx): Some of the char that starts at byte index idx of s, like s[idx], or None if idx is outside
+/// s or inside a character. The string is read where it is, not copied.
+pub fn char_at_impl(s: &Value, idx: &Value) -> Result<Value> {
+    let (Value::String(s), Value::Int(idx)) = (s, idx) else {
+        return Err(ByteCodeError::BadType {
+            expected: "(String, Int)".to_string(),
+            found: format!("({}, {})", type_of(s), type_of(idx)),
+        }
+        .into());
+    };
+
+    let c = usize::try_from(*idx)
+        .ok()
+        .and_then(|idx| s.get(idx..))
+        .and_then(|rest| rest.chars().next());
+    let val = match c {
+        Some(c) => Variant::some(c),
+        None => Variant::none(),
+    };
+    Ok(val.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_char_at() -> Result<()> {
+        let s: Value = "héllo".into();
+        let at = |idx: i64| char_at_impl(&s, &Value::Int(idx));
+        assert_eq!(at(0)?, Variant::some('h').into());
+        assert_eq!(at(1)?, Variant::some('é').into());
+        // inside the é, past the end and before the start
+        assert_eq!(at(2)?, Variant::none().into());
+        assert_eq!(at(6)?, Variant::none().into());
+        assert_eq!(at(-1)?, Variant::none().into());
+
+        assert!(char_at_impl(&Value::Int(1), &Value::Int(0)).is_err());
+        Ok(())
+    }
+}
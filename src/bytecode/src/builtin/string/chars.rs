@@ -0,0 +1,25 @@
+use std::rc::Weak;
+
+use anyhow::Result;
+
+use crate::{Array, FnType, Slice, Value, W};
+
+pub const CHARS_SYM: &str = "chars";
+
+pub fn chars() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: CHARS_SYM.into(),
+        prms: vec!["s".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// chars(s): the chars of s in order, as a slice, so a for loop can go through them.
+pub fn chars_impl(s: &Value) -> Result<Value> {
+    let s: String = s.clone().try_into()?;
+    let chars = s.chars().map(Value::Char).collect::<Vec<_>>();
+    let len = chars.len();
+    Ok(Value::Slice(Slice::new(Array::new(chars), 0, len)))
+}
@@ -0,0 +1,24 @@
+use std::rc::Weak;
+
+use anyhow::Result;
+
+use crate::{FnType, Value, W};
+
+pub const CONTAINS_SYM: &str = "contains";
+
+pub fn contains() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: CONTAINS_SYM.into(),
+        prms: vec!["s".into(), "pat".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// contains(s, pat): if pat is somewhere in s. Every string contains the empty string.
+pub fn contains_impl(s: &Value, pat: &Value) -> Result<Value> {
+    let s: String = s.clone().try_into()?;
+    let pat: String = pat.clone().try_into()?;
+    Ok(s.contains(&pat).into())
+}
@@ -0,0 +1,26 @@
+use std::rc::Weak;
+
+use anyhow::Result;
+
+use crate::{FnType, Value, W};
+
+pub const FIND_SYM: &str = "find";
+
+pub fn find() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: FIND_SYM.into(),
+        prms: vec!["s".into(), "pat".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// find(s, pat): the byte index of the first pat in s, like the indexes substring takes, or -1 if s doesn't
+/// contain it.
+pub fn find_impl(s: &Value, pat: &Value) -> Result<Value> {
+    let s: String = s.clone().try_into()?;
+    let pat: String = pat.clone().try_into()?;
+    let idx = s.find(&pat).map_or(-1, |idx| idx as i64);
+    Ok(Value::Int(idx))
+}
@@ -0,0 +1,111 @@
+use std::rc::Weak;
+
+use anyhow::Result;
+
+use crate::{ByteCodeError, FnType, Value, W};
+
+pub const FORMAT_SYM: &str = "format";
+
+/// format(fmt, args...) is compiled to a call with the args after the format string in an array, so the builtin
+/// takes two params however many args there are.
+pub fn format() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: FORMAT_SYM.into(),
+        prms: vec!["fmt".into(), "args".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// Put each of the args in place of the next `{}` in fmt, shown like println shows them. `{{` and `}}` are braces.
+pub fn format_impl(fmt: &Value, args: &Value) -> Result<Value> {
+    let fmt: String = fmt.clone().try_into()?;
+    let Value::Array(args) = args else {
+        return Err(ByteCodeError::TypeMismatch {
+            expected: "Array".to_string(),
+            found: format!("{:?}", args),
+        }
+        .into());
+    };
+    let args = args.borrow();
+
+    let mut out = String::with_capacity(fmt.len());
+    let mut args_iter = args.iter();
+    let mut chars = fmt.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('{', Some('{')) | ('}', Some('}')) => {
+                chars.next();
+                out.push(c);
+            }
+            ('{', Some('}')) => {
+                chars.next();
+                let arg = args_iter.next().ok_or_else(|| {
+                    ByteCodeError::BadFormat(format!(
+                        "'{}' has more placeholders than the {} arguments given",
+                        fmt,
+                        args.len()
+                    ))
+                })?;
+                out.push_str(&arg.to_string());
+            }
+            ('{', _) | ('}', _) => {
+                return Err(ByteCodeError::BadFormat(format!(
+                    "unmatched '{}' in '{}', use '{}{}' for a brace",
+                    c, fmt, c, c
+                ))
+                .into());
+            }
+            _ => out.push(c),
+        }
+    }
+
+    if args_iter.next().is_some() {
+        return Err(ByteCodeError::BadFormat(format!(
+            "'{}' has fewer placeholders than the {} arguments given",
+            fmt,
+            args.len()
+        ))
+        .into());
+    }
+
+    Ok(Value::String(out.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Array;
+
+    use super::*;
+
+    fn format_vals(fmt: &str, args: Vec<Value>) -> Result<String> {
+        let s = format_impl(&fmt.into(), &Value::Array(Array::new(args)))?;
+        Ok(s.try_into()?)
+    }
+
+    #[test]
+    fn test_format() -> Result<()> {
+        let args = vec![
+            Value::Int(1),
+            Value::Float(2.5),
+            Value::Bool(true),
+            "s".into(),
+            Value::Unit,
+            crate::builtin::format(),
+        ];
+        assert_eq!(
+            format_vals("{} {} {} {} {} {}", args)?,
+            "1 2.5 true s () closure"
+        );
+        assert_eq!(format_vals("{{}} {}", vec![Value::Int(3)])?, "{} 3");
+        assert_eq!(format_vals("no args", vec![])?, "no args");
+
+        assert!(format_vals("{} {}", vec![Value::Int(1)]).is_err());
+        assert!(format_vals("{}", vec![Value::Int(1), Value::Int(2)]).is_err());
+        assert!(format_vals("{x}", vec![]).is_err());
+        assert!(format_vals("}", vec![]).is_err());
+        Ok(())
+    }
+}
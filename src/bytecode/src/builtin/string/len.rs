@@ -10,7 +10,7 @@ pub fn string_len() -> Value {
     Value::Closure {
         fn_type: FnType::Builtin,
         sym: STRING_LEN_SYM.into(),
-        prms: vec!["s".into()],
+        prms: vec!["s".into()].into(),
         addr: 0,
         env: W(Weak::new()),
     }
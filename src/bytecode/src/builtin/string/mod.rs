@@ -1,3 +1,25 @@
+pub use char_at::*;
+pub use chars::*;
+pub use contains::*;
+pub use find::*;
+pub use format::*;
 pub use len::*;
+pub use replace::*;
+pub use split::*;
+pub use substring::*;
+pub use to_lower::*;
+pub use to_upper::*;
+pub use trim::*;
 
+mod char_at;
+mod chars;
+mod contains;
+mod find;
+mod format;
 mod len;
+mod replace;
+mod split;
+mod substring;
+mod to_lower;
+mod to_upper;
+mod trim;
@@ -0,0 +1,28 @@
+use std::rc::Weak;
+
+use anyhow::Result;
+
+use crate::{ByteCodeError, FnType, Value, W};
+
+pub const REPLACE_SYM: &str = "replace";
+
+pub fn replace() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: REPLACE_SYM.into(),
+        prms: vec!["s".into(), "from".into(), "to".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// replace(s, from, to): s with every from in it replaced by to, from the start of s, without overlaps.
+pub fn replace_impl(s: &Value, from: &Value, to: &Value) -> Result<Value> {
+    let s: String = s.clone().try_into()?;
+    let from: String = from.clone().try_into()?;
+    let to: String = to.clone().try_into()?;
+    if from.is_empty() {
+        return Err(ByteCodeError::EmptyPattern(REPLACE_SYM.to_string()).into());
+    }
+    Ok(s.replace(&from, &to).into())
+}
@@ -0,0 +1,31 @@
+use std::rc::Weak;
+
+use anyhow::Result;
+
+use crate::{Array, ByteCodeError, FnType, Slice, Value, W};
+
+pub const SPLIT_SYM: &str = "split";
+
+pub fn split() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: SPLIT_SYM.into(),
+        prms: vec!["s".into(), "sep".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// split(s, sep): the parts of s between each sep, in order, as a slice of strings. A sep at the start or end
+/// of s gives an empty part there, and s without sep in it is one part.
+pub fn split_impl(s: &Value, sep: &Value) -> Result<Value> {
+    let s: String = s.clone().try_into()?;
+    let sep: String = sep.clone().try_into()?;
+    if sep.is_empty() {
+        return Err(ByteCodeError::EmptyPattern(SPLIT_SYM.to_string()).into());
+    }
+
+    let parts = s.split(&sep).map(Value::from).collect::<Vec<_>>();
+    let len = parts.len();
+    Ok(Value::Slice(Slice::new(Array::new(parts), 0, len)))
+}
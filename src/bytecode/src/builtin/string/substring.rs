@@ -0,0 +1,46 @@
+use std::rc::Weak;
+
+use anyhow::Result;
+
+use crate::{ByteCodeError, FnType, Value, W};
+
+pub const SUBSTRING_SYM: &str = "substring";
+
+pub fn substring() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: SUBSTRING_SYM.into(),
+        prms: vec!["s".into(), "start".into(), "len".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// substring(s, start, len): the len bytes of s from byte index start, where string_len(s) is the length of s. The
+/// ends must be in s and between two characters, not inside one. Unlike atoi, ends that aren't are an error rather
+/// than an Err, like an index out of bounds: the program can check them with string_len before it calls.
+pub fn substring_impl(s: &Value, start: &Value, len: &Value) -> Result<Value> {
+    let s: String = s.clone().try_into()?;
+    let start: i64 = start.clone().try_into()?;
+    let len: i64 = len.clone().try_into()?;
+
+    let out_of_bounds = |index: i64| ByteCodeError::IndexOutOfBounds {
+        index,
+        len: s.len(),
+    };
+    if start < 0 || start as usize > s.len() {
+        return Err(out_of_bounds(start).into());
+    }
+    let end = start.saturating_add(len);
+    if len < 0 || end as usize > s.len() {
+        return Err(out_of_bounds(end).into());
+    }
+
+    let (start, end) = (start as usize, end as usize);
+    for idx in [start, end] {
+        if !s.is_char_boundary(idx) {
+            return Err(ByteCodeError::NotACharBoundary(idx as i64).into());
+        }
+    }
+    Ok(s[start..end].into())
+}
@@ -0,0 +1,23 @@
+use std::rc::Weak;
+
+use anyhow::Result;
+
+use crate::{FnType, Value, W};
+
+pub const TO_LOWER_SYM: &str = "to_lower";
+
+pub fn to_lower() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: TO_LOWER_SYM.into(),
+        prms: vec!["s".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// to_lower(s): s with each letter in lower case.
+pub fn to_lower_impl(s: &Value) -> Result<Value> {
+    let s: String = s.clone().try_into()?;
+    Ok(s.to_lowercase().into())
+}
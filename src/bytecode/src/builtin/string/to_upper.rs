@@ -0,0 +1,23 @@
+use std::rc::Weak;
+
+use anyhow::Result;
+
+use crate::{FnType, Value, W};
+
+pub const TO_UPPER_SYM: &str = "to_upper";
+
+pub fn to_upper() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: TO_UPPER_SYM.into(),
+        prms: vec!["s".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// to_upper(s): s with each letter in upper case.
+pub fn to_upper_impl(s: &Value) -> Result<Value> {
+    let s: String = s.clone().try_into()?;
+    Ok(s.to_uppercase().into())
+}
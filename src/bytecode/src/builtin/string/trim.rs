@@ -0,0 +1,23 @@
+use std::rc::Weak;
+
+use anyhow::Result;
+
+use crate::{FnType, Value, W};
+
+pub const TRIM_SYM: &str = "trim";
+
+pub fn trim() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: TRIM_SYM.into(),
+        prms: vec!["s".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// trim(s): s without the whitespace at its start and end.
+pub fn trim_impl(s: &Value) -> Result<Value> {
+    let s: String = s.clone().try_into()?;
+    Ok(s.trim().into())
+}
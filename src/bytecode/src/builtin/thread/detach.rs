@@ -0,0 +1,17 @@
+use std::rc::Weak;
+
+use crate::{FnType, Value, W};
+
+pub const DETACH_SYM: &str = "detach";
+
+/// detach(tid) lets the runtime drop the thread as soon as it finishes, since it won't be joined. The runtime does
+/// the work, since the thread belongs to it.
+pub fn detach() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: DETACH_SYM.into(),
+        prms: vec!["tid".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
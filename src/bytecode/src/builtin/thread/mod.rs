@@ -0,0 +1,3 @@
+pub use detach::*;
+
+mod detach;
@@ -0,0 +1,3 @@
+pub use sleep::*;
+
+mod sleep;
@@ -0,0 +1,29 @@
+use std::{rc::Weak, time::Duration};
+
+use anyhow::Result;
+
+use crate::{FnType, Value, W};
+
+pub const SLEEP_SYM: &str = "sleep";
+
+pub fn sleep() -> Value {
+    Value::Closure {
+        fn_type: FnType::Builtin,
+        sym: SLEEP_SYM.into(),
+        prms: vec!["ms".into()].into(),
+        addr: 0,
+        env: W(Weak::new()),
+    }
+}
+
+/// How long sleep(ms) sleeps for. A sleep of 0 or less only gives way to the other threads.
+pub fn sleep_duration(ms: &Value) -> Result<Duration> {
+    let ms: i64 = ms.clone().try_into()?;
+    Ok(Duration::from_millis(ms.max(0) as u64))
+}
+
+/// Block the OS thread for the duration. The runtime doesn't call this, since it puts the thread that called
+/// sleep aside and runs the others, but the async runner does on its blocking pool.
+pub fn sleep_impl(duration: Duration) {
+    std::thread::sleep(duration);
+}
@@ -11,6 +11,10 @@ pub type ThreadID = i64;
 /// An address is a pointer to a location in the bytecode.
 pub type Address = usize;
 
+/// An offset is a location in the bytecode relative to the instruction that holds it, so the instruction means the
+/// same wherever its code is placed.
+pub type Offset = isize;
+
 /// The bytecode instructions that the VM can execute. See ignite::micro_code crate for more information
 /// and implementation details.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
@@ -55,6 +59,67 @@ pub enum ByteCode {
     WAIT,
     /// Post the semaphore.
     POST,
+    /// Pop the given number of values and push an array of them, the deepest value being the first element.
+    ARRAY(usize),
+    /// Pop a value and push an array holding the given number of copies of it.
+    ARRAYFILL(usize),
+    /// Pop an index and an array, and push the element of the array at that index.
+    LDIDX,
+    /// Pop a value, an index and an array, and set the element of the array at that index to the value.
+    ASSIGNIDX,
+    /// Pop an end index (Unit for the end of the array), a start index and an array or slice,
+    /// and push a slice viewing the elements from start up to but not including end.
+    SLICE,
+    /// Pop an array or slice and push its length.
+    LEN,
+    /// Pop a value for each of the given fields and push a struct with the given name holding them,
+    /// the deepest value going to the first field.
+    STRUCT(Symbol, Vec<Symbol>),
+    /// Pop a struct and push the value of the given field.
+    LDFIELD(Symbol),
+    /// Pop a value and a struct, and set the given field of the struct to the value.
+    ASSIGNFIELD(Symbol),
+    /// Pop a value and a channel, and send the value on the channel.
+    SEND,
+    /// Pop a channel and push the next value received on it, blocking until one is sent.
+    RECV,
+    /// Spawn a new thread like SPAWN, but the child gets a deep copy of the current environment so that it
+    /// shares nothing with the parent except channels and semaphores.
+    SPAWNISO(Address),
+    /// Pop a timeout in ms and a thread ID, and join the thread if it finishes within the timeout, pushing true
+    /// if it was joined and false if not. The result of the thread is dropped.
+    TRYJOIN,
+    /// Pop the given number of values and push the variant with the given enum and variant names holding them,
+    /// the deepest value being the first.
+    VARIANT(Symbol, Symbol, usize),
+    /// Pop a variant and push whether it is the variant with the given name.
+    ISVARIANT(Symbol),
+    /// Pop a variant and push the value it holds at the given index.
+    LDVARIANT(usize),
+    /// Like JOF, with the address given as an offset from this instruction.
+    JOFR(Offset),
+    /// Like GOTO, with the address given as an offset from this instruction.
+    GOTOR(Offset),
+    /// Like LDF, with the address of the function given as an offset from this instruction.
+    LDFR(Offset, Vec<Symbol>),
+    /// Like SPAWN, with the address given as an offset from this instruction.
+    SPAWNR(Offset),
+    /// Like SPAWNISO, with the address given as an offset from this instruction.
+    SPAWNISOR(Offset),
+    /// Pop the given number of values and push a tuple holding them, the deepest value being the first.
+    TUPLE(usize),
+    /// Pop a tuple of the given length and push the values it holds, the first one deepest.
+    UNPACK(usize),
+    /// Pop a tuple and push the value it holds at the given index.
+    LDTUPLE(usize),
+    /// Pop the given number of key and value pairs and push a map holding them, each key pushed before its value.
+    MAP(usize),
+}
+
+/// The address an offset of the instruction at pc refers to. An offset before the start of the program gives an
+/// address past the end of any program, so it is reported as out of bounds like any other bad address.
+pub fn relative_target(pc: Address, offset: Offset) -> Address {
+    pc.checked_add_signed(offset).unwrap_or(Address::MAX)
 }
 
 /// For creating ByteCode instructions in a more ergonomic way.
@@ -92,6 +157,92 @@ impl ByteCode {
     }
 }
 
+impl ByteCode {
+    /// The name of the instruction without its operands, e.g. "LDC" for `LDC(Int(1))`.
+    pub fn opcode(&self) -> &'static str {
+        match self {
+            ByteCode::DONE => "DONE",
+            ByteCode::ASSIGN(_) => "ASSIGN",
+            ByteCode::LD(_) => "LD",
+            ByteCode::LDC(_) => "LDC",
+            ByteCode::POP => "POP",
+            ByteCode::BINOP(_) => "BINOP",
+            ByteCode::UNOP(_) => "UNOP",
+            ByteCode::JOF(_) => "JOF",
+            ByteCode::GOTO(_) => "GOTO",
+            ByteCode::RESET(_) => "RESET",
+            ByteCode::ENTERSCOPE(_) => "ENTERSCOPE",
+            ByteCode::EXITSCOPE => "EXITSCOPE",
+            ByteCode::LDF(..) => "LDF",
+            ByteCode::CALL(_) => "CALL",
+            ByteCode::SPAWN(_) => "SPAWN",
+            ByteCode::JOIN => "JOIN",
+            ByteCode::YIELD => "YIELD",
+            ByteCode::SEMCREATE => "SEMCREATE",
+            ByteCode::WAIT => "WAIT",
+            ByteCode::POST => "POST",
+            ByteCode::ARRAY(_) => "ARRAY",
+            ByteCode::ARRAYFILL(_) => "ARRAYFILL",
+            ByteCode::LDIDX => "LDIDX",
+            ByteCode::ASSIGNIDX => "ASSIGNIDX",
+            ByteCode::SLICE => "SLICE",
+            ByteCode::LEN => "LEN",
+            ByteCode::STRUCT(..) => "STRUCT",
+            ByteCode::LDFIELD(_) => "LDFIELD",
+            ByteCode::ASSIGNFIELD(_) => "ASSIGNFIELD",
+            ByteCode::SEND => "SEND",
+            ByteCode::RECV => "RECV",
+            ByteCode::SPAWNISO(_) => "SPAWNISO",
+            ByteCode::TRYJOIN => "TRYJOIN",
+            ByteCode::VARIANT(..) => "VARIANT",
+            ByteCode::ISVARIANT(_) => "ISVARIANT",
+            ByteCode::LDVARIANT(_) => "LDVARIANT",
+            ByteCode::JOFR(_) => "JOFR",
+            ByteCode::GOTOR(_) => "GOTOR",
+            ByteCode::LDFR(..) => "LDFR",
+            ByteCode::SPAWNR(_) => "SPAWNR",
+            ByteCode::SPAWNISOR(_) => "SPAWNISOR",
+            ByteCode::TUPLE(_) => "TUPLE",
+            ByteCode::UNPACK(_) => "UNPACK",
+            ByteCode::LDTUPLE(_) => "LDTUPLE",
+            ByteCode::MAP(_) => "MAP",
+        }
+    }
+
+    /// The address the instruction at pc jumps to, starts a thread at or loads a function from, if it has one.
+    /// Relative forms are resolved against pc.
+    pub fn target(&self, pc: Address) -> Option<Address> {
+        match self {
+            ByteCode::JOF(addr)
+            | ByteCode::GOTO(addr)
+            | ByteCode::SPAWN(addr)
+            | ByteCode::SPAWNISO(addr)
+            | ByteCode::LDF(addr, _) => Some(*addr),
+            ByteCode::JOFR(offset)
+            | ByteCode::GOTOR(offset)
+            | ByteCode::SPAWNR(offset)
+            | ByteCode::SPAWNISOR(offset)
+            | ByteCode::LDFR(offset, _) => Some(relative_target(pc, *offset)),
+            _ => None,
+        }
+    }
+
+    /// The relative form of the instruction at pc, which goes to the same address from there. None if the
+    /// instruction has no address or is relative already.
+    pub fn to_relative(&self, pc: Address) -> Option<ByteCode> {
+        let offset = |addr: &Address| *addr as Offset - pc as Offset;
+        let relative = match self {
+            ByteCode::JOF(addr) => ByteCode::JOFR(offset(addr)),
+            ByteCode::GOTO(addr) => ByteCode::GOTOR(offset(addr)),
+            ByteCode::SPAWN(addr) => ByteCode::SPAWNR(offset(addr)),
+            ByteCode::SPAWNISO(addr) => ByteCode::SPAWNISOR(offset(addr)),
+            ByteCode::LDF(addr, prms) => ByteCode::LDFR(offset(addr), prms.clone()),
+            _ => return None,
+        };
+        Some(relative)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -119,4 +270,30 @@ mod tests {
         let deserialized: ByteCode = bincode::deserialize(&serialized).unwrap();
         assert_eq!(unop, deserialized);
     }
+
+    #[test]
+    fn test_opcode() {
+        assert_eq!(ByteCode::ldc(42).opcode(), "LDC");
+        assert_eq!(ByteCode::ldf(0, vec!["x"]).opcode(), "LDF");
+        assert_eq!(ByteCode::EXITSCOPE.opcode(), "EXITSCOPE");
+    }
+
+    #[test]
+    fn test_relative() {
+        assert_eq!(ByteCode::GOTO(2).to_relative(5), Some(ByteCode::GOTOR(-3)));
+        assert_eq!(
+            ByteCode::ldf(9, vec!["x"]).to_relative(7),
+            Some(ByteCode::LDFR(2, vec!["x".to_string()]))
+        );
+        assert_eq!(ByteCode::GOTOR(-3).to_relative(5), None);
+        assert_eq!(ByteCode::POP.to_relative(5), None);
+
+        assert_eq!(ByteCode::GOTOR(-3).target(5), Some(2));
+        assert_eq!(ByteCode::JOFR(4).target(5), Some(9));
+        assert_eq!(ByteCode::SPAWN(4).target(5), Some(4));
+        assert_eq!(ByteCode::POP.target(5), None);
+
+        // before the start of the program
+        assert_eq!(ByteCode::GOTOR(-6).target(5), Some(Address::MAX));
+    }
 }
@@ -0,0 +1,109 @@
+use std::{cell::RefCell, fmt::Debug, rc::Rc};
+
+use anyhow::Result;
+
+use crate::{ByteCodeError, W};
+
+/// A mutable buffer of bytes, for binary data like file formats and network protocols. Like arrays, bytes have
+/// reference semantics: cloning the value shares the buffer, so a set_byte through one handle is seen through all.
+pub type Bytes = W<Rc<RefCell<Vec<u8>>>>;
+
+impl Bytes {
+    pub fn new(bytes: Vec<u8>) -> Self {
+        Self(Rc::new(RefCell::new(bytes)))
+    }
+
+    pub fn len(&self) -> usize {
+        self.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.borrow().is_empty()
+    }
+
+    /// The byte at idx.
+    ///
+    /// # Errors
+    ///
+    /// If idx is out of bounds.
+    pub fn get(&self, idx: i64) -> Result<u8> {
+        let bytes = self.borrow();
+        usize::try_from(idx)
+            .ok()
+            .and_then(|i| bytes.get(i).copied())
+            .ok_or_else(|| self.out_of_bounds(idx))
+    }
+
+    /// Set the byte at idx to byte.
+    ///
+    /// # Errors
+    ///
+    /// If idx is out of bounds, or byte isn't from 0 to 255.
+    pub fn set(&self, idx: i64, byte: i64) -> Result<()> {
+        let byte = u8::try_from(byte).map_err(|_| ByteCodeError::NotAByte(byte))?;
+        let mut bytes = self.borrow_mut();
+        let slot = usize::try_from(idx)
+            .ok()
+            .and_then(|i| bytes.get_mut(i))
+            .ok_or_else(|| self.out_of_bounds(idx))?;
+        *slot = byte;
+        Ok(())
+    }
+
+    /// Copy out the bytes.
+    pub fn to_vec(&self) -> Vec<u8> {
+        self.borrow().clone()
+    }
+
+    fn out_of_bounds(&self, idx: i64) -> anyhow::Error {
+        ByteCodeError::ByteIndexOutOfBounds {
+            index: idx,
+            len: self.0.try_borrow().map_or(0, |bytes| bytes.len()),
+        }
+        .into()
+    }
+}
+
+/// Bytes are equal if they hold the same bytes, whether or not they share the buffer.
+impl PartialEq for Bytes {
+    fn eq(&self, other: &Self) -> bool {
+        *self.borrow() == *other.borrow()
+    }
+}
+
+impl Clone for Bytes {
+    fn clone(&self) -> Self {
+        Self(Rc::clone(&self.0))
+    }
+}
+
+impl Debug for Bytes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Bytes({:?})", self.borrow())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bytes() -> Result<()> {
+        let bytes = Bytes::new(vec![0; 3]);
+        let other = bytes.clone();
+
+        // a set through one handle is seen through the other
+        bytes.set(1, 255)?;
+        assert_eq!(other.get(1)?, 255);
+        assert_eq!(other.to_vec(), vec![0, 255, 0]);
+
+        assert!(bytes.get(3).is_err());
+        assert!(bytes.get(-1).is_err());
+        assert!(bytes.set(0, 256).is_err());
+        assert!(bytes.set(0, -1).is_err());
+        assert!(bytes.set(3, 0).is_err());
+
+        assert_eq!(bytes, Bytes::new(vec![0, 255, 0]));
+        Ok(())
+    }
+}
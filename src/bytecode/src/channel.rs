@@ -0,0 +1,89 @@
+use std::{cell::RefCell, collections::VecDeque, fmt::Debug, rc::Rc};
+
+use crate::{Value, W};
+
+/// A queue of values passed between threads. Sends never block: values wait in the queue in the order they were
+/// sent until a thread receives them. Like arrays, every handle to a channel shares the same queue.
+pub type Channel = W<Rc<RefCell<VecDeque<Value>>>>;
+
+impl Channel {
+    pub fn new() -> Self {
+        Self(Rc::new(RefCell::new(VecDeque::new())))
+    }
+
+    /// Add a value to the back of the queue.
+    pub fn send(&self, val: Value) {
+        self.borrow_mut().push_back(val);
+    }
+
+    /// Take the value at the front of the queue, or None if nothing is waiting to be received.
+    pub fn recv(&self) -> Option<Value> {
+        self.borrow_mut().pop_front()
+    }
+
+    /// Copy out the values waiting to be received, oldest first.
+    pub fn values(&self) -> Vec<Value> {
+        self.borrow().iter().cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.borrow().is_empty()
+    }
+}
+
+impl Default for Channel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Channels are equal only if they are the same channel, like semaphores.
+impl PartialEq for Channel {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Clone for Channel {
+    fn clone(&self) -> Self {
+        Self(Rc::clone(&self.0))
+    }
+}
+
+impl Debug for Channel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Channel({:?})", self.borrow())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_channel_fifo() {
+        let ch = Channel::new();
+        let alias = ch.clone();
+        ch.send(Value::Int(1));
+        alias.send(Value::Int(2));
+
+        assert_eq!(ch.len(), 2);
+        assert_eq!(ch.values(), vec![Value::Int(1), Value::Int(2)]);
+        assert_eq!(alias.recv(), Some(Value::Int(1)));
+        assert_eq!(ch.recv(), Some(Value::Int(2)));
+        assert_eq!(ch.recv(), None);
+        assert!(alias.is_empty());
+    }
+
+    #[test]
+    fn test_channel_eq() {
+        let ch = Channel::new();
+        assert_eq!(ch, ch.clone());
+        assert_ne!(ch, Channel::new());
+        assert_eq!(format!("{:?}", ch), "Channel([])");
+    }
+}
@@ -0,0 +1,143 @@
+use crate::{ByteCode, FrameType, Value};
+
+/// Render a program as numbered instructions, one per line, e.g `3: JOF -> 7`.
+///
+/// Jumps, spawns and closures show their target as `-> addr`, or as an offset like `+3` in their relative forms,
+/// and instructions that are the target of one are marked with `>` so the start of each loop, branch and function
+/// stands out. Operators are shown as the symbol they are written with and string constants are quoted.
+pub fn disassemble(instrs: &[ByteCode]) -> String {
+    let width = instrs.len().saturating_sub(1).to_string().len();
+    let targets: Vec<usize> = instrs
+        .iter()
+        .enumerate()
+        .filter_map(|(pc, instr)| instr.target(pc))
+        .collect();
+
+    instrs
+        .iter()
+        .enumerate()
+        .map(|(i, instr)| {
+            let marker = if targets.contains(&i) { '>' } else { ' ' };
+            format!("{}{:>width$}: {}\n", marker, i, fmt_instr(instr))
+        })
+        .collect()
+}
+
+/// Format one instruction the way [`disassemble`] prints it, without the address.
+pub fn fmt_instr(instr: &ByteCode) -> String {
+    let opcode = instr.opcode();
+    match instr {
+        ByteCode::ASSIGN(sym) | ByteCode::LD(sym) => format!("{} {}", opcode, sym),
+        ByteCode::LDFIELD(field) | ByteCode::ASSIGNFIELD(field) => format!("{} .{}", opcode, field),
+        ByteCode::LDC(val) => format!("{} {}", opcode, fmt_const(val)),
+        ByteCode::BINOP(op) => format!("{} {}", opcode, String::from(op.clone())),
+        ByteCode::UNOP(op) => format!("{} {}", opcode, String::from(op.clone())),
+        ByteCode::JOF(addr)
+        | ByteCode::GOTO(addr)
+        | ByteCode::SPAWN(addr)
+        | ByteCode::SPAWNISO(addr) => {
+            format!("{} -> {}", opcode, addr)
+        }
+        ByteCode::LDF(addr, prms) => format!("{} -> {} ({})", opcode, addr, prms.join(", ")),
+        ByteCode::JOFR(offset)
+        | ByteCode::GOTOR(offset)
+        | ByteCode::SPAWNR(offset)
+        | ByteCode::SPAWNISOR(offset) => format!("{} {:+}", opcode, offset),
+        ByteCode::LDFR(offset, prms) => format!("{} {:+} ({})", opcode, offset, prms.join(", ")),
+        ByteCode::RESET(FrameType::CallFrame) => format!("{} call", opcode),
+        ByteCode::RESET(FrameType::BlockFrame) => format!("{} block", opcode),
+        ByteCode::ENTERSCOPE(syms) => format!("{} [{}]", opcode, syms.join(", ")),
+        ByteCode::CALL(n)
+        | ByteCode::ARRAY(n)
+        | ByteCode::ARRAYFILL(n)
+        | ByteCode::LDVARIANT(n)
+        | ByteCode::TUPLE(n)
+        | ByteCode::UNPACK(n)
+        | ByteCode::LDTUPLE(n)
+        | ByteCode::MAP(n) => {
+            format!("{} {}", opcode, n)
+        }
+        ByteCode::STRUCT(name, fields) => {
+            format!("{} {} {{ {} }}", opcode, name, fields.join(", "))
+        }
+        ByteCode::VARIANT(enum_name, name, arity) => {
+            format!("{} {}::{} {}", opcode, enum_name, name, arity)
+        }
+        ByteCode::ISVARIANT(name) => format!("{} {}", opcode, name),
+        ByteCode::DONE
+        | ByteCode::POP
+        | ByteCode::EXITSCOPE
+        | ByteCode::JOIN
+        | ByteCode::TRYJOIN
+        | ByteCode::YIELD
+        | ByteCode::SEMCREATE
+        | ByteCode::WAIT
+        | ByteCode::POST
+        | ByteCode::LDIDX
+        | ByteCode::ASSIGNIDX
+        | ByteCode::SLICE
+        | ByteCode::LEN
+        | ByteCode::SEND
+        | ByteCode::RECV => opcode.to_string(),
+    }
+}
+
+fn fmt_const(val: &Value) -> String {
+    match val {
+        Value::String(s) => format!("{:?}", s),
+        _ => format!("{:?}", val),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BinOp;
+
+    #[test]
+    fn test_disassemble() {
+        // if x { "yes" } else { 2 + 3 }
+        let instrs = vec![
+            ByteCode::ld("x"),
+            ByteCode::JOF(3),
+            ByteCode::ldc("yes"),
+            ByteCode::ldc(2),
+            ByteCode::ldc(3),
+            ByteCode::BINOP(BinOp::Add),
+            ByteCode::GOTO(7),
+            ByteCode::RESET(FrameType::CallFrame),
+            ByteCode::enterscope(vec!["a", "b"]),
+            ByteCode::ldf(0, vec!["n"]),
+            ByteCode::DONE,
+        ];
+        let exp = [
+            "> 0: LD x",
+            "  1: JOF -> 3",
+            "  2: LDC \"yes\"",
+            "> 3: LDC 2",
+            "  4: LDC 3",
+            "  5: BINOP +",
+            "  6: GOTO -> 7",
+            "> 7: RESET call",
+            "  8: ENTERSCOPE [a, b]",
+            "  9: LDF -> 0 (n)",
+            " 10: DONE",
+        ];
+        assert_eq!(disassemble(&instrs), format!("{}\n", exp.join("\n")));
+        assert_eq!(disassemble(&[]), "");
+
+        let instrs = vec![
+            ByteCode::ldc(true),
+            ByteCode::JOFR(2),
+            ByteCode::LDFR(-2, vec!["n".to_string()]),
+            ByteCode::GOTOR(-1),
+        ];
+        let exp = [
+            ">0: LDC true",
+            " 1: JOFR +2",
+            ">2: LDFR -2 (n)",
+            ">3: GOTOR -1",
+        ];
+        assert_eq!(disassemble(&instrs), format!("{}\n", exp.join("\n")));
+    }
+}
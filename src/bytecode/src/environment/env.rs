@@ -1,5 +1,5 @@
 use std::{
-    cell::RefCell,
+    cell::{Cell, RefCell},
     collections::{hash_map::Entry, HashMap},
     fmt::Debug,
     rc::{Rc, Weak},
@@ -9,10 +9,24 @@ use anyhow::Result;
 
 use crate::{builtin, ByteCodeError, Symbol, Value};
 
+/// How many frames a lookup searches before it tries the global frame directly.
+pub const DEFAULT_GLOBAL_FALLBACK_DEPTH: usize = 2;
+
 #[derive(Debug, Clone, Default)]
 pub struct Environment {
     pub parent: Option<Weak<RefCell<Environment>>>,
     pub env: HashMap<Symbol, Value>,
+    /// The root of the chain, where the builtins live. None for the root itself.
+    pub global: Option<Weak<RefCell<Environment>>>,
+    /// If this frame or one of its parents binds a name the global frame also binds. Names are bound when a
+    /// frame is made, before it has children, so the flag of a frame never changes under its children. A name
+    /// bound in the global frame later goes through `Runtime::define_global`, which sets the flag again.
+    pub shadows_global: bool,
+    /// The garbage collection in which the frame was last found to be reachable, see `Runtime::gc_epoch`.
+    pub mark: Cell<u64>,
+    /// If a closure or a spawned thread may refer to the frame, so it can outlive the call that made it. Setting it on a
+    /// frame sets it on the parents of the frame too, see [`Environment::set_escaped`].
+    pub escaped: Cell<bool>,
 }
 
 impl PartialEq for Environment {
@@ -27,6 +41,10 @@ impl Environment {
         Environment {
             parent: None,
             env: HashMap::new(),
+            global: None,
+            shadows_global: false,
+            mark: Cell::new(0),
+            escaped: Cell::new(false),
         }
     }
 
@@ -39,7 +57,8 @@ impl Environment {
     ///
     /// Built in functions are added to the global environment.
     /// - Math functions: abs, ceil, floor, round, sqrt, sin, cos, tan, log10, pow
-    /// - String functions: len
+    /// - String functions: len, format, substring, split, find, contains, replace, to_upper, to_lower, trim,
+    ///   chars
     /// - Type conversion functions: int_to_float, float_to_int, atoi, atoi
     /// - Comparison functions: min, max
     ///
@@ -59,12 +78,11 @@ impl Environment {
         env.borrow_mut().set(builtin::E_SYM, std::f64::consts::E);
 
         //Environment constants
-        env.borrow_mut().set(builtin::MAX_INT_SYM, std::i64::MAX);
-        env.borrow_mut().set(builtin::MIN_INT_SYM, std::i64::MIN);
-        env.borrow_mut().set(builtin::MAX_FLOAT_SYM, std::f64::MAX);
-        env.borrow_mut().set(builtin::MIN_FLOAT_SYM, std::f64::MIN);
-        env.borrow_mut()
-            .set(builtin::EPSILON_SYM, std::f64::EPSILON);
+        env.borrow_mut().set(builtin::MAX_INT_SYM, i64::MAX);
+        env.borrow_mut().set(builtin::MIN_INT_SYM, i64::MIN);
+        env.borrow_mut().set(builtin::MAX_FLOAT_SYM, f64::MAX);
+        env.borrow_mut().set(builtin::MIN_FLOAT_SYM, f64::MIN);
+        env.borrow_mut().set(builtin::EPSILON_SYM, f64::EPSILON);
 
         // Built in functions
         // Math functions
@@ -77,10 +95,60 @@ impl Environment {
         env.borrow_mut().set(builtin::SQRT_SYM, builtin::sqrt());
         env.borrow_mut().set(builtin::MAX_SYM, builtin::max());
         env.borrow_mut().set(builtin::MIN_SYM, builtin::min());
+        env.borrow_mut()
+            .set(builtin::DIV_EUCLID_SYM, builtin::div_euclid());
+        env.borrow_mut()
+            .set(builtin::REM_EUCLID_SYM, builtin::rem_euclid());
+        env.borrow_mut().set(builtin::DIVMOD_SYM, builtin::divmod());
 
         // String functions
         env.borrow_mut()
             .set(builtin::STRING_LEN_SYM, builtin::string_len());
+        env.borrow_mut().set(builtin::FORMAT_SYM, builtin::format());
+        env.borrow_mut()
+            .set(builtin::SUBSTRING_SYM, builtin::substring());
+        env.borrow_mut().set(builtin::SPLIT_SYM, builtin::split());
+        env.borrow_mut().set(builtin::FIND_SYM, builtin::find());
+        env.borrow_mut()
+            .set(builtin::CONTAINS_SYM, builtin::contains());
+        env.borrow_mut()
+            .set(builtin::REPLACE_SYM, builtin::replace());
+        env.borrow_mut()
+            .set(builtin::TO_UPPER_SYM, builtin::to_upper());
+        env.borrow_mut()
+            .set(builtin::TO_LOWER_SYM, builtin::to_lower());
+        env.borrow_mut().set(builtin::TRIM_SYM, builtin::trim());
+        env.borrow_mut().set(builtin::CHARS_SYM, builtin::chars());
+        env.borrow_mut()
+            .set(builtin::CHAR_AT_SYM, builtin::char_at());
+
+        // Array functions
+        env.borrow_mut()
+            .set(builtin::SLICE_LEN_SYM, builtin::slice_len());
+        env.borrow_mut().set(builtin::MAP_SYM, builtin::map());
+        env.borrow_mut().set(builtin::FILTER_SYM, builtin::filter());
+        env.borrow_mut().set(builtin::FOLD_SYM, builtin::fold());
+        env.borrow_mut()
+            .set(builtin::SORT_BY_SYM, builtin::sort_by());
+        env.borrow_mut().set(builtin::MIN_BY_SYM, builtin::min_by());
+        env.borrow_mut().set(builtin::MAX_BY_SYM, builtin::max_by());
+
+        // Byte buffer functions
+        env.borrow_mut().set(builtin::BYTES_SYM, builtin::bytes());
+        env.borrow_mut()
+            .set(builtin::BYTES_LEN_SYM, builtin::bytes_len());
+        env.borrow_mut()
+            .set(builtin::GET_BYTE_SYM, builtin::get_byte());
+        env.borrow_mut()
+            .set(builtin::SET_BYTE_SYM, builtin::set_byte());
+        env.borrow_mut()
+            .set(builtin::BYTES_FROM_STRING_SYM, builtin::bytes_from_string());
+        env.borrow_mut()
+            .set(builtin::STRING_FROM_BYTES_SYM, builtin::string_from_bytes());
+        env.borrow_mut()
+            .set(builtin::READ_BYTES_SYM, builtin::read_bytes());
+        env.borrow_mut()
+            .set(builtin::WRITE_BYTES_SYM, builtin::write_bytes());
 
         // Type conversion functions
         env.borrow_mut()
@@ -89,6 +157,8 @@ impl Environment {
             .set(builtin::FLOAT_TO_INT_SYM, builtin::float_to_int());
         env.borrow_mut().set(builtin::ATOI_SYM, builtin::atoi());
         env.borrow_mut().set(builtin::ITOA_SYM, builtin::itoa());
+        env.borrow_mut().set(builtin::ATOF_SYM, builtin::atof());
+        env.borrow_mut().set(builtin::FTOA_SYM, builtin::ftoa());
 
         // stdin, stdout
         env.borrow_mut()
@@ -96,12 +166,100 @@ impl Environment {
         env.borrow_mut().set(builtin::PRINT_SYM, builtin::print());
         env.borrow_mut()
             .set(builtin::PRINTLN_SYM, builtin::println());
+        env.borrow_mut()
+            .set(builtin::INSPECT_SYM, builtin::inspect());
+        env.borrow_mut()
+            .set(builtin::INSPECT_DEPTH_SYM, builtin::inspect_depth());
 
         // Semaphore functions
         env.borrow_mut()
             .set(builtin::SEM_CREATE_SYM, builtin::sem_create());
         env.borrow_mut()
             .set(builtin::SEM_SET_SYM, builtin::sem_set());
+        env.borrow_mut().set(builtin::MUTEX_SYM, builtin::mutex());
+
+        // Thread functions
+        env.borrow_mut().set(builtin::DETACH_SYM, builtin::detach());
+
+        // Time functions
+        env.borrow_mut().set(builtin::SLEEP_SYM, builtin::sleep());
+
+        // Hashing functions
+        env.borrow_mut().set(builtin::HASH_SYM, builtin::hash());
+        env.borrow_mut().set(builtin::CRC32_SYM, builtin::crc32());
+        env.borrow_mut().set(builtin::SHA256_SYM, builtin::sha256());
+
+        // Persistent collections
+        env.borrow_mut().set(builtin::PVEC_SYM, builtin::pvec());
+        env.borrow_mut().set(builtin::PMAP_SYM, builtin::pmap());
+        env.borrow_mut()
+            .set(builtin::PERSIST_PUSH_SYM, builtin::persist_push());
+        env.borrow_mut()
+            .set(builtin::PERSIST_SET_SYM, builtin::persist_set());
+        env.borrow_mut()
+            .set(builtin::PERSIST_INSERT_SYM, builtin::persist_insert());
+        env.borrow_mut()
+            .set(builtin::PERSIST_REMOVE_SYM, builtin::persist_remove());
+        env.borrow_mut()
+            .set(builtin::PERSIST_GET_SYM, builtin::persist_get());
+        env.borrow_mut()
+            .set(builtin::PERSIST_CONTAINS_SYM, builtin::persist_contains());
+        env.borrow_mut()
+            .set(builtin::PERSIST_LEN_SYM, builtin::persist_len());
+
+        // Map functions
+        env.borrow_mut()
+            .set(builtin::MAP_NEW_SYM, builtin::map_new());
+        env.borrow_mut()
+            .set(builtin::MAP_INSERT_SYM, builtin::map_insert());
+        env.borrow_mut()
+            .set(builtin::MAP_GET_SYM, builtin::map_get());
+        env.borrow_mut()
+            .set(builtin::MAP_REMOVE_SYM, builtin::map_remove());
+        env.borrow_mut()
+            .set(builtin::MAP_LEN_SYM, builtin::map_len());
+        env.borrow_mut()
+            .set(builtin::MAP_KEYS_SYM, builtin::map_keys());
+
+        // Channel functions
+        env.borrow_mut().set(builtin::CHAN_SYM, builtin::chan());
+        env.borrow_mut()
+            .set(builtin::TRY_RECV_SYM, builtin::try_recv());
+
+        // Network functions, only callable if the runtime allows it
+        env.borrow_mut()
+            .set(builtin::TCP_CONNECT_SYM, builtin::tcp_connect());
+        env.borrow_mut()
+            .set(builtin::TCP_LISTEN_SYM, builtin::tcp_listen());
+        env.borrow_mut()
+            .set(builtin::TCP_ACCEPT_SYM, builtin::tcp_accept());
+        env.borrow_mut()
+            .set(builtin::TCP_SEND_SYM, builtin::tcp_send());
+        env.borrow_mut()
+            .set(builtin::TCP_RECV_SYM, builtin::tcp_recv());
+        env.borrow_mut()
+            .set(builtin::UDP_BIND_SYM, builtin::udp_bind());
+        env.borrow_mut()
+            .set(builtin::UDP_SEND_TO_SYM, builtin::udp_send_to());
+        env.borrow_mut()
+            .set(builtin::UDP_RECV_SYM, builtin::udp_recv());
+        env.borrow_mut().set(builtin::CLOSE_SYM, builtin::close());
+        env.borrow_mut()
+            .set(builtin::HTTP_GET_SYM, builtin::http_get());
+        env.borrow_mut()
+            .set(builtin::HTTP_POST_SYM, builtin::http_post());
+
+        // The arguments and environment variables of the process
+        env.borrow_mut().set(builtin::ARGS_SYM, builtin::args());
+        env.borrow_mut()
+            .set(builtin::ENV_VAR_SYM, builtin::env_var());
+
+        // Stops the program if a condition is false, for #[test] fns
+        env.borrow_mut().set(builtin::ASSERT_SYM, builtin::assert());
+
+        // Process functions, only callable if the runtime allows it
+        env.borrow_mut()
+            .set(builtin::RUN_COMMAND_SYM, builtin::run_command());
 
         env
     }
@@ -113,32 +271,118 @@ impl Environment {
 }
 
 impl Environment {
-    /// Set the parent of the frame.
+    /// Record that the frame and its chain of parents may be referred to after the call that made them returns.
+    /// A frame that has escaped has had its parents set already, so the walk stops at the first one.
+    pub fn set_escaped(env: &Weak<RefCell<Environment>>) {
+        let mut next = env.upgrade();
+        while let Some(frame) = next {
+            let frame = frame.borrow();
+            if frame.escaped.replace(true) {
+                return;
+            }
+            next = frame.parent.as_ref().and_then(Weak::upgrade);
+        }
+    }
+
+    /// Set the parent of the frame. The frame shares the global frame of its parent, or has the parent as its
+    /// global frame if the parent is the root.
     pub fn set_parent(&mut self, parent: Weak<RefCell<Environment>>) {
+        if let Some(parent_rc) = parent.upgrade() {
+            let parent_ref = parent_rc.borrow();
+            self.global = Some(parent_ref.global.clone().unwrap_or_else(|| parent.clone()));
+            self.shadows_global = parent_ref.shadows_global;
+        }
         self.parent = Some(parent);
+
+        let shadows = self.env.keys().any(|sym| self.in_global(sym));
+        self.shadows_global |= shadows;
     }
 
     /// Get a snapshot of the value of a symbol in the frame at the time of the call.
+    ///
+    /// The snapshot is a cheap clone: strings, closures, arrays and structs come back as handles to the same
+    /// data as the binding, so a read never copies the contents.
     pub fn get(&self, sym: &Symbol) -> Result<Value> {
+        self.get_with_fallback(sym, DEFAULT_GLOBAL_FALLBACK_DEPTH)
+    }
+
+    /// Like get, but after `depth` frames miss, look in the global frame before searching the rest of the
+    /// chain. Skipping the frames in between is only done when none of them shadow a global name, so the
+    /// result is always the same as searching frame by frame.
+    pub fn get_with_fallback(&self, sym: &Symbol, mut depth: usize) -> Result<Value> {
         // If the symbol is found in the current environment, return the value.
-        if let Some(val) = self.env.get(sym) {
-            return Ok(val.clone());
+        if let Some(val) = self.get_here(sym, depth) {
+            return Ok(val);
         }
 
-        // If the symbol is not found in the current environment, search the parent environment.
-        let Some(parent) = &self.parent else {
-            // If the parent environment is not found, return an error.
-            return Err(ByteCodeError::UnboundedName { name: sym.clone() }.into());
-        };
+        // Otherwise search the parent environments one by one, in a loop so a long chain can't overflow the stack.
+        let mut parent = self.parent.clone();
+        loop {
+            let Some(weak) = parent else {
+                // If there are no more parent environments, return an error.
+                return Err(ByteCodeError::UnboundedName { name: sym.clone() }.into());
+            };
+
+            let Some(env) = weak.upgrade() else {
+                // If the parent environment is dropped prematurely, return an error.
+                return Err(ByteCodeError::EnvironmentDroppedError.into());
+            };
+
+            let env_ref = env.borrow();
+            depth = depth.saturating_sub(1);
+            if let Some(val) = env_ref.get_here(sym, depth) {
+                return Ok(val);
+            }
+            parent = env_ref.parent.clone();
+        }
+    }
 
-        // If the parent environment is found, search the parent environment.
-        let Some(parent) = parent.upgrade() else {
-            // If the parent environment is dropped prematurely, return an error.
-            return Err(ByteCodeError::EnvironmentDroppedError.into());
-        };
+    // The value of a symbol if it is bound in this frame, or found in the global frame once `depth` frames missed
+    fn get_here(&self, sym: &Symbol, depth: usize) -> Option<Value> {
+        // No frame from here up binds a global name, so a global binding is what the search would find
+        if depth == 0 && !self.shadows_global {
+            if let Some(val) = self.global_get(sym) {
+                return Some(val);
+            }
+        }
 
-        let parent_ref = parent.borrow();
-        parent_ref.get(sym)
+        self.env.get(sym).cloned()
+    }
+
+    fn global_get(&self, sym: &Symbol) -> Option<Value> {
+        let global = self.global.as_ref()?.upgrade()?;
+        let val = global.borrow().env.get(sym).cloned();
+        val
+    }
+
+    /// If the frame or one of its parents binds the symbol, not counting the global frame.
+    pub fn chain_binds(&self, sym: &Symbol) -> bool {
+        if self.global.is_none() {
+            return false;
+        }
+        if self.env.contains_key(sym) {
+            return true;
+        }
+
+        let mut parent = self.parent.as_ref().and_then(Weak::upgrade);
+        while let Some(env) = parent {
+            let env_ref = env.borrow();
+            if env_ref.global.is_none() {
+                return false;
+            }
+            if env_ref.env.contains_key(sym) {
+                return true;
+            }
+            parent = env_ref.parent.as_ref().and_then(Weak::upgrade);
+        }
+        false
+    }
+
+    fn in_global(&self, sym: &Symbol) -> bool {
+        self.global
+            .as_ref()
+            .and_then(Weak::upgrade)
+            .is_some_and(|global| global.borrow().env.contains_key(sym))
     }
 
     /// Set the value of a symbol in the current environment.
@@ -148,7 +392,11 @@ impl Environment {
     /// * `sym` - The symbol whose value is to be set.
     /// * `val` - The value to be set.
     pub fn set(&mut self, sym: impl Into<Symbol>, val: impl Into<Value>) {
-        self.env.insert(sym.into(), val.into());
+        let sym = sym.into();
+        if !self.shadows_global && self.in_global(&sym) {
+            self.shadows_global = true;
+        }
+        self.env.insert(sym, val.into());
     }
 
     /// Update the value of a symbol in the current environment.
@@ -176,20 +424,26 @@ impl Environment {
             return Ok(());
         }
 
-        // If the symbol is not found in the current environment, search the parent environment.
-        let Some(parent) = &self.parent else {
-            // If the parent environment is not found, return an error.
-            return Err(ByteCodeError::UnboundedName { name: sym }.into());
-        };
-
-        // If the parent environment is found, search the parent environment.
-        let Some(parent) = parent.upgrade() else {
-            // If the parent environment is dropped prematurely, return an error.
-            return Err(ByteCodeError::EnvironmentDroppedError.into());
-        };
-
-        let mut parent_ref = parent.borrow_mut();
-        parent_ref.update(sym, val)
+        // Otherwise search the parent environments one by one, in a loop so a long chain can't overflow the stack.
+        let mut parent = self.parent.clone();
+        loop {
+            let Some(weak) = parent else {
+                // If there are no more parent environments, return an error.
+                return Err(ByteCodeError::UnboundedName { name: sym }.into());
+            };
+
+            let Some(env) = weak.upgrade() else {
+                // If the parent environment is dropped prematurely, return an error.
+                return Err(ByteCodeError::EnvironmentDroppedError.into());
+            };
+
+            let mut env_ref = env.borrow_mut();
+            if let Some(old) = env_ref.env.get_mut(&sym) {
+                *old = val.into();
+                return Ok(());
+            }
+            parent = env_ref.parent.clone();
+        }
     }
 }
 
@@ -209,6 +463,20 @@ mod tests {
         assert_eq!(env.borrow().get(&"x".to_string()).unwrap(), Value::Int(42));
     }
 
+    #[test]
+    fn test_environment_get_shares_string() {
+        let env = Environment::new_wrapped();
+        env.borrow_mut().set("s", "hello");
+
+        let (Value::String(first), Value::String(second)) = (
+            env.borrow().get(&"s".to_string()).unwrap(),
+            env.borrow().get(&"s".to_string()).unwrap(),
+        ) else {
+            panic!("Expected strings");
+        };
+        assert!(Rc::ptr_eq(&first, &second));
+    }
+
     #[test]
     fn test_set_environment() {
         let parent_env = Environment::new_wrapped();
@@ -248,6 +516,90 @@ mod tests {
             child_env.borrow().get(&"y".to_string()).unwrap(),
             Value::Int(43)
         );
-        assert!(!child_env.borrow().env.contains_key(&"x".to_string()));
+        assert!(!child_env.borrow().env.contains_key("x"));
+    }
+
+    // root <- a <- b <- c, with strong refs to each so none are dropped. Each frame binds its names before it
+    // gets children, like the VM does
+    fn chain(names: [&[(&str, i64)]; 3]) -> Vec<Rc<RefCell<Environment>>> {
+        let root = Environment::new_wrapped();
+        root.borrow_mut().set("g", 1);
+        root.borrow_mut().set("x", 2);
+
+        let mut envs = vec![root];
+        for frame in names {
+            let env = Environment::new_wrapped();
+            env.borrow_mut()
+                .set_parent(weak_clone(envs.last().unwrap()));
+            for (sym, val) in frame {
+                env.borrow_mut().set(*sym, *val);
+            }
+            envs.push(env);
+        }
+        envs
+    }
+
+    #[test]
+    fn test_environment_global_fallback() {
+        let mut envs = chain([&[], &[("y", 3)], &[]]);
+        let c = envs[3].clone();
+        assert!(Rc::ptr_eq(
+            &c.borrow().global.as_ref().unwrap().upgrade().unwrap(),
+            &envs[0]
+        ));
+        assert!(!c.borrow().shadows_global);
+
+        // the frames in between are skipped, so a dropped one is never reached
+        envs.remove(2);
+        let g = "g".to_string();
+        assert_eq!(c.borrow().get_with_fallback(&g, 0).unwrap(), Value::Int(1));
+        assert!(c.borrow().get_with_fallback(&g, usize::MAX).is_err());
+    }
+
+    #[test]
+    fn test_environment_global_fallback_shadowed() {
+        let envs = chain([&[], &[("x", 42)], &[]]);
+        let shadows: Vec<bool> = envs.iter().map(|env| env.borrow().shadows_global).collect();
+        assert_eq!(shadows, vec![false, false, true, true]);
+
+        let x = "x".to_string();
+        for depth in 0..3 {
+            assert_eq!(
+                envs[3].borrow().get_with_fallback(&x, depth).unwrap(),
+                Value::Int(42)
+            );
+        }
+        assert_eq!(
+            envs[1].borrow().get_with_fallback(&x, 0).unwrap(),
+            Value::Int(2)
+        );
+    }
+
+    #[test]
+    fn test_environment_deep_chain() {
+        let root = Environment::new_wrapped();
+        root.borrow_mut().set("x", 1);
+
+        let mut envs = vec![root];
+        for _ in 0..100_000 {
+            let env = Environment::new_wrapped();
+            env.borrow_mut()
+                .set_parent(weak_clone(envs.last().unwrap()));
+            env.borrow_mut().set("y", 2);
+            envs.push(env);
+        }
+
+        // walk the whole chain rather than jumping to the global frame
+        let leaf = envs.last().unwrap();
+        let x = "x".to_string();
+        leaf.borrow_mut().update("x", 3).unwrap();
+        assert_eq!(
+            leaf.borrow().get_with_fallback(&x, usize::MAX).unwrap(),
+            Value::Int(3)
+        );
+        assert!(leaf
+            .borrow()
+            .get_with_fallback(&"z".to_string(), usize::MAX)
+            .is_err());
     }
 }
@@ -1,16 +1,159 @@
-use thiserror::Error;
+use std::fmt::Display;
 
-#[derive(Error, Debug)]
+use diagnostics::{message, Code, Message};
+
+use crate::BytecodeVersion;
+
+#[derive(Debug)]
 pub enum ByteCodeError {
-    #[error("Type mismatch, expected {expected}, found {found}")]
-    TypeMismatch { expected: String, found: String },
+    TypeMismatch {
+        expected: String,
+        found: String,
+    },
+    BadType {
+        expected: String,
+        found: String,
+    },
+    UnboundedName {
+        name: String,
+    },
+    BadMagic,
+    UnsupportedVersion {
+        found: BytecodeVersion,
+        expected: BytecodeVersion,
+    },
+    BuiltinsChanged {
+        found: u64,
+        expected: u64,
+    },
+    CantMigrate(BytecodeVersion),
+    Truncated {
+        expected: u64,
+        found: u64,
+    },
+    SocketClosed,
+    ByteIndexOutOfBounds {
+        index: i64,
+        len: usize,
+    },
+    IndexOutOfBounds {
+        index: i64,
+        len: usize,
+    },
+    KeyNotFound(String),
+    EmptyArray(String),
+    NotAByte(i64),
+    UnknownEncoding(String),
+    BadFormat(String),
+    NegativePrecision(i64),
+    EmptyPattern(String),
+    NotACharBoundary(i64),
+    DivisionByZero(String),
+    IntegerOverflow(String),
+    AssertionFailed(String),
+    EnvironmentDroppedError,
+}
 
-    #[error("Bad type, expected {expected}, found {found}")]
-    BadType { expected: String, found: String },
+impl ByteCodeError {
+    /// The message of the error, in the language of the current catalog, see [`diagnostics::set_catalog`].
+    pub fn message(&self) -> Message {
+        match self {
+            ByteCodeError::TypeMismatch { expected, found } => message!(
+                R006,
+                "Type mismatch, expected {}, found {}",
+                expected,
+                found
+            ),
+            ByteCodeError::BadType { expected, found } => {
+                message!(R006, "Bad type, expected {}, found {}", expected, found)
+            }
+            ByteCodeError::UnboundedName { name } => message!(R008, "Unbounded name: {}", name),
+            ByteCodeError::BadMagic => {
+                message!(R011, "Not a bytecode file, the magic bytes are missing")
+            }
+            ByteCodeError::UnsupportedVersion { found, expected } => message!(
+                R011,
+                "Unsupported bytecode version {}, this VM runs {}. Recompile the program, or migrate it with ignite migrate if it is older",
+                found,
+                expected
+            ),
+            ByteCodeError::BuiltinsChanged { found, expected } => message!(
+                R011,
+                "The program was compiled against other builtins (hash {}, this VM has {}). Recompile the program",
+                format!("{:016x}", found),
+                format!("{:016x}", expected)
+            ),
+            ByteCodeError::CantMigrate(version) => message!(
+                R011,
+                "Can't migrate the program from bytecode version {}, its instructions have changed since. Recompile the program",
+                version
+            ),
+            ByteCodeError::Truncated { expected, found } => message!(
+                R011,
+                "The program is cut short, its header gives {} bytes of instructions but only {} follow",
+                expected,
+                found
+            ),
+            ByteCodeError::SocketClosed => message!(R012, "Socket is closed"),
+            ByteCodeError::ByteIndexOutOfBounds { len, index } => message!(
+                R003,
+                "Byte index out of bounds: the length is {} but the index is {}",
+                len,
+                index
+            ),
+            ByteCodeError::IndexOutOfBounds { len, index } => message!(
+                R003,
+                "Index out of bounds: the length is {} but the index is {}",
+                len,
+                index
+            ),
+            ByteCodeError::KeyNotFound(key) => message!(R004, "Key not found: {}", key),
+            ByteCodeError::EmptyArray(op) => message!(R005, "{} of an empty array or slice", op),
+            ByteCodeError::NotAByte(n) => {
+                message!(R007, "{} is not a byte, bytes are from 0 to 255", n)
+            }
+            ByteCodeError::UnknownEncoding(encoding) => message!(
+                R007,
+                "Unknown encoding {}, expected utf-8, ascii or latin-1",
+                encoding
+            ),
+            ByteCodeError::BadFormat(msg) => message!(R007, "Bad format string: {}", msg),
+            ByteCodeError::NegativePrecision(precision) => message!(
+                R007,
+                "Precision must be 0 or more digits, got {}",
+                precision
+            ),
+            ByteCodeError::EmptyPattern(sym) => {
+                message!(R007, "{} needs a string to look for, got an empty one", sym)
+            }
+            ByteCodeError::NotACharBoundary(index) => message!(
+                R007,
+                "Byte index {} is inside a character, not between two",
+                index
+            ),
+            ByteCodeError::DivisionByZero(sym) => message!(R001, "Division by zero in {}", sym),
+            ByteCodeError::IntegerOverflow(sym) => message!(
+                R002,
+                "Integer overflow: the result of {} doesn't fit in an int",
+                sym
+            ),
+            ByteCodeError::AssertionFailed(msg) => message!(R015, "Assertion failed: {}", msg),
+            ByteCodeError::EnvironmentDroppedError => {
+                message!(R011, "Environment access after drop")
+            }
+        }
+    }
 
-    #[error("Unbounded name: {name}")]
-    UnboundedName { name: String },
+    /// The code of the error, see [`diagnostics`].
+    pub fn code(&self) -> Code {
+        self.message().code
+    }
+}
 
-    #[error("Environment access after drop")]
-    EnvironmentDroppedError,
+impl Display for ByteCodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message().text)
+    }
 }
+
+impl std::error::Error for ByteCodeError {}
@@ -1,14 +1,110 @@
-use std::io::{Read, Write};
+use std::{
+    collections::HashSet,
+    fmt::Display,
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    path::Path,
+};
 
 use anyhow::Result;
 
-use crate::ByteCode;
+use crate::{ByteCode, ByteCodeError, Environment, Value};
+
+/// Magic bytes at the start of every serialized program, to tell a compiled program from any other file.
+pub const BYTECODE_MAGIC: [u8; 4] = *b"O2BC";
+
+/// Version of the serialized format. Bump the minor version when a change only adds to what can be serialized,
+/// like a new instruction at the end of `ByteCode`, so programs compiled before it still read the same. Bump the
+/// major version when a change makes older programs read differently, like reordering or changing an instruction.
+pub const BYTECODE_VERSION: BytecodeVersion = BytecodeVersion { major: 2, minor: 4 };
+
+/// The first version whose builtin set hash only covers the builtins the program loads. Before it the hash covered
+/// every builtin, so adding one made every program compiled earlier fail to read.
+pub const BYTECODE_VERSION_LOADED_BUILTINS: BytecodeVersion =
+    BytecodeVersion { major: 2, minor: 4 };
+
+/// The first version of the format, which had a 2 byte version and no builtin set hash. Its programs can be
+/// migrated with `migrate_bytecode`.
+pub const BYTECODE_VERSION_1: BytecodeVersion = BytecodeVersion { major: 1, minor: 0 };
+
+/// The version of the format a program was serialized with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct BytecodeVersion {
+    pub major: u16,
+    pub minor: u16,
+}
+
+impl BytecodeVersion {
+    /// If a reader of this version can read a program serialized with the other: the major versions are the same
+    /// and the program doesn't use anything added in a later minor version.
+    pub fn can_read(&self, other: &BytecodeVersion) -> bool {
+        self.major == other.major && self.minor >= other.minor
+    }
+}
+
+impl Display for BytecodeVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+/// Hash of the builtins and constants in the global environment that the program loads, with the parameters of
+/// each builtin. A program is compiled against these, so one compiled by a VM with other builtins may call a function
+/// that isn't there or pass it the wrong arguments. Builtins the program doesn't load don't count, so adding a
+/// builtin doesn't stop programs compiled before it from reading.
+///
+/// The hash is FNV-1a, which unlike the hasher of the standard library is the same on every build.
+pub fn builtin_set_hash(bytecode: &[ByteCode]) -> u64 {
+    let loaded: HashSet<&str> = bytecode
+        .iter()
+        .filter_map(|instr| match instr {
+            ByteCode::LD(sym) => Some(sym.as_str()),
+            _ => None,
+        })
+        .collect();
+    hash_builtins(|name| loaded.contains(name))
+}
+
+// The hash of versions before BYTECODE_VERSION_LOADED_BUILTINS, over every builtin
+fn all_builtins_hash() -> u64 {
+    hash_builtins(|_| true)
+}
+
+fn hash_builtins(include: impl Fn(&str) -> bool) -> u64 {
+    const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let global = Environment::new_global_wrapped();
+    let mut names: Vec<String> = global
+        .borrow()
+        .env
+        .iter()
+        .filter(|(name, _)| include(name))
+        .map(|(name, val)| match val {
+            Value::Closure { prms, .. } => format!("{}({})", name, prms.join(",")),
+            _ => name.to_string(),
+        })
+        .collect();
+    names.sort();
+
+    names
+        .iter()
+        .flat_map(|name| name.bytes().chain([0]))
+        .fold(FNV_OFFSET, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+        })
+}
 
 /// Serialize the bytecode to the writer.
 /// The serialized format is:
+/// - 4 bytes of magic, `BYTECODE_MAGIC`
+/// - 2 bytes each for the major and minor format version, `BYTECODE_VERSION`
+/// - 8 bytes for the hash of the builtins the program loads, `builtin_set_hash`
 /// - 8 bytes for the length of the serialized bytecode
 /// - The serialized bytecode
 ///
+/// All integers are little endian.
+///
 /// # Arguments
 /// - `bytecode`: The bytecode to serialize
 /// - `writer`: The writer to write the serialized bytecode to
@@ -18,31 +114,176 @@ use crate::ByteCode;
 pub fn write_bytecode<W: Write>(bytecode: &[ByteCode], writer: &mut W) -> Result<()> {
     let serialized = bincode::serialize(bytecode)?;
     let len = serialized.len() as u64;
+    writer.write_all(&BYTECODE_MAGIC)?;
+    writer.write_all(&BYTECODE_VERSION.major.to_le_bytes())?;
+    writer.write_all(&BYTECODE_VERSION.minor.to_le_bytes())?;
+    writer.write_all(&builtin_set_hash(bytecode).to_le_bytes())?;
     writer.write_all(&len.to_le_bytes())?;
     writer.write_all(&serialized)?;
     Ok(())
 }
 
 /// Deserialize the bytecode from the reader.
-/// The serialized format is:
-/// - 8 bytes for the length of the serialized bytecode
-/// - The serialized bytecode
+/// The serialized format is as for `write_bytecode`.
 ///
 /// # Arguments
 /// - `reader`: The reader to read the serialized bytecode from
 ///
 /// # Returns
 /// - `Result<Vec<ByteCode>>`: The result of the deserialization
+///
+/// # Errors
+/// - `ByteCodeError::BadMagic` if the reader does not start with `BYTECODE_MAGIC`
+/// - `ByteCodeError::UnsupportedVersion` if the program was serialized with a version this one can't read
+/// - `ByteCodeError::BuiltinsChanged` if the program was compiled against other builtins
+/// - `ByteCodeError::Truncated` if the file ends before the instructions its header gives
 pub fn read_bytecode<R: Read>(reader: &mut R) -> Result<Vec<ByteCode>> {
-    let mut len_bytes = [0; 8];
-    reader.read_exact(&mut len_bytes)?;
-    let len = u64::from_le_bytes(len_bytes) as usize;
-    let mut serialized = vec![0; len];
-    reader.read_exact(&mut serialized)?;
+    let version = read_version(reader)?;
+    if !BYTECODE_VERSION.can_read(&version) {
+        return Err(ByteCodeError::UnsupportedVersion {
+            found: version,
+            expected: BYTECODE_VERSION,
+        }
+        .into());
+    }
+
+    let hash = read_u64(reader)?;
+    let bytecode = read_serialized(reader)?;
+    check_builtin_set_hash(hash, &version, &bytecode)?;
+    Ok(bytecode)
+}
+
+/// Deserialize bytecode serialized with an older version of the format, so it can be written again with the
+/// current one. Programs of the current version are read as by `read_bytecode`.
+///
+/// Version 1 programs can be migrated if their instructions still read the same, which they do unless they use an
+/// instruction that has since changed. They didn't record the builtins they were compiled against, so a program
+/// that calls a builtin that has since been removed only fails when it runs.
+///
+/// Programs from before `BYTECODE_VERSION_LOADED_BUILTINS` hashed every builtin, so their hash no longer matches once
+/// a builtin has been added. Like version 1 programs, they are migrated without checking it.
+///
+/// # Returns
+/// - The version the program was serialized with, and its bytecode
+///
+/// # Errors
+/// - As for `read_bytecode`, if the program is of a version that can't be migrated
+/// - `ByteCodeError::CantMigrate` if the instructions of the program don't read the same anymore
+pub fn migrate_bytecode<R: Read>(reader: &mut R) -> Result<(BytecodeVersion, Vec<ByteCode>)> {
+    let version = read_version(reader)?;
+    if version != BYTECODE_VERSION_1 {
+        if !BYTECODE_VERSION.can_read(&version) {
+            return Err(ByteCodeError::UnsupportedVersion {
+                found: version,
+                expected: BYTECODE_VERSION,
+            }
+            .into());
+        }
+        let hash = read_u64(reader)?;
+        let bytecode = read_serialized(reader)?;
+        if version >= BYTECODE_VERSION_LOADED_BUILTINS {
+            check_builtin_set_hash(hash, &version, &bytecode)?;
+        }
+        return Ok((version, bytecode));
+    }
+
+    let serialized = read_instructions(reader)?;
+    let bytecode =
+        bincode::deserialize(&serialized).map_err(|_| ByteCodeError::CantMigrate(version))?;
+    Ok((version, bytecode))
+}
+
+// Read the magic and the version after it. Version 1 only had the major version.
+fn read_version<R: Read>(reader: &mut R) -> Result<BytecodeVersion> {
+    let mut magic = [0; 4];
+    if reader.read_exact(&mut magic).is_err() || magic != BYTECODE_MAGIC {
+        return Err(ByteCodeError::BadMagic.into());
+    }
+
+    let major = read_u16(reader)?;
+    if major == BYTECODE_VERSION_1.major {
+        return Ok(BYTECODE_VERSION_1);
+    }
+
+    let minor = read_u16(reader)?;
+    Ok(BytecodeVersion { major, minor })
+}
+
+fn check_builtin_set_hash(
+    found: u64,
+    version: &BytecodeVersion,
+    bytecode: &[ByteCode],
+) -> Result<()> {
+    let expected = if *version >= BYTECODE_VERSION_LOADED_BUILTINS {
+        builtin_set_hash(bytecode)
+    } else {
+        all_builtins_hash()
+    };
+    if found != expected {
+        return Err(ByteCodeError::BuiltinsChanged { found, expected }.into());
+    }
+    Ok(())
+}
+
+fn read_serialized<R: Read>(reader: &mut R) -> Result<Vec<ByteCode>> {
+    let serialized = read_instructions(reader)?;
     let bytecode = bincode::deserialize(&serialized)?;
     Ok(bytecode)
 }
 
+// Read the length and the instructions after it. The length comes from the file, so it is only trusted as far as
+// there are bytes to back it, instead of allocating it all up front.
+fn read_instructions<R: Read>(reader: &mut R) -> Result<Vec<u8>> {
+    let len = read_u64(reader)?;
+
+    let mut serialized = Vec::new();
+    let found = reader.take(len).read_to_end(&mut serialized)? as u64;
+    if found != len {
+        return Err(ByteCodeError::Truncated {
+            expected: len,
+            found,
+        }
+        .into());
+    }
+    Ok(serialized)
+}
+
+fn read_u16<R: Read>(reader: &mut R) -> Result<u16> {
+    let mut bytes = [0; 2];
+    reader.read_exact(&mut bytes)?;
+    Ok(u16::from_le_bytes(bytes))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+/// Serialize the bytecode to a file, creating it or replacing what it holds.
+///
+/// # Arguments
+/// - `bytecode`: The bytecode to serialize
+/// - `path`: The file to write to, usually ending in .o2
+pub fn write_to_file<P: AsRef<Path>>(bytecode: &[ByteCode], path: P) -> Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_bytecode(bytecode, &mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Deserialize the bytecode from a file written by `write_to_file`.
+///
+/// # Arguments
+/// - `path`: The file to read from
+///
+/// # Errors
+/// As for `read_bytecode`, or if the file can't be opened.
+pub fn read_from_file<P: AsRef<Path>>(path: P) -> Result<Vec<ByteCode>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    read_bytecode(&mut reader)
+}
+
 #[cfg(test)]
 mod tests {
     use super::super::*;
@@ -85,4 +326,185 @@ mod tests {
         // remove file
         std::fs::remove_file("test.o2").unwrap();
     }
+
+    #[test]
+    fn test_serialization_header() {
+        let mut serialized = Vec::new();
+        write_bytecode(&[ByteCode::DONE], &mut serialized).unwrap();
+        assert_eq!(serialized[..4], BYTECODE_MAGIC);
+        assert_eq!(serialized[4..6], BYTECODE_VERSION.major.to_le_bytes());
+        assert_eq!(serialized[6..8], BYTECODE_VERSION.minor.to_le_bytes());
+        assert_eq!(
+            serialized[8..16],
+            builtin_set_hash(&[ByteCode::DONE]).to_le_bytes()
+        );
+
+        // bad magic, including a file too short to have any
+        let err = read_bytecode(&mut b"not bytecode".as_slice()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Not a bytecode file, the magic bytes are missing"
+        );
+        assert!(read_bytecode(&mut b"O2".as_slice()).is_err());
+
+        let mut newer_minor = serialized.clone();
+        newer_minor[6..8].copy_from_slice(&(BYTECODE_VERSION.minor + 1).to_le_bytes());
+        let err = read_bytecode(&mut newer_minor.as_slice()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "Unsupported bytecode version {}.{}, this VM runs {}. Recompile the program, or migrate it with ignite migrate if it is older",
+                BYTECODE_VERSION.major,
+                BYTECODE_VERSION.minor + 1,
+                BYTECODE_VERSION
+            )
+        );
+
+        let mut other_builtins = serialized.clone();
+        other_builtins[8..16].copy_from_slice(&0u64.to_le_bytes());
+        let err = read_bytecode(&mut other_builtins.as_slice()).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("The program was compiled against other builtins (hash 0000000000000000"));
+    }
+
+    #[test]
+    fn test_read_bad_length() {
+        let bc = vec![ByteCode::ldc(1), ByteCode::POP, ByteCode::DONE];
+        let mut serialized = Vec::new();
+        write_bytecode(&bc, &mut serialized).unwrap();
+        let len = serialized.len() as u64 - 24;
+
+        // the file ends before the instructions do
+        let truncated = &serialized[..serialized.len() - 3];
+        let err = read_bytecode(&mut &truncated[..]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "The program is cut short, its header gives {} bytes of instructions but only {} follow",
+                len,
+                len - 3
+            )
+        );
+
+        // a length far past the end of the file is not allocated up front
+        for bad_len in [1u64 << 62, u64::MAX] {
+            let mut oversized = serialized.clone();
+            oversized[16..24].copy_from_slice(&bad_len.to_le_bytes());
+            let err = read_bytecode(&mut oversized.as_slice()).unwrap_err();
+            assert!(matches!(
+                err.downcast_ref::<ByteCodeError>(),
+                Some(ByteCodeError::Truncated { expected, found }) if *expected == bad_len && *found == len
+            ));
+        }
+    }
+
+    #[test]
+    fn test_version_can_read() {
+        let v2_1 = BytecodeVersion { major: 2, minor: 1 };
+        assert!(v2_1.can_read(&BytecodeVersion { major: 2, minor: 0 }));
+        assert!(v2_1.can_read(&v2_1));
+        assert!(!v2_1.can_read(&BytecodeVersion { major: 2, minor: 2 }));
+        assert!(!v2_1.can_read(&BYTECODE_VERSION_1));
+        assert!(!v2_1.can_read(&BytecodeVersion { major: 3, minor: 0 }));
+        assert_eq!(v2_1.to_string(), "2.1");
+    }
+
+    #[test]
+    fn test_builtin_set_hash() {
+        let prints = [ByteCode::ld("println"), ByteCode::ldc(1), ByteCode::CALL(1)];
+        // the same on every call, so programs compiled by one run of the VM can be read by the next
+        assert_eq!(builtin_set_hash(&prints), builtin_set_hash(&prints));
+        assert_ne!(builtin_set_hash(&prints), 0);
+
+        // only the builtins loaded count, not other names or builtins the program doesn't use
+        let with_local = [
+            ByteCode::ld("x"),
+            ByteCode::ld("println"),
+            ByteCode::assign("len"),
+        ];
+        assert_eq!(builtin_set_hash(&prints), builtin_set_hash(&with_local));
+        assert_ne!(
+            builtin_set_hash(&prints),
+            builtin_set_hash(&[ByteCode::ld("len")])
+        );
+        assert_eq!(
+            builtin_set_hash(&[]),
+            builtin_set_hash(&[ByteCode::ld("x")])
+        );
+    }
+
+    #[test]
+    fn test_read_older_builtin_set_hash() {
+        // a program of a version that hashed every builtin, compiled by a VM with other builtins
+        let bc = vec![ByteCode::ld("println"), ByteCode::POP, ByteCode::DONE];
+        let mut serialized = Vec::new();
+        write_bytecode(&bc, &mut serialized).unwrap();
+        serialized[6..8].copy_from_slice(&3u16.to_le_bytes());
+        let err = read_bytecode(&mut serialized.as_slice()).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("The program was compiled against other builtins"));
+
+        // its hash is right if it has every builtin of this VM
+        serialized[8..16].copy_from_slice(&super::all_builtins_hash().to_le_bytes());
+        assert_eq!(read_bytecode(&mut serialized.as_slice()).unwrap(), bc);
+
+        // and it can be migrated either way
+        serialized[8..16].copy_from_slice(&0u64.to_le_bytes());
+        let (version, migrated) = migrate_bytecode(&mut serialized.as_slice()).unwrap();
+        assert_eq!(version, BytecodeVersion { major: 2, minor: 3 });
+        assert_eq!(migrated, bc);
+    }
+
+    // A program as version 1 wrote it: a 2 byte version and no builtin set hash
+    fn serialize_v1(bytecode: &[u8]) -> Vec<u8> {
+        let mut serialized = BYTECODE_MAGIC.to_vec();
+        serialized.extend(1u16.to_le_bytes());
+        serialized.extend((bytecode.len() as u64).to_le_bytes());
+        serialized.extend(bytecode);
+        serialized
+    }
+
+    #[test]
+    fn test_migrate_bytecode() {
+        let bc = vec![ByteCode::ldc(1), ByteCode::POP, ByteCode::DONE];
+        let v1 = serialize_v1(&bincode::serialize(&bc).unwrap());
+
+        let err = read_bytecode(&mut v1.as_slice()).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("Unsupported bytecode version 1.0, this VM runs"));
+
+        let (version, migrated) = migrate_bytecode(&mut v1.as_slice()).unwrap();
+        assert_eq!(version, BYTECODE_VERSION_1);
+        assert_eq!(migrated, bc);
+
+        // a program of the current version is read as it is
+        let mut current = Vec::new();
+        write_bytecode(&bc, &mut current).unwrap();
+        let (version, migrated) = migrate_bytecode(&mut current.as_slice()).unwrap();
+        assert_eq!(version, BYTECODE_VERSION);
+        assert_eq!(migrated, bc);
+
+        // an instruction that doesn't read the same anymore
+        let bad = serialize_v1(&[1, 0, 0, 0, 0, 0, 0, 0, 255, 0, 0, 0]);
+        let err = migrate_bytecode(&mut bad.as_slice()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Can't migrate the program from bytecode version 1.0, its instructions have changed since. Recompile the program"
+        );
+    }
+
+    #[test]
+    fn test_read_write_file() {
+        let bc = vec![ByteCode::ldc("hello"), ByteCode::POP, ByteCode::DONE];
+        let path = std::env::temp_dir().join(format!("io_test_{}.o2", std::process::id()));
+
+        write_to_file(&bc, &path).unwrap();
+        let deserialized = read_from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(bc, deserialized);
+    }
 }
@@ -1,20 +1,42 @@
+pub use array::*;
 pub use bytecode::*;
+pub use bytes::*;
+pub use channel::*;
+pub use disasm::*;
 pub use environment::*;
 pub use error::*;
 pub use io::*;
+pub use line_table::*;
+pub use map::*;
 pub use operator::*;
+pub use persist::*;
 pub use prelude::*;
 pub use semaphore::*;
+pub use socket::*;
 pub use stack_frame::*;
+pub use struct_::*;
+pub use tuple::*;
 pub use value::*;
+pub use variant::*;
 
+mod array;
 pub mod builtin;
 mod bytecode;
+mod bytes;
+mod channel;
+mod disasm;
 mod environment;
 mod error;
 mod io;
+mod line_table;
+mod map;
 mod operator;
+mod persist;
 mod prelude;
 mod semaphore;
+mod socket;
 mod stack_frame;
+mod struct_;
+mod tuple;
 mod value;
+mod variant;
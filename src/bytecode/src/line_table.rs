@@ -0,0 +1,93 @@
+/// The source line each instruction was compiled from, for tools that show where the program is, like the
+/// debugger of ignite. Stored as the pc where each run of instructions from one line starts, in order of pc.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LineTable {
+    // (pc, line) where the instructions from pc up to the next entry are from line
+    entries: Vec<(usize, usize)>,
+}
+
+impl LineTable {
+    pub fn new() -> Self {
+        LineTable::default()
+    }
+
+    /// Record that the instructions from pc on are from line. pc must not be before the last pc recorded.
+    pub fn push(&mut self, pc: usize, line: usize) {
+        if let Some(last) = self.entries.last_mut() {
+            debug_assert!(
+                last.0 <= pc,
+                "line table entries should be pushed in order of pc"
+            );
+
+            // Nothing was compiled for the last line, so only the new one is kept
+            if last.0 == pc {
+                *last = (pc, line);
+                self.dedup_last();
+                return;
+            }
+
+            if last.1 == line {
+                return;
+            }
+        }
+
+        self.entries.push((pc, line));
+    }
+
+    // The last entry continues the line of the one before it, so it doesn't start a run
+    fn dedup_last(&mut self) {
+        let len = self.entries.len();
+        if len >= 2 && self.entries[len - 2].1 == self.entries[len - 1].1 {
+            self.entries.pop();
+        }
+    }
+
+    /// The line the instruction at pc was compiled from, or None if it is before the first line recorded.
+    pub fn line(&self, pc: usize) -> Option<usize> {
+        let idx = self.entries.partition_point(|&(start, _)| start <= pc);
+        idx.checked_sub(1).map(|idx| self.entries[idx].1)
+    }
+
+    /// The pcs where the runs of instructions from line start. A line has more than one if code from other lines
+    /// is compiled in the middle of it, like the body of a fn declared on it.
+    pub fn pcs(&self, line: usize) -> Vec<usize> {
+        self.entries
+            .iter()
+            .filter(|&&(_, l)| l == line)
+            .map(|&(pc, _)| pc)
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_table() {
+        let mut lines = LineTable::new();
+        assert_eq!(lines.line(0), None);
+
+        lines.push(1, 1);
+        lines.push(3, 1);
+        lines.push(4, 2);
+        lines.push(4, 3);
+        lines.push(6, 1);
+        lines.push(6, 3);
+
+        assert_eq!(lines.line(0), None);
+        assert_eq!(lines.line(2), Some(1));
+        assert_eq!(lines.line(3), Some(1));
+        assert_eq!(lines.line(5), Some(3));
+        assert_eq!(lines.line(100), Some(3));
+
+        // 4 to 6 continue on line 3 since nothing was compiled for line 1 at 6
+        assert_eq!(lines.pcs(1), vec![1]);
+        assert_eq!(lines.pcs(3), vec![4]);
+        assert!(lines.pcs(2).is_empty());
+    }
+}
@@ -0,0 +1,196 @@
+use std::{cell::RefCell, collections::BTreeMap, fmt::Debug, rc::Rc};
+
+use anyhow::Result;
+
+use crate::{type_of, ByteCodeError, Value};
+
+/// A map from int, string or bool keys to values, like `#{"a": 1}`. Like arrays, maps have reference semantics:
+/// cloning the value shares the entries, so an insert through one handle is seen through every other.
+#[derive(Clone)]
+pub struct Map(Rc<RefCell<BTreeMap<MapKey, Value>>>);
+
+// Keys of different types are ordered by type, ints then strings then bools, like the keys of a PMap
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum MapKey {
+    Int(i64),
+    String(Rc<str>),
+    Bool(bool),
+}
+
+impl TryFrom<&Value> for MapKey {
+    type Error = anyhow::Error;
+
+    fn try_from(key: &Value) -> Result<Self> {
+        match key {
+            Value::Int(i) => Ok(MapKey::Int(*i)),
+            Value::String(s) => Ok(MapKey::String(s.clone())),
+            Value::Bool(b) => Ok(MapKey::Bool(*b)),
+            _ => Err(ByteCodeError::BadType {
+                expected: "int, string or bool key".to_string(),
+                found: type_of(key).to_string(),
+            }
+            .into()),
+        }
+    }
+}
+
+impl From<&MapKey> for Value {
+    fn from(key: &MapKey) -> Self {
+        match key {
+            MapKey::Int(i) => Value::Int(*i),
+            MapKey::String(s) => Value::String(s.clone()),
+            MapKey::Bool(b) => Value::Bool(*b),
+        }
+    }
+}
+
+impl Map {
+    pub fn new() -> Self {
+        Map(Rc::new(RefCell::new(BTreeMap::new())))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.borrow().is_empty()
+    }
+
+    /// The value of a key, or None if the map doesn't have it.
+    ///
+    /// # Errors
+    ///
+    /// If the key is not an int, string or bool.
+    pub fn get(&self, key: &Value) -> Result<Option<Value>> {
+        let key = MapKey::try_from(key)?;
+        Ok(self.0.borrow().get(&key).cloned())
+    }
+
+    /// Set the value of a key, and return the value it had before if any.
+    ///
+    /// # Errors
+    ///
+    /// If the key is not an int, string or bool.
+    pub fn insert(&self, key: Value, val: Value) -> Result<Option<Value>> {
+        let key = MapKey::try_from(&key)?;
+        Ok(self.0.borrow_mut().insert(key, val))
+    }
+
+    /// Remove a key, and return the value it had if any.
+    ///
+    /// # Errors
+    ///
+    /// If the key is not an int, string or bool.
+    pub fn remove(&self, key: &Value) -> Result<Option<Value>> {
+        let key = MapKey::try_from(key)?;
+        Ok(self.0.borrow_mut().remove(&key))
+    }
+
+    /// Copy out the keys in order.
+    pub fn keys(&self) -> Vec<Value> {
+        self.0.borrow().keys().map(Value::from).collect()
+    }
+
+    /// Copy out the entries in the order of their keys.
+    pub fn entries(&self) -> Vec<(Value, Value)> {
+        self.0
+            .borrow()
+            .iter()
+            .map(|(key, val)| (key.into(), val.clone()))
+            .collect()
+    }
+
+    /// The address of the entries, the same for every handle that shares them.
+    pub fn as_ptr(&self) -> *const () {
+        Rc::as_ptr(&self.0) as *const ()
+    }
+
+    /// Copy the entries, including nested arrays, structs and maps, so the result shares nothing with self.
+    pub fn deep_clone(&self) -> Self {
+        let entries = self
+            .0
+            .borrow()
+            .iter()
+            .map(|(key, val)| {
+                let val = match val {
+                    Value::Array(arr) => Value::Array(arr.deep_clone()),
+                    Value::Struct(s) => Value::Struct(s.deep_clone()),
+                    Value::Variant(v) => Value::Variant(v.deep_clone()),
+                    Value::Tuple(t) => Value::Tuple(t.deep_clone()),
+                    Value::Map(m) => Value::Map(m.deep_clone()),
+                    _ => val.clone(),
+                };
+                (key.clone(), val)
+            })
+            .collect();
+
+        Map(Rc::new(RefCell::new(entries)))
+    }
+}
+
+impl Default for Map {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PartialEq for Map {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0) || *self.0.borrow() == *other.0.borrow()
+    }
+}
+
+impl Debug for Map {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let entries: Vec<String> = self
+            .entries()
+            .iter()
+            .map(|(key, val)| format!("{:?}: {:?}", key, val))
+            .collect();
+        write!(f, "#{{{}}}", entries.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map() -> Result<()> {
+        let m = Map::new();
+        assert!(m.is_empty());
+        assert_eq!(m.insert("b".into(), Value::Int(2))?, None);
+        assert_eq!(m.insert(Value::Int(1), Value::Int(1))?, None);
+        assert_eq!(m.insert(Value::Bool(true), Value::Int(3))?, None);
+        assert_eq!(m.insert("b".into(), Value::Int(4))?, Some(Value::Int(2)));
+
+        // shared through clones
+        let other = m.clone();
+        assert_eq!(other.get(&"b".into())?, Some(Value::Int(4)));
+        assert_eq!(other.len(), 3);
+        assert_eq!(m.keys(), vec![Value::Int(1), "b".into(), Value::Bool(true)]);
+
+        assert_eq!(m.remove(&Value::Int(1))?, Some(Value::Int(1)));
+        assert_eq!(m.remove(&Value::Int(1))?, None);
+        assert_eq!(other.get(&Value::Int(1))?, None);
+
+        assert!(m.insert(Value::Float(1.0), Value::Unit).is_err());
+        assert!(m.get(&Value::Unit).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_map_deep_clone() -> Result<()> {
+        let m = Map::new();
+        let inner = Map::new();
+        inner.insert(Value::Int(1), Value::Int(1))?;
+        m.insert("a".into(), inner.clone().into())?;
+
+        let copy = m.deep_clone();
+        assert_eq!(copy, m);
+        inner.insert(Value::Int(2), Value::Int(2))?;
+        assert_ne!(copy, m);
+        Ok(())
+    }
+}
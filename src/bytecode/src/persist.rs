@@ -0,0 +1,525 @@
+use std::{cmp::Ordering, fmt::Debug, rc::Rc};
+
+use anyhow::Result;
+
+use crate::{type_of, ByteCodeError, Value};
+
+// Each node of a PVec holds up to 32 children or values, picked by 5 bits of the index
+const BITS: u32 = 5;
+const WIDTH: usize = 1 << BITS;
+const MASK: usize = WIDTH - 1;
+
+/// A persistent vector: pushing or setting an element gives a new vector and leaves the old one as it was.
+/// The two share every node but the ones on the path to the element, so each takes O(log32 n) time and space
+/// instead of the O(n) of copying an array.
+#[derive(Clone)]
+pub struct PVec {
+    root: Rc<VecNode>,
+    len: usize,
+    // How far the index is shifted to pick the child of the root, BITS times the height of the tree
+    shift: u32,
+}
+
+enum VecNode {
+    Branch(Vec<Rc<VecNode>>),
+    Leaf(Vec<Value>),
+}
+
+impl PVec {
+    pub fn new() -> Self {
+        PVec {
+            root: Rc::new(VecNode::Leaf(vec![])),
+            len: 0,
+            shift: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// # Errors
+    ///
+    /// If the index is out of bounds.
+    pub fn get(&self, idx: i64) -> Result<Value> {
+        let idx = self.check_idx(idx)?;
+        let mut node = &self.root;
+        let mut shift = self.shift;
+        loop {
+            match node.as_ref() {
+                VecNode::Branch(children) => node = &children[(idx >> shift) & MASK],
+                VecNode::Leaf(vals) => return Ok(vals[idx & MASK].clone()),
+            }
+            shift -= BITS;
+        }
+    }
+
+    /// A vector with the value at the index replaced.
+    ///
+    /// # Errors
+    ///
+    /// If the index is out of bounds.
+    pub fn set(&self, idx: i64, val: Value) -> Result<Self> {
+        let idx = self.check_idx(idx)?;
+        Ok(PVec {
+            root: Rc::new(set_in(&self.root, self.shift, idx, val)),
+            len: self.len,
+            shift: self.shift,
+        })
+    }
+
+    /// A vector with the value added to the end.
+    pub fn push(&self, val: Value) -> Self {
+        // A full tree gets a new root above it, with the old tree as its first child
+        if self.len == WIDTH << self.shift {
+            let path = new_path(self.shift, val);
+            return PVec {
+                root: Rc::new(VecNode::Branch(vec![self.root.clone(), Rc::new(path)])),
+                len: self.len + 1,
+                shift: self.shift + BITS,
+            };
+        }
+
+        PVec {
+            root: Rc::new(push_in(&self.root, self.shift, self.len, val)),
+            len: self.len + 1,
+            shift: self.shift,
+        }
+    }
+
+    /// Copy out the values in order.
+    pub fn to_vec(&self) -> Vec<Value> {
+        let mut vals = Vec::with_capacity(self.len);
+        collect(&self.root, &mut vals);
+        vals
+    }
+
+    fn check_idx(&self, idx: i64) -> Result<usize> {
+        if idx < 0 || idx as usize >= self.len {
+            return Err(ByteCodeError::IndexOutOfBounds {
+                index: idx,
+                len: self.len,
+            }
+            .into());
+        }
+        Ok(idx as usize)
+    }
+}
+
+impl Default for PVec {
+    fn default() -> Self {
+        PVec::new()
+    }
+}
+
+impl FromIterator<Value> for PVec {
+    fn from_iter<T: IntoIterator<Item = Value>>(iter: T) -> Self {
+        iter.into_iter()
+            .fold(PVec::new(), |pvec, val| pvec.push(val))
+    }
+}
+
+/// Vectors are equal if their elements are, whether or not they share nodes.
+impl PartialEq for PVec {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.to_vec() == other.to_vec()
+    }
+}
+
+impl Debug for PVec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PVec({:?})", self.to_vec())
+    }
+}
+
+fn set_in(node: &VecNode, shift: u32, idx: usize, val: Value) -> VecNode {
+    match node {
+        VecNode::Branch(children) => {
+            let mut children = children.clone();
+            let child = (idx >> shift) & MASK;
+            children[child] = Rc::new(set_in(&children[child], shift - BITS, idx, val));
+            VecNode::Branch(children)
+        }
+        VecNode::Leaf(vals) => {
+            let mut vals = vals.clone();
+            vals[idx & MASK] = val;
+            VecNode::Leaf(vals)
+        }
+    }
+}
+
+// Push into a tree that has room, where idx is the current length
+fn push_in(node: &VecNode, shift: u32, idx: usize, val: Value) -> VecNode {
+    match node {
+        VecNode::Branch(children) => {
+            let mut children = children.clone();
+            let child = (idx >> shift) & MASK;
+            if child < children.len() {
+                children[child] = Rc::new(push_in(&children[child], shift - BITS, idx, val));
+            } else {
+                children.push(Rc::new(new_path(shift - BITS, val)));
+            }
+            VecNode::Branch(children)
+        }
+        VecNode::Leaf(vals) => {
+            let mut vals = vals.clone();
+            vals.push(val);
+            VecNode::Leaf(vals)
+        }
+    }
+}
+
+// The nodes down to a leaf holding only the value
+fn new_path(shift: u32, val: Value) -> VecNode {
+    if shift == 0 {
+        VecNode::Leaf(vec![val])
+    } else {
+        VecNode::Branch(vec![Rc::new(new_path(shift - BITS, val))])
+    }
+}
+
+fn collect(node: &VecNode, out: &mut Vec<Value>) {
+    match node {
+        VecNode::Branch(children) => children.iter().for_each(|child| collect(child, out)),
+        VecNode::Leaf(vals) => out.extend(vals.iter().cloned()),
+    }
+}
+
+/// A persistent map from ints, strings or bools to values: inserting or removing a key gives a new map and
+/// leaves the old one as it was. It is a treap, a search tree kept balanced by giving each key a priority from
+/// its hash, so the two maps share every node but the O(log n) on the path to the key. The keys are kept in
+/// order, ints before strings before bools.
+#[derive(Clone, Default)]
+pub struct PMap {
+    root: Option<Rc<MapNode>>,
+    len: usize,
+}
+
+struct MapNode {
+    key: Value,
+    val: Value,
+    priority: u64,
+    left: Option<Rc<MapNode>>,
+    right: Option<Rc<MapNode>>,
+}
+
+type Link = Option<Rc<MapNode>>;
+
+impl PMap {
+    pub fn new() -> Self {
+        PMap::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The value of the key, if it is in the map.
+    ///
+    /// # Errors
+    ///
+    /// If the key is not an int, string or bool.
+    pub fn get(&self, key: &Value) -> Result<Option<Value>> {
+        check_key(key)?;
+        let mut node = &self.root;
+        while let Some(n) = node {
+            node = match key_cmp(key, &n.key) {
+                Ordering::Less => &n.left,
+                Ordering::Greater => &n.right,
+                Ordering::Equal => return Ok(Some(n.val.clone())),
+            };
+        }
+        Ok(None)
+    }
+
+    /// A map with the key set to the value, replacing the value it had if it was there.
+    ///
+    /// # Errors
+    ///
+    /// If the key is not an int, string or bool.
+    pub fn insert(&self, key: Value, val: Value) -> Result<Self> {
+        check_key(&key)?;
+        let replaced = self.get(&key)?.is_some();
+        let priority = priority(&key);
+        Ok(PMap {
+            root: Some(insert_in(&self.root, key, val, priority)),
+            len: if replaced { self.len } else { self.len + 1 },
+        })
+    }
+
+    /// A map without the key. The map is the same if the key wasn't in it.
+    ///
+    /// # Errors
+    ///
+    /// If the key is not an int, string or bool.
+    pub fn remove(&self, key: &Value) -> Result<Self> {
+        if self.get(key)?.is_none() {
+            return Ok(self.clone());
+        }
+        Ok(PMap {
+            root: remove_in(&self.root, key),
+            len: self.len - 1,
+        })
+    }
+
+    /// Copy out the keys and values, in the order of the keys.
+    pub fn entries(&self) -> Vec<(Value, Value)> {
+        let mut entries = Vec::with_capacity(self.len);
+        collect_entries(&self.root, &mut entries);
+        entries
+    }
+}
+
+/// Maps are equal if their entries are, whether or not they share nodes.
+impl PartialEq for PMap {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.entries() == other.entries()
+    }
+}
+
+impl Debug for PMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PMap({:?})", self.entries())
+    }
+}
+
+fn check_key(key: &Value) -> Result<()> {
+    match key {
+        Value::Int(_) | Value::String(_) | Value::Bool(_) => Ok(()),
+        _ => Err(ByteCodeError::BadType {
+            expected: "int, string or bool key".to_string(),
+            found: type_of(key).to_string(),
+        }
+        .into()),
+    }
+}
+
+// Keys of different types are ordered by type, so a map can hold all of them
+fn key_cmp(a: &Value, b: &Value) -> Ordering {
+    fn rank(key: &Value) -> u8 {
+        match key {
+            Value::Int(_) => 0,
+            Value::String(_) => 1,
+            _ => 2,
+        }
+    }
+
+    match (a, b) {
+        (Value::Int(a), Value::Int(b)) => a.cmp(b),
+        (Value::String(a), Value::String(b)) => a.cmp(b),
+        (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+fn priority(key: &Value) -> u64 {
+    crate::builtin::hash_impl(key).expect("keys are checked to be hashable") as u64
+}
+
+fn node(key: Value, val: Value, priority: u64, left: Link, right: Link) -> Rc<MapNode> {
+    Rc::new(MapNode {
+        key,
+        val,
+        priority,
+        left,
+        right,
+    })
+}
+
+fn insert_in(link: &Link, key: Value, val: Value, priority: u64) -> Rc<MapNode> {
+    let Some(n) = link else {
+        return node(key, val, priority, None, None);
+    };
+
+    match key_cmp(&key, &n.key) {
+        Ordering::Equal => node(key, val, n.priority, n.left.clone(), n.right.clone()),
+        // The new key goes above the nodes with a lower priority, with them split around it
+        _ if priority > n.priority => {
+            let (left, right) = split(link, &key);
+            node(key, val, priority, left, right)
+        }
+        Ordering::Less => node(
+            n.key.clone(),
+            n.val.clone(),
+            n.priority,
+            Some(insert_in(&n.left, key, val, priority)),
+            n.right.clone(),
+        ),
+        Ordering::Greater => node(
+            n.key.clone(),
+            n.val.clone(),
+            n.priority,
+            n.left.clone(),
+            Some(insert_in(&n.right, key, val, priority)),
+        ),
+    }
+}
+
+// The nodes with keys less than the key and those with keys greater, leaving out the key itself
+fn split(link: &Link, key: &Value) -> (Link, Link) {
+    let Some(n) = link else {
+        return (None, None);
+    };
+
+    match key_cmp(&n.key, key) {
+        Ordering::Less => {
+            let (left, right) = split(&n.right, key);
+            let n = node(
+                n.key.clone(),
+                n.val.clone(),
+                n.priority,
+                n.left.clone(),
+                left,
+            );
+            (Some(n), right)
+        }
+        Ordering::Greater => {
+            let (left, right) = split(&n.left, key);
+            let n = node(
+                n.key.clone(),
+                n.val.clone(),
+                n.priority,
+                right,
+                n.right.clone(),
+            );
+            (left, Some(n))
+        }
+        Ordering::Equal => (n.left.clone(), n.right.clone()),
+    }
+}
+
+fn remove_in(link: &Link, key: &Value) -> Link {
+    let n = link.as_ref()?;
+    match key_cmp(key, &n.key) {
+        Ordering::Equal => merge(&n.left, &n.right),
+        Ordering::Less => Some(node(
+            n.key.clone(),
+            n.val.clone(),
+            n.priority,
+            remove_in(&n.left, key),
+            n.right.clone(),
+        )),
+        Ordering::Greater => Some(node(
+            n.key.clone(),
+            n.val.clone(),
+            n.priority,
+            n.left.clone(),
+            remove_in(&n.right, key),
+        )),
+    }
+}
+
+// Join two trees where every key on the left is less than every key on the right
+fn merge(left: &Link, right: &Link) -> Link {
+    match (left, right) {
+        (None, _) => right.clone(),
+        (_, None) => left.clone(),
+        (Some(l), Some(r)) if l.priority > r.priority => Some(node(
+            l.key.clone(),
+            l.val.clone(),
+            l.priority,
+            l.left.clone(),
+            merge(&l.right, right),
+        )),
+        (_, Some(r)) => Some(node(
+            r.key.clone(),
+            r.val.clone(),
+            r.priority,
+            merge(left, &r.left),
+            r.right.clone(),
+        )),
+    }
+}
+
+fn collect_entries(link: &Link, out: &mut Vec<(Value, Value)>) {
+    if let Some(n) = link {
+        collect_entries(&n.left, out);
+        out.push((n.key.clone(), n.val.clone()));
+        collect_entries(&n.right, out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ints(range: std::ops::Range<i64>) -> Vec<Value> {
+        range.map(Value::Int).collect()
+    }
+
+    #[test]
+    fn test_pvec() -> Result<()> {
+        // Enough to need three levels of nodes
+        let n = WIDTH * WIDTH + 7;
+        let pvec: PVec = ints(0..n as i64).into_iter().collect();
+        assert_eq!(pvec.len(), n);
+        assert_eq!(pvec.to_vec(), ints(0..n as i64));
+        assert_eq!(pvec.get(1000)?, Value::Int(1000));
+        assert!(pvec.get(n as i64).is_err());
+        assert!(pvec.get(-1).is_err());
+
+        // The old vector is left as it was
+        let pushed = pvec.push(Value::Int(-1));
+        let set = pushed.set(5, "five".into())?;
+        assert_eq!(pvec.len(), n);
+        assert_eq!(pushed.get(n as i64)?, Value::Int(-1));
+        assert_eq!(pushed.get(5)?, Value::Int(5));
+        assert_eq!(set.get(5)?, "five".into());
+        assert!(pvec.set(n as i64, Value::Unit).is_err());
+
+        assert_eq!(
+            PVec::new().push(Value::Int(1)),
+            ints(1..2).into_iter().collect()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_pmap() -> Result<()> {
+        let mut map = PMap::new();
+        for i in (0..200).rev() {
+            map = map.insert(Value::Int(i), Value::Int(i * i))?;
+        }
+        let with_keys = map
+            .insert("b".into(), Value::Bool(true))?
+            .insert(Value::Bool(false), Value::Unit)?
+            .insert("a".into(), Value::Unit)?;
+
+        assert_eq!(map.len(), 200);
+        assert_eq!(map.get(&Value::Int(12))?, Some(Value::Int(144)));
+        assert_eq!(map.get(&"b".into())?, None);
+        assert_eq!(with_keys.get(&"b".into())?, Some(Value::Bool(true)));
+
+        // In the order of the keys, ints then strings then bools
+        let keys: Vec<Value> = with_keys.entries().into_iter().map(|(k, _)| k).collect();
+        let mut expected = ints(0..200);
+        expected.extend(["a".into(), "b".into(), Value::Bool(false)]);
+        assert_eq!(keys, expected);
+
+        // Replacing keeps the length, removing takes one off, and the old map is left as it was
+        let replaced = map.insert(Value::Int(3), Value::Unit)?;
+        assert_eq!(replaced.len(), 200);
+        assert_eq!(replaced.get(&Value::Int(3))?, Some(Value::Unit));
+        let removed = (0..100).try_fold(map.clone(), |m, i| m.remove(&Value::Int(i * 2)))?;
+        assert_eq!(removed.len(), 100);
+        assert_eq!(removed.get(&Value::Int(4))?, None);
+        assert_eq!(removed.get(&Value::Int(5))?, Some(Value::Int(25)));
+        assert_eq!(map.get(&Value::Int(4))?, Some(Value::Int(16)));
+        assert_eq!(removed.remove(&Value::Int(4))?, removed);
+
+        assert!(map.insert(Value::Float(1.0), Value::Unit).is_err());
+
+        // persist_get errors on a missing key instead of giving back nothing
+        let err = crate::builtin::persist_get_impl(&map.into(), &Value::Int(-1)).unwrap_err();
+        assert_eq!(err.to_string(), "Key not found: -1");
+        Ok(())
+    }
+}
@@ -0,0 +1,118 @@
+use std::{
+    cell::RefCell,
+    fmt::Debug,
+    net::{TcpListener, TcpStream, UdpSocket},
+    rc::Rc,
+};
+
+use anyhow::Result;
+
+use crate::{ByteCodeError, W};
+
+/// An open network socket, made by the net builtins. Like channels, every handle to a socket shares it, so closing
+/// it through one closes it for all of them.
+pub type Socket = W<Rc<RefCell<Option<SocketKind>>>>;
+
+/// What a socket is: a TCP connection, a TCP listener waiting for connections, or a UDP socket.
+#[derive(Debug)]
+pub enum SocketKind {
+    Stream(TcpStream),
+    Listener(TcpListener),
+    Udp(UdpSocket),
+}
+
+impl SocketKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SocketKind::Stream(_) => "TCP connection",
+            SocketKind::Listener(_) => "TCP listener",
+            SocketKind::Udp(_) => "UDP socket",
+        }
+    }
+
+    /// Another handle to the same OS socket, which can be moved to another thread to block on it there.
+    pub fn try_clone(&self) -> Result<SocketKind> {
+        Ok(match self {
+            SocketKind::Stream(s) => SocketKind::Stream(s.try_clone()?),
+            SocketKind::Listener(l) => SocketKind::Listener(l.try_clone()?),
+            SocketKind::Udp(u) => SocketKind::Udp(u.try_clone()?),
+        })
+    }
+}
+
+impl Socket {
+    pub fn new(kind: SocketKind) -> Self {
+        Self(Rc::new(RefCell::new(Some(kind))))
+    }
+
+    /// Use the socket.
+    ///
+    /// # Errors
+    ///
+    /// If the socket was closed, or f fails.
+    pub fn with<T>(&self, f: impl FnOnce(&SocketKind) -> Result<T>) -> Result<T> {
+        match self.borrow().as_ref() {
+            Some(kind) => f(kind),
+            None => Err(ByteCodeError::SocketClosed.into()),
+        }
+    }
+
+    /// Another handle to the OS socket, see [`SocketKind::try_clone`].
+    ///
+    /// # Errors
+    ///
+    /// If the socket was closed, or the OS handle can't be duplicated.
+    pub fn kind(&self) -> Result<SocketKind> {
+        match self.borrow().as_ref() {
+            Some(kind) => kind.try_clone(),
+            None => Err(ByteCodeError::SocketClosed.into()),
+        }
+    }
+
+    /// Close the socket. Closing a socket that is already closed does nothing.
+    pub fn close(&self) {
+        self.borrow_mut().take();
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.borrow().is_none()
+    }
+}
+
+/// Sockets are equal only if they are the same socket, like channels.
+impl PartialEq for Socket {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Clone for Socket {
+    fn clone(&self) -> Self {
+        Self(Rc::clone(&self.0))
+    }
+}
+
+impl Debug for Socket {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Socket({:?})", self.borrow())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_socket() -> Result<()> {
+        let sock = Socket::new(SocketKind::Udp(UdpSocket::bind("127.0.0.1:0")?));
+        let other = sock.clone();
+        assert!(matches!(other.kind()?, SocketKind::Udp(_)));
+
+        // closing one handle closes the socket for all of them
+        sock.close();
+        assert!(other.is_closed());
+        assert!(other.kind().is_err());
+        assert_eq!(sock, other);
+        Ok(())
+    }
+}
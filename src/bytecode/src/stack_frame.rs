@@ -1,3 +1,5 @@
+use std::rc::Rc;
+
 use serde::{Deserialize, Serialize};
 
 use crate::EnvWeak;
@@ -13,6 +15,8 @@ pub struct StackFrame {
     pub frame_type: FrameType,
     pub address: Option<usize>,
     pub env: EnvWeak,
+    /// The call a call frame is for, for backtraces.
+    pub call: Option<Rc<CallInfo>>,
 }
 
 impl StackFrame {
@@ -21,6 +25,7 @@ impl StackFrame {
             frame_type,
             address: None,
             env,
+            call: None,
         }
     }
 
@@ -29,6 +34,42 @@ impl StackFrame {
             frame_type,
             address: Some(address),
             env,
+            call: None,
+        }
+    }
+}
+
+/// A call of a function, kept in its call frame. Each call links to the call it was made in, so the calls a thread
+/// is in can be kept when it stops with an error without copying its runtime stack.
+#[derive(PartialEq)]
+pub struct CallInfo {
+    /// The pc of the first instruction of the function.
+    pub addr: usize,
+    /// The pc of the CALL.
+    pub call_pc: usize,
+    /// The call the CALL was made in, None if it was made at the top level of the thread.
+    pub caller: Option<Rc<CallInfo>>,
+}
+
+// The callers are in the frames below, so each frame shows only its own call
+impl std::fmt::Debug for CallInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CallInfo")
+            .field("addr", &self.addr)
+            .field("call_pc", &self.call_pc)
+            .finish_non_exhaustive()
+    }
+}
+
+// Drop the calls this one was made in one at a time, since dropping them recursively would overflow the stack for
+// deep recursion
+impl Drop for CallInfo {
+    fn drop(&mut self) {
+        let mut caller = self.caller.take();
+        while let Some(call) = caller {
+            caller = Rc::try_unwrap(call)
+                .ok()
+                .and_then(|mut call| call.caller.take());
         }
     }
 }
@@ -0,0 +1,152 @@
+use std::{cell::RefCell, fmt::Debug, rc::Rc};
+
+use crate::{Symbol, Value};
+
+/// An instance of a user declared struct. Like arrays, structs have reference semantics: cloning the value
+/// shares the fields, so an update through one handle is seen through every other.
+#[derive(Clone)]
+pub struct Struct {
+    pub name: Symbol,
+    fields: Rc<RefCell<Vec<(Symbol, Value)>>>,
+}
+
+impl Struct {
+    pub fn new(name: Symbol, fields: Vec<(Symbol, Value)>) -> Self {
+        Struct {
+            name,
+            fields: Rc::new(RefCell::new(fields)),
+        }
+    }
+
+    /// Get the value of a field, or None if the struct has no such field.
+    pub fn get(&self, field: &str) -> Option<Value> {
+        self.fields
+            .borrow()
+            .iter()
+            .find(|(name, _)| name == field)
+            .map(|(_, val)| val.clone())
+    }
+
+    /// Set the value of a field. Returns false if the struct has no such field.
+    pub fn set(&self, field: &str, val: Value) -> bool {
+        let mut fields = self.fields.borrow_mut();
+        match fields.iter_mut().find(|(name, _)| name == field) {
+            Some((_, old)) => {
+                *old = val;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Copy out the fields in the order they were given.
+    pub fn fields(&self) -> Vec<(Symbol, Value)> {
+        self.fields.borrow().clone()
+    }
+
+    /// The address of the fields, the same for every handle that shares them.
+    pub fn as_ptr(&self) -> *const () {
+        Rc::as_ptr(&self.fields) as *const ()
+    }
+
+    /// Copy the fields, including nested arrays and structs, so the result shares nothing with self.
+    pub fn deep_clone(&self) -> Self {
+        let fields = self
+            .fields
+            .borrow()
+            .iter()
+            .map(|(name, val)| {
+                let val = match val {
+                    Value::Array(arr) => Value::Array(arr.deep_clone()),
+                    Value::Struct(s) => Value::Struct(s.deep_clone()),
+                    Value::Variant(v) => Value::Variant(v.deep_clone()),
+                    Value::Tuple(t) => Value::Tuple(t.deep_clone()),
+                    Value::Map(m) => Value::Map(m.deep_clone()),
+                    _ => val.clone(),
+                };
+                (name.clone(), val)
+            })
+            .collect();
+
+        Self::new(self.name.clone(), fields)
+    }
+}
+
+/// Structs are equal if they have the same name and their fields are, whatever order the fields were given in.
+impl PartialEq for Struct {
+    fn eq(&self, other: &Self) -> bool {
+        if self.name != other.name {
+            return false;
+        }
+
+        let fields = self.fields.borrow();
+        fields.len() == other.fields.borrow().len()
+            && fields
+                .iter()
+                .all(|(name, val)| other.get(name).as_ref() == Some(val))
+    }
+}
+
+impl Debug for Struct {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let fields: Vec<String> = self
+            .fields
+            .borrow()
+            .iter()
+            .map(|(name, val)| format!("{}: {:?}", name, val))
+            .collect();
+        write!(f, "{} {{ {} }}", self.name, fields.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(x: i64, y: f64) -> Struct {
+        Struct::new(
+            "Point".to_string(),
+            vec![("x".to_string(), x.into()), ("y".to_string(), y.into())],
+        )
+    }
+
+    #[test]
+    fn test_struct_shares_fields() {
+        let p = point(1, 2.0);
+        let alias = p.clone();
+        assert!(alias.set("x", Value::Int(42)));
+        assert!(!alias.set("z", Value::Int(42)));
+
+        assert_eq!(p.get("x"), Some(Value::Int(42)));
+        assert_eq!(p.get("z"), None);
+        assert_eq!(p, alias);
+    }
+
+    #[test]
+    fn test_struct_eq() {
+        let swapped = Struct::new(
+            "Point".to_string(),
+            vec![("y".to_string(), 2.0.into()), ("x".to_string(), 1.into())],
+        );
+        assert_eq!(point(1, 2.0), swapped);
+        assert_ne!(point(1, 2.0), point(1, 3.0));
+
+        let other = Struct::new("Other".to_string(), point(1, 2.0).fields());
+        assert_ne!(point(1, 2.0), other);
+        assert_eq!(format!("{:?}", point(1, 2.5)), "Point { x: 1, y: 2.5 }");
+    }
+
+    #[test]
+    fn test_struct_deep_clone() {
+        let inner = point(1, 2.0);
+        let outer = Struct::new(
+            "Line".to_string(),
+            vec![("start".to_string(), inner.clone().into())],
+        );
+        let copy = outer.deep_clone();
+        inner.set("x", Value::Int(5));
+
+        assert_eq!(copy.get("start"), Some(point(1, 2.0).into()));
+        assert_ne!(copy, outer);
+    }
+}
@@ -0,0 +1,82 @@
+use std::{fmt::Debug, ops::Deref, rc::Rc};
+
+use crate::Value;
+
+/// A fixed number of values of any type, like `(1, true)`. Like variants, tuples can't be changed once made, so
+/// cloning one shares the values and they compare by what they hold.
+#[derive(Clone, PartialEq)]
+pub struct Tuple(Rc<[Value]>);
+
+impl Tuple {
+    pub fn new(elems: Vec<Value>) -> Self {
+        Tuple(elems.into())
+    }
+
+    /// Copy the values, including nested arrays and structs, so the result shares nothing with self.
+    pub fn deep_clone(&self) -> Self {
+        let elems = self
+            .iter()
+            .map(|val| match val {
+                Value::Array(arr) => Value::Array(arr.deep_clone()),
+                Value::Struct(s) => Value::Struct(s.deep_clone()),
+                Value::Variant(v) => Value::Variant(v.deep_clone()),
+                Value::Tuple(t) => Value::Tuple(t.deep_clone()),
+                Value::Map(m) => Value::Map(m.deep_clone()),
+                _ => val.clone(),
+            })
+            .collect();
+
+        Self::new(elems)
+    }
+}
+
+impl Deref for Tuple {
+    type Target = [Value];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Debug for Tuple {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let elems: Vec<String> = self.iter().map(|v| format!("{:?}", v)).collect();
+        // (1,) so a tuple of one value isn't read as a value in parentheses
+        match elems.as_slice() {
+            [elem] => write!(f, "({},)", elem),
+            _ => write!(f, "({})", elems.join(", ")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Array;
+
+    use super::*;
+
+    #[test]
+    fn test_tuple_eq() {
+        let t = |a: i64, b: bool| Tuple::new(vec![Value::Int(a), Value::Bool(b)]);
+        assert_eq!(t(1, true), t(1, true));
+        assert_ne!(t(1, true), t(1, false));
+        assert_eq!(t(2, false).len(), 2);
+
+        assert_eq!(format!("{:?}", t(1, true)), "(1, true)");
+        assert_eq!(format!("{:?}", Tuple::new(vec![Value::Int(1)])), "(1,)");
+    }
+
+    #[test]
+    fn test_tuple_deep_clone() {
+        let arr = Array::new(vec![Value::Int(1)]);
+        let t = Tuple::new(vec![arr.clone().into(), Value::Int(2)]);
+        let copy = t.deep_clone();
+        arr.borrow_mut()[0] = Value::Int(3);
+
+        assert_eq!(
+            copy,
+            Tuple::new(vec![vec![Value::Int(1)].into(), Value::Int(2)])
+        );
+        assert_ne!(copy, t);
+    }
+}
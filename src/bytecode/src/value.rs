@@ -1,10 +1,19 @@
-use std::fmt::{Debug, Display};
+use std::{
+    fmt::{Debug, Display},
+    rc::Rc,
+};
 
 use serde::{Deserialize, Serialize};
 
-use crate::{ByteCodeError, EnvWeak, Semaphore, Symbol};
+use crate::{
+    Array, ByteCodeError, Bytes, Channel, EnvWeak, Map, PMap, PVec, Semaphore, Slice, Socket,
+    Struct, Symbol, Tuple, Variant,
+};
 
 /// The values that can be stored on the operant stack.
+///
+/// Cloning a value is cheap: ints, floats, bools and chars are copied, and strings, closure params, arrays and structs
+/// are shared behind an `Rc`, so loading a variable never copies what it holds.
 #[derive(Serialize, Deserialize, Clone, PartialEq)]
 pub enum Value {
     Unitialized,
@@ -12,14 +21,37 @@ pub enum Value {
     Int(i64),
     Float(f64),
     Bool(bool),
-    String(String),
+    String(Rc<str>),
+    Char(char),
     #[serde(skip_serializing, skip_deserializing)]
     Semaphore(Semaphore),
     #[serde(skip_serializing, skip_deserializing)]
+    Channel(Channel),
+    #[serde(skip_serializing, skip_deserializing)]
+    Array(Array),
+    #[serde(skip_serializing, skip_deserializing)]
+    Slice(Slice),
+    #[serde(skip_serializing, skip_deserializing)]
+    Struct(Struct),
+    #[serde(skip_serializing, skip_deserializing)]
+    Variant(Variant),
+    #[serde(skip_serializing, skip_deserializing)]
+    Tuple(Tuple),
+    #[serde(skip_serializing, skip_deserializing)]
+    Map(Map),
+    #[serde(skip_serializing, skip_deserializing)]
+    Socket(Socket),
+    #[serde(skip_serializing, skip_deserializing)]
+    Bytes(Bytes),
+    #[serde(skip_serializing, skip_deserializing)]
+    PVec(PVec),
+    #[serde(skip_serializing, skip_deserializing)]
+    PMap(PMap),
+    #[serde(skip_serializing, skip_deserializing)]
     Closure {
         fn_type: FnType,
         sym: Symbol,
-        prms: Vec<Symbol>,
+        prms: Rc<[Symbol]>,
         addr: usize,
         env: EnvWeak,
     },
@@ -40,26 +72,136 @@ pub fn type_of(value: &Value) -> &'static str {
         Value::Float(_) => "Float",
         Value::Bool(_) => "Bool",
         Value::String(_) => "String",
+        Value::Char(_) => "Char",
         Value::Semaphore(_) => "Semaphore",
+        Value::Channel(_) => "Channel",
+        Value::Array(_) => "Array",
+        Value::Slice(_) => "Slice",
+        Value::Struct(_) => "Struct",
+        Value::Variant(_) => "Variant",
+        Value::Tuple(_) => "Tuple",
+        Value::Map(_) => "Map",
+        Value::Socket(_) => "Socket",
+        Value::Bytes(_) => "Bytes",
+        Value::PVec(_) => "PVec",
+        Value::PMap(_) => "PMap",
         Value::Closure { .. } => "Closure",
     }
 }
 
 impl Display for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let res = match self {
-            Value::Unitialized => "uninitialized".to_string(),
-            Value::Unit => "()".to_string(),
-            Value::String(s) => s.to_string(),
-            Value::Bool(b) => b.to_string(),
-            Value::Int(i) => i.to_string(),
-            Value::Float(f) => f.to_string(),
-            Value::Semaphore(_) => "semaphore".to_string(),
-            Value::Closure { .. } => "closure".to_string(),
-        };
+        write!(f, "{}", display_value(self, &mut Vec::new()))
+    }
+}
 
-        write!(f, "{}", res)
+// Arrays, structs and maps can be changed to hold themselves, so ancestors has the storage of each one being shown
+// around this value, and one that holds itself is shown as <cycle> where it comes back, like inspect does
+fn display_value(value: &Value, ancestors: &mut Vec<*const ()>) -> String {
+    match value {
+        Value::Unitialized => "uninitialized".to_string(),
+        Value::Unit => "()".to_string(),
+        Value::String(s) => s.to_string(),
+        Value::Char(c) => c.to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Int(i) => i.to_string(),
+        Value::Float(f) => f.to_string(),
+        Value::Semaphore(_) => "semaphore".to_string(),
+        Value::Channel(_) => "channel".to_string(),
+        Value::Array(arr) => nested(Rc::as_ptr(arr) as *const (), ancestors, |ancestors| {
+            display_elems(&arr.borrow(), ancestors)
+        }),
+        Value::Slice(slice) => nested(
+            Rc::as_ptr(&slice.arr) as *const (),
+            ancestors,
+            |ancestors| display_elems(&slice.to_vec(), ancestors),
+        ),
+        Value::Struct(s) => nested(s.as_ptr(), ancestors, |ancestors| {
+            display_fields(&s.name, &s.fields(), ancestors)
+        }),
+        Value::Variant(v) => display_variant(v, ancestors),
+        Value::Tuple(t) => display_tuple(t, ancestors),
+        Value::Map(m) => nested(m.as_ptr(), ancestors, |ancestors| {
+            format!("#{}", display_entries(&m.entries(), ancestors))
+        }),
+        Value::Socket(_) => "socket".to_string(),
+        Value::Bytes(bytes) => display_bytes(&bytes.borrow()),
+        Value::PVec(pvec) => display_elems(&pvec.to_vec(), ancestors),
+        Value::PMap(pmap) => display_entries(&pmap.entries(), ancestors),
+        Value::Closure { .. } => "closure".to_string(),
+    }
+}
+
+fn nested(
+    ptr: *const (),
+    ancestors: &mut Vec<*const ()>,
+    display: impl FnOnce(&mut Vec<*const ()>) -> String,
+) -> String {
+    if ancestors.contains(&ptr) {
+        return "<cycle>".to_string();
     }
+
+    ancestors.push(ptr);
+    let res = display(ancestors);
+    ancestors.pop();
+    res
+}
+
+// Bytes as b"..." with the bytes that aren't printable ascii escaped, like Rust byte strings
+fn display_bytes(bytes: &[u8]) -> String {
+    let escaped: String = bytes
+        .iter()
+        .flat_map(|&b| std::ascii::escape_default(b))
+        .map(char::from)
+        .collect();
+    format!("b\"{}\"", escaped)
+}
+
+fn display_elems(vals: &[Value], ancestors: &mut Vec<*const ()>) -> String {
+    let vals: Vec<String> = vals.iter().map(|v| display_value(v, ancestors)).collect();
+    format!("[{}]", vals.join(", "))
+}
+
+fn display_entries(entries: &[(Value, Value)], ancestors: &mut Vec<*const ()>) -> String {
+    let entries: Vec<String> = entries
+        .iter()
+        .map(|(key, v)| format!("{}: {}", key, display_value(v, ancestors)))
+        .collect();
+    format!("{{{}}}", entries.join(", "))
+}
+
+fn display_variant(v: &Variant, ancestors: &mut Vec<*const ()>) -> String {
+    if v.payload.is_empty() {
+        return v.name.to_string();
+    }
+
+    let vals: Vec<String> = v
+        .payload
+        .iter()
+        .map(|v| display_value(v, ancestors))
+        .collect();
+    format!("{}({})", v.name, vals.join(", "))
+}
+
+// (1,) so a tuple of one value isn't read as a value in parentheses
+fn display_tuple(t: &Tuple, ancestors: &mut Vec<*const ()>) -> String {
+    let vals: Vec<String> = t.iter().map(|v| display_value(v, ancestors)).collect();
+    match vals.as_slice() {
+        [val] => format!("({},)", val),
+        _ => format!("({})", vals.join(", ")),
+    }
+}
+
+fn display_fields(
+    name: &str,
+    fields: &[(Symbol, Value)],
+    ancestors: &mut Vec<*const ()>,
+) -> String {
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|(field, v)| format!("{}: {}", field, display_value(v, ancestors)))
+        .collect();
+    format!("{} {{ {} }}", name, fields.join(", "))
 }
 
 impl Debug for Value {
@@ -68,10 +210,22 @@ impl Debug for Value {
             Value::Unitialized => "uninitialized".to_string(),
             Value::Unit => "()".to_string(),
             Value::String(s) => s.to_string(),
+            Value::Char(c) => c.to_string(),
             Value::Bool(b) => b.to_string(),
             Value::Int(i) => i.to_string(),
             Value::Float(f) => f.to_string(),
             Value::Semaphore(_) => "semaphore".to_string(),
+            Value::Channel(_) => "channel".to_string(),
+            Value::Array(arr) => format!("{:?}", arr),
+            Value::Slice(slice) => format!("{:?}", slice),
+            Value::Struct(s) => format!("{:?}", s),
+            Value::Variant(v) => format!("{:?}", v),
+            Value::Tuple(t) => format!("{:?}", t),
+            Value::Map(m) => format!("{:?}", m),
+            Value::Socket(s) => format!("{:?}", s),
+            Value::Bytes(bytes) => format!("{:?}", bytes),
+            Value::PVec(pvec) => format!("{:?}", pvec),
+            Value::PMap(pmap) => format!("{:?}", pmap),
             Value::Closure {
                 sym,
                 fn_type,
@@ -114,13 +268,19 @@ impl From<()> for Value {
 
 impl From<String> for Value {
     fn from(v: String) -> Self {
-        Value::String(v)
+        Value::String(v.into())
     }
 }
 
 impl From<&str> for Value {
     fn from(v: &str) -> Self {
-        Value::String(v.to_string())
+        Value::String(v.into())
+    }
+}
+
+impl From<char> for Value {
+    fn from(v: char) -> Self {
+        Value::Char(v)
     }
 }
 
@@ -130,6 +290,78 @@ impl From<Semaphore> for Value {
     }
 }
 
+impl From<Channel> for Value {
+    fn from(v: Channel) -> Self {
+        Value::Channel(v)
+    }
+}
+
+impl From<Socket> for Value {
+    fn from(v: Socket) -> Self {
+        Value::Socket(v)
+    }
+}
+
+impl From<Bytes> for Value {
+    fn from(v: Bytes) -> Self {
+        Value::Bytes(v)
+    }
+}
+
+impl From<PVec> for Value {
+    fn from(v: PVec) -> Self {
+        Value::PVec(v)
+    }
+}
+
+impl From<PMap> for Value {
+    fn from(v: PMap) -> Self {
+        Value::PMap(v)
+    }
+}
+
+impl From<Array> for Value {
+    fn from(v: Array) -> Self {
+        Value::Array(v)
+    }
+}
+
+impl From<Struct> for Value {
+    fn from(v: Struct) -> Self {
+        Value::Struct(v)
+    }
+}
+
+impl From<Variant> for Value {
+    fn from(v: Variant) -> Self {
+        Value::Variant(v)
+    }
+}
+
+impl From<Tuple> for Value {
+    fn from(v: Tuple) -> Self {
+        Value::Tuple(v)
+    }
+}
+
+impl From<Map> for Value {
+    fn from(v: Map) -> Self {
+        Value::Map(v)
+    }
+}
+
+impl From<Slice> for Value {
+    fn from(v: Slice) -> Self {
+        Value::Slice(v)
+    }
+}
+
+impl From<Vec<Value>> for Value {
+    fn from(v: Vec<Value>) -> Self {
+        Value::Array(Array::new(v))
+    }
+}
+
 impl TryFrom<Value> for () {
     type Error = ByteCodeError;
 
@@ -191,7 +423,7 @@ impl TryFrom<Value> for String {
 
     fn try_from(value: Value) -> Result<Self, Self::Error> {
         match value {
-            Value::String(s) => Ok(s),
+            Value::String(s) => Ok(s.to_string()),
             _ => Err(ByteCodeError::TypeMismatch {
                 expected: "String".to_string(),
                 found: format!("{:?}", value),
@@ -200,6 +432,20 @@ impl TryFrom<Value> for String {
     }
 }
 
+impl TryFrom<Value> for char {
+    type Error = ByteCodeError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Char(c) => Ok(c),
+            _ => Err(ByteCodeError::TypeMismatch {
+                expected: "Char".to_string(),
+                found: format!("{:?}", value),
+            }),
+        }
+    }
+}
+
 impl TryFrom<Value> for Semaphore {
     type Error = ByteCodeError;
 
@@ -214,6 +460,104 @@ impl TryFrom<Value> for Semaphore {
     }
 }
 
+impl TryFrom<Value> for Channel {
+    type Error = ByteCodeError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Channel(ch) => Ok(ch),
+            _ => Err(ByteCodeError::TypeMismatch {
+                expected: "Channel".to_string(),
+                found: format!("{:?}", value),
+            }),
+        }
+    }
+}
+
+impl TryFrom<Value> for Socket {
+    type Error = ByteCodeError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Socket(s) => Ok(s),
+            _ => Err(ByteCodeError::TypeMismatch {
+                expected: "Socket".to_string(),
+                found: format!("{:?}", value),
+            }),
+        }
+    }
+}
+
+impl TryFrom<Value> for Bytes {
+    type Error = ByteCodeError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Bytes(bytes) => Ok(bytes),
+            _ => Err(ByteCodeError::TypeMismatch {
+                expected: "Bytes".to_string(),
+                found: format!("{:?}", value),
+            }),
+        }
+    }
+}
+
+impl TryFrom<Value> for PVec {
+    type Error = ByteCodeError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::PVec(pvec) => Ok(pvec),
+            _ => Err(ByteCodeError::TypeMismatch {
+                expected: "PVec".to_string(),
+                found: format!("{:?}", value),
+            }),
+        }
+    }
+}
+
+impl TryFrom<Value> for PMap {
+    type Error = ByteCodeError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::PMap(pmap) => Ok(pmap),
+            _ => Err(ByteCodeError::TypeMismatch {
+                expected: "PMap".to_string(),
+                found: format!("{:?}", value),
+            }),
+        }
+    }
+}
+
+impl TryFrom<Value> for Map {
+    type Error = ByteCodeError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Map(m) => Ok(m),
+            _ => Err(ByteCodeError::TypeMismatch {
+                expected: "Map".to_string(),
+                found: format!("{:?}", value),
+            }),
+        }
+    }
+}
+
+impl TryFrom<Value> for Array {
+    type Error = ByteCodeError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Array(arr) => Ok(arr),
+            _ => Err(ByteCodeError::TypeMismatch {
+                expected: "Array".to_string(),
+                found: format!("{:?}", value),
+            }),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -270,6 +614,106 @@ mod tests {
     fn test_from_string() {
         let string_value: String = "Hello, World!".to_string();
         let value: Value = string_value.clone().into();
-        assert_eq!(value, Value::String(string_value));
+        assert_eq!(value, Value::String(string_value.into()));
+    }
+
+    #[test]
+    fn test_from_char() -> Result<(), ByteCodeError> {
+        let value: Value = 'é'.into();
+        assert_eq!(value, Value::Char('é'));
+        assert_eq!(value.to_string(), "é");
+        assert_eq!(char::try_from(value)?, 'é');
+        assert!(char::try_from(Value::String("é".into())).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_display_array() {
+        let value: Value = vec![Value::Int(1), Value::String("two".into())].into();
+        assert_eq!(value.to_string(), "[1, two]");
+
+        let nested: Value = vec![value.clone(), Value::from(Vec::new())].into();
+        assert_eq!(nested.to_string(), "[[1, two], []]");
+
+        let Value::Array(arr) = value else {
+            unreachable!()
+        };
+        let slice: Value = Slice::new(arr, 1, 1).into();
+        assert_eq!(slice.to_string(), "[two]");
+    }
+
+    #[test]
+    fn test_display_bytes() {
+        let value: Value = Bytes::new(b"hi\n\x00\xff\"".to_vec()).into();
+        assert_eq!(value.to_string(), r#"b"hi\n\x00\xff\"""#);
+        assert_eq!(type_of(&value), "Bytes");
+    }
+
+    #[test]
+    fn test_display_persist() -> anyhow::Result<()> {
+        let pvec: Value = PVec::from_iter([Value::Int(1), "two".into()]).into();
+        assert_eq!(pvec.to_string(), "[1, two]");
+
+        let pmap: Value = PMap::new()
+            .insert("b".into(), Value::Int(2))?
+            .insert("a".into(), pvec)?
+            .into();
+        assert_eq!(pmap.to_string(), "{a: [1, two], b: 2}");
+        assert_eq!(type_of(&pmap), "PMap");
+        Ok(())
+    }
+
+    #[test]
+    fn test_display_variant() {
+        let value: Value = Variant::some(vec![Value::Int(1)]).into();
+        assert_eq!(value.to_string(), "Some([1])");
+        assert_eq!(Value::from(Variant::none()).to_string(), "None");
+        assert_eq!(Value::from(Variant::err("bad")).to_string(), "Err(bad)");
+        assert_eq!(type_of(&value), "Variant");
+    }
+
+    #[test]
+    fn test_display_tuple() {
+        let value: Value =
+            Tuple::new(vec![Value::Int(1), "a".into(), Variant::none().into()]).into();
+        assert_eq!(value.to_string(), "(1, a, None)");
+        assert_eq!(
+            Value::from(Tuple::new(vec![Value::Int(1)])).to_string(),
+            "(1,)"
+        );
+        assert_eq!(type_of(&value), "Tuple");
+    }
+
+    #[test]
+    fn test_display_map() -> anyhow::Result<()> {
+        let m = Map::new();
+        m.insert("b".into(), Value::Int(2))?;
+        m.insert(Value::Int(1), "one".into())?;
+        let value: Value = m.into();
+        assert_eq!(value.to_string(), "#{1: one, b: 2}");
+        assert_eq!(format!("{:?}", value), "#{1: one, b: 2}");
+        assert_eq!(type_of(&value), "Map");
+        Ok(())
+    }
+
+    #[test]
+    fn test_display_cycle() -> anyhow::Result<()> {
+        let kids = Map::new();
+        let node = Struct::new("N".into(), vec![("kids".into(), kids.clone().into())]);
+        kids.insert("self".into(), node.clone().into())?;
+        assert_eq!(
+            Value::from(node).to_string(),
+            "N { kids: #{self: <cycle>} }"
+        );
+
+        // the same array twice side by side is not a cycle
+        let arr: Value = vec![Value::Int(1)].into();
+        let Value::Array(outer) = Value::from(vec![arr.clone(), arr]) else {
+            unreachable!()
+        };
+        assert_eq!(Value::Array(outer.clone()).to_string(), "[[1], [1]]");
+        outer.borrow_mut().push(Value::Array(outer.clone()));
+        assert_eq!(Value::Array(outer).to_string(), "[[1], [1], <cycle>]");
+        Ok(())
     }
 }
@@ -0,0 +1,111 @@
+use std::{fmt::Debug, rc::Rc};
+
+use crate::{Symbol, Value};
+
+pub const OPTION_SYM: &str = "Option";
+pub const SOME_SYM: &str = "Some";
+pub const NONE_SYM: &str = "None";
+pub const RESULT_SYM: &str = "Result";
+pub const OK_SYM: &str = "Ok";
+pub const ERR_SYM: &str = "Err";
+
+/// A variant of an enum, like `Some(1)` of Option, with the values it holds. Unlike structs, variants can't be
+/// changed once made, so cloning one can share the payload and they compare by what they hold.
+#[derive(Clone, PartialEq)]
+pub struct Variant {
+    pub enum_name: Symbol,
+    pub name: Symbol,
+    pub payload: Rc<[Value]>,
+}
+
+impl Variant {
+    pub fn new(enum_name: impl Into<Symbol>, name: impl Into<Symbol>, payload: Vec<Value>) -> Self {
+        Variant {
+            enum_name: enum_name.into(),
+            name: name.into(),
+            payload: payload.into(),
+        }
+    }
+
+    pub fn some(val: impl Into<Value>) -> Self {
+        Self::new(OPTION_SYM, SOME_SYM, vec![val.into()])
+    }
+
+    pub fn none() -> Self {
+        Self::new(OPTION_SYM, NONE_SYM, vec![])
+    }
+
+    pub fn ok(val: impl Into<Value>) -> Self {
+        Self::new(RESULT_SYM, OK_SYM, vec![val.into()])
+    }
+
+    pub fn err(val: impl Into<Value>) -> Self {
+        Self::new(RESULT_SYM, ERR_SYM, vec![val.into()])
+    }
+
+    /// Ok with the value, or Err with the message.
+    pub fn from_result(res: Result<impl Into<Value>, String>) -> Self {
+        match res {
+            Ok(val) => Self::ok(val),
+            Err(msg) => Self::err(msg),
+        }
+    }
+
+    /// Copy the payload, including nested arrays and structs, so the result shares nothing with self.
+    pub fn deep_clone(&self) -> Self {
+        let payload = self
+            .payload
+            .iter()
+            .map(|val| match val {
+                Value::Array(arr) => Value::Array(arr.deep_clone()),
+                Value::Struct(s) => Value::Struct(s.deep_clone()),
+                Value::Variant(v) => Value::Variant(v.deep_clone()),
+                Value::Tuple(t) => Value::Tuple(t.deep_clone()),
+                Value::Map(m) => Value::Map(m.deep_clone()),
+                _ => val.clone(),
+            })
+            .collect();
+
+        Self::new(self.enum_name.clone(), self.name.clone(), payload)
+    }
+}
+
+impl Debug for Variant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.payload.is_empty() {
+            return write!(f, "{}", self.name);
+        }
+
+        let payload: Vec<String> = self.payload.iter().map(|v| format!("{:?}", v)).collect();
+        write!(f, "{}({})", self.name, payload.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Array;
+
+    use super::*;
+
+    #[test]
+    fn test_variant_eq() {
+        assert_eq!(Variant::some(1), Variant::some(1));
+        assert_ne!(Variant::some(1), Variant::some(2));
+        assert_ne!(Variant::ok(1), Variant::err(1));
+        assert_eq!(Variant::none(), Variant::none());
+
+        assert_eq!(format!("{:?}", Variant::some("a")), "Some(a)");
+        assert_eq!(format!("{:?}", Variant::none()), "None");
+    }
+
+    #[test]
+    fn test_variant_deep_clone() {
+        let arr = Array::new(vec![Value::Int(1)]);
+        let v = Variant::ok(arr.clone());
+        let copy = v.deep_clone();
+        arr.borrow_mut()[0] = Value::Int(2);
+
+        assert_eq!(copy, Variant::ok(vec![Value::Int(1)]));
+        assert_ne!(copy, v);
+    }
+}
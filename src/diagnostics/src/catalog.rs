@@ -0,0 +1,256 @@
+use std::{cell::RefCell, collections::HashMap, fmt::Display};
+
+use crate::Code;
+
+/// The message of an error, with its code.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    pub code: Code,
+    pub text: String,
+}
+
+/// Make the [`Message`] of an error from its code, its English template and the arguments for the template, like
+/// `format!`. The template is looked up in the catalog of the current thread, so the message is in its language
+/// if it has a translation, see [`set_catalog`].
+///
+/// ```
+/// use diagnostics::{message, Code};
+///
+/// let msg = message!(T001, "Identifier '{}' not declared", "x");
+/// assert_eq!(msg.code, Code::T001);
+/// assert_eq!(msg.text, "Identifier 'x' not declared");
+/// ```
+#[macro_export]
+macro_rules! message {
+    ($code:ident, $template:expr $(, $arg:expr)* $(,)?) => {
+        $crate::render(
+            $crate::Code::$code,
+            $template,
+            &[$(&$arg as &dyn ::std::fmt::Display),*],
+        )
+    };
+}
+
+/// Translations of the messages of errors, keyed by the code of the error and its English template.
+///
+/// A catalog file gives each template as it is in the source, after its code in brackets, and the translation
+/// on the next line after `=`. Each `{}` takes the next argument, and `{0}`, `{1}` and so on take them by
+/// position, so a translation can put them in another order. Lines starting with `#` are comments.
+///
+/// ```text
+/// [T001] Identifier '{}' not declared
+/// = Bezeichner '{}' ist nicht deklariert
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Catalog {
+    messages: HashMap<(Code, String), String>,
+}
+
+impl Catalog {
+    pub fn new() -> Catalog {
+        Catalog::default()
+    }
+
+    pub fn insert(&mut self, code: Code, template: &str, translation: &str) {
+        self.messages
+            .insert((code, template.to_string()), translation.to_string());
+    }
+
+    /// The translation of the template, if there is one.
+    pub fn get(&self, code: Code, template: &str) -> Option<&str> {
+        self.messages
+            .get(&(code, template.to_string()))
+            .map(String::as_str)
+    }
+
+    /// Read a catalog file, see [`Catalog`] for the format.
+    ///
+    /// # Errors
+    ///
+    /// [`CatalogError`] at the first line that isn't a comment, a template or the translation of the template
+    /// before it.
+    pub fn parse(text: &str) -> Result<Catalog, CatalogError> {
+        let mut catalog = Catalog::new();
+        let mut pending: Option<(Code, &str)> = None;
+
+        for (i, line) in text.lines().enumerate() {
+            let err = |msg: &str| CatalogError {
+                line: i + 1,
+                msg: msg.to_string(),
+            };
+
+            let line = line.trim_end();
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(translation) = line.strip_prefix('=') {
+                let Some((code, template)) = pending.take() else {
+                    return Err(err("translation without a template before it"));
+                };
+                catalog.insert(code, template, translation.trim_start());
+                continue;
+            }
+
+            if pending.is_some() {
+                return Err(err("template without a translation after it"));
+            }
+
+            let Some((code, template)) =
+                line.strip_prefix('[').and_then(|line| line.split_once(']'))
+            else {
+                return Err(err(
+                    "expected a template like '[T001] Identifier '{}' not declared'",
+                ));
+            };
+            let code = code
+                .parse()
+                .map_err(|e: crate::UnknownCode| err(&e.to_string()))?;
+            pending = Some((code, template.trim_start()));
+        }
+
+        match pending {
+            Some(_) => Err(CatalogError {
+                line: text.lines().count(),
+                msg: "template without a translation after it".to_string(),
+            }),
+            None => Ok(catalog),
+        }
+    }
+}
+
+/// Why a catalog file couldn't be read.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CatalogError {
+    pub line: usize,
+    pub msg: String,
+}
+
+impl Display for CatalogError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Bad catalog at line {}: {}", self.line, self.msg)
+    }
+}
+
+impl std::error::Error for CatalogError {}
+
+thread_local! {
+    static CATALOG: RefCell<Option<Catalog>> = const { RefCell::new(None) };
+}
+
+/// Show the messages of errors made on this thread from now on in the language of the catalog, or in English if
+/// it is None. Messages the catalog has no translation for stay in English.
+pub fn set_catalog(catalog: Option<Catalog>) {
+    CATALOG.with(|current| *current.borrow_mut() = catalog);
+}
+
+/// Make a message from the translation of the template in the current catalog, or the template itself if there is
+/// none. Use [`message!`] rather than calling this directly.
+pub fn render(code: Code, template: &str, args: &[&dyn Display]) -> Message {
+    let text = CATALOG.with(|catalog| match catalog.borrow().as_ref() {
+        Some(catalog) => interpolate(catalog.get(code, template).unwrap_or(template), args),
+        None => interpolate(template, args),
+    });
+
+    Message { code, text }
+}
+
+// Put the args in the template. {{ and }} are braces, and a brace that doesn't start a placeholder is kept as it
+// is, so a template that isn't a format string, like "Expected '}'", stays the same
+fn interpolate(template: &str, args: &[&dyn Display]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut next = 0;
+    let mut rest = template;
+
+    while let Some(i) = rest.find(['{', '}']) {
+        out.push_str(&rest[..i]);
+        let tail = &rest[i..];
+
+        if tail.starts_with("{{") || tail.starts_with("}}") {
+            out.push_str(&tail[..1]);
+            rest = &tail[2..];
+            continue;
+        }
+
+        let placeholder = tail
+            .strip_prefix('{')
+            .and_then(|tail| tail.split_once('}'))
+            .filter(|(pos, _)| pos.chars().all(|c| c.is_ascii_digit()));
+
+        match placeholder {
+            Some((pos, after)) => {
+                let idx = match pos.parse::<usize>() {
+                    Ok(idx) => idx,
+                    Err(_) => {
+                        next += 1;
+                        next - 1
+                    }
+                };
+                match args.get(idx) {
+                    Some(arg) => out.push_str(&arg.to_string()),
+                    None => out.push_str(&tail[..=pos.len() + 1]),
+                }
+                rest = after;
+            }
+            None => {
+                out.push_str(&tail[..1]);
+                rest = &tail[1..];
+            }
+        }
+    }
+
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interpolate() {
+        assert_eq!(interpolate("{} and {}", &[&1, &"two"]), "1 and two");
+        assert_eq!(interpolate("{1} before {0}", &[&1, &2]), "2 before 1");
+        assert_eq!(interpolate("{{}} and {}", &[&1]), "{} and 1");
+        assert_eq!(
+            interpolate("Expected '}' or '{'", &[]),
+            "Expected '}' or '{'"
+        );
+        assert_eq!(interpolate("{x} {}", &[&1]), "{x} 1");
+        // a placeholder without an arg is kept, so a bad translation shows what is missing
+        assert_eq!(interpolate("{} {}", &[&1]), "1 {}");
+    }
+
+    #[test]
+    fn test_catalog() {
+        let text = "# German\n\n[T001] Identifier '{}' not declared\n= Bezeichner '{}' ist nicht deklariert\n";
+        let catalog = Catalog::parse(text).expect("should parse");
+        assert_eq!(
+            catalog.get(Code::T001, "Identifier '{}' not declared"),
+            Some("Bezeichner '{}' ist nicht deklariert")
+        );
+
+        set_catalog(Some(catalog));
+        let msg = message!(T001, "Identifier '{}' not declared", "x");
+        assert_eq!(msg.text, "Bezeichner 'x' ist nicht deklariert");
+
+        // the code is part of the key, and a message with no translation stays in English
+        let msg = message!(T002, "Identifier '{}' not declared", "x");
+        assert_eq!(msg.text, "Identifier 'x' not declared");
+
+        set_catalog(None);
+        let msg = message!(T001, "Identifier '{}' not declared", "x");
+        assert_eq!(msg.text, "Identifier 'x' not declared");
+    }
+
+    #[test]
+    fn test_catalog_errs() {
+        let err = |text: &str| Catalog::parse(text).expect_err("should not parse");
+
+        assert_eq!(err("= no template").line, 1);
+        assert_eq!(err("[T001] a\n[T002] b\n= c").line, 2);
+        assert_eq!(err("[T001] a").line, 1);
+        assert!(err("[T999] a\n= b").msg.contains("T999"));
+        assert_eq!(err("T001 a\n= b").line, 1);
+    }
+}
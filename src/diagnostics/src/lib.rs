@@ -0,0 +1,165 @@
+//! Stable codes for the errors RustScript reports, with an explanation of each.
+//!
+//! The letter of a code is the stage that reports it: P for the parser, T for the type checker, C for the
+//! compiler and R for the runtime. A code keeps its meaning once it is released, so it can be searched for and
+//! explained with `ignite --explain T012` long after the wording of the message has changed. An error that goes
+//! away retires its code rather than giving it to a new one.
+//!
+//! Messages are made with [`message!`], which looks them up in a [`Catalog`] so they can be shown in another
+//! language. Tests can check the code of an error rather than its wording.
+
+use std::{fmt::Display, str::FromStr};
+
+pub use catalog::*;
+
+mod catalog;
+
+// Each code with its title. The explanation of a code is in explain/<code>.md
+macro_rules! codes {
+    ($($code:ident => $title:literal,)*) => {
+        /// The code of an error, see the [crate] docs.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum Code {
+            $($code,)*
+        }
+
+        impl Code {
+            /// Every code, in order.
+            pub const ALL: &'static [Code] = &[$(Code::$code,)*];
+
+            pub fn as_str(self) -> &'static str {
+                match self {
+                    $(Code::$code => stringify!($code),)*
+                }
+            }
+
+            /// One line saying what went wrong.
+            pub fn title(self) -> &'static str {
+                match self {
+                    $(Code::$code => $title,)*
+                }
+            }
+
+            /// What the error means and how to fix it, with examples.
+            pub fn explanation(self) -> &'static str {
+                match self {
+                    $(Code::$code => include_str!(concat!("../explain/", stringify!($code), ".md")),)*
+                }
+            }
+        }
+    };
+}
+
+codes! {
+    P001 => "Unexpected token",
+    P002 => "Missing token",
+    P003 => "Statement used as an expression",
+    P004 => "break or return outside of a loop or function",
+    P005 => "Invalid type annotation",
+    P006 => "Invalid array length",
+    P007 => "Chained comparison",
+    P008 => "Invalid macro",
+    P009 => "Misplaced attribute or doc comment",
+    P010 => "Parameter bound more than once",
+    P011 => "Invalid token",
+    T001 => "Undeclared name",
+    T002 => "Mismatched types",
+    T003 => "Operator applied to the wrong types",
+    T004 => "Wrong arguments in a call",
+    T005 => "Unknown type",
+    T006 => "Missing type annotation",
+    T007 => "Invalid struct, enum or field",
+    T008 => "Invalid index or slice",
+    T009 => "Invalid match",
+    T010 => "Invalid attribute",
+    T011 => "Value can't be iterated over",
+    T012 => "Misused thread or mutex",
+    T013 => "Macro not expanded before type checking",
+    C001 => "Macro not expanded before compiling",
+    C002 => "Source file not found",
+    C003 => "Not a RustScript source file",
+    C004 => "Warning denied by strict mode",
+    R001 => "Division by zero",
+    R002 => "Integer overflow",
+    R003 => "Index out of bounds",
+    R004 => "Missing key or field",
+    R005 => "Empty array",
+    R006 => "Value of the wrong type",
+    R007 => "Invalid argument",
+    R008 => "Unbound name",
+    R009 => "Permission denied",
+    R010 => "Invalid thread operation",
+    R011 => "Malformed bytecode",
+    R012 => "I/O error",
+    R013 => "Out of memory",
+    R014 => "Stack overflow",
+    R015 => "Assertion failed",
+}
+
+impl Code {
+    /// The title and explanation of the code, as `--explain` prints them.
+    pub fn explain(self) -> String {
+        format!("{}: {}\n\n{}", self, self.title(), self.explanation())
+    }
+}
+
+impl Display for Code {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// A string that isn't the code of any error.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnknownCode(pub String);
+
+impl Display for UnknownCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "No error has the code {}, codes look like T012", self.0)
+    }
+}
+
+impl std::error::Error for UnknownCode {}
+
+// case doesn't matter, t012 is T012
+impl FromStr for Code {
+    type Err = UnknownCode;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Code::ALL
+            .iter()
+            .find(|code| code.as_str().eq_ignore_ascii_case(s.trim()))
+            .copied()
+            .ok_or_else(|| UnknownCode(s.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_code() {
+        assert_eq!("T012".parse(), Ok(Code::T012));
+        assert_eq!(" r001 ".parse(), Ok(Code::R001));
+        assert_eq!("T999".parse::<Code>(), Err(UnknownCode("T999".to_string())));
+
+        for code in Code::ALL {
+            assert_eq!(code.as_str().parse(), Ok(*code));
+        }
+    }
+
+    // every explanation starts with what the error means and shows an example
+    #[test]
+    fn test_explanations() {
+        for code in Code::ALL {
+            let explanation = code.explanation();
+            assert!(!explanation.starts_with('\n'), "{} starts blank", code);
+            assert!(explanation.contains("```"), "{} has no example", code);
+        }
+
+        assert!(Code::T002
+            .explain()
+            .starts_with("T002: Mismatched types\n\n"));
+    }
+}
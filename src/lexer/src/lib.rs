@@ -1,4 +1,4 @@
-use logos::{Lexer, Logos, Skip};
+use logos::{Filter, FilterResult, Lexer, Logos, Skip};
 
 /// Update the line count and the char index.
 fn newline_callback(lex: &mut Lexer<Token>) -> Skip {
@@ -12,8 +12,163 @@ fn comment_callback(_lex: &mut Lexer<Token>) -> Skip {
     Skip
 }
 
+/// Skip a block comment. Like Rust, block comments nest, so /* a /* b */ c */ is one comment and code with
+/// comments in it can be commented out. Newlines in the comment still count towards the line.
+fn block_comment_callback(lex: &mut Lexer<Token>) -> FilterResult<(), LexError> {
+    let rest = lex.remainder();
+    let mut depth = 1;
+    let mut len = 0;
+    while depth > 0 {
+        match rest.as_bytes().get(len..len + 2) {
+            Some(b"/*") => {
+                depth += 1;
+                len += 2;
+            }
+            Some(b"*/") => {
+                depth -= 1;
+                len += 2;
+            }
+            Some(_) => len += 1,
+            None => {
+                lex.bump(rest.len());
+                return FilterResult::Error(LexError::UnterminatedComment);
+            }
+        }
+    }
+
+    let comment = &rest[..len];
+    if let Some(last) = comment.rfind('\n') {
+        lex.extras.0 += comment.matches('\n').count();
+        lex.extras.1 = lex.span().end + last + 1;
+    }
+    lex.bump(len);
+    FilterResult::Skip
+}
+
+/// Keep the text of a doc comment without the leading '///' and one space. Like Rust, '////' is a plain comment.
+fn doc_comment_callback(lex: &mut Lexer<Token>) -> Filter<String> {
+    let text = &lex.slice()[3..];
+    if text.starts_with('/') {
+        return Filter::Skip;
+    }
+
+    let text = text.strip_prefix(' ').unwrap_or(text);
+    Filter::Emit(text.trim_end().to_owned())
+}
+
+/// Why the lexer couldn't make a token.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum LexError {
+    /// A character that doesn't start any token.
+    #[default]
+    UnexpectedChar,
+    /// A backslash in a string or char literal that doesn't start an escape, with the text of the escape.
+    InvalidEscape(String),
+    /// A string with no closing quote, or a raw string with no closing quote and hashes.
+    UnterminatedString,
+    /// A /* with no */ to close it.
+    UnterminatedComment,
+}
+
+impl std::fmt::Display for LexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LexError::UnexpectedChar => write!(f, "unexpected character"),
+            LexError::InvalidEscape(esc) => write!(
+                f,
+                r#"invalid escape '{}', expected one of \n \r \t \0 \\ \" \' or \u{{..}} with 1 to 6 hex digits"#,
+                esc
+            ),
+            LexError::UnterminatedString => write!(f, "unterminated string"),
+            LexError::UnterminatedComment => write!(f, "unterminated block comment"),
+        }
+    }
+}
+
+/// Turn the escapes in the text of a string or char literal into the chars they stand for: \n, \r, \t, \0, \\,
+/// \", \' and \u{..} with the hex code of any char.
+fn unescape(text: &str) -> Result<String, LexError> {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+
+        let escaped = match chars.next() {
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('0') => '\0',
+            Some('\\') => '\\',
+            Some('"') => '"',
+            Some('\'') => '\'',
+            Some('u') => unicode_escape(&mut chars)?,
+            Some(other) => return Err(LexError::InvalidEscape(format!("\\{}", other))),
+            None => return Err(LexError::InvalidEscape("\\".to_string())),
+        };
+        out.push(escaped);
+    }
+
+    Ok(out)
+}
+
+// The char of \u{..}, with chars just after the u
+fn unicode_escape(chars: &mut std::str::Chars) -> Result<char, LexError> {
+    let rest = chars.as_str();
+    let close = rest.find('}').filter(|_| rest.starts_with('{'));
+    let Some(close) = close else {
+        return Err(LexError::InvalidEscape("\\u".to_string()));
+    };
+
+    let hex = &rest[1..close];
+    let c = Some(hex)
+        .filter(|hex| (1..=6).contains(&hex.len()) && hex.chars().all(|c| c.is_ascii_hexdigit()))
+        .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+        .and_then(char::from_u32);
+    let Some(c) = c else {
+        return Err(LexError::InvalidEscape(format!("\\u{}", &rest[..=close])));
+    };
+
+    *chars = rest[close + 1..].chars();
+    Ok(c)
+}
+
+/// The text of a string literal, with the quotes taken off and the escapes turned into the chars they stand for.
+fn string_callback(lex: &mut Lexer<Token>) -> Result<String, LexError> {
+    let slice = lex.slice();
+    unescape(&slice[1..slice.len() - 1])
+}
+
+/// The text of a raw string, r"..." or r#"..."# with any number of #, kept as it is written. The regex only
+/// matches up to the opening quote, since it can't count the # to find the end.
+fn raw_string_callback(lex: &mut Lexer<Token>) -> Result<String, LexError> {
+    let hashes = lex.slice().len() - 2;
+    let close = format!("\"{}", "#".repeat(hashes));
+    let Some(end) = lex.remainder().find(&close) else {
+        lex.bump(lex.remainder().len());
+        return Err(LexError::UnterminatedString);
+    };
+
+    let text = lex.remainder()[..end].to_owned();
+    lex.bump(end + close.len());
+    Ok(text)
+}
+
+/// The char of a char literal, with the quotes taken off and an escape like '\n' turned into the char it stands for.
+fn char_callback(lex: &mut Lexer<Token>) -> Result<char, LexError> {
+    let slice = lex.slice();
+    let text = unescape(&slice[1..slice.len() - 1])?;
+    let mut chars = text.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(c),
+        _ => Err(LexError::UnexpectedChar),
+    }
+}
+
 #[derive(Debug, Logos, PartialEq, Clone)]
-#[logos(skip r"[ \t\r\f]+", extras=(usize, usize))]
+#[logos(skip r"[ \t\r\f]+", extras=(usize, usize), error = LexError)]
 // #[logos(extras = (usize, usize))]
 pub enum Token {
     #[regex(r"\n", newline_callback)]
@@ -25,9 +180,18 @@ pub enum Token {
     #[token(":")]
     Colon,
 
+    #[token("::")]
+    DoubleColon,
+
     #[token(".")]
     Dot,
 
+    #[token("..")]
+    DotDot,
+
+    #[token("..=")]
+    DotDotEq,
+
     #[token(",")]
     Comma,
 
@@ -61,6 +225,9 @@ pub enum Token {
     #[token("?")]
     Question,
 
+    #[token("??")]
+    Coalesce,
+
     #[token("$")]
     Dollar,
 
@@ -70,6 +237,9 @@ pub enum Token {
     #[token("==")]
     LogEq,
 
+    #[token("=>")]
+    FatArrow,
+
     #[token("!")]
     Bang,
 
@@ -94,6 +264,9 @@ pub enum Token {
     #[token("||")]
     LogOr,
 
+    #[token("|>")]
+    Pipeline,
+
     #[token("+")]
     Plus,
 
@@ -121,6 +294,21 @@ pub enum Token {
     #[token("fn")]
     Fn,
 
+    #[token("struct")]
+    Struct,
+
+    #[token("enum")]
+    Enum,
+
+    #[token("macro")]
+    Macro,
+
+    #[token("match")]
+    Match,
+
+    #[token("lock")]
+    Lock,
+
     #[token("->")]
     FnDeclReturn,
 
@@ -130,21 +318,44 @@ pub enum Token {
     #[regex(r#"[a-zA-Z_][a-zA-Z0-9_]*"#, |lex| lex.slice().to_owned())]
     Ident(String),
 
+    // $x - a parameter inside a macro body, includes the $
+    #[regex(r#"\$[a-zA-Z_][a-zA-Z0-9_]*"#, |lex| lex.slice().to_owned())]
+    MacroVar(String),
+
     #[regex(r#"//[^\n]*"#, comment_callback)]
+    #[token("/*", block_comment_callback)]
     Comment,
 
+    #[regex(r#"///[^\n]*"#, doc_comment_callback)]
+    DocComment(String),
+
     #[token("loop")]
     Loop,
 
     #[token("break")]
     Break,
 
+    #[token("while")]
+    While,
+
+    #[token("for")]
+    For,
+
+    #[token("in")]
+    In,
+
     #[token("spawn")]
     Spawn,
 
     #[token("join")]
     Join,
 
+    #[token("try_join")]
+    TryJoin,
+
+    #[token("join_timeout")]
+    JoinTimeout,
+
     #[token("wait")]
     Wait,
 
@@ -167,12 +378,17 @@ pub enum Token {
     #[regex(r"\d*\.\d+", |lex| lex.slice().parse::<f64>().unwrap())]
     Float(f64),
 
-    #[regex(r#""([^"\\]|\\["\\bnfrt]|u[a-fA-F0-9]{4})*""#, |lex| {
-      let slice = lex.slice();
-      let stripped = &slice[1..slice.len() - 1];
-      stripped.to_owned()
-  })]
+    // "text", with escapes like \n turned into the chars they stand for. A string with no closing quote runs to
+    // the end of the input, so the error is about the string rather than the next thing after it
+    #[regex(r#""([^"\\]|\\.)*""#, string_callback)]
+    #[regex(r#""([^"\\]|\\.)*"#, |_| Err(LexError::UnterminatedString))]
+    // r"text" or r#"text"#, with no escapes
+    #[regex(r##"r#*""##, raw_string_callback)]
     String(String),
+
+    // 'a', or an escape like the ones in strings: '\n' or '\u{e9}'
+    #[regex(r"'([^'\\\n]|\\[^\n]|\\u\{[0-9a-fA-F]*\})'", char_callback)]
+    Char(char),
 }
 
 impl std::fmt::Display for Token {
@@ -185,10 +401,15 @@ impl Token {
     pub fn repr(&self) -> String {
         match self {
             Self::Ident(id) => id.to_string(),
+            Self::MacroVar(var) => var.to_string(),
             Self::String(str) => str.to_string(),
+            Self::Char(c) => format!("{:?}", c),
             Self::Semi => ";".to_string(),
             Self::Colon => ":".to_string(),
+            Self::DoubleColon => "::".to_string(),
             Self::Dot => ".".to_string(),
+            Self::DotDot => "..".to_string(),
+            Self::DotDotEq => "..=".to_string(),
             Self::Comma => ",".to_string(),
             Self::OpenParen => "(".to_string(),
             Self::CloseParen => ")".to_string(),
@@ -220,17 +441,31 @@ impl Token {
             Self::If => "if".to_string(),
             Self::Else => "else".to_string(),
             Self::LogEq => "==".to_string(),
+            Self::FatArrow => "=>".to_string(),
             Self::LogAnd => "&&".to_string(),
             Self::LogOr => "||".to_string(),
+            Self::Pipeline => "|>".to_string(),
+            Self::Coalesce => "??".to_string(),
             Self::Loop => "loop".to_string(),
             Self::Break => "break".to_string(),
+            Self::While => "while".to_string(),
+            Self::For => "for".to_string(),
+            Self::In => "in".to_string(),
             Self::Comment => "//".to_string(),
+            Self::DocComment(_) => "///".to_string(),
             Self::Newline => "\n".to_string(),
             Self::Fn => "fn".to_string(),
+            Self::Struct => "struct".to_string(),
+            Self::Enum => "enum".to_string(),
+            Self::Macro => "macro".to_string(),
+            Self::Match => "match".to_string(),
+            Self::Lock => "lock".to_string(),
             Self::Return => "return".to_string(),
             Self::FnDeclReturn => "->".to_string(),
             Self::Spawn => "spawn".to_string(),
             Self::Join => "join".to_string(),
+            Self::TryJoin => "try_join".to_string(),
+            Self::JoinTimeout => "join_timeout".to_string(),
             Self::Wait => "wait".to_string(),
             Self::Post => "post".to_string(),
             Self::Yield => "yield".to_string(),
@@ -242,11 +477,70 @@ pub fn lex(input: &str) -> Lexer<'_, Token> {
     Token::lexer(input)
 }
 
+/// Where a token, or a node parsed from tokens, is in the source: its byte range, and the line and column it
+/// starts at, both counting from 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub col: usize,
+}
+
+impl Span {
+    /// The span from the start of self to the end of other.
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            end: other.end,
+            ..self
+        }
+    }
+}
+
+impl std::fmt::Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.col)
+    }
+}
+
+/// Lex all of the input, pairing each token with its span. Columns count chars, not bytes.
+pub fn lex_spanned(mut lexer: Lexer<'_, Token>) -> Vec<(Result<Token, LexError>, Span)> {
+    let source = lexer.source();
+    let mut tokens = vec![];
+    let mut line = 1;
+    let mut line_start = 0;
+    // everything before this has been scanned for newlines
+    let mut scanned = 0;
+
+    while let Some(tok) = lexer.next() {
+        let range = lexer.span();
+        for (i, c) in source[scanned..range.start].char_indices() {
+            if c == '\n' {
+                line += 1;
+                line_start = scanned + i + 1;
+            }
+        }
+        scanned = range.start;
+
+        let col = source[line_start..range.start].chars().count() + 1;
+        tokens.push((
+            tok,
+            Span {
+                start: range.start,
+                end: range.end,
+                line,
+                col,
+            },
+        ));
+    }
+
+    tokens
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
     use std::f64;
-    use std::i64;
 
     #[test]
     fn test_bool() {
@@ -379,6 +673,90 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_string_escapes() {
+        let lex_one = |t: &str| Token::lexer(t).next().expect("Expected a token");
+        let string = |s: &str| Ok(Token::String(s.to_string()));
+
+        assert_eq!(
+            lex_one(r#""a\nb\t\"c\"\\ \r\0 \'""#),
+            string("a\nb\t\"c\"\\ \r\0 '")
+        );
+        assert_eq!(lex_one(r#""\u{41}\u{e9}\u{1F600}""#), string("Aé😀"));
+        assert_eq!(lex_one("\"two\nlines\""), string("two\nlines"));
+
+        let invalid = |esc: &str| Err(LexError::InvalidEscape(esc.to_string()));
+        assert_eq!(lex_one(r#""a\qb""#), invalid("\\q"));
+        assert_eq!(lex_one(r#""\u0041""#), invalid("\\u"));
+        assert_eq!(lex_one(r#""\u{}""#), invalid("\\u{}"));
+        assert_eq!(lex_one(r#""\u{1234567}""#), invalid("\\u{1234567}"));
+        assert_eq!(lex_one(r#""\u{D800}""#), invalid("\\u{D800}"));
+        assert_eq!(lex_one(r#""\u{zz}""#), invalid("\\u{zz}"));
+
+        // the rest of the input is part of the string
+        let mut lexer = Token::lexer(r#"let s = "abc; let t = 2;"#);
+        assert_eq!(lexer.nth(3), Some(Err(LexError::UnterminatedString)));
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn test_raw_string() {
+        let tokens: Vec<Result<Token, LexError>> =
+            Token::lexer(r###"r"C:\dir\n" r#"say "hi""# r##"a "# b"## r"" r"###).collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Ok(Token::String(r"C:\dir\n".to_string())),
+                Ok(Token::String(r#"say "hi""#.to_string())),
+                Ok(Token::String(r##"a "# b"##.to_string())),
+                Ok(Token::String(String::new())),
+                Ok(Token::Ident("r".to_string())),
+            ]
+        );
+
+        // r without a quote after it is still a name
+        let tokens: Vec<Token> = Token::lexer("r + r2 r #[test]")
+            .map(|tok| tok.expect("Expected token"))
+            .collect();
+        assert_eq!(tokens[0], Token::Ident("r".to_string()));
+        assert_eq!(tokens[2], Token::Ident("r2".to_string()));
+        assert_eq!(tokens[3], Token::Ident("r".to_string()));
+        assert_eq!(tokens[4], Token::Pound);
+
+        assert_eq!(
+            Token::lexer(r##"r#"open" ended"##).next(),
+            Some(Err(LexError::UnterminatedString))
+        );
+    }
+
+    #[test]
+    fn test_char() {
+        let lexer = Token::lexer(r#"'a' 'é' '\n' '\'' '\\' ' ' '"' '\"' '\u{263A}'"#);
+        let tokens: Vec<Token> = lexer.map(|tok| tok.expect("Expected token")).collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Char('a'),
+                Token::Char('é'),
+                Token::Char('\n'),
+                Token::Char('\''),
+                Token::Char('\\'),
+                Token::Char(' '),
+                Token::Char('"'),
+                Token::Char('"'),
+                Token::Char('☺'),
+            ]
+        );
+
+        // more than one char, or none, isn't a char literal
+        assert!(Token::lexer("'ab'").any(|tok| tok.is_err()));
+        assert!(Token::lexer("''").any(|tok| tok.is_err()));
+        assert_eq!(
+            Token::lexer(r"'\q'").next(),
+            Some(Err(LexError::InvalidEscape(r"\q".to_string())))
+        );
+    }
+
     #[test]
     fn test_single_char_symbols() {
         let input = ";:.,{}()@#~?$=-&|+*/^%";
@@ -414,6 +792,65 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_multi_char_symbols() {
+        let input = "-> => :: .. ..= |> ||> ??? ??";
+        let mut lexer = Token::lexer(input);
+
+        let expected = vec![
+            Token::FnDeclReturn,
+            Token::FatArrow,
+            Token::DoubleColon,
+            Token::DotDot,
+            Token::DotDotEq,
+            Token::Pipeline,
+            Token::LogOr,
+            Token::Gt,
+            Token::Coalesce,
+            Token::Question,
+            Token::Coalesce,
+        ];
+
+        for e in expected {
+            assert_eq!(e, lexer.next().unwrap().expect("Expected token"));
+        }
+    }
+
+    #[test]
+    fn test_multi_char_symbols_maximal_munch() {
+        // The longest operator wins, whatever is left over starts the next token
+        let input = "-->==>:::...= 1..2 0..=9 .5.. ...1";
+        let mut lexer = Token::lexer(input);
+
+        let expected = vec![
+            Token::Minus,
+            Token::FnDeclReturn,
+            Token::LogEq,
+            Token::Gt,
+            Token::DoubleColon,
+            Token::Colon,
+            Token::DotDot,
+            Token::Dot,
+            Token::Eq,
+            Token::Integer(1),
+            Token::DotDot,
+            Token::Integer(2),
+            Token::Integer(0),
+            Token::DotDotEq,
+            Token::Integer(9),
+            Token::Float(0.5),
+            Token::DotDot,
+            // A dot directly before digits is part of a float
+            Token::DotDot,
+            Token::Float(0.1),
+        ];
+
+        for e in expected {
+            assert_eq!(e, lexer.next().unwrap().expect("Expected token"));
+        }
+        assert_eq!(lexer.next(), None);
+    }
+
     #[test]
     fn test_identifiers() {
         let input = "foo bar baz _john _ fn let mut continue break struct";
@@ -465,7 +902,7 @@ mod test {
         let mut lexer = Token::lexer(input);
 
         let expected = vec![
-            Token::Ident("while".to_string()),
+            Token::While,
             Token::OpenParen,
             Token::Ident("x".to_string()),
             Token::Lt,
@@ -592,6 +1029,22 @@ mod test {
         for e in exp {
             assert_eq!(e, lexer.next().unwrap().expect("Expected token"));
         }
+
+        let t = "while whiles { } for i in inx";
+        let exp = vec![
+            Token::While,
+            Token::Ident("whiles".to_string()),
+            Token::OpenBrace,
+            Token::CloseBrace,
+            Token::For,
+            Token::Ident("i".to_string()),
+            Token::In,
+            Token::Ident("inx".to_string()),
+        ];
+        let mut lexer = Token::lexer(t);
+        for e in exp {
+            assert_eq!(e, lexer.next().unwrap().expect("Expected token"));
+        }
     }
 
     #[test]
@@ -638,15 +1091,84 @@ mod test {
         assert_eq!(lexer.next(), None);
     }
 
+    #[test]
+    fn test_lex_block_comments() {
+        let t = "1 /* one */ 2 /* a /* nested */ still // in it\n */ 3 /**/ 4 /*/ 5 */ 6";
+        let tokens: Vec<Token> = Token::lexer(t)
+            .map(|tok| tok.expect("Expected token"))
+            .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Integer(1),
+                Token::Integer(2),
+                Token::Integer(3),
+                Token::Integer(4),
+                Token::Integer(6),
+            ]
+        );
+
+        // newlines in a comment count towards the line
+        let mut lexer = Token::lexer("/*\n\n*/ 1\n2");
+        assert_eq!(lexer.next(), Some(Ok(Token::Integer(1))));
+        assert_eq!(lexer.extras, (2, 4));
+        assert_eq!(lexer.next(), Some(Ok(Token::Integer(2))));
+        assert_eq!(lexer.extras.0, 3);
+
+        // a string can hold the start of a comment, and a line comment can hold either end
+        let tokens: Vec<Token> = Token::lexer("\"/*\" // */ /*\n;")
+            .map(|tok| tok.expect("Expected token"))
+            .collect();
+        assert_eq!(tokens, vec![Token::String("/*".to_string()), Token::Semi]);
+
+        let mut lexer = Token::lexer("1 /* a /* b */");
+        assert_eq!(lexer.next(), Some(Ok(Token::Integer(1))));
+        assert_eq!(lexer.next(), Some(Err(LexError::UnterminatedComment)));
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn test_lex_doc_comments() {
+        let t = r"
+        /// Adds one.
+        ///
+        ///Returns an int.   
+        //// not a doc comment
+        fn // /// not a doc comment either
+        ";
+        let mut lexer = Token::lexer(t);
+        assert_eq!(
+            lexer.next().unwrap().unwrap(),
+            Token::DocComment("Adds one.".to_string())
+        );
+        assert_eq!(
+            lexer.next().unwrap().unwrap(),
+            Token::DocComment("".to_string())
+        );
+        assert_eq!(
+            lexer.next().unwrap().unwrap(),
+            Token::DocComment("Returns an int.".to_string())
+        );
+        assert_eq!(lexer.next().unwrap().unwrap(), Token::Fn);
+        assert_eq!(lexer.extras.0, 5);
+        assert_eq!(lexer.next(), None);
+    }
+
     #[test]
     fn test_lex_spawn_join() {
         let t = r"
-        spawn join
+        spawn join try_join join_timeout joined
         ";
         let mut lexer = Token::lexer(t);
 
         assert_eq!(lexer.next().unwrap().unwrap(), Token::Spawn);
         assert_eq!(lexer.next().unwrap().unwrap(), Token::Join);
+        assert_eq!(lexer.next().unwrap().unwrap(), Token::TryJoin);
+        assert_eq!(lexer.next().unwrap().unwrap(), Token::JoinTimeout);
+        assert_eq!(
+            lexer.next().unwrap().unwrap(),
+            Token::Ident("joined".to_string())
+        );
     }
 
     #[test]
@@ -660,4 +1182,145 @@ mod test {
         assert_eq!(lexer.next().unwrap().unwrap(), Token::Post);
         assert_eq!(lexer.next().unwrap().unwrap(), Token::Yield);
     }
+
+    #[test]
+    fn test_lex_struct() {
+        let t = "struct structs p.x";
+        let mut lexer = Token::lexer(t);
+
+        assert_eq!(lexer.next().unwrap().unwrap(), Token::Struct);
+        assert_eq!(
+            lexer.next().unwrap().unwrap(),
+            Token::Ident("structs".to_string())
+        );
+        assert_eq!(
+            lexer.next().unwrap().unwrap(),
+            Token::Ident("p".to_string())
+        );
+        assert_eq!(lexer.next().unwrap().unwrap(), Token::Dot);
+        assert_eq!(
+            lexer.next().unwrap().unwrap(),
+            Token::Ident("x".to_string())
+        );
+    }
+
+    #[test]
+    fn test_lex_enum() {
+        let t = "enum enums Shape";
+        let mut lexer = Token::lexer(t);
+
+        assert_eq!(lexer.next().unwrap().unwrap(), Token::Enum);
+        assert_eq!(
+            lexer.next().unwrap().unwrap(),
+            Token::Ident("enums".to_string())
+        );
+        assert_eq!(
+            lexer.next().unwrap().unwrap(),
+            Token::Ident("Shape".to_string())
+        );
+    }
+
+    #[test]
+    fn test_lex_macro() {
+        let t = "macro swap($a, $b) $ x!";
+        let mut lexer = Token::lexer(t);
+
+        assert_eq!(lexer.next().unwrap().unwrap(), Token::Macro);
+        assert_eq!(
+            lexer.next().unwrap().unwrap(),
+            Token::Ident("swap".to_string())
+        );
+        assert_eq!(lexer.next().unwrap().unwrap(), Token::OpenParen);
+        assert_eq!(
+            lexer.next().unwrap().unwrap(),
+            Token::MacroVar("$a".to_string())
+        );
+        assert_eq!(lexer.next().unwrap().unwrap(), Token::Comma);
+        assert_eq!(
+            lexer.next().unwrap().unwrap(),
+            Token::MacroVar("$b".to_string())
+        );
+        assert_eq!(lexer.next().unwrap().unwrap(), Token::CloseParen);
+        assert_eq!(lexer.next().unwrap().unwrap(), Token::Dollar);
+        assert_eq!(
+            lexer.next().unwrap().unwrap(),
+            Token::Ident("x".to_string())
+        );
+        assert_eq!(lexer.next().unwrap().unwrap(), Token::Bang);
+    }
+
+    #[test]
+    fn test_lex_match() {
+        let t = "match x { 1 => y, _ => z } matches";
+        let mut lexer = Token::lexer(t);
+
+        let expected = vec![
+            Token::Match,
+            Token::Ident("x".to_string()),
+            Token::OpenBrace,
+            Token::Integer(1),
+            Token::FatArrow,
+            Token::Ident("y".to_string()),
+            Token::Comma,
+            Token::Ident("_".to_string()),
+            Token::FatArrow,
+            Token::Ident("z".to_string()),
+            Token::CloseBrace,
+            Token::Ident("matches".to_string()),
+        ];
+
+        for e in expected {
+            assert_eq!(e, lexer.next().unwrap().expect("Expected token"));
+        }
+    }
+
+    #[test]
+    fn test_lex_lock() {
+        let t = "lock m { x } locked";
+        let mut lexer = Token::lexer(t);
+
+        let expected = vec![
+            Token::Lock,
+            Token::Ident("m".to_string()),
+            Token::OpenBrace,
+            Token::Ident("x".to_string()),
+            Token::CloseBrace,
+            Token::Ident("locked".to_string()),
+        ];
+
+        for e in expected {
+            assert_eq!(e, lexer.next().unwrap().expect("Expected token"));
+        }
+    }
+
+    #[test]
+    fn test_lex_spanned() {
+        let t = "let s = \"a\";\n  // comment\n\n\tx \"é\"+ y";
+        let spans: Vec<(Token, usize, usize)> = lex_spanned(lex(t))
+            .into_iter()
+            .map(|(tok, span)| (tok.expect("Expected token"), span.line, span.col))
+            .collect();
+
+        let expected = vec![
+            (Token::Let, 1, 1),
+            (Token::Ident("s".to_string()), 1, 5),
+            (Token::Eq, 1, 7),
+            (Token::String("a".to_string()), 1, 9),
+            (Token::Semi, 1, 12),
+            (Token::Ident("x".to_string()), 4, 2),
+            (Token::String("é".to_string()), 4, 4),
+            (Token::Plus, 4, 7),
+            (Token::Ident("y".to_string()), 4, 9),
+        ];
+        assert_eq!(spans, expected);
+
+        let span = Span {
+            start: 4,
+            end: 5,
+            line: 1,
+            col: 5,
+        };
+        assert_eq!(span.to(Span { end: 9, ..span }).end, 9);
+        assert_eq!(span.to_string(), "line 1, column 5");
+    }
 }
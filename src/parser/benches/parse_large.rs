@@ -0,0 +1,52 @@
+//! Parses large generated programs, to keep an eye on the cost of building the AST.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use parser::Parser;
+
+/// `lines` statements of mixed declarations, assignments and control flow.
+fn generate_program(lines: usize) -> String {
+    let mut prog = String::new();
+    for i in 0..lines {
+        let line = match i % 4 {
+            0 => format!("let x{i} = {i} + 2 * ({i} - 1) / 3;\n"),
+            1 => format!("x{} = x{} * 2 + 1 - 4;\n", i - 1, i - 1),
+            2 => format!(
+                "if x{} > 10 {{ x{} = 0; }} else {{ x{} = 1; }}\n",
+                i - 2,
+                i - 2,
+                i - 2
+            ),
+            _ => format!("fn f{i}(a: int, b: int) -> int {{ a * b + {i} }}\n"),
+        };
+        prog.push_str(&line);
+    }
+    prog
+}
+
+/// A single expression chaining `terms` additions, which stresses the Pratt loop.
+fn generate_chain(terms: usize) -> String {
+    let mut prog = String::from("1");
+    for i in 0..terms {
+        prog.push_str(&format!(" + {i}"));
+    }
+    prog
+}
+
+fn bench_parse(c: &mut Criterion) {
+    let prog = generate_program(100_000);
+    c.bench_function("parse 100k lines", |b| {
+        b.iter(|| Parser::new_from_string(black_box(&prog)).parse().unwrap())
+    });
+
+    let chain = generate_chain(2_000);
+    c.bench_function("parse 2k term chain", |b| {
+        b.iter(|| Parser::new_from_string(black_box(&chain)).parse().unwrap())
+    });
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = bench_parse
+}
+criterion_main!(benches);
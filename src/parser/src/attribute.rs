@@ -0,0 +1,109 @@
+use crate::Attribute;
+use crate::ParseError;
+use crate::Parser;
+use diagnostics::message;
+use lexer::Token;
+
+impl Parser {
+    // Invariant: peek is at '#'
+    // #[name] or #[name(arg, ...)]
+    pub(crate) fn parse_attribute(&mut self) -> Result<Attribute, ParseError> {
+        self.consume_token_type(Token::Pound, "Expected '#' to start attribute")?;
+        self.consume_token_type(Token::OpenBracket, "Expected '[' after '#' for attribute")?;
+
+        self.advance();
+        let name = match self.expect_prev_tok()? {
+            Token::Ident(name) => name.to_owned(),
+            tok => {
+                let e = message!(P009, "Expected attribute name but got '{}'", tok);
+                return Err(self.prev_tok_err(e));
+            }
+        };
+
+        let mut args = vec![];
+        if self.consume_opt_token_type(Token::OpenParen) {
+            while !self.is_peek_token_type(Token::CloseParen) {
+                self.advance();
+                args.push(self.parse_expr(0)?.to_expr()?);
+
+                if !self.is_peek_token_type(Token::CloseParen) {
+                    self.consume_token_type(
+                        Token::Comma,
+                        "Expected ',' to separate attribute arguments",
+                    )?;
+                }
+            }
+
+            self.consume_token_type(Token::CloseParen, "Expected ')'")?;
+        }
+
+        self.consume_token_type(Token::CloseBracket, "Expected ']' to close attribute")?;
+
+        Ok(Attribute { name, args })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::test_parse_err;
+    use crate::{Decl, Parser};
+
+    fn parse_attrs(inp: &str) -> Vec<String> {
+        let prog = Parser::new_from_string(inp).parse().expect("Should parse");
+        let attrs = match prog.decls.first() {
+            Some(Decl::FnDeclStmt(data)) => &data.attrs,
+            Some(Decl::LetStmt(data)) => &data.attrs,
+            _ => panic!("Expected fn or let"),
+        };
+        attrs.iter().map(|x| x.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_attributes() {
+        let t = r#"
+        #[test]
+        #[deprecated("use g")]
+        fn f() {}
+        "#;
+        assert_eq!(parse_attrs(t), vec!["#[test]", r#"#[deprecated("use g")]"#]);
+
+        // mixed with doc comments, any args are parsed as expressions
+        let t = r"
+        /// docs
+        #[inline]
+        /// more docs
+        #[custom(a, 1 + 2, )]
+        fn f() {}
+        ";
+        assert_eq!(parse_attrs(t), vec!["#[inline]", "#[custom(a,(1+2))]"]);
+
+        let t = "#[deprecated] let x = 2;";
+        assert_eq!(parse_attrs(t), vec!["#[deprecated]"]);
+    }
+
+    #[test]
+    fn test_parse_attribute_errs() {
+        test_parse_err(
+            "#test fn f() {}",
+            "Expected '[' after '#' for attribute",
+            true,
+        );
+        test_parse_err(
+            "#[2] fn f() {}",
+            "Expected attribute name but got '2'",
+            true,
+        );
+        test_parse_err("#[test fn f() {}", "Expected ']' to close attribute", true);
+        test_parse_err(
+            "#[a(1; 2)] fn f() {}",
+            "Expected ',' to separate attribute arguments",
+            true,
+        );
+        test_parse_err("#[test] 2;", "attributes to fn or let declarations", true);
+        test_parse_err(
+            "#[test]",
+            "Expected a declaration after doc comment or attribute",
+            true,
+        );
+    }
+}
@@ -4,7 +4,7 @@ use crate::ParseError;
 use crate::Parser;
 use lexer::Token;
 
-impl<'inp> Parser<'inp> {
+impl Parser {
     // Invariant: open brace has been consumed and peek is at the first token inside the block
     pub(crate) fn parse_blk(&mut self) -> Result<Decl, ParseError> {
         // BlockSeq - vec decls, last expr
@@ -14,7 +14,7 @@ impl<'inp> Parser<'inp> {
         self.consume_token_type(Token::CloseBrace, &err)?;
 
         // dbg!("prev_tok after blk:", &self.prev_tok);
-        // dbg!("peek after blk:", &self.lexer.peek());
+        // dbg!("peek after blk:", &self.tokens.peek());
 
         Ok(res)
     }
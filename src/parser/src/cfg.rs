@@ -0,0 +1,166 @@
+use std::collections::HashSet;
+
+use diagnostics::message;
+
+use crate::fold::{walk_blk, walk_expr, Fold};
+use crate::{Attribute, BlockSeq, Decl, Expr, ParseError};
+
+pub const CFG: &str = "cfg";
+
+/// Apply compile time flags to a parsed program. Declarations marked `#[cfg(flag)]` are removed when the flag
+/// is not in `defines`, and `cfg(flag)` expressions are folded to `true` or `false`.
+///
+/// Runs before type checking, so code under a flag that is not defined is never checked or compiled.
+pub fn apply_cfg(program: BlockSeq, defines: &HashSet<String>) -> Result<BlockSeq, ParseError> {
+    Cfg { defines }.fold_blk(program)
+}
+
+struct Cfg<'a> {
+    defines: &'a HashSet<String>,
+}
+
+impl<'a> Cfg<'a> {
+    fn flag_of(args: &[Expr]) -> Result<&str, ParseError> {
+        match args {
+            [Expr::Symbol(flag)] => Ok(flag),
+            _ => Err(ParseError::new(message!(
+                P009,
+                "{} expects a single flag name",
+                CFG
+            ))),
+        }
+    }
+
+    // false if the decl has a #[cfg(flag)] whose flag is not defined
+    fn is_enabled(&self, attrs: &[Attribute]) -> Result<bool, ParseError> {
+        for attr in attrs.iter().filter(|attr| attr.name == CFG) {
+            if !self.defines.contains(Cfg::flag_of(&attr.args)?) {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+impl<'a> Fold for Cfg<'a> {
+    fn fold_blk(&mut self, blk: BlockSeq) -> Result<BlockSeq, ParseError> {
+        let mut decls = vec![];
+        let mut spans = vec![];
+        let mut all_spans = blk.spans.into_iter();
+        for decl in blk.decls.into_iter() {
+            let span = all_spans.next();
+            let enabled = match &decl {
+                Decl::LetStmt(stmt) => self.is_enabled(&stmt.attrs)?,
+                Decl::FnDeclStmt(fn_decl) => self.is_enabled(&fn_decl.attrs)?,
+                _ => true,
+            };
+
+            if enabled {
+                decls.push(decl);
+                spans.extend(span);
+            }
+        }
+        // the span of last_expr, if any
+        spans.extend(all_spans);
+
+        // drop symbols whose only declarations were removed
+        let symbols = blk
+            .symbols
+            .into_iter()
+            .filter(|sym| {
+                decls.iter().any(|decl| match decl {
+                    Decl::LetStmt(stmt) => &stmt.ident == sym,
+                    Decl::LetTupleStmt(stmt) => stmt.idents.contains(sym),
+                    Decl::FnDeclStmt(fn_decl) => &fn_decl.name == sym,
+                    _ => false,
+                })
+            })
+            .collect();
+
+        let blk = BlockSeq {
+            decls,
+            last_expr: blk.last_expr,
+            symbols,
+            spans,
+        };
+        walk_blk(self, blk)
+    }
+
+    fn fold_expr(&mut self, expr: Expr) -> Result<Expr, ParseError> {
+        match expr {
+            Expr::FnCallExpr(fn_call) if fn_call.name == CFG => Ok(Expr::Bool(
+                self.defines.contains(Cfg::flag_of(&fn_call.args)?),
+            )),
+            _ => walk_expr(self, expr),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Parser;
+
+    fn test_cfg(inp: &str, defines: &[&str], exp: &str) -> BlockSeq {
+        let prog = Parser::new_from_string(inp).parse().expect("Should parse");
+        let defines = defines.iter().map(|x| x.to_string()).collect();
+        let prog = apply_cfg(prog, &defines).expect("Should apply cfg");
+        assert_eq!(prog.to_string(), exp);
+        prog
+    }
+
+    #[test]
+    fn test_apply_cfg() {
+        let t = r"
+        #[cfg(debug)]
+        fn log(x: int) { println(x); }
+
+        #[cfg(debug)]
+        let y = 2;
+
+        let x = 1;
+        if cfg(debug) {
+            log(x);
+        }
+        x
+        ";
+        let prog = test_cfg(t, &[], "let x = 1;if false { log(x); };x");
+        assert_eq!(prog.symbols, vec!["x".to_string()]);
+
+        let prog = test_cfg(
+            t,
+            &["debug"],
+            "fn log (x:int) { println(x); };let y = 2;let x = 1;if true { log(x); };x",
+        );
+        assert_eq!(prog.symbols.len(), 3);
+
+        // nested blocks and expressions
+        let t = r"
+        fn f() -> int {
+            #[cfg(fast)]
+            let n = 1;
+            let m = [cfg(fast), !cfg(slow)];
+            { cfg(slow) || cfg(fast) }
+        }
+        ";
+        test_cfg(
+            t,
+            &["slow"],
+            "fn f () -> int { let m = [false,(!true)];{ (true||false) } };",
+        );
+
+        // shadowed symbol is kept if any declaration is left
+        let prog = test_cfg("let x = 1; #[cfg(a)] let x = 2; x", &[], "let x = 1;x");
+        assert!(prog.symbols.contains(&"x".to_string()));
+    }
+
+    #[test]
+    fn test_apply_cfg_errs() {
+        for t in ["cfg(1)", "cfg()", "cfg(a, b)", "#[cfg] let x = 2;"] {
+            let prog = Parser::new_from_string(t).parse().expect("Should parse");
+            let err = apply_cfg(prog, &HashSet::new()).expect_err("Should fail");
+            assert!(err.to_string().contains("cfg expects a single flag name"));
+        }
+    }
+}
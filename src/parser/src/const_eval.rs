@@ -0,0 +1,53 @@
+use crate::{BinOpType, Expr, UnOpType};
+
+/// Evaluate an integer expression made of literals and arithmetic at compile time.
+///
+/// Returns None if the expression is not constant (e.g. it reads a variable or calls a function),
+/// is not an integer, or overflows or divides by zero while being evaluated.
+pub fn const_eval(expr: &Expr) -> Option<i64> {
+    match expr {
+        Expr::Integer(val) => Some(*val),
+        Expr::UnOpExpr(UnOpType::Negate, expr) => const_eval(expr)?.checked_neg(),
+        Expr::BinOpExpr(op, lhs, rhs) => {
+            let lhs = const_eval(lhs)?;
+            let rhs = const_eval(rhs)?;
+            match op {
+                BinOpType::Add => lhs.checked_add(rhs),
+                BinOpType::Sub => lhs.checked_sub(rhs),
+                BinOpType::Mul => lhs.checked_mul(rhs),
+                BinOpType::Div => lhs.checked_div(rhs),
+                BinOpType::Mod => lhs.checked_rem(rhs),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Parser;
+
+    fn eval(inp: &str) -> Option<i64> {
+        let prog = Parser::new_from_string(inp).parse().expect("Should parse");
+        let expr = prog.last_expr.expect("Should have last expr");
+        const_eval(&expr)
+    }
+
+    #[test]
+    fn test_const_eval() {
+        assert_eq!(eval("4"), Some(4));
+        assert_eq!(eval("2 * (3 + 1) - 10 / 5"), Some(6));
+        assert_eq!(eval("-2 + 1"), Some(-1));
+
+        assert_eq!(eval("n + 1"), None);
+        assert_eq!(eval("f(2)"), None);
+        assert_eq!(eval("1.5 * 2"), None);
+        assert_eq!(eval("2 > 1"), None);
+        assert_eq!(eval("4 / (2 - 2)"), None);
+        assert_eq!(eval("7 % 3 * 2"), Some(2));
+        assert_eq!(eval("4 % 0"), None);
+        assert_eq!(eval("9223372036854775807 + 1"), None);
+    }
+}
@@ -1,12 +1,14 @@
 use crate::Decl;
 use crate::Decl::*;
 use crate::Expr;
+use crate::FnCallData;
 use crate::ParseError;
 use crate::Parser;
 use crate::{BinOpType, UnOpType};
+use diagnostics::message;
 use lexer::Token;
 
-impl<'inp> Parser<'inp> {
+impl Parser {
     // Parses and returns an expression (something that is definitely an expression)
     // Return as Decl for consistency
     // Invariant: prev_tok should contain the start of the expr before call
@@ -16,13 +18,18 @@ impl<'inp> Parser<'inp> {
             Token::OpenParen => {
                 self.advance();
                 let lhs = self.parse_expr(0)?;
-                self.consume_token_type(Token::CloseParen, "Expected closing parenthesis")?;
-                Ok(lhs)
+                if self.is_peek_token_type(Token::Comma) {
+                    self.parse_tuple(lhs)
+                } else {
+                    self.consume_token_type(Token::CloseParen, "Expected closing parenthesis")?;
+                    Ok(lhs)
+                }
             }
             Token::Integer(val) => Ok(ExprStmt(Expr::Integer(*val))),
             Token::Float(val) => Ok(ExprStmt(Expr::Float(*val))),
             Token::Bool(val) => Ok(ExprStmt(Expr::Bool(*val))),
             Token::String(str) => Ok(ExprStmt(Expr::StringLiteral(str.to_owned()))),
+            Token::Char(c) => Ok(ExprStmt(Expr::Char(*c))),
             // Unary
             Token::Minus => {
                 let ((), r_bp) = Parser::get_prefix_bp(&UnOpType::Negate);
@@ -41,12 +48,26 @@ impl<'inp> Parser<'inp> {
             Token::Ident(id) => {
                 // Three cases: id, id = ..., id() => load var, assignment, func call
                 // Handle just id first
-                // dbg!(&self.lexer.peek());
+                // dbg!(&self.tokens.peek());
                 self.parse_ident(id.to_string(), min_bp)
             }
+            Token::MacroVar(var) => {
+                if !self.is_macro {
+                    let e = message!(P008, "Macro parameter '{}' used outside of a macro", var);
+                    return Err(self.prev_tok_err(e));
+                }
+                self.parse_ident(var.to_string(), min_bp)
+            }
             Token::OpenBrace => self.parse_blk(),
+            Token::OpenBracket => self.parse_array(),
+            Token::Pound => self.parse_map(),
             Token::If => self.parse_if_else(min_bp),
-            _ => Err(ParseError::new(&format!(
+            Token::Match => self.parse_match(),
+            Token::Lock => self.parse_lock(),
+            Token::TryJoin | Token::JoinTimeout => self.parse_try_join(),
+            Token::Or | Token::LogOr => self.parse_lambda(),
+            _ => Err(self.prev_tok_err(message!(
+                P001,
                 "Unexpected token - not an expression: '{}'",
                 prev_tok
             ))),
@@ -54,27 +75,95 @@ impl<'inp> Parser<'inp> {
 
         // dbg!("LHS:", &lhs);
         loop {
-            if self.lexer.peek().is_none()
+            if self.tokens.peek().is_none()
                 || self.is_peek_token_type(Token::Semi)
                 || self.is_peek_token_type(Token::CloseBrace)
                 || self.is_peek_token_type(Token::CloseParen)
+                || self.is_peek_token_type(Token::CloseBracket)
+                // to deal with slice bounds e.g xs[i + 1..]
+                || self.is_peek_token_type(Token::DotDot)
+                || self.is_peek_token_type(Token::DotDotEq)
                 // to deal with if and bracket e.g if { .. } else { .. } when it reaches last bracket
                 || self.is_peek_token_type(Token::OpenBrace)
                 // to deal with comma in func call e.g print(2,3);
                 || self.is_peek_token_type(Token::Comma)
+                // to deal with the key of a map literal e.g #{k: v}
+                || self.is_peek_token_type(Token::Colon)
+                // to deal with the guard of a match arm e.g Some(n) if n > 0 => n
+                || (self.is_guard && self.is_peek_token_type(Token::FatArrow))
             {
                 break;
             }
 
+            // xs[i] - a '[' after a block starts the next statement instead
+            if self.is_peek_token_type(Token::OpenBracket)
+                && !matches!(self.prev_tok, Some(Token::CloseBrace))
+            {
+                if Parser::get_index_bp() < min_bp {
+                    break;
+                }
+
+                lhs = self.parse_index(lhs)?;
+                continue;
+            }
+
+            // t.0
+            if self.is_peek_tuple_index() {
+                if Parser::get_index_bp() < min_bp {
+                    break;
+                }
+
+                lhs = self.parse_tuple_index(lhs)?;
+                continue;
+            }
+
+            // p.x
+            if self.is_peek_token_type(Token::Dot) {
+                if Parser::get_index_bp() < min_bp {
+                    break;
+                }
+
+                lhs = self.parse_field(lhs)?;
+                continue;
+            }
+
+            // x |> f(y) is sugar for f(x, y)
+            if self.is_peek_token_type(Token::Pipeline) {
+                let (l_bp, r_bp) = Parser::get_pipeline_bp();
+                if l_bp < min_bp {
+                    break;
+                }
+
+                self.advance();
+                self.advance();
+                let rhs = self.parse_expr(r_bp)?.to_expr()?;
+                lhs = ExprStmt(Parser::pipe_into(lhs.to_expr()?, rhs)?);
+                continue;
+            }
+
+            // opt ?? default
+            if self.is_peek_token_type(Token::Coalesce) {
+                let (l_bp, r_bp) = Parser::get_coalesce_bp();
+                if l_bp < min_bp {
+                    break;
+                }
+
+                self.advance();
+                self.advance();
+                let rhs = self.parse_expr(r_bp)?.to_expr()?;
+                lhs = ExprStmt(Expr::CoalesceExpr(Box::new(lhs.to_expr()?), Box::new(rhs)));
+                continue;
+            }
+
             let tok = self
-                .lexer
+                .tokens
                 .peek()
                 .expect("Should have token")
-                .clone()
+                .as_ref()
                 .expect("Lexer should not fail");
 
             // dbg!("Prev_tok before from_token:", &self.prev_tok);
-            let binop = BinOpType::from_token(&tok);
+            let binop = BinOpType::from_token(tok);
 
             if let (&Some(Token::CloseBrace), &Err(_)) = (&self.prev_tok, &binop) {
                 break;
@@ -85,9 +174,10 @@ impl<'inp> Parser<'inp> {
             let (l_bp, r_bp) = Parser::get_infix_bp(&binop);
             // comparison ops have no associativity (this is how Rust works) so left/right prec are same
             if l_bp == min_bp {
-                return Err(ParseError::new(
-                    "Comparison operators can't be chained. Use parentheses to disambiguate.",
-                ));
+                return Err(ParseError::new(message!(
+                    P007,
+                    "Comparison operators can't be chained. Use parentheses to disambiguate."
+                )));
             }
             // self.advance();
             if l_bp < min_bp {
@@ -113,6 +203,26 @@ impl<'inp> Parser<'inp> {
 
         Ok(lhs)
     }
+
+    // Desugar arg |> stage: a bare function name is called with arg, a call gets arg as its first argument
+    fn pipe_into(arg: Expr, stage: Expr) -> Result<Expr, ParseError> {
+        match stage {
+            Expr::Symbol(name) => Ok(Expr::FnCallExpr(FnCallData {
+                name,
+                args: vec![arg],
+            })),
+            Expr::FnCallExpr(mut fn_call) => {
+                fn_call.args.insert(0, arg);
+                Ok(Expr::FnCallExpr(fn_call))
+            }
+            _ => Err(ParseError::new(message!(
+                P001,
+                "Expected a function or function call after '{}' but got '{}'",
+                Token::Pipeline,
+                stage
+            ))),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -134,6 +244,11 @@ mod tests {
         test_parse("2-3+4/5*6-8+9; 2+2;", "((((2-3)+((4/5)*6))-8)+9);(2+2);");
 
         test_parse("let x = 2+3*4-5; 300", "let x = ((2+(3*4))-5);300");
+
+        // % binds like * and /
+        test_parse("7 % 3", "(7%3)");
+        test_parse("1+7%3*2", "(1+((7%3)*2))");
+        test_parse("-7 % 3 == 1", "(((-7)%3)==1)");
     }
 
     #[test]
@@ -237,4 +352,41 @@ mod tests {
         // can override
         test_parse("!(x && y) || !z == false", "((!(x&&y))||((!z)==false))");
     }
+
+    #[test]
+    fn test_parse_pipeline() {
+        test_parse("x |> f", "f(x)");
+        test_parse("x |> f |> g(2)", "g(f(x),2)");
+        test_parse("x |> f(y |> g)", "f(x,g(y))");
+        test_parse("let y = 2 * 3 + 1 |> f(); y", "let y = f(((2*3)+1));y");
+        test_parse("a || b |> f", "f((a||b))");
+        test_parse("xs[0] |> f", "f(xs[0])");
+        test_parse("print(2 |> f, 3)", "print(f(2),3)");
+
+        test_parse_err(
+            "x |> f + 1",
+            "Expected a function or function call after '|>' but got '(f+1)'",
+            true,
+        );
+        test_parse_err(
+            "x |> 2",
+            "Expected a function or function call after '|>' but got '2'",
+            true,
+        );
+    }
+
+    #[test]
+    fn test_parse_coalesce() {
+        test_parse("x ?? 0", "(x??0)");
+        // right associative, looser than every binop and |>
+        test_parse("a ?? b ?? 0", "(a??(b??0))");
+        test_parse("a ?? b || c && d", "(a??(b||(c&&d)))");
+        test_parse("a || b ?? c", "((a||b)??c)");
+        test_parse("f(x) ?? 1 + 2 * 3", "(f(x)??(1+(2*3)))");
+        test_parse("x |> f ?? 0", "(f(x)??0)");
+        test_parse("m ?? x |> f", "(m??f(x))");
+        test_parse("let y = xs[0] ?? { 2 }; y", "let y = (xs[0]??{ 2 });y");
+
+        test_parse_err("?? x", "Unexpected token: '??'", true);
+    }
 }
@@ -6,11 +6,12 @@ use crate::FnParam;
 use crate::ParseError;
 use crate::Parser;
 use crate::Type;
+use diagnostics::message;
 use lexer::Token;
 
 // FnDecl is only statement, not expression
 // return stmt is only allowed inside a function
-impl<'inp> Parser<'inp> {
+impl Parser {
     pub(crate) fn parse_fn_decl(&mut self) -> Result<Decl, ParseError> {
         let prev_is_loop = self.is_loop;
         let prev_is_fn = self.is_fn;
@@ -28,29 +29,25 @@ impl<'inp> Parser<'inp> {
 
     pub(crate) fn parse_fn_decl_inner(&mut self) -> Result<Decl, ParseError> {
         // Get name
-        crate::expect_token_body!(self.lexer.peek(), Ident, "identifier")?;
-        let fn_name = Parser::string_from_ident(self.lexer.peek());
+        crate::expect_token_body!(self.tokens.peek(), Ident, "identifier")?;
+        let fn_name = Parser::string_from_ident(self.tokens.peek());
         self.advance();
 
-        self.consume_token_type(
-            Token::OpenParen,
-            &format!("Expected {} for function parameters", Token::OpenBrace),
-        )?;
+        self.consume_token_type(Token::OpenParen, "Expected { for function parameters")?;
 
         let mut params: Vec<FnParam> = vec![];
         // to prevent duplicate params e.g f(x,x). HashSet doesn't preserve order so I need a separate one
         let mut seen_ident: HashSet<String> = HashSet::new();
 
         // Parse params
-        while let Some(tok) = self.lexer.peek() {
-            let tok = tok.clone();
+        while self.tokens.peek().is_some() {
             // stop at )
-            if tok.clone().unwrap().eq(&Token::CloseParen) {
+            if self.is_peek_token_type(Token::CloseParen) {
                 break;
             }
 
             // Invariant: at start peek is a param identifier
-            let param_name = Parser::string_from_ident(self.lexer.peek());
+            let param_name = Parser::string_from_ident(self.tokens.peek());
             let mut param_ty: Option<Type> = None;
 
             self.advance(); // go past ident
@@ -66,7 +63,7 @@ impl<'inp> Parser<'inp> {
             }
 
             // Comma or CloseParen
-            if !self.lexer.peek().eq(&Some(&Ok(Token::CloseParen))) {
+            if !self.tokens.peek().eq(&Some(&Ok(Token::CloseParen))) {
                 self.consume_token_type(
                     Token::Comma,
                     "Expected ',' to separate function parameters",
@@ -74,11 +71,13 @@ impl<'inp> Parser<'inp> {
             }
 
             if seen_ident.contains(&param_name) {
-                let e = format!(
+                let e = message!(
+                    P010,
                     "Parameter '{}' bound more than once for function {}",
-                    param_name, fn_name
+                    param_name,
+                    fn_name
                 );
-                return Err(ParseError::new(&e));
+                return Err(ParseError::new(e));
             }
 
             seen_ident.insert(param_name.clone());
@@ -91,23 +90,20 @@ impl<'inp> Parser<'inp> {
 
         self.advance(); // skip past close paren, peek is at OpenBrace or ret type first token
 
-        let mut ret_ty = Type::Unit;
+        let mut ret_ty = None;
 
         // Parse return type: expect -> first
-        // if its there parse ret type, else keep it as Unit
+        // if its there parse ret type, else leave it to the type checker to infer
         if self.consume_opt_token_type(Token::FnDeclReturn) {
             // peek is now at type_ann first token
             let ret_ty_ann = self.parse_type_annotation()?;
             // self.advance(); // go past last token of ty_ann
 
-            ret_ty = ret_ty_ann;
+            ret_ty = Some(ret_ty_ann);
         }
 
         // Parse body
-        self.consume_token_type(
-            Token::OpenBrace,
-            &format!("Expected {} for function body", Token::OpenBrace),
-        )?;
+        self.consume_token_type(Token::OpenBrace, "Expected { for function body")?;
 
         let body = self.parse_blk()?.to_block()?;
 
@@ -116,6 +112,8 @@ impl<'inp> Parser<'inp> {
             name: fn_name,
             ret_type: ret_ty,
             body,
+            doc: None,
+            attrs: vec![],
         };
 
         Ok(Decl::FnDeclStmt(fn_decl))
@@ -150,6 +148,21 @@ mod tests {
         assert_eq!(res.symbols, vec!["x".to_string(), "f".to_string()])
     }
 
+    #[test]
+    fn test_parse_syms_order() {
+        // declaration order, a shadowed name keeps its first position
+        let t = r"
+        let z = 1;
+        fn b() {}
+        let a = 2;
+        let z = 3;
+        fn b() {}
+        let m = 4;
+        ";
+        let res = Parser::new(Token::lexer(t)).parse().expect("Should parse");
+        assert_eq!(res.symbols, vec!["z", "b", "a", "m"]);
+    }
+
     #[test]
     fn test_parse_fn_decl_basic() {
         let t = r"
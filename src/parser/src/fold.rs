@@ -0,0 +1,290 @@
+use std::rc::Rc;
+
+use crate::{
+    BlockSeq, Decl, Expr, FnCallData, ForData, ForIter, IfElseData, LoopData, MatchArm, ParseError,
+    Pattern, SpawnData,
+};
+
+/// A pass that rewrites a parsed program. Each method defaults to rebuilding the node from its folded children,
+/// so a pass only overrides the nodes it cares about and calls the matching `walk_` fn to carry on into the rest.
+pub(crate) trait Fold: Sized {
+    fn fold_blk(&mut self, blk: BlockSeq) -> Result<BlockSeq, ParseError> {
+        walk_blk(self, blk)
+    }
+
+    fn fold_decl(&mut self, decl: Decl) -> Result<Decl, ParseError> {
+        walk_decl(self, decl)
+    }
+
+    fn fold_expr(&mut self, expr: Expr) -> Result<Expr, ParseError> {
+        walk_expr(self, expr)
+    }
+
+    /// Names that are not symbol expressions: the targets of let and assignment, loop vars, fn names and params,
+    /// the binds of match arms, the names called and the symbols declared in a block.
+    fn fold_name(&mut self, name: String) -> Result<String, ParseError> {
+        Ok(name)
+    }
+}
+
+pub(crate) fn walk_blk<F: Fold>(f: &mut F, blk: BlockSeq) -> Result<BlockSeq, ParseError> {
+    let decls = blk
+        .decls
+        .into_iter()
+        .map(|decl| f.fold_decl(decl))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let last_expr = match blk.last_expr {
+        Some(expr) => Some(Rc::new(f.fold_expr(Rc::unwrap_or_clone(expr))?)),
+        None => None,
+    };
+
+    let symbols = blk
+        .symbols
+        .into_iter()
+        .map(|sym| f.fold_name(sym))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(BlockSeq {
+        decls,
+        last_expr,
+        symbols,
+        spans: blk.spans,
+    })
+}
+
+pub(crate) fn walk_decl<F: Fold>(f: &mut F, decl: Decl) -> Result<Decl, ParseError> {
+    let decl = match decl {
+        Decl::LetStmt(mut stmt) => {
+            stmt.ident = f.fold_name(stmt.ident)?;
+            stmt.expr = f.fold_expr(stmt.expr)?;
+            Decl::LetStmt(stmt)
+        }
+        Decl::LetTupleStmt(mut stmt) => {
+            stmt.idents = stmt
+                .idents
+                .into_iter()
+                .map(|ident| f.fold_name(ident))
+                .collect::<Result<Vec<_>, _>>()?;
+            stmt.expr = f.fold_expr(stmt.expr)?;
+            Decl::LetTupleStmt(stmt)
+        }
+        Decl::AssignStmt(mut stmt) => {
+            stmt.ident = f.fold_name(stmt.ident)?;
+            stmt.expr = f.fold_expr(stmt.expr)?;
+            Decl::AssignStmt(stmt)
+        }
+        Decl::IndexAssignStmt(mut stmt) => {
+            stmt.arr = f.fold_expr(stmt.arr)?;
+            stmt.index = f.fold_expr(stmt.index)?;
+            stmt.expr = f.fold_expr(stmt.expr)?;
+            Decl::IndexAssignStmt(stmt)
+        }
+        Decl::FieldAssignStmt(mut stmt) => {
+            stmt.obj = f.fold_expr(stmt.obj)?;
+            stmt.expr = f.fold_expr(stmt.expr)?;
+            Decl::FieldAssignStmt(stmt)
+        }
+        Decl::ExprStmt(expr) => Decl::ExprStmt(f.fold_expr(expr)?),
+        Decl::IfOnlyStmt(if_else) => Decl::IfOnlyStmt(walk_if_else(f, if_else)?),
+        Decl::LoopStmt(lp) => Decl::LoopStmt(LoopData {
+            cond: lp.cond.map(|cond| f.fold_expr(cond)).transpose()?,
+            body: f.fold_blk(lp.body)?,
+        }),
+        Decl::ForStmt(lp) => {
+            let iter = match lp.iter {
+                ForIter::Range {
+                    start,
+                    end,
+                    inclusive,
+                } => ForIter::Range {
+                    start: f.fold_expr(start)?,
+                    end: f.fold_expr(end)?,
+                    inclusive,
+                },
+                ForIter::Elems(expr) => ForIter::Elems(f.fold_expr(expr)?),
+            };
+
+            Decl::ForStmt(ForData {
+                var: f.fold_name(lp.var)?,
+                iter,
+                body: f.fold_blk(lp.body)?,
+            })
+        }
+        Decl::FnDeclStmt(mut fn_decl) => {
+            fn_decl.name = f.fold_name(fn_decl.name)?;
+            for param in fn_decl.params.iter_mut() {
+                param.name = f.fold_name(std::mem::take(&mut param.name))?;
+            }
+            fn_decl.body = f.fold_blk(fn_decl.body)?;
+            Decl::FnDeclStmt(fn_decl)
+        }
+        Decl::ReturnStmt(expr) => Decl::ReturnStmt(expr.map(|expr| f.fold_expr(expr)).transpose()?),
+        Decl::WaitStmt(sem) => Decl::WaitStmt(f.fold_name(sem)?),
+        Decl::PostStmt(sem) => Decl::PostStmt(f.fold_name(sem)?),
+        Decl::StructDeclStmt(_)
+        | Decl::EnumDeclStmt(_)
+        | Decl::MacroDeclStmt(_)
+        | Decl::BreakStmt
+        | Decl::YieldStmt => decl,
+    };
+
+    Ok(decl)
+}
+
+pub(crate) fn walk_if_else<F: Fold>(
+    f: &mut F,
+    if_else: IfElseData,
+) -> Result<IfElseData, ParseError> {
+    Ok(IfElseData {
+        cond: f.fold_expr(if_else.cond)?,
+        if_blk: f.fold_blk(if_else.if_blk)?,
+        else_blk: if_else.else_blk.map(|blk| f.fold_blk(blk)).transpose()?,
+    })
+}
+
+pub(crate) fn walk_fn_call<F: Fold>(
+    f: &mut F,
+    fn_call: FnCallData,
+) -> Result<FnCallData, ParseError> {
+    let args = fn_call
+        .args
+        .into_iter()
+        .map(|arg| f.fold_expr(arg))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(FnCallData {
+        name: f.fold_name(fn_call.name)?,
+        args,
+    })
+}
+
+pub(crate) fn walk_expr<F: Fold>(f: &mut F, expr: Expr) -> Result<Expr, ParseError> {
+    let expr = match expr {
+        Expr::FnCallExpr(fn_call) => Expr::FnCallExpr(walk_fn_call(f, fn_call)?),
+        Expr::SpawnExpr(data) => Expr::SpawnExpr(SpawnData {
+            call: walk_fn_call(f, data.call)?,
+            isolate: data.isolate,
+        }),
+        // the macro name is not a name in the program, only the args are
+        Expr::MacroCallExpr(mut call) => {
+            call.args = call
+                .args
+                .into_iter()
+                .map(|arg| f.fold_expr(arg))
+                .collect::<Result<Vec<_>, _>>()?;
+            Expr::MacroCallExpr(call)
+        }
+        Expr::JoinExpr(tid) => Expr::JoinExpr(f.fold_name(tid)?),
+        Expr::TryJoinExpr(mut data) => {
+            data.tid = f.fold_name(data.tid)?;
+            data.timeout = data.timeout.map(|ms| f.fold_expr(ms)).transpose()?;
+            Expr::TryJoinExpr(data)
+        }
+        Expr::UnOpExpr(op, expr) => Expr::UnOpExpr(op, Box::new(f.fold_expr(*expr)?)),
+        Expr::BinOpExpr(op, lhs, rhs) => Expr::BinOpExpr(
+            op,
+            Box::new(f.fold_expr(*lhs)?),
+            Box::new(f.fold_expr(*rhs)?),
+        ),
+        Expr::CoalesceExpr(opt, default) => Expr::CoalesceExpr(
+            Box::new(f.fold_expr(*opt)?),
+            Box::new(f.fold_expr(*default)?),
+        ),
+        Expr::BlockExpr(blk) => Expr::BlockExpr(f.fold_blk(blk)?),
+        Expr::IfElseExpr(if_else) => Expr::IfElseExpr(Box::new(walk_if_else(f, *if_else)?)),
+        Expr::ArrayExpr(elems) => Expr::ArrayExpr(
+            elems
+                .into_iter()
+                .map(|elem| f.fold_expr(elem))
+                .collect::<Result<Vec<_>, _>>()?,
+        ),
+        Expr::TupleExpr(elems) => Expr::TupleExpr(
+            elems
+                .into_iter()
+                .map(|elem| f.fold_expr(elem))
+                .collect::<Result<Vec<_>, _>>()?,
+        ),
+        Expr::TupleIndexExpr(t, idx) => Expr::TupleIndexExpr(Box::new(f.fold_expr(*t)?), idx),
+        Expr::MapExpr(entries) => Expr::MapExpr(
+            entries
+                .into_iter()
+                .map(|(key, val)| Ok((f.fold_expr(key)?, f.fold_expr(val)?)))
+                .collect::<Result<Vec<_>, _>>()?,
+        ),
+        Expr::ArrayFillExpr(val, len) => Expr::ArrayFillExpr(Box::new(f.fold_expr(*val)?), len),
+        Expr::IndexExpr(arr, index) => {
+            Expr::IndexExpr(Box::new(f.fold_expr(*arr)?), Box::new(f.fold_expr(*index)?))
+        }
+        Expr::SliceExpr(mut slice) => {
+            slice.arr = f.fold_expr(slice.arr)?;
+            slice.start = slice.start.map(|x| f.fold_expr(x)).transpose()?;
+            slice.end = slice.end.map(|x| f.fold_expr(x)).transpose()?;
+            Expr::SliceExpr(slice)
+        }
+        Expr::StructExpr(mut data) => {
+            data.fields = data
+                .fields
+                .into_iter()
+                .map(|(field, expr)| Ok((field, f.fold_expr(expr)?)))
+                .collect::<Result<Vec<_>, ParseError>>()?;
+            Expr::StructExpr(data)
+        }
+        Expr::FieldAccessExpr(obj, field) => {
+            Expr::FieldAccessExpr(Box::new(f.fold_expr(*obj)?), field)
+        }
+        Expr::MatchExpr(mut data) => {
+            data.subject = f.fold_expr(data.subject)?;
+            data.arms = data
+                .arms
+                .into_iter()
+                .map(|arm| {
+                    let pat = match arm.pat {
+                        Pattern::Variant(name, binds) => Pattern::Variant(
+                            name,
+                            binds
+                                .into_iter()
+                                .map(|bind| f.fold_name(bind))
+                                .collect::<Result<Vec<_>, _>>()?,
+                        ),
+                        Pattern::Struct { name, fields, rest } => Pattern::Struct {
+                            name,
+                            fields: fields
+                                .into_iter()
+                                .map(|(field, bind)| Ok((field, f.fold_name(bind)?)))
+                                .collect::<Result<Vec<_>, ParseError>>()?,
+                            rest,
+                        },
+                        pat => pat,
+                    };
+                    Ok(MatchArm {
+                        pat,
+                        guard: arm.guard.map(|guard| f.fold_expr(guard)).transpose()?,
+                        body: f.fold_expr(arm.body)?,
+                    })
+                })
+                .collect::<Result<Vec<_>, ParseError>>()?;
+            Expr::MatchExpr(data)
+        }
+        Expr::LambdaExpr(mut data) => {
+            for param in data.params.iter_mut() {
+                param.name = f.fold_name(std::mem::take(&mut param.name))?;
+            }
+            data.body = f.fold_expr(data.body)?;
+            Expr::LambdaExpr(data)
+        }
+        Expr::LockExpr(mut data) => {
+            data.mutex = f.fold_expr(data.mutex)?;
+            data.body = f.fold_blk(data.body)?;
+            Expr::LockExpr(data)
+        }
+        Expr::Symbol(_)
+        | Expr::Integer(_)
+        | Expr::Float(_)
+        | Expr::Bool(_)
+        | Expr::StringLiteral(_)
+        | Expr::Char(_) => expr,
+    };
+
+    Ok(expr)
+}
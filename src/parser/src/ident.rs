@@ -6,12 +6,14 @@ use crate::ParseError;
 use crate::Parser;
 use lexer::Token;
 
-impl<'inp> Parser<'inp> {
+const FORMAT: &str = "format";
+
+impl Parser {
     pub fn parse_ident(&mut self, ident: String, min_bp: u8) -> Result<Decl, ParseError> {
         let sym = Expr::Symbol(ident.to_string());
 
         // Handle assignment, fn call
-        if let Some(tok) = self.lexer.peek() {
+        if let Some(tok) = self.tokens.peek() {
             let tok = tok.as_ref().expect("Lexer should not fail");
 
             // Assignment x = 2
@@ -25,50 +27,125 @@ impl<'inp> Parser<'inp> {
                 let assign = AssignStmtData { ident, expr };
 
                 return Ok(Decl::AssignStmt(assign));
+            } else if self.is_struct_expr_start() {
+                return self.parse_struct_expr(ident);
+            } else if self.is_macro_call_start() {
+                return self.parse_macro_call(ident);
             } else if tok.eq(&Token::OpenParen) {
                 // Fn call
-                self.consume_token_type(Token::OpenParen, "Expected '('")?;
-                // dbg!("tok after:", &self.lexer.peek());
+                let mut args = self.parse_call_args()?;
+                if ident == FORMAT {
+                    args = capture_format_args(args);
+                }
+                let data = FnCallData { name: ident, args };
+                return Ok(Decl::ExprStmt(Expr::FnCallExpr(data)));
+            }
+        }
 
-                let mut args: Vec<Expr> = vec![];
+        Ok(Decl::ExprStmt(sym))
+    }
 
-                while let Some(tok) = self.lexer.peek() {
-                    let tok = tok.clone();
-                    // stop at )
-                    if tok.clone().unwrap().eq(&Token::CloseParen) {
-                        break;
-                    }
+    // name!( starts a macro call. Needs the paren since ! alone is logical not
+    fn is_macro_call_start(&self) -> bool {
+        matches!(
+            (self.tokens.peek(), self.tokens.peek_nth(1)),
+            (Some(Ok(Token::Bang)), Some(Ok(Token::OpenParen)))
+        )
+    }
 
-                    self.advance(); // put next tok into prev_tok so parse_expr can use it
+    // (arg1, arg2, ...) for fn and macro calls
+    // Invariant: peek is the opening paren
+    pub(crate) fn parse_call_args(&mut self) -> Result<Vec<Expr>, ParseError> {
+        self.consume_token_type(Token::OpenParen, "Expected '('")?;
 
-                    // let expr = self.parse_expr(min_bp)?.to_expr()?;
-                    // need to reset min_bp when parsing each expr, shouldnt depend on prev
-                    let expr = self.parse_expr(0)?.to_expr()?;
+        let mut args: Vec<Expr> = vec![];
 
-                    // dbg!("Peek after parsing:", &self.lexer.peek(), &expr);
+        while self.tokens.peek().is_some() {
+            // stop at )
+            if self.is_peek_token_type(Token::CloseParen) {
+                break;
+            }
 
-                    args.push(expr);
+            self.advance(); // put next tok into prev_tok so parse_expr can use it
 
-                    if !self.lexer.peek().eq(&Some(&Ok(Token::CloseParen))) {
-                        self.consume_token_type(
-                            Token::Comma,
-                            "Expected ',' to separate function arguments",
-                        )?;
-                    }
-                }
+            // need to reset min_bp when parsing each expr, shouldnt depend on prev
+            let expr = self.parse_expr(0)?.to_expr()?;
+            args.push(expr);
 
-                self.consume_token_type(Token::CloseParen, "Expected ')'")?;
+            if !self.tokens.peek().eq(&Some(&Ok(Token::CloseParen))) {
+                self.consume_token_type(
+                    Token::Comma,
+                    "Expected ',' to separate function arguments",
+                )?;
+            }
+        }
 
-                let data = FnCallData { name: ident, args };
+        self.consume_token_type(Token::CloseParen, "Expected ')'")?;
+
+        Ok(args)
+    }
+}
+
+// format("x = {x}") is format("x = {}", x): a name in braces in a literal format string is taken as an argument,
+// in order with the {} placeholders, like the captured arguments of Rust's format!
+fn capture_format_args(args: Vec<Expr>) -> Vec<Expr> {
+    let Some(Expr::StringLiteral(fmt)) = args.first() else {
+        return args;
+    };
+    let fmt = fmt.clone();
+    let mut args = args.into_iter().skip(1);
+
+    let mut out = String::with_capacity(fmt.len());
+    let mut captured = vec![];
+    let mut rest = fmt.as_str();
+
+    while let Some(i) = rest.find(['{', '}']) {
+        out.push_str(&rest[..i]);
+        let tail = &rest[i..];
+
+        if tail.starts_with("{{") || tail.starts_with("}}") {
+            out.push_str(&tail[..2]);
+            rest = &tail[2..];
+            continue;
+        }
 
-                let fn_call = Expr::FnCallExpr(data);
+        let name = tail
+            .strip_prefix('{')
+            .and_then(|tail| tail.split_once('}'))
+            .filter(|(name, _)| is_ident(name));
 
-                return Ok(Decl::ExprStmt(fn_call));
+        match name {
+            Some((name, after)) => {
+                out.push_str("{}");
+                captured.push(Expr::Symbol(name.to_string()));
+                rest = after;
+            }
+            None => {
+                // {} takes the next argument, so it stays in order with the captured names
+                if tail.starts_with("{}") {
+                    captured.extend(args.next());
+                }
+                let len = if tail.starts_with("{}") { 2 } else { 1 };
+                out.push_str(&tail[..len]);
+                rest = &tail[len..];
             }
         }
-
-        Ok(Decl::ExprStmt(sym))
     }
+    out.push_str(rest);
+
+    // args left over are kept, so the type checker reports that there are too many
+    let mut new_args = vec![Expr::StringLiteral(out)];
+    new_args.extend(captured);
+    new_args.extend(args);
+    new_args
+}
+
+fn is_ident(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
 }
 
 #[cfg(test)]
@@ -139,6 +216,19 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_format_capture() {
+        test_parse(r#"format("x = {x}");"#, r#"format("x = {}",x);"#);
+        test_parse(
+            r#"format("{a} {} {b} {}", 1, 2);"#,
+            r#"format("{} {} {} {}",a,1,b,2);"#,
+        );
+        // escaped braces and ones around something that isn't a name are left alone
+        test_parse(r#"format("{{x}} {1x}");"#, r#"format("{{x}} {1x}");"#);
+        // only a literal format string is looked into
+        test_parse("format(s, x);", "format(s,x);");
+    }
+
     #[test]
     fn test_parse_fn_call_err() {
         test_parse_err("print(", "Expected ')'", true);
@@ -8,8 +8,12 @@ use crate::Parser;
 // use crate::{BinOpType, UnOpType};
 use lexer::Token;
 
-impl<'inp> Parser<'inp> {
+impl Parser {
     pub(crate) fn parse_if_else(&mut self, min_bp: u8) -> Result<Decl, ParseError> {
+        if self.is_peek_token_type(Token::Let) {
+            return self.parse_if_let();
+        }
+
         // condition - in parens
         // self.consume_token_type(Token::OpenParen, "Expected open parenthesis")?;
 
@@ -21,10 +25,7 @@ impl<'inp> Parser<'inp> {
         let cond = self.parse_expr(min_bp)?.to_expr()?;
 
         // go past OpenBrace, put in prev_tok
-        self.consume_token_type(
-            Token::OpenBrace,
-            &format!("Expected {} for if block", Token::OpenBrace),
-        )?;
+        self.consume_token_type(Token::OpenBrace, "Expected { for if block")?;
 
         let if_blk = self.parse_blk()?.to_block()?;
 
@@ -33,10 +34,7 @@ impl<'inp> Parser<'inp> {
 
         if self.expect_token_type(Token::Else, "").is_ok() {
             self.consume_token_type(Token::Else, "Expected 'else' for if")?;
-            self.consume_token_type(
-                Token::OpenBrace,
-                &format!("Expected {} for else block", Token::OpenBrace),
-            )?;
+            self.consume_token_type(Token::OpenBrace, "Expected { for else block")?;
 
             let blk = self.parse_blk()?.to_block()?;
 
@@ -59,6 +57,30 @@ impl<'inp> Parser<'inp> {
             Ok(Decl::IfOnlyStmt(stmt))
         }
     }
+
+    // if let Some(x) = opt { a } else { b } is sugar for match opt { Some(x) => { a }, _ => { b } }, with an empty
+    // block for b if there is no else
+    // Invariant: prev_tok is if and peek is let
+    fn parse_if_let(&mut self) -> Result<Decl, ParseError> {
+        let (pat, subject) = self.parse_let_pattern("if")?;
+
+        self.consume_token_type(Token::OpenBrace, "Expected { for if let block")?;
+        let if_blk = self.parse_blk()?.to_block()?;
+
+        let mut else_blk = BlockSeq {
+            decls: vec![],
+            last_expr: None,
+            symbols: vec![],
+            spans: vec![],
+        };
+        if self.consume_opt_token_type(Token::Else) {
+            self.consume_token_type(Token::OpenBrace, "Expected { for else block")?;
+            else_blk = self.parse_blk()?.to_block()?;
+        }
+
+        let expr = Parser::let_match(pat, subject, if_blk, else_blk);
+        Ok(Decl::ExprStmt(expr))
+    }
 }
 
 #[cfg(test)]
@@ -269,4 +291,30 @@ mod tests {
         ";
         test_parse(t, "let x = { if false { 20; };if true { 2 } else { 3 } };");
     }
+
+    #[test]
+    fn test_parse_if_let() {
+        let t = r"
+        if let Some(x) = map_get(m, k) {
+            x
+        } else {
+            0
+        }
+        ";
+        test_parse(t, "match map_get(m,k) { Some(x) => { x }, _ => { 0 } }");
+
+        // without else the other arm is an empty block, and it is a statement like if
+        let t = r"
+        if let 1 | 2 = x { println(1); }
+        x
+        ";
+        test_parse(t, "match x { 1 | 2 => { println(1); }, _ => {  } };x");
+
+        test_parse_err("if let Some(x) = y", "Expected { for if let block", true);
+        test_parse_err(
+            "if let Some(x) == y {}",
+            "Expected '=' after pattern 'Some(x)' of if let",
+            true,
+        );
+    }
 }
@@ -6,12 +6,16 @@ use crate::Parser;
 use crate::Type;
 use lexer::Token;
 
-impl<'inp> Parser<'inp> {
+impl Parser {
     // Parse let statement
     // let x = 2;
     pub(crate) fn parse_let(&mut self) -> Result<Decl, ParseError> {
-        crate::expect_token_body!(self.lexer.peek(), Ident, "identifier")?;
-        let ident = Parser::string_from_ident(self.lexer.peek());
+        if self.is_peek_token_type(Token::OpenParen) {
+            return self.parse_let_tuple();
+        }
+
+        crate::expect_token_body!(self.tokens.peek(), Ident, "identifier")?;
+        let ident = Parser::string_from_ident(self.tokens.peek());
         self.advance();
 
         let mut type_ann: Option<Type> = None;
@@ -41,6 +45,8 @@ impl<'inp> Parser<'inp> {
             ident,
             expr,
             type_ann,
+            doc: None,
+            attrs: vec![],
         };
 
         Ok(LetStmt(stmt))
@@ -107,8 +113,10 @@ pub mod tests {
         test_parse("let x : bool = 2.3;", "let x : bool = 2.3;");
         test_parse("let x : float = 5;", "let x : float = 5;");
 
+        // other names are struct types, resolved by the type checker
+        test_parse("let x : u32 = true;", "let x : u32 = true;");
+
         // basic err cases
-        test_parse_err("let x : u32 = true;", "Unknown primitive type", true);
         test_parse_err("let x : = true;", "Expected identifier", true);
     }
 
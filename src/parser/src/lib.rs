@@ -1,86 +1,106 @@
-use lexer::{lex, Token};
+use diagnostics::{message, Message};
+use lexer::{lex, LexError, Token};
 use logos::Lexer;
-use std::iter::Peekable;
 use structs::*;
+use token_buffer::TokenBuffer;
 
+pub mod attribute;
 pub mod blk;
+pub mod cfg;
+pub mod const_eval;
 pub mod expr;
 pub mod fn_decl;
+pub(crate) mod fold;
 pub mod ident;
 pub mod if_else;
 pub mod let_stmt;
+pub mod macros;
+pub mod parse_array;
+pub mod parse_enum;
+pub mod parse_join;
+pub mod parse_lambda;
+pub mod parse_lock;
 pub mod parse_loop;
+pub mod parse_map;
+pub mod parse_match;
+pub mod parse_struct;
+pub mod parse_tuple;
 pub mod parse_type_ann;
 pub mod seq;
 pub mod structs;
+pub mod token_buffer;
 
 // To expect token types that have a value inside (for Ident and primitives)
 macro_rules! expect_token_body {
     ($peek:expr, $token:ident, $expected:expr) => {{
-        let err = Err(ParseError::new(concat!("Expected ", $expected)));
+        let err = Err(ParseError::new(diagnostics::message!(
+            P002,
+            concat!("Expected ", $expected)
+        )));
         let pk = $peek;
 
-        if pk.is_none() {
-            err
-        } else {
-            let pk = pk
-                .expect("Peek has something")
-                .as_ref()
-                .expect("Expect lexer to succeed");
-            match pk {
+        match pk {
+            Some(pk) => match pk.as_ref().expect("Expect lexer to succeed") {
                 Token::$token(_) => Ok(()),
                 _ => err,
-            }
+            },
+            None => err,
         }
     }};
 }
 
 pub(crate) use expect_token_body;
 
-pub struct Parser<'inp> {
+pub struct Parser {
     prev_tok: Option<Token>,
-    lexer: Peekable<Lexer<'inp, Token>>,
+    tokens: TokenBuffer,
     pub is_loop: bool,
     pub is_fn: bool,
+    // inside a macro body, where $x params can be used
+    pub is_macro: bool,
+    // inside the guard of a match arm, which ends at '=>'
+    pub is_guard: bool,
+    // errors from declarations that were skipped so parsing could go on
+    errors: Vec<ParseError>,
 }
 
-impl<'inp> Parser<'inp> {
-    pub fn new(lexer: Lexer<'_, Token>) -> Parser<'_> {
+impl Parser {
+    pub fn new(lexer: Lexer<'_, Token>) -> Parser {
         Parser {
             prev_tok: None,
-            lexer: lexer.peekable(),
+            tokens: TokenBuffer::new(lexer),
             is_loop: false,
             is_fn: false,
+            is_macro: false,
+            is_guard: false,
+            errors: vec![],
         }
     }
 
-    pub fn new_from_string(inp: &str) -> Parser<'_> {
+    pub fn new_from_string(inp: &str) -> Parser {
         Parser {
             prev_tok: None,
-            lexer: lex(inp).peekable(),
+            tokens: TokenBuffer::new(lex(inp)),
             is_loop: false,
             is_fn: false,
+            is_macro: false,
+            is_guard: false,
+            errors: vec![],
         }
     }
 
     // Check if peek is a specific token type
     fn is_peek_token_type(&mut self, token: Token) -> bool {
-        let pk = self.lexer.peek();
-        if pk.is_none() {
-            false
-        } else {
-            let pk = pk.unwrap();
-            match pk {
-                Ok(prev) => prev.eq(&token),
-                _ => false,
-            }
+        match self.tokens.peek() {
+            Some(Ok(prev)) => prev.eq(&token),
+            _ => false,
         }
     }
 
     /// To expect token types at peek that have no value (most of them)
     fn expect_token_type(&mut self, token: Token, expected_msg: &str) -> Result<(), ParseError> {
         if !self.is_peek_token_type(token) {
-            Err(ParseError::new(expected_msg))
+            Err(ParseError::new(message!(P002, expected_msg)))
         } else {
             Ok(())
         }
@@ -89,7 +109,7 @@ impl<'inp> Parser<'inp> {
     /// Expect token type at peek and advance if it was there
     fn consume_token_type(&mut self, token: Token, expected_msg: &str) -> Result<(), ParseError> {
         if !self.is_peek_token_type(token) {
-            Err(ParseError::new(expected_msg))
+            Err(ParseError::new(message!(P002, expected_msg)))
         } else {
             self.advance();
             Ok(())
@@ -107,12 +127,10 @@ impl<'inp> Parser<'inp> {
         }
     }
 
-    // Store current lexer token as prev_tok and move up lexer
+    // Move the next token out of the buffer into prev_tok
     fn advance(&mut self) {
-        if let Some(val) = self.lexer.peek() {
-            self.prev_tok
-                .replace(val.clone().expect("Expect lexer to succeed"));
-            self.lexer.next();
+        if let Some(val) = self.tokens.advance() {
+            self.prev_tok.replace(val.expect("Expect lexer to succeed"));
         }
     }
 
@@ -120,12 +138,12 @@ impl<'inp> Parser<'inp> {
     fn expect_prev_tok(&self) -> Result<&Token, ParseError> {
         match &self.prev_tok {
             Some(tok) => Ok(tok),
-            None => Err(ParseError::new("Expected previous token")),
+            None => Err(ParseError::new(message!(P002, "Expected previous token"))),
         }
     }
 
-    // Pass in self.lexer.peek() => get String out for Ident, String in quotes
-    pub(crate) fn string_from_ident(token: Option<&Result<Token, ()>>) -> String {
+    // Pass in self.tokens.peek() => get String out for Ident, String in quotes
+    pub(crate) fn string_from_ident(token: Option<&Result<Token, LexError>>) -> String {
         // dbg!("string from ident token:", &token);
         let tok = token.unwrap();
         let tok = tok.clone().unwrap();
@@ -133,22 +151,26 @@ impl<'inp> Parser<'inp> {
     }
 
     /// Expect one of Ident, (, or fn to start type annotation
-    fn expect_token_for_type_ann(token: Option<&Result<Token, ()>>) -> Result<(), ParseError> {
+    fn expect_token_for_type_ann(
+        token: Option<&Result<Token, LexError>>,
+    ) -> Result<(), ParseError> {
         if let Some(Ok(tok)) = token {
             match tok {
-                Token::Ident(_) | Token::OpenParen | Token::Fn => Ok(()),
+                Token::Ident(_) | Token::OpenParen | Token::OpenBracket | Token::Fn => Ok(()),
                 _ => {
-                    let e = format!(
+                    let e = message!(
+                        P005,
                         "Expected identifier or '(' for type annotation, got '{}'",
                         tok
                     );
-                    Err(ParseError::new(&e))
+                    Err(ParseError::new(e))
                 }
             }
         } else {
-            Err(ParseError::new(
-                "Expected identifier or '(' for type annotation, got end of input",
-            ))
+            Err(ParseError::new(message!(
+                P005,
+                "Expected identifier or '(' for type annotation, got end of input"
+            )))
         }
     }
     /* Precedence */
@@ -158,22 +180,38 @@ impl<'inp> Parser<'inp> {
     // (left, right) => left < right means left associative. left > right means right associative. equal => no associativity (error)
     fn get_infix_bp(binop: &BinOpType) -> (u8, u8) {
         match binop {
-            BinOpType::Mul | BinOpType::Div => (8, 9),
-            BinOpType::Add | BinOpType::Sub => (6, 7),
+            BinOpType::Mul | BinOpType::Div | BinOpType::Mod => (10, 11),
+            BinOpType::Add | BinOpType::Sub => (8, 9),
             // no associativity for comparison ops
-            BinOpType::LogicalEq | BinOpType::Gt | BinOpType::Lt => (5, 5),
-            BinOpType::LogicalAnd => (3, 4),
-            BinOpType::LogicalOr => (1, 2),
+            BinOpType::LogicalEq | BinOpType::Gt | BinOpType::Lt => (7, 7),
+            BinOpType::LogicalAnd => (5, 6),
+            BinOpType::LogicalOr => (3, 4),
         }
     }
 
+    // Pipeline binds loosest so each stage can be any expression e.g a + b |> f is f(a + b)
+    fn get_pipeline_bp() -> (u8, u8) {
+        (1, 2)
+    }
+
+    // ?? binds loosest of all, even |> so x |> f ?? 0 is f(x) ?? 0, and is right associative so a ?? b ?? 0 is
+    // a ?? (b ?? 0)
+    fn get_coalesce_bp() -> (u8, u8) {
+        (0, 0)
+    }
+
     // Unary negation must have a higher precedence than binops
     fn get_prefix_bp(unop: &UnOpType) -> ((), u8) {
         match unop {
-            UnOpType::Negate | UnOpType::Not => ((), 10),
+            UnOpType::Negate | UnOpType::Not => ((), 12),
         }
     }
 
+    // Indexing and field access bind tighter than unary operators e.g -xs[0] is -(xs[0])
+    fn get_index_bp() -> u8 {
+        13
+    }
+
     // Parses and returns a declaration. At this stage "declaration" includes values, let assignments, fn declarations, etc
     // Because treatment of something as an expression can vary based on whether it is last value or not, whether semicolon comes after, etc.
     fn parse_decl(&mut self) -> Result<Decl, ParseError> {
@@ -184,19 +222,39 @@ impl<'inp> Parser<'inp> {
             | Token::Bool(_)
             | Token::Minus
             | Token::Ident(_)
+            | Token::MacroVar(_)
             | Token::OpenParen
             | Token::Bang
             | Token::OpenBrace
+            | Token::OpenBracket
+            | Token::Pound
             | Token::If
-            | Token::String(_) => self.parse_expr(0),
+            | Token::Match
+            | Token::Lock
+            | Token::TryJoin
+            | Token::JoinTimeout
+            | Token::Or
+            | Token::LogOr
+            | Token::String(_)
+            | Token::Char(_) => self.parse_expr(0),
             Token::Spawn => {
+                // isolate is only a keyword right before the name called, so it can still name a fn
+                let isolate = matches!(self.tokens.peek(), Some(Ok(Token::Ident(name))) if name == "isolate")
+                    && matches!(self.tokens.peek_nth(1), Some(Ok(Token::Ident(_))));
+                if isolate {
+                    self.advance();
+                }
                 self.advance();
+
                 let fn_call = self.parse_expr(0)?.to_expr()?;
-                if let Expr::FnCallExpr(fn_data) = fn_call {
-                    let sp = Expr::SpawnExpr(fn_data);
+                if let Expr::FnCallExpr(call) = fn_call {
+                    let sp = Expr::SpawnExpr(SpawnData { call, isolate });
                     Ok(Decl::ExprStmt(sp))
                 } else {
-                    Err(ParseError::new("spawn expected function call"))
+                    Err(ParseError::new(message!(
+                        P002,
+                        "spawn expected function call"
+                    )))
                 }
             }
             // join t;
@@ -207,7 +265,10 @@ impl<'inp> Parser<'inp> {
                     let j = Expr::JoinExpr(tid);
                     Ok(Decl::ExprStmt(j))
                 } else {
-                    Err(ParseError::new("join expected variable for thread to join"))
+                    Err(ParseError::new(message!(
+                        P002,
+                        "join expected variable for thread to join"
+                    )))
                 }
             }
             // wait sem;
@@ -217,7 +278,10 @@ impl<'inp> Parser<'inp> {
                 if let Expr::Symbol(sem_sym) = sem {
                     Ok(Decl::WaitStmt(sem_sym))
                 } else {
-                    Err(ParseError::new("wait expected semaphore variable"))
+                    Err(ParseError::new(message!(
+                        P002,
+                        "wait expected semaphore variable"
+                    )))
                 }
             }
             Token::Post => {
@@ -226,13 +290,16 @@ impl<'inp> Parser<'inp> {
                 if let Expr::Symbol(sem_sym) = sem {
                     Ok(Decl::PostStmt(sem_sym))
                 } else {
-                    Err(ParseError::new("post expected semaphore variable"))
+                    Err(ParseError::new(message!(
+                        P002,
+                        "post expected semaphore variable"
+                    )))
                 }
             }
             // if not is_loop, error
             Token::Break => {
                 if !self.is_loop {
-                    return Err(ParseError::new("break outside of loop"));
+                    return Err(self.prev_tok_err(message!(P004, "break outside of loop")));
                 }
                 Ok(Decl::BreakStmt)
             }
@@ -240,7 +307,7 @@ impl<'inp> Parser<'inp> {
             // if not is_fn, err
             Token::Return => {
                 if !self.is_fn {
-                    return Err(ParseError::new("return outside of fn"));
+                    return Err(self.prev_tok_err(message!(P004, "return outside of fn")));
                 }
 
                 // parse expr if not semicolon
@@ -255,23 +322,72 @@ impl<'inp> Parser<'inp> {
             }
             Token::Let => self.parse_let(),
             Token::Loop => self.parse_loop(),
+            Token::While => self.parse_while(),
+            Token::For => self.parse_for(),
             Token::Fn => self.parse_fn_decl(),
-            _ => Err(ParseError::new(&format!(
-                "Unexpected token: '{}'",
-                prev_tok
-            ))),
+            Token::Struct => self.parse_struct_decl(),
+            Token::Enum => self.parse_enum_decl(),
+            Token::Macro => self.parse_macro_decl(),
+            _ => Err(self.prev_tok_err(message!(P001, "Unexpected token: '{}'", prev_tok))),
         }
     }
 
     // Implicit block
-    pub fn parse(mut self) -> Result<BlockSeq, ParseError> {
-        self.parse_seq()
+    /// Parse the whole program. If any declaration fails to parse, all of the errors are returned.
+    pub fn parse(mut self) -> Result<BlockSeq, ParseErrors> {
+        // the tokens around one the lexer couldn't make can't be trusted, so don't parse them
+        let lex_errors: Vec<ParseError> = self
+            .tokens
+            .lex_errors()
+            .map(|(err, span)| Parser::lex_error(err).or_at(Some(span)))
+            .collect();
+        if !lex_errors.is_empty() {
+            return Err(ParseErrors(lex_errors));
+        }
+
+        let program = self.parse_seq();
+        let program = match program {
+            Ok(program) if self.errors.is_empty() => program,
+            Ok(_) => return Err(ParseErrors(self.errors)),
+            Err(e) => {
+                self.record_err(e);
+                return Err(ParseErrors(self.errors));
+            }
+        };
+        Ok(macros::expand_macros(program)?)
+    }
+
+    fn lex_error(err: &LexError) -> ParseError {
+        let e = match err {
+            LexError::UnexpectedChar => message!(P011, "Unexpected character"),
+            LexError::InvalidEscape(esc) => message!(
+                P011,
+                r#"Invalid escape '{}', expected one of \n \r \t \0 \\ \" \' or \u{..} with 1 to 6 hex digits"#,
+                esc
+            ),
+            LexError::UnterminatedString => message!(P011, "Unterminated string"),
+            LexError::UnterminatedComment => message!(P011, "Unterminated block comment"),
+        };
+        ParseError::new(e)
+    }
+
+    // An error about the token just consumed. Errors without a span are about the token parsing stopped at, see
+    // record_err
+    pub(crate) fn prev_tok_err(&self, msg: Message) -> ParseError {
+        ParseError::new(msg).or_at(self.tokens.prev_span())
+    }
+
+    // parsing stops at the token it couldn't handle, or after the last one if the input ended early
+    pub(crate) fn record_err(&mut self, e: ParseError) {
+        let span = self.tokens.peek_span().or(self.tokens.prev_span());
+        self.errors.push(e.or_at(span));
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use diagnostics::Code;
     use logos::Logos;
 
     pub fn test_parse(inp: &str, expected: &str) {
@@ -339,6 +455,24 @@ mod tests {
         test_parse_err("20 30", "infix operator", true);
     }
 
+    #[test]
+    fn test_parse_multi_char_token_near_misses() {
+        // Single char operators directly followed by another operator stay separate tokens
+        test_parse("let x=-1; x", "let x = (-1);x");
+        test_parse("let x:int=2; x", "let x : int = 2;x");
+        test_parse("fn f()->int { 2 } f()", "fn f () -> int { 2 };f()");
+        test_parse("let x = 2 --3; x", "let x = (2-(-3));x");
+
+        // Multi char tokens are recognised as a whole, so they don't parse as their prefixes
+        test_parse_err("x => 2", "infix operator but got: =>", true);
+        test_parse_err("x ==> 2", "not an expression: '>'", true);
+        test_parse_err("x::y", "infix operator but got: ::", true);
+        test_parse_err("x :: int", "infix operator but got: ::", true);
+        test_parse_err("1..2", "Expected semicolon", true);
+        test_parse_err("1..=2", "Expected semicolon", true);
+        test_parse_err("let x = ..2;", "Unexpected token: '..'", true);
+    }
+
     #[test]
     fn test_parse_assignment() {
         test_parse_err("x = y = 2", "not an expression", true);
@@ -373,6 +507,13 @@ mod tests {
         ";
         test_parse(t, "let t = spawn func();let res = join t;");
 
+        // isolate is only a keyword right before the fn name
+        let t = r"
+        let t = spawn isolate func(xs);
+        spawn isolate(xs);
+        ";
+        test_parse(t, "let t = spawn isolate func(xs);spawn isolate(xs);");
+
         // wait and post
         let t = r"
         let sem = sem_create();
@@ -418,9 +559,175 @@ mod tests {
     #[test]
     fn test_parse_string() {
         let t = r#""hello" + "world""#;
-        test_parse(t, "(hello+world)");
+        test_parse(t, r#"("hello"+"world")"#);
 
         let t = r#"let t = "hello world"; println(t);"#;
-        test_parse(t, "let t = hello world;println(t);");
+        test_parse(t, r#"let t = "hello world";println(t);"#);
+
+        test_parse(r#""tab\there" + r"\d+""#, r#"("tab\there"+"\\d+")"#);
+    }
+
+    #[test]
+    fn test_parse_lex_errors() {
+        // every token the lexer couldn't make is reported, where it is
+        assert_eq!(
+            parse_errs("let a = \"ok\";\nlet b = `;\nlet c = \"x\\qy\";"),
+            vec![
+                "[ParseError P011]: Unexpected character at line 2, column 9",
+                r#"[ParseError P011]: Invalid escape '\q', expected one of \n \r \t \0 \\ \" \' or \u{..} with 1 to 6 hex digits at line 3, column 9"#,
+            ]
+        );
+        test_parse_err(
+            "let s = r#\"no end\";",
+            "[ParseError P011]: Unterminated string at line 1, column 9",
+            false,
+        );
+        test_parse_err(
+            "let x = 1;\n/* open /* nested */\nlet y = 2;",
+            "[ParseError P011]: Unterminated block comment at line 2, column 1",
+            false,
+        );
+    }
+
+    #[test]
+    fn test_parse_block_comments() {
+        test_parse("1 /* one */ + /* /* two */ */ 2", "(1+2)");
+        test_parse("/* let x = 1; */ let y = 2;", "let y = 2;");
+
+        // errors after a comment over several lines point at the right place
+        let t = "/* a\n   /* b\n */ c\n*/ let x = 2;\nlet y = x /* é */ z;";
+        test_parse_err(
+            t,
+            "[ParseError P001]: Expected infix operator but got: z at line 5, column 19",
+            false,
+        );
+    }
+
+    #[test]
+    fn test_parse_err_location() {
+        // the error is at the token the parser stopped on
+        let t = "let x = 2;\nlet y = x\n  z;";
+        test_parse_err(
+            t,
+            "[ParseError P001]: Expected infix operator but got: z at line 3, column 3",
+            false,
+        );
+
+        // or at the last token when the input ends early
+        test_parse_err("let x = ", "at line 1, column 7", true);
+
+        let err = Parser::new_from_string("fn f(x: int) {\n  x +\n}")
+            .parse()
+            .expect_err("Should err");
+        let span = err.errors()[0].span().expect("Should have span");
+        assert_eq!((span.line, span.col), (3, 1));
+
+        // errors from expanding macros happen after parsing, so have no location
+        let err = Parser::new_from_string("m!(1)")
+            .parse()
+            .expect_err("Should err");
+        assert_eq!(err.errors()[0].span(), None);
+    }
+
+    #[test]
+    fn test_parse_err_codes() {
+        let codes = |inp: &str| {
+            let errs = Parser::new_from_string(inp)
+                .parse()
+                .expect_err("Should err");
+            errs.errors()
+                .iter()
+                .map(|err| err.code())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(codes("let x = ;\nbreak;"), vec![Code::P001, Code::P004]);
+        assert_eq!(codes("let w = (1;"), vec![Code::P002]);
+        assert_eq!(codes("let x = if true { 1 };"), vec![Code::P003]);
+        assert_eq!(codes("1 < 2 < 3"), vec![Code::P007]);
+        assert_eq!(codes("fn f(x: int, x: int) {}"), vec![Code::P010]);
+        assert_eq!(codes("m!(1)"), vec![Code::P008]);
+    }
+
+    fn parse_errs(inp: &str) -> Vec<String> {
+        let errs = Parser::new_from_string(inp)
+            .parse()
+            .expect_err("Should err");
+        errs.errors().iter().map(|err| err.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_err_recovery() {
+        // each bad declaration is skipped and parsing goes on, in nested blocks too
+        let t = "let x = ;\nlet y = 2;\nfn f() {\n  let z = 3 +;\n  z\n}\nlet w = (1;\nprint(y)";
+        assert_eq!(
+            parse_errs(t),
+            vec![
+                "[ParseError P001]: Unexpected token: ';' at line 1, column 9",
+                "[ParseError P001]: Unexpected token - not an expression: ';' at line 4, column 14",
+                "[ParseError P002]: Expected closing parenthesis at line 7, column 11",
+            ]
+        );
+
+        // the error is at the token that was wrong, even when parsing has moved past it
+        assert_eq!(
+            parse_errs("let a = 1;\nlet z = 3 +;\nlet b = 2;"),
+            vec![
+                "[ParseError P001]: Unexpected token - not an expression: ';' at line 2, column 12"
+            ]
+        );
+
+        // a skipped loop doesn't leave break allowed after it
+        assert_eq!(
+            parse_errs("loop { let a = ; }\nbreak;"),
+            vec![
+                "[ParseError P001]: Unexpected token: ';' at line 1, column 16",
+                "[ParseError P004]: break outside of loop at line 2, column 1",
+            ]
+        );
+
+        // a semicolon inside a skipped block doesn't end the skipping
+        assert_eq!(
+            parse_errs("let a = if { 1; 2 } else { 3; 4 };\nlet b = ;"),
+            vec![
+                "[ParseError P002]: Expected { for if block at line 1, column 21",
+                "[ParseError P001]: Unexpected token: ';' at line 2, column 9",
+            ]
+        );
+
+        // all of the errors are shown, one per line
+        test_parse_err(
+            "let x = ;\nlet y = ;",
+            "[ParseError P001]: Unexpected token: ';' at line 1, column 9\n[ParseError P001]: Unexpected token: ';' at line 2, column 9",
+            false,
+        );
+    }
+
+    #[test]
+    fn test_parse_spans() {
+        let t = "let x = 2;\nfn f() {\n  x\n}\n  f()";
+        let program = Parser::new_from_string(t).parse().expect("Should parse");
+
+        let spans: Vec<_> = program
+            .spans
+            .iter()
+            .map(|span| (&t[span.start..span.end], span.line, span.col))
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                ("let x = 2", 1, 1),
+                ("fn f() {\n  x\n}", 2, 1),
+                ("f()", 5, 3)
+            ]
+        );
+        assert_eq!(program.decl_span(1).map(|span| span.line), Some(2));
+        assert_eq!(program.last_expr_span().map(|span| span.line), Some(5));
+
+        // blocks inside keep their own spans
+        let Decl::FnDeclStmt(f) = &program.decls[1] else {
+            panic!("Expected fn decl");
+        };
+        assert_eq!(f.body.last_expr_span().map(|span| span.col), Some(3));
     }
 }
@@ -0,0 +1,472 @@
+use std::collections::{HashMap, HashSet};
+
+use diagnostics::{message, Message};
+use lexer::Token;
+
+use crate::fold::{walk_decl, walk_expr, Fold};
+use crate::{BlockSeq, Decl, Expr, FnCallData, MacroDeclData, ParseError, Parser};
+
+/// How deep macros can expand into other macros, so a macro that calls itself forever is an error.
+pub const MACRO_RECURSION_LIMIT: usize = 64;
+
+impl Parser {
+    // macro swap($a, $b) { let t = $a; $a = $b; $b = t; }
+    // Invariant: prev_tok is macro
+    pub(crate) fn parse_macro_decl(&mut self) -> Result<Decl, ParseError> {
+        let Some(Ok(Token::Ident(name))) = self.tokens.peek() else {
+            return Err(ParseError::new(message!(
+                P008,
+                "Expected macro name after 'macro'"
+            )));
+        };
+        let name = name.to_owned();
+        self.advance();
+
+        self.consume_token_type(Token::OpenParen, "Expected ( for macro parameters")?;
+
+        let mut params: Vec<String> = vec![];
+        while !self.is_peek_token_type(Token::CloseParen) {
+            let param = match self.tokens.peek() {
+                Some(Ok(Token::MacroVar(param))) => param.to_owned(),
+                Some(Ok(tok)) => {
+                    let e = message!(P008, "Expected macro parameter like '$x' but got '{}'", tok);
+                    return Err(ParseError::new(e));
+                }
+                _ => return Err(ParseError::new(message!(P008, "Expected ')'"))),
+            };
+            self.advance();
+
+            if params.contains(&param) {
+                let e = message!(
+                    P010,
+                    "Parameter '{}' bound more than once for macro {}",
+                    param,
+                    name
+                );
+                return Err(ParseError::new(e));
+            }
+            params.push(param);
+
+            if !self.is_peek_token_type(Token::CloseParen) {
+                self.consume_token_type(Token::Comma, "Expected ',' to separate macro parameters")?;
+            }
+        }
+        self.advance(); // go past )
+
+        self.consume_token_type(Token::OpenBrace, "Expected { for macro body")?;
+
+        let prev_is_macro = self.is_macro;
+        self.is_macro = true;
+        let body = self.parse_blk();
+        self.is_macro = prev_is_macro;
+        let body = body?.to_block()?;
+
+        Ok(Decl::MacroDeclStmt(MacroDeclData { name, params, body }))
+    }
+
+    // swap!(x, y)
+    // Invariant: prev_tok is the macro name and peek is the !
+    pub(crate) fn parse_macro_call(&mut self, name: String) -> Result<Decl, ParseError> {
+        self.advance(); // go past !
+        let args = self.parse_call_args()?;
+        Ok(Decl::ExprStmt(Expr::MacroCallExpr(FnCallData {
+            name,
+            args,
+        })))
+    }
+}
+
+/// Replace every macro call with the body of its macro, removing the macro declarations. Runs right after
+/// parsing, so everything after works on programs without macros.
+///
+/// Args are substituted for the params in the body as they are, so an arg used twice is evaluated twice.
+/// Macros are hygienic for the names the body binds, which are renamed on each expansion to names not used
+/// anywhere else in the program so they can't capture or shadow names at the call site.
+pub fn expand_macros(program: BlockSeq) -> Result<BlockSeq, ParseError> {
+    let mut macros: HashMap<String, MacroDeclData> = HashMap::new();
+    let mut decls = vec![];
+
+    for decl in program.decls.into_iter() {
+        match decl {
+            Decl::MacroDeclStmt(data) => {
+                if macros.contains_key(&data.name) {
+                    let e = message!(P008, "Macro '{}' is already declared", data.name);
+                    return Err(ParseError::new(e));
+                }
+                macros.insert(data.name.to_owned(), data);
+            }
+            _ => decls.push(decl),
+        }
+    }
+
+    let program = BlockSeq { decls, ..program };
+
+    let mut names = Names(HashSet::new());
+    names.fold_blk(program.clone())?;
+    for mac in macros.values() {
+        names.fold_blk(mac.body.clone())?;
+    }
+
+    Expander {
+        macros,
+        stack: vec![],
+        expansions: 0,
+        taken: names.0,
+    }
+    .fold_blk(program)
+}
+
+struct Expander {
+    macros: HashMap<String, MacroDeclData>,
+    // names of the macros being expanded, outermost first
+    stack: Vec<String>,
+    // count of expansions so far, to make renamed bindings unique
+    expansions: usize,
+    // every name in the program and the macro bodies, plus the renames handed out so far
+    taken: HashSet<String>,
+}
+
+impl Expander {
+    // where the error happened, since expanded code has no position of its own
+    fn context(&self) -> Option<String> {
+        let calls: Vec<String> = self
+            .stack
+            .iter()
+            .map(|name| format!("'{}!'", name))
+            .collect();
+        if calls.is_empty() {
+            None
+        } else {
+            Some(calls.join(" -> "))
+        }
+    }
+
+    fn error(&self, msg: Message) -> ParseError {
+        in_context(msg, &self.context())
+    }
+
+    // t in the 2nd expansion becomes t_2, or t_2_2 and so on if the program already uses that name. Renames
+    // stay valid identifiers so the expanded program can be printed and parsed again
+    fn fresh(&mut self, name: &str) -> String {
+        let mut renamed = format!("{}_{}", name, self.expansions);
+        while self.taken.contains(&renamed) {
+            renamed = format!("{}_{}", renamed, self.expansions);
+        }
+        self.taken.insert(renamed.to_owned());
+        renamed
+    }
+
+    fn expand(&mut self, call: FnCallData) -> Result<Expr, ParseError> {
+        let Some(mac) = self.macros.get(&call.name).cloned() else {
+            let e = message!(P008, "Macro '{}' not declared", call.name);
+            return Err(self.error(e));
+        };
+
+        if call.args.len() != mac.params.len() {
+            let e = message!(
+                P008,
+                "Macro '{}' expects {} arguments but got {}",
+                mac.name,
+                mac.params.len(),
+                call.args.len()
+            );
+            return Err(self.error(e));
+        }
+
+        if self.stack.len() == MACRO_RECURSION_LIMIT {
+            let e = message!(
+                P008,
+                "Macro recursion limit of {} reached while expanding '{}!'",
+                MACRO_RECURSION_LIMIT,
+                mac.name
+            );
+            return Err(ParseError::new(e));
+        }
+
+        self.expansions += 1;
+        self.stack.push(mac.name.to_owned());
+
+        let mut bindings = Bindings(vec![]);
+        bindings.fold_blk(mac.body.clone())?;
+        let renames = bindings
+            .0
+            .into_iter()
+            .map(|name| {
+                let renamed = self.fresh(&name);
+                (name, renamed)
+            })
+            .collect();
+
+        let mut instantiate = Instantiate {
+            args: mac.params.into_iter().zip(call.args).collect(),
+            renames,
+            context: self.context(),
+        };
+
+        // macro calls in the args are expanded along with the ones in the body
+        let body = instantiate
+            .fold_blk(mac.body)
+            .and_then(|body| self.fold_blk(body));
+
+        self.stack.pop();
+        Ok(Expr::BlockExpr(body?))
+    }
+}
+
+impl Fold for Expander {
+    fn fold_decl(&mut self, decl: Decl) -> Result<Decl, ParseError> {
+        if let Decl::MacroDeclStmt(data) = decl {
+            let e = message!(
+                P008,
+                "Macros can only be declared at the top level, found '{}'",
+                data.name
+            );
+            return Err(self.error(e));
+        }
+
+        walk_decl(self, decl)
+    }
+
+    fn fold_expr(&mut self, expr: Expr) -> Result<Expr, ParseError> {
+        match expr {
+            Expr::MacroCallExpr(call) => self.expand(call),
+            _ => walk_expr(self, expr),
+        }
+    }
+}
+
+// Names bound in a macro body: by let or for, fn names and params, lambda params, and the binds of match arms
+struct Bindings(Vec<String>);
+
+impl Fold for Bindings {
+    fn fold_decl(&mut self, decl: Decl) -> Result<Decl, ParseError> {
+        match &decl {
+            Decl::LetStmt(stmt) => self.0.push(stmt.ident.to_owned()),
+            Decl::LetTupleStmt(stmt) => {
+                let idents = stmt.idents.iter().filter(|ident| *ident != "_");
+                self.0.extend(idents.cloned())
+            }
+            Decl::ForStmt(lp) => self.0.push(lp.var.to_owned()),
+            Decl::FnDeclStmt(fn_decl) => {
+                self.0.push(fn_decl.name.to_owned());
+                self.0
+                    .extend(fn_decl.params.iter().map(|param| param.name.to_owned()));
+            }
+            _ => (),
+        }
+
+        walk_decl(self, decl)
+    }
+
+    fn fold_expr(&mut self, expr: Expr) -> Result<Expr, ParseError> {
+        match &expr {
+            Expr::MatchExpr(data) => {
+                for arm in data.arms.iter() {
+                    self.0.extend(arm.pat.binds().into_iter().cloned());
+                }
+            }
+            Expr::LambdaExpr(data) => {
+                self.0
+                    .extend(data.params.iter().map(|param| param.name.to_owned()));
+            }
+            _ => (),
+        }
+
+        walk_expr(self, expr)
+    }
+}
+
+// Every name used in a program, so renamed bindings can't clash with one
+struct Names(HashSet<String>);
+
+impl Fold for Names {
+    fn fold_expr(&mut self, expr: Expr) -> Result<Expr, ParseError> {
+        if let Expr::Symbol(sym) = &expr {
+            self.0.insert(sym.to_owned());
+        }
+        walk_expr(self, expr)
+    }
+
+    fn fold_name(&mut self, name: String) -> Result<String, ParseError> {
+        self.0.insert(name.to_owned());
+        Ok(name)
+    }
+}
+
+// Substitutes args for params and renames bindings in one copy of a macro body. Args are not folded, so names
+// at the call site are left alone
+struct Instantiate {
+    args: HashMap<String, Expr>,
+    renames: HashMap<String, String>,
+    context: Option<String>,
+}
+
+impl Instantiate {
+    fn unknown_param(&self, param: &str) -> ParseError {
+        let e = message!(P008, "Unknown macro parameter '{}'", param);
+        in_context(e, &self.context)
+    }
+}
+
+impl Fold for Instantiate {
+    fn fold_expr(&mut self, expr: Expr) -> Result<Expr, ParseError> {
+        match expr {
+            Expr::Symbol(sym) if sym.starts_with('$') => match self.args.get(&sym) {
+                Some(arg) => Ok(arg.to_owned()),
+                None => Err(self.unknown_param(&sym)),
+            },
+            Expr::Symbol(sym) => Ok(Expr::Symbol(self.renames.get(&sym).cloned().unwrap_or(sym))),
+            _ => walk_expr(self, expr),
+        }
+    }
+
+    // e.g $a = 2; needs the arg for $a to be a name
+    fn fold_name(&mut self, name: String) -> Result<String, ParseError> {
+        if let Some(renamed) = self.renames.get(&name) {
+            return Ok(renamed.to_owned());
+        }
+
+        if !name.starts_with('$') {
+            return Ok(name);
+        }
+
+        match self.args.get(&name) {
+            Some(Expr::Symbol(sym)) => Ok(sym.to_owned()),
+            Some(arg) => {
+                let e = message!(
+                    P008,
+                    "Argument '{}' for '{}' must be an identifier",
+                    arg,
+                    name
+                );
+                Err(in_context(e, &self.context))
+            }
+            None => Err(self.unknown_param(&name)),
+        }
+    }
+}
+
+// Say which macros were being expanded when the error happened, if any
+fn in_context(msg: Message, context: &Option<String>) -> ParseError {
+    match context {
+        Some(calls) => ParseError::new(message!(P008, "{} (while expanding {})", msg.text, calls)),
+        None => ParseError::new(msg),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{test_parse, test_parse_err};
+
+    #[test]
+    fn test_parse_macro_expand() {
+        let swap = "macro swap($a, $b) { let t = $a; $a = $b; $b = t; }";
+        test_parse(
+            &format!("{} swap!(x, y);", swap),
+            "{ let t_1 = x;x = y;y = t_1; };",
+        );
+
+        // the caller's t is not captured by the t in the body
+        test_parse(
+            &format!("{} swap!(t, x); swap!(x, t);", swap),
+            "{ let t_1 = t;t = x;x = t_1; };{ let t_2 = x;x = t;t = t_2; };",
+        );
+
+        // used before it is declared, and as a value
+        test_parse(
+            "let m = max!(x + 1, 2); macro max($a, $b) { if $a > $b { $a } else { $b } }",
+            "let m = { if ((x+1)>2) { (x+1) } else { 2 } };",
+        );
+
+        // fn names and params, and match binds, are renamed too
+        test_parse(
+            "macro m($a) { fn h(z: int) -> int { z + $a } println(h(1)); } m!(z);",
+            "{ fn h_1 (z_1:int) -> int { (z_1+z) };println(h_1(1)); };",
+        );
+        test_parse(
+            "macro m($a) { match $a { Some(v) => v, None => 0 } } m!(v)",
+            "{ match v { Some(v_1) => v_1, None => 0 } }",
+        );
+        test_parse(
+            "macro m($a) { let f = |t: int| t + $a; } m!(t);",
+            "{ let f_1 = |t_1:int| (t_1+t); };",
+        );
+
+        // nested calls in the body and in the args
+        test_parse(
+            "macro double($a) { $a * 2 } macro quad($a) { double!(double!($a)) } quad!(y)",
+            "{ { ({ (y*2) }*2) } }",
+        );
+    }
+
+    #[test]
+    fn test_parse_macro_decl_errs() {
+        test_parse_err(
+            "macro ($a) { $a }",
+            "Expected macro name after 'macro'",
+            true,
+        );
+        test_parse_err("macro m { 1 }", "Expected ( for macro parameters", true);
+        test_parse_err(
+            "macro m(a) { a }",
+            "Expected macro parameter like '$x' but got 'a'",
+            true,
+        );
+        test_parse_err(
+            "macro m($a, $a) { $a }",
+            "Parameter '$a' bound more than once for macro m",
+            true,
+        );
+        test_parse_err(
+            "macro m($a $b) { $a }",
+            "Expected ',' to separate macro parameters",
+            true,
+        );
+        test_parse_err("macro m($a) $a", "Expected { for macro body", true);
+        test_parse_err(
+            "let x = $a;",
+            "Macro parameter '$a' used outside of a macro",
+            true,
+        );
+        test_parse_err(
+            "macro m() { 1 } macro m() { 2 }",
+            "Macro 'm' is already declared",
+            true,
+        );
+        test_parse_err(
+            "fn f() { macro m() { 1 } }",
+            "Macros can only be declared at the top level, found 'm'",
+            true,
+        );
+    }
+
+    #[test]
+    fn test_parse_macro_expand_errs() {
+        test_parse_err("m!(1)", "Macro 'm' not declared", true);
+        test_parse_err(
+            "macro swap($a, $b) { $a } swap!(1)",
+            "Macro 'swap' expects 2 arguments but got 1",
+            true,
+        );
+        test_parse_err(
+            "macro inc($a) { $a = $a + 1; } inc!(x + 1);",
+            "Argument '(x+1)' for '$a' must be an identifier (while expanding 'inc!')",
+            true,
+        );
+        test_parse_err(
+            "macro m($a) { $b }  m!(1)",
+            "Unknown macro parameter '$b' (while expanding 'm!')",
+            true,
+        );
+        test_parse_err(
+            "macro f($a) { g!($a) } macro g($a) { h!($a) } f!(1)",
+            "Macro 'h' not declared (while expanding 'f!' -> 'g!')",
+            true,
+        );
+        test_parse_err(
+            "macro m($a) { m!($a) } m!(1)",
+            "Macro recursion limit of 64 reached while expanding 'm!'",
+            true,
+        );
+    }
+}
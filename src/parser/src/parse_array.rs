@@ -0,0 +1,237 @@
+use diagnostics::message;
+use lexer::Token;
+
+use crate::const_eval::const_eval;
+use crate::Decl;
+use crate::Expr;
+use crate::IndexAssignData;
+use crate::ParseError;
+use crate::Parser;
+use crate::SliceData;
+
+impl Parser {
+    // Array literal: [1, 2, 3] or [0; 4]
+    // Invariant: prev_tok is the opening bracket
+    pub(crate) fn parse_array(&mut self) -> Result<Decl, ParseError> {
+        let mut elems: Vec<Expr> = vec![];
+
+        while self.tokens.peek().is_some() {
+            // stop at ]
+            if self.is_peek_token_type(Token::CloseBracket) {
+                break;
+            }
+
+            self.advance();
+            let elem = self.parse_expr(0)?.to_expr()?;
+
+            // [val; len]
+            if elems.is_empty() && self.consume_opt_token_type(Token::Semi) {
+                let len = self.parse_array_len()?;
+                self.consume_token_type(Token::CloseBracket, "Expected ']'")?;
+                return Ok(Decl::ExprStmt(Expr::ArrayFillExpr(Box::new(elem), len)));
+            }
+
+            elems.push(elem);
+
+            if !self.is_peek_token_type(Token::CloseBracket) {
+                self.consume_token_type(Token::Comma, "Expected ',' to separate array elements")?;
+            }
+        }
+
+        self.consume_token_type(Token::CloseBracket, "Expected ']'")?;
+
+        Ok(Decl::ExprStmt(Expr::ArrayExpr(elems)))
+    }
+
+    /// Parse the length of an array type or repeat literal, which must be a constant integer expression.
+    /// Expects peek to be at the first token of the length, and leaves peek after its last token.
+    pub(crate) fn parse_array_len(&mut self) -> Result<usize, ParseError> {
+        self.advance();
+        let len_expr = self.parse_expr(0)?.to_expr()?;
+
+        let len = const_eval(&len_expr).ok_or_else(|| {
+            ParseError::new(message!(
+                P006,
+                "Expected a constant integer expression for array length, got '{}'",
+                len_expr
+            ))
+        })?;
+
+        usize::try_from(len).map_err(|_| {
+            ParseError::new(message!(
+                P006,
+                "Array length can't be negative, got {}",
+                len
+            ))
+        })
+    }
+
+    // Index into arr, or assign to the indexed element if an '=' follows e.g xs[i] = 2
+    // A range in the brackets makes a slice instead e.g xs[1..3]
+    // Invariant: peek is the opening bracket
+    pub(crate) fn parse_index(&mut self, arr: Decl) -> Result<Decl, ParseError> {
+        let arr = arr.to_expr()?;
+
+        self.advance(); // go past [
+
+        // xs[..end]
+        if self.is_peek_token_type(Token::DotDot) || self.is_peek_token_type(Token::DotDotEq) {
+            return self.parse_slice(arr, None);
+        }
+
+        self.advance(); // put first token of the index in prev_tok
+        let index = self.parse_expr(0)?.to_expr()?;
+
+        if self.is_peek_token_type(Token::DotDot) || self.is_peek_token_type(Token::DotDotEq) {
+            return self.parse_slice(arr, Some(index));
+        }
+
+        self.consume_token_type(Token::CloseBracket, "Expected ']' after index")?;
+
+        if self.consume_opt_token_type(Token::Eq) {
+            self.advance();
+            let expr = self.parse_expr(0)?.to_expr()?;
+            let assign = IndexAssignData { arr, index, expr };
+            return Ok(Decl::IndexAssignStmt(assign));
+        }
+
+        Ok(Decl::ExprStmt(Expr::IndexExpr(
+            Box::new(arr),
+            Box::new(index),
+        )))
+    }
+
+    // Invariant: peek is the range token
+    fn parse_slice(&mut self, arr: Expr, start: Option<Expr>) -> Result<Decl, ParseError> {
+        let inclusive = self.consume_opt_token_type(Token::DotDotEq);
+        if !inclusive {
+            self.consume_token_type(Token::DotDot, "Expected '..'")?;
+        }
+
+        let mut end: Option<Expr> = None;
+        if !self.is_peek_token_type(Token::CloseBracket) {
+            self.advance();
+            end.replace(self.parse_expr(0)?.to_expr()?);
+        } else if inclusive {
+            return Err(ParseError::new(message!(
+                P002,
+                "Expected end of slice after '..='"
+            )));
+        }
+
+        self.consume_token_type(Token::CloseBracket, "Expected ']' after slice")?;
+
+        let slice = SliceData {
+            arr,
+            start,
+            end,
+            inclusive,
+        };
+
+        Ok(Decl::ExprStmt(Expr::SliceExpr(Box::new(slice))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{test_parse, test_parse_err};
+
+    #[test]
+    fn test_parse_array_literal() {
+        test_parse("[1, 2, 3]", "[1,2,3]");
+        test_parse("[1, 2, 3,];", "[1,2,3];");
+        test_parse("[]", "[]");
+        test_parse("[x+1, f(2), [true]]", "[(x+1),f(2),[true]]");
+        test_parse("let xs = [1, 2];", "let xs = [1,2];");
+    }
+
+    #[test]
+    fn test_parse_array_fill() {
+        test_parse("[0; 4]", "[0; 4]");
+        test_parse("[x + 1; 2 * (3 + 1)]", "[(x+1); 8]");
+        test_parse("[[0; 2]; 3]", "[[0; 2]; 3]");
+
+        test_parse_err(
+            "[0; n]",
+            "Expected a constant integer expression for array length, got 'n'",
+            true,
+        );
+        test_parse_err("[0; 1 - 2]", "Array length can't be negative, got -1", true);
+        test_parse_err("[0; 2, 3]", "Expected ']'", true);
+    }
+
+    #[test]
+    fn test_parse_array_type_ann() {
+        test_parse("let xs: [int; 4] = [0; 4];", "let xs : [int; 4] = [0; 4];");
+        test_parse(
+            "let xs: [[bool; 2]; 1 + 1] = [[true; 2]; 2];",
+            "let xs : [[bool; 2]; 2] = [[true; 2]; 2];",
+        );
+        test_parse(
+            "fn f(xs: [int; 2]) -> [int; 2] { xs }",
+            "fn f (xs:[int; 2]) -> [int; 2] { xs };",
+        );
+
+        test_parse("let xs: [int] = ys[..];", "let xs : [int] = ys[..];");
+        test_parse(
+            "fn f(xs: [[int; 2]]) -> [int] { xs[0][..] }",
+            "fn f (xs:[[int; 2]]) -> [int] { xs[0][..] };",
+        );
+        test_parse_err(
+            "let xs: [int 2] = [1];",
+            "Expected ';' and a length, or ']' for array type annotation",
+            true,
+        );
+        test_parse_err(
+            "let xs: [int; n] = [1];",
+            "constant integer expression for array length",
+            true,
+        );
+    }
+
+    #[test]
+    fn test_parse_index() {
+        test_parse("xs[0]", "xs[0]");
+        test_parse("xs[i + 1] * 2", "(xs[(i+1)]*2)");
+        test_parse("-xs[0]", "(-xs[0])");
+        test_parse("grid[i][j]", "grid[i][j]");
+        test_parse("f(2)[0]", "f(2)[0]");
+        test_parse("[1, 2][1]", "[1,2][1]");
+        test_parse("(xs)[0]", "xs[0]");
+        test_parse_err("xs[0", "Expected ']' after index", true);
+    }
+
+    #[test]
+    fn test_parse_slice() {
+        test_parse("xs[1..3]", "xs[1..3]");
+        test_parse("xs[1..=3]", "xs[1..=3]");
+        test_parse("xs[..]", "xs[..]");
+        test_parse("xs[..n]", "xs[..n]");
+        test_parse("xs[..=n]", "xs[..=n]");
+        test_parse("xs[mid + 1..]", "xs[(mid+1)..]");
+        test_parse("xs[i * 2..n - 1][0]", "xs[(i*2)..(n-1)][0]");
+        test_parse("xs[1..][0] = 0;", "xs[1..][0] = 0;");
+        test_parse("f(xs[..mid], 2)", "f(xs[..mid],2)");
+
+        test_parse_err("xs[1..=]", "Expected end of slice after '..='", true);
+        test_parse_err("xs[1..2", "Expected ']' after slice", true);
+    }
+
+    #[test]
+    fn test_parse_index_assign() {
+        test_parse("xs[0] = 2;", "xs[0] = 2;");
+        test_parse("grid[i][j + 1] = x * 2;", "grid[i][(j+1)] = (x*2);");
+        test_parse_err(
+            "let y = xs[0] = 2;",
+            "'xs[0] = 2' is not an expression",
+            true,
+        );
+        test_parse_err("xs[0] = 2", "Expected semicolon", true);
+    }
+
+    #[test]
+    fn test_parse_bracket_after_block() {
+        // A '[' after a block-like statement starts a new array rather than indexing the block
+        test_parse("if x { 1; } [1, 2]", "if x { 1; };[1,2]");
+    }
+}
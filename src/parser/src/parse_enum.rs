@@ -0,0 +1,145 @@
+use diagnostics::message;
+use lexer::Token;
+
+use crate::Decl;
+use crate::EnumDeclData;
+use crate::ParseError;
+use crate::Parser;
+use crate::Type;
+
+impl Parser {
+    // enum Shape { Circle(float), Square(float), Empty }
+    // Invariant: prev_tok is enum
+    pub(crate) fn parse_enum_decl(&mut self) -> Result<Decl, ParseError> {
+        let Some(Ok(Token::Ident(name))) = self.tokens.peek() else {
+            return Err(ParseError::new(message!(
+                P002,
+                "Expected enum name after 'enum'"
+            )));
+        };
+        let name = name.to_owned();
+        self.advance();
+
+        self.consume_token_type(Token::OpenBrace, "Expected { for enum declaration")?;
+
+        let mut variants: Vec<(String, Vec<Type>)> = vec![];
+        while !self.is_peek_token_type(Token::CloseBrace) {
+            let Some(Ok(Token::Ident(variant))) = self.tokens.peek() else {
+                let e = message!(P002, "Expected variant name in enum '{}'", name);
+                return Err(ParseError::new(e));
+            };
+            let variant = variant.to_owned();
+            self.advance();
+
+            // patterns tell a variant from a name to bind by the case, like None and x
+            if !variant.starts_with(char::is_uppercase) {
+                let e = message!(
+                    P002,
+                    "Variant '{}' of enum '{}' must start with an uppercase letter",
+                    variant,
+                    name
+                );
+                return Err(ParseError::new(e));
+            }
+
+            let mut payload: Vec<Type> = vec![];
+            if self.consume_opt_token_type(Token::OpenParen) {
+                while !self.is_peek_token_type(Token::CloseParen) {
+                    payload.push(self.parse_type_annotation()?);
+                    if !self.is_peek_token_type(Token::CloseParen) {
+                        self.consume_token_type(
+                            Token::Comma,
+                            "Expected ',' to separate the types a variant holds",
+                        )?;
+                    }
+                }
+                self.consume_token_type(Token::CloseParen, "Expected ')'")?;
+
+                if payload.is_empty() {
+                    let e = message!(
+                        P002,
+                        "Variant '{}' holds no values, leave out the parentheses",
+                        variant
+                    );
+                    return Err(ParseError::new(e));
+                }
+            }
+            variants.push((variant, payload));
+
+            if !self.is_peek_token_type(Token::CloseBrace) {
+                self.consume_token_type(Token::Comma, "Expected ',' to separate enum variants")?;
+            }
+        }
+
+        self.consume_token_type(Token::CloseBrace, "Expected '}'")?;
+
+        if variants.is_empty() {
+            let e = message!(P002, "Enum '{}' must have at least one variant", name);
+            return Err(ParseError::new(e));
+        }
+
+        Ok(Decl::EnumDeclStmt(EnumDeclData {
+            name,
+            variants,
+            doc: None,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{test_parse, test_parse_err};
+
+    #[test]
+    fn test_parse_enum_decl() {
+        let t = r"
+        enum Shape {
+            Circle(float),
+            Rect(float, float),
+            Empty,
+        }
+        enum Tree { Leaf, Node([int; 2], Shape) }
+        ";
+        test_parse(
+            t,
+            "enum Shape { Circle(float), Rect(float, float), Empty };enum Tree { Leaf, Node([int; 2], Shape) };",
+        );
+
+        // constructors are fn calls and unit variants are symbols until the type checker
+        test_parse("let s = Circle(2.0);", "let s = Circle(2.0);");
+        test_parse(
+            "match s { Circle(r) => r, Empty => 0.0, _ => 1.0 }",
+            "match s { Circle(r) => r, Empty => 0.0, _ => 1.0 }",
+        );
+
+        test_parse_err("enum { A }", "Expected enum name after 'enum'", true);
+        test_parse_err("enum E A", "Expected { for enum declaration", true);
+        test_parse_err("enum E { 2 }", "Expected variant name in enum 'E'", true);
+        test_parse_err(
+            "enum E { a }",
+            "Variant 'a' of enum 'E' must start with an uppercase letter",
+            true,
+        );
+        test_parse_err(
+            "enum E { A B }",
+            "Expected ',' to separate enum variants",
+            true,
+        );
+        test_parse_err(
+            "enum E { A(int float) }",
+            "Expected ',' to separate the types a variant holds",
+            true,
+        );
+        test_parse_err(
+            "enum E { A() }",
+            "Variant 'A' holds no values, leave out the parentheses",
+            true,
+        );
+        test_parse_err("enum E {}", "Enum 'E' must have at least one variant", true);
+        test_parse_err(
+            "let x = enum E { A };",
+            "Enum declaration is not an expression",
+            true,
+        );
+    }
+}
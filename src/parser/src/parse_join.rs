@@ -0,0 +1,73 @@
+use diagnostics::message;
+use lexer::Token;
+
+use crate::Decl;
+use crate::Expr;
+use crate::ParseError;
+use crate::Parser;
+use crate::TryJoinData;
+
+impl Parser {
+    // try_join(t) or join_timeout(t, ms)
+    // Invariant: prev_tok is try_join or join_timeout
+    pub(crate) fn parse_try_join(&mut self) -> Result<Decl, ParseError> {
+        let has_timeout = matches!(self.prev_tok, Some(Token::JoinTimeout));
+        let name = if has_timeout {
+            Token::JoinTimeout
+        } else {
+            Token::TryJoin
+        };
+
+        let mut args = self.parse_call_args()?.into_iter();
+        let (tid, timeout) = match (args.next(), args.next(), args.next(), has_timeout) {
+            (Some(Expr::Symbol(tid)), None, None, false) => (tid, None),
+            (Some(Expr::Symbol(tid)), Some(ms), None, true) => (tid, Some(ms)),
+            (Some(Expr::Symbol(_)), ..) => {
+                let e = if has_timeout {
+                    message!(P002, "{} expected a thread and a timeout in ms", name)
+                } else {
+                    message!(P002, "{} expected a thread", name)
+                };
+                return Err(ParseError::new(e));
+            }
+            _ => {
+                let e = message!(P002, "{} expected variable for thread to join", name);
+                return Err(ParseError::new(e));
+            }
+        };
+
+        Ok(Decl::ExprStmt(Expr::TryJoinExpr(Box::new(TryJoinData {
+            tid,
+            timeout,
+        }))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{test_parse, test_parse_err};
+
+    #[test]
+    fn test_parse_try_join() {
+        test_parse(
+            "let done = try_join(t); if join_timeout(t, 10 * 2) { 1 } else { 2 }",
+            "let done = try_join(t);if join_timeout(t, (10*2)) { 1 } else { 2 }",
+        );
+    }
+
+    #[test]
+    fn test_parse_try_join_errs() {
+        test_parse_err(
+            "try_join(2)",
+            "try_join expected variable for thread to join",
+            true,
+        );
+        test_parse_err("try_join(t, 10)", "try_join expected a thread", true);
+        test_parse_err(
+            "join_timeout(t)",
+            "join_timeout expected a thread and a timeout in ms",
+            true,
+        );
+        test_parse_err("try_join t", "Expected '('", true);
+    }
+}
@@ -0,0 +1,136 @@
+use diagnostics::message;
+use lexer::Token;
+
+use crate::Decl;
+use crate::Expr;
+use crate::FnParam;
+use crate::LambdaData;
+use crate::ParseError;
+use crate::Parser;
+
+impl Parser {
+    // |x: int, y: int| x + y or |x: int| -> int { x } or || 2
+    // Invariant: prev_tok is | or ||
+    pub(crate) fn parse_lambda(&mut self) -> Result<Decl, ParseError> {
+        let prev_is_loop = self.is_loop;
+        let prev_is_fn = self.is_fn;
+
+        // the body is a fn body: return is allowed and break is not
+        self.is_loop = false;
+        self.is_fn = true;
+        let res = self.parse_lambda_inner();
+
+        self.is_loop = prev_is_loop;
+        self.is_fn = prev_is_fn;
+        res
+    }
+
+    fn parse_lambda_inner(&mut self) -> Result<Decl, ParseError> {
+        let mut params: Vec<FnParam> = vec![];
+
+        // || has no params
+        if matches!(self.prev_tok, Some(Token::Or)) {
+            while !self.is_peek_token_type(Token::Or) {
+                let name = match self.tokens.peek() {
+                    Some(Ok(Token::Ident(name))) => name.to_owned(),
+                    Some(Ok(tok)) => {
+                        let e = message!(P002, "Expected lambda parameter but got '{}'", tok);
+                        return Err(ParseError::new(e));
+                    }
+                    _ => {
+                        return Err(ParseError::new(message!(
+                            P002,
+                            "Expected '|' to close lambda parameters"
+                        )))
+                    }
+                };
+                self.advance();
+
+                if params.iter().any(|param| param.name == name) {
+                    let e = message!(P010, "Parameter '{}' bound more than once for lambda", name);
+                    return Err(ParseError::new(e));
+                }
+
+                let type_ann = if self.consume_opt_token_type(Token::Colon) {
+                    Some(self.parse_type_annotation()?)
+                } else {
+                    None
+                };
+                params.push(FnParam { name, type_ann });
+
+                if !self.is_peek_token_type(Token::Or) {
+                    self.consume_token_type(
+                        Token::Comma,
+                        "Expected ',' to separate lambda parameters",
+                    )?;
+                }
+            }
+            self.advance(); // go past |
+        }
+
+        // like Rust, a return type needs a block body
+        let (ret_type, body) = if self.consume_opt_token_type(Token::FnDeclReturn) {
+            let ret_type = self.parse_type_annotation()?;
+            self.consume_token_type(
+                Token::OpenBrace,
+                "Expected { for lambda body after return type",
+            )?;
+            let body = self.parse_blk()?.to_expr()?;
+            (Some(ret_type), body)
+        } else {
+            self.advance();
+            (None, self.parse_expr(0)?.to_expr()?)
+        };
+
+        Ok(Decl::ExprStmt(Expr::LambdaExpr(Box::new(LambdaData {
+            params,
+            ret_type,
+            body,
+        }))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{test_parse, test_parse_err};
+
+    #[test]
+    fn test_parse_lambda() {
+        test_parse(
+            "let f = |x: int, y: int| x + y;",
+            "let f = |x:int, y:int| (x+y);",
+        );
+        test_parse("let f = || 2; f()", "let f = || 2;f()");
+        test_parse(
+            "apply(|x: int| -> int { return x; }, 2)",
+            "apply(|x:int| -> int { return x; },2)",
+        );
+        // the body goes as far right as it can
+        test_parse("let f = |x| |y| x * y + 1;", "let f = |x| |y| ((x*y)+1);");
+    }
+
+    #[test]
+    fn test_parse_lambda_errs() {
+        test_parse_err(
+            "|x, x| x",
+            "Parameter 'x' bound more than once for lambda",
+            true,
+        );
+        test_parse_err(
+            "|x y| x",
+            "Expected ',' to separate lambda parameters",
+            true,
+        );
+        test_parse_err("|2| x", "Expected lambda parameter but got '2'", true);
+        test_parse_err(
+            "|x: int| -> int x",
+            "Expected { for lambda body after return type",
+            true,
+        );
+        test_parse_err(
+            "loop { let f = || { break; }; }",
+            "break outside of loop",
+            true,
+        );
+    }
+}
@@ -0,0 +1,52 @@
+use diagnostics::message;
+use lexer::Token;
+
+use crate::Decl;
+use crate::Expr;
+use crate::LockData;
+use crate::ParseError;
+use crate::Parser;
+
+impl Parser {
+    // lock m { x = x + 1; x }
+    // Invariant: prev_tok is lock
+    pub(crate) fn parse_lock(&mut self) -> Result<Decl, ParseError> {
+        if self.is_peek_token_type(Token::OpenBrace) {
+            return Err(ParseError::new(message!(
+                P002,
+                "Expected mutex after 'lock'"
+            )));
+        }
+
+        self.advance();
+        let mutex = self.parse_expr(0)?.to_expr()?;
+
+        self.consume_token_type(Token::OpenBrace, "Expected { for lock block")?;
+        let body = self.parse_blk()?.to_block()?;
+
+        Ok(Decl::ExprStmt(Expr::LockExpr(Box::new(LockData {
+            mutex,
+            body,
+        }))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{test_parse, test_parse_err};
+
+    #[test]
+    fn test_parse_lock() {
+        test_parse(
+            "lock m { x = x + 1; } let y = lock s.m { x };",
+            "lock m { x = (x+1); };let y = lock s.m { x };",
+        );
+        test_parse("lock a { lock b { x } } 2", "lock a { lock b { x } };2");
+    }
+
+    #[test]
+    fn test_parse_lock_errs() {
+        test_parse_err("lock { x }", "Expected mutex after 'lock'", true);
+        test_parse_err("lock m;", "Expected { for lock block", true);
+    }
+}
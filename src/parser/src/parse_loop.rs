@@ -1,7 +1,11 @@
+use diagnostics::message;
 use lexer::Token;
 
+use crate::BlockSeq;
 use crate::Decl;
 use crate::Expr;
+use crate::ForData;
+use crate::ForIter;
 use crate::LoopData;
 use crate::ParseError;
 use crate::Parser;
@@ -30,16 +34,16 @@ loop x < 5 {
     }
 }
 */
-impl<'inp> Parser<'inp> {
+impl Parser {
     /*
 
     */
-    // Ensure is_loop flag is saved and restored as long as valid return. Error crashes the whole parser so it's fine
+    // Ensure is_loop flag is saved and restored, also on an error since parsing goes on after one
     pub(crate) fn parse_loop(&mut self) -> Result<Decl, ParseError> {
         let prev_is_loop = self.is_loop;
-        let lp = self.parse_loop_inner()?;
+        let lp = self.parse_loop_inner();
         self.is_loop = prev_is_loop;
-        Ok(lp)
+        lp
     }
 
     fn parse_loop_inner(&mut self) -> Result<Decl, ParseError> {
@@ -59,7 +63,7 @@ impl<'inp> Parser<'inp> {
 
         // If the thing we parsed is a block, this is a loop with just a body and no cond
         if let Expr::BlockExpr(ref blk) = cond {
-            // dbg!("peek after parsing blk:", &self.lexer.peek());
+            // dbg!("peek after parsing blk:", &self.tokens.peek());
             // next token is NOT OpenBrace: we just parsed body, there is no condition
             let lp = LoopData {
                 cond: None,
@@ -70,10 +74,7 @@ impl<'inp> Parser<'inp> {
         }
 
         // go past OpenBrace, put in prev_tok
-        self.consume_token_type(
-            Token::OpenBrace,
-            &format!("Expected {} for loop block", Token::OpenBrace),
-        )?;
+        self.consume_token_type(Token::OpenBrace, "Expected { for loop block")?;
 
         let loop_blk = self.parse_blk()?.to_block()?;
 
@@ -87,6 +88,121 @@ impl<'inp> Parser<'inp> {
 
         Ok(Decl::LoopStmt(lp))
     }
+
+    // while cond { .. } is a loop that always has a condition
+    pub(crate) fn parse_while(&mut self) -> Result<Decl, ParseError> {
+        let prev_is_loop = self.is_loop;
+        let lp = self.parse_while_inner()?;
+        self.is_loop = prev_is_loop;
+        Ok(lp)
+    }
+
+    fn parse_while_inner(&mut self) -> Result<Decl, ParseError> {
+        if self.is_peek_token_type(Token::Let) {
+            return self.parse_while_let();
+        }
+
+        // a block straight after while would otherwise be taken as the condition
+        if self.is_peek_token_type(Token::OpenBrace) {
+            return Err(ParseError::new(message!(
+                P002,
+                "Expected condition for while loop"
+            )));
+        }
+
+        self.advance();
+        self.is_loop = true;
+
+        let cond = self.parse_expr(0)?.to_expr()?;
+
+        self.consume_token_type(Token::OpenBrace, "Expected { for while loop block")?;
+
+        let body = self.parse_blk()?.to_block()?;
+
+        let lp = LoopData {
+            cond: Some(cond),
+            body,
+        };
+
+        Ok(Decl::LoopStmt(lp))
+    }
+
+    // while let Some(x) = next() { a } is sugar for loop { match next() { Some(x) => { a }, _ => { break; } } }
+    // Invariant: prev_tok is while and peek is let
+    fn parse_while_let(&mut self) -> Result<Decl, ParseError> {
+        self.is_loop = true;
+        let (pat, subject) = self.parse_let_pattern("while")?;
+
+        self.consume_token_type(Token::OpenBrace, "Expected { for while let block")?;
+        let body = self.parse_blk()?.to_block()?;
+
+        let brk = BlockSeq {
+            decls: vec![Decl::BreakStmt],
+            last_expr: None,
+            symbols: vec![],
+            spans: vec![],
+        };
+        let body = BlockSeq {
+            decls: vec![Decl::ExprStmt(Parser::let_match(pat, subject, body, brk))],
+            last_expr: None,
+            symbols: vec![],
+            spans: vec![],
+        };
+
+        Ok(Decl::LoopStmt(LoopData { cond: None, body }))
+    }
+
+    // for i in start..end { .. }, for i in start..=end { .. } or for x in xs { .. }
+    pub(crate) fn parse_for(&mut self) -> Result<Decl, ParseError> {
+        let prev_is_loop = self.is_loop;
+        let lp = self.parse_for_inner()?;
+        self.is_loop = prev_is_loop;
+        Ok(lp)
+    }
+
+    fn parse_for_inner(&mut self) -> Result<Decl, ParseError> {
+        self.advance();
+        let var = match self.expect_prev_tok()? {
+            Token::Ident(var) => var.to_owned(),
+            tok => {
+                let e = message!(P002, "Expected identifier after 'for' but got '{}'", tok);
+                return Err(self.prev_tok_err(e));
+            }
+        };
+
+        self.consume_token_type(Token::In, "Expected 'in' after for loop variable")?;
+        self.advance();
+
+        let start = self.parse_expr(0)?.to_expr()?;
+
+        let inclusive = self.is_peek_token_type(Token::DotDotEq);
+        let iter = if inclusive || self.is_peek_token_type(Token::DotDot) {
+            self.advance();
+            if self.is_peek_token_type(Token::OpenBrace) {
+                return Err(ParseError::new(message!(
+                    P002,
+                    "Expected end of range in for loop"
+                )));
+            }
+
+            self.advance();
+            let end = self.parse_expr(0)?.to_expr()?;
+            ForIter::Range {
+                start,
+                end,
+                inclusive,
+            }
+        } else {
+            ForIter::Elems(start)
+        };
+
+        self.consume_token_type(Token::OpenBrace, "Expected { for for loop block")?;
+
+        self.is_loop = true;
+        let body = self.parse_blk()?.to_block()?;
+
+        Ok(Decl::ForStmt(ForData { var, iter, body }))
+    }
 }
 
 #[cfg(test)]
@@ -321,4 +437,109 @@ mod tests {
         ";
         test_parse(t, "loop  { let x = if true { break;3 } else { 5 }; };");
     }
+
+    #[test]
+    fn test_parse_while() {
+        let t = r"
+        let x = 0;
+        while x < 5 {
+            x = x + 1;
+        }
+        x
+        ";
+        test_parse(t, "let x = 0;loop (x<5) { x = (x+1); };x");
+
+        let t = r"
+        while (x < 5) && !done {
+            if x == 3 {
+                break;
+            }
+        }
+        ";
+        test_parse(t, "loop ((x<5)&&(!done)) { if (x==3) { break; }; };");
+
+        // nested with a loop
+        let t = r"
+        while true {
+            loop {
+                break;
+            }
+            break;
+        }
+        ";
+        test_parse(t, "loop true { loop  { break; };break; };");
+
+        test_parse_err("while { 2; }", "Expected condition for while loop", true);
+        test_parse_err("while x < 5", "Expected { for while loop block", true);
+        test_parse_err("let x = while true {};", "loop is not an expression", true);
+        test_parse_err("while true { 2; } break;", "break outside of loop", true);
+    }
+
+    #[test]
+    fn test_parse_for() {
+        let t = r"
+        let sum = 0;
+        for i in 0..n + 1 {
+            sum = sum + i;
+        }
+        sum
+        ";
+        test_parse(t, "let sum = 0;for i in 0..(n+1) { sum = (sum+i); };sum");
+
+        test_parse(
+            "for i in -2..=2 { break; }",
+            "for i in (-2)..=2 { break; };",
+        );
+        test_parse("for x in xs[1..] { x; };", "for x in xs[1..] { x; };");
+        test_parse("for x in [1, 2] { }", "for x in [1,2] {  };");
+
+        // nested
+        let t = r"
+        for i in 0..3 {
+            for j in i..3 {
+                break;
+            }
+            break;
+        }
+        ";
+        test_parse(t, "for i in 0..3 { for j in i..3 { break; };break; };");
+
+        test_parse_err("for 2 in xs {}", "Expected identifier after 'for'", true);
+        test_parse_err("for i xs {}", "Expected 'in' after for loop variable", true);
+        test_parse_err("for i in 0.. {}", "Expected end of range in for loop", true);
+        test_parse_err("for i in 0..3", "Expected { for for loop block", true);
+        test_parse_err("let x = for i in xs {};", "for is not an expression", true);
+        test_parse_err("for i in 0..3 {} break;", "break outside of loop", true);
+    }
+
+    #[test]
+    fn test_parse_while_let() {
+        let t = r"
+        while let Some(x) = next(it) {
+            if x > 2 { break; }
+            println(x);
+        }
+        done()
+        ";
+        test_parse(
+            t,
+            "loop  { match next(it) { Some(x) => { if (x>2) { break; };println(x); }, _ => { break; } }; };done()",
+        );
+
+        test_parse_err(
+            "while let Some(x) next() {}",
+            "Expected '=' after pattern 'Some(x)' of while let",
+            true,
+        );
+        test_parse_err(
+            "while let Some(x) = next()",
+            "Expected { for while let block",
+            true,
+        );
+        test_parse_err(
+            "while let y = next() {}",
+            "Expected int, bool, variant, struct or '_' pattern but got 'y'",
+            true,
+        );
+    }
 }
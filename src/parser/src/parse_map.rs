@@ -0,0 +1,56 @@
+use lexer::Token;
+
+use crate::Decl;
+use crate::Expr;
+use crate::ParseError;
+use crate::Parser;
+
+impl Parser {
+    // Map literal: #{"a": 1, "b": 2} or #{}
+    // Invariant: prev_tok is #
+    pub(crate) fn parse_map(&mut self) -> Result<Decl, ParseError> {
+        self.consume_token_type(Token::OpenBrace, "Expected '{' after '#' for map literal")?;
+        let mut entries: Vec<(Expr, Expr)> = vec![];
+
+        while !self.is_peek_token_type(Token::CloseBrace) {
+            self.advance(); // put the first token of the key in prev_tok
+            let key = self.parse_expr(0)?.to_expr()?;
+            self.consume_token_type(Token::Colon, "Expected ':' after key in map literal")?;
+
+            self.advance();
+            let val = self.parse_expr(0)?.to_expr()?;
+            entries.push((key, val));
+
+            if !self.is_peek_token_type(Token::CloseBrace) {
+                self.consume_token_type(Token::Comma, "Expected ',' or '}' in map literal")?;
+            }
+        }
+        self.consume_token_type(Token::CloseBrace, "Expected '}' to close map literal")?;
+
+        Ok(Decl::ExprStmt(Expr::MapExpr(entries)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{test_parse, test_parse_err};
+
+    #[test]
+    fn test_parse_map() {
+        test_parse(r#"#{"a": 1, "b": 2}"#, r#"#{"a": 1,"b": 2}"#);
+        test_parse(r#"#{"a": 1,}"#, r#"#{"a": 1}"#);
+        test_parse("#{}", "#{}");
+        test_parse("#{1 + 2: [3], x: f(y)}", "#{(1+2): [3],x: f(y)}");
+        test_parse("let m = #{true: #{}};", "let m = #{true: #{}};");
+        test_parse("#[test] fn f() {} #{1: 2}", "fn f () {  };#{1: 2}");
+
+        test_parse_err("#{1, 2}", "Expected ':' after key in map literal", true);
+        test_parse_err("#{1: 2; 3: 4}", "Expected ',' or '}' in map literal", true);
+        test_parse_err("#{1: 2", "Expected ',' or '}' in map literal", true);
+        test_parse_err(
+            "let m = #(1);",
+            "Expected '{' after '#' for map literal",
+            true,
+        );
+    }
+}
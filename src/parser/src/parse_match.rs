@@ -0,0 +1,487 @@
+use diagnostics::message;
+use lexer::Token;
+
+use crate::BlockSeq;
+use crate::Decl;
+use crate::Expr;
+use crate::MatchArm;
+use crate::MatchData;
+use crate::ParseError;
+use crate::Parser;
+use crate::Pattern;
+
+impl Parser {
+    // match x { 1 => a, -1 => b, n if n > 10 => n, _ => { c } }
+    // Invariant: prev_tok is match
+    pub(crate) fn parse_match(&mut self) -> Result<Decl, ParseError> {
+        self.advance();
+        let subject = self.parse_expr(0)?.to_expr()?;
+
+        self.consume_token_type(Token::OpenBrace, "Expected { for match arms")?;
+
+        let mut arms: Vec<MatchArm> = vec![];
+        while !self.is_peek_token_type(Token::CloseBrace) {
+            let pat = self.parse_pattern()?;
+
+            let mut guard = None;
+            if self.is_peek_token_type(Token::If) {
+                self.advance();
+                self.advance();
+
+                let prev_is_guard = self.is_guard;
+                self.is_guard = true;
+                let expr = self.parse_expr(0);
+                self.is_guard = prev_is_guard;
+                guard.replace(expr?.to_expr()?);
+            }
+
+            if !self.is_peek_token_type(Token::FatArrow) {
+                let e = match guard {
+                    Some(guard) => message!(
+                        P002,
+                        "Expected '=>' after guard '{}' of pattern '{}'",
+                        guard,
+                        pat
+                    ),
+                    None => message!(P002, "Expected '=>' after pattern '{}'", pat),
+                };
+                return Err(ParseError::new(e));
+            }
+            self.advance();
+            self.advance();
+            let body = self.parse_expr(0)?.to_expr()?;
+            arms.push(MatchArm { pat, guard, body });
+
+            // the comma is optional after a block body and after the last arm, like Rust
+            let after_blk = matches!(self.prev_tok, Some(Token::CloseBrace));
+            if !self.consume_opt_token_type(Token::Comma)
+                && !after_blk
+                && !self.is_peek_token_type(Token::CloseBrace)
+            {
+                return Err(ParseError::new(message!(
+                    P002,
+                    "Expected ',' to separate match arms"
+                )));
+            }
+        }
+
+        self.consume_token_type(Token::CloseBrace, "Expected '}'")?;
+
+        if arms.is_empty() {
+            return Err(ParseError::new(message!(
+                P002,
+                "match must have at least one arm"
+            )));
+        }
+
+        Ok(Decl::ExprStmt(Expr::MatchExpr(Box::new(MatchData {
+            subject,
+            arms,
+        }))))
+    }
+
+    // The 'let Some(x) = opt' of if let or while let, as the pattern and the matched value
+    // Invariant: peek is let. Leaves prev_tok on the last token of the matched value
+    pub(crate) fn parse_let_pattern(&mut self, kind: &str) -> Result<(Pattern, Expr), ParseError> {
+        self.advance();
+        let pat = self.parse_pattern()?;
+
+        let err = format!("Expected '=' after pattern '{}' of {} let", pat, kind);
+        self.consume_token_type(Token::Eq, &err)?;
+        self.advance();
+        let subject = self.parse_expr(0)?.to_expr()?;
+
+        Ok((pat, subject))
+    }
+
+    // match subject { pat => { blk }, _ => { otherwise } }, what if let and while let desugar to
+    pub(crate) fn let_match(
+        pat: Pattern,
+        subject: Expr,
+        blk: BlockSeq,
+        otherwise: BlockSeq,
+    ) -> Expr {
+        let arms = vec![
+            MatchArm {
+                pat,
+                guard: None,
+                body: Expr::BlockExpr(blk),
+            },
+            MatchArm {
+                pat: Pattern::Wildcard,
+                guard: None,
+                body: Expr::BlockExpr(otherwise),
+            },
+        ];
+        Expr::MatchExpr(Box::new(MatchData { subject, arms }))
+    }
+
+    // 1 | 2 | 3, or a single pattern
+    // Invariant: peek is the first token of the pattern. Leaves prev_tok on the last token of it
+    fn parse_pattern(&mut self) -> Result<Pattern, ParseError> {
+        let pat = self.parse_single_pattern()?;
+        if !self.is_peek_token_type(Token::Or) {
+            return Ok(pat);
+        }
+
+        let mut alts = vec![pat];
+        while self.consume_opt_token_type(Token::Or) {
+            alts.push(self.parse_single_pattern()?);
+        }
+        Ok(Pattern::Or(alts))
+    }
+
+    // Invariant: peek is the first token of the pattern. Leaves prev_tok on the last token of it
+    fn parse_single_pattern(&mut self) -> Result<Pattern, ParseError> {
+        if let Some(lo) = self.parse_int_pattern() {
+            return self.parse_range_pattern(lo);
+        }
+
+        let pat = match (self.tokens.peek(), self.tokens.peek_nth(1)) {
+            (Some(Ok(Token::Bool(val))), _) => Pattern::Bool(*val),
+            (Some(Ok(Token::Ident(id))), _) if id == "_" => Pattern::Wildcard,
+            (Some(Ok(Token::Ident(id))), Some(Ok(Token::OpenParen))) => {
+                let name = id.to_owned();
+                self.advance();
+                return self.parse_variant_binds(name);
+            }
+            (Some(Ok(Token::Ident(id))), Some(Ok(Token::OpenBrace))) => {
+                let name = id.to_owned();
+                self.advance();
+                return self.parse_struct_binds(name);
+            }
+            // variants are capitalised, like None, so a lowercase name isn't mistaken for one
+            (Some(Ok(Token::Ident(id))), _) if id.starts_with(char::is_uppercase) => {
+                Pattern::Variant(id.to_owned(), vec![])
+            }
+            (Some(Ok(tok)), _) => {
+                let e = message!(
+                    P002,
+                    "Expected int, bool, variant, struct or '_' pattern but got '{}'",
+                    tok
+                );
+                return Err(ParseError::new(e));
+            }
+            _ => return Err(ParseError::new(message!(P002, "Expected '}'"))),
+        };
+        self.advance();
+
+        Ok(pat)
+    }
+
+    // 1 or -1, None if peek isn't an int
+    // Invariant: peek is the int or the '-' before it. Leaves prev_tok on the int
+    fn parse_int_pattern(&mut self) -> Option<i64> {
+        let val = match (self.tokens.peek(), self.tokens.peek_nth(1)) {
+            (Some(Ok(Token::Integer(val))), _) => *val,
+            (Some(Ok(Token::Minus)), Some(Ok(Token::Integer(val)))) => {
+                let val = -*val;
+                self.advance();
+                val
+            }
+            _ => return None,
+        };
+        self.advance();
+
+        Some(val)
+    }
+
+    // 0..10 or 0..=9, or just the int lo
+    // Invariant: prev_tok is lo. Leaves prev_tok on hi
+    fn parse_range_pattern(&mut self, lo: i64) -> Result<Pattern, ParseError> {
+        let inclusive = match self.tokens.peek() {
+            Some(Ok(Token::DotDot)) => false,
+            Some(Ok(Token::DotDotEq)) => true,
+            _ => return Ok(Pattern::Int(lo)),
+        };
+        self.advance();
+
+        let Some(hi) = self.parse_int_pattern() else {
+            let op = if inclusive { "..=" } else { ".." };
+            let e = message!(
+                P002,
+                "Expected an int to end the range pattern '{}{}'",
+                lo,
+                op
+            );
+            return Err(ParseError::new(e));
+        };
+
+        Ok(Pattern::Range { lo, hi, inclusive })
+    }
+
+    // Some(x) or Err(_)
+    // Invariant: prev_tok is the name of the variant and peek is '('. Leaves prev_tok on ')'
+    fn parse_variant_binds(&mut self, name: String) -> Result<Pattern, ParseError> {
+        self.advance();
+
+        let mut binds: Vec<String> = vec![];
+        while !self.consume_opt_token_type(Token::CloseParen) {
+            let Some(Ok(Token::Ident(bind))) = self.tokens.peek() else {
+                let e = message!(
+                    P002,
+                    "Expected a name to bind in pattern '{}', like {}(x)",
+                    name,
+                    name
+                );
+                return Err(ParseError::new(e));
+            };
+            binds.push(bind.to_owned());
+            self.advance();
+
+            if !self.is_peek_token_type(Token::CloseParen) {
+                self.consume_token_type(
+                    Token::Comma,
+                    "Expected ',' to separate the names bound by a pattern",
+                )?;
+            }
+        }
+
+        Ok(Pattern::Variant(name, binds))
+    }
+
+    // Point { x, y: b, .. }
+    // Invariant: prev_tok is the name of the struct and peek is '{'. Leaves prev_tok on '}'
+    fn parse_struct_binds(&mut self, name: String) -> Result<Pattern, ParseError> {
+        self.advance();
+
+        let no_field = || {
+            let e = message!(
+                P002,
+                "Expected a field to bind in pattern '{}', like {} {{ x }}",
+                name,
+                name
+            );
+            ParseError::new(e)
+        };
+
+        let mut fields: Vec<(String, String)> = vec![];
+        let mut rest = false;
+        while !self.consume_opt_token_type(Token::CloseBrace) {
+            if rest {
+                let e = message!(P002, "Expected '}}' after '..' in pattern '{}'", name);
+                return Err(ParseError::new(e));
+            }
+
+            match self.tokens.peek() {
+                Some(Ok(Token::DotDot)) => {
+                    rest = true;
+                    self.advance();
+                }
+                Some(Ok(Token::Ident(field))) => {
+                    let field = field.to_owned();
+                    self.advance();
+
+                    // x binds the field to a name of its own, x: b to b
+                    let bind = match self.consume_opt_token_type(Token::Colon) {
+                        true => match self.tokens.peek() {
+                            Some(Ok(Token::Ident(bind))) => {
+                                let bind = bind.to_owned();
+                                self.advance();
+                                bind
+                            }
+                            _ => {
+                                let e = message!(
+                                    P002,
+                                    "Expected a name to bind field '{}' to in pattern '{}'",
+                                    field,
+                                    name
+                                );
+                                return Err(ParseError::new(e));
+                            }
+                        },
+                        false => field.to_owned(),
+                    };
+                    fields.push((field, bind));
+                }
+                _ => return Err(no_field()),
+            }
+
+            if !self.is_peek_token_type(Token::CloseBrace) {
+                self.consume_token_type(
+                    Token::Comma,
+                    "Expected ',' to separate the fields bound by a pattern",
+                )?;
+            }
+        }
+
+        if fields.is_empty() && !rest {
+            return Err(no_field());
+        }
+
+        Ok(Pattern::Struct { name, fields, rest })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{test_parse, test_parse_err};
+
+    #[test]
+    fn test_parse_match() {
+        let t = r"
+        match x {
+            1 => 10,
+            -2 => { 20 }
+            _ => 30,
+        }
+        ";
+        test_parse(t, "match x { 1 => 10, -2 => { 20 }, _ => 30 }");
+
+        test_parse(
+            "let y = match x + 1 { true => f(1), false => { g(); 2 } };",
+            "let y = match (x+1) { true => f(1), false => { g();2 } };",
+        );
+
+        // a statement in the middle of a block, like if
+        let t = r"
+        match x {
+            1 => { println(1); }
+            _ => {}
+        }
+        x
+        ";
+        test_parse(t, "match x { 1 => { println(1); }, _ => {  } };x");
+
+        test_parse(
+            "match match x { _ => 1 } { _ => 2 }",
+            "match match x { _ => 1 } { _ => 2 }",
+        );
+    }
+
+    #[test]
+    fn test_parse_match_variant() {
+        let t = r"
+        match atoi(s) {
+            Ok(n) => Some(n),
+            Err(_) => None,
+        }
+        ";
+        test_parse(t, "match atoi(s) { Ok(n) => Some(n), Err(_) => None }");
+        test_parse(
+            "match x { Pair(a, b) => a, Unit() => 0 }",
+            "match x { Pair(a, b) => a, Unit => 0 }",
+        );
+    }
+
+    #[test]
+    fn test_parse_match_or_range() {
+        let t = r"
+        match x {
+            1 | 2 | -3 => 10,
+            0..10 => 20,
+            -5..=-1 | 100 => 30,
+            _ => 40,
+        }
+        ";
+        test_parse(
+            t,
+            "match x { 1 | 2 | -3 => 10, 0..10 => 20, -5..=-1 | 100 => 30, _ => 40 }",
+        );
+        test_parse(
+            "match x { Some(_) | None => 1 }",
+            "match x { Some(_) | None => 1 }",
+        );
+        test_parse_err(
+            "match x { 0..= => 1 }",
+            "Expected an int to end the range pattern '0..='",
+            true,
+        );
+        test_parse_err(
+            "match x { 1 | => 1 }",
+            "Expected int, bool, variant, struct or '_' pattern but got '=>'",
+            true,
+        );
+    }
+
+    #[test]
+    fn test_parse_match_guard() {
+        let t = r"
+        match x {
+            Some(n) if n > 0 && n < 10 => n,
+            0 if f() => { 1 }
+            _ => 0,
+        }
+        ";
+        test_parse(
+            t,
+            "match x { Some(n) if ((n>0)&&(n<10)) => n, 0 if f() => { 1 }, _ => 0 }",
+        );
+        test_parse_err(
+            "match x { _ if x > 0, 1 }",
+            "Expected '=>' after guard '(x>0)' of pattern '_'",
+            true,
+        );
+    }
+
+    #[test]
+    fn test_parse_match_struct() {
+        let t = r"
+        match p {
+            Point { x, y: b } => x + b,
+        }
+        ";
+        test_parse(t, "match p { Point { x, y: b } => (x+b) }");
+        test_parse(
+            "match p { Point { x: _, .. } => 1, P {..} => 2 }",
+            "match p { Point { x: _, .. } => 1, P { .. } => 2 }",
+        );
+        test_parse("match p { P { x, } => x }", "match p { P { x } => x }");
+    }
+
+    #[test]
+    fn test_parse_match_struct_errs() {
+        test_parse_err(
+            "match p { P {} => 1 }",
+            "Expected a field to bind in pattern 'P', like P { x }",
+            true,
+        );
+        test_parse_err(
+            "match p { P { x: } => 1 }",
+            "Expected a name to bind field 'x' to in pattern 'P'",
+            true,
+        );
+        test_parse_err(
+            "match p { P { .., x } => 1 }",
+            "Expected '}' after '..' in pattern 'P'",
+            true,
+        );
+        test_parse_err(
+            "match p { P { x y } => 1 }",
+            "Expected ',' to separate the fields bound by a pattern",
+            true,
+        );
+    }
+
+    #[test]
+    fn test_parse_match_errs() {
+        test_parse_err("match x; 1 => 2", "Expected { for match arms", true);
+        test_parse_err("match x { 1 2 }", "Expected '=>' after pattern '1'", true);
+        test_parse_err(
+            "match x { 1 => 2; _ => 3 }",
+            "Expected ',' to separate match arms",
+            true,
+        );
+        test_parse_err(
+            "match x { 2.5 => 1 }",
+            "Expected int, bool, variant, struct or '_' pattern but got '2.5'",
+            true,
+        );
+        test_parse_err(
+            "match x { y => 1 }",
+            "Expected int, bool, variant, struct or '_' pattern but got 'y'",
+            true,
+        );
+        test_parse_err("match x {}", "match must have at least one arm", true);
+        test_parse_err(
+            "match x { Some(1) => 1 }",
+            "Expected a name to bind in pattern 'Some', like Some(x)",
+            true,
+        );
+        test_parse_err(
+            "match x { 1 => let y = 2; }",
+            "Unexpected token - not an expression: 'let'",
+            true,
+        );
+    }
+}
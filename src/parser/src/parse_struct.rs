@@ -0,0 +1,246 @@
+use diagnostics::message;
+use lexer::Token;
+
+use crate::Decl;
+use crate::Expr;
+use crate::FieldAssignData;
+use crate::ParseError;
+use crate::Parser;
+use crate::StructDeclData;
+use crate::StructExprData;
+use crate::Type;
+
+impl Parser {
+    // struct Point { x: int, y: float }
+    // Invariant: prev_tok is struct
+    pub(crate) fn parse_struct_decl(&mut self) -> Result<Decl, ParseError> {
+        let Some(Ok(Token::Ident(name))) = self.tokens.peek() else {
+            return Err(ParseError::new(message!(
+                P002,
+                "Expected struct name after 'struct'"
+            )));
+        };
+        let name = name.to_owned();
+        self.advance();
+
+        self.consume_token_type(Token::OpenBrace, "Expected { for struct declaration")?;
+
+        let mut fields: Vec<(String, Type)> = vec![];
+        while !self.is_peek_token_type(Token::CloseBrace) {
+            let Some(Ok(Token::Ident(field))) = self.tokens.peek() else {
+                let e = message!(P002, "Expected field name in struct '{}'", name);
+                return Err(ParseError::new(e));
+            };
+            let field = field.to_owned();
+            self.advance();
+
+            if !self.is_peek_token_type(Token::Colon) {
+                let e = message!(P002, "Expected ':' and a type after field '{}'", field);
+                return Err(ParseError::new(e));
+            }
+            self.advance();
+            let ty = self.parse_type_annotation()?;
+            fields.push((field, ty));
+
+            if !self.is_peek_token_type(Token::CloseBrace) {
+                self.consume_token_type(Token::Comma, "Expected ',' to separate struct fields")?;
+            }
+        }
+
+        self.consume_token_type(Token::CloseBrace, "Expected '}'")?;
+
+        // an empty literal would look like a block e.g if x {}
+        if fields.is_empty() {
+            let e = message!(P002, "Struct '{}' must have at least one field", name);
+            return Err(ParseError::new(e));
+        }
+
+        Ok(Decl::StructDeclStmt(StructDeclData {
+            name,
+            fields,
+            doc: None,
+        }))
+    }
+
+    // Whether an ident followed by the peeked tokens starts a struct literal. Needs { field: so that blocks after
+    // an ident still parse e.g if x { y }
+    pub(crate) fn is_struct_expr_start(&self) -> bool {
+        matches!(
+            (
+                self.tokens.peek(),
+                self.tokens.peek_nth(1),
+                self.tokens.peek_nth(2)
+            ),
+            (
+                Some(Ok(Token::OpenBrace)),
+                Some(Ok(Token::Ident(_))),
+                Some(Ok(Token::Colon))
+            )
+        )
+    }
+
+    // Point { x: 1, y: 2.0 }
+    // Invariant: prev_tok is the struct name and peek is the opening brace
+    pub(crate) fn parse_struct_expr(&mut self, name: String) -> Result<Decl, ParseError> {
+        self.advance(); // go past {
+
+        let mut fields: Vec<(String, Expr)> = vec![];
+        while !self.is_peek_token_type(Token::CloseBrace) {
+            let Some(Ok(Token::Ident(field))) = self.tokens.peek() else {
+                let e = message!(P002, "Expected field name in '{}' literal", name);
+                return Err(ParseError::new(e));
+            };
+            let field = field.to_owned();
+            self.advance();
+
+            if !self.is_peek_token_type(Token::Colon) {
+                let e = message!(P002, "Expected ':' and a value after field '{}'", field);
+                return Err(ParseError::new(e));
+            }
+            self.advance();
+            self.advance();
+            let expr = self.parse_expr(0)?.to_expr()?;
+            fields.push((field, expr));
+
+            if !self.is_peek_token_type(Token::CloseBrace) {
+                self.consume_token_type(Token::Comma, "Expected ',' to separate struct fields")?;
+            }
+        }
+
+        self.consume_token_type(Token::CloseBrace, "Expected '}'")?;
+
+        Ok(Decl::ExprStmt(Expr::StructExpr(StructExprData {
+            name,
+            fields,
+        })))
+    }
+
+    // Access a field of obj, or assign to it if an '=' follows e.g p.x = 2
+    // Invariant: peek is the dot
+    pub(crate) fn parse_field(&mut self, obj: Decl) -> Result<Decl, ParseError> {
+        let obj = obj.to_expr()?;
+
+        self.advance(); // go past .
+        let Some(Ok(Token::Ident(field))) = self.tokens.peek() else {
+            return Err(ParseError::new(message!(
+                P002,
+                "Expected field name after '.'"
+            )));
+        };
+        let field = field.to_owned();
+        self.advance();
+
+        if self.consume_opt_token_type(Token::Eq) {
+            self.advance();
+            let expr = self.parse_expr(0)?.to_expr()?;
+            let assign = FieldAssignData { obj, field, expr };
+            return Ok(Decl::FieldAssignStmt(assign));
+        }
+
+        Ok(Decl::ExprStmt(Expr::FieldAccessExpr(Box::new(obj), field)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{test_parse, test_parse_err};
+
+    #[test]
+    fn test_parse_struct_decl() {
+        let t = r"
+        struct Point {
+            x: int,
+            y: float,
+        }
+        struct Line { start: Point, end: Point }
+        ";
+        test_parse(
+            t,
+            "struct Point { x: int, y: float };struct Line { start: Point, end: Point };",
+        );
+
+        test_parse(
+            "struct P { xs: [int; 2], f: fn(int) -> int }",
+            "struct P { xs: [int; 2], f: fn(int) -> int };",
+        );
+
+        test_parse_err(
+            "struct { x: int }",
+            "Expected struct name after 'struct'",
+            true,
+        );
+        test_parse_err("struct P x: int", "Expected { for struct declaration", true);
+        test_parse_err(
+            "struct P { x }",
+            "Expected ':' and a type after field 'x'",
+            true,
+        );
+        test_parse_err(
+            "struct P { x: int y: int }",
+            "Expected ',' to separate struct fields",
+            true,
+        );
+        test_parse_err(
+            "struct P { 2: int }",
+            "Expected field name in struct 'P'",
+            true,
+        );
+        test_parse_err(
+            "struct P {}",
+            "Struct 'P' must have at least one field",
+            true,
+        );
+        test_parse_err(
+            "let x = struct P { x: int };",
+            "Struct declaration is not an expression",
+            true,
+        );
+    }
+
+    #[test]
+    fn test_parse_struct_expr() {
+        test_parse("Point { x: 1, y: 2.0 }", "Point { x: 1, y: 2.0 }");
+        test_parse(
+            "let p = Point { x: 1 + 2, y: f(3), };",
+            "let p = Point { x: (1+2), y: f(3) };",
+        );
+        test_parse(
+            "Line { start: Point { x: 1 }, end: p }",
+            "Line { start: Point { x: 1 }, end: p }",
+        );
+
+        // a block after an ident is not a struct literal
+        test_parse("if x { y } else { z }", "if x { y } else { z }");
+        test_parse("loop x { y; }", "loop x { y; };");
+
+        test_parse_err(
+            "Point { x: 1, y }",
+            "Expected ':' and a value after field 'y'",
+            true,
+        );
+        test_parse_err(
+            "Point { x: 1, 2 }",
+            "Expected field name in 'Point' literal",
+            true,
+        );
+    }
+
+    #[test]
+    fn test_parse_field_access() {
+        test_parse("p.x", "p.x");
+        test_parse("p.x + q.y * 2", "(p.x+(q.y*2))");
+        test_parse("-p.x", "(-p.x)");
+        test_parse("line.start.x", "line.start.x");
+        test_parse("ps[0].x", "ps[0].x");
+        test_parse("p.xs[1]", "p.xs[1]");
+        test_parse("Point { x: 1 }.x", "Point { x: 1 }.x");
+        test_parse("if p.ok { 1 } else { 2 }", "if p.ok { 1 } else { 2 }");
+
+        test_parse("p.x = 2;", "p.x = 2;");
+        test_parse("line.start.x = p.x + 1;", "line.start.x = (p.x+1);");
+        test_parse("ps[0].x = 3;", "ps[0].x = 3;");
+
+        test_parse_err("p.(x)", "Expected field name after '.'", true);
+        test_parse_err("let y = p.x = 2;", "is not an expression", true);
+    }
+}
@@ -0,0 +1,157 @@
+use diagnostics::message;
+use lexer::Token;
+
+use crate::Decl;
+use crate::Expr;
+use crate::LetTupleData;
+use crate::ParseError;
+use crate::Parser;
+use crate::Type;
+
+impl Parser {
+    // (1, true) or (1,)
+    // Invariant: the first value was parsed and peek is the comma after it
+    pub(crate) fn parse_tuple(&mut self, first: Decl) -> Result<Decl, ParseError> {
+        let mut elems = vec![first.to_expr()?];
+        while self.consume_opt_token_type(Token::Comma) {
+            if self.is_peek_token_type(Token::CloseParen) {
+                break;
+            }
+
+            self.advance(); // put the first token of the value in prev_tok
+            elems.push(self.parse_expr(0)?.to_expr()?);
+        }
+
+        self.consume_token_type(Token::CloseParen, "Expected ',' or ')' in tuple")?;
+        Ok(Decl::ExprStmt(Expr::TupleExpr(elems)))
+    }
+
+    /// The lexer reads .0 as a float, so t.0 comes in as t then 0.0. A float right after the previous token with
+    /// no space in between is a tuple index instead.
+    pub(crate) fn is_peek_tuple_index(&mut self) -> bool {
+        let (Some(Ok(Token::Float(_))), Some(span), Some(prev)) = (
+            self.tokens.peek(),
+            self.tokens.peek_span(),
+            self.tokens.prev_span(),
+        ) else {
+            return false;
+        };
+
+        span.start == prev.end
+    }
+
+    // t.0, and t.0.1 since the lexer reads .0.1 as two floats
+    // Invariant: is_peek_tuple_index is true
+    pub(crate) fn parse_tuple_index(&mut self, tuple: Decl) -> Result<Decl, ParseError> {
+        let tuple = tuple.to_expr()?;
+        let (Some(Ok(Token::Float(val))), Some(span)) =
+            (self.tokens.peek(), self.tokens.peek_span())
+        else {
+            unreachable!("peek should be a tuple index");
+        };
+
+        // the float lost the digits as written, so .10 is 0.1 with two digits after the dot
+        let digits = span.end - span.start - 1;
+        let idx = (val * 10f64.powi(digits as i32)).round() as usize;
+        if idx.to_string().len() != digits {
+            return Err(ParseError::new(message!(
+                P002,
+                "Tuple index can't have leading zeros"
+            )));
+        }
+        self.advance();
+
+        Ok(Decl::ExprStmt(Expr::TupleIndexExpr(Box::new(tuple), idx)))
+    }
+
+    // let (a, b) = f();
+    // Invariant: prev_tok is let and peek is (
+    pub(crate) fn parse_let_tuple(&mut self) -> Result<Decl, ParseError> {
+        self.advance(); // go past (
+
+        let mut idents: Vec<String> = vec![];
+        while !self.is_peek_token_type(Token::CloseParen) {
+            crate::expect_token_body!(self.tokens.peek(), Ident, "identifier")?;
+            idents.push(Parser::string_from_ident(self.tokens.peek()));
+            self.advance();
+
+            if !self.is_peek_token_type(Token::CloseParen) {
+                self.consume_token_type(Token::Comma, "Expected ',' to separate the names bound")?;
+            }
+        }
+        self.consume_token_type(Token::CloseParen, "Expected ')'")?;
+
+        if idents.is_empty() {
+            return Err(ParseError::new(message!(
+                P002,
+                "Expected at least one name to bind in let (..)"
+            )));
+        }
+
+        let mut type_ann: Option<Type> = None;
+        if self.consume_opt_token_type(Token::Colon) {
+            type_ann.replace(self.parse_type_annotation()?);
+        }
+
+        self.consume_token_type(Token::Eq, "Expected '='")?;
+        self.advance(); // store the start tok of the next expr as prev_tok
+
+        let expr = self.parse_decl()?.to_expr()?;
+        self.expect_token_type(Token::Semi, "Expected semicolon after let")?;
+
+        Ok(Decl::LetTupleStmt(LetTupleData {
+            idents,
+            expr,
+            type_ann,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{test_parse, test_parse_err};
+
+    #[test]
+    fn test_parse_tuple() {
+        test_parse("(1, true)", "(1,true)");
+        test_parse("(1, true,)", "(1,true)");
+        test_parse("(2,)", "(2,)");
+        // parentheses around one value are not a tuple
+        test_parse("(2)", "2");
+        test_parse("((1, 2), [3])", "((1,2),[3])");
+        test_parse("f((1, 2), 3)", "f((1,2),3)");
+
+        test_parse("t.0", "t.0");
+        test_parse("t.1 + 2", "(t.1+2)");
+        test_parse("t.10", "t.10");
+        test_parse("t.0.1", "t.0.1");
+        test_parse("f().1", "f().1");
+        test_parse("(1, 2).1", "(1,2).1");
+        test_parse("-t.0", "(-t.0)");
+        test_parse("t.0.x", "t.0.x");
+        test_parse("xs[0].1", "xs[0].1");
+
+        test_parse_err("(1, 2", "Expected ',' or ')' in tuple", true);
+        test_parse_err("t.01", "Tuple index can't have leading zeros", true);
+    }
+
+    #[test]
+    fn test_parse_let_tuple() {
+        test_parse("let (a, b) = f();", "let (a, b) = f();");
+        test_parse("let (a,) = (1,);", "let (a,) = (1,);");
+        test_parse("let (_, b,) = (1, 2);", "let (_, b) = (1,2);");
+        test_parse(
+            "let (a, b) : (int, bool) = (1, true);",
+            "let (a, b) : (int, bool) = (1,true);",
+        );
+
+        test_parse_err("let () = f();", "Expected at least one name to bind", true);
+        test_parse_err(
+            "let (a b) = f();",
+            "Expected ',' to separate the names bound",
+            true,
+        );
+        test_parse_err("let (a, 2) = f();", "Expected identifier", true);
+        test_parse_err("let (a, b) f();", "Expected '='", true);
+    }
+}
@@ -4,37 +4,175 @@ use crate::Parser;
 use crate::Type;
 use lexer::Token;
 
-impl<'inp> Parser<'inp> {
+impl Parser {
     /// Parse and return type annotation. Expect lexer.peek() to be at Colon before call
     // Should only consume tokens belonging to the annotation, starting peek at first token and ending
     // peek at token AFTER the last token of type annotation
     pub(crate) fn parse_type_annotation(&mut self) -> Result<Type, ParseError> {
         // self.consume_token_type(Token::Colon, "Expected a colon")?;
-        // expect_token_body!(self.lexer.peek(), Ident, "identifier")?;
-        Parser::expect_token_for_type_ann(self.lexer.peek())?;
+        // expect_token_body!(self.tokens.peek(), Ident, "identifier")?;
+        Parser::expect_token_for_type_ann(self.tokens.peek())?;
 
         // if ident, get the string and try to convert type. else, handle specially
         let peek = self
-            .lexer
+            .tokens
             .peek()
             .unwrap()
             .to_owned()
             .expect("Lexer should not fail"); // would have erred earlier
 
         let type_ann = match peek {
+            // chan[int]
+            Token::Ident(id)
+                if id == "chan" && self.tokens.peek_nth(1) == Some(&Ok(Token::OpenBracket)) =>
+            {
+                self.advance(); // go past chan
+                self.advance(); // go past [
+                let elem_ty = self.parse_type_annotation()?;
+                self.consume_token_type(
+                    Token::CloseBracket,
+                    "Expected ']' to close channel type annotation",
+                )?;
+
+                Ok(Type::Channel(Box::new(elem_ty)))
+            }
+            // pvec[int]
+            Token::Ident(id)
+                if id == "pvec" && self.tokens.peek_nth(1) == Some(&Ok(Token::OpenBracket)) =>
+            {
+                self.advance(); // go past pvec
+                self.advance(); // go past [
+                let elem_ty = self.parse_type_annotation()?;
+                self.consume_token_type(
+                    Token::CloseBracket,
+                    "Expected ']' to close pvec type annotation",
+                )?;
+
+                Ok(Type::PVec(Box::new(elem_ty)))
+            }
+            // pmap[str, int]
+            Token::Ident(id)
+                if id == "pmap" && self.tokens.peek_nth(1) == Some(&Ok(Token::OpenBracket)) =>
+            {
+                self.advance(); // go past pmap
+                self.advance(); // go past [
+                let key_ty = self.parse_type_annotation()?;
+                self.consume_token_type(
+                    Token::Comma,
+                    "Expected ',' between key and value types of pmap type annotation",
+                )?;
+                let val_ty = self.parse_type_annotation()?;
+                self.consume_token_type(
+                    Token::CloseBracket,
+                    "Expected ']' to close pmap type annotation",
+                )?;
+
+                Ok(Type::PMap(Box::new(key_ty), Box::new(val_ty)))
+            }
+            // map[str, int]
+            Token::Ident(id)
+                if id == "map" && self.tokens.peek_nth(1) == Some(&Ok(Token::OpenBracket)) =>
+            {
+                self.advance(); // go past map
+                self.advance(); // go past [
+                let key_ty = self.parse_type_annotation()?;
+                self.consume_token_type(
+                    Token::Comma,
+                    "Expected ',' between key and value types of map type annotation",
+                )?;
+                let val_ty = self.parse_type_annotation()?;
+                self.consume_token_type(
+                    Token::CloseBracket,
+                    "Expected ']' to close map type annotation",
+                )?;
+
+                Ok(Type::Map(Box::new(key_ty), Box::new(val_ty)))
+            }
+            // option[int]
+            Token::Ident(id)
+                if id == "option" && self.tokens.peek_nth(1) == Some(&Ok(Token::OpenBracket)) =>
+            {
+                self.advance(); // go past option
+                self.advance(); // go past [
+                let some_ty = self.parse_type_annotation()?;
+                self.consume_token_type(
+                    Token::CloseBracket,
+                    "Expected ']' to close option type annotation",
+                )?;
+
+                Ok(Type::Option(Box::new(some_ty)))
+            }
+            // result[int, str]
+            Token::Ident(id)
+                if id == "result" && self.tokens.peek_nth(1) == Some(&Ok(Token::OpenBracket)) =>
+            {
+                self.advance(); // go past result
+                self.advance(); // go past [
+                let ok_ty = self.parse_type_annotation()?;
+                self.consume_token_type(
+                    Token::Comma,
+                    "Expected ',' between ok and err types of result type annotation",
+                )?;
+                let err_ty = self.parse_type_annotation()?;
+                self.consume_token_type(
+                    Token::CloseBracket,
+                    "Expected ']' to close result type annotation",
+                )?;
+
+                Ok(Type::Result(Box::new(ok_ty), Box::new(err_ty)))
+            }
+            // any other name is a struct, which the type checker resolves
             Token::Ident(id) => {
-                let res = Type::from_string(&id);
+                let res = Type::from_string(&id).unwrap_or(Type::Struct(id));
                 self.advance();
-                res
+                Ok(res)
             }
+            // () or a tuple like (int, bool) or (int,)
             Token::OpenParen => {
                 self.advance();
-                if let Some(Ok(Token::CloseParen)) = self.lexer.peek() {
-                    self.advance();
-                    Ok(Type::Unit)
-                } else {
-                    Err(ParseError::new("Expected '()' for unit type annotation"))
+                if self.consume_opt_token_type(Token::CloseParen) {
+                    return Ok(Type::Unit);
+                }
+
+                let mut elem_tys = vec![self.parse_type_annotation()?];
+                let mut is_tuple = false;
+                while self.consume_opt_token_type(Token::Comma) {
+                    is_tuple = true;
+                    if self.is_peek_token_type(Token::CloseParen) {
+                        break;
+                    }
+                    elem_tys.push(self.parse_type_annotation()?);
+                }
+                self.consume_token_type(
+                    Token::CloseParen,
+                    "Expected ',' or ')' in tuple type annotation",
+                )?;
+
+                // (int) is just int, like (2) is just 2
+                if !is_tuple {
+                    return Ok(elem_tys.remove(0));
                 }
+                Ok(Type::Tuple(elem_tys))
+            }
+            // [int; 4] or [int]
+            Token::OpenBracket => {
+                self.advance(); // go past [
+                let elem_ty = self.parse_type_annotation()?;
+                if self.consume_opt_token_type(Token::CloseBracket) {
+                    return Ok(Type::Slice(Box::new(elem_ty)));
+                }
+
+                self.consume_token_type(
+                    Token::Semi,
+                    "Expected ';' and a length, or ']' for array type annotation",
+                )?;
+                let len = self.parse_array_len()?;
+                self.consume_token_type(
+                    Token::CloseBracket,
+                    "Expected ']' to close array type annotation",
+                )?;
+
+                Ok(Type::Array(Box::new(elem_ty), len))
             }
             Token::Fn => {
                 self.advance(); // go past fn
@@ -47,10 +185,9 @@ impl<'inp> Parser<'inp> {
                 let mut ret_ty = Type::Unit;
 
                 // Parse param types
-                while let Some(tok) = self.lexer.peek() {
-                    let tok = tok.clone();
+                while self.tokens.peek().is_some() {
                     // stop at )
-                    if tok.clone().unwrap().eq(&Token::CloseParen) {
+                    if self.is_peek_token_type(Token::CloseParen) {
                         break;
                     }
 
@@ -59,7 +196,7 @@ impl<'inp> Parser<'inp> {
                     // self.advance(); // go past token of last ty_an
 
                     // Comma or CloseParen
-                    if !self.lexer.peek().eq(&Some(&Ok(Token::CloseParen))) {
+                    if !self.tokens.peek().eq(&Some(&Ok(Token::CloseParen))) {
                         self.consume_token_type(
                             Token::Comma,
                             "Expected ',' to separate function parameters",
@@ -67,7 +204,7 @@ impl<'inp> Parser<'inp> {
                     }
                 }
 
-                // dbg!("PEEK AFTER LOOP:", &self.lexer.peek());
+                // dbg!("PEEK AFTER LOOP:", &self.tokens.peek());
 
                 self.advance(); // skip past open paren, peek is at return arrow or equals
 
@@ -105,6 +242,31 @@ mod tests {
         test_parse("let x : () = true;", "let x : () = true;");
         test_parse(r"let x : str = 2;", "let x : str = 2;");
         test_parse("let x : sem = 2;", "let x : sem = 2;");
+        test_parse("let c : chan[int] = chan();", "let c : chan[int] = chan();");
+        test_parse(
+            "let c : chan[[chan[bool]; 2]] = chan();",
+            "let c : chan[[chan[bool]; 2]] = chan();",
+        );
+        // only a channel when followed by [
+        test_parse("let c : chan = 2;", "let c : chan = 2;");
+        test_parse("let v : pvec[int] = pvec();", "let v : pvec[int] = pvec();");
+        test_parse(
+            "let m : pmap[str, pvec[bool]] = pmap();",
+            "let m : pmap[str, pvec[bool]] = pmap();",
+        );
+        test_parse(
+            "let m : map[int, [str]] = #{};",
+            "let m : map[int, [str]] = #{};",
+        );
+        test_parse(
+            "let r : result[option[int], str] = Ok(None);",
+            "let r : result[option[int], str] = Ok(None);",
+        );
+        test_parse(
+            "let t : (int, [bool; 2], (str,)) = f();",
+            "let t : (int, [bool; 2], (str,)) = f();",
+        );
+        test_parse("let t : (int) = 2;", "let t : int = 2;");
     }
 
     #[test]
@@ -127,7 +289,27 @@ mod tests {
         );
         test_parse_err(
             "let x : (2 ",
-            "Expected '()' for unit type annotation",
+            "Expected identifier or '(' for type annotation, got '2'",
+            true,
+        );
+        test_parse_err(
+            "let x : (int bool) = (1, true);",
+            "Expected ',' or ')' in tuple type annotation",
+            true,
+        );
+        test_parse_err(
+            "let x : chan[int = 2;",
+            "Expected ']' to close channel type annotation",
+            true,
+        );
+        test_parse_err(
+            "let m : pmap[str int] = pmap();",
+            "Expected ',' between key and value types of pmap type annotation",
+            true,
+        );
+        test_parse_err(
+            "let r : result[int] = Ok(1);",
+            "Expected ',' between ok and err types of result type annotation",
             true,
         );
     }
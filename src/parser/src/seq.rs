@@ -1,31 +1,43 @@
+use crate::Attribute;
 use crate::BlockSeq;
 use crate::Decl;
 use crate::Expr;
 use crate::ParseError;
 use crate::Parser;
-use lexer::Token;
+use diagnostics::message;
+use lexer::{Span, Token};
 use std::rc::Rc;
 
-impl<'inp> Parser<'inp> {
+impl Parser {
+    // A declaration that fails to parse is recorded and skipped, so the rest of the sequence is still parsed
+    // and its errors reported too
     pub(crate) fn parse_seq(&mut self) -> Result<BlockSeq, ParseError> {
         let mut decls: Vec<Decl> = vec![];
         let mut symbols: Vec<String> = vec![];
+        let mut spans: Vec<Span> = vec![];
         let mut last_expr: Option<Expr> = None;
 
-        while self.lexer.peek().is_some() {
+        while self.tokens.peek().is_some() {
             // parsing a block: break so parse_blk can consume CloseBrace
             if self.is_peek_token_type(Token::CloseBrace) {
                 break;
             }
 
-            self.advance();
-            // dbg!("prev_tok:", &self.prev_tok);
-
-            let expr = self.parse_decl()?;
+            let flags = (self.is_loop, self.is_fn, self.is_macro);
+            let (expr, span) = match self.parse_seq_decl() {
+                Ok(res) => res,
+                Err(e) => {
+                    self.record_err(e);
+                    // the flags of whatever the declaration was inside of may not have been restored
+                    (self.is_loop, self.is_fn, self.is_macro) = flags;
+                    self.synchronize();
+                    continue;
+                }
+            };
 
             // Include function names in list of symbols to be used for ENTERSCOPE
             if let Decl::FnDeclStmt(ref data) = expr {
-                symbols.push(data.name.to_owned());
+                Parser::push_symbol(&mut symbols, &data.name);
             }
 
             // if ends with semicolon: statement, advance past semi
@@ -34,20 +46,26 @@ impl<'inp> Parser<'inp> {
 
                 // push declared symbols from let or fn declarations so that they can be put in ENTERSCOPE
                 if let Decl::LetStmt(ref stmt) = expr {
-                    symbols.push(stmt.ident.to_owned());
+                    Parser::push_symbol(&mut symbols, &stmt.ident);
+                }
+                if let Decl::LetTupleStmt(ref stmt) = expr {
+                    for ident in stmt.idents.iter().filter(|ident| *ident != "_") {
+                        Parser::push_symbol(&mut symbols, ident);
+                    }
                 }
 
                 decls.push(expr);
+                spans.push(span);
 
                 self.advance();
                 continue;
-                // dbg!("Peek after semi:", &self.lexer.peek());
-            } else if self.lexer.peek().is_none() || self.is_peek_token_type(Token::CloseBrace) {
+                // dbg!("Peek after semi:", &self.tokens.peek());
+            } else if self.tokens.peek().is_none() || self.is_peek_token_type(Token::CloseBrace) {
                 // reached end of block / program: treat as last_expr, UNLESS it can't be converted to expr
                 // e.g: if with no else, fn decl - these are handled in the next branch (which also handles them when not at last)
-                let to_expr = expr.to_expr();
-                if to_expr.is_ok() {
-                    last_expr.replace(to_expr?);
+                if let Decl::ExprStmt(expr) = expr {
+                    last_expr.replace(expr);
+                    spans.push(span);
                     break;
                 }
             }
@@ -61,10 +79,11 @@ impl<'inp> Parser<'inp> {
                 .unwrap_or(false)
             {
                 decls.push(expr);
+                spans.push(span);
             }
-            // Syntax error
+            // Syntax error. The declaration itself is whole, so carry on from the next one without skipping
             else {
-                return Err(ParseError::new("Expected semicolon"));
+                self.record_err(ParseError::new(message!(P002, "Expected semicolon")));
             }
         }
         // dbg!(&last_expr, &decls);
@@ -72,6 +91,231 @@ impl<'inp> Parser<'inp> {
             decls,
             last_expr: last_expr.map(Rc::new),
             symbols,
+            spans,
         })
     }
+
+    // One declaration with its doc comments and attributes, and where it is in the source
+    fn parse_seq_decl(&mut self) -> Result<(Decl, Span), ParseError> {
+        let (doc, attrs) = self.parse_decl_prefix()?;
+        if (doc.is_some() || !attrs.is_empty())
+            && (self.tokens.peek().is_none() || self.is_peek_token_type(Token::CloseBrace))
+        {
+            return Err(ParseError::new(message!(
+                P009,
+                "Expected a declaration after doc comment or attribute"
+            )));
+        }
+
+        self.advance();
+        // dbg!("prev_tok:", &self.prev_tok);
+        let start = self.tokens.prev_span().unwrap_or_default();
+
+        let mut expr = self.parse_decl()?;
+        let span = start.to(self.tokens.prev_span().unwrap_or(start));
+
+        if doc.is_some() || !attrs.is_empty() {
+            Parser::attach_decl_prefix(&mut expr, doc, attrs)?;
+        }
+
+        Ok((expr, span))
+    }
+
+    // Skip the rest of a declaration that failed to parse: up to and past the next ';', up to the '}' that
+    // closes the enclosing block, or past a block the skipped tokens opened (and an else or ';' after it).
+    // A ';' inside a skipped block doesn't stop the skipping
+    fn synchronize(&mut self) {
+        // the declaration got as far as its own ';' before failing
+        if self.prev_tok == Some(Token::Semi) {
+            return;
+        }
+
+        let mut depth = 0;
+        while let Some(tok) = self.tokens.peek() {
+            match tok {
+                Ok(Token::Semi) if depth == 0 => {
+                    self.advance();
+                    return;
+                }
+                Ok(Token::CloseBrace) if depth == 0 => return,
+                Ok(Token::CloseBrace) => {
+                    self.advance();
+                    depth -= 1;
+                    if depth == 0 && !self.is_peek_token_type(Token::Else) {
+                        self.consume_opt_token_type(Token::Semi);
+                        return;
+                    }
+                }
+                Ok(Token::OpenBrace) => {
+                    self.advance();
+                    depth += 1;
+                }
+                Ok(_) => self.advance(),
+                // a token the lexer couldn't read is skipped along with the rest
+                Err(_) => {
+                    self.tokens.advance();
+                }
+            }
+        }
+    }
+
+    // A name declared again in the same block (shadowing) keeps the position of its first declaration
+    fn push_symbol(symbols: &mut Vec<String>, sym: &str) {
+        if !symbols.iter().any(|s| s == sym) {
+            symbols.push(sym.to_owned());
+        }
+    }
+
+    // Doc comments and attributes before a declaration, in any order. A # before { starts a map literal instead. Doc lines are joined into one string
+    fn parse_decl_prefix(&mut self) -> Result<(Option<String>, Vec<Attribute>), ParseError> {
+        let mut lines: Vec<String> = vec![];
+        let mut attrs: Vec<Attribute> = vec![];
+
+        loop {
+            if let Some(Ok(Token::DocComment(line))) = self.tokens.peek() {
+                lines.push(line.to_owned());
+                self.advance();
+            } else if self.is_peek_token_type(Token::Pound)
+                && self.tokens.peek_nth(1) != Some(&Ok(Token::OpenBrace))
+            {
+                attrs.push(self.parse_attribute()?);
+            } else {
+                break;
+            }
+        }
+
+        let doc = if lines.is_empty() {
+            None
+        } else {
+            Some(lines.join("\n"))
+        };
+
+        Ok((doc, attrs))
+    }
+
+    fn attach_decl_prefix(
+        decl: &mut Decl,
+        doc: Option<String>,
+        attrs: Vec<Attribute>,
+    ) -> Result<(), ParseError> {
+        match decl {
+            Decl::FnDeclStmt(data) => {
+                data.doc = doc;
+                data.attrs = attrs;
+            }
+            Decl::LetStmt(data) => {
+                data.doc = doc;
+                data.attrs = attrs;
+            }
+            Decl::StructDeclStmt(data) if attrs.is_empty() => data.doc = doc,
+            Decl::EnumDeclStmt(data) if attrs.is_empty() => data.doc = doc,
+            Decl::StructDeclStmt(_) | Decl::EnumDeclStmt(_) => {
+                return Err(ParseError::new(message!(
+                    P009,
+                    "Attributes can only be attached to fn or let declarations"
+                )))
+            }
+            _ => {
+                return Err(ParseError::new(message!(
+                    P009,
+                    "Doc comments can only be attached to fn, let, struct or enum declarations, and attributes to fn or let declarations"
+                )))
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::test_parse_err;
+    use crate::{Decl, Parser};
+
+    fn parse_docs(inp: &str) -> Vec<Option<String>> {
+        let prog = Parser::new_from_string(inp).parse().expect("Should parse");
+        prog.decls
+            .iter()
+            .filter_map(|decl| match decl {
+                Decl::FnDeclStmt(data) => Some(data.doc.clone()),
+                Decl::LetStmt(data) => Some(data.doc.clone()),
+                Decl::StructDeclStmt(data) => Some(data.doc.clone()),
+                Decl::EnumDeclStmt(data) => Some(data.doc.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_doc_comments() {
+        let t = r"
+        /// Adds one.
+        ///
+        /// Works for negative numbers too.
+        fn inc(x: int) -> int {
+            x + 1
+        }
+
+        // plain comments are not docs
+        let y = 2;
+
+        /// The answer
+        let z = inc(41);
+
+        /// A point.
+        struct P { x: int }
+
+        /// Which way.
+        enum Dir { Up, Down }
+        ";
+        let exp = vec![
+            Some("Adds one.\n\nWorks for negative numbers too.".to_string()),
+            None,
+            Some("The answer".to_string()),
+            Some("A point.".to_string()),
+            Some("Which way.".to_string()),
+        ];
+        assert_eq!(parse_docs(t), exp);
+
+        // docs on nested declarations are kept as well
+        let t = r"
+        fn f() {
+            /// inner
+            let x = 2;
+        }
+        ";
+        let prog = Parser::new_from_string(t).parse().expect("Should parse");
+        let Some(Decl::FnDeclStmt(f)) = prog.decls.first() else {
+            panic!("Expected fn decl");
+        };
+        let Some(Decl::LetStmt(x)) = f.body.decls.first() else {
+            panic!("Expected let");
+        };
+        assert_eq!(x.doc, Some("inner".to_string()));
+    }
+
+    #[test]
+    fn test_parse_doc_comment_errs() {
+        test_parse_err(
+            "/// two\n2;",
+            "Doc comments can only be attached to fn, let, struct or enum declarations",
+            true,
+        );
+        test_parse_err(
+            "let x = 2;\n/// dangling",
+            "Expected a declaration after doc comment or attribute",
+            true,
+        );
+        test_parse_err(
+            "fn f() {\n/// dangling\n}",
+            "Expected a declaration after doc comment or attribute",
+            true,
+        );
+        test_parse_err(
+            "#[inline]\nstruct P { x: int }",
+            "Attributes can only be attached to fn or let declarations",
+            true,
+        );
+        test_parse_err("print(2, /// arg\n 3);", "not an expression: '///'", true);
+    }
 }
@@ -1,7 +1,8 @@
 use std::fmt::{Display, Formatter};
 use std::rc::Rc;
 
-use lexer::Token;
+use diagnostics::{message, Code, Message};
+use lexer::{Span, Token};
 
 #[derive(Debug, Clone)]
 pub enum BinOpType {
@@ -9,6 +10,7 @@ pub enum BinOpType {
     Sub,
     Mul,
     Div,
+    Mod,
     Gt,
     Lt,
     LogicalEq,
@@ -23,12 +25,14 @@ impl BinOpType {
             Token::Minus => Ok(Self::Sub),
             Token::Star => Ok(Self::Mul),
             Token::Slash => Ok(Self::Div),
+            Token::Percent => Ok(Self::Mod),
             Token::Gt => Ok(Self::Gt),
             Token::Lt => Ok(Self::Lt),
             Token::LogEq => Ok(Self::LogicalEq),
             Token::LogAnd => Ok(Self::LogicalAnd),
             Token::LogOr => Ok(Self::LogicalOr),
-            _ => Err(ParseError::new(&format!(
+            _ => Err(ParseError::new(message!(
+                P001,
                 "Expected infix operator but got: {}",
                 token
             ))),
@@ -43,6 +47,7 @@ impl Display for BinOpType {
             BinOpType::Sub => "-",
             BinOpType::Mul => "*",
             BinOpType::Div => "/",
+            BinOpType::Mod => "%",
             BinOpType::Lt => "<",
             BinOpType::Gt => ">",
             BinOpType::LogicalEq => "==",
@@ -88,6 +93,24 @@ impl Display for FnCallData {
     }
 }
 
+// spawn f(x) or spawn isolate f(x)
+#[derive(Debug, Clone)]
+pub struct SpawnData {
+    pub call: FnCallData,
+    // the thread runs in a copy of the spawning thread's environment, see the spawn docs
+    pub isolate: bool,
+}
+
+impl Display for SpawnData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.isolate {
+            write!(f, "spawn isolate {}", self.call)
+        } else {
+            write!(f, "spawn {}", self.call)
+        }
+    }
+}
+
 // Different from bytecode Value because values on op stack might be different (e.g fn call)
 #[derive(Debug, Clone)]
 pub enum Expr {
@@ -96,21 +119,55 @@ pub enum Expr {
     Float(f64),
     Bool(bool),
     StringLiteral(String),
+    // 'a'
+    Char(char),
     UnOpExpr(UnOpType, Box<Expr>),
     BinOpExpr(BinOpType, Box<Expr>, Box<Expr>),
     BlockExpr(BlockSeq), // expr can be a block
     IfElseExpr(Box<IfElseData>),
     FnCallExpr(FnCallData),
-    SpawnExpr(FnCallData),
+    SpawnExpr(SpawnData),
     // Because join can return something so must be able to assign to it
     // String is the symbol of the thread id to join
     JoinExpr(String),
+    // try_join(t) or join_timeout(t, ms) - true if the thread finished and was joined
+    TryJoinExpr(Box<TryJoinData>),
+    // [1, 2, 3]
+    ArrayExpr(Vec<Expr>),
+    // [0; 4] - length is const evaluated during parsing
+    ArrayFillExpr(Box<Expr>, usize),
+    // xs[i]
+    IndexExpr(Box<Expr>, Box<Expr>),
+    // xs[start..end] - a view over xs
+    SliceExpr(Box<SliceData>),
+    // Point { x: 1, y: 2.0 }
+    StructExpr(StructExprData),
+    // p.x
+    FieldAccessExpr(Box<Expr>, String),
+    // (1, true) - a tuple of one value is written (1,)
+    TupleExpr(Vec<Expr>),
+    // t.0
+    TupleIndexExpr(Box<Expr>, usize),
+    // #{"a": 1, "b": 2}
+    MapExpr(Vec<(Expr, Expr)>),
+    // swap!(x, y) - replaced by the macro body when macros are expanded
+    MacroCallExpr(FnCallData),
+    // match x { 1 => a, _ => b }
+    MatchExpr(Box<MatchData>),
+    // opt ?? default, what opt holds if it is Some and default if it is None
+    CoalesceExpr(Box<Expr>, Box<Expr>),
+    // lock m { a }
+    LockExpr(Box<LockData>),
+    // |x: int| x + 1
+    LambdaExpr(Box<LambdaData>),
 }
 
 impl Display for Expr {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let string = match self {
             Expr::Integer(val) => val.to_string(),
+            // 1.0 prints as 1, which would read back as an int
+            Expr::Float(val) if val.fract() == 0.0 => format!("{}.0", val),
             Expr::Float(val) => val.to_string(),
             Expr::Bool(val) => val.to_string(),
             Expr::UnOpExpr(op, expr) => {
@@ -124,9 +181,43 @@ impl Display for Expr {
             // Expr::BlockExpr(seq) => seq.to_string(),
             Expr::IfElseExpr(expr) => expr.to_string(),
             Expr::FnCallExpr(expr) => expr.to_string(),
-            Expr::SpawnExpr(expr) => format!("spawn {}", expr),
+            Expr::SpawnExpr(data) => data.to_string(),
             Expr::JoinExpr(sym) => format!("join {}", sym),
-            Expr::StringLiteral(str) => str.to_string(),
+            Expr::TryJoinExpr(data) => data.to_string(),
+            Expr::StringLiteral(str) => format!("{:?}", str),
+            Expr::Char(c) => format!("{:?}", c),
+            Expr::ArrayExpr(elems) => {
+                let elems: Vec<String> = elems.iter().map(|x| x.to_string()).collect();
+                format!("[{}]", elems.join(","))
+            }
+            Expr::ArrayFillExpr(val, len) => format!("[{}; {}]", val, len),
+            Expr::IndexExpr(arr, idx) => format!("{}[{}]", arr, idx),
+            Expr::SliceExpr(slice) => slice.to_string(),
+            Expr::StructExpr(data) => data.to_string(),
+            Expr::FieldAccessExpr(obj, field) => format!("{}.{}", obj, field),
+            Expr::TupleExpr(elems) => {
+                let elems: Vec<String> = elems.iter().map(|x| x.to_string()).collect();
+                match elems.as_slice() {
+                    [elem] => format!("({},)", elem),
+                    _ => format!("({})", elems.join(",")),
+                }
+            }
+            Expr::TupleIndexExpr(tuple, idx) => format!("{}.{}", tuple, idx),
+            Expr::MapExpr(entries) => {
+                let entries: Vec<String> = entries
+                    .iter()
+                    .map(|(key, val)| format!("{}: {}", key, val))
+                    .collect();
+                format!("#{{{}}}", entries.join(","))
+            }
+            Expr::MacroCallExpr(call) => {
+                let args: Vec<String> = call.args.iter().map(|x| x.to_string()).collect();
+                format!("{}!({})", call.name, args.join(","))
+            }
+            Expr::MatchExpr(data) => data.to_string(),
+            Expr::CoalesceExpr(opt, default) => format!("({}??{})", opt, default),
+            Expr::LockExpr(data) => data.to_string(),
+            Expr::LambdaExpr(data) => data.to_string(),
         };
 
         write!(f, "{}", string)
@@ -138,6 +229,17 @@ pub struct LetStmtData {
     pub ident: String,
     pub expr: Expr,
     pub type_ann: Option<Type>,
+    // text of the /// comments right before the let, if any
+    pub doc: Option<String>,
+    pub attrs: Vec<Attribute>,
+}
+
+// let (a, b) = f(); - '_' leaves a value out
+#[derive(Debug, Clone)]
+pub struct LetTupleData {
+    pub idents: Vec<String>,
+    pub expr: Expr,
+    pub type_ann: Option<Type>,
 }
 
 #[derive(Debug, Clone)]
@@ -146,6 +248,40 @@ pub struct AssignStmtData {
     pub expr: Expr,
 }
 
+// xs[start..end] or xs[start..=end], both bounds are optional for '..'
+#[derive(Debug, Clone)]
+pub struct SliceData {
+    pub arr: Expr,
+    pub start: Option<Expr>,
+    pub end: Option<Expr>,
+    pub inclusive: bool,
+}
+
+impl Display for SliceData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let start = self
+            .start
+            .as_ref()
+            .map(|x| x.to_string())
+            .unwrap_or_default();
+        let end = self.end.as_ref().map(|x| x.to_string()).unwrap_or_default();
+        let range = if self.inclusive {
+            Token::DotDotEq
+        } else {
+            Token::DotDot
+        };
+        write!(f, "{}[{}{}{}]", self.arr, start, range, end)
+    }
+}
+
+// xs[i] = expr
+#[derive(Debug, Clone)]
+pub struct IndexAssignData {
+    pub arr: Expr,
+    pub index: Expr,
+    pub expr: Expr,
+}
+
 impl Display for LetStmtData {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let string = if let Some(ty) = &self.type_ann {
@@ -158,12 +294,175 @@ impl Display for LetStmtData {
     }
 }
 
+impl Display for LetTupleData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let idents = match self.idents.as_slice() {
+            [ident] => format!("({},)", ident),
+            _ => format!("({})", self.idents.join(", ")),
+        };
+
+        if let Some(ty) = &self.type_ann {
+            write!(f, "let {} : {} = {}", idents, ty, self.expr)
+        } else {
+            write!(f, "let {} = {}", idents, self.expr)
+        }
+    }
+}
+
 impl Display for AssignStmtData {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{} = {}", self.ident, self.expr)
     }
 }
 
+impl Display for IndexAssignData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}[{}] = {}", self.arr, self.index, self.expr)
+    }
+}
+
+// What a match arm compares the matched value against
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern {
+    Int(i64),
+    // 0..10 or 0..=9, the ints between lo and hi, with hi only if inclusive
+    Range {
+        lo: i64,
+        hi: i64,
+        inclusive: bool,
+    },
+    Bool(bool),
+    // Some(x) or None - a variant with a name for each value it holds, bound in the body of the arm. '_' binds
+    // nothing
+    Variant(String, Vec<String>),
+    // Point { x, y: b, .. } - a struct with a name for each field listed, bound in the body of the arm. '..' leaves
+    // out the fields that aren't listed, which are otherwise all needed. Matches any value of the struct
+    Struct {
+        name: String,
+        fields: Vec<(String, String)>,
+        rest: bool,
+    },
+    // _ matches anything
+    Wildcard,
+    // 1 | 2 | 3 matches what any of the patterns does. They can't bind names, since only one of them matches
+    Or(Vec<Pattern>),
+}
+
+impl Pattern {
+    /// The smallest and largest int a range pattern matches, None if it matches none or isn't a range.
+    pub fn int_range(&self) -> Option<(i64, i64)> {
+        match *self {
+            Pattern::Range { lo, hi, inclusive } if inclusive && lo <= hi => Some((lo, hi)),
+            Pattern::Range { lo, hi, inclusive } if !inclusive && lo < hi => Some((lo, hi - 1)),
+            _ => None,
+        }
+    }
+
+    /// The names the pattern binds in the body of its arm, leaving out '_'.
+    pub fn binds(&self) -> Vec<&String> {
+        let binds: Vec<&String> = match self {
+            Pattern::Variant(_, binds) => binds.iter().collect(),
+            Pattern::Struct { fields, .. } => fields.iter().map(|(_, bind)| bind).collect(),
+            _ => vec![],
+        };
+        binds.into_iter().filter(|bind| *bind != "_").collect()
+    }
+}
+
+impl Display for Pattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Pattern::Int(val) => write!(f, "{}", val),
+            Pattern::Range { lo, hi, inclusive } => match inclusive {
+                true => write!(f, "{}..={}", lo, hi),
+                false => write!(f, "{}..{}", lo, hi),
+            },
+            Pattern::Bool(val) => write!(f, "{}", val),
+            Pattern::Variant(name, binds) if binds.is_empty() => write!(f, "{}", name),
+            Pattern::Variant(name, binds) => write!(f, "{}({})", name, binds.join(", ")),
+            Pattern::Struct { name, fields, rest } => {
+                let mut fields: Vec<String> = fields
+                    .iter()
+                    .map(|(field, bind)| match field == bind {
+                        true => field.to_string(),
+                        false => format!("{}: {}", field, bind),
+                    })
+                    .collect();
+                if *rest {
+                    fields.push("..".to_string());
+                }
+                write!(f, "{} {{ {} }}", name, fields.join(", "))
+            }
+            Pattern::Wildcard => write!(f, "_"),
+            Pattern::Or(alts) => {
+                let alts: Vec<String> = alts.iter().map(|alt| alt.to_string()).collect();
+                write!(f, "{}", alts.join(" | "))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MatchArm {
+    pub pat: Pattern,
+    // x if x > 0 => ..., tried after the pattern binds its names
+    pub guard: Option<Expr>,
+    pub body: Expr,
+}
+
+impl Display for MatchArm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.guard {
+            Some(guard) => write!(f, "{} if {} => {}", self.pat, guard, self.body),
+            None => write!(f, "{} => {}", self.pat, self.body),
+        }
+    }
+}
+
+// Arms are tried in order, the first arm whose pattern matches and whose guard is true is the value of the match
+#[derive(Debug, Clone)]
+pub struct MatchData {
+    pub subject: Expr,
+    pub arms: Vec<MatchArm>,
+}
+
+impl Display for MatchData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let arms: Vec<String> = self.arms.iter().map(|arm| arm.to_string()).collect();
+        write!(f, "match {} {{ {} }}", self.subject, arms.join(", "))
+    }
+}
+
+// The mutex is held while the body runs and released however the body is left, including break and return
+#[derive(Debug, Clone)]
+pub struct TryJoinData {
+    // symbol of the thread id to join
+    pub tid: String,
+    // how many ms to wait for the thread, None for try_join which doesn't wait
+    pub timeout: Option<Expr>,
+}
+
+impl Display for TryJoinData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.timeout {
+            Some(ms) => write!(f, "join_timeout({}, {})", self.tid, ms),
+            None => write!(f, "try_join({})", self.tid),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LockData {
+    pub mutex: Expr,
+    pub body: BlockSeq,
+}
+
+impl Display for LockData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "lock {} {{ {} }}", self.mutex, self.body)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct IfElseData {
     pub cond: Expr,
@@ -201,6 +500,145 @@ impl Display for LoopData {
     }
 }
 
+// for var in iter { body }
+#[derive(Debug, Clone)]
+pub struct ForData {
+    pub var: String,
+    pub iter: ForIter,
+    pub body: BlockSeq,
+}
+
+// What a for loop goes over: a range of ints, or the elements of an array or slice
+#[derive(Debug, Clone)]
+pub enum ForIter {
+    Range {
+        start: Expr,
+        end: Expr,
+        inclusive: bool,
+    },
+    Elems(Expr),
+}
+
+impl Display for ForData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let iter = match &self.iter {
+            ForIter::Range {
+                start,
+                end,
+                inclusive,
+            } => {
+                let range = if *inclusive {
+                    Token::DotDotEq
+                } else {
+                    Token::DotDot
+                };
+                format!("{}{}{}", start, range, end)
+            }
+            ForIter::Elems(expr) => expr.to_string(),
+        };
+        write!(f, "for {} in {} {{ {} }}", self.var, iter, self.body)
+    }
+}
+
+// struct Point { x: int, y: float }
+#[derive(Debug, Clone)]
+pub struct StructDeclData {
+    pub name: String,
+    pub fields: Vec<(String, Type)>,
+    // text of the /// comments right before the struct, if any
+    pub doc: Option<String>,
+}
+
+impl Display for StructDeclData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let fields: Vec<String> = self
+            .fields
+            .iter()
+            .map(|(name, ty)| format!("{}: {}", name, ty))
+            .collect();
+        write!(f, "struct {} {{ {} }}", self.name, fields.join(", "))
+    }
+}
+
+// enum Shape { Circle(float), Square(float), Empty } - variants are kept in the order written
+#[derive(Debug, Clone)]
+pub struct EnumDeclData {
+    pub name: String,
+    // the types of the values each variant holds, empty for a variant that holds nothing
+    pub variants: Vec<(String, Vec<Type>)>,
+    // text of the /// comments right before the enum, if any
+    pub doc: Option<String>,
+}
+
+impl Display for EnumDeclData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let variants: Vec<String> = self
+            .variants
+            .iter()
+            .map(|(name, payload)| {
+                if payload.is_empty() {
+                    return name.to_owned();
+                }
+                let payload: Vec<String> = payload.iter().map(|ty| ty.to_string()).collect();
+                format!("{}({})", name, payload.join(", "))
+            })
+            .collect();
+        write!(f, "enum {} {{ {} }}", self.name, variants.join(", "))
+    }
+}
+
+// Point { x: 1, y: 2.0 } - fields are kept in the order written, which is the order they are evaluated in
+#[derive(Debug, Clone)]
+pub struct StructExprData {
+    pub name: String,
+    pub fields: Vec<(String, Expr)>,
+}
+
+impl Display for StructExprData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let fields: Vec<String> = self
+            .fields
+            .iter()
+            .map(|(name, expr)| format!("{}: {}", name, expr))
+            .collect();
+        write!(f, "{} {{ {} }}", self.name, fields.join(", "))
+    }
+}
+
+// p.x = expr
+#[derive(Debug, Clone)]
+pub struct FieldAssignData {
+    pub obj: Expr,
+    pub field: String,
+    pub expr: Expr,
+}
+
+impl Display for FieldAssignData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{} = {}", self.obj, self.field, self.expr)
+    }
+}
+
+// macro swap($a, $b) { let t = $a; $a = $b; $b = t; } - params include the $
+#[derive(Debug, Clone)]
+pub struct MacroDeclData {
+    pub name: String,
+    pub params: Vec<String>,
+    pub body: BlockSeq,
+}
+
+impl Display for MacroDeclData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "macro {}({}) {{ {} }}",
+            self.name,
+            self.params.join(", "),
+            self.body
+        )
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 // function parameter
 pub struct FnParam {
@@ -220,13 +658,36 @@ impl Display for FnParam {
     }
 }
 
+// #[name] or #[name(arg, ...)] before a fn or let declaration. Args are kept as expressions so each attribute
+// decides what it accepts
+#[derive(Debug, Clone)]
+pub struct Attribute {
+    pub name: String,
+    pub args: Vec<Expr>,
+}
+
+impl Display for Attribute {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.args.is_empty() {
+            return write!(f, "#[{}]", self.name);
+        }
+
+        let args: Vec<String> = self.args.iter().map(|x| x.to_string()).collect();
+        write!(f, "#[{}({})]", self.name, args.join(","))
+    }
+}
+
 // Fn Decl
 #[derive(Debug, Clone)]
 pub struct FnDeclData {
     pub name: String,
     pub params: Vec<FnParam>,
-    pub ret_type: Type,
+    // None if there is no `-> T`, so the type checker infers it from the body
+    pub ret_type: Option<Type>,
     pub body: BlockSeq,
+    // text of the /// comments right before the fn, if any
+    pub doc: Option<String>,
+    pub attrs: Vec<Attribute>,
 }
 
 impl Display for FnDeclData {
@@ -234,10 +695,9 @@ impl Display for FnDeclData {
         let params: Vec<String> = self.params.iter().map(|x| x.to_string()).collect();
         let params = params.join(", ");
 
-        let ret_type_str = if self.ret_type.eq(&Type::Unit) {
-            " ".to_string()
-        } else {
-            format!(" -> {} ", self.ret_type)
+        let ret_type_str = match &self.ret_type {
+            Some(ret_type) if !ret_type.eq(&Type::Unit) => format!(" -> {} ", ret_type),
+            _ => " ".to_string(),
         };
 
         let s = format!(
@@ -248,17 +708,42 @@ impl Display for FnDeclData {
     }
 }
 
+// An anonymous fn. Without a return type annotation it returns the type of its body
+#[derive(Debug, Clone)]
+pub struct LambdaData {
+    pub params: Vec<FnParam>,
+    pub ret_type: Option<Type>,
+    pub body: Expr,
+}
+
+impl Display for LambdaData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let params: Vec<String> = self.params.iter().map(|x| x.to_string()).collect();
+        match &self.ret_type {
+            Some(ret_type) => write!(f, "|{}| -> {} {}", params.join(", "), ret_type, self.body),
+            None => write!(f, "|{}| {}", params.join(", "), self.body),
+        }
+    }
+}
+
 // Later: LetStmt, IfStmt, FnDef, etc.
 #[derive(Debug, Clone)]
 pub enum Decl {
     LetStmt(LetStmtData),
+    LetTupleStmt(LetTupleData),
     AssignStmt(AssignStmtData),
+    IndexAssignStmt(IndexAssignData),
+    FieldAssignStmt(FieldAssignData),
     ExprStmt(Expr),
     // if with no else should only be stmt. use same struct because compilation is very similar to if-else
     IfOnlyStmt(IfElseData),
     // loop is always a stmt (for now)
     LoopStmt(LoopData),
+    ForStmt(ForData),
     FnDeclStmt(FnDeclData),
+    StructDeclStmt(StructDeclData),
+    EnumDeclStmt(EnumDeclData),
+    MacroDeclStmt(MacroDeclData),
     // only inside loop
     BreakStmt,
     // only inside fn
@@ -272,40 +757,82 @@ pub enum Decl {
 }
 
 impl Decl {
-    // Need to clone so we can re-use in pratt parser loop
-    // Reasoning: parsing won't take most of the runtime
-    pub fn to_expr(&self) -> Result<Expr, ParseError> {
+    /// Consumes the Decl so the expression is moved out rather than cloned.
+    pub fn to_expr(self) -> Result<Expr, ParseError> {
         // Decls that return parse error will always be treated as statements
         match self {
-            Self::LetStmt(ref stmt) => {
-                Err(ParseError::new(&format!("'{}' is not an expression", stmt)))
-            }
-            Self::AssignStmt(ref stmt) => {
-                Err(ParseError::new(&format!("'{}' is not an expression", stmt)))
-            }
-            Self::IfOnlyStmt(_) => Err(ParseError::new(
-                "if without else branch is not an expression",
-            )),
-            Self::FnDeclStmt(_) => {
-                Err(ParseError::new("Function declaration is not an expression"))
-            }
-            Self::LoopStmt(_) => Err(ParseError::new("loop is not an expression")),
-            Self::BreakStmt => Err(ParseError::new("break is not an expression")),
-            Self::ReturnStmt(_) => Err(ParseError::new("return is not an expression")),
-            Self::WaitStmt(_) => Err(ParseError::new("wait is not an expression")),
-            Self::PostStmt(_) => Err(ParseError::new("post is not an expression")),
-            Self::YieldStmt => Err(ParseError::new("yield is not an expression")),
-            Self::ExprStmt(expr) => Ok(expr.clone()),
+            Self::LetStmt(ref stmt) => Err(ParseError::new(message!(
+                P003,
+                "'{}' is not an expression",
+                stmt
+            ))),
+            Self::LetTupleStmt(ref stmt) => Err(ParseError::new(message!(
+                P003,
+                "'{}' is not an expression",
+                stmt
+            ))),
+            Self::AssignStmt(ref stmt) => Err(ParseError::new(message!(
+                P003,
+                "'{}' is not an expression",
+                stmt
+            ))),
+            Self::IndexAssignStmt(ref stmt) => Err(ParseError::new(message!(
+                P003,
+                "'{}' is not an expression",
+                stmt
+            ))),
+            Self::FieldAssignStmt(ref stmt) => Err(ParseError::new(message!(
+                P003,
+                "'{}' is not an expression",
+                stmt
+            ))),
+            Self::IfOnlyStmt(_) => Err(ParseError::new(message!(
+                P003,
+                "if without else branch is not an expression"
+            ))),
+            Self::FnDeclStmt(_) => Err(ParseError::new(message!(
+                P003,
+                "Function declaration is not an expression"
+            ))),
+            Self::StructDeclStmt(_) => Err(ParseError::new(message!(
+                P003,
+                "Struct declaration is not an expression"
+            ))),
+            Self::EnumDeclStmt(_) => Err(ParseError::new(message!(
+                P003,
+                "Enum declaration is not an expression"
+            ))),
+            Self::MacroDeclStmt(_) => Err(ParseError::new(message!(
+                P003,
+                "Macro declaration is not an expression"
+            ))),
+            Self::LoopStmt(_) => Err(ParseError::new(message!(P003, "loop is not an expression"))),
+            Self::ForStmt(_) => Err(ParseError::new(message!(P003, "for is not an expression"))),
+            Self::BreakStmt => Err(ParseError::new(message!(
+                P003,
+                "break is not an expression"
+            ))),
+            Self::ReturnStmt(_) => Err(ParseError::new(message!(
+                P003,
+                "return is not an expression"
+            ))),
+            Self::WaitStmt(_) => Err(ParseError::new(message!(P003, "wait is not an expression"))),
+            Self::PostStmt(_) => Err(ParseError::new(message!(P003, "post is not an expression"))),
+            Self::YieldStmt => Err(ParseError::new(message!(
+                P003,
+                "yield is not an expression"
+            ))),
+            Self::ExprStmt(expr) => Ok(expr),
         }
     }
 
-    pub fn to_block(&self) -> Result<BlockSeq, ParseError> {
-        if let Self::ExprStmt(Expr::BlockExpr(seq)) = &self {
-            return Ok(seq.clone());
+    pub fn to_block(self) -> Result<BlockSeq, ParseError> {
+        if let Self::ExprStmt(Expr::BlockExpr(seq)) = self {
+            return Ok(seq);
         }
 
-        let e = format!("Expected block but got '{}'", self);
-        Err(ParseError::new(&e))
+        let e = message!(P002, "Expected block but got '{}'", self);
+        Err(ParseError::new(e))
     }
 
     /// Returns true if this Decl has to be treated as a stmt, but has no semicolon terminating
@@ -320,11 +847,18 @@ impl Display for Decl {
         let string = match self {
             Decl::ExprStmt(expr) => expr.to_string(),
             Decl::LetStmt(stmt) => stmt.to_string(),
+            Decl::LetTupleStmt(stmt) => stmt.to_string(),
             Decl::AssignStmt(stmt) => stmt.to_string(),
+            Decl::IndexAssignStmt(stmt) => stmt.to_string(),
+            Decl::FieldAssignStmt(stmt) => stmt.to_string(),
             Decl::IfOnlyStmt(expr) => expr.to_string(),
             Decl::LoopStmt(lp) => lp.to_string(),
+            Decl::ForStmt(lp) => lp.to_string(),
             Decl::BreakStmt => Token::Break.to_string(),
             Decl::FnDeclStmt(fn_decl) => fn_decl.to_string(),
+            Decl::StructDeclStmt(data) => data.to_string(),
+            Decl::EnumDeclStmt(data) => data.to_string(),
+            Decl::MacroDeclStmt(data) => data.to_string(),
             Decl::ReturnStmt(expr) => {
                 let str = expr
                     .clone()
@@ -352,8 +886,26 @@ impl Display for Decl {
 pub struct BlockSeq {
     pub decls: Vec<Decl>,
     pub last_expr: Option<Rc<Expr>>,
-    // List of top level uninitialised symbols (variable/func declarations)
+    // List of top level uninitialised symbols (variable/func declarations), in the order they are first declared
+    // and without duplicates. This is the order of the block's ENTERSCOPE, so the same program always compiles
+    // to the same bytecode
     pub symbols: Vec<String>,
+    // Where each decl is in the source, followed by where last_expr is if there is one. Empty for blocks that
+    // weren't parsed from source, like those made by desugaring
+    pub spans: Vec<Span>,
+}
+
+impl BlockSeq {
+    /// Where the decl at idx is in the source, if the block was parsed from source.
+    pub fn decl_span(&self, idx: usize) -> Option<Span> {
+        self.spans.get(idx).copied()
+    }
+
+    /// Where the last expr is in the source, if there is one and the block was parsed from source.
+    pub fn last_expr_span(&self) -> Option<Span> {
+        self.last_expr.as_ref()?;
+        self.spans.get(self.decls.len()).copied()
+    }
 }
 
 impl Display for BlockSeq {
@@ -374,26 +926,79 @@ impl Display for BlockSeq {
 
 #[derive(Debug, PartialEq)]
 pub struct ParseError {
+    code: Code,
     msg: String,
+    // where parsing stopped. None for errors from passes after parsing, like macro expansion
+    span: Option<Span>,
 }
 
 impl ParseError {
-    pub fn new(err: &str) -> ParseError {
+    pub fn new(msg: Message) -> ParseError {
         ParseError {
-            msg: err.to_owned(),
+            code: msg.code,
+            msg: msg.text,
+            span: None,
         }
     }
+
+    /// The code of the error, see [`diagnostics`].
+    pub fn code(&self) -> Code {
+        self.code
+    }
+
+    /// Where the error is in the source, if known.
+    pub fn span(&self) -> Option<Span> {
+        self.span
+    }
+
+    // Give the error a span, unless it already has one
+    pub(crate) fn or_at(mut self, span: Option<Span>) -> ParseError {
+        self.span = self.span.or(span);
+        self
+    }
 }
 
 impl Display for ParseError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "[ParseError]: {}", self.msg)
+        write!(f, "[ParseError {}]: {}", self.code, self.msg)?;
+        if let Some(span) = self.span {
+            write!(f, " at {}", span)?;
+        }
+
+        Ok(())
     }
 }
 
 // automatic due to Display
 impl std::error::Error for ParseError {}
 
+/// Every error found in one parse, in the order they were found. The parser skips past a declaration it
+/// can't parse and carries on, so one run reports all the syntax errors rather than only the first.
+#[derive(Debug, PartialEq)]
+pub struct ParseErrors(pub Vec<ParseError>);
+
+impl ParseErrors {
+    pub fn errors(&self) -> &[ParseError] {
+        &self.0
+    }
+}
+
+impl From<ParseError> for ParseErrors {
+    fn from(err: ParseError) -> Self {
+        ParseErrors(vec![err])
+    }
+}
+
+// one error per line
+impl Display for ParseErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let errs: Vec<String> = self.0.iter().map(|err| err.to_string()).collect();
+        write!(f, "{}", errs.join("\n"))
+    }
+}
+
+impl std::error::Error for ParseErrors {}
+
 // Type of a function value - subset of FnDeclData
 // Params: care only about types not names
 #[derive(Debug, Clone, PartialEq)]
@@ -430,20 +1035,102 @@ impl Display for FnTypeData {
 }
 
 // Type annotation corresponding to compile time types
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub enum Type {
     Int,
     Float,
     Bool,
     String,
+    Char,
     UserFn(Box<FnTypeData>),
     BuiltInFn, // type checking done separately since it can be polymorphic unlike user fn
     ThreadId,  // result of spawn
     Semaphore,
-    Unit,        // void type like Rust
+    Mutex,                        // a semaphore that can only be held with lock
+    Channel(Box<Type>),           // chan[int] - carries values of one type between threads
+    PVec(Box<Type>),              // pvec[int] - persistent vector, changing it gives a new one
+    PMap(Box<Type>, Box<Type>),   // pmap[str, int] - persistent map, keys are int, str or bool
+    Map(Box<Type>, Box<Type>), // map[str, int] - changed in place and shared like arrays, same keys as pmap
+    Array(Box<Type>, usize),   // [int; 4] - fixed length, like Rust
+    Slice(Box<Type>),          // [int] - view into an array of any length
+    Struct(String), // a struct or enum by name - two with the same fields are different types
+    Socket,         // a TCP connection or listener, or a UDP socket
+    Bytes,          // a mutable buffer of bytes, shared like arrays
+    Option(Box<Type>), // option[int] - Some(1) or None
+    Result(Box<Type>, Box<Type>), // result[int, str] - Ok(1) or Err("bad")
+    Tuple(Vec<Type>), // (int, bool) - a fixed number of values of any type
+    Unit,           // void type like Rust
+    // the part of a type a value doesn't say, like the T of None - equal to every type
+    Infer,
     Unitialised, // Type for variables that exist in a block but not yet declared - only used for TyEnv
 }
 
+/// Types are equal if they have the same shape, where [`Type::Infer`] is equal to any type. `None` has type
+/// `option[_]`, so it can be given where an `option[int]` is expected.
+impl PartialEq for Type {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            // a name that isn't declared yet has no value, not one of a type that isn't known
+            (Type::Unitialised, _) | (_, Type::Unitialised) => {
+                matches!((self, other), (Type::Unitialised, Type::Unitialised))
+            }
+            (Type::Infer, _) | (_, Type::Infer) => true,
+            (Type::UserFn(a), Type::UserFn(b)) => a == b,
+            (Type::Channel(a), Type::Channel(b))
+            | (Type::PVec(a), Type::PVec(b))
+            | (Type::Slice(a), Type::Slice(b))
+            | (Type::Option(a), Type::Option(b)) => a == b,
+            (Type::PMap(k1, v1), Type::PMap(k2, v2))
+            | (Type::Map(k1, v1), Type::Map(k2, v2))
+            | (Type::Result(k1, v1), Type::Result(k2, v2)) => k1 == k2 && v1 == v2,
+            (Type::Array(a, n), Type::Array(b, m)) => n == m && a == b,
+            (Type::Struct(a), Type::Struct(b)) => a == b,
+            (Type::Tuple(a), Type::Tuple(b)) => a == b,
+            _ => std::mem::discriminant(self) == std::mem::discriminant(other),
+        }
+    }
+}
+
+impl Type {
+    /// Whether a part of the type is [`Type::Infer`], so a value of it can't be given a name without an annotation.
+    pub fn has_infer(&self) -> bool {
+        match self {
+            Type::Infer => true,
+            Type::Channel(ty)
+            | Type::PVec(ty)
+            | Type::Slice(ty)
+            | Type::Array(ty, _)
+            | Type::Option(ty) => ty.has_infer(),
+            Type::PMap(a, b) | Type::Map(a, b) | Type::Result(a, b) => {
+                a.has_infer() || b.has_infer()
+            }
+            Type::Tuple(elems) => elems.iter().any(Type::has_infer),
+            Type::UserFn(fn_ty) => {
+                fn_ty.params.iter().any(Type::has_infer) || fn_ty.ret_type.has_infer()
+            }
+            _ => false,
+        }
+    }
+
+    /// The type equal to both self and other that says the most, filling the parts of self that are
+    /// [`Type::Infer`] from other. Two arms giving `Some(1)` and `None` make an `option[int]`.
+    pub fn merge(&self, other: &Type) -> Type {
+        match (self, other) {
+            (Type::Infer, _) => other.clone(),
+            (Type::Option(a), Type::Option(b)) => Type::Option(Box::new(a.merge(b))),
+            (Type::Result(a1, b1), Type::Result(a2, b2)) => {
+                Type::Result(Box::new(a1.merge(a2)), Box::new(b1.merge(b2)))
+            }
+            (Type::Array(a, n), Type::Array(b, _)) => Type::Array(Box::new(a.merge(b)), *n),
+            (Type::Slice(a), Type::Slice(b)) => Type::Slice(Box::new(a.merge(b))),
+            (Type::Tuple(a), Type::Tuple(b)) if a.len() == b.len() => {
+                Type::Tuple(a.iter().zip(b).map(|(a, b)| a.merge(b)).collect())
+            }
+            _ => self.clone(),
+        }
+    }
+}
+
 impl Type {
     // Cast to fn type
     pub fn to_fn_type(&self) -> Option<Box<FnTypeData>> {
@@ -462,8 +1149,13 @@ impl Type {
             "bool" => Ok(Self::Bool),
             "float" => Ok(Self::Float),
             "str" => Ok(Self::String),
+            "char" => Ok(Self::Char),
             "sem" => Ok(Self::Semaphore),
-            _ => Err(ParseError::new(&format!(
+            "mutex" => Ok(Self::Mutex),
+            "socket" => Ok(Self::Socket),
+            "bytes" => Ok(Self::Bytes),
+            _ => Err(ParseError::new(message!(
+                P005,
                 "Unknown primitive type: {}",
                 input
             ))),
@@ -481,9 +1173,31 @@ impl Display for Type {
             Self::Unitialised => "uninit".to_string(),
             Self::BuiltInFn => "builtin_fn".to_string(),
             Self::String => "str".to_string(),
+            Self::Char => "char".to_string(),
             Self::UserFn(fn_ty) => fn_ty.to_string(),
             Self::ThreadId => "tid".to_string(),
             Self::Semaphore => "sem".to_string(),
+            Self::Mutex => "mutex".to_string(),
+            Self::Channel(elem_ty) => format!("chan[{}]", elem_ty),
+            Self::PVec(elem_ty) => format!("pvec[{}]", elem_ty),
+            Self::PMap(key_ty, val_ty) => format!("pmap[{}, {}]", key_ty, val_ty),
+            Self::Map(key_ty, val_ty) => format!("map[{}, {}]", key_ty, val_ty),
+            Self::Array(elem_ty, len) => format!("[{}; {}]", elem_ty, len),
+            Self::Slice(elem_ty) => format!("[{}]", elem_ty),
+            Self::Struct(name) => name.to_string(),
+            Self::Socket => "socket".to_string(),
+            Self::Bytes => "bytes".to_string(),
+            Self::Option(ty) => format!("option[{}]", ty),
+            Self::Result(ok_ty, err_ty) => format!("result[{}, {}]", ok_ty, err_ty),
+            // (int,) so a tuple of one type isn't read as a type in parentheses
+            Self::Tuple(elems) => match elems.as_slice() {
+                [ty] => format!("({},)", ty),
+                _ => {
+                    let elems: Vec<String> = elems.iter().map(|ty| ty.to_string()).collect();
+                    format!("({})", elems.join(", "))
+                }
+            },
+            Self::Infer => "_".to_string(),
         };
 
         write!(f, "{}", string)
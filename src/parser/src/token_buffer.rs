@@ -0,0 +1,93 @@
+use std::collections::VecDeque;
+
+use lexer::{lex_spanned, LexError, Span, Token};
+use logos::Lexer;
+
+/// The whole token stream of the input, lexed up front.
+///
+/// Peeking borrows from the buffer and `advance` moves tokens out of it, so the parser never has to
+/// clone a token it is looking ahead at. Arbitrary lookahead is available through `peek_nth`.
+///
+/// Each token keeps its span, so the parser can say where in the source it is.
+#[derive(Debug, Default)]
+pub struct TokenBuffer {
+    tokens: VecDeque<(Result<Token, LexError>, Span)>,
+    // span of the last token consumed
+    prev_span: Option<Span>,
+}
+
+impl TokenBuffer {
+    pub fn new(lexer: Lexer<'_, Token>) -> TokenBuffer {
+        TokenBuffer {
+            tokens: lex_spanned(lexer).into(),
+            prev_span: None,
+        }
+    }
+
+    /// Next token without consuming it
+    pub fn peek(&self) -> Option<&Result<Token, LexError>> {
+        self.tokens.front().map(|(tok, _)| tok)
+    }
+
+    /// Token n positions after the next one without consuming anything. peek_nth(0) is peek()
+    pub fn peek_nth(&self, n: usize) -> Option<&Result<Token, LexError>> {
+        self.tokens.get(n).map(|(tok, _)| tok)
+    }
+
+    /// Span of the next token
+    pub fn peek_span(&self) -> Option<Span> {
+        self.tokens.front().map(|(_, span)| *span)
+    }
+
+    /// Span of the last token consumed
+    pub fn prev_span(&self) -> Option<Span> {
+        self.prev_span
+    }
+
+    /// Consume and return the next token
+    pub fn advance(&mut self) -> Option<Result<Token, LexError>> {
+        let (tok, span) = self.tokens.pop_front()?;
+        self.prev_span = Some(span);
+        Some(tok)
+    }
+
+    /// Every token the lexer couldn't make, in order, with where it is
+    pub fn lex_errors(&self) -> impl Iterator<Item = (&LexError, Span)> {
+        self.tokens
+            .iter()
+            .filter_map(|(tok, span)| tok.as_ref().err().map(|err| (err, *span)))
+    }
+
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lexer::lex;
+
+    #[test]
+    fn test_token_buffer() {
+        let mut buf = TokenBuffer::new(lex("let x = 2;"));
+        assert_eq!(buf.len(), 5);
+        assert_eq!(buf.peek(), Some(&Ok(Token::Let)));
+        assert_eq!(buf.peek_nth(2), Some(&Ok(Token::Eq)));
+        assert_eq!(buf.peek_nth(5), None);
+
+        assert_eq!(buf.prev_span(), None);
+        assert_eq!(buf.advance(), Some(Ok(Token::Let)));
+        assert_eq!(buf.peek(), Some(&Ok(Token::Ident("x".to_string()))));
+        assert_eq!(buf.prev_span().map(|span| span.start..span.end), Some(0..3));
+        assert_eq!(buf.peek_span().map(|span| span.col), Some(5));
+
+        while buf.advance().is_some() {}
+        assert!(buf.is_empty());
+        assert_eq!(buf.peek(), None);
+    }
+}
@@ -0,0 +1,184 @@
+//! The RustScript pipeline as a library, for tools that want to build on it without depending on the crates
+//! it is made of.
+//!
+//! Source goes through each stage in turn:
+//!
+//! - [`lex`] splits it into tokens
+//! - [`parse`] builds the program, with macros expanded
+//! - [`typecheck`] checks the program is well typed
+//! - [`compile`] turns the program into bytecode
+//! - [`run`] runs the bytecode on a [`Runtime`], or [`run_async`] in an async program
+//!
+//! The functions here and [`Error`] follow semver: a breaking change to them is a new major version. The types
+//! re-exported from the other crates, [`Token`], [`Program`], [`ByteCode`] and [`Runtime`], are passed between
+//! the stages as they are, so their contents can change in a minor version.
+
+use std::collections::HashSet;
+
+use compiler::compiler::{desugar_with_defines, Compiler};
+use lexer::LexError;
+use thiserror::Error;
+use types::type_checker::TypeChecker;
+
+pub use bytecode::ByteCode;
+pub use ignite::Runtime;
+pub use lexer::Token;
+pub use parser::structs::BlockSeq as Program;
+
+/// Why a stage of the pipeline failed. Each holds the message of the stage, with every error it found.
+#[derive(Error, Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Error {
+    #[error("[LexError]: unexpected character at line {line}, column {col}")]
+    Lex { line: usize, col: usize },
+
+    #[error("[LexError]: {reason} at line {line}, column {col}")]
+    BadToken {
+        line: usize,
+        col: usize,
+        reason: String,
+    },
+
+    #[error("{0}")]
+    Parse(String),
+
+    #[error("{0}")]
+    Type(String),
+
+    #[error("{0}")]
+    Compile(String),
+
+    #[error("{0}")]
+    Runtime(String),
+}
+
+/// Split source into tokens.
+///
+/// # Errors
+///
+/// [`Error::Lex`] at the first character that doesn't start a token, or [`Error::BadToken`] at the first string,
+/// char or block comment that is malformed, like an invalid escape or a missing end.
+pub fn lex(src: &str) -> Result<Vec<Token>, Error> {
+    lexer::lex_spanned(lexer::lex(src))
+        .into_iter()
+        .map(|(tok, span)| {
+            tok.map_err(|err| match err {
+                LexError::UnexpectedChar => Error::Lex {
+                    line: span.line,
+                    col: span.col,
+                },
+                _ => Error::BadToken {
+                    line: span.line,
+                    col: span.col,
+                    reason: err.to_string(),
+                },
+            })
+        })
+        .collect()
+}
+
+/// Parse source into a program, expanding macros and leaving out the code behind `#[cfg(..)]`, since no flags
+/// are defined. The result is the program [`typecheck`] and [`compile`] expect.
+///
+/// # Errors
+///
+/// [`Error::Parse`] with every error found.
+pub fn parse(src: &str) -> Result<Program, Error> {
+    desugar_with_defines(src, &HashSet::new()).map_err(|e| Error::Parse(e.to_string()))
+}
+
+/// Check a program is well typed.
+///
+/// # Errors
+///
+/// [`Error::Type`] with every error found.
+pub fn typecheck(program: &Program) -> Result<(), Error> {
+    TypeChecker::new(program)
+        .type_check()
+        .map(|_| ())
+        .map_err(|e| Error::Type(e.to_string()))
+}
+
+/// Compile a program to bytecode. The program is not type checked, see [`typecheck`].
+///
+/// # Errors
+///
+/// [`Error::Compile`] if the program can't be compiled.
+pub fn compile(program: &Program) -> Result<Vec<ByteCode>, Error> {
+    Compiler::new(program.clone())
+        .compile()
+        .map_err(|e| Error::Compile(e.to_string()))
+}
+
+/// Verify the bytecode of the runtime and run it until every thread is done, returning the runtime so the result
+/// can be read with [`Runtime::result`].
+///
+/// # Errors
+///
+/// [`Error::Runtime`] if the bytecode is malformed or the program fails.
+pub fn run(rt: Runtime) -> Result<Runtime, Error> {
+    ignite::verify(&rt.instrs).map_err(|e| Error::Runtime(ignite::format_runtime_error(&e)))?;
+    ignite::run(rt).map_err(|e| Error::Runtime(ignite::format_runtime_error(&e)))
+}
+
+/// Like [`run`], but with the builtins that block on IO run as tokio tasks, so the program doesn't stall the
+/// executor it is awaited on. The future is not `Send`, see [`ignite::run_async`].
+///
+/// # Errors
+///
+/// [`Error::Runtime`] if the bytecode is malformed or the program fails.
+pub async fn run_async(rt: Runtime) -> Result<Runtime, Error> {
+    ignite::verify(&rt.instrs).map_err(|e| Error::Runtime(ignite::format_runtime_error(&e)))?;
+    ignite::run_async(rt)
+        .await
+        .map_err(|e| Error::Runtime(ignite::format_runtime_error(&e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use bytecode::Value;
+
+    use super::*;
+
+    #[test]
+    fn test_pipeline() -> Result<(), Error> {
+        let toks = lex("let x = 2;")?;
+        assert_eq!(toks.len(), 5);
+
+        let program = parse("fn sq(x: int) -> int { x * x } sq(7)")?;
+        typecheck(&program)?;
+        let rt = run(Runtime::new(compile(&program)?))?;
+        assert_eq!(rt.result(), Some(Value::Int(49)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_pipeline_errors() {
+        assert_eq!(lex("let x = `;"), Err(Error::Lex { line: 1, col: 9 }));
+        assert_eq!(
+            lex("let x = 1; /* no end"),
+            Err(Error::BadToken {
+                line: 1,
+                col: 12,
+                reason: "unterminated block comment".to_string()
+            })
+        );
+        assert_eq!(
+            lex(r#"let x = "a\qb";"#).map_err(|err| err.to_string()),
+            Err(r#"[LexError]: invalid escape '\q', expected one of \n \r \t \0 \\ \" \' or \u{..} with 1 to 6 hex digits at line 1, column 9"#.to_string())
+        );
+        assert!(matches!(parse("let = 2;"), Err(Error::Parse(_))));
+
+        let program = parse("let x: int = true;").expect("should parse");
+        let err = typecheck(&program).expect_err("should not type check");
+        assert!(err.to_string().contains("[TypeError T002]"));
+
+        let program = parse("let xs = [1]; xs[3]").expect("should parse");
+        let code = compile(&program).expect("should compile");
+        let Err(err) = run(Runtime::new(code)) else {
+            panic!("should fail to run")
+        };
+        assert!(matches!(err, Error::Runtime(_)));
+        assert!(err.to_string().starts_with("[RuntimeError R003]"));
+    }
+}
@@ -26,7 +26,7 @@ impl<'prog> TypeChecker<'prog> {
         let mut must_break = false;
         let mut must_return = false;
 
-        for decl in program.decls.iter() {
+        for (idx, decl) in program.decls.iter().enumerate() {
             match self.check_decl(decl) {
                 Ok(check_res) => {
                     // propagate must_break/must_return
@@ -34,6 +34,7 @@ impl<'prog> TypeChecker<'prog> {
                     must_return = must_return || check_res.must_return;
                 }
                 Err(mut decl_errs) => {
+                    decl_errs.or_at(program.decl_span(idx));
                     errs.append(&mut decl_errs);
 
                     // if this err means we can't proceed, stop e.g let x = -true; let y = x + 3; - we don't know type of x since invalid
@@ -75,7 +76,10 @@ impl<'prog> TypeChecker<'prog> {
                     };
                     return Ok(res);
                 }
-                Err(mut expr_errs) => errs.append(&mut expr_errs),
+                Err(mut expr_errs) => {
+                    expr_errs.or_at(program.last_expr_span());
+                    errs.append(&mut expr_errs)
+                }
             };
         }
 
@@ -225,7 +229,7 @@ mod tests {
 
         expect_err(
             t,
-            "[TypeError]: Identifier 'x' assigned before declaration",
+            "[TypeError T001]: Identifier 'x' assigned before declaration at line 4, column 13",
             false,
         );
     }
@@ -241,6 +245,6 @@ mod tests {
         x + false
         ";
 
-        expect_err(t, "[TypeError]: 'x' has declared type int but assigned type bool\n[TypeError]: 'y' has declared type bool but assigned type int", true);
+        expect_err(t, "[TypeError T002]: 'x' has declared type int but assigned type bool at line 2, column 9\n[TypeError T002]: 'y' has declared type bool but assigned type int at line 5, column 13", true);
     }
 }
@@ -0,0 +1,476 @@
+use crate::type_checker::{CheckResult, TypeChecker, TypeErrors};
+use diagnostics::message;
+use parser::const_eval::const_eval;
+use parser::structs::{Expr, IndexAssignData, SliceData, Type};
+
+impl<'prog> TypeChecker<'prog> {
+    // [e1, e2, ...]: all elements must have the same type
+    pub(crate) fn check_array(&mut self, elems: &[Expr]) -> Result<CheckResult, TypeErrors> {
+        let mut ty_errs = TypeErrors::new();
+        let mut res = CheckResult {
+            ty: Type::Unit,
+            must_break: false,
+            must_return: false,
+        };
+        let mut elem_types: Vec<Type> = vec![];
+
+        for elem in elems.iter() {
+            match self.check_expr(elem) {
+                Ok(elem_res) => {
+                    res = CheckResult::combine(&res, &elem_res);
+                    elem_types.push(elem_res.ty);
+                }
+                Err(mut errs) => ty_errs.append(&mut errs),
+            }
+        }
+
+        if !ty_errs.is_ok() {
+            return Err(ty_errs);
+        }
+
+        let Some(elem_ty) = elem_types.first() else {
+            let e = message!(
+                T006,
+                "Can't infer the element type of an empty array, use [val; 0] instead"
+            );
+            return Err(TypeErrors::new_err(e));
+        };
+
+        if let Some(ty) = elem_types.iter().find(|ty| *ty != elem_ty) {
+            let e = message!(
+                T002,
+                "Array elements must have the same type, expected '{}' but got '{}'",
+                elem_ty,
+                ty
+            );
+            return Err(TypeErrors::new_err(e));
+        }
+
+        let elem_ty = elem_types
+            .iter()
+            .fold(elem_ty.to_owned(), |ty, t| ty.merge(t));
+        res.ty = Type::Array(Box::new(elem_ty), elems.len());
+        Ok(res)
+    }
+
+    // [val; len]
+    pub(crate) fn check_array_fill(
+        &mut self,
+        val: &Expr,
+        len: usize,
+    ) -> Result<CheckResult, TypeErrors> {
+        let mut res = self.check_expr(val)?;
+        res.ty = Type::Array(Box::new(res.ty), len);
+        Ok(res)
+    }
+
+    /// Check arr[index] and return the element type. Indices that are constant expressions are checked against the length.
+    /// A string can be indexed too, giving a char.
+    pub(crate) fn check_index(
+        &mut self,
+        arr: &Expr,
+        index: &Expr,
+    ) -> Result<CheckResult, TypeErrors> {
+        self.check_index_of(arr, index).map(|(res, _)| res)
+    }
+
+    // check_index, also giving the type of what is indexed
+    fn check_index_of(
+        &mut self,
+        arr: &Expr,
+        index: &Expr,
+    ) -> Result<(CheckResult, Type), TypeErrors> {
+        let mut ty_errs = TypeErrors::new();
+        let mut arr_res = self.check_expr(arr);
+        let mut index_res = self.check_expr(index);
+
+        if let Err(ref mut errs) = arr_res {
+            ty_errs.append(errs);
+        }
+
+        if let Err(ref mut errs) = index_res {
+            ty_errs.append(errs);
+        }
+
+        if !ty_errs.is_ok() {
+            return Err(ty_errs);
+        }
+
+        let arr_res = arr_res?;
+        let index_res = index_res?;
+
+        // slice and string lengths are only known at runtime
+        let (elem_ty, len) = match &arr_res.ty {
+            Type::Array(elem_ty, len) => (*elem_ty.clone(), Some(*len)),
+            Type::Slice(elem_ty) => (*elem_ty.clone(), None),
+            Type::String => (Type::Char, None),
+            _ => {
+                let e = message!(T008, "Can't index into type '{}'", arr_res.ty);
+                return Err(TypeErrors::new_err(e));
+            }
+        };
+
+        if index_res.ty != Type::Int {
+            let kind = if arr_res.ty == Type::String {
+                "String"
+            } else {
+                "Array"
+            };
+            let e = message!(
+                T008,
+                "{} index must have type 'int' but got '{}'",
+                kind,
+                index_res.ty
+            );
+            return Err(TypeErrors::new_err(e));
+        }
+
+        if let (Some(idx), Some(len)) = (const_eval(index), len) {
+            if usize::try_from(idx).map_or(true, |idx| idx >= len) {
+                let e = message!(
+                    T008,
+                    "Index {} is out of bounds for array of length {}",
+                    idx,
+                    len
+                );
+                return Err(TypeErrors::new_err(e));
+            }
+        }
+
+        let mut res = CheckResult::combine(&arr_res, &index_res);
+        res.ty = elem_ty;
+        Ok((res, arr_res.ty))
+    }
+
+    /// Check arr[start..end] and return a slice of the element type. Constant bounds on arrays are checked against the length.
+    pub(crate) fn check_slice(&mut self, slice: &SliceData) -> Result<CheckResult, TypeErrors> {
+        let mut ty_errs = TypeErrors::new();
+        let mut res = match self.check_expr(&slice.arr) {
+            Ok(res) => res,
+            Err(mut errs) => {
+                ty_errs.append(&mut errs);
+                CheckResult {
+                    ty: Type::Unit,
+                    must_break: false,
+                    must_return: false,
+                }
+            }
+        };
+        let arr_ty = res.ty.clone();
+
+        for bound in [&slice.start, &slice.end].into_iter().flatten() {
+            match self.check_expr(bound) {
+                Ok(bound_res) => {
+                    if bound_res.ty != Type::Int {
+                        let e = message!(
+                            T008,
+                            "Slice bounds must have type 'int' but got '{}'",
+                            bound_res.ty
+                        );
+                        ty_errs.add(e);
+                    }
+                    res = CheckResult::combine(&res, &bound_res);
+                }
+                Err(mut errs) => ty_errs.append(&mut errs),
+            }
+        }
+
+        if !ty_errs.is_ok() {
+            return Err(ty_errs);
+        }
+
+        let (elem_ty, len) = match arr_ty {
+            Type::Array(elem_ty, len) => (elem_ty, Some(len)),
+            Type::Slice(elem_ty) => (elem_ty, None),
+            _ => {
+                let e = message!(T008, "Can't slice type '{}'", arr_ty);
+                return Err(TypeErrors::new_err(e));
+            }
+        };
+
+        let start = slice.start.as_ref().map_or(Some(0), const_eval);
+        let end = match &slice.end {
+            Some(end) => const_eval(end).map(|end| end + i64::from(slice.inclusive)),
+            None => len.and_then(|len| i64::try_from(len).ok()),
+        };
+
+        if let (Some(start), Some(end)) = (start, end) {
+            if start > end {
+                let e = message!(T008, "Slice starts at {} but ends at {}", start, end);
+                return Err(TypeErrors::new_err(e));
+            }
+        }
+
+        if let Some(len) = len {
+            for bound in [start, end].into_iter().flatten() {
+                if usize::try_from(bound).map_or(true, |bound| bound > len) {
+                    let e = message!(
+                        T008,
+                        "Slice bound {} is out of bounds for array of length {}",
+                        bound,
+                        len
+                    );
+                    return Err(TypeErrors::new_err(e));
+                }
+            }
+        }
+
+        res.ty = Type::Slice(elem_ty);
+        Ok(res)
+    }
+
+    // arr[index] = expr: expr must have the element type. Produces Unit like other assignments.
+    pub(crate) fn check_index_assign(
+        &mut self,
+        stmt: &IndexAssignData,
+    ) -> Result<CheckResult, TypeErrors> {
+        let mut ty_errs = TypeErrors::new();
+        let mut elem_res = self.check_index_of(&stmt.arr, &stmt.index);
+        let mut expr_res = self.check_expr(&stmt.expr);
+
+        if let Err(ref mut errs) = elem_res {
+            ty_errs.append(errs);
+        }
+
+        if let Err(ref mut errs) = expr_res {
+            ty_errs.append(errs);
+        }
+
+        if !ty_errs.is_ok() {
+            return Err(ty_errs);
+        }
+
+        let (elem_res, arr_ty) = elem_res?;
+        let expr_res = expr_res?;
+
+        if arr_ty == Type::String {
+            let e = message!(
+                T008,
+                "Can't assign to '{}[{}]', strings can't be changed",
+                stmt.arr,
+                stmt.index
+            );
+            return Err(TypeErrors::new_err(e));
+        }
+
+        if elem_res.ty != expr_res.ty {
+            let e = message!(
+                T002,
+                "'{}[{}]' has type {} but assigned type {}",
+                stmt.arr,
+                stmt.index,
+                elem_res.ty,
+                expr_res.ty
+            );
+            return Err(TypeErrors::new_err(e));
+        }
+
+        Ok(CheckResult::combine(&elem_res, &expr_res))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use parser::structs::Type;
+
+    use crate::type_checker::{expect_err, expect_pass, expect_pass_str};
+
+    #[test]
+    fn test_type_check_array_literal() {
+        expect_pass_str("[1, 2, 3]", "[int; 3]");
+        expect_pass_str("[[true], [false]]", "[[bool; 1]; 2]");
+        expect_pass_str("[0; 4]", "[int; 4]");
+        expect_pass_str("[[0.5; 2]; 3]", "[[float; 2]; 3]");
+        expect_pass_str("let x = 2; [x, x + 1]", "[int; 2]");
+
+        expect_err(
+            "[1, true]",
+            "Array elements must have the same type, expected 'int' but got 'bool'",
+            true,
+        );
+        expect_err(
+            "[[1], [1, 2]]",
+            "expected '[int; 1]' but got '[int; 2]'",
+            true,
+        );
+        expect_err("[]", "Can't infer the element type of an empty array", true);
+        expect_pass_str("[1; 0]", "[int; 0]");
+
+        // errors in elements are collected
+        expect_err(
+            "[x, -true]",
+            "[TypeError T001]: Identifier 'x' not declared at line 1, column 1\n[TypeError T003]: Can't negate type bool at line 1, column 1",
+            false,
+        );
+    }
+
+    #[test]
+    fn test_type_check_array_ann() {
+        expect_pass("let xs: [int; 4] = [0; 4]; xs[3]", Type::Int);
+        expect_pass("let xs: [int; 2 * 2] = [1, 2, 3, 4];", Type::Unit);
+
+        expect_err(
+            "let xs: [int; 4] = [0; 3];",
+            "'xs' has declared type [int; 4] but assigned type [int; 3]",
+            true,
+        );
+        expect_err(
+            "let xs: [int; 2] = [0.0; 2];",
+            "'xs' has declared type [int; 2] but assigned type [float; 2]",
+            true,
+        );
+        expect_err(
+            "let xs = [0; 2]; xs = [0; 3];",
+            "'xs' declared with type [int; 2] but assigned type [int; 3]",
+            true,
+        );
+
+        let t = r"
+        fn sum(xs: [int; 3]) -> int {
+            xs[0] + xs[1] + xs[2]
+        }
+        sum([1, 2, 3])
+        ";
+        expect_pass(t, Type::Int);
+
+        let t = r"
+        fn sum(xs: [int; 3]) -> int {
+            xs[0] + xs[1] + xs[2]
+        }
+        sum([1, 2])
+        ";
+        expect_err(t, "Mismatched types in function call", true);
+    }
+
+    #[test]
+    fn test_type_check_index() {
+        expect_pass("let xs = [1, 2]; xs[0] + xs[1]", Type::Int);
+        expect_pass("let grid = [[true; 2]; 3]; grid[2][1]", Type::Bool);
+        expect_pass("let xs = [1, 2]; let i = 5; xs[i]", Type::Int);
+        expect_pass(r#"let s = "hi"; s[1]"#, Type::Char);
+        expect_pass(
+            r#"let s = "hi"; let c: char = 'a'; c < s[0] && s[1] == 'i'"#,
+            Type::Bool,
+        );
+
+        expect_err("let x = 2; x[0]", "Can't index into type 'int'", true);
+        expect_err(
+            "let xs = [1, 2]; xs[true]",
+            "Array index must have type 'int' but got 'bool'",
+            true,
+        );
+        expect_err(
+            r#"let s = "hi"; s['a']"#,
+            "String index must have type 'int' but got 'char'",
+            true,
+        );
+    }
+
+    #[test]
+    fn test_type_check_index_bounds() {
+        // literal and constant indices are checked against the length
+        expect_err(
+            "let xs = [1, 2]; xs[2]",
+            "Index 2 is out of bounds for array of length 2",
+            true,
+        );
+        expect_err(
+            "let xs: [int; 4] = [0; 4]; xs[2 * 2]",
+            "Index 4 is out of bounds for array of length 4",
+            true,
+        );
+        expect_err(
+            "let xs = [1, 2]; xs[-1]",
+            "Index -1 is out of bounds for array of length 2",
+            true,
+        );
+        expect_err(
+            "let grid = [[0; 3]; 2]; grid[1][3]",
+            "Index 3 is out of bounds for array of length 3",
+            true,
+        );
+        expect_err(
+            "let xs = [0; 0]; xs[0] = 1;",
+            "Index 0 is out of bounds for array of length 0",
+            true,
+        );
+        expect_pass("let xs = [1, 2]; xs[2 - 1]", Type::Int);
+    }
+
+    #[test]
+    fn test_type_check_index_assign() {
+        expect_pass(
+            "let xs = [1, 2]; xs[0] = 3; xs",
+            Type::Array(Box::new(Type::Int), 2),
+        );
+        expect_pass("let grid = [[0; 2]; 2]; grid[0][1] = 3;", Type::Unit);
+        expect_pass("let grid = [[0; 2]; 2]; grid[0] = [1, 2];", Type::Unit);
+
+        expect_err(
+            "let xs = [1, 2]; xs[0] = true;",
+            "'xs[0]' has type int but assigned type bool",
+            true,
+        );
+        expect_err(
+            "let grid = [[0; 2]; 2]; grid[0] = [1, 2, 3];",
+            "'grid[0]' has type [int; 2] but assigned type [int; 3]",
+            true,
+        );
+        expect_err("ys[0] = 1;", "Identifier 'ys' not declared", true);
+        expect_err(
+            r#"let s = "hi"; s[0] = 'a';"#,
+            "Can't assign to 's[0]', strings can't be changed",
+            true,
+        );
+    }
+
+    #[test]
+    fn test_type_check_slice() {
+        let int_slice = Type::Slice(Box::new(Type::Int));
+        expect_pass("let xs = [1, 2, 3]; xs[1..]", int_slice.clone());
+        expect_pass("let xs = [1, 2, 3]; xs[..]", int_slice.clone());
+        expect_pass("let xs = [1, 2, 3]; xs[0..=2]", int_slice.clone());
+        expect_pass("let xs = [1, 2, 3]; xs[1..][0..1]", int_slice.clone());
+        expect_pass("let xs = [1, 2, 3]; let s = xs[1..]; s[5]", Type::Int);
+        expect_pass(
+            "let xs = [1, 2, 3]; let s: [int] = xs[..2]; s[0] = 4;",
+            Type::Unit,
+        );
+        expect_pass(
+            "let xs = [1, 2, 3]; slice_len(xs[1..]) + slice_len(xs)",
+            Type::Int,
+        );
+
+        expect_err("let x = 2; x[0..]", "Can't slice type 'int'", true);
+        expect_err(
+            "let xs = [1, 2, 3]; xs[true..]",
+            "Slice bounds must have type 'int' but got 'bool'",
+            true,
+        );
+        expect_err(
+            "let xs = [1, 2, 3]; xs[..4]",
+            "Slice bound 4 is out of bounds for array of length 3",
+            true,
+        );
+        expect_err(
+            "let xs = [1, 2, 3]; xs[..=3]",
+            "Slice bound 4 is out of bounds for array of length 3",
+            true,
+        );
+        expect_err(
+            "let xs = [1, 2, 3]; xs[2..1]",
+            "Slice starts at 2 but ends at 1",
+            true,
+        );
+        expect_err(
+            "let xs = [1, 2, 3]; let s: [int; 3] = xs[..];",
+            "'s' has declared type [int; 3] but assigned type [int]",
+            true,
+        );
+        expect_err(
+            "slice_len(2)",
+            "Expected an array or slice but got (int)",
+            true,
+        );
+    }
+}
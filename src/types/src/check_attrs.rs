@@ -0,0 +1,244 @@
+use diagnostics::{message, Message};
+use parser::cfg::CFG;
+use parser::structs::{Attribute, Expr, FnDeclData, LetStmtData, Type};
+
+use crate::type_checker::{CheckResult, TypeChecker, TypeErrors};
+
+pub const ATTR_TEST: &str = "test";
+pub const ATTR_DEPRECATED: &str = "deprecated";
+pub const ATTR_INLINE: &str = "inline";
+pub const ATTR_NOINLINE: &str = "noinline";
+
+// What the attributes are attached to
+pub(crate) enum AttrTarget<'a> {
+    Fn(&'a FnDeclData),
+    Let(&'a LetStmtData),
+}
+
+impl<'prog> TypeChecker<'prog> {
+    /// Check the attributes on a declaration are known and used correctly. Their effects are up to the
+    /// later stages that look for them: `ignite test` runs the #[test] fns, and the compiler warns about uses of
+    /// #[deprecated] declarations. #[inline] and #[noinline] are only hints, and the compiler has no inliner to
+    /// take them, so every call stays a call whichever is given.
+    pub(crate) fn check_attrs(&self, target: AttrTarget) -> Result<(), TypeErrors> {
+        let mut ty_errs = TypeErrors::new();
+        let attrs = match target {
+            AttrTarget::Fn(data) => &data.attrs,
+            AttrTarget::Let(data) => &data.attrs,
+        };
+
+        for (i, attr) in attrs.iter().enumerate() {
+            if attrs[..i].iter().any(|prev| prev.name == attr.name) {
+                ty_errs.add(message!(T010, "Duplicate attribute '{}'", attr.name));
+                continue;
+            }
+
+            if attr.name == ATTR_NOINLINE && attrs.iter().any(|other| other.name == ATTR_INLINE) {
+                let e = message!(
+                    T010,
+                    "Attributes '{}' and '{}' can't both be used",
+                    ATTR_INLINE,
+                    ATTR_NOINLINE
+                );
+                ty_errs.add(e);
+                continue;
+            }
+
+            if let Err(e) = self.check_attr(attr, &target) {
+                ty_errs.add(e);
+            }
+        }
+
+        if ty_errs.is_ok() {
+            Ok(())
+        } else {
+            Err(ty_errs)
+        }
+    }
+
+    fn check_attr(&self, attr: &Attribute, target: &AttrTarget) -> Result<(), Message> {
+        match attr.name.as_str() {
+            ATTR_TEST => {
+                let AttrTarget::Fn(fn_decl) = target else {
+                    return Err(message!(
+                        T010,
+                        "Attribute '{}' can only be used on fn declarations",
+                        attr.name
+                    ));
+                };
+
+                if !attr.args.is_empty() {
+                    return Err(message!(
+                        T010,
+                        "Attribute '{}' takes no arguments",
+                        attr.name
+                    ));
+                }
+
+                if !fn_decl.params.is_empty() {
+                    return Err(message!(
+                        T010,
+                        "Test function '{}' can't take parameters",
+                        fn_decl.name
+                    ));
+                }
+
+                // tests are run by calling them after the top level of the program, so they must be declared there
+                if self.envs.len() != 1 {
+                    return Err(message!(
+                        T010,
+                        "Test function '{}' must be declared at the top level",
+                        fn_decl.name
+                    ));
+                }
+
+                Ok(())
+            }
+            // accepted so they can be written, but there is no inliner for them to guide
+            ATTR_INLINE | ATTR_NOINLINE => {
+                if !matches!(target, AttrTarget::Fn(_)) {
+                    return Err(message!(
+                        T010,
+                        "Attribute '{}' can only be used on fn declarations",
+                        attr.name
+                    ));
+                }
+
+                if !attr.args.is_empty() {
+                    return Err(message!(
+                        T010,
+                        "Attribute '{}' takes no arguments",
+                        attr.name
+                    ));
+                }
+
+                Ok(())
+            }
+            // #[deprecated] or #[deprecated("message")]
+            ATTR_DEPRECATED => match attr.args.as_slice() {
+                [] | [Expr::StringLiteral(_)] => Ok(()),
+                _ => Err(message!(
+                    T010,
+                    "Attribute '{}' takes an optional {} message",
+                    attr.name,
+                    Type::String
+                )),
+            },
+            // #[cfg(flag)] - declarations whose flag is not defined are removed before type checking
+            CFG => match attr.args.as_slice() {
+                [Expr::Symbol(_)] => Ok(()),
+                _ => Err(message!(
+                    T010,
+                    "Attribute '{}' takes a single flag name",
+                    attr.name
+                )),
+            },
+            _ => Err(message!(T010, "Unknown attribute '{}'", attr.name)),
+        }
+    }
+
+    /// Attribute errors don't stop the declaration from being checked, so report both.
+    pub(crate) fn with_attr_errs(
+        attrs: Result<(), TypeErrors>,
+        res: Result<CheckResult, TypeErrors>,
+    ) -> Result<CheckResult, TypeErrors> {
+        match (attrs, res) {
+            (Ok(()), res) => res,
+            (Err(errs), Ok(_)) => Err(errs),
+            (Err(mut errs), Err(mut res_errs)) => {
+                errs.append(&mut res_errs);
+                errs.set_cont(res_errs.cont);
+                Err(errs)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use parser::structs::Type;
+
+    use crate::type_checker::{expect_err, expect_pass};
+
+    #[test]
+    fn test_type_check_attrs() {
+        let t = r#"
+        #[test]
+        fn f() {}
+
+        #[deprecated("use y")]
+        fn g(x: int) -> int { x }
+
+        #[inline]
+        fn h() {}
+
+        #[noinline]
+        fn k() {}
+
+        #[deprecated]
+        #[cfg(debug)]
+        let y = 2;
+        y
+        "#;
+        expect_pass(t, Type::Int);
+
+        expect_err("#[foo] fn f() {}", "Unknown attribute 'foo'", true);
+        expect_err(
+            "#[test] let x = 2;",
+            "Attribute 'test' can only be used on fn declarations",
+            true,
+        );
+        expect_err(
+            "#[test] fn f(x: int) {}",
+            "Test function 'f' can't take parameters",
+            true,
+        );
+        expect_err(
+            "#[test(always)] fn f() {}",
+            "Attribute 'test' takes no arguments",
+            true,
+        );
+        expect_err(
+            "fn g() { #[test] fn f() {} }",
+            "Test function 'f' must be declared at the top level",
+            true,
+        );
+        expect_err(
+            "#[inline] let x = 2;",
+            "Attribute 'inline' can only be used on fn declarations",
+            true,
+        );
+        expect_err(
+            "#[noinline(always)] fn f() {}",
+            "Attribute 'noinline' takes no arguments",
+            true,
+        );
+        expect_err(
+            "#[inline] #[noinline] fn f() {}",
+            "Attributes 'inline' and 'noinline' can't both be used",
+            true,
+        );
+        expect_err(
+            "#[deprecated(2)] fn f() {}",
+            "Attribute 'deprecated' takes an optional str message",
+            true,
+        );
+        expect_err(
+            "#[cfg(a, b)] fn f() {}",
+            "Attribute 'cfg' takes a single flag name",
+            true,
+        );
+        expect_err(
+            "#[test] #[test] fn f() {}",
+            "Duplicate attribute 'test'",
+            true,
+        );
+
+        // the declaration is still checked
+        expect_err(
+            "#[foo] let x: int = true;",
+            "[TypeError T010]: Unknown attribute 'foo' at line 1, column 8\n[TypeError T002]: 'x' has declared type int but assigned type bool at line 1, column 8",
+            false,
+        );
+    }
+}
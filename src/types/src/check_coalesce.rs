@@ -0,0 +1,99 @@
+use crate::type_checker::{CheckResult, TypeChecker, TypeErrors};
+use diagnostics::message;
+use parser::structs::{Expr, Type};
+
+impl<'prog> TypeChecker<'prog> {
+    // opt ?? default: opt must be an option, and default must have the type it holds, which is the type of the ??.
+    // default only runs if opt is None, so only opt decides whether it breaks or returns
+    pub(crate) fn check_coalesce(
+        &mut self,
+        opt: &Expr,
+        default: &Expr,
+    ) -> Result<CheckResult, TypeErrors> {
+        let mut ty_errs = TypeErrors::new();
+
+        let opt_res = match self.check_expr(opt) {
+            Ok(res) if matches!(res.ty, Type::Option(_)) => Some(res),
+            Ok(res) => {
+                let e = message!(T002, "'??' expected an option but got type '{}'", res.ty);
+                ty_errs.add(e);
+                None
+            }
+            Err(mut errs) => {
+                ty_errs.append(&mut errs);
+                None
+            }
+        };
+
+        let default_res = match self.check_expr(default) {
+            Ok(res) => Some(res),
+            Err(mut errs) => {
+                ty_errs.append(&mut errs);
+                None
+            }
+        };
+
+        let (Some(mut res), Some(default_res)) = (opt_res, default_res) else {
+            return Err(ty_errs);
+        };
+        let Type::Option(some_ty) = res.ty else {
+            unreachable!("Only options are kept");
+        };
+
+        // None ?? 1 is an int, and Some(None) ?? Some(1) an option[int]
+        let ty = some_ty.merge(&default_res.ty);
+        if ty != default_res.ty.merge(&some_ty) {
+            let e = message!(
+                T002,
+                "'??' default has type '{}' but the option holds '{}'",
+                default_res.ty,
+                some_ty
+            );
+            return Err(TypeErrors::new_err(e));
+        }
+
+        res.ty = ty;
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use parser::structs::Type;
+
+    use crate::type_checker::{expect_err, expect_pass};
+
+    #[test]
+    fn test_type_check_coalesce() {
+        let t = r#"
+        let m = #{"a": 1};
+        map_get(m, "a") ?? 0
+        "#;
+        expect_pass(t, Type::Int);
+        expect_pass("None ?? 2.5", Type::Float);
+        expect_pass(
+            "let a: option[int] = None; let b = Some(2); a ?? b ?? 3",
+            Type::Int,
+        );
+        expect_pass(
+            "let a: option[option[int]] = Some(None); a ?? Some(1)",
+            Type::Option(Box::new(Type::Int)),
+        );
+        // binds looser than arithmetic and comparisons
+        expect_pass("Some(1) ?? 2 + 3", Type::Int);
+        expect_pass("Some(true) ?? 1 > 2", Type::Bool);
+
+        expect_err("1 ?? 2", "'??' expected an option but got type 'int'", true);
+        expect_err(
+            "Some(1) ?? true",
+            "'??' default has type 'bool' but the option holds 'int'",
+            true,
+        );
+        expect_err(
+            "Ok(1) ?? 2",
+            "'??' expected an option but got type 'result[int, _]'",
+            true,
+        );
+        expect_err("Some(1) ?? x", "Identifier 'x' not declared", true);
+    }
+}
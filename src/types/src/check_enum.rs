@@ -0,0 +1,223 @@
+use std::collections::HashSet;
+
+use crate::check_fn_call::NONE;
+use crate::type_checker::{CheckResult, TypeChecker, TypeErrors};
+use diagnostics::message;
+use parser::structs::{BlockSeq, Decl, EnumDeclData, Type};
+
+impl<'prog> TypeChecker<'prog> {
+    /// Register the enums declared at the top level before checking, like structs, so their variants can be made
+    /// before the declaration. Variants are used without the enum name, so each name can only be a variant once.
+    pub(crate) fn register_enums(&mut self, program: &BlockSeq) -> Result<(), TypeErrors> {
+        let mut ty_errs = TypeErrors::new();
+
+        for (idx, decl) in program.decls.iter().enumerate() {
+            let Decl::EnumDeclStmt(data) = decl else {
+                continue;
+            };
+
+            if self.enums.contains_key(&data.name) || self.structs.contains_key(&data.name) {
+                let e = message!(T007, "Type '{}' is already declared", data.name);
+                ty_errs.add_at(e, program.decl_span(idx));
+                continue;
+            }
+
+            for (variant, _) in data.variants.iter() {
+                if TypeChecker::is_builtin_fn(variant) || variant == NONE {
+                    let e = message!(T007, "Variant '{}' is already a builtin", variant);
+                    ty_errs.add_at(e, program.decl_span(idx));
+                } else if let Some((enum_name, _)) = self.enum_variant(variant) {
+                    let e = message!(
+                        T007,
+                        "Variant '{}' is already declared in enum '{}'",
+                        variant,
+                        enum_name
+                    );
+                    ty_errs.add_at(e, program.decl_span(idx));
+                }
+            }
+
+            self.enums
+                .insert(data.name.to_owned(), data.variants.to_owned());
+        }
+
+        if ty_errs.is_ok() {
+            Ok(())
+        } else {
+            Err(ty_errs)
+        }
+    }
+
+    /// The enum a variant is of, with the types of the values it holds.
+    pub(crate) fn enum_variant(&self, name: &str) -> Option<(&str, &[Type])> {
+        self.enums.iter().find_map(|(enum_name, variants)| {
+            variants
+                .iter()
+                .find(|(variant, _)| variant == name)
+                .map(|(_, payload)| (enum_name.as_str(), payload.as_slice()))
+        })
+    }
+
+    // Variants were registered before checking, so only need to validate them here
+    pub(crate) fn check_enum_decl(
+        &mut self,
+        data: &EnumDeclData,
+    ) -> Result<CheckResult, TypeErrors> {
+        // program block is the only env at the top level
+        if self.envs.len() != 1 {
+            let e = message!(
+                T007,
+                "Enums can only be declared at the top level, found '{}'",
+                data.name
+            );
+            return Err(TypeErrors::new_err(e));
+        }
+
+        let mut ty_errs = TypeErrors::new();
+        let mut seen: HashSet<&str> = HashSet::new();
+
+        for (variant, payload) in data.variants.iter() {
+            if !seen.insert(variant) {
+                let e = message!(
+                    T007,
+                    "Duplicate variant '{}' in enum '{}'",
+                    variant,
+                    data.name
+                );
+                ty_errs.add(e);
+            }
+
+            for ty in payload.iter() {
+                if let Err(mut errs) = self.check_type_ann(ty) {
+                    ty_errs.append(&mut errs);
+                }
+            }
+        }
+
+        if ty_errs.is_ok() {
+            Ok(CheckResult {
+                ty: Type::Unit,
+                must_break: false,
+                must_return: false,
+            })
+        } else {
+            Err(ty_errs)
+        }
+    }
+
+    /// Check the args of a variant made like Circle(2.0) against the types it holds, and return the enum type.
+    pub(crate) fn check_variant_call(
+        &self,
+        name: &str,
+        arg_types: &[Type],
+    ) -> Option<Result<Type, TypeErrors>> {
+        let (enum_name, payload) = self.enum_variant(name)?;
+        let res = TypeChecker::check_arg_params_match(name, arg_types, payload)
+            .map(|_| Type::Struct(enum_name.to_owned()));
+        Some(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use parser::structs::Type;
+
+    use crate::type_checker::{expect_err, expect_pass, expect_pass_str};
+
+    const SHAPE: &str = "enum Shape { Circle(float), Rect(float, float), Empty }";
+
+    #[test]
+    fn test_type_check_enum() {
+        expect_pass_str(&format!("{} Circle(2.0)", SHAPE), "Shape");
+        expect_pass_str(&format!("{} Rect(1.0, 2.0)", SHAPE), "Shape");
+        expect_pass_str(&format!("{} Empty", SHAPE), "Shape");
+
+        // can be used before the declaration, in annotations and in other types
+        let t = r"
+        fn area(s: Shape) -> float {
+            match s {
+                Circle(r) => 3.0 * r * r,
+                Rect(w, h) => w * h,
+                Empty => 0.0,
+            }
+        }
+        let shapes: [Shape; 3] = [Circle(1.0), Rect(2.0, 3.0), Empty];
+        enum Shape { Circle(float), Rect(float, float), Empty }
+        area(shapes[0]) + area(shapes[1])
+        ";
+        expect_pass(t, Type::Float);
+
+        let t = r"
+        enum Tree { Leaf, Node(int, [Tree; 2]) }
+        let t = Node(1, [Leaf, Leaf]);
+        t == Leaf
+        ";
+        expect_pass(t, Type::Bool);
+
+        // nominal: variants of different enums are different types
+        let t = r"
+        enum A { X }
+        enum B { Y }
+        let a: A = Y;
+        ";
+        expect_err(t, "'a' has declared type A but assigned type B", true);
+    }
+
+    #[test]
+    fn test_type_check_enum_errs() {
+        expect_err(
+            "enum E { A } enum E { B }",
+            "Type 'E' is already declared",
+            true,
+        );
+        expect_err(
+            "struct E { x: int } enum E { B }",
+            "Type 'E' is already declared",
+            true,
+        );
+        expect_err("enum E { A, A }", "Duplicate variant 'A' in enum 'E'", true);
+        expect_err(
+            "enum E { A } enum F { A }",
+            "Variant 'A' is already declared in enum 'E'",
+            true,
+        );
+        expect_err(
+            "enum E { None }",
+            "Variant 'None' is already a builtin",
+            true,
+        );
+        expect_err(
+            "enum E { Ok(int) }",
+            "Variant 'Ok' is already a builtin",
+            true,
+        );
+        expect_err("enum E { A(Q) }", "Unknown type 'Q'", true);
+        expect_err(
+            "fn f() { enum E { A } }",
+            "Enums can only be declared at the top level, found 'E'",
+            true,
+        );
+
+        let t = SHAPE;
+        expect_err(
+            &format!("{} Circle(1)", t),
+            "Mismatched types in function call: got ((int)) but expected ((float))",
+            true,
+        );
+        expect_err(
+            &format!("{} Rect(1.0)", t),
+            "Function 'Rect' takes 2 arguments but 1 were supplied",
+            true,
+        );
+        expect_err(
+            &format!("{} let c = Circle;", t),
+            "Variant 'Circle' of Shape holds values, make one with Circle(..)",
+            true,
+        );
+        expect_err(
+            &format!("{} Empty.x", t),
+            "Can't access field 'x' on type 'Shape'",
+            true,
+        );
+    }
+}
@@ -1,11 +1,25 @@
+use std::collections::HashMap;
+
 use crate::type_checker::{CheckResult, TypeChecker, TypeErrors};
-use parser::structs::{FnCallData, Type};
+use diagnostics::message;
+use parser::structs::{Expr, FnCallData, Type};
 
 // Ideally these constants should be shared across type checker and VM but I don't want to waste time refactoring
 const READ_LINE: &str = "read_line";
 const PRINT: &str = "print";
 const PRINTLN: &str = "println";
 const STRING_LEN: &str = "string_len";
+const FORMAT: &str = "format";
+const SUBSTRING: &str = "substring";
+const SPLIT: &str = "split";
+const FIND: &str = "find";
+const CONTAINS: &str = "contains";
+const REPLACE: &str = "replace";
+const TO_UPPER: &str = "to_upper";
+const TO_LOWER: &str = "to_lower";
+const TRIM: &str = "trim";
+const CHARS: &str = "chars";
+const CHAR_AT: &str = "char_at";
 const MIN: &str = "min";
 const MAX: &str = "max";
 const ABS: &str = "abs";
@@ -15,18 +29,99 @@ const TAN: &str = "tan";
 const SQRT: &str = "sqrt";
 const LOG: &str = "log";
 const POW: &str = "pow";
+const DIV_EUCLID: &str = "div_euclid";
+const REM_EUCLID: &str = "rem_euclid";
+const DIVMOD: &str = "divmod";
 const ITOA: &str = "itoa";
 const ATOI: &str = "atoi";
+const FTOA: &str = "ftoa";
+const ATOF: &str = "atof";
 const FLOAT_TO_INT: &str = "float_to_int";
 const INT_TO_FLOAT: &str = "int_to_float";
 const SEM_CREATE: &str = "sem_create";
 const SEM_SET: &str = "sem_set";
+const MUTEX: &str = "mutex";
+const SLICE_LEN: &str = "slice_len";
+const MAP: &str = "map";
+const FILTER: &str = "filter";
+const FOLD: &str = "fold";
+const SORT_BY: &str = "sort_by";
+const MIN_BY: &str = "min_by";
+const MAX_BY: &str = "max_by";
+pub(crate) const CHAN: &str = "chan";
+const SEND: &str = "send";
+const RECV: &str = "recv";
+const TRY_RECV: &str = "try_recv";
+const SOME: &str = "Some";
+const OK: &str = "Ok";
+const ERR: &str = "Err";
+pub(crate) const NONE: &str = "None";
+const TCP_CONNECT: &str = "tcp_connect";
+const TCP_LISTEN: &str = "tcp_listen";
+const TCP_ACCEPT: &str = "tcp_accept";
+const TCP_SEND: &str = "tcp_send";
+const TCP_RECV: &str = "tcp_recv";
+const UDP_BIND: &str = "udp_bind";
+const UDP_SEND_TO: &str = "udp_send_to";
+const UDP_RECV: &str = "udp_recv";
+const CLOSE: &str = "close";
+const HTTP_GET: &str = "http_get";
+const HTTP_POST: &str = "http_post";
+const ARGS: &str = "args";
+const ENV_VAR: &str = "env_var";
+const RUN_COMMAND: &str = "run_command";
+const ASSERT: &str = "assert";
+const BYTES: &str = "bytes";
+const BYTES_LEN: &str = "bytes_len";
+const GET_BYTE: &str = "get_byte";
+const SET_BYTE: &str = "set_byte";
+const BYTES_FROM_STRING: &str = "bytes_from_string";
+const STRING_FROM_BYTES: &str = "string_from_bytes";
+const READ_BYTES: &str = "read_bytes";
+const WRITE_BYTES: &str = "write_bytes";
+const SLEEP: &str = "sleep";
+const HASH: &str = "hash";
+const CRC32: &str = "crc32";
+const SHA256: &str = "sha256";
+const DETACH: &str = "detach";
+const INSPECT: &str = "inspect";
+const INSPECT_DEPTH: &str = "inspect_depth";
+pub(crate) const PVEC: &str = "pvec";
+pub(crate) const PMAP: &str = "pmap";
+const PERSIST_PUSH: &str = "persist_push";
+const PERSIST_SET: &str = "persist_set";
+const PERSIST_INSERT: &str = "persist_insert";
+const PERSIST_REMOVE: &str = "persist_remove";
+const PERSIST_GET: &str = "persist_get";
+const PERSIST_CONTAINS: &str = "persist_contains";
+const PERSIST_LEN: &str = "persist_len";
+pub(crate) const MAP_NEW: &str = "map_new";
+const MAP_INSERT: &str = "map_insert";
+const MAP_GET: &str = "map_get";
+const MAP_REMOVE: &str = "map_remove";
+const MAP_LEN: &str = "map_len";
+const MAP_KEYS: &str = "map_keys";
+
+// The structs builtins return, declared for every program
+const HTTP_RESPONSE: &str = "HttpResponse";
+const COMMAND_OUTPUT: &str = "CommandOutput";
 
-const BUILTINS: [&str; 19] = [
+const BUILTINS: [&str; 95] = [
     READ_LINE,
     PRINT,
     PRINTLN,
     STRING_LEN,
+    FORMAT,
+    SUBSTRING,
+    SPLIT,
+    FIND,
+    CONTAINS,
+    REPLACE,
+    TO_UPPER,
+    TO_LOWER,
+    TRIM,
+    CHARS,
+    CHAR_AT,
     MIN,
     MAX,
     ABS,
@@ -36,14 +131,163 @@ const BUILTINS: [&str; 19] = [
     SQRT,
     LOG,
     POW,
+    DIV_EUCLID,
+    REM_EUCLID,
+    DIVMOD,
     ITOA,
     ATOI,
+    FTOA,
+    ATOF,
     FLOAT_TO_INT,
     INT_TO_FLOAT,
     SEM_CREATE,
     SEM_SET,
+    MUTEX,
+    SLICE_LEN,
+    MAP,
+    FILTER,
+    FOLD,
+    SORT_BY,
+    MIN_BY,
+    MAX_BY,
+    CHAN,
+    SEND,
+    RECV,
+    TRY_RECV,
+    SOME,
+    OK,
+    ERR,
+    TCP_CONNECT,
+    TCP_LISTEN,
+    TCP_ACCEPT,
+    TCP_SEND,
+    TCP_RECV,
+    UDP_BIND,
+    UDP_SEND_TO,
+    UDP_RECV,
+    CLOSE,
+    HTTP_GET,
+    HTTP_POST,
+    ARGS,
+    ENV_VAR,
+    RUN_COMMAND,
+    ASSERT,
+    BYTES,
+    BYTES_LEN,
+    GET_BYTE,
+    SET_BYTE,
+    BYTES_FROM_STRING,
+    STRING_FROM_BYTES,
+    READ_BYTES,
+    WRITE_BYTES,
+    SLEEP,
+    HASH,
+    CRC32,
+    SHA256,
+    DETACH,
+    INSPECT,
+    INSPECT_DEPTH,
+    PVEC,
+    PMAP,
+    PERSIST_PUSH,
+    PERSIST_SET,
+    PERSIST_INSERT,
+    PERSIST_REMOVE,
+    PERSIST_GET,
+    PERSIST_CONTAINS,
+    PERSIST_LEN,
+    MAP_NEW,
+    MAP_INSERT,
+    MAP_GET,
+    MAP_REMOVE,
+    MAP_LEN,
+    MAP_KEYS,
 ];
 
+/// The structs builtins return, which programs can use like the structs they declare.
+pub(crate) fn builtin_structs() -> HashMap<String, Vec<(String, Type)>> {
+    HashMap::from([
+        (
+            HTTP_RESPONSE.to_string(),
+            vec![
+                ("status".to_string(), Type::Int),
+                ("body".to_string(), Type::String),
+            ],
+        ),
+        (
+            COMMAND_OUTPUT.to_string(),
+            vec![
+                ("code".to_string(), Type::Int),
+                ("stdout".to_string(), Type::String),
+                ("stderr".to_string(), Type::String),
+            ],
+        ),
+    ])
+}
+
+// Whether hash can take a value of the type: closures, semaphores, channels and sockets have no contents to hash.
+// Struct fields aren't looked into, so one holding those is caught when it is hashed instead
+fn is_hashable(ty: &Type) -> bool {
+    match ty {
+        Type::Int
+        | Type::Float
+        | Type::Bool
+        | Type::String
+        | Type::Bytes
+        | Type::Unit
+        | Type::ThreadId
+        | Type::Struct(_) => true,
+        Type::Array(elem_ty, _) | Type::Slice(elem_ty) | Type::PVec(elem_ty) => {
+            is_hashable(elem_ty)
+        }
+        Type::PMap(_, val_ty) | Type::Map(_, val_ty) | Type::Option(val_ty) => is_hashable(val_ty),
+        Type::Result(ok_ty, err_ty) => is_hashable(ok_ty) && is_hashable(err_ty),
+        _ => false,
+    }
+}
+
+// A format string given as a literal must have a {} for each arg after it, and no braces that aren't doubled
+fn check_format_args(fmt: &str, arg_len: usize) -> Result<(), TypeErrors> {
+    let mut placeholders = 0;
+    let mut chars = fmt.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('{', Some('{')) | ('}', Some('}')) => {
+                chars.next();
+            }
+            ('{', Some('}')) => {
+                chars.next();
+                placeholders += 1;
+            }
+            ('{', _) | ('}', _) => {
+                let e = message!(
+                    T004,
+                    "Unmatched '{}' in format string \"{}\", use '{}{}' for a brace",
+                    c,
+                    fmt,
+                    c,
+                    c
+                );
+                return Err(TypeErrors::new_err(e));
+            }
+            _ => (),
+        }
+    }
+
+    if placeholders != arg_len {
+        let e = message!(
+            T004,
+            "Format string \"{}\" has {} placeholders but {} arguments were supplied",
+            fmt,
+            placeholders,
+            arg_len
+        );
+        return Err(TypeErrors::new_err(e));
+    }
+
+    Ok(())
+}
+
 impl<'prog> TypeChecker<'prog> {
     /// Check if name is a builtin function
     pub(crate) fn is_builtin_fn(name: &str) -> bool {
@@ -66,16 +310,24 @@ impl<'prog> TypeChecker<'prog> {
         exp_len: usize,
     ) -> Result<(), TypeErrors> {
         if arg_len != exp_len {
-            let e = format!(
+            let e = message!(
+                T004,
                 "Function '{}' takes {} arguments but {} were supplied",
-                fn_name, exp_len, arg_len
+                fn_name,
+                exp_len,
+                arg_len
             );
-            return Err(TypeErrors::new_err(&e));
+            return Err(TypeErrors::new_err(e));
         }
 
         Ok(())
     }
 
+    /// `result[ty, str]`, what a builtin that can fail gives, with why it failed in the Err.
+    pub(crate) fn str_result(ty: Type) -> Type {
+        Type::Result(Box::new(ty), Box::new(Type::String))
+    }
+
     /// Check if a arg type match given vector of param types. If not, throw a suitable error - report length mismatch or
     /// type mismatch.
     pub(crate) fn check_arg_params_match(
@@ -94,12 +346,13 @@ impl<'prog> TypeChecker<'prog> {
         }
 
         if mismatch {
-            let error_msg = format!(
+            let error_msg = message!(
+                T004,
                 "Mismatched types in function call: got ({}) but expected ({})",
                 TypeChecker::get_type_string(arg_types),
                 TypeChecker::get_type_string(param_types),
             );
-            return Err(TypeErrors::new_err(&error_msg));
+            return Err(TypeErrors::new_err(error_msg));
         }
 
         Ok(())
@@ -134,6 +387,198 @@ impl<'prog> TypeChecker<'prog> {
                 TypeChecker::check_arg_params_match(name, &arg_types, &[Type::String])?;
                 Type::Int
             }
+            // (string, any...) => string
+            FORMAT => {
+                if arg_types.first() != Some(&Type::String) {
+                    let e = message!(
+                        T004,
+                        "Expected a format string and the values to put in it but got {}",
+                        TypeChecker::get_type_string(&arg_types)
+                    );
+                    return Err(TypeErrors::new_err(e));
+                }
+                Type::String
+            }
+            // (string, int, int) => string
+            SUBSTRING => {
+                TypeChecker::check_arg_params_match(
+                    name,
+                    &arg_types,
+                    &[Type::String, Type::Int, Type::Int],
+                )?;
+                Type::String
+            }
+            // (string, string) => [string]
+            SPLIT => {
+                TypeChecker::check_arg_params_match(
+                    name,
+                    &arg_types,
+                    &[Type::String, Type::String],
+                )?;
+                Type::Slice(Box::new(Type::String))
+            }
+            // (string, string) => int, -1 if not found
+            FIND => {
+                TypeChecker::check_arg_params_match(
+                    name,
+                    &arg_types,
+                    &[Type::String, Type::String],
+                )?;
+                Type::Int
+            }
+            // (string, string) => bool
+            CONTAINS => {
+                TypeChecker::check_arg_params_match(
+                    name,
+                    &arg_types,
+                    &[Type::String, Type::String],
+                )?;
+                Type::Bool
+            }
+            // (string, string, string) => string
+            REPLACE => {
+                TypeChecker::check_arg_params_match(
+                    name,
+                    &arg_types,
+                    &[Type::String, Type::String, Type::String],
+                )?;
+                Type::String
+            }
+            // (string) => string
+            TO_UPPER | TO_LOWER | TRIM => {
+                TypeChecker::check_arg_params_match(name, &arg_types, &[Type::String])?;
+                Type::String
+            }
+            // (string) => [char]
+            CHARS => {
+                TypeChecker::check_arg_params_match(name, &arg_types, &[Type::String])?;
+                Type::Slice(Box::new(Type::Char))
+            }
+            // (string, int) => option[char], None if no char starts at the byte index
+            CHAR_AT => {
+                TypeChecker::check_arg_params_match(name, &arg_types, &[Type::String, Type::Int])?;
+                Type::Option(Box::new(Type::Char))
+            }
+            // ([T; n]) => int or ([T]) => int
+            SLICE_LEN => {
+                TypeChecker::check_arg_params_len(name, arg_types.len(), 1)?;
+                match arg_types.first().unwrap() {
+                    Type::Array(..) | Type::Slice(_) => Type::Int,
+                    _ => {
+                        let e = message!(
+                            T004,
+                            "Expected an array or slice but got {}",
+                            TypeChecker::get_type_string(&arg_types)
+                        );
+                        return Err(TypeErrors::new_err(e));
+                    }
+                }
+            }
+            // ([T; n], fn(T) -> U) => [U; n] or ([T], fn(T) -> U) => [U]
+            MAP => {
+                TypeChecker::check_arg_params_len(name, arg_types.len(), 2)?;
+                match (arg_types.first().unwrap(), arg_types.get(1).unwrap()) {
+                    (Type::Array(elem_ty, len), Type::UserFn(f))
+                        if f.params == [*elem_ty.clone()] =>
+                    {
+                        Type::Array(Box::new(f.ret_type.clone()), *len)
+                    }
+                    (Type::Slice(elem_ty), Type::UserFn(f)) if f.params == [*elem_ty.clone()] => {
+                        Type::Slice(Box::new(f.ret_type.clone()))
+                    }
+                    _ => {
+                        let e = message!(
+                            T004,
+                            "Expected ([T; n] or [T], fn(T) -> U) but got {}",
+                            TypeChecker::get_type_string(&arg_types)
+                        );
+                        return Err(TypeErrors::new_err(e));
+                    }
+                }
+            }
+            // ([T; n] or [T], fn(T) -> bool) => [T]
+            FILTER => {
+                TypeChecker::check_arg_params_len(name, arg_types.len(), 2)?;
+                match (arg_types.first().unwrap(), arg_types.get(1).unwrap()) {
+                    (Type::Array(elem_ty, _) | Type::Slice(elem_ty), Type::UserFn(f))
+                        if f.params == [*elem_ty.clone()] && f.ret_type == Type::Bool =>
+                    {
+                        Type::Slice(elem_ty.clone())
+                    }
+                    _ => {
+                        let e = message!(
+                            T004,
+                            "Expected ([T; n] or [T], fn(T) -> bool) but got {}",
+                            TypeChecker::get_type_string(&arg_types)
+                        );
+                        return Err(TypeErrors::new_err(e));
+                    }
+                }
+            }
+            // ([T; n] or [T], U, fn(U, T) -> U) => U
+            FOLD => {
+                TypeChecker::check_arg_params_len(name, arg_types.len(), 3)?;
+                match (
+                    arg_types.first().unwrap(),
+                    arg_types.get(1).unwrap(),
+                    arg_types.get(2).unwrap(),
+                ) {
+                    (Type::Array(elem_ty, _) | Type::Slice(elem_ty), init_ty, Type::UserFn(f))
+                        if f.params == [init_ty.clone(), *elem_ty.clone()]
+                            && f.ret_type == *init_ty =>
+                    {
+                        init_ty.clone()
+                    }
+                    _ => {
+                        let e = message!(
+                            T004,
+                            "Expected ([T; n] or [T], U, fn(U, T) -> U) but got {}",
+                            TypeChecker::get_type_string(&arg_types)
+                        );
+                        return Err(TypeErrors::new_err(e));
+                    }
+                }
+            }
+            // ([T; n], fn(T, T) -> int) => [T; n] or ([T], fn(T, T) -> int) => [T]
+            SORT_BY => {
+                TypeChecker::check_arg_params_len(name, arg_types.len(), 2)?;
+                match (arg_types.first().unwrap(), arg_types.get(1).unwrap()) {
+                    (xs_ty @ (Type::Array(elem_ty, _) | Type::Slice(elem_ty)), Type::UserFn(f))
+                        if f.params == [*elem_ty.clone(), *elem_ty.clone()]
+                            && f.ret_type == Type::Int =>
+                    {
+                        xs_ty.clone()
+                    }
+                    _ => {
+                        let e = message!(
+                            T004,
+                            "Expected ([T; n] or [T], fn(T, T) -> int) but got {}",
+                            TypeChecker::get_type_string(&arg_types)
+                        );
+                        return Err(TypeErrors::new_err(e));
+                    }
+                }
+            }
+            // ([T; n] or [T], fn(T, T) -> int) => T
+            MIN_BY | MAX_BY => {
+                TypeChecker::check_arg_params_len(name, arg_types.len(), 2)?;
+                match (arg_types.first().unwrap(), arg_types.get(1).unwrap()) {
+                    (Type::Array(elem_ty, _) | Type::Slice(elem_ty), Type::UserFn(f))
+                        if f.params == [*elem_ty.clone(), *elem_ty.clone()]
+                            && f.ret_type == Type::Int =>
+                    {
+                        *elem_ty.clone()
+                    }
+                    _ => {
+                        let e = message!(
+                            T004,
+                            "Expected ([T; n] or [T], fn(T, T) -> int) but got {}",
+                            TypeChecker::get_type_string(&arg_types)
+                        );
+                        return Err(TypeErrors::new_err(e));
+                    }
+                }
+            }
             // (int, int) => int or (float, float) => float
             MIN => {
                 TypeChecker::check_arg_params_len(name, arg_types.len(), 2)?;
@@ -141,11 +586,12 @@ impl<'prog> TypeChecker<'prog> {
                     (Type::Int, Type::Int) => Type::Int,
                     (Type::Float, Type::Float) => Type::Float,
                     _ => {
-                        let e = format!(
+                        let e = message!(
+                            T004,
                             "Expected (int, int) or (float, float) but got {}",
                             TypeChecker::get_type_string(&arg_types)
                         );
-                        return Err(TypeErrors::new_err(&e));
+                        return Err(TypeErrors::new_err(e));
                     }
                 }
             }
@@ -156,11 +602,12 @@ impl<'prog> TypeChecker<'prog> {
                     (Type::Int, Type::Int) => Type::Int,
                     (Type::Float, Type::Float) => Type::Float,
                     _ => {
-                        let e = format!(
+                        let e = message!(
+                            T004,
                             "Expected (int, int) or (float, float) but got {}",
                             TypeChecker::get_type_string(&arg_types)
                         );
-                        return Err(TypeErrors::new_err(&e));
+                        return Err(TypeErrors::new_err(e));
                     }
                 }
             }
@@ -171,11 +618,12 @@ impl<'prog> TypeChecker<'prog> {
                     Type::Int => Type::Int,
                     Type::Float => Type::Float,
                     _ => {
-                        let e = format!(
+                        let e = message!(
+                            T004,
                             "Expected int or float but got {}",
                             TypeChecker::get_type_string(&arg_types)
                         );
-                        return Err(TypeErrors::new_err(&e));
+                        return Err(TypeErrors::new_err(e));
                     }
                 }
             }
@@ -185,11 +633,12 @@ impl<'prog> TypeChecker<'prog> {
                 match arg_types.first().unwrap() {
                     Type::Float => Type::Float,
                     _ => {
-                        let e = format!(
+                        let e = message!(
+                            T004,
                             "Expected float but got {}",
                             TypeChecker::get_type_string(&arg_types)
                         );
-                        return Err(TypeErrors::new_err(&e));
+                        return Err(TypeErrors::new_err(e));
                     }
                 }
             }
@@ -199,11 +648,12 @@ impl<'prog> TypeChecker<'prog> {
                 match arg_types.first().unwrap() {
                     Type::Float => Type::Float,
                     _ => {
-                        let e = format!(
+                        let e = message!(
+                            T004,
                             "Expected float but got {}",
                             TypeChecker::get_type_string(&arg_types)
                         );
-                        return Err(TypeErrors::new_err(&e));
+                        return Err(TypeErrors::new_err(e));
                     }
                 }
             }
@@ -213,11 +663,12 @@ impl<'prog> TypeChecker<'prog> {
                 match arg_types.first().unwrap() {
                     Type::Float => Type::Float,
                     _ => {
-                        let e = format!(
+                        let e = message!(
+                            T004,
                             "Expected float but got {}",
                             TypeChecker::get_type_string(&arg_types)
                         );
-                        return Err(TypeErrors::new_err(&e));
+                        return Err(TypeErrors::new_err(e));
                     }
                 }
             }
@@ -227,11 +678,12 @@ impl<'prog> TypeChecker<'prog> {
                 match arg_types.first().unwrap() {
                     Type::Float => Type::Float,
                     _ => {
-                        let e = format!(
+                        let e = message!(
+                            T004,
                             "Expected float but got {}",
                             TypeChecker::get_type_string(&arg_types)
                         );
-                        return Err(TypeErrors::new_err(&e));
+                        return Err(TypeErrors::new_err(e));
                     }
                 }
             }
@@ -241,11 +693,12 @@ impl<'prog> TypeChecker<'prog> {
                 match arg_types.first().unwrap() {
                     Type::Float => Type::Float,
                     _ => {
-                        let e = format!(
+                        let e = message!(
+                            T004,
                             "Expected float but got {}",
                             TypeChecker::get_type_string(&arg_types)
                         );
-                        return Err(TypeErrors::new_err(&e));
+                        return Err(TypeErrors::new_err(e));
                     }
                 }
             }
@@ -255,11 +708,42 @@ impl<'prog> TypeChecker<'prog> {
                 match (arg_types.first().unwrap(), arg_types.get(1).unwrap()) {
                     (Type::Float, Type::Float) => Type::Float,
                     _ => {
-                        let e = format!(
+                        let e = message!(
+                            T004,
                             "Expected (float, float) but got {}",
                             TypeChecker::get_type_string(&arg_types)
                         );
-                        return Err(TypeErrors::new_err(&e));
+                        return Err(TypeErrors::new_err(e));
+                    }
+                }
+            }
+            // int, int => int
+            DIV_EUCLID | REM_EUCLID => {
+                TypeChecker::check_arg_params_len(name, arg_types.len(), 2)?;
+                match (arg_types.first().unwrap(), arg_types.get(1).unwrap()) {
+                    (Type::Int, Type::Int) => Type::Int,
+                    _ => {
+                        let e = message!(
+                            T004,
+                            "Expected (int, int) but got {}",
+                            TypeChecker::get_type_string(&arg_types)
+                        );
+                        return Err(TypeErrors::new_err(e));
+                    }
+                }
+            }
+            // int, int => (int, int)
+            DIVMOD => {
+                TypeChecker::check_arg_params_len(name, arg_types.len(), 2)?;
+                match (arg_types.first().unwrap(), arg_types.get(1).unwrap()) {
+                    (Type::Int, Type::Int) => Type::Tuple(vec![Type::Int, Type::Int]),
+                    _ => {
+                        let e = message!(
+                            T004,
+                            "Expected (int, int) but got {}",
+                            TypeChecker::get_type_string(&arg_types)
+                        );
+                        return Err(TypeErrors::new_err(e));
                     }
                 }
             }
@@ -269,27 +753,30 @@ impl<'prog> TypeChecker<'prog> {
                 match arg_types.first().unwrap() {
                     Type::Int => Type::String,
                     _ => {
-                        let e = format!(
+                        let e = message!(
+                            T004,
                             "Expected int but got {}",
                             TypeChecker::get_type_string(&arg_types)
                         );
-                        return Err(TypeErrors::new_err(&e));
+                        return Err(TypeErrors::new_err(e));
                     }
                 }
             }
             // string -> int
+            // string -> result[int, str], Err with why the string isn't an int
             ATOI => {
-                TypeChecker::check_arg_params_len(name, arg_types.len(), 1)?;
-                match arg_types.first().unwrap() {
-                    Type::String => Type::Int,
-                    _ => {
-                        let e = format!(
-                            "Expected string but got {}",
-                            TypeChecker::get_type_string(&arg_types)
-                        );
-                        return Err(TypeErrors::new_err(&e));
-                    }
-                }
+                TypeChecker::check_arg_params_match(name, &arg_types, &[Type::String])?;
+                Type::Result(Box::new(Type::Int), Box::new(Type::String))
+            }
+            // (float, int) -> string, where the int is the digits after the point
+            FTOA => {
+                TypeChecker::check_arg_params_match(name, &arg_types, &[Type::Float, Type::Int])?;
+                Type::String
+            }
+            // string -> result[float, str], Err with why the string isn't a float
+            ATOF => {
+                TypeChecker::check_arg_params_match(name, &arg_types, &[Type::String])?;
+                TypeChecker::str_result(Type::Float)
             }
             // float -> int
             FLOAT_TO_INT => {
@@ -297,11 +784,12 @@ impl<'prog> TypeChecker<'prog> {
                 match arg_types.first().unwrap() {
                     Type::Float => Type::Int,
                     _ => {
-                        let e = format!(
+                        let e = message!(
+                            T004,
                             "Expected float but got {}",
                             TypeChecker::get_type_string(&arg_types)
                         );
-                        return Err(TypeErrors::new_err(&e));
+                        return Err(TypeErrors::new_err(e));
                     }
                 }
             }
@@ -311,11 +799,12 @@ impl<'prog> TypeChecker<'prog> {
                 match arg_types.first().unwrap() {
                     Type::Int => Type::Float,
                     _ => {
-                        let e = format!(
+                        let e = message!(
+                            T004,
                             "Expected int but got {}",
                             TypeChecker::get_type_string(&arg_types)
                         );
-                        return Err(TypeErrors::new_err(&e));
+                        return Err(TypeErrors::new_err(e));
                     }
                 }
             }
@@ -329,6 +818,479 @@ impl<'prog> TypeChecker<'prog> {
                 // Fill out this block
                 todo!()
             }
+            // () -> mutex
+            MUTEX => {
+                TypeChecker::check_arg_params_len(name, arg_types.len(), 0)?;
+                Type::Mutex
+            }
+            // () -> chan[T], where T comes from the annotation of the let. see check_let
+            CHAN => {
+                let e = message!(
+                    T006,
+                    "{}() needs a type annotation e.g let c: chan[int] = {}();",
+                    CHAN,
+                    CHAN
+                );
+                return Err(TypeErrors::new_err(e));
+            }
+            // (chan[T], T) -> ()
+            SEND => {
+                TypeChecker::check_arg_params_len(name, arg_types.len(), 2)?;
+                match arg_types.first().unwrap() {
+                    Type::Channel(elem_ty) => {
+                        TypeChecker::check_arg_params_match(
+                            name,
+                            &arg_types,
+                            &[Type::Channel(elem_ty.clone()), *elem_ty.clone()],
+                        )?;
+                        Type::Unit
+                    }
+                    _ => {
+                        let e = message!(
+                            T004,
+                            "Expected (chan[T], T) but got {}",
+                            TypeChecker::get_type_string(&arg_types)
+                        );
+                        return Err(TypeErrors::new_err(e));
+                    }
+                }
+            }
+            // chan[T] -> T
+            RECV => {
+                TypeChecker::check_arg_params_len(name, arg_types.len(), 1)?;
+                match arg_types.first().unwrap() {
+                    Type::Channel(elem_ty) => *elem_ty.clone(),
+                    _ => {
+                        let e = message!(
+                            T004,
+                            "Expected a channel but got {}",
+                            TypeChecker::get_type_string(&arg_types)
+                        );
+                        return Err(TypeErrors::new_err(e));
+                    }
+                }
+            }
+            // chan[T] -> option[T], None instead of blocking if nothing is waiting
+            TRY_RECV => {
+                TypeChecker::check_arg_params_len(name, arg_types.len(), 1)?;
+                match arg_types.first().unwrap() {
+                    Type::Channel(elem_ty) => Type::Option(elem_ty.clone()),
+                    _ => {
+                        let e = message!(
+                            T004,
+                            "Expected a channel but got {}",
+                            TypeChecker::get_type_string(&arg_types)
+                        );
+                        return Err(TypeErrors::new_err(e));
+                    }
+                }
+            }
+            // T -> option[T]
+            SOME => {
+                TypeChecker::check_arg_params_len(name, arg_types.len(), 1)?;
+                Type::Option(Box::new(arg_types[0].clone()))
+            }
+            // T -> result[T, _], the type of the error is given by where the result goes
+            OK => {
+                TypeChecker::check_arg_params_len(name, arg_types.len(), 1)?;
+                Type::Result(Box::new(arg_types[0].clone()), Box::new(Type::Infer))
+            }
+            // E -> result[_, E]
+            ERR => {
+                TypeChecker::check_arg_params_len(name, arg_types.len(), 1)?;
+                Type::Result(Box::new(Type::Infer), Box::new(arg_types[0].clone()))
+            }
+            // str -> result[socket, str]
+            TCP_CONNECT | TCP_LISTEN | UDP_BIND => {
+                TypeChecker::check_arg_params_match(name, &arg_types, &[Type::String])?;
+                TypeChecker::str_result(Type::Socket)
+            }
+            // socket -> result[socket, str]
+            TCP_ACCEPT => {
+                TypeChecker::check_arg_params_match(name, &arg_types, &[Type::Socket])?;
+                TypeChecker::str_result(Type::Socket)
+            }
+            // (socket, str) -> result[int, str], the number of bytes sent
+            TCP_SEND => {
+                TypeChecker::check_arg_params_match(
+                    name,
+                    &arg_types,
+                    &[Type::Socket, Type::String],
+                )?;
+                TypeChecker::str_result(Type::Int)
+            }
+            // (socket, str, str) -> result[int, str], the number of bytes sent
+            UDP_SEND_TO => {
+                TypeChecker::check_arg_params_match(
+                    name,
+                    &arg_types,
+                    &[Type::Socket, Type::String, Type::String],
+                )?;
+                TypeChecker::str_result(Type::Int)
+            }
+            // socket -> result[str, str]
+            TCP_RECV | UDP_RECV => {
+                TypeChecker::check_arg_params_match(name, &arg_types, &[Type::Socket])?;
+                TypeChecker::str_result(Type::String)
+            }
+            // socket -> ()
+            CLOSE => {
+                TypeChecker::check_arg_params_match(name, &arg_types, &[Type::Socket])?;
+                Type::Unit
+            }
+            // str -> result[HttpResponse, str]
+            HTTP_GET => {
+                TypeChecker::check_arg_params_match(name, &arg_types, &[Type::String])?;
+                TypeChecker::str_result(Type::Struct(HTTP_RESPONSE.to_string()))
+            }
+            // (str, str) -> result[HttpResponse, str]
+            HTTP_POST => {
+                TypeChecker::check_arg_params_match(
+                    name,
+                    &arg_types,
+                    &[Type::String, Type::String],
+                )?;
+                TypeChecker::str_result(Type::Struct(HTTP_RESPONSE.to_string()))
+            }
+            // () -> [string]
+            ARGS => {
+                TypeChecker::check_arg_params_match(name, &arg_types, &[])?;
+                Type::Slice(Box::new(Type::String))
+            }
+            // string -> string, empty if not set
+            ENV_VAR => {
+                TypeChecker::check_arg_params_match(name, &arg_types, &[Type::String])?;
+                Type::String
+            }
+            // (bool, str) -> (), stops the program with the message if the bool is false
+            ASSERT => {
+                TypeChecker::check_arg_params_match(name, &arg_types, &[Type::Bool, Type::String])?;
+                Type::Unit
+            }
+            // (str, [str; n] or [str], int) -> result[CommandOutput, str]
+            RUN_COMMAND => {
+                TypeChecker::check_arg_params_len(name, arg_types.len(), 3)?;
+                match (&arg_types[0], &arg_types[1], &arg_types[2]) {
+                    (Type::String, Type::Array(elem_ty, _) | Type::Slice(elem_ty), Type::Int)
+                        if **elem_ty == Type::String =>
+                    {
+                        TypeChecker::str_result(Type::Struct(COMMAND_OUTPUT.to_string()))
+                    }
+                    _ => {
+                        let e = message!(
+                            T004,
+                            "Expected (str, [str; n] or [str], int) but got {}",
+                            TypeChecker::get_type_string(&arg_types)
+                        );
+                        return Err(TypeErrors::new_err(e));
+                    }
+                }
+            }
+            // int -> bytes
+            BYTES => {
+                TypeChecker::check_arg_params_match(name, &arg_types, &[Type::Int])?;
+                Type::Bytes
+            }
+            // bytes -> int
+            BYTES_LEN => {
+                TypeChecker::check_arg_params_match(name, &arg_types, &[Type::Bytes])?;
+                Type::Int
+            }
+            // (bytes, int) -> int
+            GET_BYTE => {
+                TypeChecker::check_arg_params_match(name, &arg_types, &[Type::Bytes, Type::Int])?;
+                Type::Int
+            }
+            // (bytes, int, int) -> ()
+            SET_BYTE => {
+                TypeChecker::check_arg_params_match(
+                    name,
+                    &arg_types,
+                    &[Type::Bytes, Type::Int, Type::Int],
+                )?;
+                Type::Unit
+            }
+            // str -> bytes
+            BYTES_FROM_STRING => {
+                TypeChecker::check_arg_params_match(name, &arg_types, &[Type::String])?;
+                Type::Bytes
+            }
+            // str -> result[bytes, str], Err with why the file couldn't be read
+            READ_BYTES => {
+                TypeChecker::check_arg_params_match(name, &arg_types, &[Type::String])?;
+                Type::Result(Box::new(Type::Bytes), Box::new(Type::String))
+            }
+            // (bytes, str) -> str, where the second is the encoding
+            STRING_FROM_BYTES => {
+                TypeChecker::check_arg_params_match(
+                    name,
+                    &arg_types,
+                    &[Type::Bytes, Type::String],
+                )?;
+                Type::String
+            }
+            // (str, bytes) -> result[(), str]
+            WRITE_BYTES => {
+                TypeChecker::check_arg_params_match(
+                    name,
+                    &arg_types,
+                    &[Type::String, Type::Bytes],
+                )?;
+                Type::Result(Box::new(Type::Unit), Box::new(Type::String))
+            }
+            // int -> (), the number of ms to sleep for
+            SLEEP => {
+                TypeChecker::check_arg_params_match(name, &arg_types, &[Type::Int])?;
+                Type::Unit
+            }
+            // T -> int, for a T made of values with contents to hash
+            HASH => {
+                TypeChecker::check_arg_params_len(name, arg_types.len(), 1)?;
+                if !is_hashable(&arg_types[0]) {
+                    let e = message!(
+                        T004,
+                        "Can't hash a value of type {}, since it has no contents to hash",
+                        arg_types[0]
+                    );
+                    return Err(TypeErrors::new_err(e));
+                }
+                Type::Int
+            }
+            // bytes -> int
+            CRC32 => {
+                TypeChecker::check_arg_params_match(name, &arg_types, &[Type::Bytes])?;
+                Type::Int
+            }
+            // bytes -> str, the digest in hex
+            SHA256 => {
+                TypeChecker::check_arg_params_match(name, &arg_types, &[Type::Bytes])?;
+                Type::String
+            }
+            // tid -> ()
+            DETACH => {
+                TypeChecker::check_arg_params_match(name, &arg_types, &[Type::ThreadId])?;
+                Type::Unit
+            }
+            // T -> str, for any T
+            INSPECT => {
+                TypeChecker::check_arg_params_len(name, arg_types.len(), 1)?;
+                Type::String
+            }
+            // (T, int) -> str, where the second is the max depth
+            INSPECT_DEPTH => {
+                TypeChecker::check_arg_params_len(name, arg_types.len(), 2)?;
+                if arg_types[1] != Type::Int {
+                    let e = message!(
+                        T004,
+                        "Expected (T, int) but got {}",
+                        TypeChecker::get_type_string(&arg_types)
+                    );
+                    return Err(TypeErrors::new_err(e));
+                }
+                Type::String
+            }
+            // () -> pvec[T] and () -> pmap[K, V], where the types come from the annotation of the let. see check_let
+            PVEC | PMAP => {
+                let e = message!(
+                    T006,
+                    "{}() needs a type annotation e.g let v: pvec[int] = {}();",
+                    name,
+                    name
+                );
+                return Err(TypeErrors::new_err(e));
+            }
+            // (pvec[T], T) -> pvec[T]
+            PERSIST_PUSH => match arg_types.first() {
+                Some(Type::PVec(elem_ty)) => {
+                    let vec_ty = Type::PVec(elem_ty.clone());
+                    TypeChecker::check_arg_params_match(
+                        name,
+                        &arg_types,
+                        &[vec_ty.clone(), *elem_ty.clone()],
+                    )?;
+                    vec_ty
+                }
+                _ => {
+                    let e = message!(
+                        T004,
+                        "Expected (pvec[T], T) but got {}",
+                        TypeChecker::get_type_string(&arg_types)
+                    );
+                    return Err(TypeErrors::new_err(e));
+                }
+            },
+            // (pvec[T], int, T) -> pvec[T]
+            PERSIST_SET => match arg_types.first() {
+                Some(Type::PVec(elem_ty)) => {
+                    let vec_ty = Type::PVec(elem_ty.clone());
+                    TypeChecker::check_arg_params_match(
+                        name,
+                        &arg_types,
+                        &[vec_ty.clone(), Type::Int, *elem_ty.clone()],
+                    )?;
+                    vec_ty
+                }
+                _ => {
+                    let e = message!(
+                        T004,
+                        "Expected (pvec[T], int, T) but got {}",
+                        TypeChecker::get_type_string(&arg_types)
+                    );
+                    return Err(TypeErrors::new_err(e));
+                }
+            },
+            // (pmap[K, V], K, V) -> pmap[K, V]
+            PERSIST_INSERT => match arg_types.first() {
+                Some(Type::PMap(key_ty, val_ty)) => {
+                    let map_ty = Type::PMap(key_ty.clone(), val_ty.clone());
+                    TypeChecker::check_arg_params_match(
+                        name,
+                        &arg_types,
+                        &[map_ty.clone(), *key_ty.clone(), *val_ty.clone()],
+                    )?;
+                    map_ty
+                }
+                _ => {
+                    let e = message!(
+                        T004,
+                        "Expected (pmap[K, V], K, V) but got {}",
+                        TypeChecker::get_type_string(&arg_types)
+                    );
+                    return Err(TypeErrors::new_err(e));
+                }
+            },
+            // (pmap[K, V], K) -> pmap[K, V] for remove, bool for contains
+            PERSIST_REMOVE | PERSIST_CONTAINS => match arg_types.first() {
+                Some(Type::PMap(key_ty, val_ty)) => {
+                    let map_ty = Type::PMap(key_ty.clone(), val_ty.clone());
+                    TypeChecker::check_arg_params_match(
+                        name,
+                        &arg_types,
+                        &[map_ty.clone(), *key_ty.clone()],
+                    )?;
+                    if name == PERSIST_REMOVE {
+                        map_ty
+                    } else {
+                        Type::Bool
+                    }
+                }
+                _ => {
+                    let e = message!(
+                        T004,
+                        "Expected (pmap[K, V], K) but got {}",
+                        TypeChecker::get_type_string(&arg_types)
+                    );
+                    return Err(TypeErrors::new_err(e));
+                }
+            },
+            // (pvec[T], int) -> T or (pmap[K, V], K) -> V
+            PERSIST_GET => match arg_types.first() {
+                Some(Type::PVec(elem_ty)) => {
+                    TypeChecker::check_arg_params_match(
+                        name,
+                        &arg_types,
+                        &[Type::PVec(elem_ty.clone()), Type::Int],
+                    )?;
+                    *elem_ty.clone()
+                }
+                Some(Type::PMap(key_ty, val_ty)) => {
+                    TypeChecker::check_arg_params_match(
+                        name,
+                        &arg_types,
+                        &[Type::PMap(key_ty.clone(), val_ty.clone()), *key_ty.clone()],
+                    )?;
+                    *val_ty.clone()
+                }
+                _ => {
+                    let e = message!(
+                        T004,
+                        "Expected (pvec[T], int) or (pmap[K, V], K) but got {}",
+                        TypeChecker::get_type_string(&arg_types)
+                    );
+                    return Err(TypeErrors::new_err(e));
+                }
+            },
+            // pvec[T] -> int or pmap[K, V] -> int
+            PERSIST_LEN => {
+                TypeChecker::check_arg_params_len(name, arg_types.len(), 1)?;
+                match arg_types.first().unwrap() {
+                    Type::PVec(_) | Type::PMap(_, _) => Type::Int,
+                    _ => {
+                        let e = message!(
+                            T004,
+                            "Expected a pvec or pmap but got {}",
+                            TypeChecker::get_type_string(&arg_types)
+                        );
+                        return Err(TypeErrors::new_err(e));
+                    }
+                }
+            }
+            // () -> map[K, V], where the types come from the annotation of the let like pmap()
+            MAP_NEW => {
+                let e = message!(
+                    T006,
+                    "map_new() needs a type annotation e.g let m: map[str, int] = map_new();"
+                );
+                return Err(TypeErrors::new_err(e));
+            }
+            // (map[K, V], K, V) -> ()
+            MAP_INSERT => match arg_types.first() {
+                Some(Type::Map(key_ty, val_ty)) => {
+                    TypeChecker::check_arg_params_match(
+                        name,
+                        &arg_types,
+                        &[
+                            Type::Map(key_ty.clone(), val_ty.clone()),
+                            *key_ty.clone(),
+                            *val_ty.clone(),
+                        ],
+                    )?;
+                    Type::Unit
+                }
+                _ => {
+                    let e = message!(
+                        T004,
+                        "Expected (map[K, V], K, V) but got {}",
+                        TypeChecker::get_type_string(&arg_types)
+                    );
+                    return Err(TypeErrors::new_err(e));
+                }
+            },
+            // (map[K, V], K) -> option[V], the value the key had for remove
+            MAP_GET | MAP_REMOVE => match arg_types.first() {
+                Some(Type::Map(key_ty, val_ty)) => {
+                    TypeChecker::check_arg_params_match(
+                        name,
+                        &arg_types,
+                        &[Type::Map(key_ty.clone(), val_ty.clone()), *key_ty.clone()],
+                    )?;
+                    Type::Option(val_ty.clone())
+                }
+                _ => {
+                    let e = message!(
+                        T004,
+                        "Expected (map[K, V], K) but got {}",
+                        TypeChecker::get_type_string(&arg_types)
+                    );
+                    return Err(TypeErrors::new_err(e));
+                }
+            },
+            // map[K, V] -> int for len, [K] for keys
+            MAP_LEN | MAP_KEYS => {
+                TypeChecker::check_arg_params_len(name, arg_types.len(), 1)?;
+                match arg_types.first().unwrap() {
+                    Type::Map(key_ty, _) if name == MAP_KEYS => Type::Slice(key_ty.clone()),
+                    Type::Map(_, _) => Type::Int,
+                    _ => {
+                        let e = message!(
+                            T004,
+                            "Expected a map but got {}",
+                            TypeChecker::get_type_string(&arg_types)
+                        );
+                        return Err(TypeErrors::new_err(e));
+                    }
+                }
+            }
             _ => todo!(),
         };
 
@@ -371,10 +1333,21 @@ impl<'prog> TypeChecker<'prog> {
             return Err(ty_errs);
         }
 
+        if let (FORMAT, Some(Expr::StringLiteral(fmt))) =
+            (fn_call.name.as_str(), fn_call.args.first())
+        {
+            check_format_args(fmt, fn_call.args.len() - 1)?;
+        }
+
         if TypeChecker::is_builtin_fn(&fn_call.name) {
             return self.check_builtin_fn_call(&fn_call.name, arg_types, check_res);
         }
 
+        if let Some(variant_ty) = self.check_variant_call(&fn_call.name, &arg_types) {
+            check_res.ty = variant_ty?;
+            return Ok(check_res);
+        }
+
         // User fn
 
         // Check arg and params match
@@ -385,7 +1358,15 @@ impl<'prog> TypeChecker<'prog> {
             let param_types: Vec<Type> = ty.params.iter().map(|x| x.to_owned()).collect();
 
             TypeChecker::check_arg_params_match(&fn_call.name, &arg_types, &param_types)?;
-            check_res.ty = ty.ret_type;
+
+            // a fn without `-> T` called from its own body, before its ret type is inferred. Assume (), and
+            // check the assumption once it is inferred
+            if ty.ret_type.eq(&Type::Unitialised) {
+                self.called_before_inferred.insert(fn_call.name.to_owned());
+                check_res.ty = Type::Unit;
+            } else {
+                check_res.ty = ty.ret_type;
+            }
         }
         // dbg!("fn_ty", fn_ty);
         // check_res.ty = fn_ty;
@@ -398,7 +1379,7 @@ impl<'prog> TypeChecker<'prog> {
 mod tests {
     use parser::structs::Type;
 
-    use crate::type_checker::{expect_err, expect_pass};
+    use crate::type_checker::{expect_err, expect_pass, expect_pass_str};
 
     use super::BUILTINS;
 
@@ -459,6 +1440,29 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_type_check_pipeline() {
+        // each stage is checked as the call it desugars to
+        let t = r"
+        fn double(x: int) -> int {
+            x * 2
+        }
+        fn add(x: int, y: int) -> int {
+            x + y
+        }
+        3 |> double |> add(1) |> itoa
+        ";
+        expect_pass(t, Type::String);
+
+        let t = r"
+        fn double(x: int) -> int {
+            x * 2
+        }
+        3 |> itoa |> double
+        ";
+        expect_err(t, "Mismatched types in function call", true);
+    }
+
     #[test]
     fn test_type_check_builtin_functions() {
         expect_pass("let x : () = print(2); x", Type::Unit);
@@ -493,11 +1497,35 @@ mod tests {
         // Test pow
         expect_pass("let x : float = pow(2.0, 3.0); x", Type::Float);
 
+        // Test div_euclid, rem_euclid and divmod
+        expect_pass("div_euclid(-7, 2) + rem_euclid(-7, 2)", Type::Int);
+        expect_pass("let (q, r) = divmod(17, 5); q * 5 + r", Type::Int);
+        expect_err(
+            "divmod(7.0, 2)",
+            "Expected (int, int) but got (float, int)",
+            true,
+        );
+
         // Test itoa
         // expect_pass("let x : string = itoa(123); x", Type::String);
 
         // Test atoi
-        // expect_pass("let x : int = atoi(\"123\"); x", Type::Int);
+        expect_pass(
+            "let x : result[int, str] = atoi(\"123\"); x",
+            Type::Result(Box::new(Type::Int), Box::new(Type::String)),
+        );
+
+        // Test ftoa and atof
+        expect_pass("let x : str = ftoa(3.5, 2); x", Type::String);
+        expect_pass(
+            "let x : result[float, str] = atof(\"3.5\"); x",
+            Type::Result(Box::new(Type::Float), Box::new(Type::String)),
+        );
+        expect_err(
+            "ftoa(3, 2)",
+            "got ((int, int)) but expected ((float, int))",
+            true,
+        );
 
         // Test float_to_int
         expect_pass("let x : int = float_to_int(3.5); x", Type::Int);
@@ -508,4 +1536,406 @@ mod tests {
         // Test sem
         expect_pass("let x = sem_create(); x", Type::Semaphore);
     }
+
+    #[test]
+    fn test_type_check_channels() {
+        let t = r"
+        let c : chan[int] = chan();
+        fn produce(c: chan[int]) {
+            send(c, 2);
+        }
+        spawn produce(c);
+        recv(c) + 1
+        ";
+        expect_pass(t, Type::Int);
+
+        let t = "let c : chan[[bool; 2]] = chan(); send(c, [true, false]); c";
+        expect_pass_str(t, "chan[[bool; 2]]");
+
+        expect_err(
+            "let c = chan();",
+            "chan() needs a type annotation e.g let c: chan[int] = chan();",
+            true,
+        );
+        expect_err(
+            "let c : chan[int] = chan(); send(c, true);",
+            "Mismatched types in function call: got ((chan[int], bool)) but expected ((chan[int], int))",
+            true,
+        );
+        expect_err(
+            "send(2, 3)",
+            "Expected (chan[T], T) but got (int, int)",
+            true,
+        );
+        expect_err("recv(2)", "Expected a channel but got (int)", true);
+        expect_err("let c : chan[Q] = chan();", "Unknown type 'Q'", true);
+    }
+
+    #[test]
+    fn test_type_check_net() {
+        let t = r#"
+        fn echo(server: socket) {
+            let conn = match tcp_accept(server) { Ok(conn) => conn, Err(_) => { return; } };
+            let data: result[str, str] = tcp_recv(conn);
+            match data {
+                Ok(data) => { let n: result[int, str] = tcp_send(conn, data); }
+                Err(e) => { println(e); }
+            }
+            close(conn);
+        }
+        match tcp_listen("127.0.0.1:8080") {
+            Ok(server) => { echo(server); }
+            Err(e) => { println(e); }
+        }
+        let udp: result[socket, str] = udp_bind("127.0.0.1:0");
+        match udp {
+            Ok(udp) => match udp_send_to(udp, "127.0.0.1:9000", "hi") {
+                Ok(n) => n,
+                Err(_) => 0,
+            },
+            Err(_) => 0,
+        }
+        "#;
+        expect_pass(t, Type::Int);
+
+        expect_err(
+            "tcp_connect(8080)",
+            "Mismatched types in function call: got ((int)) but expected ((str))",
+            true,
+        );
+        expect_err(
+            r#"tcp_send(tcp_connect("127.0.0.1:80"), 2)"#,
+            "Mismatched types in function call",
+            true,
+        );
+        expect_err("close(2)", "Mismatched types in function call", true);
+    }
+
+    #[test]
+    fn test_type_check_http() {
+        let t = r#"
+        fn fetch(url: str) -> HttpResponse {
+            match http_get(url) {
+                Ok(res) => res,
+                Err(e) => HttpResponse { status: 0, body: e },
+            }
+        }
+        let res = fetch("http://127.0.0.1:8080/");
+        match http_post("http://127.0.0.1:8080/", res.body) {
+            Ok(posted) => if posted.status == 200 { string_len(posted.body) } else { res.status },
+            Err(_) => 0,
+        }
+        "#;
+        expect_pass(t, Type::Int);
+
+        expect_err(
+            r#"http_post("http://127.0.0.1:8080/")"#,
+            "Function 'http_post' takes 2 arguments but 1 were supplied",
+            true,
+        );
+        expect_err(
+            r#"match http_get("http://127.0.0.1:8080/") { Ok(res) => res.headers, Err(_) => 0 }"#,
+            "headers",
+            true,
+        );
+        expect_err(
+            "struct HttpResponse { code: int }",
+            "Struct 'HttpResponse' is already declared",
+            true,
+        );
+    }
+
+    #[test]
+    fn test_type_check_run_command() {
+        let t = r#"
+        match run_command("echo", ["hi", "there"], 1000) {
+            Ok(res) => if res.code == 0 { res.stdout } else { res.stderr },
+            Err(e) => e,
+        }
+        "#;
+        expect_pass(t, Type::String);
+
+        let t = r#"let cmd_args = ["-c", "exit 3", "x"]; run_command("sh", cmd_args[..2], 0)"#;
+        expect_pass(
+            t,
+            Type::Result(
+                Box::new(Type::Struct("CommandOutput".to_string())),
+                Box::new(Type::String),
+            ),
+        );
+
+        expect_err(
+            r#"run_command("echo", [1, 2], 0)"#,
+            "Expected (str, [str; n] or [str], int) but got (str, [int; 2], int)",
+            true,
+        );
+        expect_err(
+            r#"run_command("echo", ["hi"])"#,
+            "Function 'run_command' takes 3 arguments but 2 were supplied",
+            true,
+        );
+    }
+
+    #[test]
+    fn test_type_check_bytes() {
+        let t = r#"
+        let b: bytes = bytes(4);
+        set_byte(b, 0, 255);
+        let copy = match read_bytes("in.bin") {
+            Ok(b) => b,
+            Err(_) => bytes(0),
+        };
+        write_bytes("out.bin", copy);
+        string_from_bytes(bytes_from_string("hi"), "utf-8");
+        get_byte(b, 0) + bytes_len(copy)
+        "#;
+        expect_pass(t, Type::Int);
+
+        expect_err(
+            "let b = bytes(4); set_byte(b, 0, true)",
+            "got ((bytes, int, bool)) but expected ((bytes, int, int))",
+            true,
+        );
+        expect_err(r#"let s: str = bytes_from_string("hi"); s"#, "bytes", true);
+    }
+
+    #[test]
+    fn test_type_check_hash() {
+        expect_pass("hash(1) + hash(\"key\") + hash([[1.5], [2.0]])", Type::Int);
+        expect_pass("crc32(bytes_from_string(\"abc\"))", Type::Int);
+        expect_pass_str("sha256(bytes(4))", "str");
+
+        expect_err(
+            "hash(|x: int| x)",
+            "Can't hash a value of type fn(int) -> int",
+            true,
+        );
+        expect_err(
+            "let c: chan[int] = chan(); hash([c])",
+            "Can't hash a value of type [chan[int]; 1]",
+            true,
+        );
+        expect_err(
+            "sha256(\"abc\")",
+            "got ((str)) but expected ((bytes))",
+            true,
+        );
+    }
+
+    #[test]
+    fn test_type_check_detach() {
+        let t = r"
+        fn work() {}
+        let t = spawn work();
+        let x: () = detach(t);
+        x
+        ";
+        expect_pass(t, Type::Unit);
+        expect_err("detach(2)", "got ((int)) but expected ((tid))", true);
+    }
+
+    #[test]
+    fn test_type_check_inspect() {
+        expect_pass_str("let s: str = inspect([1, 2]); inspect(|x: int| x)", "str");
+        expect_pass_str(r#"inspect_depth("a", 2)"#, "str");
+        expect_err(
+            "inspect(1, 2)",
+            "Function 'inspect' takes 1 arguments but 2 were supplied",
+            true,
+        );
+        expect_err(
+            "inspect_depth([1], true)",
+            "Expected (T, int) but got ([int; 1], bool)",
+            true,
+        );
+    }
+
+    #[test]
+    fn test_type_check_format() {
+        expect_pass_str(r#"let x = 2; format("{} and {x}", 1.5)"#, "str");
+        expect_pass_str(r#"let f = "{}"; format(f, true, "s")"#, "str");
+        expect_err(
+            "format(1)",
+            "Expected a format string and the values to put in it but got (int)",
+            true,
+        );
+        expect_err(
+            r#"format("{} {}", 1)"#,
+            "Format string \"{} {}\" has 2 placeholders but 1 arguments were supplied",
+            true,
+        );
+        expect_err(
+            r#"format("{", 1)"#,
+            "Unmatched '{' in format string \"{\", use '{{' for a brace",
+            true,
+        );
+        // a name in braces has to be declared
+        expect_err(r#"format("{y}")"#, "Identifier 'y' not declared", true);
+    }
+
+    #[test]
+    fn test_type_check_string_lib() {
+        let t = r#"
+        let s = trim("  Hello, World ");
+        let parts = split(to_lower(s), ", ");
+        let i = find(s, "W");
+        contains(parts[0], "he") && substring(replace(s, "l", "L"), i, 5) == "World"
+        "#;
+        expect_pass(t, Type::Bool);
+        expect_pass_str(r#"split("a b", " ")"#, "[str]");
+        expect_pass_str(r#"chars("ab")"#, "[char]");
+        expect_pass_str(r#"char_at("ab", 1)"#, "option[char]");
+        expect_err(
+            r#"char_at("ab", '1')"#,
+            "got ((str, char)) but expected ((str, int))",
+            true,
+        );
+        expect_err(
+            "'a' + 'b'",
+            "Can't apply '+' to types 'char' and 'char'",
+            true,
+        );
+        expect_err(
+            r#"substring("abc", 1)"#,
+            "Function 'substring' takes 3 arguments but 2 were supplied",
+            true,
+        );
+        expect_err("to_upper(1)", "got ((int)) but expected ((str))", true);
+    }
+
+    #[test]
+    fn test_type_check_args() {
+        let t = r#"
+        let argv = args();
+        slice_len(argv) > 0 && argv[0] == env_var("HOME")
+        "#;
+        expect_pass(t, Type::Bool);
+        expect_err(
+            "env_var()",
+            "Function 'env_var' takes 1 arguments but 0 were supplied",
+            true,
+        );
+    }
+
+    #[test]
+    fn test_type_check_assert() {
+        expect_pass(r#"let x: () = assert(1 < 2, "ok"); x"#, Type::Unit);
+        expect_err(
+            r#"assert(1, "ok")"#,
+            "got ((int, str)) but expected ((bool, str))",
+            true,
+        );
+    }
+
+    #[test]
+    fn test_type_check_persist() {
+        let t = r"
+        let v: pvec[int] = pvec();
+        let v2 = persist_set(persist_push(v, 1), 0, 2);
+        persist_get(v2, 0) + persist_len(v)
+        ";
+        expect_pass(t, Type::Int);
+
+        let t = r#"
+        let m: pmap[str, bool] = pmap();
+        let m2 = persist_remove(persist_insert(m, "a", true), "b");
+        persist_contains(m2, "a") && persist_get(m2, "a")
+        "#;
+        expect_pass(t, Type::Bool);
+
+        expect_err(
+            "let v = pvec();",
+            "pvec() needs a type annotation e.g let v: pvec[int] = pvec();",
+            true,
+        );
+        expect_err(
+            "let v: pvec[int] = pvec(); persist_push(v, true)",
+            "Mismatched types in function call: got ((pvec[int], bool)) but expected ((pvec[int], int))",
+            true,
+        );
+        expect_err(
+            "let m: pmap[str, int] = pmap(); persist_get(m, 1)",
+            "Mismatched types in function call: got ((pmap[str, int], int)) but expected ((pmap[str, int], str))",
+            true,
+        );
+        expect_err(
+            "persist_len([1, 2])",
+            "Expected a pvec or pmap but got ([int; 2])",
+            true,
+        );
+        expect_err(
+            "let m: pmap[float, int] = pmap();",
+            "Keys of a pmap must be int, str or bool, got float",
+            true,
+        );
+    }
+
+    #[test]
+    fn test_type_check_sort_by() {
+        let t = "sort_by([3, 1, 2], |a: int, b: int| a - b)";
+        expect_pass_str(t, "[int; 3]");
+
+        let t = "let xs = [1.5, 0.5]; sort_by(xs[..], |a: float, b: float| float_to_int(a - b))";
+        expect_pass_str(t, "[float]");
+
+        let t = r#"min_by(["a", "bc"], |a: str, b: str| string_len(a) - string_len(b))"#;
+        expect_pass(t, Type::String);
+        expect_pass("max_by([true], |a: bool, b: bool| 0)", Type::Bool);
+
+        expect_err(
+            "sort_by([1, 2], |a: int, b: int| a < b)",
+            "Expected ([T; n] or [T], fn(T, T) -> int) but got ([int; 2], fn(int, int) -> bool)",
+            true,
+        );
+        expect_err(
+            "min_by([1, 2], |a: int| a)",
+            "Expected ([T; n] or [T], fn(T, T) -> int) but got ([int; 2], fn(int) -> int)",
+            true,
+        );
+    }
+
+    #[test]
+    fn test_type_check_sleep() {
+        expect_pass("let x: () = sleep(10); x", Type::Unit);
+        expect_err("sleep(1.5)", "got ((float)) but expected ((int))", true);
+    }
+
+    #[test]
+    fn test_type_check_higher_order() {
+        let t = "map([1, 2, 3], |x: int| x > 1)";
+        expect_pass_str(t, "[bool; 3]");
+
+        let t = "let xs = [1, 2, 3, 4]; map(xs[1..], |x: int| int_to_float(x))";
+        expect_pass_str(t, "[float]");
+
+        let t = r"
+        fn even(x: int) -> bool {
+            x % 2 == 0
+        }
+        let evens = filter([1, 2, 3, 4], even);
+        fold(evens, 0, |acc: int, x: int| acc + x)
+        ";
+        expect_pass(t, Type::Int);
+
+        expect_err(
+            "map([1, 2], |x: bool| x)",
+            "Expected ([T; n] or [T], fn(T) -> U) but got ([int; 2], fn(bool) -> bool)",
+            true,
+        );
+        expect_err(
+            "filter([1, 2], |x: int| x)",
+            "Expected ([T; n] or [T], fn(T) -> bool) but got ([int; 2], fn(int) -> int)",
+            true,
+        );
+        expect_err(
+            "fold([1, 2], 0.0, |acc: int, x: int| acc + x)",
+            "Expected ([T; n] or [T], U, fn(U, T) -> U) but got ([int; 2], float, fn(int, int) -> int)",
+            true,
+        );
+        expect_err(
+            "map(2, |x: int| x)",
+            "Expected ([T; n] or [T], fn(T) -> U) but got (int, fn(int) -> int)",
+            true,
+        );
+    }
 }
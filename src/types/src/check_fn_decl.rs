@@ -1,16 +1,37 @@
+use diagnostics::message;
 use parser::structs::{FnDeclData, FnTypeData, Type};
 
-use crate::type_checker::{CheckResult, TypeChecker, TypeErrors};
+use crate::{
+    check_isolate::captured_writes,
+    type_checker::{CheckResult, FnRet, TypeChecker, TypeErrors},
+};
 
 impl<'prog> TypeChecker<'prog> {
     pub(crate) fn check_fn_decl(
         &mut self,
         fn_decl: &FnDeclData,
     ) -> Result<CheckResult, TypeErrors> {
-        self.fn_type_stack.push(fn_decl.ret_type.clone());
+        self.captured_writes
+            .insert(fn_decl.name.clone(), captured_writes(fn_decl));
+
+        let fn_ret = match &fn_decl.ret_type {
+            Some(ty) => FnRet::Annotated(ty.to_owned()),
+            None => {
+                // a fn of the same name declared before doesn't count
+                self.called_before_inferred.remove(&fn_decl.name);
+                FnRet::Inferred(vec![])
+            }
+        };
+        self.fn_type_stack.push(fn_ret);
         let res = self.check_fn_decl_inner(fn_decl);
-        self.fn_type_stack.pop();
-        res
+        let fn_ret = self.fn_type_stack.pop();
+
+        match (res, fn_ret) {
+            (Ok(res), Some(FnRet::Inferred(ret_types))) => {
+                self.infer_fn_ret_type(fn_decl, res, ret_types)
+            }
+            (res, _) => res,
+        }
     }
 
     // 1. all nested returns belonging to fn should have same type as annotated ret type: use fn_stack to track this
@@ -20,72 +41,127 @@ impl<'prog> TypeChecker<'prog> {
     // AND (somewhere in the block we encounter a terminating decl/ last_expr OR the
     // last expression of the block has the same type as the ty_ann)
     // Everything after a must_return is ignored. function returns unit => don't need must_return, but nested ret cannot return anything else
+
+    // Without a ty_ann, the returns are collected on the fn_stack instead and the result has the type of the block
+    // as its ret type, for infer_fn_ret_type to check against them
     fn check_fn_decl_inner(&mut self, fn_decl: &FnDeclData) -> Result<CheckResult, TypeErrors> {
         // Assert all params have type ann and add their types
         let mut param_types: Vec<Type> = vec![];
 
         for param in fn_decl.params.iter() {
             if let Some(ty) = &param.type_ann {
+                self.check_type_ann(ty)?;
                 param_types.push(ty.to_owned());
             } else {
-                let e = format!("Parameter '{}' has no type annotation", param.name);
-                return Err(TypeErrors::new_err(&e));
+                let e = message!(T006, "Parameter '{}' has no type annotation", param.name);
+                return Err(TypeErrors::new_err(e));
             }
         }
 
-        let fn_ty = FnTypeData {
-            params: param_types,
-            ret_type: fn_decl.ret_type.clone(),
+        // uninit until inferred, so a call to the fn in its own body knows to assume ()
+        let ret_type = match &fn_decl.ret_type {
+            Some(ty) => {
+                self.check_type_ann(ty)?;
+                ty.to_owned()
+            }
+            None => Type::Unitialised,
         };
 
-        let fn_ty = Type::UserFn(Box::new(fn_ty));
-        // let mut ty_errs = TypeErrors::new();
-
-        let fn_res = CheckResult {
-            ty: fn_ty.clone(),
-            must_break: false,
-            must_return: false,
+        let mut fn_ty = FnTypeData {
+            params: param_types,
+            ret_type: ret_type.clone(),
         };
 
         // Before checking block, add this fn to env to support recursion
-        self.assign_ident(&fn_decl.name, fn_ty.clone())?; // should work because of enterscope
+        self.assign_ident(&fn_decl.name, Type::UserFn(Box::new(fn_ty.clone())))?; // should work because of enterscope
 
         // dbg!("FN_PARAMS:", &fn_decl.params, &fn_decl.name);
 
         let blk_res = self.check_block(&fn_decl.body, fn_decl.params.clone())?;
         // dbg!("FN BLK TYPE:", &blk_res);
 
-        // If must_return encountered in block, we assume nested returns are correct type so just stop here
-        if blk_res.must_return {
-            return Ok(fn_res);
-        }
-
-        // check blk_ty matches overall ret type only if last_expr exists
-        if fn_decl.body.last_expr.is_some() {
-            if blk_res.ty.eq(&fn_decl.ret_type) {
-                return Ok(fn_res);
+        if fn_decl.ret_type.is_none() {
+            // the block type is only a candidate if control can reach the end of the block
+            fn_ty.ret_type = if blk_res.must_return {
+                Type::Unitialised
             } else {
-                let e = format!(
+                blk_res.ty
+            };
+        } else if blk_res.must_return {
+            // If must_return encountered in block, we assume nested returns are correct type so just stop here
+        } else if fn_decl.body.last_expr.is_some() {
+            // check blk_ty matches overall ret type only if last_expr exists
+            if !blk_res.ty.eq(&ret_type) {
+                let e = message!(
+                    T002,
                     "Function '{}' has return type '{}' but found block type '{}'",
-                    fn_decl.name, fn_decl.ret_type, blk_res.ty
+                    fn_decl.name,
+                    ret_type,
+                    blk_res.ty
                 );
-                return Err(TypeErrors::new_err(&e));
+                return Err(TypeErrors::new_err(e));
             }
-        }
-
-        // if no must_return, and no last_expr, and overall type is not Unit, err
-        if !fn_decl.ret_type.eq(&Type::Unit) {
-            let e = format!(
+        } else if !ret_type.eq(&Type::Unit) {
+            // if no must_return, and no last_expr, and overall type is not Unit, err
+            let e = message!(
+                T002,
                 "Function '{}' might not return '{}'",
-                fn_decl.name, fn_decl.ret_type
+                fn_decl.name,
+                ret_type
             );
-            return Err(TypeErrors::new_err(&e));
+            return Err(TypeErrors::new_err(e));
         }
 
-        Ok(fn_res)
-
         // If everything is ok, return the annotated types
         // Fn decl doesn't contribute to overall must_ret / must_break of the outer block
+        Ok(CheckResult {
+            ty: Type::UserFn(Box::new(fn_ty)),
+            must_break: false,
+            must_return: false,
+        })
+    }
+
+    // The ret type of a fn without `-> T` is the type every return and the end of the block agree on
+    fn infer_fn_ret_type(
+        &mut self,
+        fn_decl: &FnDeclData,
+        mut res: CheckResult,
+        mut ret_types: Vec<Type>,
+    ) -> Result<CheckResult, TypeErrors> {
+        let Type::UserFn(fn_ty) = &mut res.ty else {
+            unreachable!("Fn decl should have a fn type");
+        };
+
+        // uninit if the end of the block is never reached
+        if !fn_ty.ret_type.eq(&Type::Unitialised) {
+            ret_types.push(fn_ty.ret_type.clone());
+        }
+
+        let recursive = self.called_before_inferred.remove(&fn_decl.name);
+        if recursive && ret_types.iter().any(|ty| !ty.eq(&Type::Unit)) {
+            let e = message!(
+                T006,
+                "Function '{}' calls itself so it needs a return type annotation",
+                fn_decl.name
+            );
+            return Err(TypeErrors::new_err(e));
+        }
+
+        let ret_type = ret_types.first().cloned().unwrap_or(Type::Unit);
+        if let Some(other) = ret_types.iter().find(|ty| !ty.eq(&&ret_type)) {
+            let e = message!(
+                T006,
+                "Function '{}' returns both '{}' and '{}', add a return type annotation to pick one",
+                fn_decl.name,
+                ret_type,
+                other
+            );
+            return Err(TypeErrors::new_err(e));
+        }
+
+        fn_ty.ret_type = ret_type;
+        self.assign_ident(&fn_decl.name, res.ty.clone())?;
+        Ok(res)
     }
 }
 
@@ -138,7 +214,11 @@ mod tests {
 
         }
         ";
-        expect_err(t, "[TypeError]: Parameter 'y' has no type annotation", true);
+        expect_err(
+            t,
+            "[TypeError T006]: Parameter 'y' has no type annotation",
+            true,
+        );
 
         let t = r"
         fn fac(n) {
@@ -262,7 +342,7 @@ mod tests {
             return 5;
         }
         ";
-        expect_err(t, "[TypeError]: Expected function return type 'int' but return statement has type 'bool'\n[TypeError]: Expected function return type 'int' but return statement has type 'float'", false);
+        expect_err(t, "[TypeError T002]: Expected function return type 'int' but return statement has type 'bool' at line 4, column 17\n[TypeError T002]: Expected function return type 'int' but return statement has type 'float' at line 6, column 17", false);
 
         // check that it ignores inner return for hof
         let t = r"
@@ -286,7 +366,96 @@ mod tests {
             return !true;
         }
         ";
-        expect_err(t, "[TypeError]: Can't apply logical NOT to type int\n[TypeError]: Expected function return type 'int' but return statement has type 'bool'", false);
+        expect_err(t, "[TypeError T003]: Can't apply logical NOT to type int at line 4, column 17\n[TypeError T002]: Expected function return type 'int' but return statement has type 'bool' at line 6, column 13", false);
+    }
+
+    #[test]
+    fn test_type_check_fn_ret_inference() {
+        expect_pass_str("fn f(x: int) { x + 1 } f", "fn(int) -> int");
+        expect_pass_str("fn f() { return; } f", "fn()");
+
+        // returns and the end of the block agree
+        let t = r"
+        fn f(x: int) {
+            if x > 2 {
+                return true;
+            }
+            x == 0
+        }
+        f
+        ";
+        expect_pass_str(t, "fn(int) -> bool");
+
+        // the end of the block is never reached, so only the returns count
+        let t = r"
+        fn f(x: int) {
+            if x > 2 {
+                return 1.5;
+            } else {
+                return 2.5;
+            }
+        }
+        f(1) + 1.0
+        ";
+        expect_pass(t, Type::Float);
+
+        // the inferred type is used at the call site
+        let t = r"
+        fn f() {
+            2
+        }
+        let x: bool = f();
+        ";
+        expect_err(t, "'x' has declared type bool but assigned type int", true);
+
+        let t = r"
+        fn f(x: int) {
+            if x > 2 {
+                return true;
+            }
+            x
+        }
+        ";
+        expect_err(
+            t,
+            "Function 'f' returns both 'bool' and 'int', add a return type annotation to pick one",
+            true,
+        );
+
+        // falls off the end with ()
+        let t = r"
+        fn f(x: int) {
+            if x > 2 {
+                return x;
+            }
+        }
+        ";
+        expect_err(t, "Function 'f' returns both 'int' and '()'", true);
+
+        // calls to itself are taken to return (), which only holds if the rest of it agrees
+        let t = r"
+        fn f(x: int) {
+            if x > 0 {
+                f(x - 1);
+            }
+        }
+        f
+        ";
+        expect_pass_str(t, "fn(int)");
+
+        let t = r"
+        fn f(x: int) {
+            if x == 0 {
+                return 0;
+            }
+            f(x - 1)
+        }
+        ";
+        expect_err(
+            t,
+            "Function 'f' calls itself so it needs a return type annotation",
+            true,
+        );
     }
 
     #[test]
@@ -0,0 +1,297 @@
+use diagnostics::message;
+use parser::structs::{BlockSeq, Decl, Expr, FnCallData, FnDeclData, FnParam, ForIter};
+
+use crate::type_checker::{TypeChecker, TypeErrors};
+
+impl<'prog> TypeChecker<'prog> {
+    /// An isolated thread only has a copy of the variables of its parent, so an assignment by the fn it runs to a
+    /// variable the fn didn't declare itself is never seen by the parent. That is almost always a value that was
+    /// meant to be sent back, so it is an error rather than a silent no-op.
+    ///
+    /// Only assignments in the body of the fn spawned are checked, not those of the fns it calls.
+    pub(crate) fn check_isolate(&self, call: &FnCallData) -> Result<(), TypeErrors> {
+        let Some(writes) = self.captured_writes.get(&call.name) else {
+            return Ok(());
+        };
+
+        let mut errs = TypeErrors::new();
+        for name in writes.iter() {
+            let e = message!(
+                T012,
+                "Isolated thread running '{}' assigns to '{}', which it only has a copy of. Send the value on a channel instead",
+                call.name,
+                name
+            );
+            errs.add(e);
+        }
+
+        if errs.is_ok() {
+            Ok(())
+        } else {
+            Err(errs)
+        }
+    }
+}
+
+/// The names fn_decl assigns to, or assigns an element or field of, that are not declared in it, in the order they
+/// are first assigned.
+pub(crate) fn captured_writes(fn_decl: &FnDeclData) -> Vec<String> {
+    let mut writes = Writes::default();
+    writes.scopes.push(param_names(&fn_decl.params));
+    writes.blk(&fn_decl.body);
+    writes.found
+}
+
+fn param_names(params: &[FnParam]) -> Vec<String> {
+    params.iter().map(|param| param.name.clone()).collect()
+}
+
+// The names declared by each enclosing scope, innermost last
+#[derive(Default)]
+struct Writes {
+    scopes: Vec<Vec<String>>,
+    found: Vec<String>,
+}
+
+impl Writes {
+    fn write(&mut self, name: &str) {
+        let declared = self
+            .scopes
+            .iter()
+            .any(|scope| scope.iter().any(|x| x == name));
+        if !declared && !self.found.iter().any(|x| x == name) {
+            self.found.push(name.to_string());
+        }
+    }
+
+    // xs[i] = v and p.x = v write to what xs and p hold
+    fn write_through(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Symbol(name) => self.write(name),
+            Expr::IndexExpr(arr, _) => self.write_through(arr),
+            Expr::FieldAccessExpr(obj, _) => self.write_through(obj),
+            Expr::TupleIndexExpr(tuple, _) => self.write_through(tuple),
+            _ => (),
+        }
+    }
+
+    fn scoped(&mut self, names: Vec<String>, f: impl FnOnce(&mut Self)) {
+        self.scopes.push(names);
+        f(self);
+        self.scopes.pop();
+    }
+
+    fn blk(&mut self, blk: &BlockSeq) {
+        self.scoped(blk.symbols.clone(), |w| {
+            for decl in blk.decls.iter() {
+                w.decl(decl);
+            }
+            if let Some(expr) = &blk.last_expr {
+                w.expr(expr);
+            }
+        });
+    }
+
+    fn decl(&mut self, decl: &Decl) {
+        match decl {
+            Decl::LetStmt(stmt) => self.expr(&stmt.expr),
+            Decl::LetTupleStmt(stmt) => self.expr(&stmt.expr),
+            Decl::AssignStmt(stmt) => {
+                self.write(&stmt.ident);
+                self.expr(&stmt.expr);
+            }
+            Decl::IndexAssignStmt(stmt) => {
+                self.write_through(&stmt.arr);
+                self.expr(&stmt.arr);
+                self.expr(&stmt.index);
+                self.expr(&stmt.expr);
+            }
+            Decl::FieldAssignStmt(stmt) => {
+                self.write_through(&stmt.obj);
+                self.expr(&stmt.obj);
+                self.expr(&stmt.expr);
+            }
+            Decl::ExprStmt(expr) => self.expr(expr),
+            Decl::ReturnStmt(expr) => {
+                if let Some(expr) = expr {
+                    self.expr(expr);
+                }
+            }
+            Decl::IfOnlyStmt(if_else) => {
+                self.expr(&if_else.cond);
+                self.blk(&if_else.if_blk);
+                if let Some(blk) = &if_else.else_blk {
+                    self.blk(blk);
+                }
+            }
+            Decl::LoopStmt(lp) => {
+                if let Some(cond) = &lp.cond {
+                    self.expr(cond);
+                }
+                self.blk(&lp.body);
+            }
+            Decl::ForStmt(lp) => {
+                match &lp.iter {
+                    ForIter::Range { start, end, .. } => {
+                        self.expr(start);
+                        self.expr(end);
+                    }
+                    ForIter::Elems(expr) => self.expr(expr),
+                }
+                self.scoped(vec![lp.var.clone()], |w| w.blk(&lp.body));
+            }
+            Decl::FnDeclStmt(fn_decl) => {
+                self.scoped(param_names(&fn_decl.params), |w| w.blk(&fn_decl.body))
+            }
+            Decl::StructDeclStmt(_)
+            | Decl::EnumDeclStmt(_)
+            | Decl::MacroDeclStmt(_)
+            | Decl::WaitStmt(_)
+            | Decl::PostStmt(_)
+            | Decl::BreakStmt
+            | Decl::YieldStmt => (),
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::BlockExpr(blk) => self.blk(blk),
+            Expr::IfElseExpr(if_else) => {
+                self.expr(&if_else.cond);
+                self.blk(&if_else.if_blk);
+                if let Some(blk) = &if_else.else_blk {
+                    self.blk(blk);
+                }
+            }
+            Expr::TryJoinExpr(data) => {
+                if let Some(ms) = &data.timeout {
+                    self.expr(ms);
+                }
+            }
+            Expr::LockExpr(data) => {
+                self.expr(&data.mutex);
+                self.blk(&data.body);
+            }
+            Expr::MatchExpr(data) => {
+                self.expr(&data.subject);
+                for arm in data.arms.iter() {
+                    if let Some(guard) = &arm.guard {
+                        self.expr(guard);
+                    }
+                    self.expr(&arm.body);
+                }
+            }
+            Expr::LambdaExpr(data) => {
+                self.scoped(param_names(&data.params), |w| w.expr(&data.body))
+            }
+            Expr::UnOpExpr(_, expr)
+            | Expr::FieldAccessExpr(expr, _)
+            | Expr::TupleIndexExpr(expr, _)
+            | Expr::ArrayFillExpr(expr, _) => self.expr(expr),
+            Expr::BinOpExpr(_, lhs, rhs)
+            | Expr::IndexExpr(lhs, rhs)
+            | Expr::CoalesceExpr(lhs, rhs) => {
+                self.expr(lhs);
+                self.expr(rhs);
+            }
+            Expr::SliceExpr(slice) => {
+                self.expr(&slice.arr);
+                for bound in [&slice.start, &slice.end].into_iter().flatten() {
+                    self.expr(bound);
+                }
+            }
+            Expr::FnCallExpr(call) | Expr::MacroCallExpr(call) => {
+                call.args.iter().for_each(|arg| self.expr(arg))
+            }
+            Expr::SpawnExpr(data) => data.call.args.iter().for_each(|arg| self.expr(arg)),
+            Expr::ArrayExpr(elems) | Expr::TupleExpr(elems) => {
+                elems.iter().for_each(|elem| self.expr(elem))
+            }
+            Expr::StructExpr(data) => data.fields.iter().for_each(|(_, expr)| self.expr(expr)),
+            Expr::MapExpr(entries) => entries.iter().for_each(|(key, val)| {
+                self.expr(key);
+                self.expr(val);
+            }),
+            Expr::JoinExpr(_)
+            | Expr::Symbol(_)
+            | Expr::Integer(_)
+            | Expr::Float(_)
+            | Expr::Bool(_)
+            | Expr::StringLiteral(_)
+            | Expr::Char(_) => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use parser::structs::Type;
+
+    use crate::type_checker::{expect_err, expect_pass};
+
+    #[test]
+    fn test_type_check_isolate() {
+        // writes to its own names and sends on a channel
+        let t = r"
+        let xs = [1, 2, 3];
+        let c: chan[int] = chan();
+        fn work(xs: [int; 3], c: chan[int]) {
+            let sum = 0;
+            for x in xs {
+                sum = sum + x;
+            }
+            xs[0] = sum;
+            send(c, sum);
+        }
+        spawn isolate work(xs, c);
+        ";
+        expect_pass(t, Type::Unit);
+
+        let t = r"
+        let count = 0;
+        fn work() {
+            count = count + 1;
+        }
+        spawn isolate work();
+        ";
+        expect_err(
+            t,
+            "Isolated thread running 'work' assigns to 'count', which it only has a copy of",
+            true,
+        );
+
+        // writing through an element or field is a write too, and so is one in a lambda in the fn
+        let t = r"
+        let xs = [1, 2, 3];
+        fn work() {
+            let f = |x: int| { xs[0] = x; };
+            f(2);
+        }
+        spawn isolate work();
+        ";
+        expect_err(t, "assigns to 'xs'", true);
+
+        // and so is one in the guard of a match arm
+        let t = r"
+        let count = 0;
+        fn work() {
+            match 1 {
+                _ if { count = 2; true } => 1,
+                _ => 0,
+            };
+        }
+        spawn isolate work();
+        ";
+        expect_err(t, "assigns to 'count'", true);
+
+        // a plain spawn shares the variable so the write is seen
+        let t = r"
+        let count = 0;
+        fn work() {
+            count = count + 1;
+        }
+        spawn work();
+        ";
+        expect_pass(t, Type::Unit);
+    }
+}
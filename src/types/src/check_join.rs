@@ -0,0 +1,85 @@
+use diagnostics::message;
+use parser::structs::{TryJoinData, Type};
+
+use crate::type_checker::{CheckResult, TypeChecker, TypeErrors};
+
+impl<'prog> TypeChecker<'prog> {
+    // try_join(t) and join_timeout(t, ms) are bool: if the thread finished and was joined
+    pub(crate) fn check_try_join(&mut self, data: &TryJoinData) -> Result<CheckResult, TypeErrors> {
+        let mut ty_errs = TypeErrors::new();
+        let name = match data.timeout {
+            Some(_) => "join_timeout",
+            None => "try_join",
+        };
+
+        match self.get_type(&data.tid) {
+            Ok(Type::ThreadId) => (),
+            Ok(ty) => {
+                let e = message!(T012, "{} expected a thread but got type '{}'", name, ty);
+                ty_errs.add(e);
+            }
+            Err(mut errs) => ty_errs.append(&mut errs),
+        }
+
+        let mut res = CheckResult {
+            ty: Type::Bool,
+            must_break: false,
+            must_return: false,
+        };
+
+        if let Some(ms) = &data.timeout {
+            match self.check_expr(ms) {
+                Ok(ms_res) if ms_res.ty == Type::Int => {
+                    res = CheckResult::combine(&res, &ms_res);
+                    res.ty = Type::Bool;
+                }
+                Ok(ms_res) => {
+                    let e = message!(
+                        T012,
+                        "{} expected a timeout in ms of type 'int' but got type '{}'",
+                        name,
+                        ms_res.ty
+                    );
+                    ty_errs.add(e);
+                }
+                Err(mut errs) => ty_errs.append(&mut errs),
+            }
+        }
+
+        if ty_errs.is_ok() {
+            Ok(res)
+        } else {
+            Err(ty_errs)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use parser::structs::Type;
+
+    use crate::type_checker::{expect_err, expect_pass};
+
+    #[test]
+    fn test_type_check_try_join() {
+        let t = r"
+        fn work() {}
+        let t = spawn work();
+        let done: bool = try_join(t);
+        done || join_timeout(t, 100)
+        ";
+        expect_pass(t, Type::Bool);
+
+        expect_err(
+            "let t = 2; try_join(t)",
+            "try_join expected a thread but got type 'int'",
+            true,
+        );
+        expect_err(
+            "fn work() {} let t = spawn work(); join_timeout(t, 1.5)",
+            "join_timeout expected a timeout in ms of type 'int' but got type 'float'",
+            true,
+        );
+        expect_err("try_join(t)", "Identifier 't' not declared", true);
+    }
+}
@@ -0,0 +1,123 @@
+use std::rc::Rc;
+
+use crate::type_checker::{CheckResult, FnRet, TypeChecker, TypeErrors};
+use diagnostics::message;
+use parser::structs::{BlockSeq, FnTypeData, LambdaData, Type};
+
+impl<'prog> TypeChecker<'prog> {
+    // |x: int| x + 1 has type fn(int) -> int. Without an annotation the return type is the type of the body
+    pub(crate) fn check_lambda(&mut self, data: &LambdaData) -> Result<CheckResult, TypeErrors> {
+        let fn_ret = match &data.ret_type {
+            Some(ty) => FnRet::Annotated(ty.to_owned()),
+            None => FnRet::Unannotated,
+        };
+        self.fn_type_stack.push(fn_ret);
+        let res = self.check_lambda_inner(data);
+        self.fn_type_stack.pop();
+        res
+    }
+
+    fn check_lambda_inner(&mut self, data: &LambdaData) -> Result<CheckResult, TypeErrors> {
+        let mut param_types: Vec<Type> = vec![];
+
+        for param in data.params.iter() {
+            if let Some(ty) = &param.type_ann {
+                self.check_type_ann(ty)?;
+                param_types.push(ty.to_owned());
+            } else {
+                let e = message!(T006, "Parameter '{}' has no type annotation", param.name);
+                return Err(TypeErrors::new_err(e));
+            }
+        }
+
+        if let Some(ty) = &data.ret_type {
+            self.check_type_ann(ty)?;
+        }
+
+        // the body goes in a block of its own so the params are bound there
+        let body = BlockSeq {
+            decls: vec![],
+            last_expr: Some(Rc::new(data.body.clone())),
+            symbols: vec![],
+            spans: vec![],
+        };
+        let body_res = self.check_block(&body, data.params.clone())?;
+
+        let ret_type = match &data.ret_type {
+            Some(ret_type) if !body_res.must_return && !body_res.ty.eq(ret_type) => {
+                let e = message!(
+                    T002,
+                    "Lambda has return type '{}' but found body type '{}'",
+                    ret_type,
+                    body_res.ty
+                );
+                return Err(TypeErrors::new_err(e));
+            }
+            Some(ret_type) => ret_type.to_owned(),
+            None => body_res.ty,
+        };
+
+        let fn_ty = FnTypeData {
+            params: param_types,
+            ret_type,
+        };
+
+        // like a fn decl, the body doesn't break or return for the enclosing block
+        Ok(CheckResult {
+            ty: Type::UserFn(Box::new(fn_ty)),
+            must_break: false,
+            must_return: false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use parser::structs::Type;
+
+    use crate::type_checker::{expect_err, expect_pass, expect_pass_str};
+
+    #[test]
+    fn test_type_check_lambda() {
+        expect_pass_str("|x: int, y: int| x + y", "fn(int, int) -> int");
+        expect_pass_str("|| {}", "fn()");
+        expect_pass_str("|x: int| -> bool { return x > 2; }", "fn(int) -> bool");
+        expect_pass_str("|x: int| |y: float| x", "fn(int) -> fn(float) -> int");
+
+        let t = r"
+        fn apply(f: fn(int) -> int, x: int) -> int {
+            f(x)
+        }
+        let k = 3;
+        let add_k = |x: int| x + k;
+        let f: fn(int) -> int = |x: int| -> int { if x > 0 { return 1; } 0 };
+        apply(add_k, 2) + apply(|x: int| x * 2, f(5)) + add_k(1)
+        ";
+        expect_pass(t, Type::Int);
+    }
+
+    #[test]
+    fn test_type_check_lambda_errs() {
+        expect_err("|x| x", "Parameter 'x' has no type annotation", true);
+        expect_err(
+            "|x: int| -> bool { x }",
+            "Lambda has return type 'bool' but found body type 'int'",
+            true,
+        );
+        expect_err(
+            "|x: int| { return x; }",
+            "Lambda with a return statement needs a return type annotation e.g |x: int| -> int { ... }",
+            true,
+        );
+        expect_err(
+            "|x: int| -> int { return true; }",
+            "Expected function return type 'int' but return statement has type 'bool'",
+            true,
+        );
+        expect_err(
+            "let f = |x: int| x; f(true)",
+            "Mismatched types in function call: got ((bool)) but expected ((int))",
+            true,
+        );
+    }
+}
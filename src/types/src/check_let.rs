@@ -1,10 +1,50 @@
-use crate::type_checker::{CheckResult, TypeChecker, TypeErrors};
-use parser::structs::LetStmtData;
+use crate::{
+    check_fn_call::{CHAN, MAP_NEW, PMAP, PVEC},
+    type_checker::{CheckResult, TypeChecker, TypeErrors},
+};
+use diagnostics::message;
+use parser::structs::{Expr, LetStmtData, Type};
 
 impl<'prog> TypeChecker<'prog> {
+    /// A let without an annotation gives the name the type of its expression, whatever that is built from:
+    /// blocks, if-else with branches of the same type, calls and so on. An annotation is optional, and when
+    /// there is one the expression is checked against it.
     pub(crate) fn check_let(&mut self, stmt: &LetStmtData) -> Result<CheckResult, TypeErrors> {
         let mut ty_errs = TypeErrors::new();
 
+        // can't give the ident a type that doesn't exist
+        if let Some(ty_ann) = &stmt.type_ann {
+            if let Err(mut errs) = self.check_type_ann(ty_ann) {
+                errs.set_cont(false);
+                return Err(errs);
+            }
+        }
+
+        // chan(), pvec(), pmap(), map_new() and #{} have no type of their own, so they take the element types from
+        // the annotation
+        let empty = match (&stmt.expr, &stmt.type_ann) {
+            (Expr::FnCallExpr(fn_call), Some(ty_ann)) => {
+                fn_call.args.is_empty()
+                    && matches!(
+                        (fn_call.name.as_str(), ty_ann),
+                        (CHAN, Type::Channel(_))
+                            | (PVEC, Type::PVec(_))
+                            | (PMAP, Type::PMap(_, _))
+                            | (MAP_NEW, Type::Map(_, _))
+                    )
+            }
+            (Expr::MapExpr(entries), Some(Type::Map(_, _))) => entries.is_empty(),
+            _ => false,
+        };
+        if let (true, Some(ty_ann)) = (empty, &stmt.type_ann) {
+            self.assign_ident(&stmt.ident.to_owned(), ty_ann.to_owned())?;
+            return Ok(CheckResult {
+                ty: ty_ann.to_owned(),
+                must_break: false,
+                must_return: false,
+            });
+        }
+
         let mut expr_type: Option<CheckResult> = None;
         match self.check_expr(&stmt.expr) {
             Ok(res) => {
@@ -33,6 +73,17 @@ impl<'prog> TypeChecker<'prog> {
             // expr is well-typed + no type annotation e.g let x = 2+2;
             // use expr type, no err
             (Some(expr_res), None) => {
+                // let x = None; says nothing about what x may hold
+                if expr_res.ty.has_infer() {
+                    let e = message!(
+                        T006,
+                        "Can't infer the type of '{}' from {}, give it a type annotation",
+                        stmt.ident,
+                        expr_res.ty
+                    );
+                    return Err(TypeErrors::new_err(e));
+                }
+
                 // assign ident, return checkresult propagated from expr
 
                 self.assign_ident(&stmt.ident.to_owned(), expr_res.ty.clone())?;
@@ -52,11 +103,14 @@ impl<'prog> TypeChecker<'prog> {
                 self.assign_ident(&stmt.ident.to_owned(), ty_ann.to_owned())?;
 
                 if !ty_ann.eq(&expr_res.ty) {
-                    let string = format!(
+                    let string = message!(
+                        T002,
                         "'{}' has declared type {} but assigned type {}",
-                        stmt.ident, ty_ann, expr_res.ty
+                        stmt.ident,
+                        ty_ann,
+                        expr_res.ty
                     );
-                    ty_errs.add(&string);
+                    ty_errs.add(string);
                     return Err(ty_errs);
                 }
 
@@ -83,13 +137,13 @@ mod tests {
         // first has err but no type ann: we don't proceed
         expect_err(
             "let x = -true; let y : int = x + 2; let z : bool = !x;",
-            "[TypeError]: Can't negate type bool",
+            "[TypeError T003]: Can't negate type bool at line 1, column 1",
             false,
         );
 
         // expr has err but we have ann: can proceed
         expect_err("let x : int = -true; let y : int = x + false;", 
-        "[TypeError]: Can't negate type bool\n[TypeError]: Can't apply '+' to types 'int' and 'bool'", false);
+        "[TypeError T003]: Can't negate type bool at line 1, column 1\n[TypeError T003]: Can't apply '+' to types 'int' and 'bool' at line 1, column 22", false);
 
         // expr is fine but no annotation: use inferred type
         expect_err(
@@ -102,22 +156,99 @@ mod tests {
         // expr is fine and we have annotation: check for mismatch, can proceed with binding type = annotation
         // here !y is fine so no error, since y is annotated bool
         expect_err("let x : int = !true; let y: bool = x + false; let z : bool = !y;", 
-        "[TypeError]: 'x' has declared type int but assigned type bool\n[TypeError]: Can't apply '+' to types 'int' and 'bool'", false);
+        "[TypeError T002]: 'x' has declared type int but assigned type bool at line 1, column 1\n[TypeError T003]: Can't apply '+' to types 'int' and 'bool' at line 1, column 22", false);
 
         expect_err("let x : int = !true; let y: bool = x + false; let z : bool = y + x;", 
-        "[TypeError]: 'x' has declared type int but assigned type bool\n[TypeError]: Can't apply '+' to types 'int' and 'bool'\n[TypeError]: Can't apply '+' to types 'bool' and 'int'",
+        "[TypeError T002]: 'x' has declared type int but assigned type bool at line 1, column 1\n[TypeError T003]: Can't apply '+' to types 'int' and 'bool' at line 1, column 22\n[TypeError T003]: Can't apply '+' to types 'bool' and 'int' at line 1, column 47",
         false);
     }
 
+    #[test]
+    fn test_type_check_let_inference() {
+        // through blocks, nested or not
+        expect_pass("let x = { let y = 2; y * 3 }; x", Type::Int);
+        expect_pass("let x = { let y = { 2.5 }; y }; x", Type::Float);
+        expect_pass("let x = { }; x", Type::Unit);
+
+        // through if-else with matching branches, where a branch that returns or breaks doesn't count
+        expect_pass("let x = if true { 1 } else { 2 }; x", Type::Int);
+        expect_pass(
+            "let x = { let y = 2; if y > 1 { y } else { 0 } }; x",
+            Type::Int,
+        );
+        expect_pass(
+            "fn f(a: int) -> int { let x = if a > 2 { a } else { return 0; }; x } f(1)",
+            Type::Int,
+        );
+        expect_pass(
+            "let i = 0; loop { let x = if i > 2 { break; } else { i }; i = x + 1; } i",
+            Type::Int,
+        );
+        expect_err(
+            "let x = if true { 1 } else { false }; x",
+            "if-else has type mismatch - consequent: int, alt: bool",
+            true,
+        );
+
+        // from the return types of fns and lambdas, and inside fn bodies
+        expect_pass("fn f() -> int { 2 } let x = f(); x", Type::Int);
+        expect_pass(
+            "fn g(a: int) -> int { let r = { a + 1 }; r } let z = g(1) + g(2); z",
+            Type::Int,
+        );
+        expect_pass("let f = |a: int| a > 1; let x = f(2); x", Type::Bool);
+
+        // along a chain of lets
+        expect_pass(
+            "let a = [1, 2, 3]; let s = a[0..2]; let t = s; t",
+            Type::Slice(Box::new(Type::Int)),
+        );
+
+        // an annotation is still checked against the inferred type
+        expect_pass("let x = 2; let y: int = x; y", Type::Int);
+        expect_err(
+            "let x = 2; let y = x; let z = y; let w: bool = z; w",
+            "'w' has declared type bool but assigned type int",
+            true,
+        );
+        expect_err(
+            "let f = |a: int| a > 1; let x: int = f(2); x",
+            "'x' has declared type int but assigned type bool",
+            true,
+        );
+
+        // None and Err say only part of their type, which a branch or an annotation fills in
+        let some_int = Type::Option(Box::new(Type::Int));
+        expect_pass(
+            "let x = if true { None } else { Some(1) }; x",
+            some_int.clone(),
+        );
+        expect_pass("let x: option[int] = None; x", some_int);
+        expect_err(
+            "let x = None;",
+            "Can't infer the type of 'x' from option[_], give it a type annotation",
+            true,
+        );
+        expect_err(
+            "let r: result[int, str] = Err(1);",
+            "'r' has declared type result[int, str] but assigned type result[_, int]",
+            true,
+        );
+    }
+
     #[test]
     fn test_type_check_ident_decl() {
         // stops immediately because y has no annotation
         let t = "let y = x + 2; let z = y - false;";
-        expect_err(t, "[TypeError]: Identifier 'x' not declared", false);
+        expect_err(
+            t,
+            "[TypeError T001]: Identifier 'x' not declared at line 1, column 1",
+            false,
+        );
 
         // continues because y has type annotation
         let t = "let y : int = x + 2; let z = y - false;";
-        expect_err(t, "[TypeError]: Identifier 'x' not declared\n[TypeError]: Can't apply '-' to types 'int' and 'bool'", false);
+        expect_err(t, "[TypeError T001]: Identifier 'x' not declared at line 1, column 1\n[TypeError T003]: Can't apply '-' to types 'int' and 'bool' at line 1, column 22", false);
 
         // unit
         let t = "let x : () = {}; let y : () = { 2; 3; }; x";
@@ -133,14 +264,18 @@ mod tests {
     #[test]
     fn test_type_check_bigger() {
         let t = "let y : bool = 20; let x : int = y; let z : int = x*y + 3; z";
-        expect_err(t, "[TypeError]: 'y' has declared type bool but assigned type int\n[TypeError]: 'x' has declared type int but assigned type bool\n[TypeError]: Can't apply '*' to types 'int' and 'bool'", false);
+        expect_err(t, "[TypeError T002]: 'y' has declared type bool but assigned type int at line 1, column 1\n[TypeError T002]: 'x' has declared type int but assigned type bool at line 1, column 20\n[TypeError T003]: Can't apply '*' to types 'int' and 'bool' at line 1, column 37", false);
     }
 
     #[test]
     fn test_type_check_assign() {
         // don't continue since first one has err
         let t = "let x = !20; x = true; x";
-        expect_err(t, "[TypeError]: Can't apply logical NOT to type int", false);
+        expect_err(
+            t,
+            "[TypeError T003]: Can't apply logical NOT to type int at line 1, column 1",
+            false,
+        );
 
         let t = "let x = 20; x = true; x";
         expect_err(t, "'x' declared with type int but assigned type bool", true);
@@ -149,7 +284,7 @@ mod tests {
         expect_err(t, "'x' declared with type int but assigned type bool", true);
 
         let t = "let x : int = !20; x = !true; x";
-        expect_err(t,"[TypeError]: Can't apply logical NOT to type int\n[TypeError]: 'x' declared with type int but assigned type bool", false);
+        expect_err(t,"[TypeError T003]: Can't apply logical NOT to type int at line 1, column 1\n[TypeError T002]: 'x' declared with type int but assigned type bool at line 1, column 20", false);
 
         let t = "let y = 2; x = 10;";
         expect_err(t, "Identifier 'x' not declared", true);
@@ -158,7 +293,7 @@ mod tests {
         let t = "x = 10; let x = 5;";
         expect_err(
             t,
-            "[TypeError]: Identifier 'x' assigned before declaration",
+            "[TypeError T001]: Identifier 'x' assigned before declaration at line 1, column 1",
             false,
         );
     }
@@ -0,0 +1,91 @@
+use crate::type_checker::{CheckResult, TypeChecker, TypeErrors};
+use diagnostics::message;
+use parser::structs::{LockData, Type};
+
+impl<'prog> TypeChecker<'prog> {
+    // lock m { body }: m must be a mutex, the lock has the type of its body
+    pub(crate) fn check_lock(&mut self, data: &LockData) -> Result<CheckResult, TypeErrors> {
+        let mut ty_errs = TypeErrors::new();
+
+        let mutex_res = match self.check_expr(&data.mutex) {
+            Ok(res) if res.ty == Type::Mutex => Some(res),
+            Ok(res) => {
+                let e = message!(T012, "lock expected a mutex but got type '{}'", res.ty);
+                ty_errs.add(e);
+                None
+            }
+            Err(mut errs) => {
+                ty_errs.append(&mut errs);
+                None
+            }
+        };
+
+        let body_res = match self.check_block(&data.body, vec![]) {
+            Ok(res) => Some(res),
+            Err(mut errs) => {
+                ty_errs.append(&mut errs);
+                None
+            }
+        };
+
+        match (mutex_res, body_res) {
+            (Some(mutex_res), Some(body_res)) if ty_errs.is_ok() => {
+                let mut res = CheckResult::combine(&mutex_res, &body_res);
+                res.ty = body_res.ty;
+                Ok(res)
+            }
+            _ => Err(ty_errs),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use parser::structs::Type;
+
+    use crate::type_checker::{expect_err, expect_pass};
+
+    #[test]
+    fn test_type_check_lock() {
+        let t = r"
+        let m = mutex();
+        let count = 0;
+        fn incr(m: mutex) -> int {
+            lock m {
+                count = count + 1;
+                return count;
+            }
+        }
+        let x = lock m { count * 2 };
+        incr(m) + x
+        ";
+        expect_pass(t, Type::Int);
+
+        let t = "let m : mutex = mutex(); for i in 0..2 { lock m { break; } } lock m {}";
+        expect_pass(t, Type::Unit);
+    }
+
+    #[test]
+    fn test_type_check_lock_errs() {
+        expect_err(
+            "let s = sem_create(); lock s { 1 }",
+            "lock expected a mutex but got type 'sem'",
+            true,
+        );
+        expect_err(
+            "let m = mutex(); let x : int = lock m { true };",
+            "'x' has declared type int but assigned type bool",
+            true,
+        );
+        expect_err(
+            "lock 2 { !1 }",
+            "[TypeError T012]: lock expected a mutex but got type 'int' at line 1, column 1\n[TypeError T003]: Can't apply logical NOT to type int at line 1, column 10",
+            false,
+        );
+        expect_err(
+            "mutex(1)",
+            "Function 'mutex' takes 0 arguments but 1 were supplied",
+            true,
+        );
+    }
+}
@@ -1,5 +1,6 @@
-use crate::type_checker::{CheckResult, TypeChecker, TypeErrors};
-use parser::structs::{LoopData, Type};
+use crate::type_checker::{new_env_with_syms, CheckResult, TypeChecker, TypeErrors};
+use diagnostics::message;
+use parser::structs::{Expr, ForData, ForIter, LoopData, Type};
 
 impl<'prog> TypeChecker<'prog> {
     // if loop cond present, must be bool. else just check blks.
@@ -18,12 +19,13 @@ impl<'prog> TypeChecker<'prog> {
                     must_return: _,
                 }) => (),
                 Ok(ty) => {
-                    let e = format!(
+                    let e = message!(
+                        T002,
                         "Expected type '{}' for loop predicate but got '{}'",
                         Type::Bool,
                         ty.ty
                     );
-                    ty_errs.add(&e);
+                    ty_errs.add(e);
                 }
                 Err(mut errs) => ty_errs.append(&mut errs),
             }
@@ -45,6 +47,78 @@ impl<'prog> TypeChecker<'prog> {
             Err(ty_errs)
         }
     }
+
+    // range bounds must be ints, otherwise must iterate over an array or slice.
+    // the loop var gets its own env around the body, like the scope it gets at runtime
+    pub(crate) fn check_for(&mut self, for_data: &ForData) -> Result<CheckResult, TypeErrors> {
+        let mut ty_errs = TypeErrors::new();
+
+        let var_ty = match &for_data.iter {
+            ForIter::Range { start, end, .. } => {
+                for (bound, expr) in [("start", start), ("end", end)] {
+                    self.check_for_bound(bound, expr, &mut ty_errs);
+                }
+                Type::Int
+            }
+            ForIter::Elems(expr) => match self.check_expr(expr) {
+                Ok(CheckResult {
+                    ty: Type::Array(elem_ty, _) | Type::Slice(elem_ty),
+                    ..
+                }) => *elem_ty,
+                Ok(res) => {
+                    let e = message!(T011, "Can't iterate over type '{}' in for loop", res.ty);
+                    ty_errs.add(e);
+                    Type::Unitialised
+                }
+                Err(mut errs) => {
+                    ty_errs.append(&mut errs);
+                    Type::Unitialised
+                }
+            },
+        };
+
+        // can't know the type of the loop var, so the body can't be checked
+        if var_ty == Type::Unitialised {
+            return Err(ty_errs);
+        }
+
+        let mut env = new_env_with_syms(vec![]);
+        env.insert(for_data.var.to_owned(), var_ty);
+        self.envs.push(env);
+        let check_blk = self.check_block(&for_data.body, vec![]);
+        self.envs.pop();
+
+        if let Err(mut errs) = check_blk {
+            ty_errs.append(&mut errs);
+        }
+
+        if ty_errs.is_ok() {
+            Ok(CheckResult {
+                ty: Type::Unit,
+                must_break: false,
+                must_return: false,
+            })
+        } else {
+            Err(ty_errs)
+        }
+    }
+
+    fn check_for_bound(&mut self, bound: &str, expr: &Expr, ty_errs: &mut TypeErrors) {
+        match self.check_expr(expr) {
+            Ok(CheckResult { ty: Type::Int, .. }) => (),
+            Ok(res) => {
+                let e = message!(
+                    T002,
+                    "Expected type '{}' for range {} in for loop but got '{}'",
+                    Type::Int,
+                    bound,
+                    res.ty
+                );
+                ty_errs.add(e);
+            }
+            Err(mut errs) => ty_errs.append(&mut errs),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -127,7 +201,7 @@ mod tests {
             2+false;
         }
         ";
-        expect_err(t,  "[TypeError]: Identifier 'x' not declared\n[TypeError]: Can't apply '+' to types 'int' and 'bool'", false);
+        expect_err(t,  "[TypeError T001]: Identifier 'x' not declared at line 2, column 9\n[TypeError T003]: Can't apply '+' to types 'int' and 'bool' at line 3, column 13", false);
 
         // cond ok but not type bool, body has errs
         let t = r"
@@ -135,7 +209,15 @@ mod tests {
             2+false;
         }
         ";
-        expect_err(t,  "[TypeError]: Expected type 'bool' for loop predicate but got 'float'\n[TypeError]: Can't apply '+' to types 'int' and 'bool'", false);
+        expect_err(t,  "[TypeError T002]: Expected type 'bool' for loop predicate but got 'float' at line 2, column 9\n[TypeError T003]: Can't apply '+' to types 'int' and 'bool' at line 3, column 13", false);
+
+        // while goes through the same checks
+        expect_pass("let x = 0; while x < 3 { x = x + 1; } x", Type::Int);
+        expect_err(
+            "let x = 0; while x { x = x + 1; }",
+            "Expected type 'bool' for loop predicate but got 'int'",
+            true,
+        );
     }
 
     #[test]
@@ -193,4 +275,57 @@ mod tests {
             true,
         );
     }
+
+    #[test]
+    fn test_type_check_for() {
+        let t = r"
+        let sum = 0;
+        for i in 0..=10 {
+            sum = sum + i;
+        }
+        sum
+        ";
+        expect_pass(t, Type::Int);
+
+        // elements of arrays and slices
+        let t = r"
+        let xs = [1.5, 2.5, 3.5];
+        let total = 0.0;
+        for x in xs {
+            total = total + x;
+        }
+        for x in xs[1..] {
+            total = total - x;
+        }
+        total
+        ";
+        expect_pass(t, Type::Float);
+
+        // loop var is scoped to the loop
+        expect_err("for i in 0..3 { } i", "Identifier 'i' not declared", true);
+
+        // shadows an outer var only inside the loop
+        expect_pass("let i = true; for i in 0..3 { i + 1; } i", Type::Bool);
+
+        expect_err(
+            "for i in 0..true { }",
+            "Expected type 'int' for range end in for loop but got 'bool'",
+            true,
+        );
+        expect_err(
+            "for i in 0.5..2 { }",
+            "Expected type 'int' for range start in for loop but got 'float'",
+            true,
+        );
+        expect_err(
+            "for x in 5 { }",
+            "Can't iterate over type 'int' in for loop",
+            true,
+        );
+        expect_err(
+            "for x in [true, false] { x + 1; }",
+            "Can't apply '+' to types 'bool' and 'int'",
+            true,
+        );
+    }
 }
@@ -0,0 +1,172 @@
+use crate::type_checker::{CheckResult, TypeChecker, TypeErrors};
+use diagnostics::message;
+use parser::structs::{Expr, Type};
+
+impl<'prog> TypeChecker<'prog> {
+    // #{k1: v1, k2: v2}: like an array, every key has the same type and so does every value
+    pub(crate) fn check_map(
+        &mut self,
+        entries: &[(Expr, Expr)],
+    ) -> Result<CheckResult, TypeErrors> {
+        let mut ty_errs = TypeErrors::new();
+        let mut res = CheckResult {
+            ty: Type::Unit,
+            must_break: false,
+            must_return: false,
+        };
+        let mut entry_types: Vec<(Type, Type)> = vec![];
+
+        for (key, val) in entries.iter() {
+            match (self.check_expr(key), self.check_expr(val)) {
+                (Ok(key_res), Ok(val_res)) => {
+                    res = CheckResult::combine(&res, &key_res);
+                    res = CheckResult::combine(&res, &val_res);
+                    entry_types.push((key_res.ty, val_res.ty));
+                }
+                (key_res, val_res) => {
+                    for mut errs in [key_res.err(), val_res.err()].into_iter().flatten() {
+                        ty_errs.append(&mut errs);
+                    }
+                }
+            }
+        }
+
+        if !ty_errs.is_ok() {
+            return Err(ty_errs);
+        }
+
+        // #{} with an annotation is checked in check_let
+        let Some((key_ty, val_ty)) = entry_types.first() else {
+            let e = message!(
+                T006,
+                "Can't infer the key and value types of an empty map, give it a type annotation e.g let m: map[str, int] = #{};"
+            );
+            return Err(TypeErrors::new_err(e));
+        };
+
+        if !matches!(key_ty, Type::Int | Type::String | Type::Bool) {
+            let e = message!(
+                T005,
+                "Keys of a map must be int, str or bool, got {}",
+                key_ty
+            );
+            return Err(TypeErrors::new_err(e));
+        }
+
+        for (k, v) in entry_types.iter() {
+            if k != key_ty || v != val_ty {
+                let e = message!(
+                    T002,
+                    "Map entries must have the same types, expected '{}: {}' but got '{}: {}'",
+                    key_ty,
+                    val_ty,
+                    k,
+                    v
+                );
+                return Err(TypeErrors::new_err(e));
+            }
+        }
+
+        let val_ty = entry_types
+            .iter()
+            .fold(val_ty.to_owned(), |ty, (_, v)| ty.merge(v));
+        res.ty = Type::Map(Box::new(key_ty.to_owned()), Box::new(val_ty));
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use parser::structs::Type;
+
+    use crate::type_checker::{expect_err, expect_pass};
+
+    fn map_ty(key_ty: Type, val_ty: Type) -> Type {
+        Type::Map(Box::new(key_ty), Box::new(val_ty))
+    }
+
+    #[test]
+    fn test_type_check_map() {
+        expect_pass(r#"#{"a": 1, "b": 2}"#, map_ty(Type::String, Type::Int));
+        expect_pass(
+            "let m: map[int, bool] = #{}; m",
+            map_ty(Type::Int, Type::Bool),
+        );
+        expect_pass(
+            "let m: map[int, bool] = map_new(); m",
+            map_ty(Type::Int, Type::Bool),
+        );
+        expect_pass(
+            "#{1: None, 2: Some(true)}",
+            map_ty(Type::Int, Type::Option(Box::new(Type::Bool))),
+        );
+
+        let t = r#"
+        let m: map[str, int] = map_new();
+        map_insert(m, "a", 1);
+        let old: option[int] = map_remove(m, "a");
+        let keys: [str] = map_keys(m);
+        map_len(m) + slice_len(keys)
+        "#;
+        expect_pass(t, Type::Int);
+
+        expect_pass(
+            r#"let m = #{"a": 1}; map_get(m, "a")"#,
+            Type::Option(Box::new(Type::Int)),
+        );
+        expect_pass(
+            "let fs = #{1: |x: int| x + 1}; fs",
+            map_ty(
+                Type::Int,
+                Type::UserFn(Box::new(parser::structs::FnTypeData {
+                    params: vec![Type::Int],
+                    ret_type: Type::Int,
+                })),
+            ),
+        );
+    }
+
+    #[test]
+    fn test_type_check_map_errs() {
+        expect_err(
+            "let m = #{};",
+            "Can't infer the key and value types of an empty map",
+            true,
+        );
+        expect_err(
+            "let m = map_new();",
+            "map_new() needs a type annotation e.g let m: map[str, int] = map_new();",
+            true,
+        );
+        expect_err(
+            "#{1.0: 2}",
+            "Keys of a map must be int, str or bool, got float",
+            true,
+        );
+        expect_err(
+            "let m: map[float, int] = #{};",
+            "Keys of a map must be int, str or bool, got float",
+            true,
+        );
+        expect_err(
+            r#"#{1: 2, "a": 3}"#,
+            "Map entries must have the same types, expected 'int: int' but got 'str: int'",
+            true,
+        );
+        expect_err(
+            r#"let m = #{"a": 1}; map_insert(m, "b", true);"#,
+            "Mismatched types in function call: got ((map[str, int], str, bool)) but expected ((map[str, int], str, int))",
+            true,
+        );
+        expect_err(
+            "let m: pmap[str, int] = pmap(); map_len(m)",
+            "Expected a map but got (pmap[str, int])",
+            true,
+        );
+        expect_err(
+            r#"let m = #{"a": 1}; map_get(m)"#,
+            "Function 'map_get' takes 2 arguments but 1 were supplied",
+            true,
+        );
+    }
+}
@@ -0,0 +1,937 @@
+use crate::type_checker::{new_env_with_syms, CheckResult, TypeChecker, TypeErrors};
+use diagnostics::{message, Message};
+use parser::structs::{MatchArm, MatchData, Pattern, Type};
+
+impl<'prog> TypeChecker<'prog> {
+    /*
+    1. Matched value must be int, bool, option, result, an enum or a struct, and every pattern must have its type
+    2. A variant pattern binds a name for each value the variant holds, and a struct pattern one for each field
+       it lists, in the guard and body of its arm only
+    3. A guard must be bool, and an arm with one doesn't count towards covering every value
+    4. Arms that don't terminate must all have the same type, which is the type of the match
+    5. A match must cover every value of the matched type, with a '_' arm if its arms don't, even as a statement
+    */
+    pub(crate) fn check_match(&mut self, data: &MatchData) -> Result<CheckResult, TypeErrors> {
+        let mut ty_errs = TypeErrors::new();
+
+        let subject_ty = match self.check_expr(&data.subject) {
+            Ok(res)
+                if matches!(res.ty, Type::Int | Type::Bool)
+                    || !self.variants_of(&res.ty).is_empty()
+                    || self.fields_of(&res.ty).is_some() =>
+            {
+                Some(res.ty)
+            }
+            Ok(res) => {
+                let e = message!(
+                    T009,
+                    "Can't match on type '{}', expected int, bool, option, result, an enum or a struct",
+                    res.ty
+                );
+                ty_errs.add(e);
+                None
+            }
+            Err(mut errs) => {
+                ty_errs.append(&mut errs);
+                None
+            }
+        };
+
+        // the types of the names each arm binds, empty for arms that bind nothing
+        let mut arm_binds: Vec<Vec<(String, Type)>> = vec![vec![]; data.arms.len()];
+
+        if let Some(ref subject_ty) = subject_ty {
+            // what the unguarded arms before each arm cover
+            let variants = self.variants_of(subject_ty);
+            let mut coverage = Coverage::new(variants.into_iter().map(|(name, _)| name).collect());
+
+            for (i, arm) in data.arms.iter().enumerate() {
+                // a guarded arm may not be taken, so it never makes a later arm unreachable
+                let prev: Vec<&MatchArm> = data.arms[..i]
+                    .iter()
+                    .filter(|prev| prev.guard.is_none())
+                    .collect();
+                if prev.iter().any(|prev| prev.pat == Pattern::Wildcard) {
+                    let e = message!(T009, "Unreachable arm '{}' after wildcard arm", arm.pat);
+                    ty_errs.add(e);
+                    continue;
+                }
+
+                if let Some(prev) = prev
+                    .iter()
+                    .find(|prev| matches!(prev.pat, Pattern::Struct { .. }))
+                {
+                    let e = message!(
+                        T009,
+                        "Unreachable arm '{}' after '{}', which matches every value",
+                        arm.pat,
+                        prev.pat
+                    );
+                    ty_errs.add(e);
+                    continue;
+                }
+
+                if prev.iter().any(|prev| same_case(&prev.pat, &arm.pat)) {
+                    let e = message!(T009, "Pattern '{}' is matched more than once", arm.pat);
+                    ty_errs.add(e);
+                    continue;
+                }
+
+                match self.check_pattern(&arm.pat, subject_ty) {
+                    // like a '_' arm after true and false, or 3 after 0..=9
+                    Ok(_) if coverage.covers(&arm.pat) => {
+                        let e = message!(
+                            T009,
+                            "Unreachable arm '{}', the arms before it match every value it does",
+                            arm.pat
+                        );
+                        ty_errs.add(e);
+                    }
+                    Ok(binds) => arm_binds[i] = binds,
+                    Err(e) => ty_errs.add(e),
+                }
+                if arm.guard.is_none() {
+                    coverage.add(&arm.pat);
+                }
+            }
+        }
+
+        let mut arm_results = vec![];
+        for (arm, binds) in data.arms.iter().zip(arm_binds) {
+            match self.check_arm(arm, binds) {
+                Ok(res) => arm_results.push(res),
+                Err(mut errs) => ty_errs.append(&mut errs),
+            }
+        }
+
+        if !ty_errs.is_ok() {
+            return Err(ty_errs);
+        }
+
+        // arms that break or return don't produce a value, so their type doesn't count
+        let mut match_ty: Option<Type> = None;
+        for res in arm_results.iter() {
+            if res.must_break || res.must_return {
+                continue;
+            }
+
+            match match_ty {
+                Some(ref ty) if *ty != res.ty => {
+                    let e = message!(
+                        T002,
+                        "match arms have different types - expected {}, got {}",
+                        ty,
+                        res.ty
+                    );
+                    return Err(TypeErrors::new_err(e));
+                }
+                // Some(1) in one arm and None in another make an option[int]
+                Some(ref ty) => match_ty = Some(ty.merge(&res.ty)),
+                None => match_ty = Some(res.ty.to_owned()),
+            }
+        }
+        let match_ty = match_ty.unwrap_or(Type::Unit);
+
+        // only arms without a guard count towards covering every case
+        let variants = subject_ty
+            .as_ref()
+            .map(|ty| self.variants_of(ty))
+            .unwrap_or_default();
+        let mut coverage = Coverage::new(variants.into_iter().map(|(name, _)| name).collect());
+        for arm in data.arms.iter().filter(|arm| arm.guard.is_none()) {
+            coverage.add(&arm.pat);
+        }
+
+        if !coverage.is_exhaustive() {
+            let e = match (match_ty, subject_ty) {
+                (Type::Unit, Some(subject_ty)) => message!(
+                    T009,
+                    "match {} doesn't cover every value of type {}",
+                    self.missing_arms(Some(&subject_ty)),
+                    subject_ty
+                ),
+                (match_ty, subject_ty) => message!(
+                    T009,
+                    "match {} can't produce a value of type {}",
+                    self.missing_arms(subject_ty.as_ref()),
+                    match_ty
+                ),
+            };
+            return Err(TypeErrors::new_err(e));
+        }
+
+        Ok(CheckResult {
+            ty: match_ty,
+            must_break: arm_results.iter().all(|res| res.must_break),
+            must_return: arm_results.iter().all(|res| res.must_return),
+        })
+    }
+
+    // The names the pattern binds are only in scope in the guard and body of the arm
+    fn check_arm(
+        &mut self,
+        arm: &MatchArm,
+        binds: Vec<(String, Type)>,
+    ) -> Result<CheckResult, TypeErrors> {
+        if binds.is_empty() {
+            return self.check_guarded_body(arm);
+        }
+
+        let mut env = new_env_with_syms(vec![]);
+        env.extend(binds);
+        self.envs.push(env);
+        let res = self.check_guarded_body(arm);
+        self.envs.pop();
+        res
+    }
+
+    // The guard must be bool, errors from it and the body are reported together
+    fn check_guarded_body(&mut self, arm: &MatchArm) -> Result<CheckResult, TypeErrors> {
+        let mut ty_errs = TypeErrors::new();
+
+        if let Some(guard) = &arm.guard {
+            match self.check_expr(guard) {
+                Ok(res) if res.ty == Type::Bool => (),
+                Ok(res) => {
+                    let e = message!(
+                        T002,
+                        "Expected type '{}' for guard of pattern '{}' but got '{}'",
+                        Type::Bool,
+                        arm.pat,
+                        res.ty
+                    );
+                    ty_errs.add(e);
+                }
+                Err(mut errs) => ty_errs.append(&mut errs),
+            }
+        }
+
+        match self.check_expr(&arm.body) {
+            Ok(res) if ty_errs.is_ok() => Ok(res),
+            Ok(_) => Err(ty_errs),
+            Err(mut errs) => {
+                ty_errs.append(&mut errs);
+                Err(ty_errs)
+            }
+        }
+    }
+
+    // What a match that doesn't cover every value is missing, for the error
+    fn missing_arms(&self, subject_ty: Option<&Type>) -> String {
+        match subject_ty {
+            Some(Type::Option(_)) => "without both Some and None arms or a '_' arm".to_string(),
+            Some(Type::Result(_, _)) => "without both Ok and Err arms or a '_' arm".to_string(),
+            Some(ty @ Type::Struct(name)) if self.fields_of(ty).is_some() => {
+                format!("without an unguarded {} {{ .. }} arm or a '_' arm", name)
+            }
+            Some(Type::Struct(name)) => {
+                format!("without an arm for each variant of {} or a '_' arm", name)
+            }
+            _ => "without a '_' arm".to_string(),
+        }
+    }
+
+    // The names the pattern binds with their types, leaving out '_'
+    fn check_pattern(
+        &self,
+        pat: &Pattern,
+        subject_ty: &Type,
+    ) -> Result<Vec<(String, Type)>, Message> {
+        let pat_ty = match pat {
+            Pattern::Int(_) => Type::Int,
+            Pattern::Range { .. } if pat.int_range().is_none() => {
+                return Err(message!(T009, "Range pattern '{}' matches no values", pat));
+            }
+            Pattern::Range { .. } => Type::Int,
+            Pattern::Bool(_) => Type::Bool,
+            Pattern::Variant(name, binds) => {
+                let variants = self.variants_of(subject_ty);
+                return check_variant_pattern(pat, name, binds, subject_ty, &variants);
+            }
+            Pattern::Struct { .. } => return self.check_struct_pattern(pat, subject_ty),
+            Pattern::Wildcard => return Ok(vec![]),
+            Pattern::Or(alts) => {
+                for alt in alts.iter() {
+                    if !self.check_pattern(alt, subject_ty)?.is_empty() {
+                        return Err(message!(
+                            T009,
+                            "Pattern '{}' in '{}' can't bind names, use '_' instead",
+                            alt,
+                            pat
+                        ));
+                    }
+                }
+                return Ok(vec![]);
+            }
+        };
+
+        if pat_ty != *subject_ty {
+            return Err(message!(
+                T009,
+                "Pattern '{}' has type {} but matched value has type {}",
+                pat,
+                pat_ty,
+                subject_ty
+            ));
+        }
+
+        Ok(vec![])
+    }
+
+    // The fields of a struct with their types, None for other types
+    fn fields_of(&self, ty: &Type) -> Option<&Vec<(String, Type)>> {
+        match ty {
+            Type::Struct(name) => self.structs.get(name),
+            _ => None,
+        }
+    }
+
+    // The names a struct pattern binds with their types, leaving out '_'
+    fn check_struct_pattern(
+        &self,
+        pat: &Pattern,
+        subject_ty: &Type,
+    ) -> Result<Vec<(String, Type)>, Message> {
+        let Pattern::Struct { name, fields, rest } = pat else {
+            unreachable!("Only called with struct patterns");
+        };
+
+        let Some(struct_fields) = self.fields_of(subject_ty) else {
+            return Err(message!(
+                T009,
+                "Pattern '{}' is a struct but matched value has type {}",
+                pat,
+                subject_ty
+            ));
+        };
+        if Type::Struct(name.to_owned()) != *subject_ty {
+            return Err(message!(
+                T009,
+                "Pattern '{}' has type {} but matched value has type {}",
+                pat,
+                name,
+                subject_ty
+            ));
+        }
+
+        let mut binds = vec![];
+        for (i, (field, bind)) in fields.iter().enumerate() {
+            if fields[..i].iter().any(|(prev, _)| prev == field) {
+                return Err(message!(
+                    T009,
+                    "Field '{}' is bound more than once in pattern '{}'",
+                    field,
+                    pat
+                ));
+            }
+
+            let Some((_, ty)) = struct_fields.iter().find(|(name, _)| name == field) else {
+                return Err(message!(T007, "Struct '{}' has no field '{}'", name, field));
+            };
+            if bind != "_" {
+                binds.push((bind.to_owned(), ty.to_owned()));
+            }
+        }
+
+        if !rest {
+            let missing = struct_fields
+                .iter()
+                .find(|(field, _)| !fields.iter().any(|(listed, _)| listed == field));
+            if let Some((field, _)) = missing {
+                return Err(message!(
+                    T009,
+                    "Pattern '{}' doesn't list field '{}', list it or add '..' to leave it out",
+                    pat,
+                    field
+                ));
+            }
+        }
+
+        Ok(binds)
+    }
+
+    // The variants of an option, result or enum with the types of the values they hold, empty for other types
+    fn variants_of(&self, ty: &Type) -> Vec<(String, Vec<Type>)> {
+        match ty {
+            Type::Option(some_ty) => vec![
+                ("Some".to_string(), vec![*some_ty.clone()]),
+                ("None".to_string(), vec![]),
+            ],
+            Type::Result(ok_ty, err_ty) => vec![
+                ("Ok".to_string(), vec![*ok_ty.clone()]),
+                ("Err".to_string(), vec![*err_ty.clone()]),
+            ],
+            Type::Struct(name) => self.enums.get(name).cloned().unwrap_or_default(),
+            _ => vec![],
+        }
+    }
+}
+
+/// What the unguarded arms of a match cover so far, to tell whether a later arm can ever be taken and whether the
+/// match covers every value. The type checker rejects a match that fails either, the compiler uses it to leave out
+/// arms that can't be taken when the program isn't type checked.
+#[derive(Debug, Clone, Default)]
+pub struct Coverage {
+    // the names of the variants of the matched type, empty if it has none
+    variants: Vec<String>,
+    // the variants covered, by name
+    covered: Vec<String>,
+    bools: Vec<bool>,
+    // the ints covered as inclusive ranges, sorted and merged so no two overlap or touch
+    ints: Vec<(i64, i64)>,
+    // a '_' or struct pattern covers every value
+    all: bool,
+}
+
+impl Coverage {
+    pub fn new(variants: Vec<String>) -> Coverage {
+        Coverage {
+            variants,
+            ..Default::default()
+        }
+    }
+
+    /// Whether every value the pattern matches is matched by a pattern added before it.
+    pub fn covers(&self, pat: &Pattern) -> bool {
+        if self.is_exhaustive() {
+            return true;
+        }
+
+        match pat {
+            Pattern::Int(val) => self.covers_ints(*val, *val),
+            Pattern::Range { .. } => match pat.int_range() {
+                Some((lo, hi)) => self.covers_ints(lo, hi),
+                None => true,
+            },
+            Pattern::Bool(val) => self.bools.contains(val),
+            Pattern::Variant(name, _) => self.covered.contains(name),
+            Pattern::Or(alts) => alts.iter().all(|alt| self.covers(alt)),
+            Pattern::Struct { .. } | Pattern::Wildcard => false,
+        }
+    }
+
+    pub fn add(&mut self, pat: &Pattern) {
+        match pat {
+            Pattern::Int(val) => self.add_ints(*val, *val),
+            Pattern::Range { .. } => {
+                if let Some((lo, hi)) = pat.int_range() {
+                    self.add_ints(lo, hi);
+                }
+            }
+            Pattern::Bool(val) => self.bools.push(*val),
+            Pattern::Variant(name, _) => self.covered.push(name.to_owned()),
+            Pattern::Or(alts) => alts.iter().for_each(|alt| self.add(alt)),
+            Pattern::Struct { .. } | Pattern::Wildcard => self.all = true,
+        }
+    }
+
+    /// Whether every value of the matched type is covered, like true and false or every variant of an enum.
+    pub fn is_exhaustive(&self) -> bool {
+        self.all
+            || (self.bools.contains(&true) && self.bools.contains(&false))
+            || (!self.variants.is_empty() && self.variants.iter().all(|v| self.covered.contains(v)))
+            || self.ints == [(i64::MIN, i64::MAX)]
+    }
+
+    // The ranges are merged, so lo..=hi is covered only if one range holds all of it
+    fn covers_ints(&self, lo: i64, hi: i64) -> bool {
+        self.ints
+            .iter()
+            .any(|(start, end)| *start <= lo && hi <= *end)
+    }
+
+    fn add_ints(&mut self, lo: i64, hi: i64) {
+        self.ints.push((lo, hi));
+        self.ints.sort();
+
+        let mut merged: Vec<(i64, i64)> = vec![];
+        for (lo, hi) in self.ints.drain(..) {
+            match merged.last_mut() {
+                // 0..=4 and 5..=9 touch, so they merge into 0..=9
+                Some((_, end)) if lo <= end.saturating_add(1) => *end = (*end).max(hi),
+                _ => merged.push((lo, hi)),
+            }
+        }
+        self.ints = merged;
+    }
+}
+
+// Whether two patterns match the same values, like Some(x) and Some(y)
+fn same_case(a: &Pattern, b: &Pattern) -> bool {
+    match (a, b) {
+        (Pattern::Variant(a, _), Pattern::Variant(b, _)) => a == b,
+        _ => a == b,
+    }
+}
+
+// The names the pattern binds with their types, leaving out '_'
+fn check_variant_pattern(
+    pat: &Pattern,
+    name: &str,
+    binds: &[String],
+    subject_ty: &Type,
+    variants: &[(String, Vec<Type>)],
+) -> Result<Vec<(String, Type)>, Message> {
+    let Some((_, payload)) = variants.iter().find(|(variant, _)| variant == name) else {
+        let e = match variants.is_empty() {
+            true => message!(
+                T009,
+                "Pattern '{}' is a variant but matched value has type {}",
+                pat,
+                subject_ty
+            ),
+            false => message!(T009, "'{}' is not a variant of {}", name, subject_ty),
+        };
+        return Err(e);
+    };
+
+    if payload.len() != binds.len() {
+        let e = message!(
+            T009,
+            "Pattern '{}' binds {} names but {} holds {} values",
+            pat,
+            binds.len(),
+            name,
+            payload.len()
+        );
+        return Err(e);
+    }
+
+    Ok(binds
+        .iter()
+        .zip(payload.iter())
+        .filter(|(bind, _)| *bind != "_")
+        .map(|(bind, ty)| (bind.to_owned(), ty.to_owned()))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use parser::structs::{Pattern, Type};
+
+    use super::Coverage;
+    use crate::type_checker::{expect_err, expect_pass};
+
+    #[test]
+    fn test_type_check_match() {
+        let t = r"
+        let x = 2;
+        match x {
+            1 => 10,
+            -1 => { 20 }
+            _ => x * 2,
+        }
+        ";
+        expect_pass(t, Type::Int);
+
+        // true and false cover every bool
+        expect_pass("match 1 > 2 { true => 1.5, false => 2.5 }", Type::Float);
+        expect_pass(
+            "let y: bool = match 3 { 3 => true, _ => false }; y",
+            Type::Bool,
+        );
+
+        // a match used as a statement still has to cover every value
+        expect_pass("match 1 { 1 => { println(1); } _ => {} }", Type::Unit);
+        expect_pass("match true { true => {} false => {} }", Type::Unit);
+
+        // arms that return don't count towards the type
+        let t = r"
+        fn f(x: int) -> int {
+            let y = match x {
+                0 => { return 0; }
+                _ => x + 1,
+            };
+            y
+        }
+        fn g(x: bool) -> int {
+            match x {
+                true => { return 1; }
+                false => { return 2; }
+            }
+        }
+        f(2) + g(true)
+        ";
+        expect_pass(t, Type::Int);
+    }
+
+    #[test]
+    fn test_type_check_match_errs() {
+        expect_err(
+            "match 2.5 { _ => 1 }",
+            "Can't match on type 'float', expected int, bool, option, result, an enum or a struct",
+            true,
+        );
+        expect_err(
+            "match 1 { true => 1, _ => 2 }",
+            "Pattern 'true' has type bool but matched value has type int",
+            true,
+        );
+        expect_err(
+            "match true { 1 => 1, _ => 2 }",
+            "Pattern '1' has type int but matched value has type bool",
+            true,
+        );
+        expect_err(
+            "match 1 { 1 => 1, 1 => 2, _ => 3 }",
+            "Pattern '1' is matched more than once",
+            true,
+        );
+        expect_err(
+            "match 1 { _ => 1, 2 => 2 }",
+            "Unreachable arm '2' after wildcard arm",
+            true,
+        );
+        expect_err(
+            "match 1 { 1 => 1, _ => true }",
+            "match arms have different types - expected int, got bool",
+            true,
+        );
+        expect_err(
+            "let x = match 1 { 1 => 2 };",
+            "match without a '_' arm can't produce a value of type int",
+            true,
+        );
+        expect_err(
+            "let x = 3; match x { 1 => { println(1); } }",
+            "match without a '_' arm doesn't cover every value of type int",
+            true,
+        );
+        expect_err(
+            "match true { true => {} }",
+            "match without a '_' arm doesn't cover every value of type bool",
+            true,
+        );
+        expect_err("match y { _ => !1 }", "Identifier 'y' not declared", true);
+        expect_err(
+            "match 1 { Some(x) => x, _ => 0 }",
+            "Pattern 'Some(x)' is a variant but matched value has type int",
+            true,
+        );
+        expect_err("match y { _ => !1 }", "Can't apply logical NOT", true);
+    }
+
+    #[test]
+    fn test_type_check_match_variant() {
+        let t = r#"
+        fn parse(s: str) -> option[int] {
+            match atoi(s) {
+                Ok(n) => Some(n),
+                Err(_) => None,
+            }
+        }
+        match parse("42") {
+            Some(n) => n + 1,
+            None => 0,
+        }
+        "#;
+        expect_pass(t, Type::Int);
+
+        // None and Err take the rest of their type from the other arm
+        let t = r#"
+        let r: result[int, str] = Err("bad");
+        let x = match r {
+            Ok(n) => Some(n),
+            Err(e) => None,
+        };
+        x
+        "#;
+        expect_pass(t, Type::Option(Box::new(Type::Int)));
+
+        // the bound name is only in scope in its arm
+        expect_err(
+            "let o = Some(1); match o { Some(x) => x, None => x }",
+            "Identifier 'x' not declared",
+            true,
+        );
+        expect_pass(
+            "let o = Some(2); match o { Some(_) => true, _ => false }",
+            Type::Bool,
+        );
+    }
+
+    #[test]
+    fn test_type_check_match_variant_errs() {
+        expect_err(
+            "let o = Some(1); match o { Some(x) => x }",
+            "match without both Some and None arms or a '_' arm can't produce a value of type int",
+            true,
+        );
+        expect_err(
+            "match atoi(\"1\") { Ok(n) => n }",
+            "match without both Ok and Err arms or a '_' arm can't produce a value of type int",
+            true,
+        );
+        expect_err(
+            "match Some(1) { Ok(n) => n, _ => 0 }",
+            "'Ok' is not a variant of option[int]",
+            true,
+        );
+        expect_err(
+            "match Some(1) { Some(a, b) => a, _ => 0 }",
+            "Pattern 'Some(a, b)' binds 2 names but Some holds 1 values",
+            true,
+        );
+        expect_err(
+            "match Some(1) { Some(a) => a, Some(b) => b, None => 0 }",
+            "Pattern 'Some(b)' is matched more than once",
+            true,
+        );
+        expect_err(
+            "match Some(1) { Some(a) => a, None => true }",
+            "match arms have different types - expected int, got bool",
+            true,
+        );
+    }
+
+    #[test]
+    fn test_type_check_match_enum() {
+        let shape = "enum Shape { Circle(float), Rect(float, float), Empty }";
+        let t = format!(
+            "{} let s = Rect(2.0, 3.0); match s {{ Circle(r) => r * r, Rect(w, _) => w, Empty => 0.0 }}",
+            shape
+        );
+        expect_pass(&t, Type::Float);
+        expect_pass(
+            &format!("{} match Empty {{ Empty => true, _ => false }}", shape),
+            Type::Bool,
+        );
+
+        // a match used as a statement still has to cover every variant
+        expect_pass(
+            &format!(
+                "{} match Empty {{ Empty => {{ println(1); }} _ => {{}} }}",
+                shape
+            ),
+            Type::Unit,
+        );
+        expect_err(
+            &format!("{} match Empty {{ Empty => {{ println(1); }} }}", shape),
+            "match without an arm for each variant of Shape or a '_' arm doesn't cover every value of type Shape",
+            true,
+        );
+        expect_err(
+            &format!("{} match Empty {{ Circle(r) => r, Empty => 0.0 }}", shape),
+            "match without an arm for each variant of Shape or a '_' arm can't produce a value of type float",
+            true,
+        );
+        expect_err(
+            &format!("{} match Empty {{ Some(r) => r, _ => 0.0 }}", shape),
+            "'Some' is not a variant of Shape",
+            true,
+        );
+        expect_err(
+            &format!("{} match Empty {{ Rect(w) => w, _ => 0.0 }}", shape),
+            "Pattern 'Rect(w)' binds 1 names but Rect holds 2 values",
+            true,
+        );
+    }
+
+    #[test]
+    fn test_type_check_match_or_range() {
+        let t = r"
+        let x = 5;
+        match x {
+            0 | 1 => 1,
+            2..5 => 2,
+            5..=9 | 20 => 3,
+            _ => 4,
+        }
+        ";
+        expect_pass(t, Type::Int);
+        expect_pass("match Some(1) { Some(_) | None => true }", Type::Bool);
+        expect_pass("match true { true | false => 1 }", Type::Int);
+
+        // ints are only covered by '_', or ranges that cover every int
+        expect_err(
+            "match 1 { 0..=9 => 1, 10 | 11 => 2 }",
+            "match without a '_' arm can't produce a value of type int",
+            true,
+        );
+        expect_err(
+            "match 1 { 5..5 => 1, _ => 2 }",
+            "Range pattern '5..5' matches no values",
+            true,
+        );
+        expect_err(
+            "match 1 { 5..=4 => 1, _ => 2 }",
+            "Range pattern '5..=4' matches no values",
+            true,
+        );
+        expect_err(
+            "match true { 0..2 => 1, _ => 2 }",
+            "Pattern '0..2' has type int but matched value has type bool",
+            true,
+        );
+        expect_err(
+            "match Some(1) { Some(x) | None => 1 }",
+            "Pattern 'Some(x)' in 'Some(x) | None' can't bind names, use '_' instead",
+            true,
+        );
+        expect_err(
+            "match 1 { 1 | true => 1, _ => 2 }",
+            "Pattern 'true' has type bool but matched value has type int",
+            true,
+        );
+
+        // an arm the arms before it cover is unreachable, the same as an arm after '_'
+        let unreachable = [
+            ("match true { true => 1, false => 2, _ => 3 }", "_"),
+            ("match 5 { 0..=9 => 1, 3 => 2, _ => 3 }", "3"),
+            (
+                "match 5 { 0..5 | 5..10 => 1, 2..=7 | 9 => 2, _ => 3 }",
+                "2..=7 | 9",
+            ),
+            ("match None { Some(_) | None => 1, None => 2 }", "None"),
+            ("enum E { A, B } match A { A => 1, B => 2, _ => 3 }", "_"),
+        ];
+        for (inp, arm) in unreachable {
+            expect_err(
+                inp,
+                &format!(
+                    "Unreachable arm '{}', the arms before it match every value it does",
+                    arm
+                ),
+                true,
+            );
+        }
+
+        // a guarded arm may not be taken, so it doesn't cover the arms after it
+        expect_pass(
+            "match true { true => 1, false if 1 > 2 => 2, _ => 3 }",
+            Type::Int,
+        );
+        expect_pass("match 5 { 0..=9 if false => 1, 3 => 2, _ => 3 }", Type::Int);
+    }
+
+    #[test]
+    fn test_match_coverage() {
+        let range = |lo, hi| Pattern::Range {
+            lo,
+            hi,
+            inclusive: true,
+        };
+
+        let mut coverage = Coverage::new(vec![]);
+        coverage.add(&Pattern::Or(vec![range(0, 4), Pattern::Int(10)]));
+        coverage.add(&range(5, 9));
+        assert!(coverage.covers(&Pattern::Int(3)));
+        assert!(coverage.covers(&range(0, 10)));
+        assert!(!coverage.covers(&range(0, 11)));
+        assert!(!coverage.covers(&Pattern::Or(vec![Pattern::Int(1), Pattern::Int(-1)])));
+        assert!(!coverage.is_exhaustive());
+
+        coverage.add(&range(i64::MIN, -1));
+        coverage.add(&Pattern::Range {
+            lo: 11,
+            hi: i64::MAX,
+            inclusive: true,
+        });
+        assert!(coverage.is_exhaustive());
+        assert!(coverage.covers(&Pattern::Wildcard));
+
+        let mut coverage = Coverage::new(vec!["Some".to_string(), "None".to_string()]);
+        coverage.add(&Pattern::Variant("Some".to_string(), vec!["x".to_string()]));
+        assert!(!coverage.is_exhaustive());
+        coverage.add(&Pattern::Variant("None".to_string(), vec![]));
+        assert!(coverage.is_exhaustive());
+    }
+
+    #[test]
+    fn test_type_check_match_guard() {
+        // the guard sees the names its pattern binds
+        expect_pass(
+            "match Some(2) { Some(n) if n > 1 => n, Some(n) => -n, None => 0 }",
+            Type::Int,
+        );
+        // a guarded arm doesn't make a later arm for the same case unreachable
+        expect_pass("match 1 { 1 if false => 1, 1 => 2, _ => 3 }", Type::Int);
+        expect_pass("match 1 { _ if true => 1, _ => 2 }", Type::Int);
+
+        expect_err(
+            "match 1 { 1 if 1 => 1, _ => 2 }",
+            "Expected type 'bool' for guard of pattern '1' but got 'int'",
+            true,
+        );
+        expect_err(
+            "match Some(1) { Some(n) if n => 1, _ => 2 }",
+            "Expected type 'bool' for guard of pattern 'Some(n)' but got 'int'",
+            true,
+        );
+        // guarded arms don't count towards covering every value
+        expect_err(
+            "match true { true => 1, false if true => 2 }",
+            "match without a '_' arm can't produce a value of type int",
+            true,
+        );
+        expect_err(
+            "match Some(1) { Some(n) => n, None if true => 0 }",
+            "match without both Some and None arms or a '_' arm can't produce a value of type int",
+            true,
+        );
+        expect_err(
+            "struct P { x: int } match P { x: 1 } { P { x } if x > 0 => x }",
+            "match without an unguarded P { .. } arm or a '_' arm can't produce a value of type int",
+            true,
+        );
+        expect_err(
+            "match 1 { 1 => 1, 1 if true => 2, _ => 3 }",
+            "Pattern '1' is matched more than once",
+            true,
+        );
+    }
+
+    #[test]
+    fn test_type_check_match_struct() {
+        let p = "struct P { x: int, y: float } let p = P { x: 1, y: 2.0 };";
+        expect_pass(
+            &format!("{} match p {{ P {{ x, y: b }} => b }}", p),
+            Type::Float,
+        );
+        expect_pass(
+            &format!("{} match p {{ P {{ x, .. }} => x }}", p),
+            Type::Int,
+        );
+        expect_pass(
+            &format!("{} match p {{ P {{ x: _, .. }} => 1 }}", p),
+            Type::Int,
+        );
+
+        // binds only live in the arm
+        expect_err(
+            &format!("{} match p {{ P {{ x, .. }} => x }}; x", p),
+            "Identifier 'x' not declared",
+            true,
+        );
+        expect_err(
+            &format!("{} match p {{ P {{ x }} => x }}", p),
+            "Pattern 'P { x }' doesn't list field 'y', list it or add '..' to leave it out",
+            true,
+        );
+        expect_err(
+            &format!("{} match p {{ P {{ z, .. }} => 1 }}", p),
+            "Struct 'P' has no field 'z'",
+            true,
+        );
+        expect_err(
+            &format!("{} match p {{ P {{ x, x: a, .. }} => 1 }}", p),
+            "Field 'x' is bound more than once in pattern 'P { x, x: a, .. }'",
+            true,
+        );
+        expect_err(
+            &format!("{} struct Q {{ x: int }} match p {{ Q {{ x }} => x }}", p),
+            "Pattern 'Q { x }' has type Q but matched value has type P",
+            true,
+        );
+        expect_err(
+            "match 1 { P { x } => x, _ => 0 }",
+            "Pattern 'P { x }' is a struct but matched value has type int",
+            true,
+        );
+        expect_err(
+            &format!("{} match p {{ P {{ .. }} => 1, _ => 2 }}", p),
+            "Unreachable arm '_' after 'P { .. }', which matches every value",
+            true,
+        );
+    }
+}
@@ -0,0 +1,397 @@
+use std::collections::HashSet;
+
+use crate::type_checker::{CheckResult, TypeChecker, TypeErrors};
+use diagnostics::message;
+use parser::structs::{
+    BlockSeq, Decl, Expr, FieldAssignData, StructDeclData, StructExprData, Type,
+};
+
+impl<'prog> TypeChecker<'prog> {
+    /// Register the structs declared at the top level before checking, so they can be used before their
+    /// declaration like fns.
+    pub(crate) fn register_structs(&mut self, program: &BlockSeq) -> Result<(), TypeErrors> {
+        let mut ty_errs = TypeErrors::new();
+
+        for (idx, decl) in program.decls.iter().enumerate() {
+            let Decl::StructDeclStmt(data) = decl else {
+                continue;
+            };
+
+            if self.structs.contains_key(&data.name) {
+                let e = message!(T007, "Struct '{}' is already declared", data.name);
+                ty_errs.add_at(e, program.decl_span(idx));
+                continue;
+            }
+
+            self.structs
+                .insert(data.name.to_owned(), data.fields.to_owned());
+        }
+
+        if ty_errs.is_ok() {
+            Ok(())
+        } else {
+            Err(ty_errs)
+        }
+    }
+
+    /// Check that a type annotation only refers to types that exist, looking inside arrays, channels, maps, persistent
+    /// collections and fn types.
+    pub(crate) fn check_type_ann(&self, ty: &Type) -> Result<(), TypeErrors> {
+        match ty {
+            Type::Struct(name)
+                if !self.structs.contains_key(name) && !self.enums.contains_key(name) =>
+            {
+                let e = message!(T005, "Unknown type '{}'", name);
+                Err(TypeErrors::new_err(e))
+            }
+            Type::Array(elem_ty, _)
+            | Type::Slice(elem_ty)
+            | Type::Channel(elem_ty)
+            | Type::PVec(elem_ty) => self.check_type_ann(elem_ty),
+            Type::PMap(key_ty, val_ty) | Type::Map(key_ty, val_ty) => {
+                if !matches!(key_ty.as_ref(), Type::Int | Type::String | Type::Bool) {
+                    let kind = if matches!(ty, Type::Map(_, _)) {
+                        "map"
+                    } else {
+                        "pmap"
+                    };
+                    let e = message!(
+                        T005,
+                        "Keys of a {} must be int, str or bool, got {}",
+                        kind,
+                        key_ty
+                    );
+                    return Err(TypeErrors::new_err(e));
+                }
+                self.check_type_ann(val_ty)
+            }
+            Type::UserFn(fn_ty) => {
+                for param_ty in fn_ty.params.iter() {
+                    self.check_type_ann(param_ty)?;
+                }
+                self.check_type_ann(&fn_ty.ret_type)
+            }
+            Type::Tuple(elem_tys) => elem_tys
+                .iter()
+                .try_for_each(|elem_ty| self.check_type_ann(elem_ty)),
+            _ => Ok(()),
+        }
+    }
+
+    // Fields were registered before checking, so only need to validate them here
+    pub(crate) fn check_struct_decl(
+        &mut self,
+        data: &StructDeclData,
+    ) -> Result<CheckResult, TypeErrors> {
+        // program block is the only env at the top level
+        if self.envs.len() != 1 {
+            let e = message!(
+                T007,
+                "Structs can only be declared at the top level, found '{}'",
+                data.name
+            );
+            return Err(TypeErrors::new_err(e));
+        }
+
+        let mut ty_errs = TypeErrors::new();
+        let mut seen: HashSet<&str> = HashSet::new();
+
+        for (field, ty) in data.fields.iter() {
+            if !seen.insert(field) {
+                let e = message!(
+                    T007,
+                    "Duplicate field '{}' in struct '{}'",
+                    field,
+                    data.name
+                );
+                ty_errs.add(e);
+            }
+
+            if let Err(mut errs) = self.check_type_ann(ty) {
+                ty_errs.append(&mut errs);
+            }
+        }
+
+        if ty_errs.is_ok() {
+            Ok(CheckResult {
+                ty: Type::Unit,
+                must_break: false,
+                must_return: false,
+            })
+        } else {
+            Err(ty_errs)
+        }
+    }
+
+    // Point { x: 1, y: 2.0 }: every field given once with the declared type
+    pub(crate) fn check_struct_expr(
+        &mut self,
+        data: &StructExprData,
+    ) -> Result<CheckResult, TypeErrors> {
+        let Some(decl_fields) = self.structs.get(&data.name).cloned() else {
+            let e = message!(T005, "Struct '{}' not declared", data.name);
+            return Err(TypeErrors::new_err(e));
+        };
+
+        let mut ty_errs = TypeErrors::new();
+        let mut res = CheckResult {
+            ty: Type::Struct(data.name.to_owned()),
+            must_break: false,
+            must_return: false,
+        };
+
+        for (i, (field, expr)) in data.fields.iter().enumerate() {
+            let expr_res = match self.check_expr(expr) {
+                Ok(expr_res) => expr_res,
+                Err(mut errs) => {
+                    ty_errs.append(&mut errs);
+                    continue;
+                }
+            };
+            res.must_break = res.must_break || expr_res.must_break;
+            res.must_return = res.must_return || expr_res.must_return;
+
+            if data.fields[..i].iter().any(|(prev, _)| prev == field) {
+                let e = message!(
+                    T007,
+                    "Field '{}' is specified more than once in '{}' literal",
+                    field,
+                    data.name
+                );
+                ty_errs.add(e);
+                continue;
+            }
+
+            let Some((_, field_ty)) = decl_fields.iter().find(|(name, _)| name == field) else {
+                let e = message!(T007, "Struct '{}' has no field '{}'", data.name, field);
+                ty_errs.add(e);
+                continue;
+            };
+
+            if *field_ty != expr_res.ty {
+                let e = message!(
+                    T002,
+                    "Field '{}' of struct '{}' has type '{}' but got '{}'",
+                    field,
+                    data.name,
+                    field_ty,
+                    expr_res.ty
+                );
+                ty_errs.add(e);
+            }
+        }
+
+        for (field, _) in decl_fields.iter() {
+            if !data.fields.iter().any(|(name, _)| name == field) {
+                let e = message!(T007, "Missing field '{}' in '{}' literal", field, data.name);
+                ty_errs.add(e);
+            }
+        }
+
+        if ty_errs.is_ok() {
+            Ok(res)
+        } else {
+            Err(ty_errs)
+        }
+    }
+
+    /// Check obj.field and return the type of the field.
+    pub(crate) fn check_field_access(
+        &mut self,
+        obj: &Expr,
+        field: &str,
+    ) -> Result<CheckResult, TypeErrors> {
+        let mut res = self.check_expr(obj)?;
+
+        // enums are named types like structs, but have no fields
+        let name = match &res.ty {
+            Type::Struct(name) if !self.enums.contains_key(name) => name.to_owned(),
+            _ => {
+                let e = message!(T007, "Can't access field '{}' on type '{}'", field, res.ty);
+                return Err(TypeErrors::new_err(e));
+            }
+        };
+
+        let field_ty = self
+            .structs
+            .get(&name)
+            .and_then(|fields| fields.iter().find(|(f, _)| f == field))
+            .map(|(_, ty)| ty.to_owned());
+
+        let Some(field_ty) = field_ty else {
+            let e = message!(T007, "Struct '{}' has no field '{}'", name, field);
+            return Err(TypeErrors::new_err(e));
+        };
+
+        res.ty = field_ty;
+        Ok(res)
+    }
+
+    // obj.field = expr
+    pub(crate) fn check_field_assign(
+        &mut self,
+        stmt: &FieldAssignData,
+    ) -> Result<CheckResult, TypeErrors> {
+        let mut ty_errs = TypeErrors::new();
+        let mut field_res = self.check_field_access(&stmt.obj, &stmt.field);
+        let mut expr_res = self.check_expr(&stmt.expr);
+
+        if let Err(ref mut errs) = field_res {
+            ty_errs.append(errs);
+        }
+
+        if let Err(ref mut errs) = expr_res {
+            ty_errs.append(errs);
+        }
+
+        if !ty_errs.is_ok() {
+            return Err(ty_errs);
+        }
+
+        let field_res = field_res?;
+        let expr_res = expr_res?;
+
+        if field_res.ty != expr_res.ty {
+            let e = message!(
+                T002,
+                "'{}.{}' has type {} but assigned type {}",
+                stmt.obj,
+                stmt.field,
+                field_res.ty,
+                expr_res.ty
+            );
+            return Err(TypeErrors::new_err(e));
+        }
+
+        Ok(CheckResult::combine(&field_res, &expr_res))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use parser::structs::Type;
+
+    use crate::type_checker::{expect_err, expect_pass, expect_pass_str};
+
+    #[test]
+    fn test_type_check_struct() {
+        let t = r"
+        struct Point { x: int, y: float }
+        let p = Point { y: 2.0, x: 1 };
+        p.x
+        ";
+        expect_pass(t, Type::Int);
+
+        // can be used before the declaration, and nested
+        let t = r"
+        let l = Line { start: Point { x: 1, y: 2.0 }, end: Point { x: 3, y: 4.0 } };
+        struct Line { start: Point, end: Point }
+        struct Point { x: int, y: float }
+        l.start.y + l.end.y
+        ";
+        expect_pass(t, Type::Float);
+
+        // in annotations, arrays and fns
+        let t = r"
+        struct P { x: int }
+        fn make(x: int) -> P {
+            P { x: x }
+        }
+        let ps: [P; 2] = [make(1), make(2)];
+        ps[1].x = ps[0].x + 1;
+        ps
+        ";
+        expect_pass_str(t, "[P; 2]");
+
+        // nominal: same fields but different structs
+        let t = r"
+        struct A { x: int }
+        struct B { x: int }
+        let a: A = B { x: 1 };
+        ";
+        expect_err(t, "'a' has declared type A but assigned type B", true);
+        expect_err(
+            "struct A { x: int } struct B { x: int } A { x: 1 } == B { x: 1 }",
+            "Can't apply '==' to types 'A' and 'B'",
+            true,
+        );
+        expect_pass("struct A { x: int } A { x: 1 } == A { x: 1 }", Type::Bool);
+    }
+
+    #[test]
+    fn test_type_check_struct_decl_errs() {
+        expect_err(
+            "struct P { x: int } struct P { y: int }",
+            "Struct 'P' is already declared",
+            true,
+        );
+        expect_err(
+            "struct P { x: int, x: float }",
+            "Duplicate field 'x' in struct 'P'",
+            true,
+        );
+        expect_err("struct P { x: Q }", "Unknown type 'Q'", true);
+        expect_err("struct P { xs: [Q; 2] }", "Unknown type 'Q'", true);
+        expect_err(
+            "fn f() { struct P { x: int } }",
+            "Structs can only be declared at the top level, found 'P'",
+            true,
+        );
+        expect_err("let x: u32 = 2;", "Unknown type 'u32'", true);
+        expect_err("fn f(x: Q) {}", "Unknown type 'Q'", true);
+        expect_err("fn f() -> fn(int) -> Q {}", "Unknown type 'Q'", true);
+    }
+
+    #[test]
+    fn test_type_check_struct_expr_errs() {
+        expect_err("P { x: 1 }", "Struct 'P' not declared", true);
+
+        let t = "struct P { x: int, y: float }";
+        expect_err(
+            &format!("{} P {{ x: 1 }}", t),
+            "Missing field 'y' in 'P' literal",
+            true,
+        );
+        expect_err(
+            &format!("{} P {{ x: 1, y: 2.0, z: 3 }}", t),
+            "Struct 'P' has no field 'z'",
+            true,
+        );
+        expect_err(
+            &format!("{} P {{ x: 1, y: 2.0, x: 3 }}", t),
+            "Field 'x' is specified more than once in 'P' literal",
+            true,
+        );
+        expect_err(
+            &format!("{} P {{ x: 1, y: 2 }}", t),
+            "Field 'y' of struct 'P' has type 'float' but got 'int'",
+            true,
+        );
+        expect_err(
+            &format!("{} P {{ x: !1, y: 2.0 }}", t),
+            "Can't apply logical NOT to type int",
+            true,
+        );
+    }
+
+    #[test]
+    fn test_type_check_field_errs() {
+        let t = "struct P { x: int } let p = P { x: 1 };";
+        expect_err(&format!("{} p.y", t), "Struct 'P' has no field 'y'", true);
+        expect_err(
+            "let x = 2; x.y",
+            "Can't access field 'y' on type 'int'",
+            true,
+        );
+        expect_err(
+            &format!("{} p.x = true;", t),
+            "'p.x' has type int but assigned type bool",
+            true,
+        );
+        expect_err(
+            &format!("{} p.y = 2;", t),
+            "Struct 'P' has no field 'y'",
+            true,
+        );
+    }
+}
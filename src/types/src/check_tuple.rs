@@ -0,0 +1,212 @@
+use crate::type_checker::{CheckResult, TypeChecker, TypeErrors};
+use diagnostics::message;
+use parser::structs::{Expr, LetTupleData, Type};
+
+impl<'prog> TypeChecker<'prog> {
+    // (e1, e2, ...): unlike arrays, the values can have different types
+    pub(crate) fn check_tuple(&mut self, elems: &[Expr]) -> Result<CheckResult, TypeErrors> {
+        let mut ty_errs = TypeErrors::new();
+        let mut res = CheckResult {
+            ty: Type::Unit,
+            must_break: false,
+            must_return: false,
+        };
+        let mut elem_types: Vec<Type> = vec![];
+
+        for elem in elems.iter() {
+            match self.check_expr(elem) {
+                Ok(elem_res) => {
+                    res = CheckResult::combine(&res, &elem_res);
+                    elem_types.push(elem_res.ty);
+                }
+                Err(mut errs) => ty_errs.append(&mut errs),
+            }
+        }
+
+        if !ty_errs.is_ok() {
+            return Err(ty_errs);
+        }
+
+        res.ty = Type::Tuple(elem_types);
+        Ok(res)
+    }
+
+    // t.0: the index is part of the syntax, so it is always checked against the length
+    pub(crate) fn check_tuple_index(
+        &mut self,
+        tuple: &Expr,
+        idx: usize,
+    ) -> Result<CheckResult, TypeErrors> {
+        let mut res = self.check_expr(tuple)?;
+        let Type::Tuple(elem_types) = &res.ty else {
+            let e = message!(T008, "Can't take .{} of type '{}'", idx, res.ty);
+            return Err(TypeErrors::new_err(e));
+        };
+
+        let Some(elem_ty) = elem_types.get(idx) else {
+            let e = message!(
+                T008,
+                "Tuple index {} is out of bounds for type '{}'",
+                idx,
+                res.ty
+            );
+            return Err(TypeErrors::new_err(e));
+        };
+
+        res.ty = elem_ty.to_owned();
+        Ok(res)
+    }
+
+    /// let (a, b) = expr; gives each name the type of the value at the same place in the tuple, and _ skips one.
+    /// Like let, an annotation is optional and the expression is checked against it when there is one.
+    pub(crate) fn check_let_tuple(
+        &mut self,
+        stmt: &LetTupleData,
+    ) -> Result<CheckResult, TypeErrors> {
+        let names = match stmt.idents.as_slice() {
+            [ident] => format!("({},)", ident),
+            _ => format!("({})", stmt.idents.join(", ")),
+        };
+
+        if let Some(ty_ann) = &stmt.type_ann {
+            if let Err(mut errs) = self.check_type_ann(ty_ann) {
+                errs.set_cont(false);
+                return Err(errs);
+            }
+        }
+
+        let mut res = self.check_expr(&stmt.expr).map_err(|mut errs| {
+            errs.set_cont(false);
+            errs
+        })?;
+
+        if let Some(ty_ann) = &stmt.type_ann {
+            if !ty_ann.eq(&res.ty) {
+                let e = message!(
+                    T002,
+                    "'{}' has declared type {} but assigned type {}",
+                    names,
+                    ty_ann,
+                    res.ty
+                );
+                let mut errs = TypeErrors::new_err(e);
+                errs.set_cont(false);
+                return Err(errs);
+            }
+            res.ty = ty_ann.to_owned();
+        }
+
+        let elem_types = match &res.ty {
+            Type::Tuple(elem_types) if elem_types.len() == stmt.idents.len() => elem_types,
+            _ => {
+                let e = message!(T002, "Can't destructure {} into {}", res.ty, names);
+                let mut errs = TypeErrors::new_err(e);
+                errs.set_cont(false);
+                return Err(errs);
+            }
+        };
+
+        let mut ty_errs = TypeErrors::new();
+        for (ident, ty) in stmt.idents.iter().zip(elem_types.iter()) {
+            if ident == "_" {
+                continue;
+            }
+
+            // let (a, b) = (None, 1); says nothing about what a may hold
+            if ty.has_infer() {
+                let e = message!(
+                    T006,
+                    "Can't infer the type of '{}' from {}, give it a type annotation",
+                    ident,
+                    ty
+                );
+                ty_errs.add(e);
+            }
+
+            if let Err(mut errs) = self.assign_ident(ident, ty.to_owned()) {
+                ty_errs.append(&mut errs);
+            }
+        }
+
+        if ty_errs.is_ok() {
+            Ok(res)
+        } else {
+            Err(ty_errs)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use parser::structs::Type;
+
+    use crate::type_checker::{expect_err, expect_pass};
+
+    #[test]
+    fn test_type_check_tuple() {
+        expect_pass("(1, true)", Type::Tuple(vec![Type::Int, Type::Bool]));
+        expect_pass("(2,)", Type::Tuple(vec![Type::Int]));
+        expect_pass("let t = (1, (2.0, 'c')); t.1.0", Type::Float);
+        expect_pass("let t = ([1, 2], 3); t.0[1] + t.1", Type::Int);
+        expect_pass("(1, true) == (1, false)", Type::Bool);
+
+        let t = r"
+        fn min_max(xs: [int; 3]) -> (int, int) {
+            let lo = xs[0];
+            let hi = xs[0];
+            for x in xs {
+                if x < lo { lo = x; }
+                if x > hi { hi = x; }
+            }
+            (lo, hi)
+        }
+        let (lo, hi) = min_max([3, 1, 2]);
+        let (_, top) : (int, int) = min_max([5, 6, 7]);
+        lo + hi + top
+        ";
+        expect_pass(t, Type::Int);
+
+        // None takes its type from the annotation
+        expect_pass(
+            "let (a, b) : (option[int], int) = (None, 1); a",
+            Type::Option(Box::new(Type::Int)),
+        );
+    }
+
+    #[test]
+    fn test_type_check_tuple_errs() {
+        expect_err(
+            "let t = (1, 2); t.2",
+            "Tuple index 2 is out of bounds for type '(int, int)'",
+            true,
+        );
+        expect_err("let x = 2; x.0", "Can't take .0 of type 'int'", true);
+        expect_err(
+            "let (a, b) = (1, 2, 3);",
+            "Can't destructure (int, int, int) into (a, b)",
+            true,
+        );
+        expect_err("let (a,) = 2;", "Can't destructure int into (a,)", true);
+        expect_err(
+            "let (a, b) : (int, int) = (1, true);",
+            "'(a, b)' has declared type (int, int) but assigned type (int, bool)",
+            true,
+        );
+        expect_err(
+            "let (a, b) = (None, 1);",
+            "Can't infer the type of 'a' from option[_], give it a type annotation",
+            true,
+        );
+        expect_err("let t : (int, Q) = (1, 2);", "Unknown type 'Q'", true);
+        expect_err(
+            "let t : (int, bool) = (1, 2);",
+            "'t' has declared type (int, bool) but assigned type (int, int)",
+            true,
+        );
+        expect_err(
+            "(1, true) == (true, 1)",
+            "Can't apply '==' to types '(int, bool)' and '(bool, int)'",
+            true,
+        );
+    }
+}
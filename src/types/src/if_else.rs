@@ -1,4 +1,5 @@
 use crate::type_checker::{CheckResult, TypeChecker, TypeErrors};
+use diagnostics::message;
 use parser::structs::{IfElseData, Type};
 
 impl<'prog> TypeChecker<'prog> {
@@ -22,12 +23,13 @@ impl<'prog> TypeChecker<'prog> {
             let check_cond = check_cond.unwrap();
             if !check_cond.ty.eq(&Type::Bool) {
                 // add cond is not bool err
-                let e = format!(
+                let e = message!(
+                    T002,
                     "Expected type '{}' for if condition, got '{}'",
                     Type::Bool,
                     check_cond.ty
                 );
-                ty_errs.add(&e);
+                ty_errs.add(e);
             }
         }
 
@@ -72,17 +74,21 @@ impl<'prog> TypeChecker<'prog> {
                 (false, false) => {
                     if if_ty.ty.eq(&else_ty.ty) {
                         if ty_errs.is_ok() {
-                            return Ok(if_ty);
+                            // Some(1) in one branch and None in the other make an option[int]
+                            let ty = if_ty.ty.merge(&else_ty.ty);
+                            return Ok(CheckResult { ty, ..if_ty });
                         } else {
                             return Err(ty_errs);
                         }
                     }
 
-                    let e = format!(
+                    let e = message!(
+                        T002,
                         "if-else has type mismatch - consequent: {}, alt: {}",
-                        if_ty.ty, else_ty.ty
+                        if_ty.ty,
+                        else_ty.ty
                     );
-                    ty_errs.add(&e);
+                    ty_errs.add(e);
                     // this would be the last error so we can return
                     return Err(ty_errs);
                 }
@@ -199,7 +205,7 @@ mod tests {
             30+false;
         }
         ";
-        expect_err(t,  "[TypeError]: Expected type 'bool' for if condition, got 'int'\n[TypeError]: 'x' has declared type bool but assigned type float\n[TypeError]: Can't apply '+' to types 'int' and 'bool'", false);
+        expect_err(t,  "[TypeError T002]: Expected type 'bool' for if condition, got 'int' at line 2, column 9\n[TypeError T002]: 'x' has declared type bool but assigned type float at line 3, column 13\n[TypeError T003]: Can't apply '+' to types 'int' and 'bool' at line 5, column 13", false);
 
         // multiple errs in blks
         let t = r"
@@ -211,7 +217,7 @@ mod tests {
             2.56+2;
         }
         ";
-        expect_err(t,  "[TypeError]: Expected type 'bool' for if condition, got 'int'\n[TypeError]: 'x' has declared type bool but assigned type float\n[TypeError]: Can't apply '+' to types 'int' and 'bool'\n[TypeError]: Can't apply '+' to types 'int' and 'bool'\n[TypeError]: Can't apply '+' to types 'float' and 'int'", false);
+        expect_err(t,  "[TypeError T002]: Expected type 'bool' for if condition, got 'int' at line 2, column 9\n[TypeError T002]: 'x' has declared type bool but assigned type float at line 3, column 13\n[TypeError T003]: Can't apply '+' to types 'int' and 'bool' at line 4, column 13\n[TypeError T003]: Can't apply '+' to types 'int' and 'bool' at line 6, column 13\n[TypeError T003]: Can't apply '+' to types 'float' and 'int' at line 7, column 13", false);
 
         // cond + else err
         let t = r"
@@ -221,7 +227,7 @@ mod tests {
             30+false;
         }
         ";
-        expect_err(t, "[TypeError]: Expected type 'bool' for if condition, got 'int'\n[TypeError]: Can't apply '+' to types 'int' and 'bool'", false);
+        expect_err(t, "[TypeError T002]: Expected type 'bool' for if condition, got 'int' at line 2, column 9\n[TypeError T003]: Can't apply '+' to types 'int' and 'bool' at line 5, column 13", false);
 
         // cond + if err
         let t = r"
@@ -231,7 +237,7 @@ mod tests {
              300;
          }
          ";
-        expect_err(t, "[TypeError]: Expected type 'bool' for if condition, got 'int'\n[TypeError]: Can't apply '+' to types 'int' and 'float'", false);
+        expect_err(t, "[TypeError T002]: Expected type 'bool' for if condition, got 'int' at line 2, column 10\n[TypeError T003]: Can't apply '+' to types 'int' and 'float' at line 3, column 14", false);
     }
 
     #[test]
@@ -244,7 +250,7 @@ mod tests {
             300+false;
          }
          ";
-        expect_err(t,  "[TypeError]: Can't apply '+' to types 'int' and 'float'\n[TypeError]: Can't apply '+' to types 'int' and 'bool'", false);
+        expect_err(t,  "[TypeError T003]: Can't apply '+' to types 'int' and 'float' at line 3, column 13\n[TypeError T003]: Can't apply '+' to types 'int' and 'bool' at line 5, column 13", false);
 
         // if only
         let t = r"
@@ -1,7 +1,19 @@
 pub mod blk;
+pub mod check_array;
+pub mod check_attrs;
+pub mod check_coalesce;
+pub mod check_enum;
 pub mod check_fn_call;
 pub mod check_fn_decl;
+pub mod check_isolate;
+pub mod check_join;
+pub mod check_lambda;
 pub mod check_let;
+pub mod check_lock;
 pub mod check_loop;
+pub mod check_map;
+pub mod check_match;
+pub mod check_struct;
+pub mod check_tuple;
 pub mod if_else;
 pub mod type_checker;
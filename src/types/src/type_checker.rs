@@ -1,11 +1,20 @@
+use diagnostics::{message, Code, Message};
+use lexer::Span;
 use parser::{structs::*, Parser};
-use std::{collections::HashMap, fmt::Display};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+};
 
 use parser::structs::{BlockSeq, Decl, Expr, Type};
 
+use crate::check_attrs::AttrTarget;
+use crate::check_fn_call::{builtin_structs, NONE};
+
 #[derive(Debug, PartialEq)]
 pub struct TypeErrors {
-    pub(crate) errs: Vec<String>,
+    // each error with its code, and the span of the declaration it is in once the block around it knows
+    pub(crate) errs: Vec<(Code, String, Option<Span>)>,
     pub(crate) cont: bool,
 }
 
@@ -17,9 +26,9 @@ impl TypeErrors {
         }
     }
 
-    pub fn new_err(err: &str) -> TypeErrors {
+    pub fn new_err(msg: Message) -> TypeErrors {
         TypeErrors {
-            errs: vec![err.to_string()],
+            errs: vec![(msg.code, msg.text, None)],
             cont: true,
         }
     }
@@ -28,8 +37,20 @@ impl TypeErrors {
         self.cont = cont
     }
 
-    pub fn add(&mut self, err: &str) {
-        self.errs.push(err.to_string());
+    pub fn add(&mut self, msg: Message) {
+        self.errs.push((msg.code, msg.text, None));
+    }
+
+    pub fn add_at(&mut self, msg: Message, span: Option<Span>) {
+        self.errs.push((msg.code, msg.text, span));
+    }
+
+    /// Give the errors that don't have a span yet this one. Blocks give the errors of each declaration its span,
+    /// so an error has the span of the innermost declaration it is in.
+    pub fn or_at(&mut self, span: Option<Span>) {
+        for (_, _, err_span) in self.errs.iter_mut() {
+            *err_span = err_span.or(span);
+        }
     }
 
     /// Move errors from the other into this one, leaving the other empty
@@ -40,6 +61,11 @@ impl TypeErrors {
     pub fn is_ok(&self) -> bool {
         self.errs.is_empty()
     }
+
+    /// The code of each error, in the order they were found.
+    pub fn codes(&self) -> Vec<Code> {
+        self.errs.iter().map(|(code, _, _)| *code).collect()
+    }
 }
 
 impl Display for TypeErrors {
@@ -47,7 +73,10 @@ impl Display for TypeErrors {
         let string = self
             .errs
             .iter()
-            .map(|x| format!("[TypeError]: {}", x))
+            .map(|(code, err, span)| match span {
+                Some(span) => format!("[TypeError {}]: {} at {}", code, err, span),
+                None => format!("[TypeError {}]: {}", code, err),
+            })
             .collect::<Vec<String>>()
             .join("\n");
         write!(f, "{}", string)
@@ -87,12 +116,30 @@ impl CheckResult {
     }
 }
 
+/// What the returns in the function being checked have to match.
+pub(crate) enum FnRet {
+    /// The return type annotated on a fn or lambda
+    Annotated(Type),
+    /// A fn without `-> T`: the types of its returns so far, to infer the return type from
+    Inferred(Vec<Type>),
+    /// A lambda without `-> T`, where a return can't be checked
+    Unannotated,
+}
+
 /// Struct to enable type checking by encapsulating type environment.
 pub struct TypeChecker<'prog> {
     program: &'prog BlockSeq,
     pub(crate) envs: Vec<Env>,
     // stores type of function currently being checked at top (empty if not checking function)
-    pub(crate) fn_type_stack: Vec<Type>,
+    pub(crate) fn_type_stack: Vec<FnRet>,
+    // fns without `-> T` called before their ret type was inferred, which assumed they return ()
+    pub(crate) called_before_inferred: HashSet<String>,
+    // names each fn declared so far assigns to without declaring them, for spawn isolate to check
+    pub(crate) captured_writes: HashMap<String, Vec<String>>,
+    // fields of the structs declared at the top level, by name
+    pub(crate) structs: HashMap<String, Vec<(String, Type)>>,
+    // variants of the enums declared at the top level with the types of the values they hold, by enum name
+    pub(crate) enums: HashMap<String, Vec<(String, Vec<Type>)>>,
 }
 
 impl<'prog> TypeChecker<'prog> {
@@ -101,6 +148,10 @@ impl<'prog> TypeChecker<'prog> {
             program,
             envs: vec![],
             fn_type_stack: vec![],
+            called_before_inferred: HashSet::new(),
+            captured_writes: HashMap::new(),
+            structs: builtin_structs(),
+            enums: HashMap::new(),
         }
     }
 
@@ -110,6 +161,24 @@ impl<'prog> TypeChecker<'prog> {
             return Ok(Type::BuiltInFn);
         }
 
+        if ident == NONE {
+            return Ok(Type::Option(Box::new(Type::Infer)));
+        }
+
+        if let Some((enum_name, payload)) = self.enum_variant(ident) {
+            if !payload.is_empty() {
+                let e = message!(
+                    T004,
+                    "Variant '{}' of {} holds values, make one with {}(..)",
+                    ident,
+                    enum_name,
+                    ident
+                );
+                return Err(TypeErrors::new_err(e));
+            }
+            return Ok(Type::Struct(enum_name.to_owned()));
+        }
+
         for env in self.envs.iter().rev() {
             let ty = env.get(ident);
             if let Some(ty) = ty {
@@ -117,8 +186,8 @@ impl<'prog> TypeChecker<'prog> {
             }
         }
 
-        let e = format!("Identifier '{}' not declared", ident);
-        Err(TypeErrors::new_err(&e))
+        let e = message!(T001, "Identifier '{}' not declared", ident);
+        Err(TypeErrors::new_err(e))
     }
 
     /// Returns type of identifier if initialised. If identifier doesn't exist or still uninit, returns Error.
@@ -126,8 +195,8 @@ impl<'prog> TypeChecker<'prog> {
     pub(crate) fn get_type_if_init(&self, ident: &str) -> Result<Type, TypeErrors> {
         let ty = self.get_type(ident)?;
         if ty.eq(&Type::Unitialised) {
-            let e = format!("Identifier '{}' assigned before declaration", ident);
-            Err(TypeErrors::new_err(&e))
+            let e = message!(T001, "Identifier '{}' assigned before declaration", ident);
+            Err(TypeErrors::new_err(e))
         } else {
             Ok(ty)
         }
@@ -156,8 +225,8 @@ impl<'prog> TypeChecker<'prog> {
                         env.insert(param.name.clone(), ty.to_owned());
                     }
                     None => {
-                        let e = format!("Parameter '{}' has no type annotation", param.name);
-                        ty_errs.add(&e);
+                        let e = message!(T006, "Parameter '{}' has no type annotation", param.name);
+                        ty_errs.add(e);
                     }
                 };
             }
@@ -190,8 +259,8 @@ impl<'prog> TypeChecker<'prog> {
                         Ok(res)
                     }
                     _ => {
-                        let e = format!("Can't negate type {}", check_res.ty);
-                        Err(TypeErrors::new_err(&e))
+                        let e = message!(T003, "Can't negate type {}", check_res.ty);
+                        Err(TypeErrors::new_err(e))
                     }
                 }
             }
@@ -208,8 +277,8 @@ impl<'prog> TypeChecker<'prog> {
                         Ok(res)
                     }
                     _ => {
-                        let e = format!("Can't apply logical NOT to type {}", check_res.ty);
-                        Err(TypeErrors::new_err(&e))
+                        let e = message!(T003, "Can't apply logical NOT to type {}", check_res.ty);
+                        Err(TypeErrors::new_err(e))
                     }
                 }
             }
@@ -244,11 +313,14 @@ impl<'prog> TypeChecker<'prog> {
                         Ok(res)
                     }
                     _ => {
-                        let e = format!(
+                        let e = message!(
+                            T003,
                             "Can't apply '{}' to types '{}' and '{}'",
-                            op, left_ty.ty, right_ty.ty
+                            op,
+                            left_ty.ty,
+                            right_ty.ty
                         );
-                        Err(TypeErrors::new_err(&e))
+                        Err(TypeErrors::new_err(e))
                     }
                 }
             }
@@ -283,22 +355,39 @@ impl<'prog> TypeChecker<'prog> {
         let l_type = l_type?;
         let r_type = r_type?;
 
-        let err = format!(
+        let err = message!(
+            T003,
             "Can't apply '{}' to types '{}' and '{}'",
-            op, l_type.ty, r_type.ty
+            op,
+            l_type.ty,
+            r_type.ty
         );
 
-        let err: Result<_, TypeErrors> = Err(TypeErrors::new_err(&err));
+        let err: Result<_, TypeErrors> = Err(TypeErrors::new_err(err));
 
         match op {
             BinOpType::Add | BinOpType::Sub | BinOpType::Div | BinOpType::Mul => {
                 TypeChecker::check_math_ops(op, &l_type, &r_type)
             }
-            // (num, num) => bool
+            // (int, int) => int
+            BinOpType::Mod => {
+                if matches!((&l_type.ty, &r_type.ty), (Type::Int, Type::Int)) {
+                    let res = CheckResult {
+                        ty: Type::Int,
+                        must_break: l_type.must_break || r_type.must_break,
+                        must_return: l_type.must_return || r_type.must_return,
+                    };
+
+                    Ok(res)
+                } else {
+                    err
+                }
+            }
+            // (num, num) => bool or (char, char) => bool
             BinOpType::Gt | BinOpType::Lt => {
                 if matches!(
                     (l_type.ty, r_type.ty),
-                    (Type::Int, Type::Int) | (Type::Float, Type::Float)
+                    (Type::Int, Type::Int) | (Type::Float, Type::Float) | (Type::Char, Type::Char)
                 ) {
                     // Ok(Type::Bool)
                     let res = CheckResult {
@@ -369,6 +458,11 @@ impl<'prog> TypeChecker<'prog> {
                 must_break: false,
                 must_return: false,
             },
+            Expr::Char(_) => CheckResult {
+                ty: Type::Char,
+                must_break: false,
+                must_return: false,
+            },
             Expr::Symbol(ident) => {
                 // self.ty_env.borrow().get(ident)?
                 let sym_ty = self.get_type(ident)?;
@@ -388,8 +482,11 @@ impl<'prog> TypeChecker<'prog> {
             Expr::BlockExpr(blk) => return self.check_block(blk, vec![]),
             Expr::IfElseExpr(if_else) => return self.check_if_else(if_else),
             Expr::FnCallExpr(fn_call) => return self.check_fn_call(fn_call),
-            Expr::SpawnExpr(fn_call) => {
-                self.check_fn_call(fn_call)?;
+            Expr::SpawnExpr(data) => {
+                self.check_fn_call(&data.call)?;
+                if data.isolate {
+                    self.check_isolate(&data.call)?;
+                }
                 CheckResult {
                     ty: Type::ThreadId,
                     must_break: false,
@@ -403,6 +500,25 @@ impl<'prog> TypeChecker<'prog> {
                 must_break: false,
                 must_return: false,
             },
+            Expr::TryJoinExpr(data) => return self.check_try_join(data),
+            Expr::ArrayExpr(elems) => return self.check_array(elems),
+            Expr::ArrayFillExpr(val, len) => return self.check_array_fill(val, *len),
+            Expr::IndexExpr(arr, index) => return self.check_index(arr, index),
+            Expr::TupleExpr(elems) => return self.check_tuple(elems),
+            Expr::TupleIndexExpr(tuple, idx) => return self.check_tuple_index(tuple, *idx),
+            Expr::MapExpr(entries) => return self.check_map(entries),
+            Expr::SliceExpr(slice) => return self.check_slice(slice),
+            Expr::StructExpr(data) => return self.check_struct_expr(data),
+            Expr::FieldAccessExpr(obj, field) => return self.check_field_access(obj, field),
+            Expr::MatchExpr(data) => return self.check_match(data),
+            Expr::CoalesceExpr(opt, default) => return self.check_coalesce(opt, default),
+            Expr::LockExpr(data) => return self.check_lock(data),
+            Expr::LambdaExpr(data) => return self.check_lambda(data),
+            // parser expands macros before returning the program
+            Expr::MacroCallExpr(call) => {
+                let e = message!(T013, "Macro '{}!' was not expanded", call.name);
+                return Err(TypeErrors::new_err(e));
+            }
         };
 
         if local_errs.is_ok() {
@@ -416,7 +532,11 @@ impl<'prog> TypeChecker<'prog> {
     pub(crate) fn check_decl(&mut self, decl: &Decl) -> Result<CheckResult, TypeErrors> {
         // dbg!("Type checking decl:", decl);
         match decl {
-            Decl::LetStmt(stmt) => self.check_let(stmt),
+            Decl::LetStmt(stmt) => {
+                let attrs = self.check_attrs(AttrTarget::Let(stmt));
+                TypeChecker::with_attr_errs(attrs, self.check_let(stmt))
+            }
+            Decl::LetTupleStmt(stmt) => self.check_let_tuple(stmt),
             // Type check the expr and return any errors
             Decl::ExprStmt(expr) => self.check_expr(expr),
             // Check if sym is declared already. Then check expr matches type at decl
@@ -425,11 +545,14 @@ impl<'prog> TypeChecker<'prog> {
                 let exp_ty = self.check_expr(&stmt.expr)?;
 
                 if !sym_ty.eq(&exp_ty.ty) {
-                    let e = format!(
+                    let e = message!(
+                        T002,
                         "'{}' declared with type {} but assigned type {}",
-                        stmt.ident, sym_ty, exp_ty.ty
+                        stmt.ident,
+                        sym_ty,
+                        exp_ty.ty
                     );
-                    return Err(TypeErrors::new_err(&e));
+                    return Err(TypeErrors::new_err(e));
                 }
 
                 let res = CheckResult {
@@ -440,8 +563,17 @@ impl<'prog> TypeChecker<'prog> {
 
                 Ok(res)
             }
+            Decl::IndexAssignStmt(stmt) => self.check_index_assign(stmt),
+            Decl::FieldAssignStmt(stmt) => self.check_field_assign(stmt),
+            Decl::StructDeclStmt(data) => self.check_struct_decl(data),
+            Decl::EnumDeclStmt(data) => self.check_enum_decl(data),
+            Decl::MacroDeclStmt(data) => {
+                let e = message!(T013, "Macro '{}' was not expanded", data.name);
+                Err(TypeErrors::new_err(e))
+            }
             Decl::IfOnlyStmt(if_else) => self.check_if_else(if_else),
             Decl::LoopStmt(lp) => self.check_loop(lp),
+            Decl::ForStmt(for_data) => self.check_for(for_data),
             Decl::BreakStmt => {
                 // must_break base case
                 Ok(CheckResult {
@@ -450,7 +582,10 @@ impl<'prog> TypeChecker<'prog> {
                     must_return: false,
                 })
             }
-            Decl::FnDeclStmt(fn_decl) => self.check_fn_decl(fn_decl),
+            Decl::FnDeclStmt(fn_decl) => {
+                let attrs = self.check_attrs(AttrTarget::Fn(fn_decl));
+                TypeChecker::with_attr_errs(attrs, self.check_fn_decl(fn_decl))
+            }
             // TODO: check nested returns with fn stack
             Decl::ReturnStmt(ret_expr) => {
                 // dbg!("fn_stack at return:", &self.fn_type_stack);
@@ -470,16 +605,30 @@ impl<'prog> TypeChecker<'prog> {
                 // return type must match fn annotated
 
                 // expect because parser rejects return outside function
-                let fn_ty = self
+                let fn_ret = self
                     .fn_type_stack
-                    .last()
+                    .last_mut()
                     .expect("Should have type in fn_stack");
-                if !res.ty.eq(fn_ty) {
-                    let e = format!(
-                        "Expected function return type '{}' but return statement has type '{}'",
-                        fn_ty, res.ty
-                    );
-                    return Err(TypeErrors::new_err(&e));
+                match fn_ret {
+                    FnRet::Annotated(fn_ty) if !res.ty.eq(fn_ty) => {
+                        let e = message!(
+                            T002,
+                            "Expected function return type '{}' but return statement has type '{}'",
+                            fn_ty,
+                            res.ty
+                        );
+                        return Err(TypeErrors::new_err(e));
+                    }
+                    FnRet::Annotated(_) => (),
+                    // checked against the other returns once the whole fn has been seen
+                    FnRet::Inferred(ret_types) => ret_types.push(res.ty.clone()),
+                    FnRet::Unannotated => {
+                        let e = message!(
+                            T006,
+                            "Lambda with a return statement needs a return type annotation e.g |x: int| -> int { ... }"
+                        );
+                        return Err(TypeErrors::new_err(e));
+                    }
                 }
 
                 Ok(res)
@@ -505,6 +654,8 @@ impl<'prog> TypeChecker<'prog> {
     }
 
     pub fn type_check(mut self) -> Result<Type, TypeErrors> {
+        self.register_structs(self.program)?;
+        self.register_enums(self.program)?;
         let ty = self.check_block(self.program, vec![])?;
         // dbg!(&ty);
         Ok(ty.ty)
@@ -553,8 +704,10 @@ pub fn expect_err(inp: &str, exp_err: &str, contains: bool) {
 
 #[cfg(test)]
 mod tests {
-    use super::{expect_err, expect_pass};
+    use super::{expect_err, expect_pass, TypeChecker};
+    use diagnostics::Code;
     use parser::structs::Type;
+    use parser::Parser;
 
     #[test]
     fn test_type_check_basic() {
@@ -586,7 +739,7 @@ mod tests {
 
         // Multiple errors: collects them
         expect_err("let x : float = 20; let x : int = true; let x : float = 20;",
-         "[TypeError]: 'x' has declared type float but assigned type int\n[TypeError]: 'x' has declared type int but assigned type bool\n[TypeError]: 'x' has declared type float but assigned type int", false);
+         "[TypeError T002]: 'x' has declared type float but assigned type int at line 1, column 1\n[TypeError T002]: 'x' has declared type int but assigned type bool at line 1, column 21\n[TypeError T002]: 'x' has declared type float but assigned type int at line 1, column 41", false);
     }
 
     #[test]
@@ -635,6 +788,18 @@ mod tests {
             true,
         );
         expect_err("let x : bool = true +2;", "apply", true);
+
+        expect_pass("let x : int = 7; x % 3", Type::Int);
+        expect_err(
+            "7.0 % 2.0",
+            "Can't apply '%' to types 'float' and 'float'",
+            true,
+        );
+        expect_err(
+            "7 % true",
+            "Can't apply '%' to types 'int' and 'bool'",
+            true,
+        );
     }
 
     #[test]
@@ -643,16 +808,51 @@ mod tests {
         let t = "x+y";
         expect_err(
             t,
-            "[TypeError]: Identifier 'x' not declared\n[TypeError]: Identifier 'y' not declared",
+            "[TypeError T001]: Identifier 'x' not declared at line 1, column 1\n[TypeError T001]: Identifier 'y' not declared at line 1, column 1",
             true,
         );
 
         // blks - can't get types from the blks since they have errs but those are collected
         let t = "{ 2+false; 3} - {3+3.5; true}";
-        expect_err(t,  "[TypeError]: Can't apply '+' to types 'int' and 'bool'\n[TypeError]: Can't apply '+' to types 'int' and 'float'", true);
+        expect_err(t,  "[TypeError T003]: Can't apply '+' to types 'int' and 'bool' at line 1, column 3\n[TypeError T003]: Can't apply '+' to types 'int' and 'float' at line 1, column 18", true);
 
         let t = "x+y+z";
-        expect_err(t, "[TypeError]: Identifier 'x' not declared\n[TypeError]: Identifier 'y' not declared\n[TypeError]: Identifier 'z' not declared", false);
+        expect_err(t, "[TypeError T001]: Identifier 'x' not declared at line 1, column 1\n[TypeError T001]: Identifier 'y' not declared at line 1, column 1\n[TypeError T001]: Identifier 'z' not declared at line 1, column 1", false);
+    }
+
+    #[test]
+    fn test_type_err_codes() {
+        let codes = |inp: &str| {
+            let prog = Parser::new_from_string(inp).parse().expect("Should parse");
+            TypeChecker::new(&prog)
+                .type_check()
+                .expect_err("Should err")
+                .codes()
+        };
+
+        assert_eq!(codes("let x: int = true;"), vec![Code::T002]);
+        assert_eq!(codes("x + y"), vec![Code::T001, Code::T001]);
+        assert_eq!(codes("2 + false"), vec![Code::T003]);
+        assert_eq!(codes("fn f(x: int) {} f(1, 2)"), vec![Code::T004]);
+        assert_eq!(codes("let x: integer = 2;"), vec![Code::T005]);
+        assert_eq!(codes("let m = 5; lock m {}"), vec![Code::T012]);
+    }
+
+    #[test]
+    fn test_type_err_spans() {
+        // each error is at the innermost declaration it is in
+        let t = "let a = 1;\nfn f() {\n  let b = a + true;\n}\nstruct P { x: int }\nstruct P { y: int }\na + false";
+        expect_err(
+            t,
+            "[TypeError T007]: Struct 'P' is already declared at line 6, column 1",
+            false,
+        );
+        let t = "let a = 1;\nfn f() {\n  let b = a + true;\n}\nlet c: bool = a;";
+        expect_err(
+            t,
+            "[TypeError T003]: Can't apply '+' to types 'int' and 'bool' at line 3, column 3\n[TypeError T002]: 'c' has declared type bool but assigned type int at line 5, column 1",
+            false,
+        );
     }
 
     #[test]
@@ -718,7 +918,7 @@ mod tests {
         expect_pass("false == (3 > 5)", Type::Bool);
         expect_err(
             "(5 == 3) < 5",
-            "[TypeError]: Can't apply '<' to types 'bool' and 'int'",
+            "[TypeError T003]: Can't apply '<' to types 'bool' and 'int' at line 1, column 1",
             false,
         );
     }
@@ -0,0 +1,80 @@
+//! Benchmarks for the mark and sweep garbage collector. The sweep and mark benchmarks time a single
+//! collection of a runtime prepared outside of the measurement; `gc_program` times a whole run of a
+//! program that collects every millisecond.
+//!
+//! Baseline numbers live in `benches/gc_baseline.json`, in the same format as `benches/baseline.json`.
+//! Run with `cargo bench -p ignite --bench gc`.
+
+use std::time::Duration;
+
+use compiler::compiler::compile_from_string;
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use ignite::{micro_code, run, Runtime};
+
+/// Every iteration of the loop enters a block scope that is garbage as soon as it exits.
+fn short_lived_scopes(iterations: usize) -> String {
+    format!(
+        r#"
+        let n = 0;
+        loop n < {} {{
+            let x = n;
+            n = x + 1;
+        }}
+        "#,
+        iterations
+    )
+}
+
+fn bench_sweep_garbage(c: &mut Criterion) {
+    let program = compile_from_string(&short_lived_scopes(100_000), true).unwrap();
+    c.bench_function("gc_sweep_100k_garbage", |b| {
+        b.iter_batched(
+            || {
+                // never collect while running, so all of the scopes are left for the measured collection
+                let mut rt = Runtime::new(program.clone());
+                rt.set_gc_interval(Duration::MAX);
+                rt.set_gc_threshold(None);
+                run(rt).unwrap()
+            },
+            |rt| rt.mark_and_weep(),
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+fn bench_mark_live(c: &mut Criterion) {
+    c.bench_function("gc_mark_10k_live", |b| {
+        b.iter_batched(
+            || {
+                let mut rt = Runtime::new(vec![]);
+                for _ in 0..10_000 {
+                    rt = micro_code::enter_scope(rt, vec!["x".into()]).unwrap();
+                }
+                rt
+            },
+            |rt| rt.mark_and_weep(),
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+fn bench_program(c: &mut Criterion) {
+    let program = compile_from_string(&short_lived_scopes(1_000_000), true).unwrap();
+    let mut group = c.benchmark_group("gc_program");
+    group.sample_size(10);
+    group.bench_function("1m_scopes_gc_every_ms", |b| {
+        b.iter_batched(
+            || {
+                let mut rt = Runtime::new(program.clone());
+                rt.set_gc_interval(Duration::from_millis(1));
+                rt
+            },
+            |rt| run(rt).unwrap(),
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_sweep_garbage, bench_mark_live, bench_program);
+criterion_main!(benches);
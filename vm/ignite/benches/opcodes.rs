@@ -0,0 +1,247 @@
+//! Micro-benchmarks for the hottest opcodes. Each benchmark times a single micro_code call on a
+//! runtime prepared outside of the measurement, except `read_loop` and `recursion`, which run whole
+//! programs that mostly load variables, and `spawn_program`, which spawns and joins threads from deep in
+//! a recursion.
+//!
+//! Baseline numbers live in `benches/baseline.json`. To compare against them, run
+//! `cargo bench -p ignite --bench opcodes` and diff the reported medians with the file; update the
+//! file in the same change as anything that intentionally moves them.
+
+use bytecode::{BinOp, ByteCode, Value};
+use compiler::compiler::compile_from_string;
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use ignite::{micro_code, run, Runtime};
+
+/// A runtime inside a block scope where `x` is bound to an int and `f` to a one-parameter function.
+fn scoped_runtime() -> Runtime {
+    let rt = Runtime::new(vec![]);
+    let rt = micro_code::enter_scope(rt, vec!["x".into(), "f".into()]).unwrap();
+    let rt = micro_code::ldc(rt, Value::Int(1)).unwrap();
+    let rt = micro_code::assign(rt, "x".into()).unwrap();
+    let rt = micro_code::ldf(rt, 0, vec!["n".into()]).unwrap();
+    micro_code::assign(rt, "f".into()).unwrap()
+}
+
+fn bench_ldc(c: &mut Criterion) {
+    c.bench_function("ldc", |b| {
+        b.iter_batched(
+            scoped_runtime,
+            |rt| micro_code::ldc(rt, black_box(Value::Int(42))).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+fn bench_ld(c: &mut Criterion) {
+    c.bench_function("ld", |b| {
+        b.iter_batched(
+            scoped_runtime,
+            |rt| micro_code::ld(rt, black_box("x".into())).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+fn bench_ld_string(c: &mut Criterion) {
+    c.bench_function("ld_string", |b| {
+        b.iter_batched(
+            || {
+                let rt = micro_code::enter_scope(scoped_runtime(), vec!["s".into()]).unwrap();
+                let rt = micro_code::ldc(rt, Value::String("x".repeat(1024).into())).unwrap();
+                micro_code::assign(rt, "s".into()).unwrap()
+            },
+            |rt| micro_code::ld(rt, black_box("s".into())).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+fn bench_ld_closure(c: &mut Criterion) {
+    c.bench_function("ld_closure", |b| {
+        b.iter_batched(
+            scoped_runtime,
+            |rt| micro_code::ld(rt, black_box("f".into())).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+/// Reads a string and a function from an enclosing scope on every iteration.
+fn read_loop_program() -> Vec<ByteCode> {
+    let src = format!(
+        r#"
+        let s = "{}";
+        let f = |x: int| x;
+        let n = 0;
+        loop n < 1000 {{
+            let t = s;
+            let g = f;
+            n = n + 1;
+        }}
+        "#,
+        "x".repeat(256)
+    );
+    compile_from_string(&src, true).unwrap()
+}
+
+fn bench_read_loop(c: &mut Criterion) {
+    let program = read_loop_program();
+    c.bench_function("read_loop", |b| {
+        b.iter_batched(
+            || Runtime::new(program.clone()),
+            |rt| run(rt).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+/// Recursion that calls builtins from a few scopes deep in every call.
+fn recursion_program() -> Vec<ByteCode> {
+    let src = r#"
+        fn fib(n: int) -> int {
+            if n < 2 {
+                n
+            } else {
+                let a = max(n - 1, 0);
+                {
+                    let b = min(n - 2, a);
+                    {
+                        let c = abs(b);
+                        fib(a) + fib(c)
+                    }
+                }
+            }
+        }
+        fib(15);
+        "#;
+    compile_from_string(src, true).unwrap()
+}
+
+fn bench_recursion(c: &mut Criterion) {
+    let program = recursion_program();
+    c.bench_function("recursion", |b| {
+        b.iter_batched(
+            || Runtime::new(program.clone()),
+            |rt| run(rt).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+fn bench_assign(c: &mut Criterion) {
+    c.bench_function("assign", |b| {
+        b.iter_batched(
+            || micro_code::ldc(scoped_runtime(), Value::Int(2)).unwrap(),
+            |rt| micro_code::assign(rt, black_box("x".into())).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+fn bench_binop(c: &mut Criterion) {
+    c.bench_function("binop", |b| {
+        b.iter_batched(
+            || {
+                let rt = micro_code::ldc(scoped_runtime(), Value::Int(20)).unwrap();
+                micro_code::ldc(rt, Value::Int(22)).unwrap()
+            },
+            |rt| micro_code::binop(rt, black_box(BinOp::Add)).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+fn bench_call(c: &mut Criterion) {
+    c.bench_function("call", |b| {
+        b.iter_batched(
+            || {
+                let rt = micro_code::ld(scoped_runtime(), "f".into()).unwrap();
+                micro_code::ldc(rt, Value::Int(1)).unwrap()
+            },
+            |rt| micro_code::call(rt, black_box(1)).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+fn bench_enterscope(c: &mut Criterion) {
+    c.bench_function("enterscope", |b| {
+        b.iter_batched(
+            scoped_runtime,
+            |rt| micro_code::enter_scope(rt, black_box(vec!["a".into(), "b".into()])).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+/// A runtime 1000 calls deep with 1000 values on the operand stack, to show spawning doesn't copy either.
+fn deep_runtime() -> Runtime {
+    let mut rt = scoped_runtime();
+    for i in 0..1000 {
+        rt = micro_code::ldc(rt, Value::Int(i)).unwrap();
+        rt = micro_code::ld(rt, "f".into()).unwrap();
+        rt = micro_code::ldc(rt, Value::Int(i)).unwrap();
+        rt = micro_code::call(rt, 1).unwrap();
+    }
+    rt
+}
+
+fn bench_spawn(c: &mut Criterion) {
+    c.bench_function("spawn", |b| {
+        b.iter_batched(
+            deep_runtime,
+            |rt| micro_code::spawn(rt, black_box(0)).unwrap(),
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+/// Spawns and joins 100 threads from 200 calls deep.
+fn spawn_program() -> Vec<ByteCode> {
+    let src = r#"
+        let done = 0;
+        fn work(x: int) {
+            done = done + x;
+        }
+        fn down(n: int) {
+            if n == 0 {
+                for i in 0..100 {
+                    let t = spawn work(1);
+                    join t;
+                }
+            } else {
+                down(n - 1);
+            }
+        }
+        down(200);
+        "#;
+    compile_from_string(src, true).unwrap()
+}
+
+fn bench_spawn_program(c: &mut Criterion) {
+    let program = spawn_program();
+    c.bench_function("spawn_program", |b| {
+        b.iter_batched(
+            || Runtime::new(program.clone()),
+            |rt| run(rt).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+criterion_group!(
+    benches,
+    bench_ldc,
+    bench_ld,
+    bench_ld_string,
+    bench_ld_closure,
+    bench_assign,
+    bench_binop,
+    bench_call,
+    bench_enterscope,
+    bench_read_loop,
+    bench_recursion,
+    bench_spawn,
+    bench_spawn_program
+);
+criterion_main!(benches);
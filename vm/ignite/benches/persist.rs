@@ -0,0 +1,101 @@
+//! Benchmarks for the persistent vector and map against the mutable collections they stand in for. Each group
+//! times the persistent structure next to a `Vec` or `BTreeMap` doing the same work, and the `keep_old` groups
+//! time making a changed version while keeping the old one, which a mutable collection can only do by copying.
+//!
+//! Run with `cargo bench -p ignite --bench persist`.
+
+use std::collections::BTreeMap;
+
+use bytecode::{PMap, PVec, Value};
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+
+const N: i64 = 10_000;
+
+fn bench_push(c: &mut Criterion) {
+    let mut group = c.benchmark_group("push_10k");
+    group.bench_function("pvec", |b| {
+        b.iter(|| (0..N).fold(PVec::new(), |v, i| v.push(Value::Int(i))))
+    });
+    group.bench_function("vec", |b| {
+        b.iter(|| {
+            let mut v = vec![];
+            for i in 0..N {
+                v.push(Value::Int(i));
+            }
+            v
+        })
+    });
+    group.finish();
+}
+
+fn bench_set_keep_old(c: &mut Criterion) {
+    let pvec: PVec = (0..N).map(Value::Int).collect();
+    let vec: Vec<Value> = (0..N).map(Value::Int).collect();
+
+    let mut group = c.benchmark_group("set_keep_old_10k");
+    group.bench_function("pvec", |b| {
+        b.iter(|| pvec.set(black_box(N / 2), Value::Unit).unwrap())
+    });
+    group.bench_function("vec_clone", |b| {
+        b.iter(|| {
+            let mut copy = vec.clone();
+            copy[black_box(N / 2) as usize] = Value::Unit;
+            copy
+        })
+    });
+    group.finish();
+}
+
+fn bench_insert(c: &mut Criterion) {
+    let mut group = c.benchmark_group("insert_10k");
+    group.bench_function("pmap", |b| {
+        b.iter(|| {
+            (0..N).fold(PMap::new(), |m, i| {
+                m.insert(Value::Int(i), Value::Int(i)).unwrap()
+            })
+        })
+    });
+    group.bench_function("btreemap", |b| {
+        b.iter(|| {
+            let mut m = BTreeMap::new();
+            for i in 0..N {
+                m.insert(i, Value::Int(i));
+            }
+            m
+        })
+    });
+    group.finish();
+}
+
+fn bench_insert_keep_old(c: &mut Criterion) {
+    let pmap = (0..N).fold(PMap::new(), |m, i| {
+        m.insert(Value::Int(i), Value::Int(i)).unwrap()
+    });
+    let btreemap: BTreeMap<i64, Value> = (0..N).map(|i| (i, Value::Int(i))).collect();
+
+    let mut group = c.benchmark_group("insert_keep_old_10k");
+    group.bench_function("pmap", |b| {
+        b.iter_batched(
+            || pmap.clone(),
+            |m| m.insert(Value::Int(black_box(N)), Value::Unit).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("btreemap_clone", |b| {
+        b.iter(|| {
+            let mut copy = btreemap.clone();
+            copy.insert(black_box(N), Value::Unit);
+            copy
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_push,
+    bench_set_keep_old,
+    bench_insert,
+    bench_insert_keep_old
+);
+criterion_main!(benches);
@@ -0,0 +1,195 @@
+use std::{cell::RefCell, fmt::Display, rc::Rc};
+
+use bytecode::{CallInfo, LineTable, ThreadID};
+
+use crate::Runtime;
+
+// A backtrace longer than this shows only its innermost and outermost frames, so unbounded recursion doesn't
+// print a frame for every call
+const MAX_SHOWN_FRAMES: usize = 20;
+
+/// The calls a thread was in when it stopped with an error, innermost first, like the backtrace of a Rust panic.
+/// Errors from executing an instruction carry one as context, see [`error_backtrace`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Backtrace {
+    pub thread_id: ThreadID,
+    pub frames: Vec<BacktraceFrame>,
+}
+
+/// A function the thread was in, and the instruction it was at in it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BacktraceFrame {
+    /// The pc of the first instruction of the function, None for the top level of the thread.
+    pub addr: Option<usize>,
+    /// The pc of the instruction: where the error happened in the innermost frame, the CALL in the others.
+    pub pc: usize,
+}
+
+impl Backtrace {
+    /// The backtrace of a thread stopped at pc, in the call given and the calls it was made in.
+    pub fn new(thread_id: ThreadID, pc: usize, call: Option<Rc<CallInfo>>) -> Backtrace {
+        let mut frames = vec![];
+        let mut pc = pc;
+        let mut call = call;
+
+        while let Some(info) = call {
+            frames.push(BacktraceFrame {
+                addr: Some(info.addr),
+                pc,
+            });
+            pc = info.call_pc;
+            call = info.caller.clone();
+        }
+        frames.push(BacktraceFrame { addr: None, pc });
+
+        Backtrace { thread_id, frames }
+    }
+
+    /// Show the backtrace, with the source line of each instruction if there are lines.
+    pub fn format(&self, lines: Option<&LineTable>) -> String {
+        let place = |pc: usize| match lines.and_then(|lines| lines.line(pc)) {
+            Some(line) => format!("line {}, pc {}", line, pc),
+            None => format!("pc {}", pc),
+        };
+
+        let mut out = format!("stack backtrace of thread {}:", self.thread_id);
+        let len = self.frames.len();
+        for (i, frame) in self.frames.iter().enumerate() {
+            if len > MAX_SHOWN_FRAMES && i == MAX_SHOWN_FRAMES / 2 {
+                let omitted = len - MAX_SHOWN_FRAMES;
+                out.push_str(&format!("\n      ... {} frames omitted ...", omitted));
+            }
+            if len > MAX_SHOWN_FRAMES
+                && (MAX_SHOWN_FRAMES / 2..len - MAX_SHOWN_FRAMES / 2).contains(&i)
+            {
+                continue;
+            }
+
+            let name = match frame.addr {
+                Some(addr) => format!("fn at {}", place(addr)),
+                None => "<top level>".to_string(),
+            };
+            out.push_str(&format!(
+                "\n{:>4}: {}\n             at {}",
+                i,
+                name,
+                place(frame.pc)
+            ));
+        }
+
+        out
+    }
+}
+
+impl Display for Backtrace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.format(None))
+    }
+}
+
+thread_local! {
+    // The thread the last runtime dropped on this OS thread was executing, and the innermost call it was in
+    static DROPPED_CALL: RefCell<Option<(ThreadID, Option<Rc<CallInfo>>)>> = const { RefCell::new(None) };
+}
+
+/// An instruction that fails drops the runtime it was given, and the calls of the thread with it. So the runtime
+/// keeps the innermost call of its current thread when it is dropped, for [`with_backtrace`] to build the backtrace
+/// from, instead of every instruction looking up its call in case it fails.
+impl Drop for Runtime {
+    fn drop(&mut self) {
+        let call = self.current_thread.call();
+        DROPPED_CALL.with(|dropped| {
+            dropped.replace(Some((self.current_thread.thread_id, call)));
+        });
+    }
+}
+
+/// Add the backtrace of the thread to an error from executing the instruction at pc, unless it has one from a call
+/// nested in the instruction already. The calls are those of the runtime the instruction dropped, if it was still
+/// executing the thread.
+pub(crate) fn with_backtrace(err: anyhow::Error, thread_id: ThreadID, pc: usize) -> anyhow::Error {
+    let dropped = DROPPED_CALL.with(|dropped| dropped.take());
+    if error_backtrace(&err).is_some() {
+        return err;
+    }
+
+    let call = match dropped {
+        Some((id, call)) if id == thread_id => call,
+        _ => None,
+    };
+    err.context(Backtrace::new(thread_id, pc, call))
+}
+
+/// The backtrace of the thread that stopped with the error, if it stopped executing an instruction.
+pub fn error_backtrace(err: &anyhow::Error) -> Option<&Backtrace> {
+    err.downcast_ref::<Backtrace>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backtrace() {
+        let outer = Rc::new(CallInfo {
+            addr: 2,
+            call_pc: 10,
+            caller: None,
+        });
+        let inner = Rc::new(CallInfo {
+            addr: 5,
+            call_pc: 3,
+            caller: Some(outer),
+        });
+        let bt = Backtrace::new(1, 7, Some(inner));
+        assert_eq!(
+            bt.frames,
+            vec![
+                BacktraceFrame {
+                    addr: Some(5),
+                    pc: 7
+                },
+                BacktraceFrame {
+                    addr: Some(2),
+                    pc: 3
+                },
+                BacktraceFrame { addr: None, pc: 10 },
+            ]
+        );
+
+        let mut lines = LineTable::new();
+        lines.push(0, 1);
+        lines.push(5, 2);
+        lines.push(10, 3);
+        assert_eq!(
+            bt.format(Some(&lines)),
+            "stack backtrace of thread 1:
+   0: fn at line 2, pc 5
+             at line 2, pc 7
+   1: fn at line 1, pc 2
+             at line 1, pc 3
+   2: <top level>
+             at line 3, pc 10"
+        );
+    }
+
+    #[test]
+    fn test_backtrace_omits_frames() {
+        let mut call = None;
+        for pc in 0..100 {
+            call = Some(Rc::new(CallInfo {
+                addr: 0,
+                call_pc: pc,
+                caller: call,
+            }));
+        }
+        let bt = Backtrace::new(1, 0, call);
+        assert_eq!(bt.frames.len(), 101);
+
+        let shown = bt.format(None);
+        assert!(shown.contains("   9: fn at pc 0"));
+        assert!(shown.contains("... 81 frames omitted ..."));
+        assert!(!shown.contains("  10: "));
+        assert!(shown.contains(" 100: <top level>"));
+    }
+}
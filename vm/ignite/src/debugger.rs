@@ -0,0 +1,355 @@
+use std::{
+    collections::BTreeSet,
+    io::{BufRead, Write},
+    path::Path,
+    time::Instant,
+};
+
+use anyhow::Result;
+use bytecode::{fmt_instr, LineTable};
+
+use ignite::{Runtime, VmError};
+
+const HELP: &str = "\
+Commands:
+  s, step          execute the next instruction, also an empty line
+  c, continue      run until the next breakpoint
+  b, break <pc>    stop before the instruction at pc, or the code on a line with <file>:<line>
+  d, delete <pc>   remove the breakpoint at pc, or on a line with <file>:<line>
+  w, watch <sym>   stop after a value is assigned to a name
+  unwatch <sym>    remove the watchpoint on a name
+  p, print <sym>   print the value of a name in the current environment
+  q, quit          stop the program
+  h, help          print this message";
+
+/// The step debugger of ignite. Before each step it prints the instruction the current thread is about to execute,
+/// its operand stack and its environment, and waits for a command.
+pub struct Debugger {
+    breakpoints: BTreeSet<usize>,
+    // Stop before every step, rather than only at breakpoints
+    stepping: bool,
+    // The file the program was compiled from and the line of each instruction, if it was compiled from source
+    source: Option<(String, LineTable)>,
+}
+
+impl Debugger {
+    pub fn new() -> Self {
+        Debugger {
+            breakpoints: BTreeSet::new(),
+            stepping: true,
+            source: None,
+        }
+    }
+
+    /// Show the lines of the source file the program was compiled from, and allow breakpoints on them.
+    pub fn set_source(&mut self, file: &str, lines: LineTable) {
+        self.source = Some((file.to_string(), lines));
+    }
+
+    fn lines(&self) -> Option<&LineTable> {
+        self.source.as_ref().map(|(_, lines)| lines)
+    }
+
+    /// Run the program, reading commands from input, until it is done. Returns None if the program was stopped
+    /// with quit, or the input ended before it was done.
+    pub fn run(
+        &mut self,
+        mut rt: Runtime,
+        input: impl BufRead,
+        mut out: impl Write,
+    ) -> Result<Option<Runtime>> {
+        let mut lines = input.lines();
+
+        while !rt.is_done() {
+            let pc = rt.current_thread.pc;
+            if !self.stepping && self.breakpoints.contains(&pc) {
+                writeln!(out, "Breakpoint at {}", pc)?;
+                self.stepping = true;
+            }
+
+            if self.stepping {
+                print_state(&rt, self.lines(), &mut out)?;
+
+                // The time spent at the prompt doesn't count towards the time quantum of the thread
+                let paused = Instant::now();
+                loop {
+                    write!(out, "(step) ")?;
+                    out.flush()?;
+
+                    let Some(line) = lines.next() else {
+                        return Ok(None);
+                    };
+
+                    match self.command(&mut rt, line?.trim(), &mut out)? {
+                        Command::Step => break,
+                        Command::Quit => return Ok(None),
+                        Command::Prompt => (),
+                    }
+                }
+                rt.time += paused.elapsed();
+            }
+
+            rt = rt.step()?;
+
+            // ASSIGN doesn't switch threads, so the name is in the environment of the current thread
+            if let Some(sym) = rt.watch_hit.take() {
+                let val = lookup(&rt, &sym)?;
+                writeln!(out, "Watchpoint {} = {:?}", sym, val)?;
+                self.stepping = true;
+            }
+        }
+
+        Ok(Some(rt))
+    }
+
+    fn command(&mut self, rt: &mut Runtime, line: &str, out: &mut impl Write) -> Result<Command> {
+        let (cmd, arg) = line.split_once(' ').unwrap_or((line, ""));
+        let arg = arg.trim();
+
+        match cmd {
+            "" | "s" | "step" => return Ok(Command::Step),
+            "c" | "continue" => {
+                self.stepping = false;
+                return Ok(Command::Step);
+            }
+            "q" | "quit" => return Ok(Command::Quit),
+            "b" | "break" if arg.contains(':') => match self.line_pcs(arg) {
+                Ok(pcs) => {
+                    self.breakpoints.extend(pcs);
+                    writeln!(out, "Breakpoint set at {}", arg)?;
+                }
+                Err(msg) => writeln!(out, "{}", msg)?,
+            },
+            "b" | "break" => match arg.parse::<usize>() {
+                Ok(pc) if pc < rt.instrs.len() => {
+                    self.breakpoints.insert(pc);
+                    writeln!(out, "Breakpoint set at {}", pc)?;
+                }
+                Ok(pc) => writeln!(out, "No instruction at {}", pc)?,
+                Err(_) => writeln!(out, "Usage: break <pc> or break <file>:<line>")?,
+            },
+            "d" | "delete" if arg.contains(':') => match self.line_pcs(arg) {
+                Ok(pcs) if pcs.iter().any(|pc| self.breakpoints.contains(pc)) => {
+                    for pc in pcs {
+                        self.breakpoints.remove(&pc);
+                    }
+                    writeln!(out, "Breakpoint at {} deleted", arg)?;
+                }
+                Ok(_) => writeln!(out, "No breakpoint at {}", arg)?,
+                Err(msg) => writeln!(out, "{}", msg)?,
+            },
+            "d" | "delete" => match arg.parse::<usize>() {
+                Ok(pc) if self.breakpoints.remove(&pc) => {
+                    writeln!(out, "Breakpoint at {} deleted", pc)?
+                }
+                Ok(pc) => writeln!(out, "No breakpoint at {}", pc)?,
+                Err(_) => writeln!(out, "Usage: delete <pc> or delete <file>:<line>")?,
+            },
+            "w" | "watch" if !arg.is_empty() => {
+                rt.watchpoints.insert(arg.to_string());
+                writeln!(out, "Watching {}", arg)?;
+            }
+            "w" | "watch" => writeln!(out, "Usage: watch <sym>")?,
+            "unwatch" if rt.watchpoints.remove(arg) => writeln!(out, "Stopped watching {}", arg)?,
+            "unwatch" => writeln!(out, "Not watching {}", arg)?,
+            "p" | "print" if !arg.is_empty() => match lookup(rt, arg) {
+                Ok(val) => writeln!(out, "{} = {:?}", arg, val)?,
+                Err(err) => writeln!(out, "{}", err)?,
+            },
+            "p" | "print" => writeln!(out, "Usage: print <sym>")?,
+            "h" | "help" => writeln!(out, "{}", HELP)?,
+            _ => writeln!(out, "Unknown command {}, type help for the commands", cmd)?,
+        }
+
+        Ok(Command::Prompt)
+    }
+
+    // The pcs where the code on <file>:<line> starts, or why there are none
+    fn line_pcs(&self, arg: &str) -> Result<Vec<usize>, String> {
+        let Some((file, lines)) = &self.source else {
+            return Err(
+                "No line information, only a .rst file can be stopped on lines".to_string(),
+            );
+        };
+
+        let (bp_file, line) = arg.rsplit_once(':').expect("arg has a colon");
+        let line: usize = line
+            .parse()
+            .map_err(|_| format!("Usage: break {}:<line>", bp_file))?;
+
+        if Path::new(bp_file).file_name() != Path::new(file).file_name() {
+            return Err(format!(
+                "No source file {}, the program is from {}",
+                bp_file, file
+            ));
+        }
+
+        let pcs = lines.pcs(line);
+        if pcs.is_empty() {
+            return Err(format!("No code on line {}", line));
+        }
+        Ok(pcs)
+    }
+}
+
+// The value of sym in the environment of the current thread
+fn lookup(rt: &Runtime, sym: &str) -> Result<bytecode::Value> {
+    let env = rt
+        .current_thread
+        .env
+        .upgrade()
+        .ok_or(VmError::EnvironmentDroppedError)?;
+    let val = env.borrow().get(&sym.to_string())?;
+    Ok(val)
+}
+
+enum Command {
+    Step,
+    Quit,
+    // Read another command before stepping
+    Prompt,
+}
+
+// The frames of the environment are printed innermost first, leaving out the global frame and its builtins
+fn print_state(rt: &Runtime, lines: Option<&LineTable>, out: &mut impl Write) -> Result<()> {
+    let thread = &rt.current_thread;
+    let instr = rt
+        .instrs
+        .get(thread.pc)
+        .ok_or(VmError::PcOutOfBounds(thread.pc))?;
+    let line = match lines.and_then(|lines| lines.line(thread.pc)) {
+        Some(line) => format!(" (line {})", line),
+        None => String::new(),
+    };
+    writeln!(
+        out,
+        "Thread {}, PC {}{}: {}",
+        thread.thread_id,
+        thread.pc,
+        line,
+        fmt_instr(instr)
+    )?;
+    writeln!(out, "Operand Stack: {:?}", thread.operand_stack)?;
+
+    let mut frames = vec![];
+    let mut env = thread.env.upgrade();
+    while let Some(frame) = env {
+        let frame = frame.borrow();
+        let Some(parent) = &frame.parent else {
+            break;
+        };
+
+        let mut names: Vec<_> = frame.env.iter().collect();
+        names.sort_by(|a, b| a.0.cmp(b.0));
+        let names: Vec<String> = names
+            .into_iter()
+            .map(|(sym, val)| format!("{}: {:?}", sym, val))
+            .collect();
+        frames.push(format!("{{{}}}", names.join(", ")));
+        env = parent.upgrade();
+    }
+    frames.push("global".to_string());
+    writeln!(out, "Environment: {}", frames.join(" <- "))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use compiler::compiler::{compile_from_string, compile_with_lines};
+
+    use super::*;
+
+    fn debug(src: &str, input: &str) -> Result<(Option<Runtime>, String)> {
+        let rt = Runtime::new(compile_from_string(src, true)?);
+        let mut out = vec![];
+        let rt = Debugger::new().run(rt, input.as_bytes(), &mut out)?;
+        Ok((rt, String::from_utf8(out)?))
+    }
+
+    #[test]
+    fn test_debugger() -> Result<()> {
+        let src = "let x = 2; let y = x + 3; y";
+
+        // Each step prints the state before it
+        let (rt, out) = debug(src, "s\n\nc\n")?;
+        let rt = rt.expect("program should finish");
+        assert_eq!(
+            rt.current_thread.operand_stack.last(),
+            Some(&bytecode::Value::Int(5))
+        );
+        assert!(out.starts_with(
+            "Thread 1, PC 0: ENTERSCOPE [x, y]\nOperand Stack: []\nEnvironment: global\n"
+        ));
+        assert!(out.contains(
+            "Thread 1, PC 2: ASSIGN x\nOperand Stack: [2]\nEnvironment: {x: uninitialized, y: uninitialized} <- global\n"
+        ));
+        assert_eq!(out.matches("(step) ").count(), 3);
+
+        // Continue stops at a breakpoint, where names can be printed
+        let (rt, out) = debug(src, "b 5\nb 99\nc\np x\np z\nq\n")?;
+        assert!(rt.is_none());
+        assert!(out.contains("Breakpoint set at 5\n"));
+        assert!(out.contains("No instruction at 99\n"));
+        assert!(out.contains("Breakpoint at 5\nThread 1, PC 5"));
+        assert!(out.contains("x = 2\n"));
+        assert!(out.contains("Unbounded name: z\n"));
+
+        // A deleted breakpoint is passed
+        let (rt, out) = debug(src, "b 5\nd 5\nd 5\nc\n")?;
+        assert!(rt.is_some());
+        assert!(out.contains("No breakpoint at 5\n"));
+        assert!(!out.contains("Breakpoint at 5\n"));
+
+        // The input ending stops the program
+        let (rt, _) = debug(src, "s\n")?;
+        assert!(rt.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_debugger_lines() -> Result<()> {
+        let src = "let x = 2;\nfn f(n: int) -> int {\n    n + 1\n}\nlet z = f(x);\nz";
+        let (instrs, _, lines) = compile_with_lines(src, true, &HashSet::new())?;
+        let run = |input: &str, lines: Option<LineTable>| -> Result<String> {
+            let mut debugger = Debugger::new();
+            if let Some(lines) = lines {
+                debugger.set_source("prog.rst", lines);
+            }
+            let mut out = vec![];
+            debugger.run(Runtime::new(instrs.clone()), input.as_bytes(), &mut out)?;
+            Ok(String::from_utf8(out)?)
+        };
+
+        // A breakpoint on a line inside a fn stops when it is called
+        let out = run("b src/prog.rst:3\nc\np n\nc\n", Some(lines.clone()))?;
+        assert!(out.contains("Breakpoint set at src/prog.rst:3\n"));
+        assert!(out.contains("(line 3): LD n\n"));
+        assert!(out.contains("n = 2\n"));
+
+        let out = run(
+            "b other.rst:3\nb prog.rst:4\nb prog.rst:x\nc\n",
+            Some(lines.clone()),
+        )?;
+        assert!(out.contains("No source file other.rst, the program is from prog.rst\n"));
+        assert!(out.contains("No code on line 4\n"));
+        assert!(out.contains("Usage: break prog.rst:<line>\n"));
+
+        let out = run("b prog.rst:3\nd prog.rst:3\nd prog.rst:3\nc\n", Some(lines))?;
+        assert!(out.contains("Breakpoint at prog.rst:3 deleted\n"));
+        assert!(out.contains("No breakpoint at prog.rst:3\n"));
+        assert!(!out.contains("(line 3)"));
+
+        // Without a source file only pcs can be stopped on
+        let out = run("b prog.rst:3\nc\n", None)?;
+        assert!(out.contains("No line information"));
+
+        // A watchpoint stops after every assignment to the name
+        let out = run("w z\nw x\nunwatch x\nunwatch x\nc\nc\n", None)?;
+        assert!(out.contains("Stopped watching x\n(step) Not watching x\n"));
+        assert!(out.contains("Watchpoint z = 3\n"));
+        assert!(!out.contains("Watchpoint x"));
+        Ok(())
+    }
+}
@@ -1,52 +1,338 @@
-use thiserror::Error;
+use std::fmt::Display;
 
-#[derive(Error, Debug)]
-pub enum VmError {
-    #[error("I/O error: {0}")]
-    Io(#[from] std::io::Error),
+use bytecode::{ByteCodeError, LineTable, ThreadID};
 
-    #[error("File does not exist: {0}")]
-    FileDoesNotExist(String),
+use crate::error_backtrace;
+use diagnostics::{message, Code, Message};
 
-    #[error("File is not a .o2 file: {0}")]
+#[derive(Debug)]
+pub enum VmError {
+    Io(std::io::Error),
+    FileDoesNotExist(String),
     NotO2File(String),
-
-    #[error("Unbounded name: {0}")]
+    NotRstFile(String),
     UnboundedName(String),
-
-    #[error("Operand stack underflow")]
     OperandStackUnderflow,
-
-    #[error("Runtime stack underflow")]
     RuntimeStackUnderflow,
-
-    #[error("No threads in ready queue")]
+    ScopeUnderflow {
+        pc: usize,
+    },
+    ResetFrameMismatch {
+        pc: usize,
+        expected: String,
+        found: String,
+    },
+    ResetUnderflow {
+        pc: usize,
+        expected: String,
+    },
+    ResetOutsideFunction {
+        pc: usize,
+    },
+    IndexOutOfBounds {
+        index: i64,
+        len: usize,
+        pc: usize,
+    },
+    SliceOutOfBounds {
+        start: i64,
+        end: i64,
+        len: usize,
+        pc: usize,
+    },
+    FieldNotFound {
+        name: String,
+        field: String,
+        pc: usize,
+    },
+    DivisionByZero {
+        pc: usize,
+    },
+    IntegerOverflow {
+        op: String,
+        pc: usize,
+    },
+    OutOfMemory {
+        live: usize,
+        limit: usize,
+    },
+    StackOverflow {
+        stack: Stack,
+        limit: usize,
+        thread_id: ThreadID,
+        pc: usize,
+    },
     NoThreadsInReadyQueue,
-
-    #[error("PC out of bounds: {0}")]
+    SemaphorePoisoned,
     PcOutOfBounds(usize),
+    BadType {
+        expected: String,
+        found: String,
+    },
+    IllegalArgument(String),
+    UnsupportedOperation(String, String),
+    TypeMismatch {
+        expected: String,
+        found: String,
+    },
+    ArityParamsMismatch {
+        arity: usize,
+        params: usize,
+    },
+    InsufficientArguments {
+        expected: usize,
+        got: usize,
+    },
+    EnvironmentDroppedError,
+    BlockedInCallback {
+        sym: String,
+    },
+    NetNotAllowed {
+        sym: String,
+    },
+    RunNotAllowed {
+        sym: String,
+    },
+    NoSuchThread(ThreadID),
+    ThreadsStillRunning(Vec<ThreadID>),
+    UnknownBuiltin {
+        sym: String,
+    },
+    LineEditor(String),
+}
 
-    #[error("Bad type: expected {expected}, found {found}")]
-    BadType { expected: String, found: String },
+impl VmError {
+    /// The message of the error, in the language of the current catalog, see [`diagnostics::set_catalog`].
+    pub fn message(&self) -> Message {
+        match self {
+            VmError::Io(err) => message!(R012, "I/O error: {}", err),
+            VmError::FileDoesNotExist(file) => message!(R012, "File does not exist: {}", file),
+            VmError::NotO2File(file) => message!(R012, "File is not a .o2 file: {}", file),
+            VmError::NotRstFile(file) => message!(R012, "File is not a .rst file: {}", file),
+            VmError::UnboundedName(name) => message!(R008, "Unbounded name: {}", name),
+            VmError::OperandStackUnderflow => message!(R011, "Operand stack underflow"),
+            VmError::RuntimeStackUnderflow => message!(R011, "Runtime stack underflow"),
+            VmError::ScopeUnderflow { pc } => message!(
+                R011,
+                "Scope underflow: EXITSCOPE at pc {} has no matching ENTERSCOPE",
+                pc
+            ),
+            VmError::ResetFrameMismatch { pc, expected, found } => message!(
+                R011,
+                "Reset frame mismatch at pc {}: expected {}, found {}",
+                pc,
+                expected,
+                found
+            ),
+            VmError::ResetUnderflow { pc, expected } => message!(
+                R011,
+                "Reset underflow at pc {}: no {} on the runtime stack",
+                pc,
+                expected
+            ),
+            VmError::ResetOutsideFunction { pc } => message!(
+                R011,
+                "RESET at pc {} is outside of a function body",
+                pc
+            ),
+            VmError::IndexOutOfBounds { pc, len, index } => message!(
+                R003,
+                "Index out of bounds at pc {}: the length is {} but the index is {}",
+                pc,
+                len,
+                index
+            ),
+            VmError::SliceOutOfBounds { pc, len, start, end } => message!(
+                R003,
+                "Slice out of bounds at pc {}: the length is {} but the range is {}..{}",
+                pc,
+                len,
+                start,
+                end
+            ),
+            VmError::FieldNotFound { pc, name, field } => message!(
+                R004,
+                "Field not found at pc {}: struct {} has no field {}",
+                pc,
+                name,
+                field
+            ),
+            VmError::DivisionByZero { pc } => message!(R001, "Division by zero at pc {}", pc),
+            VmError::IntegerOverflow { pc, op } => message!(
+                R002,
+                "Integer overflow at pc {}: the result of {} doesn't fit in an int",
+                pc,
+                op
+            ),
+            VmError::OutOfMemory { live, limit } => message!(
+                R013,
+                "Out of memory: {} environments are live, more than the limit of {}",
+                live,
+                limit
+            ),
+            VmError::StackOverflow {
+                stack: Stack::Runtime,
+                limit,
+                thread_id,
+                pc,
+            } => message!(
+                R014,
+                "Stack overflow in thread {} at pc {}: more than {} frames on the runtime stack",
+                thread_id,
+                pc,
+                limit
+            ),
+            VmError::StackOverflow {
+                stack: Stack::Operand,
+                limit,
+                thread_id,
+                pc,
+            } => message!(
+                R014,
+                "Stack overflow in thread {} at pc {}: more than {} values on the operand stack",
+                thread_id,
+                pc,
+                limit
+            ),
+            VmError::NoThreadsInReadyQueue => message!(R010, "No threads in ready queue"),
+            VmError::SemaphorePoisoned => message!(
+                R010,
+                "Semaphore is poisoned: a thread of the host panicked while holding it"
+            ),
+            VmError::PcOutOfBounds(pc) => message!(R011, "PC out of bounds: {}", pc),
+            VmError::BadType { expected, found } => message!(
+                R006,
+                "Bad type: expected {}, found {}",
+                expected,
+                found
+            ),
+            VmError::IllegalArgument(msg) => message!(R007, "Illegal argument: {}", msg),
+            VmError::UnsupportedOperation(op, ty) => message!(
+                R006,
+                "Unsupported operation {} on type {}",
+                op,
+                ty
+            ),
+            VmError::TypeMismatch { expected, found } => message!(
+                R006,
+                "Type mismatch: expected {}, found {}",
+                expected,
+                found
+            ),
+            VmError::ArityParamsMismatch { arity, params } => message!(
+                R011,
+                "Arity and params mismatch: arity {}, found {} params",
+                arity,
+                params
+            ),
+            VmError::InsufficientArguments { expected, got } => message!(
+                R007,
+                "Insufficient arguments: expected {}, got {}",
+                expected,
+                got
+            ),
+            VmError::EnvironmentDroppedError => message!(R011, "Environment access after drop"),
+            VmError::BlockedInCallback { sym } => message!(
+                R010,
+                "A function called by builtin {} yielded or blocked, which only the top level of a thread can do",
+                sym
+            ),
+            VmError::NetNotAllowed { sym } => message!(
+                R009,
+                "{} uses the network, which is not allowed. Run with --allow-net to allow it",
+                sym
+            ),
+            VmError::RunNotAllowed { sym } => message!(
+                R009,
+                "{} runs other programs, which is not allowed. Run with --allow-run to allow it",
+                sym
+            ),
+            VmError::NoSuchThread(tid) => message!(
+                R010,
+                "No thread {} to join or detach: it was never spawned, or was detached or reaped",
+                tid
+            ),
+            VmError::ThreadsStillRunning(tids) => message!(
+                R010,
+                "The main thread finished while threads {} were still running. Join them, or detach the ones that don't need to finish",
+                tids.iter().map(ThreadID::to_string).collect::<Vec<_>>().join(", ")
+            ),
+            VmError::UnknownBuiltin { sym } => message!(R011, "Unknown builtin: {}", sym),
+            VmError::LineEditor(err) => message!(R012, "Line editor error: {}", err),
+        }
+    }
 
-    #[error("Illegal argument: {0}")]
-    IllegalArgument(String),
+    /// The code of the error, see [`diagnostics`].
+    pub fn code(&self) -> Code {
+        self.message().code
+    }
+}
 
-    #[error("Unsupported operation {0} on type {1}")]
-    UnsupportedOperation(String, String),
+/// The stacks of a thread that have a limit, see [`VmError::StackOverflow`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stack {
+    /// The frames of the calls and blocks the thread is in.
+    Runtime,
+    /// The values the instructions of the thread work on.
+    Operand,
+}
 
-    #[error("Type mismatch: expected {expected}, found {found}")]
-    TypeMismatch { expected: String, found: String },
+impl Display for VmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message().text)
+    }
+}
 
-    #[error("Arity and params mismatch: arity {arity}, found {params} params")]
-    ArityParamsMismatch { arity: usize, params: usize },
+impl std::error::Error for VmError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            VmError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
 
-    #[error("Insufficient arguments: expected {expected}, got {got}")]
-    InsufficientArguments { expected: usize, got: usize },
+impl From<std::io::Error> for VmError {
+    fn from(err: std::io::Error) -> Self {
+        VmError::Io(err)
+    }
+}
 
-    #[error("Environment access after drop")]
-    EnvironmentDroppedError,
+/// The code of a runtime error, if it is one of the VM's or the bytecode's. Errors from elsewhere, like
+/// the OS, have none.
+pub fn error_code(err: &anyhow::Error) -> Option<Code> {
+    if let Some(err) = err.downcast_ref::<VmError>() {
+        return Some(err.code());
+    }
+
+    err.downcast_ref::<ByteCodeError>().map(ByteCodeError::code)
+}
+
+/// A runtime error as it is shown to the user, with its code if it has one and the backtrace of the thread that
+/// stopped with it.
+pub fn format_runtime_error(err: &anyhow::Error) -> String {
+    format_runtime_error_with_lines(err, None)
+}
+
+/// A runtime error as [`format_runtime_error`] shows it, with the source line of each frame of the backtrace if
+/// there are lines.
+pub fn format_runtime_error_with_lines(err: &anyhow::Error, lines: Option<&LineTable>) -> String {
+    // the backtrace is the context of the error, so the message is the error under it
+    let (msg, backtrace) = match error_backtrace(err) {
+        Some(backtrace) => {
+            let msg = err.chain().nth(1).map(ToString::to_string);
+            (msg.unwrap_or_default(), Some(backtrace))
+        }
+        None => (err.to_string(), None),
+    };
 
-    #[error("Unknown builtin: {sym}")]
-    UnknownBuiltin { sym: String },
+    let mut out = match error_code(err) {
+        Some(code) => format!("[RuntimeError {}]: {}", code, msg),
+        None => format!("[RuntimeError]: {}", msg),
+    };
+    if let Some(backtrace) = backtrace {
+        out.push('\n');
+        out.push_str(&backtrace.format(lines));
+    }
+    out
 }
@@ -0,0 +1,274 @@
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    io::Write,
+    rc::{Rc, Weak},
+};
+
+use anyhow::Result;
+use bytecode::{ByteCode, Environment, LineTable, Value};
+
+use ignite::{run, Runtime, MAIN_THREAD_ID};
+
+// The names bound in an environment frame, with their values as they are shown
+type Names = BTreeMap<String, String>;
+
+/// The teaching mode of ignite. Before each statement the main thread executes it prints the source line, then the
+/// operands of each operator and condition it evaluates, and after it the names that were bound or assigned.
+/// Other threads run without being explained.
+pub struct Explainer {
+    source: Vec<String>,
+    lines: LineTable,
+    // Past this many statements the rest of the program runs without being explained
+    limit: usize,
+    explained: usize,
+    // The line of the statement being explained, and whether it has been printed yet. A line the thread comes back
+    // to part way through, after a call or the body of an if, is only printed again if there is more to explain
+    line: Option<usize>,
+    line_printed: bool,
+    // The names bound in each environment frame the last time it was looked at, so only changes are printed.
+    // Frames that have been dropped are forgotten
+    frames: Vec<(Weak<RefCell<Environment>>, Names)>,
+}
+
+impl Explainer {
+    /// Explain the program compiled from source, with the line each instruction is from.
+    pub fn new(source: &str, lines: LineTable, limit: usize) -> Self {
+        Explainer {
+            source: source.lines().map(str::to_string).collect(),
+            lines,
+            limit,
+            explained: 0,
+            line: None,
+            line_printed: false,
+            frames: vec![],
+        }
+    }
+
+    /// Run the program until it is done, writing the explanation to out.
+    pub fn run(&mut self, mut rt: Runtime, mut out: impl Write) -> Result<Runtime> {
+        while !rt.is_done() {
+            let thread_id = rt.current_thread.thread_id;
+            let pc = rt.current_thread.pc;
+            if thread_id != MAIN_THREAD_ID {
+                rt = rt.step()?;
+                continue;
+            }
+
+            let line = self.lines.line(pc);
+            if line.is_some() && line != self.line {
+                self.print_changes(&rt, &mut out)?;
+                if self.explained >= self.limit {
+                    writeln!(
+                        out,
+                        "... explained {} statements, the rest runs without explanation",
+                        self.limit
+                    )?;
+                    return run(rt);
+                }
+
+                self.line = line;
+                self.line_printed = false;
+                // the start of the statement, rather than the thread coming back to it
+                if line.is_some_and(|line| self.lines.pcs(line).first() == Some(&pc)) {
+                    self.print_line(&mut out)?;
+                }
+            }
+
+            // the names of a scope are gone once it is left
+            if let Some(ByteCode::EXITSCOPE | ByteCode::RESET(_) | ByteCode::DONE) =
+                rt.instrs.get(pc)
+            {
+                self.print_changes(&rt, &mut out)?;
+            }
+
+            let operands = Operands::before(&rt);
+            rt = rt.step()?;
+
+            // the instruction didn't run if the thread was switched out before it
+            let thread = &rt.current_thread;
+            if thread.thread_id == thread_id && thread.pc != pc {
+                if let Some(explanation) = operands.explain(&rt) {
+                    self.print_line(&mut out)?;
+                    writeln!(out, "  {}", explanation)?;
+                }
+            }
+        }
+
+        Ok(rt)
+    }
+
+    // Print the line of the statement being explained, unless it has been already
+    fn print_line(&mut self, out: &mut impl Write) -> Result<()> {
+        let Some(line) = self.line.filter(|_| !self.line_printed) else {
+            return Ok(());
+        };
+
+        let text = self.source.get(line - 1).map_or("", |text| text.trim());
+        writeln!(out, "line {}: {}", line, text)?;
+        self.line_printed = true;
+        self.explained += 1;
+        Ok(())
+    }
+
+    // Print the names of the environment of the main thread that were bound or assigned since it was last looked
+    // at, innermost frame first, leaving out the global frame and its builtins. Names that are declared but not
+    // yet assigned aren't shown
+    fn print_changes(&mut self, rt: &Runtime, out: &mut impl Write) -> Result<()> {
+        self.frames.retain(|(frame, _)| frame.strong_count() > 0);
+
+        let mut env = rt.current_thread.env.upgrade();
+        while let Some(frame) = env {
+            let parent = match &frame.borrow().parent {
+                Some(parent) => parent.upgrade(),
+                None => break,
+            };
+
+            let names: Names = frame
+                .borrow()
+                .env
+                .iter()
+                // names starting with $ are the compiler's own, like the index of a for loop
+                .filter(|(sym, val)| !sym.starts_with('$') && !matches!(val, Value::Unitialized))
+                .map(|(sym, val)| (sym.to_string(), val.to_string()))
+                .collect();
+
+            let seen = self
+                .frames
+                .iter_mut()
+                .find(|(seen, _)| seen.upgrade().is_some_and(|seen| Rc::ptr_eq(&seen, &frame)));
+            let prev = match seen {
+                Some((_, prev)) => std::mem::replace(prev, names.clone()),
+                None => {
+                    self.frames.push((Rc::downgrade(&frame), names.clone()));
+                    BTreeMap::new()
+                }
+            };
+
+            for (sym, val) in names.iter() {
+                let change = match prev.get(sym) {
+                    None => format!("{} = {}", sym, val),
+                    Some(old) if old != val => format!("{} = {} (was {})", sym, val, old),
+                    Some(_) => continue,
+                };
+                self.print_line(out)?;
+                writeln!(out, "  {}", change)?;
+            }
+
+            env = parent;
+        }
+
+        Ok(())
+    }
+}
+
+// The values an instruction operates on, taken before it runs, so they can be shown with its result
+enum Operands {
+    BinOp(String, Value, Value),
+    UnOp(String, Value),
+    Condition(Value),
+    None,
+}
+
+impl Operands {
+    fn before(rt: &Runtime) -> Operands {
+        let stack = &rt.current_thread.operand_stack;
+        let top = |n: usize| {
+            stack
+                .len()
+                .checked_sub(n)
+                .and_then(|idx| stack.get(idx))
+                .cloned()
+        };
+
+        match (rt.instrs.get(rt.current_thread.pc), top(2), top(1)) {
+            (Some(ByteCode::BINOP(op)), Some(lhs), Some(rhs)) => {
+                Operands::BinOp(op.clone().into(), lhs, rhs)
+            }
+            (Some(ByteCode::UNOP(op)), _, Some(val)) => Operands::UnOp(op.clone().into(), val),
+            (Some(ByteCode::JOF(_) | ByteCode::JOFR(_)), _, Some(cond)) => {
+                Operands::Condition(cond)
+            }
+            _ => Operands::None,
+        }
+    }
+
+    // What the instruction did with the operands, now that it has run
+    fn explain(self, rt: &Runtime) -> Option<String> {
+        let result = rt.current_thread.operand_stack.last();
+        match (self, result) {
+            (Operands::BinOp(op, lhs, rhs), Some(result)) => {
+                Some(format!("{} {} {} is {}", lhs, op, rhs, result))
+            }
+            (Operands::UnOp(op, val), Some(result)) => {
+                Some(format!("{}({}) is {}", op, val, result))
+            }
+            (Operands::Condition(cond), _) => Some(format!("condition is {}", cond)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use compiler::compiler::compile_with_lines;
+
+    use super::*;
+
+    fn explain(src: &str, limit: usize) -> Result<String> {
+        let (instrs, _, lines) = compile_with_lines(src, true, &HashSet::new())?;
+        let mut out = vec![];
+        Explainer::new(src, lines, limit).run(Runtime::new(instrs), &mut out)?;
+        Ok(String::from_utf8(out)?)
+    }
+
+    #[test]
+    fn test_explain_run() -> Result<()> {
+        let src = "let x = 2;
+let y = x * 3 + 1;
+if y > 5 {
+    x = -x;
+}
+fn inc(n: int) -> int {
+    n + 1
+}
+let z = inc(y);";
+
+        assert_eq!(
+            explain(src, 200)?,
+            "line 1: let x = 2;
+  x = 2
+line 2: let y = x * 3 + 1;
+  2 * 3 is 6
+  6 + 1 is 7
+  y = 7
+line 3: if y > 5 {
+  7 > 5 is true
+  condition is true
+line 4: x = -x;
+  -(2) is -2
+  x = -2 (was 2)
+line 6: fn inc(n: int) -> int {
+  inc = closure
+line 9: let z = inc(y);
+  n = 7
+line 7: n + 1
+  7 + 1 is 8
+line 9: let z = inc(y);
+  z = 8
+"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_explain_run_limit() -> Result<()> {
+        let src = "let i = 0;\nwhile i < 100 {\n    i = i + 1;\n}\ni";
+        let out = explain(src, 3)?;
+        assert_eq!(out.matches("line ").count(), 3);
+        assert!(out.ends_with("... explained 3 statements, the rest runs without explanation\n"));
+        Ok(())
+    }
+}
@@ -0,0 +1,16 @@
+// Environments are hashed by pointer identity, so their interior mutability never changes a key.
+#![allow(clippy::mutable_key_type)]
+
+pub use crate::backtrace::*;
+pub use crate::error::*;
+pub use crate::runtime::*;
+pub use crate::thread::*;
+pub use crate::verifier::*;
+
+pub mod micro_code;
+
+mod backtrace;
+mod error;
+mod runtime;
+mod thread;
+mod verifier;
@@ -1,26 +1,40 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
 use std::path::Path;
 use std::time::Duration;
 
 use anyhow::{Error, Result};
-use bytecode::{builtin, read_bytecode};
-use clap::Parser;
+use bytecode::{
+    builtin, disassemble, migrate_bytecode, read_from_file, write_to_file, ByteCode, LineTable,
+    ThreadID, Value, BYTECODE_VERSION,
+};
+use clap::{Parser, Subcommand};
+use compiler::compiler::{compile_strict, compile_tests, compile_with_lines};
+use debugger::Debugger;
+use diagnostics::{set_catalog, Catalog, Code};
+use explainer::Explainer;
+use ignite::*;
 use repl::ignite_repl;
-use runtime::*;
+use stack_view::{StackFormat, StackRange, StackView};
 
-pub use crate::error::*;
-pub use crate::thread::*;
-
-mod error;
-mod micro_code;
+mod debugger;
+mod explainer;
 mod repl;
-mod runtime;
-mod thread;
+mod stack_view;
+
+const O2: &str = "o2";
+const RST: &str = "rst";
 
 #[derive(Parser, Debug)]
 #[command(name = "Ignite")]
 #[command(version = "0.1.0")]
 #[command(about = "Virtual Machine for RustScript", long_about = None)]
+#[command(args_conflicts_with_subcommands = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// File name of the program to run, must be a .o2 file.
     file: Option<String>,
 
@@ -28,31 +42,291 @@ struct Args {
     #[arg(long, short)]
     repl: bool,
 
+    #[command(flatten)]
+    run: RunArgs,
+
+    /// If present, does not type check in REPL. Ignored if only running bytecode.
+    #[arg(short)]
+    notype: bool,
+
+    /// Print the numbered instructions of the program instead of running it.
+    #[arg(long)]
+    disasm: bool,
+
+    /// Explain an error code, like T012, with examples of the error and how to fix it.
+    #[arg(long, value_name = "CODE")]
+    explain: Option<String>,
+
+    /// Show error messages in another language, from a catalog of translations keyed by error code.
+    #[arg(long, value_name = "FILE", global = true)]
+    messages: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+// parsed once, so the size of Run doesn't matter
+#[allow(clippy::large_enum_variant)]
+enum Command {
+    /// Run a program. A .rst file is compiled first, a .o2 file is run as it is.
+    Run {
+        /// File name of the program, a .rst or .o2 file.
+        file: String,
+
+        /// Compile a .rst file without type checking it.
+        #[arg(long)]
+        no_type_check: bool,
+
+        /// Compile a .rst file in strict mode: a let that shadows a name is warned about and warnings are errors.
+        /// Int/float mixing, missing param annotations and int overflow are errors with or without it.
+        #[arg(long, conflicts_with = "no_type_check")]
+        strict: bool,
+
+        #[command(flatten)]
+        run: RunArgs,
+    },
+    /// Compile a .rst file to bytecode.
+    Compile {
+        /// File name of the program, must be a .rst file.
+        file: String,
+
+        /// Where to write the bytecode. Defaults to the name of the file with extension .o2.
+        #[arg(short, long)]
+        output: Option<String>,
+
+        /// Compile without type checking.
+        #[arg(long)]
+        no_type_check: bool,
+
+        /// Compile in strict mode: a let that shadows a name is warned about and warnings are errors.
+        /// Int/float mixing, missing param annotations and int overflow are errors with or without it.
+        #[arg(long, conflicts_with = "no_type_check")]
+        strict: bool,
+    },
+    /// Run the #[test] fns of a .rst file, each in its own run of the program after its top level code, in place
+    /// of its last expression. A test fails if the run stops with an error, like a false assert.
+    Test {
+        /// File name of the program, must be a .rst file.
+        file: String,
+
+        #[command(flatten)]
+        run: RunArgs,
+    },
+    /// Start a REPL, where the names declared on a line can be used on the lines after it.
+    Repl {
+        /// Run each line without type checking it.
+        #[arg(long)]
+        no_type_check: bool,
+    },
+    /// Print the numbered instructions of a .o2 file.
+    Disasm {
+        /// File name of the program, must be a .o2 file.
+        file: String,
+    },
+    /// Rewrite a .o2 file compiled with an older version of the bytecode in the current one, if its instructions
+    /// haven't changed since.
+    Migrate {
+        /// File name of the program, must be a .o2 file.
+        file: String,
+
+        /// Where to write the migrated bytecode. Defaults to replacing the file.
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+}
+
+/// How to run the program.
+#[derive(clap::Args, Debug)]
+struct RunArgs {
     /// Set custom time quantum for the VM in milliseconds.
     /// Default is 100ms.
-    #[arg(short, long)]
+    #[arg(short, long = "time-quantum", alias = "quantum")]
     quantum: Option<u64>,
 
+    /// Preempt threads after this many instructions instead of after the time quantum, so every run interleaves
+    /// the threads the same way.
+    #[arg(long, value_name = "N", conflicts_with = "quantum")]
+    quantum_instrs: Option<usize>,
+
+    /// Pick the next thread to run at random from this seed, instead of in the order they became ready.
+    /// Needs --quantum-instrs.
+    #[arg(long, requires = "quantum_instrs")]
+    seed: Option<u64>,
+
+    /// Keep at most this many finished threads for joining. Past it the earliest spawned are dropped, and joining
+    /// them is an error. By default they are kept until joined.
+    #[arg(long, value_name = "N")]
+    max_zombies: Option<usize>,
+
     /// Set custom garbage collection interval for the VM in milliseconds.
     /// Default is 1000ms.
     #[arg(short, long)]
     gc_interval: Option<u64>,
 
+    /// Also collect garbage once there are this many environments, and after that once they have doubled since
+    /// the last collection. 0 collects on the interval only. Default is 100000.
+    #[arg(long, value_name = "N")]
+    gc_threshold: Option<usize>,
+
+    /// Sweep at most this many environments per step, so a collection that frees a lot doesn't pause the
+    /// program all at once.
+    #[arg(long, value_name = "N")]
+    gc_incremental: Option<usize>,
+
+    /// Stop the program with an out of memory error if it has more than this many environments live, after
+    /// collecting the garbage. By default there is no limit.
+    #[arg(long, value_name = "N")]
+    max_heap: Option<usize>,
+
+    /// Free the environments of a call or block as soon as it returns, rather than at the next collection, if no
+    /// closure or thread was made in them. Lowers the peak memory of deep recursion.
+    #[arg(long)]
+    eager_reset: bool,
+
+    /// Stop a thread with a stack overflow error if it calls functions deeper than this, counting the blocks it is
+    /// in as well as the calls.
+    #[arg(long, value_name = "N")]
+    max_call_depth: Option<usize>,
+
+    /// Stop a thread with a stack overflow error if it has more than this many values on its operand stack.
+    #[arg(long, value_name = "N")]
+    max_operand_stack: Option<usize>,
+
     /// Turn debugging information on
     #[arg(short, long)]
     debug: bool,
 
-    /// If present, does not type check in REPL. Ignored if only running bytecode.
-    #[arg(short)]
-    notype: bool,
+    /// Don't print the value of the last expression of the program.
+    #[arg(long)]
+    quiet: bool,
+
+    /// Print the cumulative time spent in each opcode to stderr after the run.
+    #[arg(long)]
+    profile_opcode: bool,
+
+    /// Allow the program to use the network, with tcp_connect, udp_bind and the rest.
+    #[arg(long)]
+    allow_net: bool,
+
+    /// Allow the program to run other programs with run_command.
+    #[arg(long)]
+    allow_run: bool,
+
+    /// Step through the program an instruction at a time. Type help at the prompt for the commands.
+    #[arg(long)]
+    step: bool,
+
+    /// Explain each statement the main thread runs: its source line, the operands of the operators and
+    /// conditions it evaluates, and the names it binds or assigns. After N statements, 200 by default, the rest of
+    /// the program runs without explanation. Needs a .rst file.
+    #[arg(long, value_name = "N", num_args = 0..=1, default_missing_value = "200", conflicts_with = "step")]
+    explain_run: Option<usize>,
+
+    /// Write the operand stack before and after each instruction to stderr, for the pcs in a range like 3..9 or
+    /// the body of the fn with the name.
+    #[arg(long, value_name = "PCS|FN", conflicts_with_all = ["step", "explain_run"])]
+    show_stack: Option<StackRange>,
+
+    /// How --show-stack writes the stack: a table, or a JSON object on each line for a visualizer.
+    #[arg(long, value_enum, default_value = "table", requires = "show_stack")]
+    stack_format: StackFormat,
+
+    /// Write each executed instruction, with its thread, pc and stack depth, to the file, or stderr if no file is
+    /// given.
+    #[arg(long, value_name = "FILE", num_args = 0..=1, default_missing_value = "-")]
+    trace: Option<String>,
+
+    /// What to do with the threads still running when the main thread finishes: stop them with a warning, wait
+    /// for them, or fail. Detached threads are stopped without one.
+    #[arg(long, value_enum, default_value = "cancel")]
+    on_main_exit: OnMainExit,
+
+    /// Arguments for the program, after --, which it gets with args().
+    #[arg(last = true, value_name = "ARGS")]
+    program_args: Vec<String>,
+}
+
+/// The policies of `--on-main-exit`, see [`MainExitPolicy`].
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq)]
+enum OnMainExit {
+    Cancel,
+    JoinAll,
+    Error,
+}
+
+impl From<OnMainExit> for MainExitPolicy {
+    fn from(policy: OnMainExit) -> Self {
+        match policy {
+            OnMainExit::Cancel => MainExitPolicy::Cancel,
+            OnMainExit::JoinAll => MainExitPolicy::JoinAll,
+            OnMainExit::Error => MainExitPolicy::Error,
+        }
+    }
 }
 
 fn main() -> Result<()> {
     let args = Args::parse();
+
+    // errors from the VM are shown with their code, so they can be looked up with --explain
+    ignite(args).map_err(|err| match error_code(&err) {
+        Some(_) => Error::msg(format_runtime_error(&err)),
+        None => err,
+    })
+}
+
+fn ignite(args: Args) -> Result<()> {
+    if let Some(file) = &args.messages {
+        if !Path::new(file).exists() {
+            return Err(VmError::FileDoesNotExist(file.to_string()).into());
+        }
+        let catalog = Catalog::parse(&std::fs::read_to_string(file)?)?;
+        set_catalog(Some(catalog));
+    }
+
+    if let Some(code) = args.explain {
+        let code: Code = code.parse()?;
+        print!("{}", code.explain());
+        return Ok(());
+    }
+
+    match args.command {
+        Some(Command::Run {
+            file,
+            no_type_check,
+            strict,
+            run,
+        }) => {
+            let is_rst = Path::new(&file).extension().is_some_and(|ext| ext == RST);
+            if is_rst {
+                let (bytecode, lines) = compile_file(&file, !no_type_check, strict)?;
+                return run_bytecode(bytecode, Some((&file, lines)), &run);
+            }
+            return run_bytecode(read_bytecode(&file)?, None, &run);
+        }
+        Some(Command::Compile {
+            file,
+            output,
+            no_type_check,
+            strict,
+        }) => {
+            let (bytecode, _) = compile_file(&file, !no_type_check, strict)?;
+            let out_name = output.unwrap_or_else(|| default_out_name(&file));
+            write_to_file(&bytecode, &out_name)?;
+
+            println!("Compiled successfully to {}", out_name);
+            return Ok(());
+        }
+        Some(Command::Test { file, run }) => return run_tests(&file, &run),
+        Some(Command::Repl { no_type_check }) => return ignite_repl(!no_type_check),
+        Some(Command::Disasm { file }) => {
+            print!("{}", disassemble(&read_bytecode(&file)?));
+            return Ok(());
+        }
+        Some(Command::Migrate { file, output }) => return migrate(&file, output),
+        None => (),
+    }
+
     let file_provided = args.file.is_some();
 
     if args.repl {
-        // TODO: if file provided, run the file and pass generated context to REPL
         ignite_repl(!args.notype)?;
         return Ok(()); // REPL done: exit
     } else if !args.repl && !file_provided {
@@ -60,42 +334,282 @@ fn main() -> Result<()> {
     }
 
     let file = args.file.expect("File was provided");
+    let bytecode_vec = read_bytecode(&file)?;
+
+    if args.disasm {
+        print!("{}", disassemble(&bytecode_vec));
+        return Ok(());
+    }
+
+    run_bytecode(bytecode_vec, None, &args.run)
+}
 
+/// Deserialize the program in a .o2 file.
+fn read_bytecode(file: &str) -> Result<Vec<ByteCode>> {
     // Check if the file exists
-    if !Path::new(&file).exists() {
-        return Err(VmError::FileDoesNotExist(file).into());
+    if !Path::new(file).exists() {
+        return Err(VmError::FileDoesNotExist(file.to_string()).into());
     }
 
     // check file extension
-    if Path::new(&file).extension().unwrap() != "o2" {
-        return Err(VmError::NotO2File(file).into());
+    if Path::new(file).extension().is_none_or(|ext| ext != O2) {
+        return Err(VmError::NotO2File(file.to_string()).into());
+    }
+
+    read_from_file(file)
+}
+
+/// Rewrite the program in a .o2 file with the current version of the bytecode.
+fn migrate(file: &str, output: Option<String>) -> Result<()> {
+    if !Path::new(file).exists() {
+        return Err(VmError::FileDoesNotExist(file.to_string()).into());
+    }
+
+    if Path::new(file).extension().is_none_or(|ext| ext != O2) {
+        return Err(VmError::NotO2File(file.to_string()).into());
+    }
+
+    let (version, bytecode) = migrate_bytecode(&mut BufReader::new(File::open(file)?))?;
+    let out_name = output.unwrap_or_else(|| file.to_string());
+    write_to_file(&bytecode, &out_name)?;
+
+    if version == BYTECODE_VERSION {
+        println!(
+            "{} is already bytecode version {}, wrote it to {}",
+            file, version, out_name
+        );
+    } else {
+        println!(
+            "Migrated {} from bytecode version {} to {}, wrote it to {}",
+            file, version, BYTECODE_VERSION, out_name
+        );
+    }
+    Ok(())
+}
+
+/// Compile the program in a .rst file, printing any warnings to stderr, or failing on the first one if strict is
+/// set. Returns the line each instruction is from along with the bytecode.
+fn compile_file(file: &str, type_check: bool, strict: bool) -> Result<(Vec<ByteCode>, LineTable)> {
+    let code = read_source(file)?;
+    if strict {
+        return compile_strict(&code, &HashSet::new())
+            .map_err(|err| Error::msg(format!("\n{}", err)));
+    }
+
+    match compile_with_lines(&code, type_check, &HashSet::new()) {
+        Ok((bytecode, warnings, lines)) => {
+            for warning in warnings.iter() {
+                eprintln!("{}", warning);
+            }
+            Ok((bytecode, lines))
+        }
+        Err(err) => Err(Error::msg(format!("\n{}", err))),
     }
+}
+
+/// Read the program in a .rst file.
+fn read_source(file: &str) -> Result<String> {
+    if !Path::new(file).exists() {
+        return Err(VmError::FileDoesNotExist(file.to_string()).into());
+    }
+
+    if Path::new(file).extension().is_none_or(|ext| ext != RST) {
+        return Err(VmError::NotRstFile(file.to_string()).into());
+    }
+
+    Ok(std::fs::read_to_string(file)?)
+}
+
+/// Run each #[test] fn of the program in a .rst file, see compile_tests, printing whether it passed. Fails if any of
+/// them did.
+fn run_tests(file: &str, args: &RunArgs) -> Result<()> {
+    let (tests, warnings) = compile_tests(&read_source(file)?, &HashSet::new())
+        .map_err(|err| Error::msg(format!("\n{}", err)))?;
+    for warning in warnings.iter() {
+        eprintln!("{}", warning);
+    }
+
+    let total = tests.len();
+    let plural = if total == 1 { "" } else { "s" };
+    println!("running {} test{}", total, plural);
+    let mut failed = vec![];
+    for test in tests {
+        match run_bytecode(test.bytecode, Some((file, test.lines)), args) {
+            Ok(()) => println!("test {} ... ok", test.name),
+            Err(err) => {
+                let err = match error_code(&err) {
+                    Some(_) => format_runtime_error(&err),
+                    None => err.to_string(),
+                };
+                println!("test {} ... FAILED\n{}", test.name, err);
+                failed.push(test.name);
+            }
+        }
+    }
+
+    println!(
+        "\ntest result: {}. {} passed; {} failed",
+        if failed.is_empty() { "ok" } else { "FAILED" },
+        total - failed.len(),
+        failed.len()
+    );
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::msg(format!("tests failed: {}", failed.join(", "))))
+    }
+}
+
+// program.rst compiles to program.o2 in the current directory, like oxidate
+fn default_out_name(file: &str) -> String {
+    let stem = Path::new(file)
+        .file_stem()
+        .expect("File exists")
+        .to_string_lossy();
+    format!("{}.{}", stem, O2)
+}
 
-    // Deserialize the program
-    let mut file = std::fs::File::open(file)?;
-    let bytecode_vec = read_bytecode(&mut file)?;
+/// Run the bytecode, with the file and lines it was compiled from if it was compiled from source.
+fn run_bytecode(
+    bytecode: Vec<ByteCode>,
+    source: Option<(&str, LineTable)>,
+    args: &RunArgs,
+) -> Result<()> {
+    verify(&bytecode)?;
 
-    let mut rt = Runtime::new(bytecode_vec);
+    let mut rt = Runtime::new(bytecode);
 
     if let Some(quantum) = args.quantum {
         rt.set_time_quantum(Duration::from_millis(quantum));
     }
 
+    match (args.quantum_instrs, args.seed) {
+        (Some(quantum), Some(seed)) => rt.set_scheduler(SchedulerPolicy::random(quantum, seed)),
+        (Some(quantum), None) => rt.set_scheduler(SchedulerPolicy::round_robin(quantum)),
+        _ => (),
+    }
+
+    if let Some(max_zombies) = args.max_zombies {
+        rt.set_max_zombies(max_zombies);
+    }
+
     if let Some(gc_interval) = args.gc_interval {
         rt.set_gc_interval(Duration::from_millis(gc_interval));
     }
 
+    match args.gc_threshold {
+        Some(0) => rt.set_gc_threshold(None),
+        Some(threshold) => rt.set_gc_threshold(Some(threshold)),
+        None => (),
+    }
+
+    if let Some(budget) = args.gc_incremental {
+        rt.set_gc_incremental(budget);
+    }
+
+    if let Some(max_heap) = args.max_heap {
+        rt.set_max_heap(max_heap);
+    }
+
+    if args.eager_reset {
+        rt.set_eager_reset();
+    }
+
+    if let Some(max_call_depth) = args.max_call_depth {
+        rt.set_max_call_depth(max_call_depth);
+    }
+
+    if let Some(max_operand_stack) = args.max_operand_stack {
+        rt.set_max_operand_stack(max_operand_stack);
+    }
+
     if args.debug {
         rt.set_debug_mode();
     }
 
-    let rt = run(rt)?;
+    if args.profile_opcode {
+        rt.set_profile_opcodes();
+    }
+
+    if args.allow_net {
+        rt.set_allow_net();
+    }
+
+    if args.allow_run {
+        rt.set_allow_run();
+    }
+
+    rt.set_on_main_exit(args.on_main_exit.into());
+    rt.set_main_args(args.program_args.iter().map(String::as_str));
+
+    match args.trace.as_deref() {
+        Some("-") => rt.set_trace(WriteSink::stderr()),
+        Some(file) => rt.set_trace(WriteSink::new(BufWriter::new(File::create(file)?))),
+        None => (),
+    }
 
-    // Print last value on op stack if there (result of program)
-    let top = rt.current_thread.operand_stack.last();
+    // errors from the program show the source line of each frame of their backtrace
+    let lines = source.as_ref().map(|(_, lines)| lines.clone());
+    let program_error = |err: Error| match error_backtrace(&err) {
+        Some(_) => Error::msg(format_runtime_error_with_lines(&err, lines.as_ref())),
+        None => err,
+    };
+
+    let rt = if args.step {
+        let mut debugger = Debugger::new();
+        if let Some((file, lines)) = source {
+            debugger.set_source(file, lines);
+        }
+
+        let stdin = std::io::stdin();
+        match debugger
+            .run(rt, stdin.lock(), std::io::stdout())
+            .map_err(program_error)?
+        {
+            Some(rt) => rt,
+            None => return Ok(()),
+        }
+    } else if let Some(limit) = args.explain_run {
+        let Some((file, lines)) = source else {
+            return Err(Error::msg(
+                "--explain-run needs a .rst file, a .o2 file has no source lines to explain",
+            ));
+        };
+
+        let code = std::fs::read_to_string(file)?;
+        Explainer::new(&code, lines, limit)
+            .run(rt, std::io::stdout())
+            .map_err(program_error)?
+    } else if let Some(range) = &args.show_stack {
+        let pcs = range.pcs(&rt.instrs)?;
+        StackView::new(pcs, args.stack_format)
+            .run(rt, std::io::stderr())
+            .map_err(program_error)?
+    } else {
+        run(rt).map_err(program_error)?
+    };
+
+    if let Some(profile) = &rt.profile {
+        eprint!("{}", profile);
+    }
+
+    if !rt.cancelled_threads.is_empty() {
+        let tids: Vec<_> = rt
+            .cancelled_threads
+            .iter()
+            .map(ThreadID::to_string)
+            .collect();
+        eprintln!(
+            "[Warning] - threads {} were still running when the main thread finished, and were stopped. Join them, or run with --on-main-exit join-all to wait for them",
+            tids.join(", ")
+        );
+    }
 
-    if let Some(val) = top {
-        builtin::println_impl(val);
+    // Print the value of the last expression of the program, like the REPL does. A program that ends with a
+    // statement leaves Unit, so there is nothing to show
+    let result = rt.result().filter(|val| !args.quiet && *val != Value::Unit);
+    if let Some(val) = result {
+        builtin::println_impl(&val);
     }
 
     Ok(())
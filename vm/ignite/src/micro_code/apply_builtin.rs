@@ -1,14 +1,32 @@
 use anyhow::Result;
-use bytecode::{builtin, Value};
+use bytecode::{builtin, Socket, Value, Variant};
 
 use crate::{Runtime, VmError};
 
+use super::call_closure;
+
 #[inline]
 pub fn apply_builtin(mut rt: Runtime, sym: &str, args: Vec<Value>) -> Result<Runtime> {
+    if builtin::NET_SYMS.contains(&sym) && !rt.allow_net {
+        return Err(VmError::NetNotAllowed {
+            sym: sym.to_string(),
+        }
+        .into());
+    }
+
+    if sym == builtin::RUN_COMMAND_SYM && !rt.allow_run {
+        return Err(VmError::RunNotAllowed {
+            sym: sym.to_string(),
+        }
+        .into());
+    }
+
     match sym {
         builtin::READ_LINE_SYM => {
             let input = builtin::read_line_impl()?;
-            rt.current_thread.operand_stack.push(Value::String(input));
+            rt.current_thread
+                .operand_stack
+                .push(Value::String(input.into()));
         }
         builtin::PRINT_SYM => {
             for arg in args {
@@ -32,6 +50,375 @@ pub fn apply_builtin(mut rt: Runtime, sym: &str, args: Vec<Value>) -> Result<Run
             let len = builtin::string_len_impl(s)?;
             rt.current_thread.operand_stack.push(Value::Int(len as i64));
         }
+        builtin::FORMAT_SYM => {
+            let [fmt, args] = args.as_slice() else {
+                return Err(VmError::InsufficientArguments {
+                    expected: 2,
+                    got: args.len(),
+                }
+                .into());
+            };
+
+            let s = builtin::format_impl(fmt, args)?;
+            rt.current_thread.operand_stack.push(s);
+        }
+        builtin::SUBSTRING_SYM => {
+            let [s, start, len] = args.as_slice() else {
+                return Err(VmError::InsufficientArguments {
+                    expected: 3,
+                    got: args.len(),
+                }
+                .into());
+            };
+
+            let val = builtin::substring_impl(s, start, len)?;
+            rt.current_thread.operand_stack.push(val);
+        }
+        builtin::SPLIT_SYM => {
+            let [s, sep] = args.as_slice() else {
+                return Err(VmError::InsufficientArguments {
+                    expected: 2,
+                    got: args.len(),
+                }
+                .into());
+            };
+
+            let val = builtin::split_impl(s, sep)?;
+            rt.current_thread.operand_stack.push(val);
+        }
+        builtin::FIND_SYM => {
+            let [s, pat] = args.as_slice() else {
+                return Err(VmError::InsufficientArguments {
+                    expected: 2,
+                    got: args.len(),
+                }
+                .into());
+            };
+
+            let val = builtin::find_impl(s, pat)?;
+            rt.current_thread.operand_stack.push(val);
+        }
+        builtin::CONTAINS_SYM => {
+            let [s, pat] = args.as_slice() else {
+                return Err(VmError::InsufficientArguments {
+                    expected: 2,
+                    got: args.len(),
+                }
+                .into());
+            };
+
+            let val = builtin::contains_impl(s, pat)?;
+            rt.current_thread.operand_stack.push(val);
+        }
+        builtin::REPLACE_SYM => {
+            let [s, from, to] = args.as_slice() else {
+                return Err(VmError::InsufficientArguments {
+                    expected: 3,
+                    got: args.len(),
+                }
+                .into());
+            };
+
+            let val = builtin::replace_impl(s, from, to)?;
+            rt.current_thread.operand_stack.push(val);
+        }
+        builtin::TO_UPPER_SYM => {
+            let s = args.first().ok_or(VmError::InsufficientArguments {
+                expected: 1,
+                got: args.len(),
+            })?;
+
+            let s = builtin::to_upper_impl(s)?;
+            rt.current_thread.operand_stack.push(s);
+        }
+        builtin::TO_LOWER_SYM => {
+            let s = args.first().ok_or(VmError::InsufficientArguments {
+                expected: 1,
+                got: args.len(),
+            })?;
+
+            let s = builtin::to_lower_impl(s)?;
+            rt.current_thread.operand_stack.push(s);
+        }
+        builtin::TRIM_SYM => {
+            let s = args.first().ok_or(VmError::InsufficientArguments {
+                expected: 1,
+                got: args.len(),
+            })?;
+
+            let s = builtin::trim_impl(s)?;
+            rt.current_thread.operand_stack.push(s);
+        }
+        builtin::CHARS_SYM => {
+            let s = args.first().ok_or(VmError::InsufficientArguments {
+                expected: 1,
+                got: args.len(),
+            })?;
+
+            let chars = builtin::chars_impl(s)?;
+            rt.current_thread.operand_stack.push(chars);
+        }
+        builtin::CHAR_AT_SYM => {
+            let [s, idx] = args.as_slice() else {
+                return Err(VmError::InsufficientArguments {
+                    expected: 2,
+                    got: args.len(),
+                }
+                .into());
+            };
+
+            let c = builtin::char_at_impl(s, idx)?;
+            rt.current_thread.operand_stack.push(c);
+        }
+        builtin::SLICE_LEN_SYM => {
+            let xs = args.first().ok_or(VmError::InsufficientArguments {
+                expected: 1,
+                got: args.len(),
+            })?;
+
+            let len = builtin::slice_len_impl(xs)?;
+            rt.current_thread.operand_stack.push(Value::Int(len as i64));
+        }
+        builtin::BYTES_SYM => {
+            let len = args.first().ok_or(VmError::InsufficientArguments {
+                expected: 1,
+                got: args.len(),
+            })?;
+
+            let bytes = builtin::bytes_impl(len)?;
+            rt.current_thread.operand_stack.push(bytes);
+        }
+        builtin::BYTES_LEN_SYM => {
+            let b = args.first().ok_or(VmError::InsufficientArguments {
+                expected: 1,
+                got: args.len(),
+            })?;
+
+            let len = builtin::bytes_len_impl(b)?;
+            rt.current_thread.operand_stack.push(Value::Int(len as i64));
+        }
+        builtin::GET_BYTE_SYM => {
+            let [b, idx] = args.as_slice() else {
+                return Err(VmError::InsufficientArguments {
+                    expected: 2,
+                    got: args.len(),
+                }
+                .into());
+            };
+
+            let byte = builtin::get_byte_impl(b, idx)?;
+            rt.current_thread.operand_stack.push(byte);
+        }
+        builtin::SET_BYTE_SYM => {
+            let [b, idx, byte] = args.as_slice() else {
+                return Err(VmError::InsufficientArguments {
+                    expected: 3,
+                    got: args.len(),
+                }
+                .into());
+            };
+
+            builtin::set_byte_impl(b, idx, byte)?;
+        }
+        builtin::BYTES_FROM_STRING_SYM => {
+            let s = args.first().ok_or(VmError::InsufficientArguments {
+                expected: 1,
+                got: args.len(),
+            })?;
+
+            let bytes = builtin::bytes_from_string_impl(s)?;
+            rt.current_thread.operand_stack.push(bytes);
+        }
+        builtin::STRING_FROM_BYTES_SYM => {
+            let [b, encoding] = args.as_slice() else {
+                return Err(VmError::InsufficientArguments {
+                    expected: 2,
+                    got: args.len(),
+                }
+                .into());
+            };
+
+            let s = builtin::string_from_bytes_impl(b, encoding)?;
+            rt.current_thread.operand_stack.push(s);
+        }
+        builtin::READ_BYTES_SYM => {
+            let path = args.first().ok_or(VmError::InsufficientArguments {
+                expected: 1,
+                got: args.len(),
+            })?;
+
+            let bytes = builtin::read_bytes_impl(path)?;
+            rt.current_thread.operand_stack.push(bytes);
+        }
+        builtin::WRITE_BYTES_SYM => {
+            let [path, b] = args.as_slice() else {
+                return Err(VmError::InsufficientArguments {
+                    expected: 2,
+                    got: args.len(),
+                }
+                .into());
+            };
+
+            let res = builtin::write_bytes_impl(path, b)?;
+            rt.current_thread.operand_stack.push(res);
+        }
+        builtin::DETACH_SYM => {
+            let tid = args.first().ok_or(VmError::InsufficientArguments {
+                expected: 1,
+                got: args.len(),
+            })?;
+
+            rt.detach_thread(tid.clone().try_into()?)?;
+        }
+        builtin::SLEEP_SYM => {
+            let ms = args.first().ok_or(VmError::InsufficientArguments {
+                expected: 1,
+                got: args.len(),
+            })?;
+
+            // The thread gets its unit before it sleeps, since another one runs when this returns
+            let duration = builtin::sleep_duration(ms)?;
+            rt.current_thread.operand_stack.push(Value::Unit);
+            rt.sleep_current_thread(duration)?;
+        }
+        builtin::INSPECT_SYM => {
+            let value = args.first().ok_or(VmError::InsufficientArguments {
+                expected: 1,
+                got: args.len(),
+            })?;
+
+            let inspected = builtin::inspect_impl(value, builtin::DEFAULT_INSPECT_DEPTH);
+            rt.current_thread.operand_stack.push(inspected.into());
+        }
+        builtin::INSPECT_DEPTH_SYM => {
+            let [value, max_depth] = args.as_slice() else {
+                return Err(VmError::InsufficientArguments {
+                    expected: 2,
+                    got: args.len(),
+                }
+                .into());
+            };
+
+            let inspected = builtin::inspect_depth_impl(value, max_depth)?;
+            rt.current_thread.operand_stack.push(inspected);
+        }
+        builtin::HASH_SYM => {
+            let value = args.first().ok_or(VmError::InsufficientArguments {
+                expected: 1,
+                got: args.len(),
+            })?;
+
+            let hash = builtin::hash_impl(value)?;
+            rt.current_thread.operand_stack.push(Value::Int(hash));
+        }
+        builtin::CRC32_SYM => {
+            let b = args.first().ok_or(VmError::InsufficientArguments {
+                expected: 1,
+                got: args.len(),
+            })?;
+
+            let crc = builtin::crc32_impl(b)?;
+            rt.current_thread.operand_stack.push(Value::Int(crc));
+        }
+        builtin::SHA256_SYM => {
+            let b = args.first().ok_or(VmError::InsufficientArguments {
+                expected: 1,
+                got: args.len(),
+            })?;
+
+            let digest = builtin::sha256_impl(b)?;
+            rt.current_thread.operand_stack.push(digest);
+        }
+        builtin::MAP_SYM => {
+            let [xs, f] = args.as_slice() else {
+                return Err(VmError::InsufficientArguments {
+                    expected: 2,
+                    got: args.len(),
+                }
+                .into());
+            };
+
+            let (new_rt, mapped) = builtin::map_impl(rt, xs, f, |rt, f, args| {
+                call_closure(rt, builtin::MAP_SYM, f, args)
+            })?;
+            rt = new_rt;
+            rt.current_thread.operand_stack.push(mapped);
+        }
+        builtin::FILTER_SYM => {
+            let [xs, f] = args.as_slice() else {
+                return Err(VmError::InsufficientArguments {
+                    expected: 2,
+                    got: args.len(),
+                }
+                .into());
+            };
+
+            let (new_rt, kept) = builtin::filter_impl(rt, xs, f, |rt, f, args| {
+                call_closure(rt, builtin::FILTER_SYM, f, args)
+            })?;
+            rt = new_rt;
+            rt.current_thread.operand_stack.push(kept);
+        }
+        builtin::FOLD_SYM => {
+            let [xs, init, f] = args.as_slice() else {
+                return Err(VmError::InsufficientArguments {
+                    expected: 3,
+                    got: args.len(),
+                }
+                .into());
+            };
+
+            let (new_rt, acc) = builtin::fold_impl(rt, xs, init.clone(), f, |rt, f, args| {
+                call_closure(rt, builtin::FOLD_SYM, f, args)
+            })?;
+            rt = new_rt;
+            rt.current_thread.operand_stack.push(acc);
+        }
+        builtin::SORT_BY_SYM => {
+            let [xs, cmp] = args.as_slice() else {
+                return Err(VmError::InsufficientArguments {
+                    expected: 2,
+                    got: args.len(),
+                }
+                .into());
+            };
+
+            let (new_rt, result) = builtin::sort_by_impl(rt, xs, cmp, |rt, cmp, args| {
+                call_closure(rt, builtin::SORT_BY_SYM, cmp, args)
+            })?;
+            rt = new_rt;
+            rt.current_thread.operand_stack.push(result);
+        }
+        builtin::MIN_BY_SYM => {
+            let [xs, cmp] = args.as_slice() else {
+                return Err(VmError::InsufficientArguments {
+                    expected: 2,
+                    got: args.len(),
+                }
+                .into());
+            };
+
+            let (new_rt, result) = builtin::min_by_impl(rt, xs, cmp, |rt, cmp, args| {
+                call_closure(rt, builtin::MIN_BY_SYM, cmp, args)
+            })?;
+            rt = new_rt;
+            rt.current_thread.operand_stack.push(result);
+        }
+        builtin::MAX_BY_SYM => {
+            let [xs, cmp] = args.as_slice() else {
+                return Err(VmError::InsufficientArguments {
+                    expected: 2,
+                    got: args.len(),
+                }
+                .into());
+            };
+
+            let (new_rt, result) = builtin::max_by_impl(rt, xs, cmp, |rt, cmp, args| {
+                call_closure(rt, builtin::MAX_BY_SYM, cmp, args)
+            })?;
+            rt = new_rt;
+            rt.current_thread.operand_stack.push(result);
+        }
         builtin::MIN_SYM => {
             let v1 = args.first().ok_or(VmError::InsufficientArguments {
                 expected: 2,
@@ -58,6 +445,42 @@ pub fn apply_builtin(mut rt: Runtime, sym: &str, args: Vec<Value>) -> Result<Run
             let max = builtin::max_impl(v1, v2)?;
             rt.current_thread.operand_stack.push(max);
         }
+        builtin::DIV_EUCLID_SYM => {
+            let [a, b] = args.as_slice() else {
+                return Err(VmError::InsufficientArguments {
+                    expected: 2,
+                    got: args.len(),
+                }
+                .into());
+            };
+
+            let res = builtin::div_euclid_impl(a, b)?;
+            rt.current_thread.operand_stack.push(res);
+        }
+        builtin::REM_EUCLID_SYM => {
+            let [a, b] = args.as_slice() else {
+                return Err(VmError::InsufficientArguments {
+                    expected: 2,
+                    got: args.len(),
+                }
+                .into());
+            };
+
+            let res = builtin::rem_euclid_impl(a, b)?;
+            rt.current_thread.operand_stack.push(res);
+        }
+        builtin::DIVMOD_SYM => {
+            let [a, b] = args.as_slice() else {
+                return Err(VmError::InsufficientArguments {
+                    expected: 2,
+                    got: args.len(),
+                }
+                .into());
+            };
+
+            let res = builtin::divmod_impl(a, b)?;
+            rt.current_thread.operand_stack.push(res);
+        }
         builtin::ABS_SYM => {
             let x = args.first().ok_or(VmError::InsufficientArguments {
                 expected: 1,
@@ -143,6 +566,27 @@ pub fn apply_builtin(mut rt: Runtime, sym: &str, args: Vec<Value>) -> Result<Run
             let atoi = builtin::atoi_impl(s)?;
             rt.current_thread.operand_stack.push(atoi);
         }
+        builtin::FTOA_SYM => {
+            let [f, precision] = args.as_slice() else {
+                return Err(VmError::InsufficientArguments {
+                    expected: 2,
+                    got: args.len(),
+                }
+                .into());
+            };
+
+            let ftoa = builtin::ftoa_impl(f, precision)?;
+            rt.current_thread.operand_stack.push(ftoa);
+        }
+        builtin::ATOF_SYM => {
+            let s = args.first().ok_or(VmError::InsufficientArguments {
+                expected: 1,
+                got: args.len(),
+            })?;
+
+            let atof = builtin::atof_impl(s)?;
+            rt.current_thread.operand_stack.push(atof);
+        }
         builtin::FLOAT_TO_INT_SYM => {
             let x = args.first().ok_or(VmError::InsufficientArguments {
                 expected: 1,
@@ -177,6 +621,331 @@ pub fn apply_builtin(mut rt: Runtime, sym: &str, args: Vec<Value>) -> Result<Run
 
             builtin::sem_set_impl(sem, val)?;
         }
+        builtin::MUTEX_SYM => {
+            let mutex = builtin::mutex_impl();
+            rt.current_thread.operand_stack.push(mutex);
+        }
+        builtin::PVEC_SYM => {
+            let v = builtin::pvec_impl();
+            rt.current_thread.operand_stack.push(v);
+        }
+        builtin::PMAP_SYM => {
+            let m = builtin::pmap_impl();
+            rt.current_thread.operand_stack.push(m);
+        }
+        builtin::PERSIST_PUSH_SYM => {
+            let [v, x] = args.as_slice() else {
+                return Err(VmError::InsufficientArguments {
+                    expected: 2,
+                    got: args.len(),
+                }
+                .into());
+            };
+
+            let result = builtin::persist_push_impl(v, x)?;
+            rt.current_thread.operand_stack.push(result);
+        }
+        builtin::PERSIST_SET_SYM => {
+            let [v, idx, x] = args.as_slice() else {
+                return Err(VmError::InsufficientArguments {
+                    expected: 3,
+                    got: args.len(),
+                }
+                .into());
+            };
+
+            let result = builtin::persist_set_impl(v, idx, x)?;
+            rt.current_thread.operand_stack.push(result);
+        }
+        builtin::PERSIST_INSERT_SYM => {
+            let [m, key, val] = args.as_slice() else {
+                return Err(VmError::InsufficientArguments {
+                    expected: 3,
+                    got: args.len(),
+                }
+                .into());
+            };
+
+            let result = builtin::persist_insert_impl(m, key, val)?;
+            rt.current_thread.operand_stack.push(result);
+        }
+        builtin::PERSIST_REMOVE_SYM => {
+            let [m, key] = args.as_slice() else {
+                return Err(VmError::InsufficientArguments {
+                    expected: 2,
+                    got: args.len(),
+                }
+                .into());
+            };
+
+            let result = builtin::persist_remove_impl(m, key)?;
+            rt.current_thread.operand_stack.push(result);
+        }
+        builtin::PERSIST_GET_SYM => {
+            let [c, key] = args.as_slice() else {
+                return Err(VmError::InsufficientArguments {
+                    expected: 2,
+                    got: args.len(),
+                }
+                .into());
+            };
+
+            let result = builtin::persist_get_impl(c, key)?;
+            rt.current_thread.operand_stack.push(result);
+        }
+        builtin::PERSIST_CONTAINS_SYM => {
+            let [m, key] = args.as_slice() else {
+                return Err(VmError::InsufficientArguments {
+                    expected: 2,
+                    got: args.len(),
+                }
+                .into());
+            };
+
+            let result = builtin::persist_contains_impl(m, key)?;
+            rt.current_thread.operand_stack.push(result);
+        }
+        builtin::PERSIST_LEN_SYM => {
+            let [c] = args.as_slice() else {
+                return Err(VmError::InsufficientArguments {
+                    expected: 1,
+                    got: args.len(),
+                }
+                .into());
+            };
+
+            let result = builtin::persist_len_impl(c)?;
+            rt.current_thread.operand_stack.push(result);
+        }
+        builtin::MAP_NEW_SYM => {
+            let m = builtin::map_new_impl();
+            rt.current_thread.operand_stack.push(m);
+        }
+        builtin::MAP_INSERT_SYM => {
+            let [m, key, val] = args.as_slice() else {
+                return Err(VmError::InsufficientArguments {
+                    expected: 3,
+                    got: args.len(),
+                }
+                .into());
+            };
+
+            let result = builtin::map_insert_impl(m, key, val)?;
+            rt.current_thread.operand_stack.push(result);
+        }
+        builtin::MAP_GET_SYM => {
+            let [m, key] = args.as_slice() else {
+                return Err(VmError::InsufficientArguments {
+                    expected: 2,
+                    got: args.len(),
+                }
+                .into());
+            };
+
+            let result = builtin::map_get_impl(m, key)?;
+            rt.current_thread.operand_stack.push(result);
+        }
+        builtin::MAP_REMOVE_SYM => {
+            let [m, key] = args.as_slice() else {
+                return Err(VmError::InsufficientArguments {
+                    expected: 2,
+                    got: args.len(),
+                }
+                .into());
+            };
+
+            let result = builtin::map_remove_impl(m, key)?;
+            rt.current_thread.operand_stack.push(result);
+        }
+        builtin::MAP_LEN_SYM => {
+            let [m] = args.as_slice() else {
+                return Err(VmError::InsufficientArguments {
+                    expected: 1,
+                    got: args.len(),
+                }
+                .into());
+            };
+
+            let result = builtin::map_len_impl(m)?;
+            rt.current_thread.operand_stack.push(result);
+        }
+        builtin::MAP_KEYS_SYM => {
+            let [m] = args.as_slice() else {
+                return Err(VmError::InsufficientArguments {
+                    expected: 1,
+                    got: args.len(),
+                }
+                .into());
+            };
+
+            let result = builtin::map_keys_impl(m)?;
+            rt.current_thread.operand_stack.push(result);
+        }
+        builtin::CHAN_SYM => {
+            let ch = builtin::chan_impl();
+            rt.current_thread.operand_stack.push(ch);
+        }
+        builtin::TRY_RECV_SYM => {
+            let c = args.first().ok_or(VmError::InsufficientArguments {
+                expected: 1,
+                got: args.len(),
+            })?;
+
+            let res = builtin::try_recv_impl(c)?;
+            rt.current_thread.operand_stack.push(res);
+        }
+        builtin::TCP_CONNECT_SYM | builtin::TCP_LISTEN_SYM | builtin::UDP_BIND_SYM => {
+            let [addr] = args.as_slice() else {
+                return Err(VmError::InsufficientArguments {
+                    expected: 1,
+                    got: args.len(),
+                }
+                .into());
+            };
+
+            let addr: String = addr.clone().try_into()?;
+            let kind = match sym {
+                builtin::TCP_CONNECT_SYM => builtin::tcp_connect_impl(&addr),
+                builtin::TCP_LISTEN_SYM => builtin::tcp_listen_impl(&addr),
+                _ => builtin::udp_bind_impl(&addr),
+            };
+            let res = builtin::io_result(kind)?.map(Socket::new);
+            rt.current_thread
+                .operand_stack
+                .push(Variant::from_result(res).into());
+        }
+        builtin::TCP_ACCEPT_SYM => {
+            let sock = socket_arg(&args)?;
+            let res = builtin::io_result(sock.with(builtin::tcp_accept_impl))?.map(Socket::new);
+            rt.current_thread
+                .operand_stack
+                .push(Variant::from_result(res).into());
+        }
+        builtin::TCP_RECV_SYM | builtin::UDP_RECV_SYM => {
+            let sock = socket_arg(&args)?;
+            let data = if sym == builtin::TCP_RECV_SYM {
+                sock.with(builtin::tcp_recv_impl)
+            } else {
+                sock.with(builtin::udp_recv_impl)
+            };
+            let res = builtin::io_result(data)?;
+            rt.current_thread
+                .operand_stack
+                .push(Variant::from_result(res).into());
+        }
+        builtin::TCP_SEND_SYM => {
+            let [sock, data] = args.as_slice() else {
+                return Err(VmError::InsufficientArguments {
+                    expected: 2,
+                    got: args.len(),
+                }
+                .into());
+            };
+
+            let sock: Socket = sock.clone().try_into()?;
+            let data: String = data.clone().try_into()?;
+            let sent = sock.with(|conn| builtin::tcp_send_impl(conn, &data));
+            let res = builtin::io_result(sent)?.map(|sent| sent as i64);
+            rt.current_thread
+                .operand_stack
+                .push(Variant::from_result(res).into());
+        }
+        builtin::UDP_SEND_TO_SYM => {
+            let [sock, addr, data] = args.as_slice() else {
+                return Err(VmError::InsufficientArguments {
+                    expected: 3,
+                    got: args.len(),
+                }
+                .into());
+            };
+
+            let sock: Socket = sock.clone().try_into()?;
+            let addr: String = addr.clone().try_into()?;
+            let data: String = data.clone().try_into()?;
+            let sent = sock.with(|udp| builtin::udp_send_to_impl(udp, &addr, &data));
+            let res = builtin::io_result(sent)?.map(|sent| sent as i64);
+            rt.current_thread
+                .operand_stack
+                .push(Variant::from_result(res).into());
+        }
+        builtin::CLOSE_SYM => {
+            let sock = args.first().ok_or(VmError::InsufficientArguments {
+                expected: 1,
+                got: args.len(),
+            })?;
+
+            builtin::close_impl(sock)?;
+        }
+        builtin::HTTP_GET_SYM => {
+            let url = args.first().ok_or(VmError::InsufficientArguments {
+                expected: 1,
+                got: args.len(),
+            })?;
+
+            let url: String = url.clone().try_into()?;
+            let res = builtin::io_result(builtin::http_get_impl(&url))?;
+            rt.current_thread
+                .operand_stack
+                .push(Variant::from_result(res).into());
+        }
+        builtin::HTTP_POST_SYM => {
+            let [url, body] = args.as_slice() else {
+                return Err(VmError::InsufficientArguments {
+                    expected: 2,
+                    got: args.len(),
+                }
+                .into());
+            };
+
+            let url: String = url.clone().try_into()?;
+            let body: String = body.clone().try_into()?;
+            let res = builtin::io_result(builtin::http_post_impl(&url, &body))?;
+            rt.current_thread
+                .operand_stack
+                .push(Variant::from_result(res).into());
+        }
+        builtin::ARGS_SYM => {
+            let val = builtin::args_impl(&rt.main_args);
+            rt.current_thread.operand_stack.push(val);
+        }
+        builtin::ENV_VAR_SYM => {
+            let name = args.first().ok_or(VmError::InsufficientArguments {
+                expected: 1,
+                got: args.len(),
+            })?;
+
+            let val = builtin::env_var_impl(name)?;
+            rt.current_thread.operand_stack.push(val);
+        }
+        builtin::ASSERT_SYM => {
+            let [cond, msg] = args.as_slice() else {
+                return Err(VmError::InsufficientArguments {
+                    expected: 2,
+                    got: args.len(),
+                }
+                .into());
+            };
+
+            builtin::assert_impl(cond, msg)?;
+        }
+        builtin::RUN_COMMAND_SYM => {
+            let [cmd, cmd_args, timeout] = args.as_slice() else {
+                return Err(VmError::InsufficientArguments {
+                    expected: 3,
+                    got: args.len(),
+                }
+                .into());
+            };
+
+            let cmd: String = cmd.clone().try_into()?;
+            let cmd_args = builtin::command_args(cmd_args)?;
+            let timeout = builtin::command_timeout(timeout)?;
+            let output = builtin::run_command_impl(&cmd, &cmd_args, timeout);
+            let res = builtin::io_result(output)?;
+            rt.current_thread
+                .operand_stack
+                .push(Variant::from_result(res).into());
+        }
         _ => {
             return Err(VmError::UnknownBuiltin {
                 sym: sym.to_string(),
@@ -188,11 +957,27 @@ pub fn apply_builtin(mut rt: Runtime, sym: &str, args: Vec<Value>) -> Result<Run
     Ok(rt)
 }
 
+// The socket a net builtin that takes only a socket was called with
+fn socket_arg(args: &[Value]) -> Result<Socket> {
+    let [sock] = args else {
+        return Err(VmError::InsufficientArguments {
+            expected: 1,
+            got: args.len(),
+        }
+        .into());
+    };
+
+    Ok(sock.clone().try_into()?)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use anyhow::Ok;
-    use bytecode::{builtin::*, type_of, Semaphore};
+    use bytecode::{builtin::*, type_of, Semaphore, SocketKind, Tuple, Variant};
+    use diagnostics::Code;
+
+    use crate::error_code;
 
     #[test]
     fn test_apply_builtin() -> Result<()> {
@@ -201,24 +986,132 @@ mod tests {
 
         // Stdout
         let sym = PRINT_SYM;
-        let args = vec![Value::String(hello_world.clone())];
+        let args = vec![Value::String(hello_world.as_str().into())];
         println!("Expect to see 'Hello, world!':");
         rt = apply_builtin(rt, sym, args)?;
         println!();
 
         let sym = PRINTLN_SYM;
-        let args = vec![Value::String(hello_world.clone())];
+        let args = vec![Value::String(hello_world.as_str().into())];
         println!("Expect to see 'Hello, world!':");
         rt = apply_builtin(rt, sym, args)?;
 
         let sym = STRING_LEN_SYM;
-        let args = vec![Value::String(hello_world.clone())];
+        let args = vec![Value::String(hello_world.as_str().into())];
         rt = apply_builtin(rt, sym, args)?;
         assert_eq!(
             Value::Int(hello_world.clone().len() as i64),
             rt.current_thread.operand_stack.pop().unwrap()
         );
 
+        let sym = SUBSTRING_SYM;
+        let args = vec!["hello world".into(), Value::Int(6), Value::Int(5)];
+        rt = apply_builtin(rt, sym, args)?;
+        assert_eq!(
+            Value::String("world".into()),
+            rt.current_thread.operand_stack.pop().unwrap()
+        );
+
+        let sym = SPLIT_SYM;
+        let args = vec!["a,b,,c".into(), ",".into()];
+        rt = apply_builtin(rt, sym, args)?;
+        let Some(Value::Slice(parts)) = rt.current_thread.operand_stack.pop() else {
+            panic!("split should give a slice");
+        };
+        assert_eq!(
+            parts.to_vec(),
+            vec!["a".into(), "b".into(), "".into(), "c".into()]
+        );
+
+        let sym = FIND_SYM;
+        rt = apply_builtin(rt, sym, vec!["hello".into(), "llo".into()])?;
+        assert_eq!(
+            Value::Int(2),
+            rt.current_thread.operand_stack.pop().unwrap()
+        );
+        rt = apply_builtin(rt, sym, vec!["hello".into(), "x".into()])?;
+        assert_eq!(
+            Value::Int(-1),
+            rt.current_thread.operand_stack.pop().unwrap()
+        );
+
+        let sym = CONTAINS_SYM;
+        rt = apply_builtin(rt, sym, vec!["hello".into(), "ell".into()])?;
+        assert_eq!(
+            Value::Bool(true),
+            rt.current_thread.operand_stack.pop().unwrap()
+        );
+
+        let sym = REPLACE_SYM;
+        let args = vec!["aaa".into(), "aa".into(), "b".into()];
+        rt = apply_builtin(rt, sym, args)?;
+        assert_eq!(
+            Value::String("ba".into()),
+            rt.current_thread.operand_stack.pop().unwrap()
+        );
+
+        let sym = TO_UPPER_SYM;
+        rt = apply_builtin(rt, sym, vec!["Hi there".into()])?;
+        assert_eq!(
+            Value::String("HI THERE".into()),
+            rt.current_thread.operand_stack.pop().unwrap()
+        );
+
+        let sym = TO_LOWER_SYM;
+        rt = apply_builtin(rt, sym, vec!["Hi there".into()])?;
+        assert_eq!(
+            Value::String("hi there".into()),
+            rt.current_thread.operand_stack.pop().unwrap()
+        );
+
+        let sym = TRIM_SYM;
+        rt = apply_builtin(rt, sym, vec!["  hi\n".into()])?;
+        assert_eq!(
+            Value::String("hi".into()),
+            rt.current_thread.operand_stack.pop().unwrap()
+        );
+
+        let sym = CHARS_SYM;
+        rt = apply_builtin(rt, sym, vec!["hé".into()])?;
+        let Some(Value::Slice(chars)) = rt.current_thread.operand_stack.pop() else {
+            panic!("chars should give a slice");
+        };
+        assert_eq!(chars.to_vec(), vec![Value::Char('h'), Value::Char('é')]);
+
+        let sym = CHAR_AT_SYM;
+        rt = apply_builtin(rt, sym, vec!["hé".into(), Value::Int(1)])?;
+        assert_eq!(
+            rt.current_thread.operand_stack.pop(),
+            Some(Variant::some('é').into())
+        );
+        rt = apply_builtin(rt, sym, vec!["hé".into(), Value::Int(2)])?;
+        assert_eq!(
+            rt.current_thread.operand_stack.pop(),
+            Some(Variant::none().into())
+        );
+
+        // indexes outside the string or inside a character, and empty patterns, are errors with a code
+        let args = vec!["hello".into(), Value::Int(3), Value::Int(5)];
+        let Err(err) = apply_builtin(Runtime::default(), SUBSTRING_SYM, args) else {
+            panic!("substring past the end should fail");
+        };
+        assert_eq!(error_code(&err), Some(Code::R003));
+
+        let args = vec!["héllo".into(), Value::Int(0), Value::Int(2)];
+        let Err(err) = apply_builtin(Runtime::default(), SUBSTRING_SYM, args) else {
+            panic!("substring inside a character should fail");
+        };
+        assert_eq!(
+            err.to_string(),
+            "Byte index 2 is inside a character, not between two"
+        );
+
+        let Err(err) = apply_builtin(Runtime::default(), SPLIT_SYM, vec!["a".into(), "".into()])
+        else {
+            panic!("split on nothing should fail");
+        };
+        assert_eq!(error_code(&err), Some(Code::R007));
+
         // Conv
         let sym = INT_TO_FLOAT_SYM;
         let args = vec![Value::Int(42)];
@@ -237,26 +1130,67 @@ mod tests {
         assert_eq!(expected, actual);
 
         let sym = ATOI_SYM;
-        let args = vec![Value::String("42".to_string())];
+        let args = vec![Value::String("42".into())];
         rt = apply_builtin(rt, sym, args)?;
         assert_eq!(
-            Value::Int(42),
+            Value::from(Variant::ok(42)),
             rt.current_thread.operand_stack.pop().unwrap()
         );
 
-        let args: Vec<Value> = vec![Value::String("forty-two".to_string())];
-        let result = apply_builtin(rt, sym, args);
-        assert!(result.is_err());
+        // a string that isn't an int is an Err for the program to handle, not an error of the VM
+        let args: Vec<Value> = vec![Value::String("forty-two".into())];
+        rt = apply_builtin(rt, sym, args)?;
+        assert_eq!(
+            Value::from(Variant::err("invalid digit found in string")),
+            rt.current_thread.operand_stack.pop().unwrap()
+        );
 
         let mut rt = Runtime::default();
         let sym = ITOA_SYM;
         let args = vec![Value::Int(42)];
         rt = apply_builtin(rt, sym, args)?;
         assert_eq!(
-            Value::String("42".to_string()),
+            Value::String("42".into()),
+            rt.current_thread.operand_stack.pop().unwrap()
+        );
+
+        let sym = FTOA_SYM;
+        let args = vec![Value::Float(2.345), Value::Int(2)];
+        rt = apply_builtin(rt, sym, args)?;
+        assert_eq!(
+            Value::String("2.35".into()),
+            rt.current_thread.operand_stack.pop().unwrap()
+        );
+
+        let args = vec![Value::Float(-1.5), Value::Int(0)];
+        rt = apply_builtin(rt, sym, args)?;
+        assert_eq!(
+            Value::String("-2".into()),
+            rt.current_thread.operand_stack.pop().unwrap()
+        );
+
+        let sym = ATOF_SYM;
+        let args = vec![Value::String(" -4.2e1\n".into())];
+        rt = apply_builtin(rt, sym, args)?;
+        assert_eq!(
+            Value::from(Variant::ok(-42.0)),
             rt.current_thread.operand_stack.pop().unwrap()
         );
 
+        // like atoi, a string that isn't a float is an Err for the program to handle
+        rt = apply_builtin(rt, sym, vec!["4.2.1".into()])?;
+        assert_eq!(
+            Value::from(Variant::err("invalid float literal")),
+            rt.current_thread.operand_stack.pop().unwrap()
+        );
+
+        // a negative precision is an error with a code, not a panic
+        let args = vec![Value::Float(1.0), Value::Int(-1)];
+        let Err(err) = apply_builtin(Runtime::default(), FTOA_SYM, args) else {
+            panic!("ftoa with a negative precision should fail");
+        };
+        assert_eq!(error_code(&err), Some(Code::R007));
+
         // Math
         let sym = MIN_SYM;
         let args = vec![Value::Int(42), Value::Int(24)];
@@ -375,6 +1309,34 @@ mod tests {
         let result = apply_builtin(rt, sym, args);
         assert!(result.is_err());
 
+        // -7 / 2 is -3 and -7 % 2 is -1, the euclidean versions round down instead
+        let mut rt = Runtime::default();
+        let args = vec![Value::Int(-7), Value::Int(2)];
+        rt = apply_builtin(rt, DIV_EUCLID_SYM, args.clone())?;
+        rt = apply_builtin(rt, REM_EUCLID_SYM, args.clone())?;
+        rt = apply_builtin(rt, DIVMOD_SYM, args)?;
+        assert_eq!(
+            rt.current_thread.operand_stack,
+            vec![
+                Value::Int(-4),
+                Value::Int(1),
+                Tuple::new(vec![Value::Int(-3), Value::Int(-1)]).into()
+            ]
+        );
+
+        let args = vec![Value::Int(1), Value::Int(0)];
+        let Err(err) = apply_builtin(Runtime::default(), DIVMOD_SYM, args) else {
+            panic!("divmod by zero should fail");
+        };
+        assert_eq!(err.to_string(), "Division by zero in divmod");
+        assert_eq!(error_code(&err), Some(Code::R001));
+
+        let args = vec![Value::Int(i64::MIN), Value::Int(-1)];
+        let Err(err) = apply_builtin(Runtime::default(), DIV_EUCLID_SYM, args) else {
+            panic!("div_euclid of MIN_INT by -1 should overflow");
+        };
+        assert_eq!(error_code(&err), Some(Code::R002));
+
         let mut rt = Runtime::default();
         let sym = LOG_SYM;
         let args = vec![Value::Float(42.0)];
@@ -401,4 +1363,354 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_apply_builtin_process() -> Result<()> {
+        let mut rt = Runtime::default();
+        rt.set_main_args(["in.txt", "-v"]);
+        rt = apply_builtin(rt, ARGS_SYM, vec![])?;
+        let Some(Value::Slice(args)) = rt.current_thread.operand_stack.pop() else {
+            panic!("args should give a slice");
+        };
+        assert_eq!(args.to_vec(), vec!["in.txt".into(), "-v".into()]);
+
+        let path = std::env::var("PATH").unwrap_or_default();
+        rt = apply_builtin(rt, ENV_VAR_SYM, vec!["PATH".into()])?;
+        assert_eq!(rt.current_thread.operand_stack.pop(), Some(path.into()));
+
+        // unset variables are empty
+        rt = apply_builtin(rt, ENV_VAR_SYM, vec!["RUSTSCRIPT_UNSET_VAR".into()])?;
+        assert_eq!(rt.current_thread.operand_stack.pop(), Some("".into()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_builtin_assert() -> Result<()> {
+        let mut rt = Runtime::default();
+        rt = apply_builtin(rt, ASSERT_SYM, vec![true.into(), "fine".into()])?;
+        assert!(rt.current_thread.operand_stack.is_empty());
+
+        let args = vec![false.into(), "x should be 2".into()];
+        let Err(err) = apply_builtin(rt, ASSERT_SYM, args) else {
+            panic!("a false assert should fail");
+        };
+        assert_eq!(error_code(&err), Some(Code::R015));
+        assert_eq!(err.to_string(), "Assertion failed: x should be 2");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_builtin_fallible() -> Result<()> {
+        let mut rt = Runtime::default();
+        let c = bytecode::Channel::new();
+        c.send(Value::Int(1));
+        rt = apply_builtin(rt, TRY_RECV_SYM, vec![c.clone().into()])?;
+        assert_eq!(
+            rt.current_thread.operand_stack.pop(),
+            Some(Variant::some(1).into())
+        );
+
+        // an empty channel gives None instead of blocking
+        rt = apply_builtin(rt, TRY_RECV_SYM, vec![c.into()])?;
+        assert_eq!(
+            rt.current_thread.operand_stack.pop(),
+            Some(Variant::none().into())
+        );
+
+        // a file that can't be read is an Err with why
+        let path = std::env::temp_dir().join("rustscript_missing_dir/none.bin");
+        let path: Value = path.to_string_lossy().to_string().into();
+        rt = apply_builtin(rt, READ_BYTES_SYM, vec![path.clone()])?;
+        let Some(Value::Variant(res)) = rt.current_thread.operand_stack.pop() else {
+            panic!("read_bytes should give a result");
+        };
+        assert_eq!(res.name, "Err");
+
+        rt = apply_builtin(
+            rt,
+            WRITE_BYTES_SYM,
+            vec![path, bytecode::Bytes::new(vec![1]).into()],
+        )?;
+        let Some(Value::Variant(res)) = rt.current_thread.operand_stack.pop() else {
+            panic!("write_bytes should give a result");
+        };
+        assert_eq!(res.name, "Err");
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_builtin_net() -> Result<()> {
+        use std::{
+            io::{Read, Write},
+            net::{TcpListener, TcpStream, UdpSocket},
+            thread,
+        };
+
+        // Echo one message back on a connection to the listener
+        let server = TcpListener::bind("127.0.0.1:0")?;
+        let addr = server.local_addr()?.to_string();
+        let echo = thread::spawn(move || -> std::io::Result<()> {
+            let (mut stream, _) = server.accept()?;
+            let mut buf = [0; 64];
+            let n = stream.read(&mut buf)?;
+            stream.write_all(&buf[..n])
+        });
+
+        // The network is off by default
+        let rt = Runtime::default();
+        let result = apply_builtin(
+            rt,
+            TCP_CONNECT_SYM,
+            vec![Value::String(addr.clone().into())],
+        );
+        assert!(result.is_err_and(|err| err.to_string().contains("--allow-net")));
+
+        let mut rt = Runtime::default();
+        rt.set_allow_net();
+        rt = apply_builtin(rt, TCP_CONNECT_SYM, vec![Value::String(addr.into())])?;
+        let conn = pop_ok(&mut rt);
+        assert_eq!(type_of(&conn), "Socket");
+
+        rt = apply_builtin(rt, TCP_SEND_SYM, vec![conn.clone(), "hello".into()])?;
+        assert_eq!(pop_ok(&mut rt), Value::Int(5));
+        rt = apply_builtin(rt, TCP_RECV_SYM, vec![conn.clone()])?;
+        assert_eq!(pop_ok(&mut rt), "hello".into());
+        echo.join().unwrap()?;
+
+        // At EOF recv gives the empty string
+        rt = apply_builtin(rt, TCP_RECV_SYM, vec![conn.clone()])?;
+        assert_eq!(pop_ok(&mut rt), "".into());
+
+        // Using a closed socket is an error of the program, not an Err
+        rt = apply_builtin(rt, CLOSE_SYM, vec![conn.clone()])?;
+        assert!(rt.current_thread.operand_stack.is_empty());
+        let result = apply_builtin(rt, TCP_SEND_SYM, vec![conn, "again".into()]);
+        assert!(result.is_err());
+
+        // A listener accepts connections from anywhere
+        let mut rt = Runtime::default();
+        rt.set_allow_net();
+        rt = apply_builtin(rt, TCP_LISTEN_SYM, vec!["127.0.0.1:0".into()])?;
+        let listener = pop_ok(&mut rt);
+        let Value::Socket(sock) = &listener else {
+            panic!("tcp_listen should give a socket");
+        };
+        let addr = sock.with(|kind| match kind {
+            SocketKind::Listener(l) => Ok(l.local_addr()?),
+            _ => panic!("tcp_listen should give a listener"),
+        })?;
+        let client = thread::spawn(move || -> std::io::Result<()> {
+            TcpStream::connect(addr)?.write_all(b"hi")
+        });
+        rt = apply_builtin(rt, TCP_ACCEPT_SYM, vec![listener.clone()])?;
+        let conn = pop_ok(&mut rt);
+        client.join().unwrap()?;
+        rt = apply_builtin(rt, TCP_RECV_SYM, vec![conn])?;
+        assert_eq!(pop_ok(&mut rt), "hi".into());
+
+        // Only a listener accepts
+        let result = apply_builtin(rt, TCP_RECV_SYM, vec![listener]);
+        assert!(result.is_err());
+
+        // A UDP socket sends to and receives from any address
+        let peer = UdpSocket::bind("127.0.0.1:0")?;
+        let peer_addr = peer.local_addr()?.to_string();
+        let mut rt = Runtime::default();
+        rt.set_allow_net();
+        rt = apply_builtin(rt, UDP_BIND_SYM, vec!["127.0.0.1:0".into()])?;
+        let udp = pop_ok(&mut rt);
+        rt = apply_builtin(
+            rt,
+            UDP_SEND_TO_SYM,
+            vec![udp.clone(), Value::String(peer_addr.into()), "ping".into()],
+        )?;
+        assert_eq!(pop_ok(&mut rt), Value::Int(4));
+
+        let mut buf = [0; 16];
+        let (n, from) = peer.recv_from(&mut buf)?;
+        assert_eq!(&buf[..n], b"ping");
+        peer.send_to(b"pong", from)?;
+        rt = apply_builtin(rt, UDP_RECV_SYM, vec![udp])?;
+        assert_eq!(pop_ok(&mut rt), "pong".into());
+
+        // Failing to connect is an Err for the program to handle. Nothing listens on the port of a listener that
+        // was dropped
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")?.local_addr()?;
+        rt = apply_builtin(rt, TCP_CONNECT_SYM, vec![addr.to_string().into()])?;
+        let Some(Value::Variant(res)) = rt.current_thread.operand_stack.pop() else {
+            panic!("tcp_connect should give a result");
+        };
+        assert_eq!(res.name, "Err");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_builtin_http() -> Result<()> {
+        use std::{
+            io::{Read, Write},
+            net::TcpListener,
+            thread,
+        };
+
+        // Answer each request with its method and body
+        let server = TcpListener::bind("127.0.0.1:0")?;
+        let url = format!("http://{}/", server.local_addr()?);
+        let handle = thread::spawn(move || -> Result<()> {
+            for _ in 0..2 {
+                let (mut stream, _) = server.accept()?;
+                let mut buf = [0; 1024];
+                let n = stream.read(&mut buf)?;
+                let req = String::from_utf8_lossy(&buf[..n]).into_owned();
+                let method = req.split(' ').next().unwrap_or_default();
+                let body = req.split("\r\n\r\n").nth(1).unwrap_or_default();
+                let res = format!("{} {}", method, body);
+                write!(
+                    stream,
+                    "HTTP/1.1 418 I'm a teapot\r\nContent-Length: {}\r\n\r\n{}",
+                    res.len(),
+                    res
+                )?;
+            }
+            Ok(())
+        });
+
+        let mut rt = Runtime::default();
+        rt.set_allow_net();
+        rt = apply_builtin(rt, HTTP_GET_SYM, vec![Value::String(url.clone().into())])?;
+        let Value::Struct(res) = pop_ok(&mut rt) else {
+            panic!("http_get should give a struct");
+        };
+        assert_eq!(res.name, HTTP_RESPONSE_STRUCT);
+        assert_eq!(res.get("status"), Some(Value::Int(418)));
+        assert_eq!(res.get("body"), Some("GET ".into()));
+
+        // The fields can be read in a program
+        let instrs = compiler::compiler::compile_from_string(
+            &format!(r#"match http_post("{url}", "tea") {{ Ok(res) => res.body, Err(e) => e }}"#),
+            true,
+        )?;
+        let mut rt = Runtime::new(instrs);
+        rt.set_allow_net();
+        let rt = crate::run(rt)?;
+        assert_eq!(
+            rt.current_thread.operand_stack.last(),
+            Some(&"POST tea".into())
+        );
+        handle.join().unwrap()?;
+
+        // Only http urls are supported
+        let mut rt = Runtime::default();
+        rt.set_allow_net();
+        let result = apply_builtin(rt, HTTP_GET_SYM, vec!["https://example.com".into()]);
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_builtin_bytes() -> Result<()> {
+        let mut rt = apply_builtin(Runtime::default(), BYTES_SYM, vec![Value::Int(2)])?;
+        let buf = rt.current_thread.operand_stack.pop().unwrap();
+        assert_eq!(type_of(&buf), "Bytes");
+
+        // the buffer is shared, so set_byte changes it for every handle
+        rt = apply_builtin(
+            rt,
+            SET_BYTE_SYM,
+            vec![buf.clone(), Value::Int(1), Value::Int(200)],
+        )?;
+        rt = apply_builtin(rt, GET_BYTE_SYM, vec![buf.clone(), Value::Int(1)])?;
+        assert_eq!(rt.current_thread.operand_stack.pop(), Some(Value::Int(200)));
+
+        let result = apply_builtin(
+            rt,
+            SET_BYTE_SYM,
+            vec![buf.clone(), Value::Int(0), Value::Int(256)],
+        );
+        assert!(result.is_err_and(|e| e.to_string().contains("256 is not a byte")));
+        let result = apply_builtin(Runtime::default(), GET_BYTE_SYM, vec![buf, Value::Int(2)]);
+        assert!(result.is_err_and(|e| e.to_string().contains("out of bounds")));
+        let result = apply_builtin(Runtime::default(), BYTES_SYM, vec![Value::Int(-1)]);
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_builtin_sort_by() -> Result<()> {
+        // nothing to compare, so the comparator is never called
+        let empty: Value = vec![].into();
+        let rt = apply_builtin(Runtime::default(), SORT_BY_SYM, vec![empty.clone(), max()])?;
+        assert_eq!(rt.current_thread.operand_stack.last(), Some(&empty));
+
+        let result = apply_builtin(Runtime::default(), MIN_BY_SYM, vec![empty, max()]);
+        assert!(result.is_err_and(|e| e.to_string() == "min_by of an empty array or slice"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_builtin_run_command() -> Result<()> {
+        let args = || {
+            vec![
+                "sh".into(),
+                vec!["-c".into(), "echo out; echo err >&2; exit 2".into()].into(),
+                Value::Int(5000),
+            ]
+        };
+
+        // Other programs can't be run unless allowed
+        let result = apply_builtin(Runtime::default(), RUN_COMMAND_SYM, args());
+        assert!(result.is_err_and(|e| e.to_string().contains("--allow-run")));
+
+        let mut rt = Runtime::default();
+        rt.set_allow_run();
+        rt = apply_builtin(rt, RUN_COMMAND_SYM, args())?;
+        let Value::Struct(res) = pop_ok(&mut rt) else {
+            panic!("run_command should give a struct");
+        };
+        assert_eq!(res.name, COMMAND_OUTPUT_STRUCT);
+        assert_eq!(res.get("code"), Some(Value::Int(2)));
+        assert_eq!(res.get("stdout"), Some("out\n".into()));
+        assert_eq!(res.get("stderr"), Some("err\n".into()));
+
+        // The fields can be read in a program
+        let instrs = compiler::compiler::compile_from_string(
+            r#"match run_command("sh", ["-c", "sleep 5"], 50) { Ok(res) => res.code, Err(_) => 0 }"#,
+            true,
+        )?;
+        let mut rt = Runtime::new(instrs);
+        rt.set_allow_run();
+        let rt = crate::run(rt)?;
+        assert_eq!(
+            rt.current_thread.operand_stack.last(),
+            Some(&Value::Int(-1))
+        );
+
+        // A command that can't be started is an Err with why
+        let args = vec![
+            "rustscript-no-such-command".into(),
+            Value::from(vec![]),
+            Value::Int(0),
+        ];
+        let mut rt = Runtime::default();
+        rt.set_allow_run();
+        rt = apply_builtin(rt, RUN_COMMAND_SYM, args)?;
+        let Some(Value::Variant(res)) = rt.current_thread.operand_stack.pop() else {
+            panic!("run_command should give a result");
+        };
+        assert_eq!(res.name, "Err");
+        assert!(res.payload[0]
+            .to_string()
+            .starts_with("Failed to run 'rustscript-no-such-command'"));
+        Ok(())
+    }
+
+    // The value in the Ok on top of the operand stack
+    fn pop_ok(rt: &mut Runtime) -> Value {
+        match rt.current_thread.operand_stack.pop() {
+            Some(Value::Variant(res)) if res.name == "Ok" => res.payload[0].clone(),
+            val => panic!("expected an Ok, got {:?}", val),
+        }
+    }
 }
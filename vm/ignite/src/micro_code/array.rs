@@ -0,0 +1,120 @@
+use anyhow::Result;
+use bytecode::Array;
+
+use crate::{Runtime, VmError};
+
+/// Pops the given number of values off the stack and pushes an array of them.
+/// The value that was deepest in the stack becomes the first element.
+///
+/// # Arguments
+///
+/// * `rt` - The runtime to create the array in.
+///
+/// * `len` - The number of values to pop into the array.
+///
+/// # Errors
+///
+/// If the stack has fewer than `len` values.
+#[inline]
+pub fn array(mut rt: Runtime, len: usize) -> Result<Runtime> {
+    let stack_len = rt.current_thread.operand_stack.len();
+    if stack_len < len {
+        return Err(VmError::OperandStackUnderflow.into());
+    }
+
+    let vals = rt.current_thread.operand_stack.split_off(stack_len - len);
+    rt.current_thread
+        .operand_stack
+        .push(Array::new(vals).into());
+    Ok(rt)
+}
+
+/// Pops a value off the stack and pushes an array holding `len` copies of it.
+/// Arrays and structs are copied deeply so every element gets storage of its own, e.g. each row of `[[0; 3]; 3]`
+/// can be updated independently.
+///
+/// # Arguments
+///
+/// * `rt` - The runtime to create the array in.
+///
+/// * `len` - The length of the array.
+///
+/// # Errors
+///
+/// If the stack is empty.
+#[inline]
+pub fn array_fill(mut rt: Runtime, len: usize) -> Result<Runtime> {
+    let val = rt
+        .current_thread
+        .operand_stack
+        .pop()
+        .ok_or(VmError::OperandStackUnderflow)?;
+
+    let vals = (0..len)
+        .map(|_| match &val {
+            bytecode::Value::Array(arr) => arr.deep_clone().into(),
+            bytecode::Value::Struct(s) => s.deep_clone().into(),
+            _ => val.clone(),
+        })
+        .collect();
+
+    rt.current_thread
+        .operand_stack
+        .push(Array::new(vals).into());
+    Ok(rt)
+}
+
+#[cfg(test)]
+mod tests {
+    use bytecode::Value;
+
+    use super::*;
+    use crate::micro_code::ldc;
+
+    #[test]
+    fn test_array() {
+        let mut rt = Runtime::new(vec![]);
+        rt = ldc(rt, Value::Unit).unwrap();
+        rt = ldc(rt, Value::Int(1)).unwrap();
+        rt = ldc(rt, Value::Int(2)).unwrap();
+        rt = array(rt, 2).unwrap();
+
+        assert_eq!(
+            rt.current_thread.operand_stack,
+            vec![Value::Unit, vec![Value::Int(1), Value::Int(2)].into()]
+        );
+
+        rt = array(rt, 0).unwrap();
+        assert_eq!(
+            rt.current_thread.operand_stack.pop(),
+            Some(Vec::new().into())
+        );
+
+        assert!(array(rt, 3).is_err());
+    }
+
+    #[test]
+    fn test_array_fill() {
+        let mut rt = Runtime::new(vec![]);
+        rt = ldc(rt, Value::Int(0)).unwrap();
+        rt = array_fill(rt, 3).unwrap();
+        assert_eq!(
+            rt.current_thread.operand_stack.last(),
+            Some(&vec![Value::Int(0); 3].into())
+        );
+
+        // Rows don't share storage
+        rt = array_fill(rt, 2).unwrap();
+        let Some(Value::Array(rows)) = rt.current_thread.operand_stack.pop() else {
+            panic!("Expected an array");
+        };
+        let Value::Array(first) = rows.borrow()[0].clone() else {
+            panic!("Expected an array");
+        };
+        first.borrow_mut()[0] = Value::Int(1);
+        assert_eq!(rows.borrow()[1], vec![Value::Int(0); 3].into());
+
+        let empty_rt = Runtime::new(vec![]);
+        assert!(array_fill(empty_rt, 1).is_err());
+    }
+}
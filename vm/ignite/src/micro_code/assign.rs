@@ -4,6 +4,9 @@ use bytecode::Symbol;
 use crate::{Runtime, VmError};
 
 /// Assign a value to a symbol.
+/// The binding updated is the one found on the environment chain, which may be shared with other threads.
+/// The update happens in this one instruction, so other threads see either the old or the new value.
+/// If the symbol is watched, it is left in `Runtime::watch_hit` for the debugger.
 ///
 /// # Arguments
 ///
@@ -22,6 +25,11 @@ pub fn assign(mut rt: Runtime, sym: Symbol) -> Result<Runtime> {
         .operand_stack
         .pop()
         .ok_or(VmError::OperandStackUnderflow)?;
+
+    if !rt.watchpoints.is_empty() && rt.watchpoints.contains(&sym) {
+        rt.watch_hit = Some(sym.clone());
+    }
+
     rt.current_thread
         .env
         .upgrade()
@@ -73,6 +81,30 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_assign_watched() -> Result<()> {
+        let mut rt = Runtime::new(vec![]);
+        rt.current_thread
+            .env
+            .upgrade()
+            .unwrap()
+            .borrow_mut()
+            .set("x", Value::Unitialized);
+        rt.watchpoints.insert("x".to_string());
+
+        rt.current_thread.operand_stack.push(Value::Int(42));
+        rt = assign(rt, "x".to_string())?;
+        assert_eq!(rt.watch_hit.take(), Some("x".to_string()));
+
+        // Only watched symbols are reported
+        rt.watchpoints.clear();
+        rt.current_thread.operand_stack.push(Value::Int(43));
+        rt = assign(rt, "x".to_string())?;
+        assert_eq!(rt.watch_hit, None);
+
+        Ok(())
+    }
+
     #[test]
     fn test_assign_with_parent() -> Result<()> {
         let mut rt = Runtime::new(vec![]);
@@ -92,7 +124,7 @@ mod tests {
 
         assert_eq!(parent_env.borrow().get(&"x".to_string())?, Value::Int(123));
         // The child environment should not be updated.
-        assert!(!child_env.borrow().env.contains_key(&"x".to_string()));
+        assert!(!child_env.borrow().env.contains_key("x"));
 
         rt.current_thread.operand_stack.push(Value::Int(789));
         rt = assign(rt, "y".to_string()).unwrap();
@@ -0,0 +1,65 @@
+use anyhow::Result;
+use bytecode::Symbol;
+
+use crate::{
+    micro_code::ld_field::{as_struct, field_not_found},
+    Runtime, VmError,
+};
+
+/// Pops a value and a struct off the stack, and sets the given field of the struct to the value.
+/// Every reference to the struct sees the update.
+///
+/// # Arguments
+///
+/// * `rt` - The runtime to execute the instruction on.
+///
+/// * `field` - The name of the field to set.
+///
+/// # Errors
+///
+/// If the stack has fewer than two values, the second value is not a struct or the struct has no such field.
+#[inline]
+pub fn assign_field(mut rt: Runtime, field: Symbol) -> Result<Runtime> {
+    let val = rt
+        .current_thread
+        .operand_stack
+        .pop()
+        .ok_or(VmError::OperandStackUnderflow)?;
+    let s = rt
+        .current_thread
+        .operand_stack
+        .pop()
+        .ok_or(VmError::OperandStackUnderflow)?;
+
+    let s = as_struct(s)?;
+    if !s.set(&field, val) {
+        return Err(field_not_found(&rt, &s, field).into());
+    }
+
+    Ok(rt)
+}
+
+#[cfg(test)]
+mod tests {
+    use bytecode::{Struct, Value};
+
+    use super::*;
+    use crate::micro_code::ldc;
+
+    #[test]
+    fn test_assign_field() {
+        let p = Struct::new("Point".to_string(), vec![("x".to_string(), Value::Int(1))]);
+
+        let mut rt = Runtime::new(vec![]);
+        rt = ldc(rt, p.clone().into()).unwrap();
+        rt = ldc(rt, Value::Int(42)).unwrap();
+        rt = assign_field(rt, "x".to_string()).unwrap();
+
+        assert!(rt.current_thread.operand_stack.is_empty());
+        assert_eq!(p.get("x"), Some(Value::Int(42)));
+
+        rt = ldc(rt, p.into()).unwrap();
+        rt = ldc(rt, Value::Int(42)).unwrap();
+        assert!(assign_field(rt, "y".to_string()).is_err());
+    }
+}
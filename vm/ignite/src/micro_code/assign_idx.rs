@@ -0,0 +1,80 @@
+use anyhow::Result;
+
+use crate::{micro_code::ld_idx::array_and_index, Runtime, VmError};
+
+/// Pops a value, an index and an array off the stack, and sets the element at that index to the value.
+/// Every reference to the array sees the update.
+///
+/// # Arguments
+///
+/// * `rt` - The runtime to execute the instruction on.
+///
+/// # Errors
+///
+/// If the stack has fewer than three values, the array and index are not an array and an int,
+/// or the index is out of bounds.
+#[inline]
+pub fn assign_idx(mut rt: Runtime) -> Result<Runtime> {
+    let val = rt
+        .current_thread
+        .operand_stack
+        .pop()
+        .ok_or(VmError::OperandStackUnderflow)?;
+    let index = rt
+        .current_thread
+        .operand_stack
+        .pop()
+        .ok_or(VmError::OperandStackUnderflow)?;
+    let arr = rt
+        .current_thread
+        .operand_stack
+        .pop()
+        .ok_or(VmError::OperandStackUnderflow)?;
+
+    let (arr, index) = array_and_index(&rt, arr, index)?;
+    arr.borrow_mut()[index] = val;
+
+    Ok(rt)
+}
+
+#[cfg(test)]
+mod tests {
+    use bytecode::{Array, Value};
+
+    use super::*;
+    use crate::micro_code::ldc;
+
+    #[test]
+    fn test_assign_idx() {
+        let arr = Array::new(vec![Value::Int(1), Value::Int(2)]);
+
+        let mut rt = Runtime::new(vec![]);
+        rt = ldc(rt, arr.clone().into()).unwrap();
+        rt = ldc(rt, Value::Int(0)).unwrap();
+        rt = ldc(rt, Value::Int(42)).unwrap();
+        rt = assign_idx(rt).unwrap();
+
+        assert!(rt.current_thread.operand_stack.is_empty());
+        assert_eq!(arr, Array::new(vec![Value::Int(42), Value::Int(2)]));
+    }
+
+    #[test]
+    fn test_assign_idx_out_of_bounds() {
+        let mut rt = Runtime::new(vec![]);
+        rt = ldc(rt, vec![Value::Int(1)].into()).unwrap();
+        rt = ldc(rt, Value::Int(1)).unwrap();
+        rt = ldc(rt, Value::Int(42)).unwrap();
+
+        let Err(err) = assign_idx(rt) else {
+            panic!("Should be out of bounds");
+        };
+        assert!(matches!(
+            err.downcast_ref::<VmError>(),
+            Some(VmError::IndexOutOfBounds {
+                index: 1,
+                len: 1,
+                ..
+            })
+        ));
+    }
+}
@@ -19,7 +19,8 @@ use crate::{Runtime, VmError};
 /// # Errors
 ///
 /// If the stack has fewer than two values or the operation is not supported
-/// for the types of the values on the stack.
+/// for the types of the values on the stack. Also if an int is divided by zero,
+/// or the result of an int operation doesn't fit in an int.
 #[inline]
 pub fn binop(mut rt: Runtime, op: BinOp) -> Result<Runtime> {
     let rhs_val = rt
@@ -49,14 +50,24 @@ pub fn binop(mut rt: Runtime, op: BinOp) -> Result<Runtime> {
             Ok(rt)
         }
         (Value::Int(lhs), Value::Int(rhs)) => {
+            let pc = rt.current_thread.pc.saturating_sub(1);
+            if matches!(op, BinOp::Div | BinOp::Mod) && rhs == 0 {
+                return Err(VmError::DivisionByZero { pc }.into());
+            }
+            // Checked so overflow is an error in release builds too, instead of a panic in debug builds only
+            let overflow = || VmError::IntegerOverflow {
+                op: op.clone().into(),
+                pc,
+            };
+
             let result = match op {
-                BinOp::Add => Value::Int(lhs + rhs),  // Addition
-                BinOp::Sub => Value::Int(lhs - rhs),  // Subtraction
-                BinOp::Mul => Value::Int(lhs * rhs),  // Multiplication
-                BinOp::Div => Value::Int(lhs / rhs),  // Division
-                BinOp::Mod => Value::Int(lhs % rhs),  // Modulus
-                BinOp::Gt => Value::Bool(lhs > rhs),  // Greater Than
-                BinOp::Lt => Value::Bool(lhs < rhs),  // Less Than
+                BinOp::Add => Value::Int(lhs.checked_add(rhs).ok_or_else(overflow)?), // Addition
+                BinOp::Sub => Value::Int(lhs.checked_sub(rhs).ok_or_else(overflow)?), // Subtraction
+                BinOp::Mul => Value::Int(lhs.checked_mul(rhs).ok_or_else(overflow)?), // Multiplication
+                BinOp::Div => Value::Int(lhs.checked_div(rhs).ok_or_else(overflow)?), // Division
+                BinOp::Mod => Value::Int(lhs.checked_rem(rhs).ok_or_else(overflow)?), // Modulus
+                BinOp::Gt => Value::Bool(lhs > rhs), // Greater Than
+                BinOp::Lt => Value::Bool(lhs < rhs), // Less Than
                 BinOp::Eq => Value::Bool(lhs == rhs), // Equality
                 BinOp::And => {
                     return Err(VmError::UnsupportedOperation(
@@ -128,7 +139,24 @@ pub fn binop(mut rt: Runtime, op: BinOp) -> Result<Runtime> {
         }
         (Value::String(lhs), Value::String(rhs)) => {
             let result = match op {
-                BinOp::Add => Value::String(lhs + &rhs),
+                BinOp::Add => Value::String(format!("{}{}", lhs, rhs).into()),
+                BinOp::Eq => Value::Bool(lhs == rhs),
+                _ => {
+                    return Err(VmError::UnsupportedOperation(
+                        op.into(),
+                        type_of(&rhs_val).to_string(),
+                    )
+                    .into())
+                }
+            };
+            rt.current_thread.operand_stack.push(result);
+            Ok(rt)
+        }
+        // Chars compare by their code points, so 'a' < 'b' and 'Z' < 'a'
+        (Value::Char(lhs), Value::Char(rhs)) => {
+            let result = match op {
+                BinOp::Gt => Value::Bool(lhs > rhs),
+                BinOp::Lt => Value::Bool(lhs < rhs),
                 BinOp::Eq => Value::Bool(lhs == rhs),
                 _ => {
                     return Err(VmError::UnsupportedOperation(
@@ -155,6 +183,104 @@ pub fn binop(mut rt: Runtime, op: BinOp) -> Result<Runtime> {
             rt.current_thread.operand_stack.push(result);
             Ok(rt)
         }
+        (Value::Channel(c1), Value::Channel(c2)) => {
+            let result = match op {
+                BinOp::Eq => Value::Bool(c1 == c2),
+                _ => {
+                    return Err(VmError::UnsupportedOperation(
+                        op.into(),
+                        type_of(&rhs_val).to_string(),
+                    )
+                    .into())
+                }
+            };
+            rt.current_thread.operand_stack.push(result);
+            Ok(rt)
+        }
+        (Value::Array(a1), Value::Array(a2)) => {
+            let result = match op {
+                BinOp::Eq => Value::Bool(a1 == a2),
+                _ => {
+                    return Err(VmError::UnsupportedOperation(
+                        op.into(),
+                        type_of(&rhs_val).to_string(),
+                    )
+                    .into())
+                }
+            };
+            rt.current_thread.operand_stack.push(result);
+            Ok(rt)
+        }
+        (Value::Slice(s1), Value::Slice(s2)) => {
+            let result = match op {
+                BinOp::Eq => Value::Bool(s1 == s2),
+                _ => {
+                    return Err(VmError::UnsupportedOperation(
+                        op.into(),
+                        type_of(&rhs_val).to_string(),
+                    )
+                    .into())
+                }
+            };
+            rt.current_thread.operand_stack.push(result);
+            Ok(rt)
+        }
+        (Value::Struct(s1), Value::Struct(s2)) => {
+            let result = match op {
+                BinOp::Eq => Value::Bool(s1 == s2),
+                _ => {
+                    return Err(VmError::UnsupportedOperation(
+                        op.into(),
+                        type_of(&rhs_val).to_string(),
+                    )
+                    .into())
+                }
+            };
+            rt.current_thread.operand_stack.push(result);
+            Ok(rt)
+        }
+        (Value::Variant(v1), Value::Variant(v2)) => {
+            let result = match op {
+                BinOp::Eq => Value::Bool(v1 == v2),
+                _ => {
+                    return Err(VmError::UnsupportedOperation(
+                        op.into(),
+                        type_of(&rhs_val).to_string(),
+                    )
+                    .into())
+                }
+            };
+            rt.current_thread.operand_stack.push(result);
+            Ok(rt)
+        }
+        (Value::Tuple(t1), Value::Tuple(t2)) => {
+            let result = match op {
+                BinOp::Eq => Value::Bool(t1 == t2),
+                _ => {
+                    return Err(VmError::UnsupportedOperation(
+                        op.into(),
+                        type_of(&rhs_val).to_string(),
+                    )
+                    .into())
+                }
+            };
+            rt.current_thread.operand_stack.push(result);
+            Ok(rt)
+        }
+        (Value::Map(m1), Value::Map(m2)) => {
+            let result = match op {
+                BinOp::Eq => Value::Bool(m1 == m2),
+                _ => {
+                    return Err(VmError::UnsupportedOperation(
+                        op.into(),
+                        type_of(&rhs_val).to_string(),
+                    )
+                    .into())
+                }
+            };
+            rt.current_thread.operand_stack.push(result);
+            Ok(rt)
+        }
         (Value::Closure { .. }, Value::Closure { .. }) => {
             Err(VmError::UnsupportedOperation(op.into(), type_of(&rhs_val).to_string()).into())
         }
@@ -343,4 +469,49 @@ mod tests {
             Value::Bool(true)
         );
     }
+
+    #[test]
+    fn test_binop_int_errors() {
+        for op in [BinOp::Div, BinOp::Mod] {
+            let mut rt = Runtime::new(vec![]);
+            rt = ldc(rt, Value::Int(1)).unwrap();
+            rt = ldc(rt, Value::Int(0)).unwrap();
+            let Err(err) = binop(rt, op) else {
+                panic!("Should fail");
+            };
+            assert!(matches!(
+                err.downcast_ref::<VmError>(),
+                Some(VmError::DivisionByZero { .. })
+            ));
+        }
+
+        for (lhs, rhs, op) in [
+            (i64::MAX, 1, BinOp::Add),
+            (i64::MIN, 1, BinOp::Sub),
+            (i64::MAX, 2, BinOp::Mul),
+            (i64::MIN, -1, BinOp::Div),
+            (i64::MIN, -1, BinOp::Mod),
+        ] {
+            let mut rt = Runtime::new(vec![]);
+            rt = ldc(rt, Value::Int(lhs)).unwrap();
+            rt = ldc(rt, Value::Int(rhs)).unwrap();
+            let Err(err) = binop(rt, op) else {
+                panic!("Should fail");
+            };
+            assert!(matches!(
+                err.downcast_ref::<VmError>(),
+                Some(VmError::IntegerOverflow { .. })
+            ));
+        }
+
+        // Floats follow IEEE 754 and divide by zero to infinity
+        let mut rt = Runtime::new(vec![]);
+        rt = ldc(rt, Value::Float(1.0)).unwrap();
+        rt = ldc(rt, Value::Float(0.0)).unwrap();
+        rt = binop(rt, BinOp::Div).unwrap();
+        assert_eq!(
+            rt.current_thread.operand_stack.pop().unwrap(),
+            Value::Float(f64::INFINITY)
+        );
+    }
 }
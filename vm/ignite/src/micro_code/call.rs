@@ -1,7 +1,9 @@
+use std::rc::Rc;
+
 use anyhow::Result;
-use bytecode::{type_of, FnType, FrameType, StackFrame, Value};
+use bytecode::{type_of, CallInfo, FnType, FrameType, StackFrame, Value, W};
 
-use crate::{extend_environment, Runtime, VmError};
+use crate::{execute, extend_environment, Runtime, Stack, VmError};
 
 use super::apply_builtin;
 
@@ -12,8 +14,8 @@ use super::apply_builtin;
 /// Then it pops the closure from the operand stack.
 /// It checks that the closure is a closure and that the arity of the closure matches the number of arguments.
 /// If the closure is a builtin function it applies the builtin function and returns.
-/// Otherwise it creates a new stack frame with the environment of the closure and the address of the closure.
-/// It extends the environment with the parameters and arguments.
+/// Otherwise it saves the caller's environment and the return address in a new call frame, so they are restored
+/// when the function returns. It extends the environment of the closure with the parameters and arguments.
 /// It sets the program counter to the address of the closure. Essentially calling the function.
 ///
 /// # Arguments
@@ -26,6 +28,7 @@ use super::apply_builtin;
 ///
 /// If the operand stack does not contain enough values to pop (arity + 1).
 /// If the closure is not of type closure or the arity of the closure does not match the number of arguments.
+/// If the runtime stack already holds as many frames as the call depth limit allows.
 #[inline]
 pub fn call(mut rt: Runtime, arity: usize) -> Result<Runtime> {
     let mut args = Vec::new();
@@ -75,19 +78,123 @@ pub fn call(mut rt: Runtime, arity: usize) -> Result<Runtime> {
         return apply_builtin(rt, sym.as_str(), args);
     }
 
+    if rt.current_thread.runtime_stack.len() >= rt.max_call_depth {
+        return Err(VmError::StackOverflow {
+            stack: Stack::Runtime,
+            limit: rt.max_call_depth,
+            thread_id: rt.current_thread.thread_id,
+            pc: rt.current_thread.pc.saturating_sub(1),
+        }
+        .into());
+    }
+
+    let call = CallInfo {
+        addr,
+        call_pc: rt.current_thread.pc.saturating_sub(1),
+        caller: rt.current_thread.call(),
+    };
     let frame = StackFrame {
         frame_type: FrameType::CallFrame,
-        env: env.clone(),
+        env: W(rt.current_thread.env.clone()),
         address: Some(rt.current_thread.pc),
+        call: Some(Rc::new(call)),
     };
 
     rt.current_thread.runtime_stack.push(frame);
-    rt = extend_environment(rt, env.0, prms, args)?;
+    rt = extend_environment(rt, env.0, prms.to_vec(), args)?;
     rt.current_thread.pc = addr;
 
     Ok(rt)
 }
 
+/// Call a closure from inside a builtin and run it to completion, returning its result. This is how builtins
+/// like map call back into user code.
+///
+/// The closure is called as by CALL, then instructions are executed until its call frame is reset. The result
+/// is left on the operand stack by the RESET, and is popped off and returned. A builtin closure is applied
+/// directly, and returns unit if it leaves nothing on the stack.
+///
+/// The closure runs without preemption or garbage collection, since the builtin that called it is still
+/// in the middle of an instruction, holding values the collector can't see.
+///
+/// Each call nests another interpreter loop on the host stack, so a closure that calls a builtin that calls back
+/// nests them deeper. The stack is grown onto the heap when it runs low, so they can nest as deep as the program
+/// recurses, like a recursive function calling itself through map.
+///
+/// # Arguments
+///
+/// * `rt` - The runtime to execute the closure in.
+///
+/// * `sym` - The builtin the call is made from, for errors.
+///
+/// * `f` - The closure to call.
+///
+/// * `args` - The arguments to call it with.
+///
+/// # Errors
+///
+/// If the call fails as CALL would, or executing the closure fails.
+/// If the closure yields or blocks, switching to another thread before it returns.
+pub fn call_closure(
+    rt: Runtime,
+    sym: &str,
+    f: &Value,
+    args: Vec<Value>,
+) -> Result<(Runtime, Value)> {
+    stacker::maybe_grow(CALLBACK_RED_ZONE, CALLBACK_STACK_SIZE, || {
+        run_closure(rt, sym, f, args)
+    })
+}
+
+// Executing an instruction can take a lot of stack in debug builds, over 100KB for a callback nested in a
+// callback, so a new part of the stack is started well before it runs out
+const CALLBACK_RED_ZONE: usize = 512 * 1024;
+const CALLBACK_STACK_SIZE: usize = 4 * 1024 * 1024;
+
+fn run_closure(
+    mut rt: Runtime,
+    sym: &str,
+    f: &Value,
+    args: Vec<Value>,
+) -> Result<(Runtime, Value)> {
+    let thread_id = rt.current_thread.thread_id;
+    let stack_len = rt.current_thread.operand_stack.len();
+    let depth = rt.current_thread.runtime_stack.len();
+    let arity = args.len();
+
+    rt.current_thread.operand_stack.push(f.clone());
+    rt.current_thread.operand_stack.extend(args);
+    rt = call(rt, arity)?;
+
+    // A builtin like sleep can switch threads from the call itself
+    loop {
+        if rt.current_thread.thread_id != thread_id {
+            return Err(VmError::BlockedInCallback {
+                sym: sym.to_string(),
+            }
+            .into());
+        }
+
+        if rt.current_thread.runtime_stack.len() <= depth {
+            break;
+        }
+
+        let instr = rt.fetch_instr()?;
+        rt = execute(rt, instr)?;
+    }
+
+    let val = if rt.current_thread.operand_stack.len() > stack_len {
+        rt.current_thread
+            .operand_stack
+            .pop()
+            .ok_or(VmError::OperandStackUnderflow)?
+    } else {
+        Value::Unit
+    };
+
+    Ok((rt, val))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -103,7 +210,7 @@ mod tests {
         rt.current_thread.operand_stack.push(Value::Closure {
             fn_type: FnType::User,
             sym: "Closure".to_string(),
-            prms: vec![],
+            prms: vec![].into(),
             addr: 123,
             env: Default::default(),
         });
@@ -113,4 +220,78 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_call_closure() -> Result<()> {
+        // the closure x + 1 is at 1, the caller is stopped at 0
+        let instrs = vec![
+            ByteCode::DONE,
+            ByteCode::ld("x"),
+            ByteCode::ldc(1),
+            ByteCode::BINOP(bytecode::BinOp::Add),
+            ByteCode::RESET(FrameType::CallFrame),
+        ];
+        let mut rt = crate::micro_code::ldf(Runtime::new(instrs), 1, vec!["x".to_string()])?;
+        let f = rt.current_thread.operand_stack.pop().unwrap();
+
+        let (rt, val) = call_closure(rt, "map", &f, vec![Value::Int(41)])?;
+        assert_eq!(val, Value::Int(42));
+        assert_eq!(rt.current_thread.pc, 0);
+        assert!(rt.current_thread.operand_stack.is_empty());
+        assert!(rt.current_thread.runtime_stack.is_empty());
+
+        let abs = bytecode::builtin::abs();
+        let (_, val) = call_closure(rt, "map", &abs, vec![Value::Int(-3)])?;
+        assert_eq!(val, Value::Int(3));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_call_closure_yield() -> Result<()> {
+        let instrs = vec![
+            ByteCode::DONE,
+            ByteCode::YIELD,
+            ByteCode::RESET(FrameType::CallFrame),
+        ];
+        let rt = Runtime::new(instrs);
+        let mut rt = crate::micro_code::spawn(rt, 0)?;
+        rt.current_thread.operand_stack.clear();
+        let f = Value::Closure {
+            fn_type: FnType::User,
+            sym: "Closure".to_string(),
+            prms: vec![].into(),
+            addr: 1,
+            env: W(rt.current_thread.env.clone()),
+        };
+
+        let err = call_closure(rt, "map", &f, vec![]).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "A function called by builtin map yielded or blocked, which only the top level of a thread can do"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_call_closure_recursive() -> Result<()> {
+        // every level of the recursion is a callback of fold, nested far deeper than the stack of a test thread
+        // holds without growing it
+        let src = r"
+        fn depth(n: int) -> int {
+            if n == 0 {
+                0
+            } else {
+                fold([n], 0, |acc: int, x: int| depth(x - 1)) + 1
+            }
+        }
+        depth(200)
+        ";
+        let instrs = compiler::compiler::compile_from_string(src, true)?;
+        let mut rt = crate::run(Runtime::new(instrs))?;
+        assert_eq!(rt.current_thread.operand_stack.pop(), Some(Value::Int(200)));
+
+        Ok(())
+    }
 }
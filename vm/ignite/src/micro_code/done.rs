@@ -1,9 +1,11 @@
 use anyhow::{Ok, Result};
 
-use crate::{Runtime, VmError, MAIN_THREAD_ID};
+use crate::{Runtime, MAIN_THREAD_ID};
 
-/// Set the state of the runtime to done if the current thread is the main thread.
-/// Otherwise, set the current thread to zombie and yield to the next ready thread.
+/// Set the state of the runtime to done if the current thread is the main thread, or wait for the other threads
+/// first if the policy of the runtime says to, see [`MainExitPolicy`](crate::MainExitPolicy).
+/// Otherwise, set the current thread to zombie and yield to the next ready thread. A detached thread is dropped
+/// instead, and zombies past the limit of the runtime are reaped, see [`Runtime::bury`].
 ///
 /// # Arguments
 ///
@@ -12,23 +14,18 @@ use crate::{Runtime, VmError, MAIN_THREAD_ID};
 /// # Errors
 ///
 /// * If the current thread is not the main thread and there are no threads in the ready queue.
+/// * If the main thread finishes while other threads are running, and the policy is
+///   [`MainExitPolicy::Error`](crate::MainExitPolicy::Error).
 #[inline]
 pub fn done(mut rt: Runtime) -> Result<Runtime> {
     // If the current thread is the main thread, then we are done
     if rt.current_thread.thread_id == MAIN_THREAD_ID {
-        rt.done = true;
-        Ok(rt)
+        rt.exit_main()
     // Otherwise we will set the current thread to zombie and yield
     } else {
-        let current_thread = rt.current_thread;
-        let current_thread_id = current_thread.thread_id;
-        rt.zombie_threads.insert(current_thread_id, current_thread);
-
-        let next_ready_thread = rt
-            .ready_queue
-            .pop_front()
-            .ok_or(VmError::NoThreadsInReadyQueue)?;
-        rt.current_thread = next_ready_thread;
+        let next_ready_thread = rt.next_ready_thread()?;
+        let current_thread = std::mem::replace(&mut rt.current_thread, next_ready_thread);
+        rt.bury(current_thread);
         Ok(rt)
     }
 }
@@ -1,4 +1,5 @@
 use anyhow::Result;
+use bytecode::FrameType;
 
 use crate::{Runtime, VmError};
 
@@ -10,14 +11,26 @@ use crate::{Runtime, VmError};
 ///
 /// # Errors
 ///
-/// If the runtime stack is empty.
+/// If there is no block frame on top of the runtime stack, i.e. the EXITSCOPE has no matching ENTERSCOPE
+/// in the current function. The runtime stack is left untouched in that case.
 #[inline]
 pub fn exit_scope(mut rt: Runtime) -> Result<Runtime> {
+    let is_block_frame = matches!(
+        rt.current_thread.runtime_stack.last(),
+        Some(frame) if frame.frame_type == FrameType::BlockFrame
+    );
+
+    if !is_block_frame {
+        // The pc has already been incremented past the EXITSCOPE
+        let pc = rt.current_thread.pc.saturating_sub(1);
+        return Err(VmError::ScopeUnderflow { pc }.into());
+    }
+
     let prev_frame = rt
         .current_thread
         .runtime_stack
         .pop()
-        .ok_or(VmError::RuntimeStackUnderflow)?;
+        .expect("Checked that the runtime stack is not empty");
 
     rt.current_thread.env = prev_frame.env.0;
     Ok(rt)
@@ -25,7 +38,7 @@ pub fn exit_scope(mut rt: Runtime) -> Result<Runtime> {
 
 #[cfg(test)]
 mod tests {
-    use bytecode::{weak_clone, Environment, FrameType, StackFrame, Value, W};
+    use bytecode::{weak_clone, Environment, StackFrame, Value, W};
 
     use super::*;
 
@@ -70,4 +83,37 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_exit_scope_underflow() {
+        let mut rt = Runtime::new(vec![]);
+        rt.current_thread.pc = 5;
+
+        let err = exit_scope(rt).err().expect("Should fail");
+        assert!(matches!(
+            err.downcast_ref::<VmError>(),
+            Some(VmError::ScopeUnderflow { pc: 4 })
+        ));
+    }
+
+    #[test]
+    fn test_exit_scope_call_frame() -> Result<()> {
+        let mut rt = Runtime::new(vec![]);
+        let env = weak_clone(&Environment::new_wrapped());
+        rt.current_thread
+            .runtime_stack
+            .push(StackFrame::new_with_address(
+                FrameType::CallFrame,
+                W(env),
+                0,
+            ));
+
+        let err = exit_scope(rt).err().expect("Should fail");
+        assert!(matches!(
+            err.downcast_ref::<VmError>(),
+            Some(VmError::ScopeUnderflow { .. })
+        ));
+
+        Ok(())
+    }
 }
@@ -7,8 +7,7 @@ use super::yield_;
 /// Pop the operand stack for the thread ID to join.
 /// If the thread to join is in zombie state, then the current thread will be set to ready and the result
 /// of the zombie thread will be pushed onto the current thread's operand stack. The zombie thread is deallocated.
-/// If the thread to join is not found, then panic.
-/// Otherwise, the current thread will yield.
+/// If the thread to join has not finished, the current thread will yield and try again.
 ///
 /// # Arguments
 ///
@@ -18,7 +17,7 @@ use super::yield_;
 ///
 /// # Errors
 ///
-/// * If the thread with the given ID is not found in the thread state hashmap.
+/// * If the thread with the given ID can't be joined: it was never spawned, or was detached or reaped.
 /// * If the operand stack is empty.
 /// * If the value on the operand stack is not an integer.
 #[inline]
@@ -32,6 +31,10 @@ pub fn join(mut rt: Runtime) -> Result<Runtime> {
         .try_into()?;
 
     let Some(mut zombie_thread) = rt.zombie_threads.remove(&tid) else {
+        if !rt.is_joinable(tid) {
+            return Err(VmError::NoSuchThread(tid).into());
+        }
+
         // If the thread to join is not found, we need to yield control and try again
         rt.current_thread.pc -= 1; // Decrement the program counter to re-execute the join instruction
         rt.current_thread.operand_stack.push(tid.into()); // Add the pid back to the operand stack
@@ -106,4 +109,22 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_join_03() -> Result<()> {
+        let mut rt = Runtime::default();
+        rt.current_thread.pc = 1; // prevent u64 subtraction overflow
+        rt = spawn(rt, 0)?;
+        rt.detach_thread(MAIN_THREAD_ID + 1)?;
+
+        // A detached thread can't be joined, rather than the join waiting forever
+        let result = join(rt);
+        assert!(result.is_err_and(|e| e.to_string().contains("No thread 2 to join")));
+
+        let mut rt = Runtime::default();
+        rt.current_thread.operand_stack.push(Value::Int(7));
+        assert!(join(rt).is_err());
+
+        Ok(())
+    }
 }
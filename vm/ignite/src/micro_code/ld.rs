@@ -3,7 +3,8 @@ use bytecode::Symbol;
 
 use crate::{Runtime, VmError};
 
-/// Load a value from a symbol.
+/// Load a value from a symbol. Globals are found without walking the whole chain, see
+/// `Runtime::global_fallback_depth`.
 ///
 /// # Arguments
 ///
@@ -22,7 +23,7 @@ pub fn ld(mut rt: Runtime, sym: Symbol) -> Result<Runtime> {
         .upgrade()
         .ok_or(VmError::EnvironmentDroppedError)?
         .borrow()
-        .get(&sym)?;
+        .get_with_fallback(&sym, rt.global_fallback_depth)?;
 
     rt.current_thread.operand_stack.push(val);
     Ok(rt)
@@ -0,0 +1,84 @@
+use anyhow::Result;
+use bytecode::{type_of, Struct, Symbol, Value};
+
+use crate::{Runtime, VmError};
+
+/// Pops a struct off the stack and pushes the value of the given field.
+///
+/// # Arguments
+///
+/// * `rt` - The runtime to execute the instruction on.
+///
+/// * `field` - The name of the field to load.
+///
+/// # Errors
+///
+/// If the stack is empty, the value is not a struct or the struct has no such field.
+#[inline]
+pub fn ld_field(mut rt: Runtime, field: Symbol) -> Result<Runtime> {
+    let val = rt
+        .current_thread
+        .operand_stack
+        .pop()
+        .ok_or(VmError::OperandStackUnderflow)?;
+
+    let s = as_struct(val)?;
+    let Some(val) = s.get(&field) else {
+        return Err(field_not_found(&rt, &s, field).into());
+    };
+
+    rt.current_thread.operand_stack.push(val);
+    Ok(rt)
+}
+
+pub(crate) fn as_struct(val: Value) -> Result<Struct> {
+    match val {
+        Value::Struct(s) => Ok(s),
+        _ => Err(VmError::BadType {
+            expected: "Struct".to_string(),
+            found: type_of(&val).to_string(),
+        }
+        .into()),
+    }
+}
+
+/// Expects the pc to already be past the instruction.
+pub(crate) fn field_not_found(rt: &Runtime, s: &Struct, field: Symbol) -> VmError {
+    VmError::FieldNotFound {
+        name: s.name.clone(),
+        field,
+        pc: rt.current_thread.pc.saturating_sub(1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::micro_code::ldc;
+
+    #[test]
+    fn test_ld_field() {
+        let p = Struct::new("Point".to_string(), vec![("x".to_string(), Value::Int(1))]);
+
+        let mut rt = Runtime::new(vec![]);
+        rt = ldc(rt, p.clone().into()).unwrap();
+        rt = ld_field(rt, "x".to_string()).unwrap();
+        assert_eq!(rt.current_thread.operand_stack.pop(), Some(Value::Int(1)));
+
+        rt = ldc(rt, p.into()).unwrap();
+        let Err(err) = ld_field(rt, "y".to_string()) else {
+            panic!("Should not have field y");
+        };
+        assert!(matches!(
+            err.downcast_ref::<VmError>(),
+            Some(VmError::FieldNotFound { name, field, .. }) if name == "Point" && field == "y"
+        ));
+    }
+
+    #[test]
+    fn test_ld_field_bad_type() {
+        let mut rt = Runtime::new(vec![]);
+        rt = ldc(rt, Value::Int(1)).unwrap();
+        assert!(ld_field(rt, "x".to_string()).is_err());
+    }
+}
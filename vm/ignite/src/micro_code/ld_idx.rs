@@ -0,0 +1,188 @@
+use anyhow::Result;
+use bytecode::{type_of, Array, ByteCodeError, Value};
+
+use crate::{Runtime, VmError};
+
+/// Pops an index and an array off the stack, and pushes the element at that index.
+/// A string can be indexed too, by byte like substring, giving the char that starts at the index.
+///
+/// # Arguments
+///
+/// * `rt` - The runtime to execute the instruction on.
+///
+/// # Errors
+///
+/// If the stack has fewer than two values, the values are not an array and an int,
+/// or the index is out of bounds. Also if the index into a string is inside a character.
+#[inline]
+pub fn ld_idx(mut rt: Runtime) -> Result<Runtime> {
+    let index = rt
+        .current_thread
+        .operand_stack
+        .pop()
+        .ok_or(VmError::OperandStackUnderflow)?;
+    let arr = rt
+        .current_thread
+        .operand_stack
+        .pop()
+        .ok_or(VmError::OperandStackUnderflow)?;
+
+    if let Value::String(s) = &arr {
+        let c = char_at(&rt, s, index)?;
+        rt.current_thread.operand_stack.push(Value::Char(c));
+        return Ok(rt);
+    }
+
+    let (arr, index) = array_and_index(&rt, arr, index)?;
+    let val = arr.borrow()[index].clone();
+
+    rt.current_thread.operand_stack.push(val);
+    Ok(rt)
+}
+
+/// Check the operands of an indexing instruction, returning the backing array and the index into it as a usize.
+/// Indices into a slice are checked against the slice and offset into its array.
+/// Expects the pc to already be past the instruction.
+pub(crate) fn array_and_index(rt: &Runtime, arr: Value, index: Value) -> Result<(Array, usize)> {
+    let (arr, offset, len) = match arr {
+        Value::Array(arr) => {
+            let len = arr.len();
+            (arr, 0, len)
+        }
+        Value::Slice(slice) => (slice.arr, slice.offset, slice.len),
+        _ => {
+            return Err(VmError::BadType {
+                expected: "Array".to_string(),
+                found: type_of(&arr).to_string(),
+            }
+            .into())
+        }
+    };
+
+    let Value::Int(index) = index else {
+        return Err(VmError::BadType {
+            expected: "Int".to_string(),
+            found: type_of(&index).to_string(),
+        }
+        .into());
+    };
+
+    match usize::try_from(index) {
+        Ok(idx) if idx < len => Ok((arr, offset + idx)),
+        _ => Err(VmError::IndexOutOfBounds {
+            index,
+            len,
+            pc: rt.current_thread.pc.saturating_sub(1),
+        }
+        .into()),
+    }
+}
+
+// The char starting at a byte index of the string
+fn char_at(rt: &Runtime, s: &str, index: Value) -> Result<char> {
+    let Value::Int(index) = index else {
+        return Err(VmError::BadType {
+            expected: "Int".to_string(),
+            found: type_of(&index).to_string(),
+        }
+        .into());
+    };
+
+    let idx = usize::try_from(index)
+        .ok()
+        .filter(|idx| *idx < s.len())
+        .ok_or_else(|| VmError::IndexOutOfBounds {
+            index,
+            len: s.len(),
+            pc: rt.current_thread.pc.saturating_sub(1),
+        })?;
+
+    s.get(idx..)
+        .and_then(|rest| rest.chars().next())
+        .ok_or_else(|| ByteCodeError::NotACharBoundary(index).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::micro_code::ldc;
+
+    #[test]
+    fn test_ld_idx() {
+        let mut rt = Runtime::new(vec![]);
+        rt = ldc(rt, vec![Value::Int(1), Value::Int(2)].into()).unwrap();
+        rt = ldc(rt, Value::Int(1)).unwrap();
+        rt = ld_idx(rt).unwrap();
+        assert_eq!(rt.current_thread.operand_stack, vec![Value::Int(2)]);
+    }
+
+    #[test]
+    fn test_ld_idx_string() -> Result<()> {
+        let mut rt = Runtime::new(vec![]);
+        rt = ldc(rt, "hé!".into())?;
+        rt = ldc(rt, Value::Int(1))?;
+        rt = ld_idx(rt)?;
+        assert_eq!(rt.current_thread.operand_stack, vec![Value::Char('é')]);
+
+        // the é is two bytes, so 2 is inside it and 3 is the !
+        for (index, ok) in [(2, false), (3, true), (4, false)] {
+            let mut rt = Runtime::new(vec![]);
+            rt = ldc(rt, "hé!".into())?;
+            rt = ldc(rt, Value::Int(index))?;
+            assert_eq!(ld_idx(rt).is_ok(), ok, "index {}", index);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_ld_idx_slice() {
+        let arr = Array::new(vec![Value::Int(1), Value::Int(2), Value::Int(3)]);
+        let mut rt = Runtime::new(vec![]);
+        rt = ldc(rt, bytecode::Slice::new(arr, 1, 1).into()).unwrap();
+        rt = ldc(rt, Value::Int(0)).unwrap();
+        rt = ld_idx(rt).unwrap();
+        assert_eq!(rt.current_thread.operand_stack, vec![Value::Int(2)]);
+
+        // the rest of the array is out of view
+        rt = ldc(
+            rt,
+            bytecode::Slice::new(Array::new(vec![Value::Int(1); 3]), 1, 1).into(),
+        )
+        .unwrap();
+        rt = ldc(rt, Value::Int(1)).unwrap();
+        assert!(ld_idx(rt).is_err());
+    }
+
+    #[test]
+    fn test_ld_idx_out_of_bounds() {
+        for index in [2, -1] {
+            let mut rt = Runtime::new(vec![]);
+            rt.current_thread.pc = 8;
+            rt = ldc(rt, vec![Value::Int(1), Value::Int(2)].into()).unwrap();
+            rt = ldc(rt, Value::Int(index)).unwrap();
+
+            let Err(err) = ld_idx(rt) else {
+                panic!("Should be out of bounds");
+            };
+            match err.downcast_ref::<VmError>() {
+                Some(VmError::IndexOutOfBounds { index: i, len, pc }) => {
+                    assert_eq!((*i, *len, *pc), (index, 2, 7))
+                }
+                _ => panic!("Expected IndexOutOfBounds, got: {err}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_ld_idx_bad_type() {
+        let mut rt = Runtime::new(vec![]);
+        rt = ldc(rt, Value::Int(1)).unwrap();
+        rt = ldc(rt, Value::Int(0)).unwrap();
+        assert!(ld_idx(rt).is_err());
+
+        let mut rt = Runtime::new(vec![]);
+        rt = ldc(rt, vec![Value::Int(1)].into()).unwrap();
+        rt = ldc(rt, Value::Bool(true)).unwrap();
+        assert!(ld_idx(rt).is_err());
+    }
+}
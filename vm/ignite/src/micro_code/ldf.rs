@@ -1,9 +1,10 @@
 use anyhow::Result;
-use bytecode::{FnType, Symbol, Value, W};
+use bytecode::{Environment, FnType, Symbol, Value, W};
 
 use crate::Runtime;
 
 /// Load a closure object onto the operand stack.
+/// With eager reset the environment the closure captures is marked as escaped, so it isn't dropped by a RESET.
 ///
 /// # Arguments
 ///
@@ -18,10 +19,14 @@ use crate::Runtime;
 /// Infallible.
 #[inline]
 pub fn ldf(mut rt: Runtime, addr: usize, prms: Vec<Symbol>) -> Result<Runtime> {
+    if rt.eager_reset {
+        Environment::set_escaped(&rt.current_thread.env);
+    }
+
     let closure = Value::Closure {
         fn_type: FnType::User,
         sym: "Closure".to_string(),
-        prms,
+        prms: prms.into(),
         addr,
         env: W(rt.current_thread.env.clone()),
     };
@@ -45,7 +50,7 @@ mod tests {
             &Value::Closure {
                 fn_type: FnType::User,
                 sym: "Closure".to_string(),
-                prms: vec!["y".to_string()],
+                prms: vec!["y".to_string()].into(),
                 addr: 0,
                 env: W(rt.current_thread.env.clone()),
             }
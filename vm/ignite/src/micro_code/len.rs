@@ -0,0 +1,61 @@
+use anyhow::Result;
+use bytecode::{type_of, Value};
+
+use crate::{Runtime, VmError};
+
+/// Pops an array or slice off the stack and pushes its length.
+///
+/// # Arguments
+///
+/// * `rt` - The runtime to execute the instruction on.
+///
+/// # Errors
+///
+/// If the stack is empty or the value is not an array or slice.
+#[inline]
+pub fn len(mut rt: Runtime) -> Result<Runtime> {
+    let arr = rt
+        .current_thread
+        .operand_stack
+        .pop()
+        .ok_or(VmError::OperandStackUnderflow)?;
+
+    let len = match arr {
+        Value::Array(arr) => arr.len(),
+        Value::Slice(slice) => slice.len,
+        _ => {
+            return Err(VmError::BadType {
+                expected: "Array".to_string(),
+                found: type_of(&arr).to_string(),
+            }
+            .into())
+        }
+    };
+
+    rt.current_thread.operand_stack.push(Value::Int(len as i64));
+    Ok(rt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::micro_code::{ldc, slice};
+
+    #[test]
+    fn test_len() {
+        let mut rt = Runtime::new(vec![]);
+        rt = ldc(rt, vec![Value::Int(1), Value::Int(2), Value::Int(3)].into()).unwrap();
+        rt = len(rt).unwrap();
+        assert_eq!(rt.current_thread.operand_stack.pop(), Some(Value::Int(3)));
+
+        rt = ldc(rt, vec![Value::Int(1), Value::Int(2), Value::Int(3)].into()).unwrap();
+        rt = ldc(rt, Value::Int(1)).unwrap();
+        rt = ldc(rt, Value::Unit).unwrap();
+        rt = slice(rt).unwrap();
+        rt = len(rt).unwrap();
+        assert_eq!(rt.current_thread.operand_stack.pop(), Some(Value::Int(2)));
+
+        rt = ldc(rt, Value::Int(1)).unwrap();
+        assert!(len(rt).is_err());
+    }
+}
@@ -0,0 +1,67 @@
+use anyhow::Result;
+use bytecode::{Map, Value};
+
+use crate::{Runtime, VmError};
+
+/// Pops the given number of key and value pairs off the stack and pushes a map holding them.
+/// Each key is below its value, and a key given twice has the value given last.
+///
+/// # Arguments
+///
+/// * `rt` - The runtime to create the map in.
+///
+/// * `len` - The number of key and value pairs.
+///
+/// # Errors
+///
+/// If the stack has fewer values than the pairs need, or a key is not an int, string or bool.
+#[inline]
+pub fn map(mut rt: Runtime, len: usize) -> Result<Runtime> {
+    let stack_len = rt.current_thread.operand_stack.len();
+    if stack_len < 2 * len {
+        return Err(VmError::OperandStackUnderflow.into());
+    }
+
+    let vals = rt
+        .current_thread
+        .operand_stack
+        .split_off(stack_len - 2 * len);
+    let m = Map::new();
+    for pair in vals.chunks_exact(2) {
+        m.insert(pair[0].clone(), pair[1].clone())?;
+    }
+
+    rt.current_thread.operand_stack.push(Value::Map(m));
+    Ok(rt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::micro_code::ldc;
+
+    #[test]
+    fn test_map() -> Result<()> {
+        let mut rt = Runtime::new(vec![]);
+        rt = ldc(rt, Value::Unit)?;
+        rt = ldc(rt, "a".into())?;
+        rt = ldc(rt, Value::Int(1))?;
+        rt = ldc(rt, "a".into())?;
+        rt = ldc(rt, Value::Int(2))?;
+        rt = map(rt, 2)?;
+
+        let Some(Value::Map(m)) = rt.current_thread.operand_stack.pop() else {
+            panic!("expected a map");
+        };
+        assert_eq!(m.entries(), vec![("a".into(), Value::Int(2))]);
+        assert_eq!(rt.current_thread.operand_stack, vec![Value::Unit]);
+
+        rt = ldc(rt, Value::Float(1.0))?;
+        rt = ldc(rt, Value::Int(1))?;
+        assert!(map(rt, 1).is_err());
+
+        let rt = Runtime::new(vec![]);
+        assert!(map(rt, 1).is_err());
+        Ok(())
+    }
+}
@@ -1,7 +1,10 @@
 pub use apply_builtin::apply_builtin;
+pub use array::{array, array_fill};
 pub use assign::assign;
+pub use assign_field::assign_field;
+pub use assign_idx::assign_idx;
 pub use binop::binop;
-pub use call::call;
+pub use call::{call, call_closure};
 pub use done::done;
 pub use enter_scope::enter_scope;
 pub use exit_scope::exit_scope;
@@ -9,19 +12,34 @@ pub use goto::goto;
 pub use jof::jof;
 pub use join::join;
 pub use ld::ld;
+pub use ld_field::ld_field;
+pub use ld_idx::ld_idx;
 pub use ldc::ldc;
 pub use ldf::ldf;
+pub use len::len;
+pub use map::map;
 pub use pop::pop;
 pub use post::post;
+pub use recv::recv;
 pub use reset::reset;
 pub use sem_create::sem_create;
+pub use send::send;
+pub use slice::slice;
 pub use spawn::spawn;
+pub use spawn_iso::spawn_iso;
+pub use struct_::struct_;
+pub use try_join::try_join;
+pub use tuple::{ld_tuple, tuple, unpack};
 pub use unop::unop;
+pub use variant::{is_variant, ld_variant, variant};
 pub use wait::wait;
 pub use yield_::yield_; // yield is a reserved keyword in Rust
 
 mod apply_builtin;
+mod array;
 mod assign;
+mod assign_field;
+mod assign_idx;
 mod binop;
 mod call;
 mod done;
@@ -31,13 +49,25 @@ mod goto;
 mod jof;
 mod join;
 mod ld;
+mod ld_field;
+mod ld_idx;
 mod ldc;
 mod ldf;
+mod len;
+mod map;
 mod pop;
 mod post;
+mod recv;
 mod reset;
 mod sem_create;
+mod send;
+mod slice;
 mod spawn;
+mod spawn_iso;
+mod struct_; // struct is a reserved keyword in Rust
+mod try_join;
+mod tuple;
 mod unop;
+mod variant;
 mod wait;
 mod yield_; // yield is a reserved keyword in Rust
@@ -1,7 +1,7 @@
 use anyhow::{Ok, Result};
 use bytecode::Semaphore;
 
-use crate::{Runtime, VmError};
+use crate::{BlockedOn, Runtime, VmError};
 
 /// Pops a value off the stack.
 /// The value is expected to be a semaphore.
@@ -17,6 +17,7 @@ use crate::{Runtime, VmError};
 ///
 /// If the stack is empty.
 /// If the top value on stack is not a semaphore.
+/// If the semaphore is poisoned.
 #[inline]
 pub fn post(mut rt: Runtime) -> Result<Runtime> {
     let sem: Semaphore = rt
@@ -26,14 +27,14 @@ pub fn post(mut rt: Runtime) -> Result<Runtime> {
         .ok_or(VmError::OperandStackUnderflow)?
         .try_into()?;
 
-    let mut sem_guard = sem.lock().unwrap();
+    let mut sem_guard = sem.lock().map_err(|_| VmError::SemaphorePoisoned)?;
     *sem_guard += 1;
 
     // Find the first blocked thread that is waiting on the semaphore.
     let blocked_thread = rt
         .blocked_queue
         .iter()
-        .position(|(_, blocking_sem)| blocking_sem == &sem)
+        .position(|(_, blocked_on)| matches!(blocked_on, BlockedOn::Semaphore(s) if s == &sem))
         .map(|i| rt.blocked_queue.remove(i));
 
     let Some(Some((blocked_thread, _))) = blocked_thread else {
@@ -77,6 +78,31 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_post_poisoned() -> Result<()> {
+        let mut rt = Runtime::default();
+        let sem = Semaphore::new(0);
+        // a host thread panicking while it holds the semaphore poisons it
+        let holder = sem.clone();
+        std::thread::spawn(move || {
+            let _guard = holder.lock();
+            panic!("host thread panicked");
+        })
+        .join()
+        .unwrap_err();
+
+        rt.current_thread.operand_stack.push(sem.into());
+        let Err(err) = post(rt) else {
+            panic!("post on a poisoned semaphore should fail");
+        };
+        assert!(matches!(
+            err.downcast_ref::<VmError>(),
+            Some(VmError::SemaphorePoisoned)
+        ));
+
+        Ok(())
+    }
+
     #[test]
     fn test_post_02() -> Result<()> {
         let mut rt = Runtime::default();
@@ -0,0 +1,108 @@
+use anyhow::{Ok, Result};
+use bytecode::Channel;
+
+use crate::{BlockedOn, Runtime, VmError};
+
+/// Pops a value off the stack.
+/// The value is expected to be a channel.
+/// If a value is waiting in the channel, it is removed and pushed onto the stack.
+/// If the channel is empty, the current thread is blocked until a value is sent on the channel.
+///   - The current thread is moved to the blocked queue.
+///   - The next ready thread is popped from the ready queue and set as the current thread.
+///   - The thread that sends the value pushes it onto the stack of the blocked thread.
+///
+/// # Arguments
+///
+/// * `rt` - The runtime to pop the value off of.
+///
+/// # Errors
+///
+/// If the stack is empty.
+/// If the top value on stack is not a channel.
+/// If there are no threads in the ready queue when the current thread is blocked.
+#[inline]
+pub fn recv(mut rt: Runtime) -> Result<Runtime> {
+    let ch: Channel = rt
+        .current_thread
+        .operand_stack
+        .pop()
+        .ok_or(VmError::OperandStackUnderflow)?
+        .try_into()?;
+
+    if let Some(val) = ch.recv() {
+        rt.current_thread.operand_stack.push(val);
+        return Ok(rt);
+    }
+
+    // Move the current thread to the blocked queue and pop the next ready thread.
+    let next_ready_thread = rt.next_ready_thread()?;
+
+    let current_thread = std::mem::replace(&mut rt.current_thread, next_ready_thread);
+    rt.blocked_queue
+        .push_back((current_thread, BlockedOn::Channel(ch)));
+    Ok(rt)
+}
+
+#[cfg(test)]
+mod tests {
+    use bytecode::Value;
+
+    use crate::{
+        extend_environment,
+        micro_code::{ld, spawn},
+        MAIN_THREAD_ID,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_recv_01() -> Result<()> {
+        let mut rt = Runtime::default();
+        let ch = Channel::new();
+        ch.send(Value::Int(1));
+        ch.send(Value::Int(2));
+        let current_env = rt.current_thread.env.clone();
+        rt = extend_environment(rt, current_env, vec!["ch"], vec![ch.clone()])?;
+        rt = ld(rt, "ch".into())?;
+        rt = recv(rt)?;
+
+        // Values are received in the order they were sent.
+        assert_eq!(rt.current_thread.thread_id, MAIN_THREAD_ID);
+        assert_eq!(rt.current_thread.operand_stack.pop(), Some(Value::Int(1)));
+        assert_eq!(ch.values(), vec![Value::Int(2)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_recv_02() -> Result<()> {
+        let mut rt = Runtime::default();
+        let ch = Channel::new();
+        let current_env = rt.current_thread.env.clone();
+        rt = extend_environment(rt, current_env, vec!["ch"], vec![ch.clone()])?;
+        rt = spawn(rt, 0)?; // spawn a child thread to populate ready queue
+        rt = ld(rt, "ch".into())?;
+        rt = recv(rt)?;
+
+        // The channel is empty, so the main thread blocks and the child thread runs.
+        assert_eq!(rt.current_thread.thread_id, MAIN_THREAD_ID + 1);
+        let (blocked, blocked_on) = rt.blocked_queue.pop_front().unwrap();
+        assert_eq!(blocked.thread_id, MAIN_THREAD_ID);
+        assert_eq!(blocked_on, BlockedOn::Channel(ch));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_recv_deadlock() -> Result<()> {
+        let mut rt = Runtime::default();
+        let current_env = rt.current_thread.env.clone();
+        rt = extend_environment(rt, current_env, vec!["ch"], vec![Channel::new()])?;
+        rt = ld(rt, "ch".into())?;
+
+        // Nothing else can run to send a value.
+        assert!(recv(rt).is_err());
+
+        Ok(())
+    }
+}
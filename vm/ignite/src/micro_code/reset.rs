@@ -1,10 +1,20 @@
+use std::{cell::RefCell, rc::Weak};
+
 use crate::{Runtime, VmError};
 use anyhow::Result;
-use bytecode::FrameType;
+use bytecode::{Environment, FrameType, W};
 
 /// Reset the runtime to the last frame of the given type. This will pop all frames up to and including
 /// the last frame of the given type.
 ///
+/// Only block frames may be unwound on the way to the target frame. Reaching another function's call
+/// frame first means the reset would jump across a function boundary, so it is rejected and the runtime
+/// stack is left untouched.
+///
+/// With eager reset the environments made since the target frame was pushed, which are left by the reset, are
+/// dropped there and then instead of by the garbage collector, unless a closure or thread may still refer to them.
+/// So the frames of a deep recursion are freed as it returns.
+///
 /// # Arguments
 ///
 /// * `rt` - The runtime to reset.
@@ -13,31 +23,64 @@ use bytecode::FrameType;
 ///
 /// # Errors
 ///
-/// If the runtime stack underflows. i.e. there are no frames of the given type.
+/// * `VmError::ResetFrameMismatch` if a call frame is found before a block frame when resetting to a block frame.
+/// * `VmError::ResetUnderflow` if there are no frames of the given type.
 #[inline]
 pub fn reset(mut rt: Runtime, ft: FrameType) -> Result<Runtime> {
-    loop {
-        let frame = rt
-            .current_thread
-            .runtime_stack
-            .pop()
-            .ok_or(VmError::RuntimeStackUnderflow)?;
-
-        if frame.frame_type != ft {
-            continue;
+    // The pc has already been incremented past the RESET
+    let pc = rt.current_thread.pc.saturating_sub(1);
+    let stack = &mut rt.current_thread.runtime_stack;
+
+    let Some(idx) = stack
+        .iter()
+        .rposition(|frame| frame.frame_type == ft || frame.frame_type == FrameType::CallFrame)
+    else {
+        return Err(VmError::ResetUnderflow {
+            pc,
+            expected: format!("{:?}", ft),
         }
-
-        if let Some(address) = frame.address {
-            rt.current_thread.pc = address;
+        .into());
+    };
+
+    if stack[idx].frame_type != ft {
+        return Err(VmError::ResetFrameMismatch {
+            pc,
+            expected: format!("{:?}", ft),
+            found: format!("{:?}", stack[idx].frame_type),
         }
+        .into());
+    }
 
-        rt.current_thread.env = frame.env.0;
-        break;
+    let inner = stack.split_off(idx + 1);
+    let frame = stack.pop().expect("Target frame is on the stack");
+
+    if rt.eager_reset {
+        // The current environment, and the one each frame above the target saved when it was pushed
+        let envs = inner
+            .into_iter()
+            .map(|frame| frame.env.0)
+            .chain(std::iter::once(rt.current_thread.env.clone()));
+        drop_unescaped(&mut rt, envs);
+    }
+
+    if let Some(address) = frame.address {
+        rt.current_thread.pc = address;
     }
 
+    rt.current_thread.env = frame.env.0;
     Ok(rt)
 }
 
+// Remove the environments that haven't escaped from the registry, which holds the only strong reference to them.
+// A closure or thread made in a child of one marks it as escaped too, so nothing can refer to the rest
+fn drop_unescaped(rt: &mut Runtime, envs: impl Iterator<Item = Weak<RefCell<Environment>>>) {
+    for env in envs.filter_map(|env| env.upgrade()) {
+        if !env.borrow().escaped.get() {
+            rt.env_registry.remove(&W(env));
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -45,7 +88,7 @@ mod tests {
 
     #[test]
     fn test_reset_restore_env() -> Result<()> {
-        let mut rt = Runtime::new(vec![ByteCode::RESET(FrameType::BlockFrame)]);
+        let mut rt = Runtime::new(vec![ByteCode::RESET(FrameType::CallFrame)]);
 
         let env_a = Environment::new_wrapped();
         let env_b = Environment::new_wrapped();
@@ -56,16 +99,16 @@ mod tests {
         env_c.borrow_mut().set("a", 42);
 
         let some_frame = StackFrame::new(FrameType::CallFrame, env_a_weak);
-        let block_frame = StackFrame::new(FrameType::BlockFrame, env_b_weak);
-        let call_frame = StackFrame::new(FrameType::CallFrame, env_c_weak);
+        let call_frame = StackFrame::new(FrameType::CallFrame, env_b_weak);
+        let block_frame = StackFrame::new(FrameType::BlockFrame, env_c_weak);
 
         rt.current_thread.runtime_stack.push(some_frame);
-        rt.current_thread.runtime_stack.push(block_frame);
         rt.current_thread.runtime_stack.push(call_frame);
+        rt.current_thread.runtime_stack.push(block_frame);
 
         assert!(rt.current_thread.runtime_stack.len() == 3);
 
-        rt = reset(rt, FrameType::BlockFrame).unwrap();
+        rt = reset(rt, FrameType::CallFrame).unwrap();
 
         assert!(rt.current_thread.runtime_stack.len() == 1);
         assert_eq!(
@@ -83,7 +126,7 @@ mod tests {
 
     #[test]
     fn test_set_pc() {
-        let mut rt = Runtime::new(vec![ByteCode::RESET(FrameType::BlockFrame)]);
+        let mut rt = Runtime::new(vec![ByteCode::RESET(FrameType::CallFrame)]);
 
         let env_a = Environment::new_wrapped();
         let env_b = Environment::new_wrapped();
@@ -94,18 +137,51 @@ mod tests {
         env_c.borrow_mut().set("a", 42);
 
         let some_frame = StackFrame::new(FrameType::CallFrame, env_a_weak);
-        let block_frame = StackFrame::new_with_address(FrameType::BlockFrame, env_c_weak, 123);
-        let call_frame = StackFrame::new(FrameType::CallFrame, env_b_weak);
+        let call_frame = StackFrame::new_with_address(FrameType::CallFrame, env_c_weak, 123);
+        let block_frame = StackFrame::new(FrameType::BlockFrame, env_b_weak);
 
         rt.current_thread.runtime_stack.push(some_frame);
-        rt.current_thread.runtime_stack.push(block_frame);
         rt.current_thread.runtime_stack.push(call_frame);
+        rt.current_thread.runtime_stack.push(block_frame);
 
         assert!(rt.current_thread.runtime_stack.len() == 3);
 
-        rt = reset(rt, FrameType::BlockFrame).unwrap();
+        rt = reset(rt, FrameType::CallFrame).unwrap();
 
         assert!(rt.current_thread.runtime_stack.len() == 1);
         assert_eq!(rt.current_thread.pc, 123);
     }
+
+    #[test]
+    fn test_reset_block_frame_across_call_frame() {
+        let mut rt = Runtime::new(vec![ByteCode::RESET(FrameType::BlockFrame)]);
+        rt.current_thread.pc = 1;
+
+        let env = Environment::new_wrapped();
+        let block_frame = StackFrame::new(FrameType::BlockFrame, W(weak_clone(&env)));
+        let call_frame = StackFrame::new(FrameType::CallFrame, W(weak_clone(&env)));
+        rt.current_thread.runtime_stack.push(block_frame);
+        rt.current_thread.runtime_stack.push(call_frame);
+
+        let err = reset(rt, FrameType::BlockFrame).err().expect("Should fail");
+        assert!(matches!(
+            err.downcast_ref::<VmError>(),
+            Some(VmError::ResetFrameMismatch { pc: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn test_reset_no_frame_of_type() {
+        let mut rt = Runtime::new(vec![ByteCode::RESET(FrameType::CallFrame)]);
+
+        let env = Environment::new_wrapped();
+        let block_frame = StackFrame::new(FrameType::BlockFrame, W(weak_clone(&env)));
+        rt.current_thread.runtime_stack.push(block_frame);
+
+        let err = reset(rt, FrameType::CallFrame).err().expect("Should fail");
+        assert!(matches!(
+            err.downcast_ref::<VmError>(),
+            Some(VmError::ResetUnderflow { .. })
+        ));
+    }
 }
@@ -0,0 +1,104 @@
+use anyhow::{Ok, Result};
+use bytecode::Channel;
+
+use crate::{BlockedOn, Runtime, VmError};
+
+/// Pops a value and then a channel off the stack, and sends the value on the channel.
+/// If a thread is blocked receiving on this channel, the value is handed straight to the first such thread,
+/// which is moved to the ready queue. Otherwise the value waits in the channel until it is received.
+/// Sending never blocks, the current thread continues execution.
+///
+/// # Arguments
+///
+/// * `rt` - The runtime to pop the values off of.
+///
+/// # Errors
+///
+/// If the stack has fewer than two values.
+/// If the value below the top of the stack is not a channel.
+#[inline]
+pub fn send(mut rt: Runtime) -> Result<Runtime> {
+    let val = rt
+        .current_thread
+        .operand_stack
+        .pop()
+        .ok_or(VmError::OperandStackUnderflow)?;
+
+    let ch: Channel = rt
+        .current_thread
+        .operand_stack
+        .pop()
+        .ok_or(VmError::OperandStackUnderflow)?
+        .try_into()?;
+
+    // Find the first blocked thread that is receiving on the channel.
+    let receiver = rt
+        .blocked_queue
+        .iter()
+        .position(|(_, blocked_on)| matches!(blocked_on, BlockedOn::Channel(c) if c == &ch))
+        .and_then(|i| rt.blocked_queue.remove(i));
+
+    let Some((mut receiver, _)) = receiver else {
+        ch.send(val);
+        return Ok(rt);
+    };
+
+    // The receiver is past its RECV, so it continues with the value on top of its stack.
+    receiver.operand_stack.push(val);
+    rt.ready_queue.push_back(receiver);
+    Ok(rt)
+}
+
+#[cfg(test)]
+mod tests {
+    use bytecode::Value;
+
+    use crate::{
+        extend_environment,
+        micro_code::{ld, ldc, recv, spawn, yield_},
+        MAIN_THREAD_ID,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_send_01() -> Result<()> {
+        let mut rt = Runtime::default();
+        let ch = Channel::new();
+        let current_env = rt.current_thread.env.clone();
+        rt = extend_environment(rt, current_env, vec!["ch"], vec![ch.clone()])?;
+        rt = ld(rt, "ch".into())?;
+        rt = ldc(rt, Value::Int(42))?;
+        rt = send(rt)?;
+
+        // No thread is receiving, so the value waits in the channel.
+        assert_eq!(rt.current_thread.thread_id, MAIN_THREAD_ID);
+        assert_eq!(ch.values(), vec![Value::Int(42)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_send_02() -> Result<()> {
+        let mut rt = Runtime::default();
+        let ch = Channel::new();
+        let current_env = rt.current_thread.env.clone();
+        rt = extend_environment(rt, current_env, vec!["ch"], vec![ch.clone()])?;
+        rt = spawn(rt, 0)?; // spawn a child thread to populate ready queue
+        rt = yield_(rt)?; // yield the current thread to child thread
+        rt = ld(rt, "ch".into())?;
+        rt = recv(rt)?; // child blocks, main thread runs
+        rt = ld(rt, "ch".into())?;
+        rt = ldc(rt, Value::Int(42))?;
+        rt = send(rt)?;
+
+        // The value goes straight to the child thread, which is ready again.
+        assert!(ch.is_empty());
+        let mut child = rt.ready_queue.pop_front().unwrap();
+        assert_eq!(child.thread_id, MAIN_THREAD_ID + 1);
+        assert_eq!(child.operand_stack.pop(), Some(Value::Int(42)));
+        assert!(rt.blocked_queue.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,134 @@
+use anyhow::Result;
+use bytecode::{type_of, Slice, Value};
+
+use crate::{Runtime, VmError};
+
+/// Pops an end index, a start index and an array or slice off the stack, and pushes a slice
+/// viewing the elements from start up to but not including end. A Unit end slices to the end.
+/// Slicing a slice gives a view of the same backing array.
+///
+/// # Arguments
+///
+/// * `rt` - The runtime to execute the instruction on.
+///
+/// # Errors
+///
+/// If the stack has fewer than three values, the values have the wrong types,
+/// or the range is not within the array or slice.
+#[inline]
+pub fn slice(mut rt: Runtime) -> Result<Runtime> {
+    let end = rt
+        .current_thread
+        .operand_stack
+        .pop()
+        .ok_or(VmError::OperandStackUnderflow)?;
+    let start = rt
+        .current_thread
+        .operand_stack
+        .pop()
+        .ok_or(VmError::OperandStackUnderflow)?;
+    let arr = rt
+        .current_thread
+        .operand_stack
+        .pop()
+        .ok_or(VmError::OperandStackUnderflow)?;
+
+    let (arr, offset, len) = match arr {
+        Value::Array(arr) => {
+            let len = arr.len();
+            (arr, 0, len)
+        }
+        Value::Slice(slice) => (slice.arr, slice.offset, slice.len),
+        _ => {
+            return Err(VmError::BadType {
+                expected: "Array".to_string(),
+                found: type_of(&arr).to_string(),
+            }
+            .into())
+        }
+    };
+
+    let start = match start {
+        Value::Int(start) => start,
+        _ => {
+            return Err(VmError::BadType {
+                expected: "Int".to_string(),
+                found: type_of(&start).to_string(),
+            }
+            .into())
+        }
+    };
+
+    let end = match end {
+        Value::Int(end) => end,
+        Value::Unit => len as i64,
+        _ => {
+            return Err(VmError::BadType {
+                expected: "Int".to_string(),
+                found: type_of(&end).to_string(),
+            }
+            .into())
+        }
+    };
+
+    let in_bounds = 0 <= start && start <= end && usize::try_from(end).is_ok_and(|e| e <= len);
+    if !in_bounds {
+        return Err(VmError::SliceOutOfBounds {
+            start,
+            end,
+            len,
+            pc: rt.current_thread.pc.saturating_sub(1),
+        }
+        .into());
+    }
+
+    let slice = Slice::new(arr, offset + start as usize, (end - start) as usize);
+    rt.current_thread.operand_stack.push(slice.into());
+    Ok(rt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::micro_code::ldc;
+
+    #[test]
+    fn test_slice() {
+        let mut rt = Runtime::new(vec![]);
+        rt = ldc(rt, vec![Value::Int(1), Value::Int(2), Value::Int(3)].into()).unwrap();
+        rt = ldc(rt, Value::Int(1)).unwrap();
+        rt = ldc(rt, Value::Unit).unwrap();
+        rt = slice(rt).unwrap();
+
+        // slice of the slice is offset from the start of the array
+        rt = ldc(rt, Value::Int(1)).unwrap();
+        rt = ldc(rt, Value::Int(2)).unwrap();
+        rt = slice(rt).unwrap();
+
+        let Some(Value::Slice(s)) = rt.current_thread.operand_stack.pop() else {
+            panic!("Expected a slice");
+        };
+        assert_eq!((s.offset, s.len), (2, 1));
+        assert_eq!(s.to_vec(), vec![Value::Int(3)]);
+    }
+
+    #[test]
+    fn test_slice_out_of_bounds() {
+        let mut rt = Runtime::new(vec![]);
+        rt = ldc(rt, vec![Value::Int(1), Value::Int(2)].into()).unwrap();
+        rt = ldc(rt, Value::Int(2)).unwrap();
+        rt = ldc(rt, Value::Int(1)).unwrap();
+        let Err(err) = slice(rt) else {
+            panic!("Start after end should fail");
+        };
+        assert!(matches!(
+            err.downcast_ref::<VmError>(),
+            Some(VmError::SliceOutOfBounds {
+                start: 2,
+                end: 1,
+                len: 2,
+                ..
+            })
+        ));
+    }
+}
@@ -1,8 +1,14 @@
 use anyhow::Result;
+use bytecode::Environment;
 
 use crate::Runtime;
 
-/// Spawn a child thread that clones the current/parent thread at the time of the spawn.
+/// Spawn a child thread that starts in the environment of the current/parent thread at the time of the spawn.
+/// The child thread starts with fresh stacks: nothing on the parent's operand or runtime stack is copied, so
+/// spawning costs the same however deep the parent is. The child only sees the parent through the captured
+/// environment, which both threads share. The compiler evaluates the args of `spawn f(..)` into a frame of
+/// their own before the spawn, so the environment captured holds their values at the spawn.
+/// With eager reset the shared environment is marked as escaped, so the parent doesn't drop it with a RESET.
 /// The child thread is given a unique thread ID.
 /// The child thread is added to the back of the ready queue.
 /// This thread ID is pushed onto the operand stack of the parent thread.
@@ -19,6 +25,10 @@ use crate::Runtime;
 /// Infallible.
 #[inline]
 pub fn spawn(mut rt: Runtime, addr: usize) -> Result<Runtime> {
+    if rt.eager_reset {
+        Environment::set_escaped(&rt.current_thread.env);
+    }
+
     rt.thread_count += 1;
 
     let child_thread_id = rt.thread_count;
@@ -35,7 +45,12 @@ pub fn spawn(mut rt: Runtime, addr: usize) -> Result<Runtime> {
 
 #[cfg(test)]
 mod tests {
+    use std::rc::Weak;
+
+    use bytecode::Value;
+
     use super::*;
+    use crate::micro_code;
 
     #[test]
     fn test_spawn() -> Result<()> {
@@ -45,4 +60,36 @@ mod tests {
         assert_eq!(rt.ready_queue.len(), 1);
         Ok(())
     }
+
+    #[test]
+    fn test_spawn_fresh_stacks() -> Result<()> {
+        let rt = Runtime::new(vec![]);
+        let rt = micro_code::enter_scope(rt, vec!["f".into()])?;
+        let rt = micro_code::ldf(rt, 0, vec![])?;
+        let mut rt = micro_code::assign(rt, "f".into())?;
+        for _ in 0..3 {
+            rt = micro_code::ld(rt, "f".into())?;
+            rt = micro_code::call(rt, 0)?;
+        }
+        rt = micro_code::ldc(rt, Value::Int(42))?;
+        let frames = rt.current_thread.runtime_stack.len();
+        let operands = rt.current_thread.operand_stack.len();
+
+        let rt = spawn(rt, 7)?;
+        let child = rt.ready_queue.back().expect("child should be queued");
+
+        assert_eq!(child.operand_stack, vec![Value::Int(0)]);
+        assert!(child.runtime_stack.is_empty());
+        assert_eq!(child.pc, 7);
+        assert!(Weak::ptr_eq(&child.env, &rt.current_thread.env));
+
+        // the parent keeps its stacks, with the child's id on top
+        assert_eq!(rt.current_thread.runtime_stack.len(), frames);
+        assert_eq!(rt.current_thread.operand_stack.len(), operands + 1);
+        assert_eq!(
+            rt.current_thread.operand_stack.last(),
+            Some(&Value::Int(rt.thread_count))
+        );
+        Ok(())
+    }
 }
@@ -0,0 +1,64 @@
+use anyhow::Result;
+use bytecode::{Struct, Symbol};
+
+use crate::{Runtime, VmError};
+
+/// Pops a value for each field off the stack and pushes a struct holding them.
+/// The value that was deepest in the stack goes to the first field.
+///
+/// # Arguments
+///
+/// * `rt` - The runtime to create the struct in.
+///
+/// * `name` - The name of the struct.
+///
+/// * `fields` - The names of the fields, in the order their values were pushed.
+///
+/// # Errors
+///
+/// If the stack has fewer values than there are fields.
+#[inline]
+pub fn struct_(mut rt: Runtime, name: Symbol, fields: Vec<Symbol>) -> Result<Runtime> {
+    let stack_len = rt.current_thread.operand_stack.len();
+    if stack_len < fields.len() {
+        return Err(VmError::OperandStackUnderflow.into());
+    }
+
+    let vals = rt
+        .current_thread
+        .operand_stack
+        .split_off(stack_len - fields.len());
+    let fields = fields.into_iter().zip(vals).collect();
+
+    rt.current_thread
+        .operand_stack
+        .push(Struct::new(name, fields).into());
+    Ok(rt)
+}
+
+#[cfg(test)]
+mod tests {
+    use bytecode::Value;
+
+    use super::*;
+    use crate::micro_code::ldc;
+
+    #[test]
+    fn test_struct() {
+        let mut rt = Runtime::new(vec![]);
+        rt = ldc(rt, Value::Unit).unwrap();
+        rt = ldc(rt, Value::Int(1)).unwrap();
+        rt = ldc(rt, Value::Float(2.0)).unwrap();
+        rt = struct_(rt, "Point".to_string(), vec!["x".into(), "y".into()]).unwrap();
+
+        let Some(Value::Struct(p)) = rt.current_thread.operand_stack.pop() else {
+            panic!("Should be a struct");
+        };
+        assert_eq!(p.name, "Point");
+        assert_eq!(p.get("x"), Some(Value::Int(1)));
+        assert_eq!(p.get("y"), Some(Value::Float(2.0)));
+        assert_eq!(rt.current_thread.operand_stack.pop(), Some(Value::Unit));
+
+        assert!(struct_(rt, "Point".to_string(), vec!["x".into()]).is_err());
+    }
+}
@@ -0,0 +1,145 @@
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+
+use crate::{Runtime, VmError};
+
+use super::yield_;
+
+/// Pop the operand stack for a timeout in ms and the thread ID to join.
+/// If the thread to join is in zombie state, it is deallocated and true is pushed onto the current thread's
+/// operand stack. The result of the zombie thread is dropped.
+/// If the timeout has passed since the current thread started waiting, false is pushed instead.
+/// Otherwise, the current thread will yield and try again, like join. A timeout of 0 or less doesn't wait.
+///
+/// The time the current thread stops waiting is kept on it between tries, and cleared once it is done.
+///
+/// # Arguments
+///
+/// * `rt` - The runtime to join the thread in.
+///
+/// # Errors
+///
+/// * If the thread with the given ID can't be joined: it was never spawned, or was detached or reaped.
+/// * If the operand stack has less than two values.
+/// * If the values on the operand stack are not integers.
+#[inline]
+pub fn try_join(mut rt: Runtime) -> Result<Runtime> {
+    let ms: i64 = rt
+        .current_thread
+        .operand_stack
+        .pop()
+        .ok_or(VmError::OperandStackUnderflow)?
+        .try_into()?;
+    let tid: i64 = rt
+        .current_thread
+        .operand_stack
+        .pop()
+        .ok_or(VmError::OperandStackUnderflow)?
+        .try_into()?;
+
+    if let Some(zombie_thread) = rt.zombie_threads.remove(&tid) {
+        drop(zombie_thread);
+        rt.current_thread.join_deadline = None;
+        rt.current_thread.operand_stack.push(true.into());
+        return Ok(rt);
+    }
+
+    if !rt.is_joinable(tid) {
+        rt.current_thread.join_deadline = None;
+        return Err(VmError::NoSuchThread(tid).into());
+    }
+
+    let deadline = *rt
+        .current_thread
+        .join_deadline
+        .get_or_insert_with(|| Instant::now() + Duration::from_millis(ms.max(0) as u64));
+    if Instant::now() >= deadline {
+        rt.current_thread.join_deadline = None;
+        rt.current_thread.operand_stack.push(false.into());
+        return Ok(rt);
+    }
+
+    // Re-execute the instruction with the same operands once the other threads have had a turn
+    rt.current_thread.pc -= 1;
+    rt.current_thread.operand_stack.push(tid.into());
+    rt.current_thread.operand_stack.push(ms.into());
+    yield_(rt)
+}
+
+#[cfg(test)]
+mod tests {
+    use bytecode::Value;
+
+    use crate::{
+        micro_code::{done, ldc, spawn},
+        MAIN_THREAD_ID,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_try_join_01() -> Result<()> {
+        let child = MAIN_THREAD_ID + 1;
+        let mut rt = Runtime::default();
+        rt.current_thread.pc = 1; // prevent u64 subtraction overflow
+        rt = spawn(rt, 0)?;
+
+        // With a timeout of 0 the child is still running, so false without waiting
+        rt = ldc(rt, Value::Int(0))?;
+        rt = try_join(rt)?;
+        assert_eq!(rt.current_thread.thread_id, MAIN_THREAD_ID);
+        assert_eq!(rt.current_thread.operand_stack.pop(), Some(false.into()));
+        assert!(rt.current_thread.join_deadline.is_none());
+
+        // Once the child is done it is joined, and its result dropped
+        rt = yield_(rt)?;
+        rt = done(rt)?;
+        rt = ldc(rt, Value::Int(child))?;
+        rt = ldc(rt, Value::Int(0))?;
+        rt = try_join(rt)?;
+        assert_eq!(rt.current_thread.operand_stack, vec![true.into()]);
+        assert!(rt.zombie_threads.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_try_join_02() -> Result<()> {
+        let child = MAIN_THREAD_ID + 1;
+        let mut rt = Runtime::default();
+        rt.current_thread.pc = 1; // prevent u64 subtraction overflow
+        rt = spawn(rt, 0)?;
+
+        // Within the timeout the current thread yields and keeps its operands to try again
+        rt = ldc(rt, Value::Int(10_000))?;
+        rt = try_join(rt)?;
+        assert_eq!(rt.current_thread.thread_id, child);
+        rt = yield_(rt)?;
+        assert_eq!(rt.current_thread.pc, 0);
+        assert_eq!(
+            rt.current_thread.operand_stack,
+            vec![Value::Int(child), Value::Int(10_000)]
+        );
+        assert!(rt.current_thread.join_deadline.is_some());
+
+        // Past the deadline set on the first try, it gives up
+        rt.current_thread.join_deadline = Some(Instant::now());
+        rt = try_join(rt)?;
+        assert_eq!(rt.current_thread.operand_stack.pop(), Some(false.into()));
+        assert!(rt.current_thread.join_deadline.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_try_join_03() -> Result<()> {
+        let mut rt = Runtime::default();
+        rt = ldc(rt, Value::Int(7))?;
+        rt = ldc(rt, Value::Int(0))?;
+        let result = try_join(rt);
+        assert!(result.is_err_and(|e| e.to_string().contains("No thread 7")));
+
+        Ok(())
+    }
+}
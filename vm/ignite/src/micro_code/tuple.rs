@@ -0,0 +1,159 @@
+use anyhow::Result;
+use bytecode::{type_of, Tuple, Value};
+
+use crate::{Runtime, VmError};
+
+/// Pops the given number of values off the stack and pushes a tuple holding them.
+/// The value that was deepest in the stack is the first one the tuple holds.
+///
+/// # Arguments
+///
+/// * `rt` - The runtime to create the tuple in.
+///
+/// * `len` - The number of values the tuple holds.
+///
+/// # Errors
+///
+/// If the stack has fewer values than the tuple holds.
+#[inline]
+pub fn tuple(mut rt: Runtime, len: usize) -> Result<Runtime> {
+    let stack_len = rt.current_thread.operand_stack.len();
+    if stack_len < len {
+        return Err(VmError::OperandStackUnderflow.into());
+    }
+
+    let elems = rt.current_thread.operand_stack.split_off(stack_len - len);
+    rt.current_thread
+        .operand_stack
+        .push(Tuple::new(elems).into());
+    Ok(rt)
+}
+
+/// Pops a tuple off the stack and pushes the values it holds, so the last one is on top.
+///
+/// # Errors
+///
+/// If the stack is empty, the value is not a tuple or the tuple doesn't hold the given number of values.
+#[inline]
+pub fn unpack(mut rt: Runtime, len: usize) -> Result<Runtime> {
+    let t = pop_tuple(&mut rt)?;
+    if t.len() != len {
+        return Err(VmError::BadType {
+            expected: format!("Tuple of {} values", len),
+            found: format!("Tuple of {} values", t.len()),
+        }
+        .into());
+    }
+
+    rt.current_thread.operand_stack.extend(t.iter().cloned());
+    Ok(rt)
+}
+
+/// Pops a tuple off the stack and pushes the value it holds at the given index.
+///
+/// # Errors
+///
+/// If the stack is empty, the value is not a tuple or the tuple holds fewer values than the index.
+#[inline]
+pub fn ld_tuple(mut rt: Runtime, idx: usize) -> Result<Runtime> {
+    let t = pop_tuple(&mut rt)?;
+    let Some(val) = t.get(idx) else {
+        return Err(VmError::IndexOutOfBounds {
+            index: idx as i64,
+            len: t.len(),
+            pc: rt.current_thread.pc.saturating_sub(1),
+        }
+        .into());
+    };
+
+    rt.current_thread.operand_stack.push(val.clone());
+    Ok(rt)
+}
+
+fn pop_tuple(rt: &mut Runtime) -> Result<Tuple> {
+    let val = rt
+        .current_thread
+        .operand_stack
+        .pop()
+        .ok_or(VmError::OperandStackUnderflow)?;
+
+    match val {
+        Value::Tuple(t) => Ok(t),
+        _ => Err(VmError::BadType {
+            expected: "Tuple".to_string(),
+            found: type_of(&val).to_string(),
+        }
+        .into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::micro_code::ldc;
+
+    fn pair(a: i64, b: bool) -> Value {
+        Tuple::new(vec![Value::Int(a), Value::Bool(b)]).into()
+    }
+
+    #[test]
+    fn test_tuple() -> Result<()> {
+        let mut rt = Runtime::new(vec![]);
+        rt = ldc(rt, Value::Unit)?;
+        rt = ldc(rt, Value::Int(1))?;
+        rt = ldc(rt, Value::Bool(true))?;
+        rt = tuple(rt, 2)?;
+        assert_eq!(
+            rt.current_thread.operand_stack,
+            vec![Value::Unit, pair(1, true)]
+        );
+
+        rt.current_thread.operand_stack.clear();
+        assert!(tuple(rt, 1).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_unpack() -> Result<()> {
+        let mut rt = Runtime::new(vec![]);
+        rt = ldc(rt, pair(2, false))?;
+        rt = unpack(rt, 2)?;
+        assert_eq!(
+            rt.current_thread.operand_stack,
+            vec![Value::Int(2), Value::Bool(false)]
+        );
+
+        rt = ldc(rt, pair(2, false))?;
+        assert!(unpack(rt, 3).is_err());
+
+        let mut rt = Runtime::new(vec![]);
+        rt = ldc(rt, Value::Int(1))?;
+        assert!(unpack(rt, 1).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_ld_tuple() -> Result<()> {
+        let mut rt = Runtime::new(vec![]);
+        rt = ldc(rt, pair(3, true))?;
+        rt = ld_tuple(rt, 1)?;
+        assert_eq!(
+            rt.current_thread.operand_stack.pop(),
+            Some(Value::Bool(true))
+        );
+
+        rt = ldc(rt, pair(3, true))?;
+        let Err(err) = ld_tuple(rt, 2) else {
+            panic!("the pair holds two values");
+        };
+        assert!(matches!(
+            err.downcast_ref::<VmError>(),
+            Some(VmError::IndexOutOfBounds {
+                index: 2,
+                len: 2,
+                ..
+            })
+        ));
+        Ok(())
+    }
+}
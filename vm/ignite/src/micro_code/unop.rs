@@ -28,7 +28,13 @@ pub fn unop(mut rt: Runtime, op: UnOp) -> Result<Runtime> {
         Value::Unit => Err(VmError::UnsupportedOperation(op.into(), type_of(&val).into()).into()),
         Value::Int(i) => {
             let result = match op {
-                UnOp::Neg => Value::Int(-i), // Negation
+                // Negation, which overflows for MIN_INT
+                UnOp::Neg => {
+                    Value::Int(i.checked_neg().ok_or_else(|| VmError::IntegerOverflow {
+                        op: op.clone().into(),
+                        pc: rt.current_thread.pc.saturating_sub(1),
+                    })?)
+                }
                 UnOp::Not => Value::Int(!i), // Bitwise Not
             };
             rt.current_thread.operand_stack.push(result);
@@ -52,13 +58,24 @@ pub fn unop(mut rt: Runtime, op: UnOp) -> Result<Runtime> {
                 Err(VmError::UnsupportedOperation(op.into(), type_of(&val).into()).into())
             }
         }
-        Value::String(_) => {
+        Value::String(_) | Value::Char(_) => {
             Err(VmError::UnsupportedOperation(op.into(), type_of(&val).into()).into())
         }
         Value::Unitialized => {
             Err(VmError::UnsupportedOperation(op.into(), type_of(&val).into()).into())
         }
-        Value::Semaphore(_) => {
+        Value::Semaphore(_)
+        | Value::Channel(_)
+        | Value::Array(_)
+        | Value::Slice(_)
+        | Value::Struct(_)
+        | Value::Variant(_)
+        | Value::Tuple(_)
+        | Value::Map(_)
+        | Value::Socket(_)
+        | Value::Bytes(_)
+        | Value::PVec(_)
+        | Value::PMap(_) => {
             Err(VmError::UnsupportedOperation(op.into(), type_of(&val).into()).into())
         }
         Value::Closure { .. } => {
@@ -108,6 +125,16 @@ mod tests {
         let result = unop(rt, UnOp::Not);
         assert!(result.is_err());
 
+        let mut rt = Runtime::new(vec![]);
+        rt = ldc(rt, Value::Int(i64::MIN)).unwrap();
+        let Err(err) = unop(rt, UnOp::Neg) else {
+            panic!("Negating MIN_INT should overflow");
+        };
+        assert!(matches!(
+            err.downcast_ref::<VmError>(),
+            Some(VmError::IntegerOverflow { .. })
+        ));
+
         let mut rt = Runtime::new(vec![]);
         rt = ldc(rt, Value::String("hello world".into())).unwrap();
         let result = unop(rt, UnOp::Not);
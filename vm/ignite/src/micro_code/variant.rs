@@ -0,0 +1,161 @@
+use anyhow::Result;
+use bytecode::{type_of, Symbol, Value, Variant};
+
+use crate::{Runtime, VmError};
+
+/// Pops the given number of values off the stack and pushes a variant holding them.
+/// The value that was deepest in the stack is the first one the variant holds.
+///
+/// # Arguments
+///
+/// * `rt` - The runtime to create the variant in.
+///
+/// * `enum_name` - The name of the enum the variant is of, like Option.
+///
+/// * `name` - The name of the variant, like Some.
+///
+/// * `arity` - The number of values the variant holds.
+///
+/// # Errors
+///
+/// If the stack has fewer values than the variant holds.
+#[inline]
+pub fn variant(mut rt: Runtime, enum_name: Symbol, name: Symbol, arity: usize) -> Result<Runtime> {
+    let stack_len = rt.current_thread.operand_stack.len();
+    if stack_len < arity {
+        return Err(VmError::OperandStackUnderflow.into());
+    }
+
+    let payload = rt.current_thread.operand_stack.split_off(stack_len - arity);
+    rt.current_thread
+        .operand_stack
+        .push(Variant::new(enum_name, name, payload).into());
+    Ok(rt)
+}
+
+/// Pops a variant off the stack and pushes whether it is the variant with the given name.
+///
+/// # Errors
+///
+/// If the stack is empty or the value is not a variant.
+#[inline]
+pub fn is_variant(mut rt: Runtime, name: Symbol) -> Result<Runtime> {
+    let v = pop_variant(&mut rt)?;
+    rt.current_thread
+        .operand_stack
+        .push(Value::Bool(v.name == name));
+    Ok(rt)
+}
+
+/// Pops a variant off the stack and pushes the value it holds at the given index.
+///
+/// # Errors
+///
+/// If the stack is empty, the value is not a variant or the variant holds fewer values than the index.
+#[inline]
+pub fn ld_variant(mut rt: Runtime, idx: usize) -> Result<Runtime> {
+    let v = pop_variant(&mut rt)?;
+    let Some(val) = v.payload.get(idx) else {
+        return Err(VmError::IndexOutOfBounds {
+            index: idx as i64,
+            len: v.payload.len(),
+            pc: rt.current_thread.pc.saturating_sub(1),
+        }
+        .into());
+    };
+
+    rt.current_thread.operand_stack.push(val.clone());
+    Ok(rt)
+}
+
+fn pop_variant(rt: &mut Runtime) -> Result<Variant> {
+    let val = rt
+        .current_thread
+        .operand_stack
+        .pop()
+        .ok_or(VmError::OperandStackUnderflow)?;
+
+    match val {
+        Value::Variant(v) => Ok(v),
+        _ => Err(VmError::BadType {
+            expected: "Variant".to_string(),
+            found: type_of(&val).to_string(),
+        }
+        .into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::micro_code::ldc;
+
+    #[test]
+    fn test_variant() -> Result<()> {
+        let mut rt = Runtime::new(vec![]);
+        rt = ldc(rt, Value::Unit)?;
+        rt = ldc(rt, Value::Int(1))?;
+        rt = variant(rt, "Option".into(), "Some".into(), 1)?;
+        assert_eq!(
+            rt.current_thread.operand_stack,
+            vec![Value::Unit, Variant::some(1).into()]
+        );
+
+        rt = variant(rt, "Option".into(), "None".into(), 0)?;
+        assert_eq!(
+            rt.current_thread.operand_stack.pop(),
+            Some(Variant::none().into())
+        );
+
+        rt.current_thread.operand_stack.clear();
+        assert!(variant(rt, "Result".into(), "Ok".into(), 1).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_variant() -> Result<()> {
+        let mut rt = Runtime::new(vec![]);
+        rt = ldc(rt, Variant::ok(1).into())?;
+        rt = is_variant(rt, "Ok".into())?;
+        assert_eq!(
+            rt.current_thread.operand_stack.pop(),
+            Some(Value::Bool(true))
+        );
+
+        rt = ldc(rt, Variant::ok(1).into())?;
+        rt = is_variant(rt, "Err".into())?;
+        assert_eq!(
+            rt.current_thread.operand_stack.pop(),
+            Some(Value::Bool(false))
+        );
+
+        rt = ldc(rt, Value::Int(1))?;
+        assert!(is_variant(rt, "Ok".into()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_ld_variant() -> Result<()> {
+        let mut rt = Runtime::new(vec![]);
+        rt = ldc(rt, Variant::err("bad").into())?;
+        rt = ld_variant(rt, 0)?;
+        assert_eq!(
+            rt.current_thread.operand_stack.pop(),
+            Some(Value::String("bad".into()))
+        );
+
+        rt = ldc(rt, Variant::none().into())?;
+        let Err(err) = ld_variant(rt, 0) else {
+            panic!("None holds no values");
+        };
+        assert!(matches!(
+            err.downcast_ref::<VmError>(),
+            Some(VmError::IndexOutOfBounds {
+                index: 0,
+                len: 0,
+                ..
+            })
+        ));
+        Ok(())
+    }
+}
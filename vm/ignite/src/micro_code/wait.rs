@@ -1,7 +1,7 @@
 use anyhow::{Ok, Result};
 use bytecode::Semaphore;
 
-use crate::{Runtime, VmError};
+use crate::{BlockedOn, Runtime, VmError};
 
 /// Pops a value off the stack.
 /// The value is expected to be a semaphore.
@@ -20,6 +20,7 @@ use crate::{Runtime, VmError};
 ///
 /// If the stack is empty.
 /// If the top value on stack is not a semaphore.
+/// If the semaphore is poisoned.
 /// If there are no threads in the ready queue when the current thread is blocked.
 #[inline]
 pub fn wait(mut rt: Runtime) -> Result<Runtime> {
@@ -29,7 +30,7 @@ pub fn wait(mut rt: Runtime) -> Result<Runtime> {
         .pop()
         .ok_or(VmError::OperandStackUnderflow)?
         .try_into()?;
-    let mut sem_guard = sem.lock().unwrap();
+    let mut sem_guard = sem.lock().map_err(|_| VmError::SemaphorePoisoned)?;
 
     if *sem_guard > 0 {
         *sem_guard -= 1;
@@ -40,15 +41,10 @@ pub fn wait(mut rt: Runtime) -> Result<Runtime> {
         drop(sem_guard); //unlock the semaphore
 
         // Move the current thread to the blocked queue and pop the next ready thread.
-        let current_thread = rt.current_thread;
-        rt.blocked_queue.push_back((current_thread, sem.clone()));
-
-        let next_ready_thread = rt
-            .ready_queue
-            .pop_front()
-            .ok_or(VmError::NoThreadsInReadyQueue)?;
-
-        rt.current_thread = next_ready_thread;
+        let next_ready_thread = rt.next_ready_thread()?;
+        let current_thread = std::mem::replace(&mut rt.current_thread, next_ready_thread);
+        rt.blocked_queue
+            .push_back((current_thread, BlockedOn::Semaphore(sem.clone())));
         Ok(rt)
     }
 }
@@ -1,12 +1,12 @@
-use std::time::Instant;
-
 use anyhow::Result;
 
-use crate::{Runtime, VmError};
+use crate::Runtime;
 
 /// Yield the current thread in the runtime.
+/// Take the next ready thread picked by the scheduler policy and set it as the current thread.
 /// Push the current thread to the back of the ready queue.
-/// Pop the next ready thread from the front of the ready queue and set it as the current thread.
+/// If no other thread is ready, the current thread keeps running with a new quantum.
+/// Sleeping threads whose time has come are woken first, so they get to run.
 ///
 /// # Arguments
 ///
@@ -14,19 +14,18 @@ use crate::{Runtime, VmError};
 ///
 /// # Errors
 ///
-/// Returns an error if there are no threads in the ready queue.
+/// Infallible, returns a Result like the other instructions.
 #[inline]
 pub fn yield_(mut rt: Runtime) -> Result<Runtime> {
-    let current_thread = rt.current_thread;
-    rt.ready_queue.push_back(current_thread);
-
-    let next_ready_thread = rt
-        .ready_queue
-        .pop_front()
-        .ok_or(VmError::NoThreadsInReadyQueue)?;
+    rt.wake_sleepers();
+    if rt.ready_queue.is_empty() {
+        rt.reset_quantum();
+        return Ok(rt);
+    }
 
-    rt.current_thread = next_ready_thread;
-    rt.time = Instant::now(); // Reset the time
+    let next_ready_thread = rt.next_ready_thread()?;
+    let current_thread = std::mem::replace(&mut rt.current_thread, next_ready_thread);
+    rt.ready_queue.push_back(current_thread);
     Ok(rt)
 }
 
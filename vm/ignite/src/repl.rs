@@ -1,68 +1,191 @@
-use anyhow::Result;
-use bytecode::builtin;
-use compiler::compiler;
+use std::collections::HashSet;
+
+use anyhow::{Error, Result};
+use bytecode::{builtin, Value, W};
+use compiler::compiler::{desugar_with_defines, Compiler};
+use parser::structs::{BlockSeq, Decl};
 use rustyline::DefaultEditor;
+use types::type_checker::TypeChecker;
 
-use crate::{run, Runtime};
+use ignite::{format_runtime_error, run, verify, Runtime, VmError};
 
-pub fn ignite_repl(type_check: bool) -> Result<()> {
-    let mut rl = DefaultEditor::new().unwrap();
-    println!("Welcome to the RustScript REPL! Type /exit to exit.");
-    println!();
+/// A REPL session. Each line is compiled onto the end of the bytecode of the lines before it and run on the same
+/// runtime from where the last line ended, so the names it declares are still bound for the lines after it.
+pub struct Repl {
+    rt: Runtime,
+    // The lines that ran so far as one program, which a new line is type checked at the end of
+    program: BlockSeq,
+    type_check: bool,
+}
 
-    loop {
-        let readline = rl.readline(">>> ");
+impl Repl {
+    pub fn new(type_check: bool) -> Self {
+        Repl {
+            rt: Runtime::default(),
+            program: BlockSeq {
+                decls: vec![],
+                last_expr: None,
+                symbols: vec![],
+                spans: vec![],
+            },
+            type_check,
+        }
+    }
 
-        if let Ok(inp) = readline {
-            let inp = inp.trim().to_string();
+    /// Run a line, returning its value if it ends with an expr. If the line fails to run, the session goes on
+    /// with the names bound before it, though what it changed in place stays changed.
+    pub fn eval(&mut self, inp: &str) -> Result<Option<Value>> {
+        let line = desugar_with_defines(inp, &HashSet::new())?;
+        let program = extend_program(&self.program, &line);
 
-            if inp.is_empty() {
-                continue;
-            }
+        if self.type_check {
+            TypeChecker::new(&program).type_check()?;
+        }
 
-            if inp.eq("/exit") {
-                println!("See you again!");
-                break;
+        let start = self.rt.instrs.len();
+        let instrs = Compiler::new(line.clone()).compile_line(self.rt.instrs.clone())?;
+        verify(&instrs)?;
+
+        let main_thread = self.rt.current_thread.clone();
+        self.rt.finish_sweep();
+        let envs: Vec<_> = self
+            .rt
+            .env_registry
+            .iter()
+            .map(|env| env.0.clone())
+            .collect();
+
+        let mut rt = std::mem::take(&mut self.rt);
+        rt.instrs = instrs.clone();
+        rt.current_thread.pc = start;
+        rt.done = false;
+        rt.reset_quantum();
+
+        match run(rt) {
+            Ok(mut rt) => {
+                let val = line
+                    .last_expr
+                    .and_then(|_| rt.current_thread.operand_stack.pop());
+                self.rt = rt;
+                self.program = program;
+                Ok(val)
+            }
+            Err(err) => {
+                // The code of the line is kept, since a closure it made may still be reachable
+                let mut rt = Runtime::new(instrs);
+                rt.env_registry = envs.into_iter().map(W).collect();
+                rt.current_thread = main_thread;
+                self.rt = rt;
+                Err(Error::msg(format_runtime_error(&err)))
             }
+        }
+    }
+}
 
-            rl.add_history_entry(inp.clone().trim()).unwrap();
+// The program of the lines before with the line after it. The value of their last expr was already shown, so it
+// becomes a statement
+fn extend_program(prev: &BlockSeq, line: &BlockSeq) -> BlockSeq {
+    let mut program = prev.clone();
+    if let Some(expr) = program.last_expr.take() {
+        program.decls.push(Decl::ExprStmt(expr.as_ref().clone()));
+    }
 
-            let compiled = compiler::compile_from_string(&inp, type_check);
-            match compiled {
-                Ok(_) => (),
-                Err(err) => {
-                    println!("{}", err);
-                    continue;
-                }
-            }
+    program.decls.extend(line.decls.iter().cloned());
+    program.last_expr = line.last_expr.clone();
+    for sym in line.symbols.iter() {
+        if !program.symbols.contains(sym) {
+            program.symbols.push(sym.clone());
+        }
+    }
+    program.spans.extend(line.spans.iter().copied());
+    program
+}
 
-            let compiled = compiled.unwrap();
+pub fn ignite_repl(type_check: bool) -> Result<()> {
+    let mut rl = DefaultEditor::new().map_err(|err| VmError::LineEditor(err.to_string()))?;
+    println!("Welcome to the RustScript REPL! Type /exit to exit.");
+    println!();
 
-            // For now, make a new Runtime for each line
-            // Later: try to introduce global state
-            // dbg!(&compiled);
+    let mut repl = Repl::new(type_check);
 
-            let mut rt = Runtime::new(compiled);
-            let run_res = run(rt);
+    loop {
+        let readline = rl.readline(">>> ");
 
-            match run_res {
-                Ok(_) => (),
-                Err(err) => {
-                    println!("[RuntimeError]: {}", err);
-                    continue;
-                }
-            }
+        let Ok(inp) = readline else {
+            break;
+        };
+
+        let inp = inp.trim().to_string();
 
-            rt = run_res.unwrap();
+        if inp.is_empty() {
+            continue;
+        }
 
-            let top = rt.current_thread.operand_stack.last();
-            dbg!(rt.current_thread.operand_stack.len());
+        if inp.eq("/exit") {
+            println!("See you again!");
+            break;
+        }
 
-            if let Some(val) = top {
-                builtin::println_impl(val);
-            }
+        rl.add_history_entry(inp.clone().trim())
+            .map_err(|err| VmError::LineEditor(err.to_string()))?;
+
+        match repl.eval(&inp) {
+            Ok(Some(val)) => builtin::println_impl(&val),
+            Ok(None) => (),
+            Err(err) => println!("{}", err),
         }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repl() -> Result<()> {
+        let mut repl = Repl::new(true);
+        assert_eq!(repl.eval("let x = 2;")?, None);
+        assert_eq!(repl.eval("x + 1")?, Some(Value::Int(3)));
+
+        // fns and closures declared on an earlier line can be called
+        repl.eval("fn add(y: int) -> int { x + y }")?;
+        repl.eval("let f = |y: int| add(y) * 2;")?;
+        assert_eq!(repl.eval("f(3)")?, Some(Value::Int(10)));
+
+        // a name can be declared again, with another type
+        repl.eval("let x = true;")?;
+        assert_eq!(repl.eval("!x")?, Some(Value::Bool(false)));
+        assert!(repl.eval("x + 1").is_err());
+
+        // threads spawned on a line can be joined on the next
+        repl.eval("fn work() -> int { 40 }")?;
+        repl.eval("let t = spawn work();")?;
+        assert_eq!(repl.eval("join t")?, Some(Value::Int(40)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_repl_errors() -> Result<()> {
+        let mut repl = Repl::new(true);
+        repl.eval("let xs = [1, 2, 3];")?;
+
+        // a line that doesn't type check or fails to run leaves the session as it was
+        assert!(repl.eval("let y: int = xs;").is_err());
+        assert!(repl.eval("y").is_err());
+
+        let err = repl
+            .eval("let z = 1; xs[z] = 5; xs[z + 8]")
+            .expect_err("should fail");
+        assert!(err.to_string().starts_with("[RuntimeError R003]"));
+        assert!(repl.eval("z").is_err());
+        assert_eq!(repl.eval("xs[1]")?, Some(Value::Int(5)));
+
+        // without type checking, a bad line only fails when it runs
+        let mut repl = Repl::new(false);
+        assert!(repl.eval("let x = 1 + true;").is_err());
+        assert_eq!(repl.eval("let x = 2; x")?, Some(Value::Int(2)));
+        Ok(())
+    }
+}
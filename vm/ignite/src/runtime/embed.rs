@@ -0,0 +1,110 @@
+use std::rc::Weak;
+
+use bytecode::{Symbol, Value, W};
+
+use crate::Runtime;
+
+/// The state a program embedding the runtime gives it before it runs, and reads off it after, so embedders don't
+/// depend on how threads and their environments are laid out.
+impl Runtime {
+    /// Bind a name in the global frame, alongside the builtins, so the program can use it like a constant.
+    /// Frames made before the name is bound that bind it too now shadow it, and so do the frames below them, so
+    /// lookups that skip to the global frame still find the nearer binding.
+    pub fn define_global(&mut self, name: impl Into<Symbol>, value: impl Into<Value>) {
+        let Some(env) = self.current_thread.env.upgrade() else {
+            return;
+        };
+
+        let name = name.into();
+        let global = env.borrow().global.as_ref().and_then(Weak::upgrade);
+        global.unwrap_or(env).borrow_mut().set(name.clone(), value);
+
+        for W(frame) in &self.env_registry {
+            let shadows = frame.borrow().chain_binds(&name);
+            if shadows {
+                frame.borrow_mut().shadows_global = true;
+            }
+        }
+    }
+
+    /// Set the arguments the program is run with, replacing any set before.
+    pub fn set_main_args(&mut self, args: impl IntoIterator<Item = impl Into<Value>>) {
+        self.main_args = args.into_iter().map(Into::into).collect();
+    }
+
+    /// The value the program finished with, the one left on the operand stack of the main thread. None if the
+    /// program isn't done or left nothing, like a program ending in a statement.
+    pub fn result(&self) -> Option<Value> {
+        if !self.done {
+            return None;
+        }
+
+        self.current_thread.operand_stack.last().cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use bytecode::{BinOp, ByteCode};
+
+    use crate::run;
+
+    use super::*;
+
+    #[test]
+    fn test_define_global() -> Result<()> {
+        let instrs = vec![
+            ByteCode::ld("x"),
+            ByteCode::ldc(1),
+            ByteCode::BINOP(BinOp::Add),
+            ByteCode::DONE,
+        ];
+        let mut rt = Runtime::new(instrs);
+        rt.define_global("x", 41);
+        assert_eq!(rt.result(), None);
+
+        let rt = run(rt)?;
+        assert_eq!(rt.result(), Some(Value::Int(42)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_define_global_after_frames() -> Result<()> {
+        // the frame binding x is made before x is a global, and the lookup skips straight to the global frame
+        let instrs = vec![
+            ByteCode::enterscope(vec!["x"]),
+            ByteCode::ldc(1),
+            ByteCode::assign("x"),
+            ByteCode::enterscope(vec!["y"]),
+            ByteCode::ld("x"),
+            ByteCode::DONE,
+        ];
+        let mut rt = Runtime::new(instrs);
+        rt.set_global_fallback_depth(0);
+        for _ in 0..4 {
+            rt = rt.step()?;
+        }
+        rt.define_global("x", 41);
+
+        let rt = run(rt)?;
+        assert_eq!(rt.result(), Some(Value::Int(1)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_main_args() {
+        let mut rt = Runtime::default();
+        rt.set_main_args(["a", "b"]);
+        rt.set_main_args(["c"]);
+        assert_eq!(rt.main_args, vec![Value::String("c".into())]);
+    }
+
+    #[test]
+    fn test_result_empty() -> Result<()> {
+        let rt = run(Runtime::new(vec![ByteCode::DONE]))?;
+        assert!(rt.is_done());
+        assert_eq!(rt.result(), None);
+        Ok(())
+    }
+}
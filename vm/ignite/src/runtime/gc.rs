@@ -1,136 +1,252 @@
-use std::{cell::RefCell, collections::HashMap, rc::Weak};
+use std::{cell::RefCell, rc::Weak, time::Instant};
 
-use bytecode::{weak_clone, EnvWeak, Environment, StackFrame, Value, W};
+use anyhow::Result;
+use bytecode::{Environment, Value};
 
-use crate::{Runtime, Thread};
+use crate::{Runtime, Thread, VmError};
 
 /// Runtime methods at runtime.
 impl Runtime {
     /// Mark and sweep the environment registry.
     /// This will remove all environments that are no longer referenced.
     ///
-    /// - Mark environment x -> env_registry.get(x) = true
-    /// - Sweep environment x -> env_registry.remove(x) if env_registry.get(x) = false
-    /// - Clean up -> reset env_registry.get(x) = false
+    /// Each collection starts a new epoch, and marking an environment records the epoch on it, so there is
+    /// nothing to allocate or reset per environment:
+    /// - Mark environment x -> x.mark = epoch
+    /// - Sweep environment x -> env_registry.remove(x) if x.mark != epoch
     ///
     /// Traverse through all the threads, for each thread:
     ///   - Mark its current environment and the environment of closure values in the current environment,
     ///     and the chain of parent environments.
     ///   - Go through the runtime stack and mark all the environments and environment of closure values in
     ///     their respective environment, and the chain of parent environments
-    ///   - Go through the operand stack and mark all the environments of closure values, and the chain of parent environments
+    ///   - Go through the operand stack and mark all the environments of closure values (including those inside arrays),
+    ///     and the chain of parent environments
+    ///
+    /// For a zombie thread only its result, the top of its operand stack, can still be used, by the thread that
+    /// joins it. So that is marked like a value on an operand stack, and the rest of the zombie is not.
     #[inline]
-    pub fn mark_and_weep(self) -> Self {
-        let marked = mark(&self);
-        sweep(self, marked)
+    pub fn mark_and_weep(mut self) -> Self {
+        self.finish_sweep();
+        self.gc_epoch += 1;
+        mark(&self);
+        let mut rt = sweep(self);
+        rt.raise_env_limit();
+        rt
+    }
+
+    /// Mark like [`Runtime::mark_and_weep`], but leave the sweep to [`Runtime::sweep_step`], so the environments
+    /// that are garbage can be dropped a few at a time instead of all at once.
+    ///
+    /// The registry is taken whole to be swept, and environments made in the meantime go into a new one. They
+    /// weren't there to be marked, so they must not be swept by this collection, and the ones that were there and
+    /// weren't marked are unreachable, so nothing made since can refer to them.
+    pub fn mark_and_start_sweep(mut self) -> Self {
+        self.finish_sweep();
+        self.gc_epoch += 1;
+        mark(&self);
+        self.gc_sweep = Some(std::mem::take(&mut self.env_registry).into_iter());
+        self
+    }
+
+    /// Sweep up to `budget` environments of the collection in progress, putting the marked ones back in the
+    /// registry and dropping the rest.
+    pub fn sweep_step(&mut self, budget: usize) {
+        let Some(pending) = self.gc_sweep.as_mut() else {
+            return;
+        };
+
+        let epoch = self.gc_epoch;
+        for env in pending.by_ref().take(budget) {
+            if env.0.borrow().mark.get() == epoch {
+                self.env_registry.insert(env);
+            }
+        }
+
+        if pending.len() == 0 {
+            self.gc_sweep = None;
+            self.raise_env_limit();
+        }
+    }
+
+    /// Sweep what is left of the collection in progress, if there is one. The registry only holds every
+    /// environment once it is done.
+    pub fn finish_sweep(&mut self) {
+        self.sweep_step(usize::MAX);
+    }
+
+    /// The number of environments the program has, counting those still to be swept.
+    pub fn heap_size(&self) -> usize {
+        self.env_registry.len() + self.gc_sweep.as_ref().map_or(0, |pending| pending.len())
+    }
+
+    /// Check the program is within its heap limit, if it has one. Going over it starts a full collection, and
+    /// only if the environments still live are more than the limit is it an error.
+    ///
+    /// # Errors
+    ///
+    /// [`VmError::OutOfMemory`] if more environments are live than the limit allows.
+    pub fn check_heap(self) -> Result<Self> {
+        let Some(limit) = self.max_heap else {
+            return Ok(self);
+        };
+
+        if self.heap_size() <= limit {
+            return Ok(self);
+        }
+
+        let mut rt = self.mark_and_weep();
+        rt.gc_timer = Instant::now();
+
+        let live = rt.env_registry.len();
+        if live > limit {
+            return Err(VmError::OutOfMemory { live, limit }.into());
+        }
+        Ok(rt)
+    }
+
+    // Collect again once the environments have doubled, or reached the threshold if that is more
+    fn raise_env_limit(&mut self) {
+        if let Some(threshold) = self.gc_threshold {
+            self.gc_env_limit = threshold.max(self.env_registry.len() * 2);
+        }
     }
 }
 
-fn mark(rt: &Runtime) -> HashMap<EnvWeak, bool> {
+fn mark(rt: &Runtime) {
     if rt.debug {
         println!("Mark begin")
     }
 
-    let mut marked = env_hashmap(rt);
+    let mut work = Worklist::default();
 
     // Mark the current thread
-    marked = mark_thread(marked, &rt.current_thread);
+    work.push_thread(&rt.current_thread);
 
     // Mark the ready queue
     for thread in rt.ready_queue.iter() {
-        marked = mark_thread(marked, thread);
+        work.push_thread(thread);
     }
 
     // Mark the blocked queue
     for (thread, _) in rt.blocked_queue.iter() {
-        marked = mark_thread(marked, thread);
+        work.push_thread(thread);
+    }
+
+    // Mark the sleep queue
+    for (thread, _) in rt.sleep_queue.iter() {
+        work.push_thread(thread);
     }
 
-    // Zombie threads will be ignored
+    // Mark the results of zombie threads, which are still to be joined
+    for thread in rt.zombie_threads.values() {
+        work.vals.extend(thread.operand_stack.last().cloned());
+    }
 
-    marked
+    mark_worklist(rt.gc_epoch, work);
 }
 
-fn sweep(mut rt: Runtime, m: HashMap<EnvWeak, bool>) -> Runtime {
+fn sweep(mut rt: Runtime) -> Runtime {
     if rt.debug {
         println!("Sweep begin")
     }
 
-    let registry = rt
-        .env_registry
-        .drain()
-        .filter(|env| *m.get(&W(weak_clone(env))).unwrap_or(&false))
-        .collect();
-    rt.env_registry = registry;
+    let before = rt.env_registry.len();
+    let epoch = rt.gc_epoch;
+    rt.env_registry
+        .retain(|env| env.0.borrow().mark.get() == epoch);
 
     if rt.debug {
         println!(
             "Sweep end, {} environments removed",
-            m.len() - rt.env_registry.len()
+            before - rt.env_registry.len()
         )
     }
 
     rt // Any environment that is not marked will be removed from the registry and dropped
 }
 
-fn env_hashmap(rt: &Runtime) -> HashMap<EnvWeak, bool> {
-    let mut m = HashMap::new();
-    for env in rt.env_registry.iter() {
-        m.insert(W(weak_clone(env)), false);
-    }
-    m
-}
-
-fn mark_thread(mut m: HashMap<EnvWeak, bool>, t: &Thread) -> HashMap<EnvWeak, bool> {
-    m = mark_env(m, &t.env);
-    m = mark_operand_stack(m, &t.operand_stack);
-    m = mark_runtime_stack(m, &t.runtime_stack);
-    m
+// The environments and values still to be marked. They are kept on explicit stacks rather than the host
+// stack, so a long chain of parent environments or deeply nested arrays can't overflow it
+#[derive(Default)]
+struct Worklist {
+    envs: Vec<Weak<RefCell<Environment>>>,
+    vals: Vec<Value>,
 }
 
-fn mark_env(
-    mut m: HashMap<EnvWeak, bool>,
-    env: &Weak<RefCell<Environment>>,
-) -> HashMap<EnvWeak, bool> {
-    let is_marked = m
-        .get_mut(&W(env.clone()))
-        .expect("Environment must be in the registry");
-
-    match is_marked {
-        true => return m, // Already marked
-        false => *is_marked = true,
+impl Worklist {
+    fn push_thread(&mut self, t: &Thread) {
+        self.envs.push(t.env.clone());
+        self.vals.extend(t.operand_stack.iter().cloned());
+        self.envs
+            .extend(t.runtime_stack.iter().map(|frame| frame.env.0.clone()));
     }
+}
 
-    let env = env
-        .upgrade()
-        .expect("Environment must still be referenced to be marked");
+fn mark_worklist(epoch: u64, mut work: Worklist) {
+    loop {
+        if let Some(val) = work.vals.pop() {
+            mark_value(&mut work, &val);
+            continue;
+        }
 
-    if let Some(parent) = &env.borrow().parent {
-        m = mark_env(m, parent);
+        let Some(env) = work.envs.pop() else {
+            return;
+        };
+        mark_env(epoch, &mut work, env);
     }
-
-    m
 }
 
-fn mark_operand_stack(mut m: HashMap<EnvWeak, bool>, os: &[Value]) -> HashMap<EnvWeak, bool> {
-    for val in os.iter() {
-        if let Value::Closure { env, .. } = val {
-            m = mark_env(m, env);
+// Mark an environment and its chain of parent environments, stopping at the first one already marked. The
+// values bound in each are marked too, since a closure in a variable keeps its environment alive
+fn mark_env(epoch: u64, work: &mut Worklist, env: Weak<RefCell<Environment>>) {
+    let mut next = Some(env);
+    while let Some(env) = next {
+        // Builtin closures have no environment
+        let Some(env) = env.upgrade() else {
+            break;
+        };
+
+        let env = env.borrow();
+        if env.mark.get() == epoch {
+            break; // Already marked, and so are its parents
         }
+        env.mark.set(epoch);
+
+        for val in env.env.values() {
+            mark_value(work, val);
+        }
+        next = env.parent.clone();
     }
-    m
 }
 
-fn mark_runtime_stack(mut m: HashMap<EnvWeak, bool>, rs: &[StackFrame]) -> HashMap<EnvWeak, bool> {
-    for frame in rs.iter() {
-        m = mark_env(m, &frame.env);
+// Closures can also be reached through the elements of an array, or of the array behind a slice,
+// through the fields of a struct, through the values a variant or tuple holds, through the values of a map or of a
+// persistent vector or map and through the values waiting in a channel
+fn mark_value(work: &mut Worklist, val: &Value) {
+    match val {
+        Value::Closure { env, .. } => work.envs.push(env.0.clone()),
+        Value::Array(arr) | Value::Slice(bytecode::Slice { arr, .. }) => {
+            work.vals.extend(arr.borrow().iter().cloned())
+        }
+        Value::Struct(s) => work.vals.extend(s.fields().into_iter().map(|(_, val)| val)),
+        Value::Variant(v) => work.vals.extend(v.payload.iter().cloned()),
+        Value::Tuple(t) => work.vals.extend(t.iter().cloned()),
+        Value::Map(m) => work
+            .vals
+            .extend(m.entries().into_iter().map(|(_, val)| val)),
+        Value::PVec(pvec) => work.vals.extend(pvec.to_vec()),
+        Value::PMap(pmap) => work
+            .vals
+            .extend(pmap.entries().into_iter().map(|(_, val)| val)),
+        Value::Channel(ch) => work.vals.extend(ch.values()),
+        _ => (),
     }
-    m
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::run;
+    use crate::{micro_code, run};
 
     use super::*;
 
@@ -238,4 +354,215 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_gc_closure_in_array() -> Result<()> {
+        // let fs = {
+        //   fn f() {}
+        //   [f]
+        // };
+        // // f is out of scope but still reachable through the array on the operand stack
+        let empty_vec: Vec<Symbol> = vec![];
+
+        let instrs = vec![
+            ByteCode::enterscope(vec!["f"]),
+            ByteCode::ldf(0, empty_vec),
+            ByteCode::assign("f"),
+            ByteCode::ld("f"),
+            ByteCode::ARRAY(1),
+            ByteCode::EXITSCOPE,
+            ByteCode::DONE,
+        ];
+
+        let rt = run(Runtime::new(instrs))?;
+        assert_eq!(rt.env_registry.len(), 2); // Global env, block env
+
+        let rt = rt.mark_and_weep();
+        assert_eq!(rt.env_registry.len(), 2); // The block env is kept alive by the closure
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_gc_closure_in_map() -> Result<()> {
+        // let m = {
+        //   fn f() {}
+        //   #{"f": f}
+        // };
+        // // f is out of scope but still reachable through the map on the operand stack
+        let empty_vec: Vec<Symbol> = vec![];
+
+        let instrs = vec![
+            ByteCode::enterscope(vec!["f"]),
+            ByteCode::ldf(0, empty_vec),
+            ByteCode::assign("f"),
+            ByteCode::ldc("f"),
+            ByteCode::ld("f"),
+            ByteCode::MAP(1),
+            ByteCode::EXITSCOPE,
+            ByteCode::DONE,
+        ];
+
+        let rt = run(Runtime::new(instrs))?;
+        assert_eq!(rt.env_registry.len(), 2); // Global env, block env
+
+        let rt = rt.mark_and_weep();
+        assert_eq!(rt.env_registry.len(), 2); // The block env is kept alive by the closure
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_gc_closure_in_binding() -> Result<()> {
+        // let add = {
+        //   let x = 1;
+        //   fn f() {}
+        //   f
+        // };
+        // // the block env is only reachable through the closure bound to add
+        let mut rt = Runtime::new(vec![]);
+        rt = micro_code::enter_scope(rt, vec!["add".into()])?;
+        rt = micro_code::enter_scope(rt, vec!["x".into()])?;
+        rt = micro_code::ldf(rt, 0, vec![])?;
+        rt = micro_code::exit_scope(rt)?;
+        rt = micro_code::assign(rt, "add".into())?;
+        assert!(rt.current_thread.operand_stack.is_empty());
+
+        let rt = rt.mark_and_weep();
+        assert_eq!(rt.env_registry.len(), 3); // Global env, program env, block env
+
+        // and it is still kept in later collections
+        let rt = rt.mark_and_weep();
+        assert_eq!(rt.env_registry.len(), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_gc_zombie_result() -> Result<()> {
+        // a finished thread whose result is a closure made in a block only it entered
+        let mut rt = Runtime::new(vec![]);
+        rt = micro_code::enter_scope(rt, vec!["x".into()])?;
+        rt = micro_code::ldf(rt, 0, vec![])?;
+        let closure = rt.current_thread.operand_stack.pop().unwrap();
+        rt = micro_code::exit_scope(rt)?;
+
+        let mut zombie = Thread::new(2, rt.current_thread.env.clone());
+        zombie.operand_stack.push(closure);
+        rt.zombie_threads.insert(2, zombie);
+
+        let rt = rt.mark_and_weep();
+        assert_eq!(rt.env_registry.len(), 2); // Global env, block env
+
+        // joining gives a closure that can still be called
+        let mut rt = micro_code::ldc(rt, Value::Int(2))?;
+        rt = micro_code::join(rt)?;
+        let Some(Value::Closure { env, .. }) = rt.current_thread.operand_stack.pop() else {
+            panic!("Expected closure");
+        };
+        assert!(env.0.upgrade().is_some());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_gc_deep_chain() -> Result<()> {
+        // 100k nested blocks, each still open, with a closure made in the innermost one
+        let mut rt = Runtime::new(vec![]);
+        for _ in 0..100_000 {
+            rt = micro_code::enter_scope(rt, vec!["x".into()])?;
+        }
+        rt = micro_code::ldf(rt, 0, vec![])?;
+        rt = micro_code::ldc(rt, Value::Int(1))?;
+        rt = micro_code::array(rt, 2)?;
+
+        let rt = rt.mark_and_weep();
+        assert_eq!(rt.env_registry.len(), 100_001); // Every block env is still reachable
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_gc_threshold() -> Result<()> {
+        // blocks entered and exited one after another, collected by count alone
+        let mut rt = Runtime::new(vec![]);
+        rt.set_gc_interval(std::time::Duration::MAX);
+        rt.set_gc_threshold(Some(10));
+        for _ in 0..100 {
+            rt = micro_code::enter_scope(rt, vec!["x".into()])?;
+            rt = micro_code::exit_scope(rt)?;
+            rt = rt.gc_step()?;
+        }
+        assert!(rt.env_registry.len() < 10);
+
+        // with more live environments than the threshold, the limit is raised past them
+        for _ in 0..50 {
+            rt = micro_code::enter_scope(rt, vec!["x".into()])?;
+        }
+        let rt = rt.mark_and_weep();
+        assert_eq!(rt.env_registry.len(), 51);
+        assert_eq!(rt.gc_env_limit, 102);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_heap() -> Result<()> {
+        // garbage doesn't count towards the limit, it is collected first
+        let mut rt = Runtime::new(vec![]);
+        rt.set_gc_interval(std::time::Duration::MAX);
+        rt.set_max_heap(10);
+        for _ in 0..100 {
+            rt = micro_code::enter_scope(rt, vec!["x".into()])?;
+            rt = micro_code::exit_scope(rt)?;
+            rt = rt.gc_step()?;
+        }
+        assert!(rt.env_registry.len() <= 10);
+
+        // but live environments do
+        for _ in 0..9 {
+            rt = micro_code::enter_scope(rt, vec!["x".into()])?;
+            rt = rt.gc_step()?;
+        }
+        rt = micro_code::enter_scope(rt, vec!["x".into()])?;
+        let Err(err) = rt.gc_step() else {
+            panic!("should be out of memory")
+        };
+        assert!(matches!(
+            err.downcast_ref::<VmError>(),
+            Some(VmError::OutOfMemory {
+                live: 11,
+                limit: 10
+            })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_gc_incremental() -> Result<()> {
+        let mut rt = Runtime::new(vec![]);
+        rt.set_gc_incremental(2);
+        rt = micro_code::enter_scope(rt, vec!["x".into()])?;
+        for _ in 0..5 {
+            rt = micro_code::enter_scope(rt, vec!["x".into()])?;
+            rt = micro_code::exit_scope(rt)?;
+        }
+        assert_eq!(rt.env_registry.len(), 7); // Global env, program env, 5 block envs
+
+        let mut rt = rt.mark_and_start_sweep();
+        assert!(rt.env_registry.is_empty());
+
+        // a block entered while the sweep is in progress is kept
+        rt = micro_code::enter_scope(rt, vec!["y".into()])?;
+        rt.sweep_step(2);
+        assert!(rt.gc_sweep.is_some());
+        while rt.gc_sweep.is_some() {
+            rt.sweep_step(2);
+        }
+        assert_eq!(rt.env_registry.len(), 3); // Global env, program env, new block env
+        assert!(rt.current_thread.env.upgrade().is_some());
+
+        Ok(())
+    }
 }
@@ -0,0 +1,259 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    rc::{Rc, Weak},
+};
+
+use bytecode::{
+    weak_clone, Array, Environment, Map, PMap, Slice, Struct, Tuple, Value, Variant, W,
+};
+
+use crate::Runtime;
+
+/// Runtime methods at runtime.
+impl Runtime {
+    /// Deep copy an environment for an isolated thread: the chain of frames, the frames of every closure reachable
+    /// from them, and the arrays, structs and maps they hold. What is shared before is shared within the copy, so two
+    /// variables holding the same array still do, and a cycle through a closure or an array is copied once.
+    /// Channels and semaphores are not copied, they are how an isolated thread talks to the rest.
+    ///
+    /// The copied frames are added to the environment registry.
+    ///
+    /// # Returns
+    ///
+    /// The copy of env.
+    pub fn isolate_env(&mut self, env: &Weak<RefCell<Environment>>) -> Weak<RefCell<Environment>> {
+        let mut copier = Isolate::default();
+        let copy = copier.copy_env(env);
+        self.env_registry.extend(copier.envs.into_values().map(W));
+        copy
+    }
+}
+
+// The copies made so far, by the address of what they are a copy of
+#[derive(Default)]
+struct Isolate {
+    envs: HashMap<*const RefCell<Environment>, Rc<RefCell<Environment>>>,
+    arrays: HashMap<*const RefCell<Vec<Value>>, Array>,
+    structs: HashMap<*const (), Struct>,
+    maps: HashMap<*const (), Map>,
+}
+
+impl Isolate {
+    fn copy_env(&mut self, env: &Weak<RefCell<Environment>>) -> Weak<RefCell<Environment>> {
+        // Every frame of the chain is made before any is filled in, so a closure bound in a frame that points
+        // back into the chain finds its copy. The flags of a frame only depend on the names it and its parents
+        // bind, which the copy has too, so they are copied rather than worked out again
+        let mut chain = vec![];
+        let mut next = env.upgrade();
+        while let Some(frame) = next {
+            if self.envs.contains_key(&Rc::as_ptr(&frame)) {
+                break;
+            }
+            self.envs
+                .insert(Rc::as_ptr(&frame), Environment::new_wrapped());
+            next = frame.borrow().parent.as_ref().and_then(Weak::upgrade);
+            chain.push(frame);
+        }
+
+        for frame in chain {
+            let key = Rc::as_ptr(&frame);
+            let frame = frame.borrow();
+            let env = frame
+                .env
+                .iter()
+                .map(|(sym, val)| (sym.clone(), self.copy_value(val)))
+                .collect();
+
+            let copy = Environment {
+                parent: frame.parent.as_ref().map(|parent| self.find_env(parent)),
+                env,
+                global: frame.global.as_ref().map(|global| self.find_env(global)),
+                shadows_global: frame.shadows_global,
+                mark: frame.mark.clone(),
+                escaped: frame.escaped.clone(),
+            };
+            *self.envs[&key].borrow_mut() = copy;
+        }
+
+        self.find_env(env)
+    }
+
+    // The copy of a frame that has been copied, or the frame itself if it is gone
+    fn find_env(&self, env: &Weak<RefCell<Environment>>) -> Weak<RefCell<Environment>> {
+        env.upgrade()
+            .and_then(|env| self.envs.get(&Rc::as_ptr(&env)).map(weak_clone))
+            .unwrap_or_else(|| env.clone())
+    }
+
+    fn copy_value(&mut self, val: &Value) -> Value {
+        match val {
+            Value::Array(arr) => Value::Array(self.copy_array(arr)),
+            Value::Slice(slice) => Value::Slice(Slice::new(
+                self.copy_array(&slice.arr),
+                slice.offset,
+                slice.len,
+            )),
+            Value::Struct(s) => Value::Struct(self.copy_struct(s)),
+            Value::Map(m) => Value::Map(self.copy_map(m)),
+            // Variants, tuples and persistent collections can't be changed, but the arrays and closures in them can
+            Value::Variant(v) => {
+                let payload = v.payload.iter().map(|v| self.copy_value(v)).collect();
+                Value::Variant(Variant::new(v.enum_name.clone(), v.name.clone(), payload))
+            }
+            Value::Tuple(t) => {
+                Value::Tuple(Tuple::new(t.iter().map(|v| self.copy_value(v)).collect()))
+            }
+            Value::PVec(pvec) => {
+                Value::PVec(pvec.to_vec().iter().map(|v| self.copy_value(v)).collect())
+            }
+            Value::PMap(pmap) => {
+                let copy = pmap
+                    .entries()
+                    .into_iter()
+                    .fold(PMap::new(), |copy, (key, v)| {
+                        let v = self.copy_value(&v);
+                        copy.insert(key, v).expect("keys of a map are valid keys")
+                    });
+                Value::PMap(copy)
+            }
+            Value::Closure {
+                fn_type,
+                sym,
+                prms,
+                addr,
+                env,
+            } => Value::Closure {
+                fn_type: fn_type.clone(),
+                sym: sym.clone(),
+                prms: prms.clone(),
+                addr: *addr,
+                env: W(self.copy_env(&env.0)),
+            },
+            // Channels and semaphores stay shared, the rest can't be changed in place
+            _ => val.clone(),
+        }
+    }
+
+    fn copy_array(&mut self, arr: &Array) -> Array {
+        let key = Rc::as_ptr(&arr.0);
+        if let Some(copy) = self.arrays.get(&key) {
+            return copy.clone();
+        }
+
+        // Recorded before the elements are copied, so an array holding itself holds its copy
+        let copy = Array::new(vec![]);
+        self.arrays.insert(key, copy.clone());
+
+        let vals = arr.borrow().clone();
+        let vals = vals.iter().map(|val| self.copy_value(val)).collect();
+        *copy.borrow_mut() = vals;
+        copy
+    }
+
+    fn copy_struct(&mut self, s: &Struct) -> Struct {
+        if let Some(copy) = self.structs.get(&s.as_ptr()) {
+            return copy.clone();
+        }
+
+        let fields = s.fields();
+        let copy = Struct::new(
+            s.name.clone(),
+            fields
+                .iter()
+                .map(|(field, _)| (field.clone(), Value::Unit))
+                .collect(),
+        );
+        self.structs.insert(s.as_ptr(), copy.clone());
+
+        for (field, val) in fields.iter() {
+            let val = self.copy_value(val);
+            copy.set(field, val);
+        }
+        copy
+    }
+
+    fn copy_map(&mut self, m: &Map) -> Map {
+        if let Some(copy) = self.maps.get(&m.as_ptr()) {
+            return copy.clone();
+        }
+
+        let copy = Map::new();
+        self.maps.insert(m.as_ptr(), copy.clone());
+
+        for (key, val) in m.entries() {
+            let val = self.copy_value(&val);
+            copy.insert(key, val).expect("keys of a map are valid keys");
+        }
+        copy
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use bytecode::{Channel, Value};
+
+    use super::*;
+    use crate::micro_code;
+
+    #[test]
+    fn test_isolate_env() -> Result<()> {
+        let rt = Runtime::new(vec![]);
+        let rt =
+            micro_code::enter_scope(rt, vec!["xs".into(), "ys".into(), "f".into(), "ch".into()])?;
+        let mut rt = micro_code::ldf(rt, 0, vec![])?;
+        let xs = Array::new(vec![Value::Int(1)]);
+        // an array holding itself
+        xs.borrow_mut().push(Value::Array(xs.clone()));
+        let ch = Channel::new();
+        {
+            let env = rt.current_thread.env.upgrade().unwrap();
+            let mut env = env.borrow_mut();
+            env.set("xs", Value::Array(xs.clone()));
+            env.set("ys", Value::Array(xs.clone()));
+            env.set("ch", Value::Channel(ch.clone()));
+        }
+        rt = micro_code::assign(rt, "f".into())?;
+        let envs = rt.env_registry.len();
+
+        let env = rt.current_thread.env.clone();
+        let copy = rt.isolate_env(&env);
+        // the block frame and the global frame
+        assert_eq!(rt.env_registry.len(), envs + 2);
+
+        let copy_rc = copy.upgrade().unwrap();
+        let copy = copy_rc.borrow();
+        let (Some(Value::Array(copy_xs)), Some(Value::Array(copy_ys))) =
+            (copy.env.get("xs"), copy.env.get("ys"))
+        else {
+            panic!("expected the arrays to be copied");
+        };
+
+        // a copy, but the aliasing is kept
+        assert!(!Rc::ptr_eq(&copy_xs.0, &xs.0));
+        assert!(Rc::ptr_eq(&copy_xs.0, &copy_ys.0));
+        let Value::Array(inner) = &copy_xs.borrow()[1] else {
+            panic!("expected an array");
+        };
+        assert!(Rc::ptr_eq(&inner.0, &copy_xs.0));
+
+        // the closure is in the copied frame it was made in
+        let Some(Value::Closure { env: f_env, .. }) = copy.env.get("f") else {
+            panic!("expected a closure");
+        };
+        assert!(Weak::ptr_eq(&f_env.0, &Rc::downgrade(&copy_rc)));
+
+        // the channel is shared
+        assert_eq!(copy.env.get("ch"), Some(&Value::Channel(ch)));
+
+        // the parent is the copy of the global frame
+        let global = copy.parent.as_ref().and_then(Weak::upgrade).unwrap();
+        assert!(global.borrow().env.contains_key("println"));
+        assert!(!Weak::ptr_eq(
+            copy.parent.as_ref().unwrap(),
+            env.upgrade().unwrap().borrow().parent.as_ref().unwrap()
+        ));
+        Ok(())
+    }
+}
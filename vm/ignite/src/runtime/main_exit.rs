@@ -0,0 +1,180 @@
+use std::time::Instant;
+
+use anyhow::Result;
+use bytecode::ThreadID;
+
+use crate::{micro_code, BlockedOn, Runtime, VmError};
+
+/// What happens to the threads still running when the main thread finishes. Detached threads are left out: they
+/// are stopped with the program under every policy, since nothing waits on them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MainExitPolicy {
+    /// Stop them where they are, like a process that exits, and keep their ids in
+    /// [`Runtime::cancelled_threads`] so they can be warned about.
+    #[default]
+    Cancel,
+    /// Wait for them to finish, as if the main thread joined each one. Threads blocked with nothing left to wake
+    /// them are cancelled, since waiting for them would never end.
+    JoinAll,
+    /// Stop the program with an error naming them.
+    Error,
+}
+
+/// Finishing the main thread.
+impl Runtime {
+    /// The main thread executed DONE: the program is done, unless the policy waits for the threads still running.
+    ///
+    /// # Errors
+    ///
+    /// [`VmError::ThreadsStillRunning`] if there are threads still running and the policy is
+    /// [`MainExitPolicy::Error`].
+    pub(crate) fn exit_main(mut self) -> Result<Runtime> {
+        let running = self.running_threads();
+        if running.is_empty() {
+            self.done = true;
+            return Ok(self);
+        }
+
+        match self.on_main_exit {
+            MainExitPolicy::Error => Err(VmError::ThreadsStillRunning(running).into()),
+            MainExitPolicy::JoinAll if self.can_make_progress() => {
+                // Run DONE again once the others have had a turn, like a join of a thread that isn't done
+                self.current_thread.pc -= 1;
+                match self.sleep_queue.front() {
+                    Some(&(_, wake_at)) if self.ready_queue.is_empty() => {
+                        let duration = wake_at.saturating_duration_since(Instant::now());
+                        self.sleep_current_thread(duration)?;
+                        Ok(self)
+                    }
+                    _ => micro_code::yield_(self),
+                }
+            }
+            MainExitPolicy::Cancel | MainExitPolicy::JoinAll => {
+                self.cancelled_threads = running;
+                self.done = true;
+                Ok(self)
+            }
+        }
+    }
+
+    // The threads other than the current one that haven't finished and aren't detached, in the order they were
+    // spawned
+    fn running_threads(&self) -> Vec<ThreadID> {
+        let ready = self.ready_queue.iter();
+        let blocked = self.blocked_queue.iter().map(|(t, _)| t);
+        let asleep = self.sleep_queue.iter().map(|(t, _)| t);
+
+        let mut running: Vec<_> = ready
+            .chain(blocked)
+            .chain(asleep)
+            .map(|t| t.thread_id)
+            .filter(|tid| !self.detached_threads.contains(tid))
+            .collect();
+        running.sort();
+        running
+    }
+
+    // If one of the running threads can still run: it is ready, asleep, or waiting on a builtin run by the async
+    // runner. The others are blocked on a semaphore or channel, and detached threads aren't waited on to wake them
+    fn can_make_progress(&self) -> bool {
+        let waited_on = |tid: &ThreadID| !self.detached_threads.contains(tid);
+        let io = self
+            .blocked_queue
+            .iter()
+            .filter(|(_, on)| matches!(on, BlockedOn::Io))
+            .map(|(t, _)| t);
+
+        self.ready_queue
+            .iter()
+            .chain(self.sleep_queue.iter().map(|(t, _)| t))
+            .chain(io)
+            .any(|t| waited_on(&t.thread_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use compiler::compiler::compile_from_string;
+    use diagnostics::Code;
+
+    use crate::{error_code, run, SchedulerPolicy};
+
+    use super::*;
+
+    fn run_with(src: &str, policy: MainExitPolicy) -> Result<Runtime> {
+        let mut rt = Runtime::new(compile_from_string(src, true)?);
+        rt.set_scheduler(SchedulerPolicy::round_robin(5));
+        rt.set_on_main_exit(policy);
+        run(rt)
+    }
+
+    // The main thread finishes while work(3) is still counting, listen is blocked on a channel no one sends on, and
+    // spin is detached and never ends
+    const UNFINISHED: &str = r"
+    fn work(n: int) {
+        let i = 0;
+        loop i < n {
+            sleep(1);
+            i = i + 1;
+        }
+    }
+    fn listen(c: chan[int]) {
+        recv(c);
+    }
+    fn spin() {
+        loop true {
+            yield;
+        }
+    }
+    spawn work(3);
+    let c: chan[int] = chan();
+    spawn listen(c);
+    let t = spawn spin();
+    detach(t);
+    ";
+
+    #[test]
+    fn test_main_exit_cancel() -> Result<()> {
+        let rt = run_with(UNFINISHED, MainExitPolicy::Cancel)?;
+        assert!(rt.is_done());
+        assert_eq!(rt.cancelled_threads, vec![2, 3]);
+        assert!(rt.zombie_threads.is_empty());
+
+        // nothing is cancelled if every thread is done
+        let rt = run_with(
+            "fn f() {} let t = spawn f(); join t;",
+            MainExitPolicy::Cancel,
+        )?;
+        assert!(rt.cancelled_threads.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_main_exit_join_all() -> Result<()> {
+        let rt = run_with(UNFINISHED, MainExitPolicy::JoinAll)?;
+        assert!(rt.is_done());
+
+        // work(3) ran to the end, and listen could never be woken so it was cancelled
+        assert!(rt.zombie_threads.contains_key(&2));
+        assert_eq!(rt.cancelled_threads, vec![3]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_main_exit_error() -> Result<()> {
+        let Err(err) = run_with(UNFINISHED, MainExitPolicy::Error) else {
+            panic!("threads are still running when the main thread finishes");
+        };
+        assert_eq!(error_code(&err), Some(Code::R010));
+        assert!(err
+            .root_cause()
+            .to_string()
+            .contains("threads 2, 3 were still running"));
+
+        run_with(
+            "fn f() {} let t = spawn f(); join t;",
+            MainExitPolicy::Error,
+        )?;
+        Ok(())
+    }
+}
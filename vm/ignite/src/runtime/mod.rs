@@ -1,18 +1,41 @@
 use std::{
-    collections::{HashMap, HashSet, VecDeque},
+    collections::{hash_set, BTreeMap, BTreeSet, HashSet, VecDeque},
+    path::Path,
     time::{Duration, Instant},
 };
 
-use bytecode::{weak_clone, ByteCode, EnvStrong, Environment, Semaphore, ThreadID, W};
+use anyhow::Result;
+use bytecode::{
+    read_from_file, weak_clone, ByteCode, Channel, EnvStrong, Environment, Semaphore, Symbol,
+    ThreadID, Value, DEFAULT_GLOBAL_FALLBACK_DEPTH, W,
+};
 
 use crate::Thread;
+pub use main_exit::*;
+pub use profile::*;
 pub use run::*;
+#[cfg(feature = "async")]
+pub use run_async::*;
+pub use scheduler::*;
+pub use trace::*;
 
+mod embed;
 mod gc;
+mod isolate;
+mod main_exit;
+mod profile;
+mod reap;
 mod run;
+#[cfg(feature = "async")]
+mod run_async;
+mod scheduler;
+mod trace;
 
 pub const DEFAULT_TIME_QUANTUM: Duration = Duration::from_millis(100);
 pub const DEFAULT_GC_INTERVAL: Duration = Duration::from_secs(1);
+pub const DEFAULT_GC_THRESHOLD: usize = 100_000;
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 100_000;
+pub const DEFAULT_MAX_OPERAND_STACK: usize = 1_000_000;
 pub const MAIN_THREAD_ID: i64 = 1;
 
 /// The runtime of the virtual machine.
@@ -28,26 +51,91 @@ pub struct Runtime {
     pub debug: bool,
     /// The time the program started, used for calculating the time quantum.
     pub time: Instant,
-    /// The maximum amount of time a thread can run before it is preempted.
+    /// The maximum amount of time a thread can run before it is preempted, with the timed scheduler policy.
     pub time_quantum: Duration,
+    /// When threads are preempted and which runs next.
+    pub scheduler: SchedulerPolicy,
+    /// The number of instructions the current thread has fetched in its quantum.
+    pub quantum_instrs: usize,
     /// The time the garbage collector was last run.
     pub gc_timer: Instant,
     /// The interval at which to run the mark and sweep garbage collector.
     pub gc_interval: Duration,
+    /// Also collect once there are this many environments, without waiting for the interval. None to collect on
+    /// the interval only.
+    pub gc_threshold: Option<usize>,
+    /// How many environments trigger the next collection: the threshold, or twice the environments left by the
+    /// last collection if that is more, so a program with many live environments doesn't collect every step.
+    pub gc_env_limit: usize,
+    /// In incremental mode, the most environments swept in a step. None to sweep all of them in the collection.
+    pub gc_incremental: Option<usize>,
+    /// The environments of the registry still to be swept by the collection in progress, in incremental mode.
+    pub gc_sweep: Option<hash_set::IntoIter<EnvStrong>>,
+    /// The most environments the program can have live, if there is a limit. Past it the program stops with an
+    /// out of memory error, once a collection has shown they aren't garbage.
+    pub max_heap: Option<usize>,
+    /// The most frames the runtime stack of a thread can hold when it calls a function. Blocks push frames as
+    /// well as calls, so recursion stops a little short of this depth.
+    pub max_call_depth: usize,
+    /// The most values the operand stack of a thread can hold.
+    pub max_operand_stack: usize,
+    /// Drop the environments of a call or block when it is reset, instead of waiting for the garbage collector,
+    /// if no closure or thread was made in them.
+    pub eager_reset: bool,
+    /// How many frames LD searches before it looks in the global frame directly.
+    pub global_fallback_depth: usize,
     /// The instructions to execute.
     pub instrs: Vec<ByteCode>,
     /// The environment registry, holds strong references to environments.
     pub env_registry: HashSet<EnvStrong>,
+    /// Counts the garbage collections. Environments marked with the current epoch were reachable in the last one.
+    pub gc_epoch: u64,
     /// The number of threads that have been created.
     pub thread_count: i64,
+    /// The arguments the program is run with, see [`Runtime::set_main_args`].
+    pub main_args: Vec<Value>,
     /// The current thread that is executing.
     pub current_thread: Thread,
     /// The threads that are ready to run.
     pub ready_queue: VecDeque<Thread>,
-    /// The threads that are blocked.
-    pub blocked_queue: VecDeque<(Thread, Semaphore)>,
-    /// The threads that have finished executing, waiting to be joined.
-    pub zombie_threads: HashMap<ThreadID, Thread>,
+    /// The threads that are blocked, with what each is waiting for.
+    pub blocked_queue: VecDeque<(Thread, BlockedOn)>,
+    /// The threads that called sleep, with when each wakes, earliest first.
+    pub sleep_queue: VecDeque<(Thread, Instant)>,
+    /// The threads that have finished executing, waiting to be joined. Kept in the order they were spawned, like
+    /// the other collections of threads, so anything that goes through them does so the same way every run.
+    pub zombie_threads: BTreeMap<ThreadID, Thread>,
+    /// The threads detached before they finished, which are dropped when they do instead of becoming zombies.
+    pub detached_threads: BTreeSet<ThreadID>,
+    /// The most zombie threads kept for joining, if there is a limit. Past it the earliest spawned are reaped.
+    pub max_zombies: Option<usize>,
+    /// What happens to the threads still running when the main thread finishes.
+    pub on_main_exit: MainExitPolicy,
+    /// The threads that were still running when the main thread finished, and were stopped with the program.
+    pub cancelled_threads: Vec<ThreadID>,
+    /// Per-opcode timings, only collected when profiling is turned on.
+    pub profile: Option<OpcodeProfile>,
+    /// If the program can call the builtins that use the network.
+    pub allow_net: bool,
+    /// If the program can run other programs with run_command.
+    pub allow_run: bool,
+    /// The symbols whose assignments are reported in watch_hit, for the debugger.
+    pub watchpoints: HashSet<Symbol>,
+    /// The last watched symbol assigned to, until the debugger takes it.
+    pub watch_hit: Option<Symbol>,
+    /// Where every executed instruction is recorded, only when tracing is turned on.
+    pub trace: Option<Box<dyn TraceSink>>,
+}
+
+/// What a thread in the blocked queue is waiting for.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlockedOn {
+    /// A post on the semaphore.
+    Semaphore(Semaphore),
+    /// A value to be sent on the channel.
+    Channel(Channel),
+    /// A builtin running as a task of the async runner, see `run_async`.
+    Io,
 }
 
 /// Constructors for the runtime.
@@ -63,17 +151,51 @@ impl Runtime {
             done: false,
             time: Instant::now(),
             time_quantum: DEFAULT_TIME_QUANTUM,
+            scheduler: SchedulerPolicy::default(),
+            quantum_instrs: 0,
             gc_timer: Instant::now(),
             gc_interval: DEFAULT_GC_INTERVAL,
+            gc_threshold: Some(DEFAULT_GC_THRESHOLD),
+            gc_env_limit: DEFAULT_GC_THRESHOLD,
+            gc_incremental: None,
+            gc_sweep: None,
+            max_heap: None,
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            max_operand_stack: DEFAULT_MAX_OPERAND_STACK,
+            eager_reset: false,
+            global_fallback_depth: DEFAULT_GLOBAL_FALLBACK_DEPTH,
             instrs,
             env_registry: envs,
+            gc_epoch: 0,
             thread_count: 1,
+            main_args: vec![],
             current_thread: Thread::new(MAIN_THREAD_ID, global_env_weak),
             ready_queue: VecDeque::new(),
             blocked_queue: VecDeque::new(),
-            zombie_threads: HashMap::new(),
+            sleep_queue: VecDeque::new(),
+            zombie_threads: BTreeMap::new(),
+            detached_threads: BTreeSet::new(),
+            max_zombies: None,
+            on_main_exit: MainExitPolicy::default(),
+            cancelled_threads: vec![],
+            profile: None,
+            allow_net: false,
+            allow_run: false,
+            watchpoints: HashSet::new(),
+            watch_hit: None,
+            trace: None,
         }
     }
+
+    /// Create a runtime for the program in a .o2 file, written by [`bytecode::write_to_file`].
+    ///
+    /// # Errors
+    ///
+    /// If the file can't be read, or the program was compiled with a version of the bytecode or a set of builtins
+    /// this VM can't run, see [`bytecode::read_bytecode`].
+    pub fn new_from_file(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Runtime::new(read_from_file(path)?))
+    }
 }
 
 impl Default for Runtime {
@@ -88,11 +210,71 @@ impl Runtime {
         self.time_quantum = time_quantum;
     }
 
+    pub fn set_scheduler(&mut self, scheduler: SchedulerPolicy) {
+        self.scheduler = scheduler;
+    }
+
     pub fn set_gc_interval(&mut self, gc_interval: Duration) {
         self.gc_interval = gc_interval;
     }
 
+    pub fn set_gc_threshold(&mut self, gc_threshold: Option<usize>) {
+        self.gc_threshold = gc_threshold;
+        if let Some(threshold) = gc_threshold {
+            self.gc_env_limit = threshold;
+        }
+    }
+
+    /// Sweep at most `budget` environments per step, instead of all of them when collecting.
+    pub fn set_gc_incremental(&mut self, budget: usize) {
+        self.gc_incremental = Some(budget.max(1));
+    }
+
+    pub fn set_max_heap(&mut self, max_heap: usize) {
+        self.max_heap = Some(max_heap);
+    }
+
+    pub fn set_max_call_depth(&mut self, max_call_depth: usize) {
+        self.max_call_depth = max_call_depth;
+    }
+
+    pub fn set_max_operand_stack(&mut self, max_operand_stack: usize) {
+        self.max_operand_stack = max_operand_stack;
+    }
+
+    pub fn set_eager_reset(&mut self) {
+        self.eager_reset = true;
+    }
+
+    pub fn set_global_fallback_depth(&mut self, depth: usize) {
+        self.global_fallback_depth = depth;
+    }
+
+    pub fn set_max_zombies(&mut self, max_zombies: usize) {
+        self.max_zombies = Some(max_zombies);
+    }
+
+    pub fn set_on_main_exit(&mut self, policy: MainExitPolicy) {
+        self.on_main_exit = policy;
+    }
+
     pub fn set_debug_mode(&mut self) {
         self.debug = true;
     }
+
+    pub fn set_profile_opcodes(&mut self) {
+        self.profile = Some(OpcodeProfile::new());
+    }
+
+    pub fn set_allow_net(&mut self) {
+        self.allow_net = true;
+    }
+
+    pub fn set_allow_run(&mut self) {
+        self.allow_run = true;
+    }
+
+    pub fn set_trace(&mut self, sink: impl TraceSink + 'static) {
+        self.trace = Some(Box::new(sink));
+    }
 }
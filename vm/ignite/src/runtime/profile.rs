@@ -0,0 +1,84 @@
+use std::{collections::BTreeMap, fmt::Display, time::Duration};
+
+/// Cumulative execution count and time for an opcode.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct OpcodeStats {
+    pub count: u64,
+    pub total: Duration,
+}
+
+/// Per-opcode timings collected while running with profiling turned on.
+#[derive(Debug, Default, Clone)]
+pub struct OpcodeProfile {
+    stats: BTreeMap<&'static str, OpcodeStats>,
+}
+
+impl OpcodeProfile {
+    pub fn new() -> Self {
+        OpcodeProfile::default()
+    }
+
+    /// Record one execution of the opcode that took the given time.
+    #[inline]
+    pub fn record(&mut self, opcode: &'static str, elapsed: Duration) {
+        let stats = self.stats.entry(opcode).or_default();
+        stats.count += 1;
+        stats.total += elapsed;
+    }
+
+    /// Stats for the given opcode, if it was executed at all.
+    pub fn get(&self, opcode: &str) -> Option<&OpcodeStats> {
+        self.stats.get(opcode)
+    }
+}
+
+/// One row per executed opcode, most expensive first.
+impl Display for OpcodeProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut rows: Vec<_> = self.stats.iter().collect();
+        rows.sort_by_key(|(_, stats)| std::cmp::Reverse(stats.total));
+
+        writeln!(
+            f,
+            "{:<12}{:>12}{:>16}{:>12}",
+            "opcode", "count", "total (ns)", "avg (ns)"
+        )?;
+
+        for (opcode, stats) in rows {
+            let total = stats.total.as_nanos();
+            writeln!(
+                f,
+                "{:<12}{:>12}{:>16}{:>12}",
+                opcode,
+                stats.count,
+                total,
+                total / stats.count.max(1) as u128
+            )?;
+        }
+
+        std::result::Result::Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_record() {
+        let mut profile = OpcodeProfile::new();
+        profile.record("LDC", Duration::from_nanos(10));
+        profile.record("LDC", Duration::from_nanos(30));
+        profile.record("POP", Duration::from_nanos(5));
+
+        let ldc = profile.get("LDC").expect("LDC was recorded");
+        assert_eq!(ldc.count, 2);
+        assert_eq!(ldc.total, Duration::from_nanos(40));
+        assert!(profile.get("CALL").is_none());
+
+        let table = profile.to_string();
+        let ldc_row = table.find("LDC").unwrap();
+        let pop_row = table.find("POP").unwrap();
+        assert!(ldc_row < pop_row);
+    }
+}
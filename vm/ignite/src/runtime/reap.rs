@@ -0,0 +1,120 @@
+use anyhow::Result;
+use bytecode::ThreadID;
+
+use crate::{Runtime, Thread, VmError};
+
+/// What happens to threads once they finish.
+impl Runtime {
+    /// Detach the thread, so it is dropped as soon as it finishes instead of waiting to be joined. A thread that
+    /// has already finished is dropped now.
+    ///
+    /// # Errors
+    ///
+    /// If there is no such thread, or it is already detached.
+    pub fn detach_thread(&mut self, tid: ThreadID) -> Result<()> {
+        if self.zombie_threads.remove(&tid).is_some() {
+            return Ok(());
+        }
+
+        if self.detached_threads.contains(&tid) || !self.is_running(tid) {
+            return Err(VmError::NoSuchThread(tid).into());
+        }
+
+        self.detached_threads.insert(tid);
+        Ok(())
+    }
+
+    /// Keep the finished thread as a zombie for a join, unless it was detached. Past the zombie limit the
+    /// earliest spawned zombies are reaped to make room, and can't be joined any more.
+    pub fn bury(&mut self, thread: Thread) {
+        if self.detached_threads.remove(&thread.thread_id) {
+            return;
+        }
+
+        self.zombie_threads.insert(thread.thread_id, thread);
+
+        let Some(max_zombies) = self.max_zombies else {
+            return;
+        };
+        while self.zombie_threads.len() > max_zombies {
+            self.zombie_threads.pop_first();
+        }
+    }
+
+    /// Check if a join on the thread could still succeed: it hasn't finished and isn't detached.
+    pub fn is_joinable(&self, tid: ThreadID) -> bool {
+        !self.detached_threads.contains(&tid) && self.is_running(tid)
+    }
+
+    // If the thread hasn't finished, whether it is running, ready, blocked or asleep
+    fn is_running(&self, tid: ThreadID) -> bool {
+        self.current_thread.thread_id == tid
+            || self.ready_queue.iter().any(|t| t.thread_id == tid)
+            || self.blocked_queue.iter().any(|(t, _)| t.thread_id == tid)
+            || self.sleep_queue.iter().any(|(t, _)| t.thread_id == tid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        micro_code::{done, spawn, yield_},
+        MAIN_THREAD_ID,
+    };
+
+    use super::*;
+
+    // Spawn n threads one at a time and run each to the end, leaving them as zombies
+    fn spawn_and_finish(mut rt: Runtime, n: usize) -> Result<Runtime> {
+        for _ in 0..n {
+            rt = spawn(rt, 0)?;
+            rt.current_thread.operand_stack.pop();
+            rt = yield_(rt)?;
+            rt = done(rt)?;
+        }
+        Ok(rt)
+    }
+
+    #[test]
+    fn test_detach_thread() -> Result<()> {
+        let mut rt = Runtime::default();
+        rt = spawn(rt, 0)?;
+        rt = spawn(rt, 0)?;
+        let (first, second) = (MAIN_THREAD_ID + 1, MAIN_THREAD_ID + 2);
+
+        // Detached before it finishes, so it never becomes a zombie
+        rt.detach_thread(first)?;
+        assert!(!rt.is_joinable(first));
+        assert!(rt.detach_thread(first).is_err());
+
+        rt = yield_(rt)?;
+        rt = done(rt)?;
+        rt = done(rt)?;
+        assert!(rt.zombie_threads.contains_key(&second));
+        assert!(!rt.zombie_threads.contains_key(&first));
+        assert!(rt.detached_threads.is_empty());
+
+        // Detached after it finishes, so the zombie is dropped
+        rt.detach_thread(second)?;
+        assert!(rt.zombie_threads.is_empty());
+
+        assert!(rt.detach_thread(MAIN_THREAD_ID + 3).is_err());
+        assert!(rt.is_joinable(MAIN_THREAD_ID));
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_zombies() -> Result<()> {
+        let rt = spawn_and_finish(Runtime::default(), 10)?;
+        assert_eq!(rt.zombie_threads.len(), 10);
+
+        let mut rt = Runtime::default();
+        rt.set_max_zombies(3);
+        let rt = spawn_and_finish(rt, 10)?;
+
+        let zombies: Vec<_> = rt.zombie_threads.keys().copied().collect();
+        assert_eq!(zombies, vec![9, 10, 11]);
+        assert!(!rt.is_joinable(2));
+        Ok(())
+    }
+}
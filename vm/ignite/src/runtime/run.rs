@@ -1,9 +1,9 @@
-use std::time::Instant;
+use std::{collections::BTreeMap, time::Instant};
 
 use anyhow::Result;
-use bytecode::ByteCode;
+use bytecode::{relative_target, ByteCode};
 
-use crate::{micro_code, Runtime, VmError};
+use crate::{backtrace::with_backtrace, micro_code, Runtime, Stack, TraceEvent, VmError};
 
 /// Runtime methods at runtime.
 impl Runtime {
@@ -25,45 +25,139 @@ impl Runtime {
             .cloned()
             .ok_or(VmError::PcOutOfBounds(self.current_thread.pc))?;
         self.current_thread.pc += 1;
+        self.quantum_instrs += 1;
         Ok(instr)
     }
-    /// Check if the time quantum has expired.
-    /// The time quantum is the maximum amount of time a thread can run before it is preempted.
-    #[inline]
-    pub fn time_quantum_expired(&self) -> bool {
-        self.time.elapsed() >= self.time_quantum
-    }
 
+    /// A collection is due once the interval has passed since the last one, or the environment registry has
+    /// grown to the limit if there is a threshold.
     #[inline]
     pub fn should_garbage_collect(&self) -> bool {
         self.gc_timer.elapsed() >= self.gc_interval
+            || (self.gc_threshold.is_some() && self.env_registry.len() >= self.gc_env_limit)
     }
 
+    /// Collect the garbage. In incremental mode only the mark is done here, and the sweep is left to the steps
+    /// that follow, see [`Runtime::gc_step`].
     #[inline]
     pub fn garbage_collect(mut self) -> Self {
-        self = self.mark_and_weep();
+        self = match self.gc_incremental {
+            Some(_) => self.mark_and_start_sweep(),
+            None => self.mark_and_weep(),
+        };
         self.gc_timer = Instant::now();
         self
     }
 
+    /// The garbage collection work of a step: in incremental mode, the next part of the sweep in progress if there
+    /// is one, otherwise a collection if one is due.
+    ///
+    /// # Errors
+    ///
+    /// If the program has more environments than the heap limit allows, see [`Runtime::check_heap`].
+    #[inline]
+    pub fn gc_step(mut self) -> Result<Self> {
+        if let (Some(budget), Some(_)) = (self.gc_incremental, &self.gc_sweep) {
+            self.sweep_step(budget);
+        } else if self.should_garbage_collect() {
+            self = self.garbage_collect();
+        }
+
+        self.check_heap()
+    }
+
     /// The program is done if the current thread is the main thread and the current thread is done.
     #[inline]
     pub fn is_done(&self) -> bool {
         self.done
     }
 
-    pub fn debug_print(&self) {
+    /// Take one step of the program: switch to the next ready thread if the time quantum of the current one has
+    /// expired, otherwise execute its next instruction. [`run`] takes steps until the program is done.
+    ///
+    /// # Errors
+    ///
+    /// If an error occurs during execution.
+    #[inline]
+    pub fn step(mut self) -> Result<Self> {
+        self = self.gc_step()?;
+
+        if self.time_quantum_expired() {
+            return micro_code::yield_(self);
+        }
+
+        if self.debug {
+            self.debug_print()?;
+        }
+
+        let instr = self.fetch_instr()?;
+        self.trace_instr(&instr)?;
+        execute_profiled(self, instr)
+    }
+
+    /// Stop the thread if the instruction at `pc` left more values on its operand stack than the limit allows.
+    ///
+    /// # Errors
+    ///
+    /// [`VmError::StackOverflow`] if the operand stack is over the limit.
+    #[inline]
+    pub fn check_operand_stack(self, pc: usize) -> Result<Self> {
+        if self.current_thread.operand_stack.len() <= self.max_operand_stack {
+            return Ok(self);
+        }
+
+        Err(VmError::StackOverflow {
+            stack: Stack::Operand,
+            limit: self.max_operand_stack,
+            thread_id: self.current_thread.thread_id,
+            pc,
+        }
+        .into())
+    }
+
+    /// Record the instruction that was just fetched, if tracing is turned on.
+    ///
+    /// # Errors
+    ///
+    /// If the sink fails to record it.
+    #[inline]
+    pub fn trace_instr(&mut self, instr: &ByteCode) -> Result<()> {
+        let Some(sink) = self.trace.as_mut() else {
+            return Ok(());
+        };
+
+        let thread = &self.current_thread;
+        sink.record(TraceEvent {
+            thread_id: thread.thread_id,
+            pc: thread.pc - 1,
+            instr: instr.clone(),
+            stack_depth: thread.operand_stack.len(),
+            frame_depth: thread.runtime_stack.len(),
+        })
+    }
+
+    /// Print the state of the current thread, before it executes its next instruction.
+    ///
+    /// # Errors
+    ///
+    /// If the pc is past the end of the program, or the environment of the thread has been dropped.
+    pub fn debug_print(&self) -> Result<()> {
         let thread_id = self.current_thread.thread_id;
         let pc = self.current_thread.pc;
-        let instruction = self.instrs.get(pc).expect("PC out of bounds");
+        let instruction = self.instrs.get(pc).ok_or(VmError::PcOutOfBounds(pc))?;
+        let env = self
+            .current_thread
+            .env
+            .upgrade()
+            .ok_or(VmError::EnvironmentDroppedError)?;
         println!("Thread: {}, PC: {}, {:?}", thread_id, pc, instruction);
         println!("Operand Stack: {:?}", self.current_thread.operand_stack);
         println!("Runtime Stack: {:?}", self.current_thread.runtime_stack);
-        println!(
-            "Environment: {:?}",
-            self.current_thread.env.upgrade().unwrap().borrow()
-        );
+        // in order of name, so runs print the same
+        let names: BTreeMap<_, _> = env.borrow().env.clone().into_iter().collect();
+        println!("Environment: {:?}", names);
         println!();
+        Ok(())
     }
 }
 
@@ -82,29 +176,28 @@ impl Runtime {
 /// If an error occurs during execution.
 #[inline]
 pub fn run(mut rt: Runtime) -> Result<Runtime> {
-    loop {
-        if rt.is_done() {
-            break;
-        }
-
-        if rt.should_garbage_collect() {
-            rt = rt.garbage_collect();
-        }
+    while !rt.is_done() {
+        rt = rt.step()?;
+    }
 
-        if rt.time_quantum_expired() {
-            rt = micro_code::yield_(rt)?;
-            continue;
-        }
+    Ok(rt)
+}
 
-        if rt.debug {
-            rt.debug_print();
-        }
+// Execute the instruction, timing it if profiling is turned on
+#[inline]
+pub(crate) fn execute_profiled(rt: Runtime, instr: ByteCode) -> Result<Runtime> {
+    if rt.profile.is_none() {
+        return execute(rt, instr);
+    }
 
-        let instr = rt.fetch_instr()?;
+    let opcode = instr.opcode();
+    let start = Instant::now();
+    let mut rt = execute(rt, instr)?;
+    let elapsed = start.elapsed();
 
-        rt = execute(rt, instr)?;
+    if let Some(profile) = rt.profile.as_mut() {
+        profile.record(opcode, elapsed);
     }
-
     Ok(rt)
 }
 
@@ -123,9 +216,14 @@ pub fn run(mut rt: Runtime) -> Result<Runtime> {
 /// # Errors
 ///
 /// If an error occurs during execution.
+/// If the instruction leaves more values on the operand stack than the limit allows.
+/// The error carries the [`Backtrace`] of the thread.
 #[inline]
 pub fn execute(rt: Runtime, instr: ByteCode) -> Result<Runtime> {
-    match instr {
+    let thread_id = rt.current_thread.thread_id;
+    let pc = rt.current_thread.pc.saturating_sub(1);
+
+    let res = match instr {
         ByteCode::DONE => micro_code::done(rt),
         ByteCode::ASSIGN(sym) => micro_code::assign(rt, sym),
         ByteCode::LD(sym) => micro_code::ld(rt, sym),
@@ -146,18 +244,69 @@ pub fn execute(rt: Runtime, instr: ByteCode) -> Result<Runtime> {
         ByteCode::SEMCREATE => micro_code::sem_create(rt),
         ByteCode::WAIT => micro_code::wait(rt),
         ByteCode::POST => micro_code::post(rt),
-    }
+        ByteCode::ARRAY(len) => micro_code::array(rt, len),
+        ByteCode::ARRAYFILL(len) => micro_code::array_fill(rt, len),
+        ByteCode::LDIDX => micro_code::ld_idx(rt),
+        ByteCode::ASSIGNIDX => micro_code::assign_idx(rt),
+        ByteCode::SLICE => micro_code::slice(rt),
+        ByteCode::LEN => micro_code::len(rt),
+        ByteCode::STRUCT(name, fields) => micro_code::struct_(rt, name, fields),
+        ByteCode::LDFIELD(field) => micro_code::ld_field(rt, field),
+        ByteCode::ASSIGNFIELD(field) => micro_code::assign_field(rt, field),
+        ByteCode::SEND => micro_code::send(rt),
+        ByteCode::RECV => micro_code::recv(rt),
+        ByteCode::SPAWNISO(addr) => micro_code::spawn_iso(rt, addr),
+        ByteCode::TRYJOIN => micro_code::try_join(rt),
+        ByteCode::VARIANT(enum_name, name, arity) => {
+            micro_code::variant(rt, enum_name, name, arity)
+        }
+        ByteCode::ISVARIANT(name) => micro_code::is_variant(rt, name),
+        ByteCode::LDVARIANT(idx) => micro_code::ld_variant(rt, idx),
+        ByteCode::TUPLE(len) => micro_code::tuple(rt, len),
+        ByteCode::UNPACK(len) => micro_code::unpack(rt, len),
+        ByteCode::LDTUPLE(idx) => micro_code::ld_tuple(rt, idx),
+        ByteCode::MAP(len) => micro_code::map(rt, len),
+        ByteCode::JOFR(offset) => micro_code::jof(rt, relative_target(pc, offset)),
+        ByteCode::GOTOR(offset) => micro_code::goto(rt, relative_target(pc, offset)),
+        ByteCode::LDFR(offset, prms) => micro_code::ldf(rt, relative_target(pc, offset), prms),
+        ByteCode::SPAWNR(offset) => micro_code::spawn(rt, relative_target(pc, offset)),
+        ByteCode::SPAWNISOR(offset) => micro_code::spawn_iso(rt, relative_target(pc, offset)),
+    };
+
+    res.and_then(|rt| rt.check_operand_stack(pc))
+        .map_err(|err| with_backtrace(err, thread_id, pc))
 }
 
 #[cfg(test)]
 mod tests {
-    use std::time::Duration;
+    use std::time::{Duration, Instant};
 
-    use crate::MAIN_THREAD_ID;
+    use crate::{MemorySink, SchedulerPolicy, MAIN_THREAD_ID};
 
     use super::*;
     use anyhow::{Ok, Result};
     use bytecode::{builtin, BinOp, ByteCode, FrameType, Symbol, UnOp, Value};
+    use compiler::compiler::compile_from_string;
+
+    #[test]
+    fn test_profile_opcodes() -> Result<()> {
+        let instrs = vec![
+            ByteCode::ldc(1),
+            ByteCode::ldc(2),
+            ByteCode::BINOP(BinOp::Add),
+            ByteCode::DONE,
+        ];
+        let mut rt = Runtime::new(instrs);
+        rt.set_profile_opcodes();
+        let rt = run(rt)?;
+
+        let profile = rt.profile.as_ref().expect("Profiling was turned on");
+        assert_eq!(profile.get("LDC").map(|s| s.count), Some(2));
+        assert_eq!(profile.get("BINOP").map(|s| s.count), Some(1));
+        assert_eq!(profile.get("DONE").map(|s| s.count), Some(1));
+
+        Ok(())
+    }
 
     #[test]
     fn test_pc() {
@@ -200,6 +349,36 @@ mod tests {
         assert_eq!(rt.current_thread.pc, 3);
     }
 
+    #[test]
+    fn test_step() -> Result<()> {
+        let instrs = vec![
+            ByteCode::ldc(1),
+            ByteCode::ldc(2),
+            ByteCode::BINOP(BinOp::Add),
+            ByteCode::DONE,
+        ];
+        let mut rt = Runtime::new(instrs);
+        rt = rt.step()?;
+        assert_eq!(rt.current_thread.pc, 1);
+        assert_eq!(rt.current_thread.operand_stack, vec![Value::Int(1)]);
+
+        rt = rt.step()?.step()?;
+        assert_eq!(rt.current_thread.operand_stack, vec![Value::Int(3)]);
+        assert!(!rt.is_done());
+        rt = rt.step()?;
+        assert!(rt.is_done());
+
+        // A step past the quantum switches threads instead
+        let mut rt = Runtime::new(vec![ByteCode::ldc(1), ByteCode::DONE]);
+        rt.set_time_quantum(Duration::ZERO);
+        rt.ready_queue
+            .push_back(rt.current_thread.spawn_child(2, 1));
+        rt = rt.step()?;
+        assert_eq!(rt.current_thread.thread_id, 2);
+        assert_eq!(rt.current_thread.pc, 1);
+        Ok(())
+    }
+
     #[test]
     fn test_arithmetic() {
         // 42 + 42
@@ -237,6 +416,13 @@ mod tests {
         let rt = Runtime::new(instrs);
         let rt = run(rt).unwrap();
         assert_eq!(rt.current_thread.operand_stack, vec![Value::Bool(false)]);
+
+        // remainder takes the sign of the dividend, like Rust
+        let rt = run(Runtime::new(
+            compile_from_string("7 % 3 + -7 % 3", true).unwrap(),
+        ))
+        .unwrap();
+        assert_eq!(rt.current_thread.operand_stack, vec![Value::Int(0)]);
     }
 
     #[test]
@@ -335,10 +521,7 @@ mod tests {
         let rt = Runtime::new(instrs);
         let rt = run(rt)?;
 
-        assert_eq!(
-            rt.current_thread.operand_stack,
-            vec![Value::Int(std::i64::MAX)]
-        );
+        assert_eq!(rt.current_thread.operand_stack, vec![Value::Int(i64::MAX)]);
 
         Ok(())
     }
@@ -433,6 +616,107 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_trace() -> Result<()> {
+        // spawn simple(123) and join it, as in test_concurrency_02
+        let instrs = vec![
+            ByteCode::enterscope(vec!["simple"]),
+            ByteCode::ldf(3, vec!["n"]),
+            ByteCode::GOTO(5),
+            ByteCode::ld("n"),
+            ByteCode::RESET(FrameType::CallFrame),
+            ByteCode::assign("simple"),
+            ByteCode::SPAWN(8),
+            ByteCode::GOTO(13),
+            ByteCode::POP,
+            ByteCode::ld("simple"),
+            ByteCode::ldc(123),
+            ByteCode::CALL(1),
+            ByteCode::DONE,
+            ByteCode::ldc(MAIN_THREAD_ID + 1),
+            ByteCode::JOIN,
+            ByteCode::DONE,
+        ];
+
+        let sink = MemorySink::new();
+        let mut rt = Runtime::new(instrs);
+        rt.set_trace(sink.clone());
+        run(rt)?;
+
+        let events = sink.events();
+        assert_eq!(
+            events[0],
+            TraceEvent {
+                thread_id: MAIN_THREAD_ID,
+                pc: 0,
+                instr: ByteCode::enterscope(vec!["simple"]),
+                stack_depth: 0,
+                frame_depth: 0,
+            }
+        );
+
+        // The main thread runs until it joins the child, which runs to the end before the main thread goes on
+        let mut threads: Vec<_> = events.iter().map(|e| e.thread_id).collect();
+        threads.dedup();
+        assert_eq!(
+            threads,
+            vec![MAIN_THREAD_ID, MAIN_THREAD_ID + 1, MAIN_THREAD_ID]
+        );
+
+        let body = events
+            .iter()
+            .find(|e| e.pc == 3)
+            .expect("simple should be called");
+        assert_eq!(body.thread_id, MAIN_THREAD_ID + 1);
+        assert!(body.frame_depth > 0);
+        assert_eq!(events.last().map(|e| e.pc), Some(15));
+        Ok(())
+    }
+
+    #[test]
+    fn test_trace_deterministic() -> Result<()> {
+        // workers that are joined, detached and left as zombies, interleaved by a round robin scheduler
+        let t = r"
+        let total = 0;
+        fn work(n: int) {
+            let i = 0;
+            loop i < n {
+                i = i + 1;
+            }
+            total = total * 10 + n;
+        }
+
+        let a = spawn work(5);
+        let b = spawn work(3);
+        let c = spawn work(8);
+        let d = spawn work(2);
+        detach(b);
+        join a;
+        join c;
+        yield;
+        total
+        ";
+
+        let trace = || -> Result<(Vec<TraceEvent>, Option<Value>)> {
+            let sink = MemorySink::new();
+            let mut rt = Runtime::new(compile_from_string(t, true)?);
+            rt.set_scheduler(SchedulerPolicy::round_robin(3));
+            rt.set_trace(sink.clone());
+            let rt = run(rt)?;
+            Ok((sink.events(), rt.result()))
+        };
+
+        let first = trace()?;
+        let mut threads: Vec<_> = first.0.iter().map(|e| e.thread_id).collect();
+        threads.dedup();
+        assert!(threads.len() > 5, "the threads should interleave");
+
+        for _ in 0..10 {
+            assert_eq!(trace()?, first);
+        }
+        Ok(())
+    }
+
     #[test]
     fn test_concurrency_03() -> Result<()> {
         // let count = 0;
@@ -469,8 +753,10 @@ mod tests {
             ByteCode::DONE,
         ];
 
+        // The child runs for 100 instructions before the parent is done: POP, LD and CALL, then 19 times round the
+        // 5 instructions of the loop and the first 2 of the 20th
         let mut rt = Runtime::new(instrs);
-        rt.set_time_quantum(Duration::from_millis(1000)); // Set the time quantum to 1 second
+        rt.set_scheduler(SchedulerPolicy::round_robin(100));
         let rt = run(rt)?;
 
         let final_count: i64 = rt
@@ -483,7 +769,7 @@ mod tests {
             .expect("Count not in environment")
             .try_into()?;
 
-        assert!(final_count > 0);
+        assert_eq!(final_count, 19);
         Ok(())
     }
 
@@ -620,10 +906,9 @@ mod tests {
             ByteCode::DONE, // Parent is done
         ];
 
+        // Preempt the threads after a few instructions, so that they race on count
         let mut rt = Runtime::new(instrs);
-
-        // Set the time quantum to a short time, so that race conditions are more likely to occur
-        rt.set_time_quantum(Duration::from_micros(1));
+        rt.set_scheduler(SchedulerPolicy::round_robin(3));
         let rt = run(rt)?;
 
         let final_count: i64 = rt
@@ -637,7 +922,7 @@ mod tests {
             .try_into()?;
 
         println!("Final count: {}", final_count);
-        assert!(final_count < 300); // The count should be less than 300 due to race conditions
+        assert_eq!(final_count, 200); // The same updates are lost to the race on every run
 
         Ok(())
     }
@@ -833,6 +1118,346 @@ mod tests {
         );
         assert_eq!(final_count, 300); // The count should be exactly 300
 
+        // However the threads are interleaved
+        for seed in 0..5 {
+            let mut rt = Runtime::new(instrs.clone());
+            rt.set_scheduler(SchedulerPolicy::random(3, seed));
+            let rt = run(rt)?;
+            assert_eq!(
+                rt.current_thread.operand_stack.last(),
+                Some(&Value::Int(300))
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sleep() -> Result<()> {
+        // The threads sleep at the same time, so they wake in order of how long they sleep for
+        let instrs = compile_from_string(
+            r"
+            let order = [0, 0, 0];
+            let i = 0;
+            fn mark(id: int, ms: int) {
+                sleep(ms);
+                order[i] = id;
+                i = i + 1;
+            }
+            let t1 = spawn mark(1, 60);
+            let t2 = spawn mark(2, 20);
+            mark(3, 40);
+            join t1;
+            join t2;
+            order
+            ",
+            true,
+        )?;
+        let start = Instant::now();
+        let rt = run(Runtime::new(instrs))?;
+        assert!(start.elapsed() >= Duration::from_millis(60));
+        assert_eq!(
+            rt.current_thread.operand_stack.last(),
+            Some(&vec![Value::Int(2), Value::Int(3), Value::Int(1)].into())
+        );
+        assert!(rt.sleep_queue.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_arrays() -> Result<()> {
+        let t = r"
+        let xs: [int; 4] = [0; 4];
+        let ys = xs;
+        let i = 0;
+        loop i < 4 {
+            ys[i] = i * i;
+            i = i + 1;
+        }
+        xs[1] + xs[3]
+        ";
+        let rt = run(Runtime::new(compile_from_string(t, true)?))?;
+        assert_eq!(rt.current_thread.operand_stack, vec![Value::Int(10)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_arrays_out_of_bounds() -> Result<()> {
+        let t = r"
+        let xs: [int; 4] = [0; 4];
+        let i = 4;
+        xs[i]
+        ";
+        let instrs = compile_from_string(t, true)?;
+        let ldidx_pc = instrs
+            .iter()
+            .position(|instr| *instr == ByteCode::LDIDX)
+            .expect("Should have LDIDX");
+
+        let Err(err) = run(Runtime::new(instrs)) else {
+            panic!("Should be out of bounds");
+        };
+        match err.downcast_ref::<VmError>() {
+            Some(VmError::IndexOutOfBounds { index, len, pc }) => {
+                assert_eq!((*index, *len, *pc), (4, 4, ldidx_pc))
+            }
+            _ => panic!("Expected IndexOutOfBounds, got: {err}"),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_slices() -> Result<()> {
+        let t = r"
+        fn search(xs: [int], x: int) -> bool {
+            let n = slice_len(xs);
+            if n == 0 {
+                return false;
+            }
+            let mid = n / 2;
+            if xs[mid] == x {
+                return true;
+            }
+            if xs[mid] < x {
+                return search(xs[mid + 1..], x);
+            }
+            search(xs[..mid], x)
+        }
+        let xs = [1, 3, 5, 7, 9, 11];
+        search(xs[..], 7) && search(xs[1..=4], 3) && !search(xs[..], 4) && !search(xs[2..], 1)
+        ";
+        let rt = run(Runtime::new(compile_from_string(t, true)?))?;
+        assert_eq!(rt.current_thread.operand_stack, vec![Value::Bool(true)]);
+
+        // writes through a slice are seen by the array
+        let t = r"
+        let xs = [3, 1, 2];
+        let s = xs[1..];
+        s[0] = 9;
+        xs[1] + slice_len(s)
+        ";
+        let rt = run(Runtime::new(compile_from_string(t, true)?))?;
+        assert_eq!(rt.current_thread.operand_stack, vec![Value::Int(11)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_stack_limits() -> Result<()> {
+        let t = r"
+        fn count(n: int) -> int {
+            1 + count(n + 1)
+        }
+        count(0)
+        ";
+        let mut rt = Runtime::new(compile_from_string(t, true)?);
+        rt.set_max_call_depth(100);
+        let Err(err) = run(rt) else {
+            panic!("should overflow")
+        };
+        assert!(matches!(
+            err.downcast_ref::<VmError>(),
+            Some(VmError::StackOverflow {
+                stack: Stack::Runtime,
+                limit: 100,
+                thread_id: MAIN_THREAD_ID,
+                ..
+            })
+        ));
+
+        // recursion within the limit runs
+        let t = r"
+        fn sum(n: int) -> int {
+            if n == 0 { 0 } else { n + sum(n - 1) }
+        }
+        sum(30)
+        ";
+        let mut rt = Runtime::new(compile_from_string(t, true)?);
+        rt.set_max_call_depth(100);
+        let rt = run(rt)?;
+        assert_eq!(rt.current_thread.operand_stack, vec![Value::Int(465)]);
+
+        let mut rt = Runtime::new(vec![
+            ByteCode::ldc(1),
+            ByteCode::ldc(2),
+            ByteCode::ldc(3),
+            ByteCode::DONE,
+        ]);
+        rt.set_max_operand_stack(2);
+        let Err(err) = run(rt) else {
+            panic!("should overflow")
+        };
+        assert!(matches!(
+            err.downcast_ref::<VmError>(),
+            Some(VmError::StackOverflow {
+                stack: Stack::Operand,
+                limit: 2,
+                pc: 2,
+                ..
+            })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_backtrace() -> Result<()> {
+        let t = r"
+        fn f(x: int) -> int {
+            10 / x
+        }
+        let g = |x: int| -> int { f(x - 1) + 1 };
+        g(1)
+        ";
+        let Err(err) = run(Runtime::new(compile_from_string(t, true)?)) else {
+            panic!("should divide by zero")
+        };
+        assert!(matches!(
+            err.downcast_ref::<VmError>(),
+            Some(VmError::DivisionByZero { .. })
+        ));
+
+        // in f, called from g, called from the top level
+        let backtrace = crate::error_backtrace(&err).expect("should have a backtrace");
+        assert_eq!(backtrace.thread_id, MAIN_THREAD_ID);
+        assert_eq!(backtrace.frames.len(), 3);
+        assert!(backtrace.frames[0].addr.is_some());
+        assert!(backtrace.frames[1].addr.is_some());
+        assert_eq!(backtrace.frames[2].addr, None);
+
+        // a callback shows the calls inside the builtin that called it
+        let t = r"
+        fn f(x: int) -> int {
+            10 / x
+        }
+        map([1, 0], f)
+        ";
+        let Err(err) = run(Runtime::new(compile_from_string(t, true)?)) else {
+            panic!("should divide by zero")
+        };
+        let backtrace = crate::error_backtrace(&err).expect("should have a backtrace");
+        assert_eq!(backtrace.frames.len(), 2);
+
+        Ok(())
+    }
+
+    // The most environments a program has at once, run with no garbage collection
+    fn peak_heap(t: &str, eager_reset: bool) -> Result<(usize, Runtime)> {
+        let mut rt = Runtime::new(compile_from_string(t, true)?);
+        rt.set_gc_interval(Duration::MAX);
+        rt.set_gc_threshold(None);
+        if eager_reset {
+            rt.set_eager_reset();
+        }
+
+        let mut peak = rt.heap_size();
+        while !rt.is_done() {
+            rt = rt.step()?;
+            peak = peak.max(rt.heap_size());
+        }
+        Ok((peak, rt))
+    }
+
+    #[test]
+    fn test_eager_reset() -> Result<()> {
+        // a recursion 200 deep, 50 times over
+        let t = r"
+        fn sum(n: int) -> int {
+            if n == 0 { 0 } else { n + sum(n - 1) }
+        }
+        let total = 0;
+        for i in 0..50 {
+            total = total + sum(200);
+        }
+        total
+        ";
+        let (lazy, rt) = peak_heap(t, false)?;
+        assert_eq!(rt.current_thread.operand_stack, vec![Value::Int(1_005_000)]);
+        assert!(
+            lazy > 10_000,
+            "every frame is kept until collected, got {}",
+            lazy
+        );
+
+        let (eager, rt) = peak_heap(t, true)?;
+        assert_eq!(rt.current_thread.operand_stack, vec![Value::Int(1_005_000)]);
+        assert!(
+            eager < 500,
+            "only the frames of one recursion are live, got {}",
+            eager
+        );
+
+        // frames a closure was made in are kept for it
+        let t = r"
+        fn adder(x: int) -> fn(int) -> int {
+            let y = x * 2;
+            |z: int| -> int { y + z }
+        }
+        let add = adder(3);
+        add(1)
+        ";
+        let (_, rt) = peak_heap(t, true)?;
+        assert_eq!(rt.current_thread.operand_stack, vec![Value::Int(7)]);
+
+        // and so are the frames a thread is spawned in
+        let t = r"
+        fn show(n: int) {
+            println(n);
+        }
+        fn start(x: int) {
+            let y = x + 1;
+            spawn show(y);
+        }
+        start(5);
+        yield;
+        ";
+        let (_, rt) = peak_heap(t, true)?;
+        assert!(rt.current_thread.operand_stack.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_run_position_independent() -> Result<()> {
+        let t = r"
+        fn fac(n: int) -> int {
+            let acc = 1;
+            let i = 1;
+            loop i < n + 1 {
+                acc = acc * i;
+                i = i + 1;
+            }
+            let twice = |x: int| x * 2;
+            if acc > 100 { twice(acc) } else { acc }
+        }
+        fac(5)
+        ";
+        let program = parser::Parser::new_from_string(t).parse()?;
+        let instrs = compiler::compiler::Compiler::new(program)
+            .compile_position_independent()
+            .map_err(|err| anyhow::Error::msg(err.to_string()))?;
+        let rt = run(Runtime::new(instrs.clone()))?;
+        assert_eq!(rt.current_thread.operand_stack, vec![Value::Int(240)]);
+
+        // move the body of fac to the end of the program, rewriting only the LDF and GOTO that load it
+        let (ByteCode::LDF(start, prms), ByteCode::GOTO(end)) = (&instrs[1], &instrs[2]) else {
+            panic!("fac is loaded by LDF then GOTO");
+        };
+        let body = instrs[*start..*end].to_vec();
+        let mut moved = vec![instrs[0].clone()];
+        moved.extend(instrs[*end..].iter().cloned());
+        let new_start = moved.len() + 2;
+        moved.splice(
+            1..1,
+            [ByteCode::LDF(new_start, prms.clone()), ByteCode::GOTO(3)],
+        );
+        moved.extend(body);
+
+        let rt = run(Runtime::new(moved))?;
+        assert_eq!(rt.current_thread.operand_stack, vec![Value::Int(240)]);
+
         Ok(())
     }
 }
@@ -0,0 +1,458 @@
+use std::rc::Weak;
+
+use anyhow::Result;
+use bytecode::{
+    builtin::{self, CommandOutput, HttpResponse},
+    ByteCode, FnType, Socket, SocketKind, ThreadID, Value, Variant,
+};
+use tokio::{
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    task,
+};
+
+use crate::{execute_profiled, micro_code, BlockedOn, Runtime, Thread, VmError};
+
+// Stands in the ready queue while builtins are running, so a thread that blocks always has one to switch to.
+// Thread ids start at 1, so it is never one of the program
+const IDLE_THREAD_ID: ThreadID = 0;
+
+/// Run the program until it is done, like [`run`](crate::run), but with the builtins that block on IO, like
+/// `read_line`, and `sleep` run as tokio tasks. A thread that calls one is moved to the blocked queue until it returns, and
+/// the other threads run in the meantime. When every thread is waiting on one, the runner awaits them rather than
+/// blocking the thread it runs on, so it can be embedded in an async server without stalling the executor.
+///
+/// The runtime is not `Send`, so the future must be polled on the thread it was made on: in a current thread
+/// tokio runtime, or with [`tokio::task::spawn_local`] in a [`tokio::task::LocalSet`]. The builtins run on the
+/// blocking pool of the tokio runtime.
+///
+/// # Arguments
+///
+/// * `rt` - The runtime to run.
+///
+/// # Returns
+///
+/// The runtime after the program has finished executing.
+///
+/// # Errors
+///
+/// If an error occurs during execution, or in a builtin.
+pub async fn run_async(rt: Runtime) -> Result<Runtime> {
+    Io::new().run(rt).await
+}
+
+// What a blocking builtin returns. Values can't leave the thread of the runtime, so the value is made on resume
+enum Output {
+    Unit,
+    String(String),
+    Socket(SocketKind),
+    Http(HttpResponse),
+    Command(CommandOutput),
+    // What a builtin that does IO gives the program, see builtin::io_result
+    Result(Result<Box<Output>, String>),
+}
+
+impl From<Output> for Value {
+    fn from(output: Output) -> Self {
+        match output {
+            Output::Unit => Value::Unit,
+            Output::String(s) => Value::String(s.into()),
+            Output::Socket(kind) => Value::Socket(Socket::new(kind)),
+            Output::Http(res) => res.into(),
+            Output::Command(output) => output.into(),
+            Output::Result(res) => {
+                Variant::from_result(res.map(|output| Value::from(*output))).into()
+            }
+        }
+    }
+}
+
+type Task = Box<dyn FnOnce() -> Result<Output> + Send>;
+
+// The output of a builtin that does IO, with the errors the program handles made into an Err
+fn io_output(res: Result<Output>) -> Result<Output> {
+    let res = builtin::io_result(res)?;
+    Ok(Output::Result(res.map(Box::new)))
+}
+
+// The task for a call to a builtin that blocks on IO, or None for any other call
+fn blocking_builtin(rt: &Runtime, arity: usize) -> Option<Task> {
+    let stack = &rt.current_thread.operand_stack;
+    let closure = stack.get(stack.len().checked_sub(arity + 1)?)?;
+    let Value::Closure {
+        fn_type: FnType::Builtin,
+        sym,
+        prms,
+        ..
+    } = closure
+    else {
+        return None;
+    };
+
+    // A call with the wrong arity is left to CALL to report
+    if prms.len() != arity {
+        return None;
+    }
+
+    // The net builtins and run_command are left to CALL to refuse if they aren't allowed
+    if builtin::NET_SYMS.contains(&sym.as_str()) && !rt.allow_net {
+        return None;
+    }
+    if sym == builtin::RUN_COMMAND_SYM && !rt.allow_run {
+        return None;
+    }
+
+    let args = &stack[stack.len() - arity..];
+    match sym.as_str() {
+        builtin::READ_LINE_SYM => Some(Box::new(|| builtin::read_line_impl().map(Output::String))),
+        builtin::SLEEP_SYM => {
+            let duration = builtin::sleep_duration(args.first()?).ok()?;
+            Some(Box::new(move || {
+                builtin::sleep_impl(duration);
+                Ok(Output::Unit)
+            }))
+        }
+        builtin::TCP_CONNECT_SYM => {
+            let addr: String = args.first()?.clone().try_into().ok()?;
+            Some(Box::new(move || {
+                io_output(builtin::tcp_connect_impl(&addr).map(Output::Socket))
+            }))
+        }
+        // The task blocks on its own handle to the socket. A closed socket is left to CALL to report
+        builtin::TCP_ACCEPT_SYM => {
+            let kind = socket_kind(args)?;
+            Some(Box::new(move || {
+                io_output(builtin::tcp_accept_impl(&kind).map(Output::Socket))
+            }))
+        }
+        builtin::TCP_RECV_SYM => {
+            let kind = socket_kind(args)?;
+            Some(Box::new(move || {
+                io_output(builtin::tcp_recv_impl(&kind).map(Output::String))
+            }))
+        }
+        builtin::UDP_RECV_SYM => {
+            let kind = socket_kind(args)?;
+            Some(Box::new(move || {
+                io_output(builtin::udp_recv_impl(&kind).map(Output::String))
+            }))
+        }
+        builtin::HTTP_GET_SYM => {
+            let url: String = args.first()?.clone().try_into().ok()?;
+            Some(Box::new(move || {
+                io_output(builtin::http_get_impl(&url).map(Output::Http))
+            }))
+        }
+        builtin::HTTP_POST_SYM => {
+            let url: String = args.first()?.clone().try_into().ok()?;
+            let body: String = args.get(1)?.clone().try_into().ok()?;
+            Some(Box::new(move || {
+                io_output(builtin::http_post_impl(&url, &body).map(Output::Http))
+            }))
+        }
+        builtin::RUN_COMMAND_SYM => {
+            let cmd: String = args.first()?.clone().try_into().ok()?;
+            let cmd_args = builtin::command_args(args.get(1)?).ok()?;
+            let timeout = builtin::command_timeout(args.get(2)?).ok()?;
+            Some(Box::new(move || {
+                io_output(builtin::run_command_impl(&cmd, &cmd_args, timeout).map(Output::Command))
+            }))
+        }
+        _ => None,
+    }
+}
+
+fn socket_kind(args: &[Value]) -> Option<SocketKind> {
+    let sock: Socket = args.first()?.clone().try_into().ok()?;
+    sock.kind().ok()
+}
+
+// The builtins that are running, each sending the thread that called it with the result when it is done
+struct Io {
+    tx: UnboundedSender<(ThreadID, Result<Output>)>,
+    rx: UnboundedReceiver<(ThreadID, Result<Output>)>,
+    pending: usize,
+}
+
+impl Io {
+    fn new() -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        Io { tx, rx, pending: 0 }
+    }
+
+    async fn run(&mut self, mut rt: Runtime) -> Result<Runtime> {
+        loop {
+            if rt.is_done() {
+                break;
+            }
+
+            while let Ok(done) = self.rx.try_recv() {
+                rt = self.resume(rt, done)?;
+            }
+
+            if rt.current_thread.thread_id == IDLE_THREAD_ID {
+                rt = self.idle(rt).await?;
+                continue;
+            }
+
+            rt = rt.gc_step()?;
+
+            if rt.time_quantum_expired() {
+                rt = micro_code::yield_(rt)?;
+                task::yield_now().await;
+                continue;
+            }
+
+            if rt.debug {
+                rt.debug_print()?;
+            }
+
+            let instr = rt.fetch_instr()?;
+            rt.trace_instr(&instr)?;
+
+            if let ByteCode::CALL(arity) = instr {
+                if let Some(task) = blocking_builtin(&rt, arity) {
+                    // Pop the closure and args as CALL would, the task has taken what it needs from them
+                    let len = rt.current_thread.operand_stack.len();
+                    rt.current_thread.operand_stack.truncate(len - arity - 1);
+                    rt = self.block(rt, task)?;
+                    continue;
+                }
+            }
+
+            rt = execute_profiled(rt, instr)?;
+        }
+
+        Ok(rt)
+    }
+
+    // Start the task on the blocking pool, and move the current thread to the blocked queue until it is done
+    fn block(&mut self, mut rt: Runtime, task: Task) -> Result<Runtime> {
+        let tid = rt.current_thread.thread_id;
+        let tx = self.tx.clone();
+        task::spawn_blocking(move || {
+            // The runner is gone if the program finished first, so there's no one to tell
+            let _ = tx.send((tid, task()));
+        });
+        self.pending += 1;
+
+        let has_idle = rt
+            .ready_queue
+            .iter()
+            .any(|thread| thread.thread_id == IDLE_THREAD_ID);
+        if !has_idle {
+            rt.ready_queue
+                .push_back(Thread::new(IDLE_THREAD_ID, Weak::new()));
+        }
+
+        let next_ready_thread = rt.next_ready_thread()?;
+        let current_thread = std::mem::replace(&mut rt.current_thread, next_ready_thread);
+        rt.blocked_queue.push_back((current_thread, BlockedOn::Io));
+        Ok(rt)
+    }
+
+    // Push the result onto the thread that called the builtin and make it ready
+    fn resume(
+        &mut self,
+        mut rt: Runtime,
+        (tid, result): (ThreadID, Result<Output>),
+    ) -> Result<Runtime> {
+        self.pending -= 1;
+
+        let (mut thread, _) = rt
+            .blocked_queue
+            .iter()
+            .position(|(thread, blocked_on)| {
+                thread.thread_id == tid && matches!(blocked_on, BlockedOn::Io)
+            })
+            .and_then(|pos| rt.blocked_queue.remove(pos))
+            .ok_or(VmError::NoSuchThread(tid))?;
+
+        thread.operand_stack.push(result?.into());
+        rt.ready_queue.push_back(thread);
+        Ok(rt)
+    }
+
+    // The idle thread waits for a builtin when no thread is ready, and leaves once none are running
+    async fn idle(&mut self, mut rt: Runtime) -> Result<Runtime> {
+        if self.pending > 0 && rt.ready_queue.is_empty() {
+            let done = self
+                .rx
+                .recv()
+                .await
+                .expect("runner holds a sender, so the channel is open");
+            rt = self.resume(rt, done)?;
+        }
+
+        if self.pending > 0 {
+            return micro_code::yield_(rt);
+        }
+
+        rt.current_thread = rt.next_ready_thread()?;
+        Ok(rt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{thread, time::Duration};
+
+    use compiler::compiler::compile_from_string;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_run_async() -> Result<()> {
+        // Without builtins that block it runs the same as run
+        let instrs = compile_from_string(
+            r"
+            fn f(x: int) -> int { x * 2 }
+            let t = spawn f(21);
+            join t
+            ",
+            false,
+        )?;
+        let rt = run_async(Runtime::new(instrs)).await?;
+        assert_eq!(rt.current_thread.operand_stack, vec![Value::Int(42)]);
+
+        assert!(run_async(Runtime::new(vec![ByteCode::POP])).await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_run_async_sleep() -> Result<()> {
+        // sleep runs on the blocking pool, and the threads wake in order of how long they sleep for
+        let instrs = compile_from_string(
+            r"
+            let order = [0, 0, 0];
+            let i = 0;
+            fn mark(id: int, ms: int) {
+                sleep(ms);
+                order[i] = id;
+                i = i + 1;
+            }
+            let t1 = spawn mark(1, 60);
+            let t2 = spawn mark(2, 20);
+            mark(3, 40);
+            join t1;
+            join t2;
+            order
+            ",
+            true,
+        )?;
+        let rt = run_async(Runtime::new(instrs)).await?;
+        assert_eq!(
+            rt.current_thread.operand_stack.last(),
+            Some(&vec![Value::Int(2), Value::Int(3), Value::Int(1)].into())
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_run_async_blocked() -> Result<()> {
+        // The main thread is done once the builtin returns, and the second thread spins until then
+        let instrs = vec![
+            ByteCode::DONE,
+            ByteCode::ldc(1),
+            ByteCode::POP,
+            ByteCode::GOTO(1),
+        ];
+        let mut rt = Runtime::new(instrs);
+        rt.set_time_quantum(Duration::from_millis(1));
+        rt.set_profile_opcodes();
+        rt.ready_queue
+            .push_back(rt.current_thread.spawn_child(2, 1));
+
+        let mut io = Io::new();
+        let rt = io.block(
+            rt,
+            Box::new(|| {
+                thread::sleep(Duration::from_millis(20));
+                Ok(Output::String("line".into()))
+            }),
+        )?;
+        assert_eq!(rt.current_thread.thread_id, 2);
+
+        let rt = io.run(rt).await?;
+        assert_eq!(
+            rt.current_thread.operand_stack,
+            vec![Value::String("line".into())]
+        );
+        assert!(rt.blocked_queue.is_empty());
+
+        // the second thread ran while the main thread waited
+        let profile = rt.profile.as_ref().expect("Profiling was turned on");
+        assert!(profile.get("GOTO").is_some_and(|s| s.count > 0));
+
+        // With only the main thread, the runner waits for the builtin
+        let mut rt = Runtime::new(vec![ByteCode::DONE]);
+        rt = io.block(rt, Box::new(|| Ok(Output::String("line".into()))))?;
+        assert_eq!(rt.current_thread.thread_id, IDLE_THREAD_ID);
+        let rt = io.run(rt).await?;
+        assert_eq!(rt.current_thread.thread_id, 1);
+        assert_eq!(io.pending, 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_run_async_net() -> Result<()> {
+        use std::{
+            io::{Read, Write},
+            net::TcpListener,
+        };
+
+        // Answer after a while, so the program waits in recv
+        let server = TcpListener::bind("127.0.0.1:0")?;
+        let addr = server.local_addr()?;
+        let echo = thread::spawn(move || -> std::io::Result<()> {
+            let (mut stream, _) = server.accept()?;
+            let mut buf = [0; 64];
+            let n = stream.read(&mut buf)?;
+            thread::sleep(Duration::from_millis(20));
+            stream.write_all(&buf[..n])
+        });
+
+        let instrs = compile_from_string(
+            &format!(
+                r#"
+                match tcp_connect("{addr}") {{
+                    Ok(conn) => {{
+                        tcp_send(conn, "echo");
+                        let reply = tcp_recv(conn);
+                        close(conn);
+                        match reply {{ Ok(reply) => reply, Err(e) => e }}
+                    }}
+                    Err(e) => e,
+                }}
+                "#
+            ),
+            true,
+        )?;
+
+        // Without the network allowed the call fails as in run
+        assert!(run_async(Runtime::new(instrs.clone())).await.is_err());
+
+        let mut rt = Runtime::new(instrs);
+        rt.set_allow_net();
+        let rt = run_async(rt).await?;
+        assert_eq!(
+            rt.current_thread.operand_stack,
+            vec![Value::String("echo".into())]
+        );
+        echo.join().unwrap()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_blocking_builtins() -> Result<()> {
+        let rt = Runtime::new(vec![]);
+        let mut rt = micro_code::ld(rt, builtin::READ_LINE_SYM.into())?;
+        assert!(blocking_builtin(&rt, 0).is_some());
+        assert!(blocking_builtin(&rt, 1).is_none());
+
+        rt.current_thread.operand_stack.clear();
+        let rt = micro_code::ld(rt, builtin::PRINTLN_SYM.into())?;
+        let rt = micro_code::ldc(rt, Value::Int(1))?;
+        assert!(blocking_builtin(&rt, 1).is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,206 @@
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{Runtime, Thread, VmError};
+
+/// When the runtime preempts the current thread, and which ready thread runs next.
+#[derive(Debug, Clone, Default)]
+pub enum SchedulerPolicy {
+    /// Preempt a thread once it has run for the time quantum of the runtime, and run the threads in the order they
+    /// became ready. How far a thread gets depends on how fast the machine is, so runs can interleave differently.
+    #[default]
+    Timed,
+    /// Preempt a thread once it has executed `quantum` instructions, and run the threads in the order they became
+    /// ready. Every run interleaves the same way.
+    RoundRobin { quantum: usize },
+    /// Preempt a thread once it has executed `quantum` instructions, and run a ready thread picked by a seeded rng.
+    /// Runs with the same seed interleave the same way, so different seeds can be tried to shake out races.
+    Random { quantum: usize, rng: Box<StdRng> },
+}
+
+impl SchedulerPolicy {
+    pub fn round_robin(quantum: usize) -> Self {
+        SchedulerPolicy::RoundRobin { quantum }
+    }
+
+    pub fn random(quantum: usize, seed: u64) -> Self {
+        SchedulerPolicy::Random {
+            quantum,
+            rng: Box::new(StdRng::seed_from_u64(seed)),
+        }
+    }
+}
+
+impl Runtime {
+    /// Check if the current thread has used up its quantum, in time or in instructions depending on the scheduler
+    /// policy, and should be preempted.
+    #[inline]
+    pub fn time_quantum_expired(&self) -> bool {
+        match self.scheduler {
+            SchedulerPolicy::Timed => self.time.elapsed() >= self.time_quantum,
+            SchedulerPolicy::RoundRobin { quantum } | SchedulerPolicy::Random { quantum, .. } => {
+                self.quantum_instrs >= quantum
+            }
+        }
+    }
+
+    /// Start a new quantum for the current thread.
+    #[inline]
+    pub fn reset_quantum(&mut self) {
+        self.time = Instant::now();
+        self.quantum_instrs = 0;
+    }
+
+    /// Take the thread to switch to from the ready queue, as picked by the scheduler policy, and start its quantum.
+    /// If no thread is ready but some are sleeping, wait for the first of them to wake.
+    ///
+    /// # Errors
+    ///
+    /// If there are no threads in the ready queue, and none sleeping.
+    #[inline]
+    pub fn next_ready_thread(&mut self) -> Result<Thread> {
+        self.wake_sleepers();
+        if self.ready_queue.is_empty() {
+            if let Some(&(_, wake_at)) = self.sleep_queue.front() {
+                std::thread::sleep(wake_at.saturating_duration_since(Instant::now()));
+                self.wake_sleepers();
+            }
+        }
+
+        let idx = match &mut self.scheduler {
+            SchedulerPolicy::Random { rng, .. } if !self.ready_queue.is_empty() => {
+                rng.gen_range(0..self.ready_queue.len())
+            }
+            _ => 0,
+        };
+
+        let thread = self
+            .ready_queue
+            .remove(idx)
+            .ok_or(VmError::NoThreadsInReadyQueue)?;
+        self.reset_quantum();
+        Ok(thread)
+    }
+
+    /// Put the current thread to sleep for the duration and switch to the next ready thread. The other threads
+    /// run in the meantime, and the thread is made ready again once the duration has passed.
+    ///
+    /// # Errors
+    ///
+    /// If there are no threads to switch to, see [`Runtime::next_ready_thread`].
+    pub fn sleep_current_thread(&mut self, duration: Duration) -> Result<()> {
+        let wake_at = Instant::now() + duration;
+        let thread = std::mem::take(&mut self.current_thread);
+
+        // Keep the queue in order of when the threads wake, after any that wake at the same time
+        let idx = self
+            .sleep_queue
+            .partition_point(|&(_, other)| other <= wake_at);
+        self.sleep_queue.insert(idx, (thread, wake_at));
+
+        self.current_thread = self.next_ready_thread()?;
+        Ok(())
+    }
+
+    /// Move the sleeping threads whose time has come to the ready queue, in the order they wake.
+    #[inline]
+    pub fn wake_sleepers(&mut self) {
+        let now = Instant::now();
+        while let Some(&(_, wake_at)) = self.sleep_queue.front() {
+            if wake_at > now {
+                break;
+            }
+
+            let (thread, _) = self
+                .sleep_queue
+                .pop_front()
+                .expect("front of the sleep queue was just checked");
+            self.ready_queue.push_back(thread);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytecode::ThreadID;
+
+    use crate::{micro_code, MAIN_THREAD_ID};
+
+    use super::*;
+
+    // The ids of the threads in the order yield_ switches to them
+    fn yield_order(policy: SchedulerPolicy) -> Result<Vec<ThreadID>> {
+        let mut rt = Runtime::default();
+        rt.set_scheduler(policy);
+        rt = micro_code::spawn(rt, 0)?;
+        rt = micro_code::spawn(rt, 0)?;
+
+        let mut order = vec![];
+        for _ in 0..12 {
+            rt = micro_code::yield_(rt)?;
+            order.push(rt.current_thread.thread_id);
+        }
+        Ok(order)
+    }
+
+    #[test]
+    fn test_quantum() -> Result<()> {
+        let mut rt = Runtime::new(vec![bytecode::ByteCode::ldc(1); 3]);
+        rt.set_scheduler(SchedulerPolicy::round_robin(2));
+
+        rt.fetch_instr()?;
+        assert!(!rt.time_quantum_expired());
+        rt.fetch_instr()?;
+        assert!(rt.time_quantum_expired());
+
+        rt.reset_quantum();
+        assert!(!rt.time_quantum_expired());
+        Ok(())
+    }
+
+    #[test]
+    fn test_sleep_current_thread() -> Result<()> {
+        let mut rt = Runtime::default();
+        rt = micro_code::spawn(rt, 0)?;
+        rt = micro_code::spawn(rt, 0)?;
+        let (first, second) = (MAIN_THREAD_ID + 1, MAIN_THREAD_ID + 2);
+
+        // The main thread sleeps while the others run
+        rt.sleep_current_thread(Duration::from_millis(20))?;
+        assert_eq!(rt.current_thread.thread_id, first);
+        rt.sleep_current_thread(Duration::from_millis(5))?;
+        assert_eq!(rt.current_thread.thread_id, second);
+        assert_eq!(rt.sleep_queue[0].0.thread_id, first);
+
+        // With no thread ready, the next to wake is waited for
+        rt.sleep_current_thread(Duration::from_millis(50))?;
+        assert_eq!(rt.current_thread.thread_id, first);
+
+        // A yield wakes the threads whose time has come
+        std::thread::sleep(Duration::from_millis(20));
+        rt = micro_code::yield_(rt)?;
+        assert_eq!(rt.current_thread.thread_id, MAIN_THREAD_ID);
+        assert_eq!(rt.sleep_queue.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_next_ready_thread() -> Result<()> {
+        let main = MAIN_THREAD_ID;
+        assert_eq!(
+            yield_order(SchedulerPolicy::round_robin(10))?,
+            [main + 1, main + 2, main].repeat(4)
+        );
+
+        // The same seed gives the same order, which isn't always round robin
+        let order = yield_order(SchedulerPolicy::random(10, 42))?;
+        assert_eq!(order, yield_order(SchedulerPolicy::random(10, 42))?);
+        assert_ne!(order, yield_order(SchedulerPolicy::round_robin(10))?);
+
+        let mut rt = Runtime::default();
+        assert!(rt.next_ready_thread().is_err());
+        Ok(())
+    }
+}
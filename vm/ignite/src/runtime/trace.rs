@@ -0,0 +1,126 @@
+use std::{
+    cell::RefCell,
+    fmt::Display,
+    io::{self, Write},
+    rc::Rc,
+};
+
+use anyhow::Result;
+use bytecode::{fmt_instr, ByteCode, ThreadID};
+
+/// One executed instruction, recorded when tracing is turned on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceEvent {
+    pub thread_id: ThreadID,
+    /// Where the instruction is.
+    pub pc: usize,
+    pub instr: ByteCode,
+    /// The length of the operand stack of the thread before the instruction.
+    pub stack_depth: usize,
+    /// The number of frames on the runtime stack of the thread before the instruction.
+    pub frame_depth: usize,
+}
+
+impl Display for TraceEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "thread {} pc {} stack {} frames {}: {}",
+            self.thread_id,
+            self.pc,
+            self.stack_depth,
+            self.frame_depth,
+            fmt_instr(&self.instr)
+        )
+    }
+}
+
+/// Where the events of a trace go.
+pub trait TraceSink {
+    /// Record an instruction about to be executed.
+    ///
+    /// # Errors
+    ///
+    /// If the event can't be recorded, which stops the program.
+    fn record(&mut self, event: TraceEvent) -> Result<()>;
+}
+
+/// Writes each event on its own line, to stderr or a file.
+pub struct WriteSink<W: Write> {
+    out: W,
+}
+
+impl<W: Write> WriteSink<W> {
+    pub fn new(out: W) -> Self {
+        WriteSink { out }
+    }
+}
+
+impl WriteSink<io::Stderr> {
+    pub fn stderr() -> Self {
+        WriteSink::new(io::stderr())
+    }
+}
+
+impl<W: Write> TraceSink for WriteSink<W> {
+    fn record(&mut self, event: TraceEvent) -> Result<()> {
+        writeln!(self.out, "{}", event)?;
+        Ok(())
+    }
+}
+
+/// Keeps the events in memory. Clones share the events, so one can be kept to read them after the runtime that
+/// owns the other is done, like in tests.
+#[derive(Debug, Default, Clone)]
+pub struct MemorySink {
+    events: Rc<RefCell<Vec<TraceEvent>>>,
+}
+
+impl MemorySink {
+    pub fn new() -> Self {
+        MemorySink::default()
+    }
+
+    /// The events recorded so far, in the order they were executed.
+    pub fn events(&self) -> Vec<TraceEvent> {
+        self.events.borrow().clone()
+    }
+}
+
+impl TraceSink for MemorySink {
+    fn record(&mut self, event: TraceEvent) -> Result<()> {
+        self.events.borrow_mut().push(event);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sinks() -> Result<()> {
+        let event = TraceEvent {
+            thread_id: 2,
+            pc: 7,
+            instr: ByteCode::ldc(42),
+            stack_depth: 1,
+            frame_depth: 3,
+        };
+
+        let mut buf = vec![];
+        let mut sink = WriteSink::new(&mut buf);
+        sink.record(event.clone())?;
+        sink.record(event.clone())?;
+        assert_eq!(
+            String::from_utf8(buf)?,
+            "thread 2 pc 7 stack 1 frames 3: LDC 42\n".repeat(2)
+        );
+
+        let mem = MemorySink::new();
+        let mut sink = mem.clone();
+        sink.record(event.clone())?;
+        assert_eq!(mem.events(), vec![event]);
+        Ok(())
+    }
+}
@@ -0,0 +1,211 @@
+use std::{io::Write, ops::Range, str::FromStr};
+
+use anyhow::{Error, Result};
+use bytecode::{fmt_instr, ByteCode, Value};
+
+use ignite::Runtime;
+
+/// The instructions to show the operand stack for, given to `--show-stack`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StackRange {
+    /// The instructions from the start pc up to the end pc, or just the one at a pc.
+    Pcs(Range<usize>),
+    /// The body of the fn with the name.
+    Fn(String),
+}
+
+impl FromStr for StackRange {
+    type Err = String;
+
+    // 3..9, 3 or the name of a fn
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some((start, end)) = s.split_once("..") {
+            let pc = |pc: &str| {
+                pc.trim()
+                    .parse::<usize>()
+                    .map_err(|_| format!("Expected a range of pcs like 3..9 but got {}", s))
+            };
+            return Ok(StackRange::Pcs(pc(start)?..pc(end)?));
+        }
+
+        if let Ok(pc) = s.parse::<usize>() {
+            return Ok(StackRange::Pcs(pc..pc + 1));
+        }
+
+        Ok(StackRange::Fn(s.to_string()))
+    }
+}
+
+impl StackRange {
+    /// The pcs of the instructions to show. A fn declaration compiles to LDF, then a GOTO past the body, then the
+    /// body, then the ASSIGN of the closure to its name, so the body is from the address of the LDF to the ASSIGN.
+    ///
+    /// # Errors
+    ///
+    /// If the program has no fn with the name.
+    pub fn pcs(&self, instrs: &[ByteCode]) -> Result<Range<usize>> {
+        let name = match self {
+            StackRange::Pcs(pcs) => return Ok(pcs.clone()),
+            StackRange::Fn(name) => name,
+        };
+
+        instrs
+            .windows(2)
+            .enumerate()
+            .find_map(|(pc, pair)| match pair {
+                [ldf @ (ByteCode::LDF(..) | ByteCode::LDFR(..)), goto @ (ByteCode::GOTO(_) | ByteCode::GOTOR(_))] => {
+                    let (addr, end) = (ldf.target(pc)?, goto.target(pc + 1)?);
+                    match instrs.get(end) {
+                        Some(ByteCode::ASSIGN(sym)) if sym == name => Some(addr..end),
+                        _ => None,
+                    }
+                }
+                _ => None,
+            })
+            .ok_or_else(|| Error::msg(format!("No fn named {} in the program", name)))
+    }
+}
+
+/// How `--show-stack` shows the operand stack.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum StackFormat {
+    /// A table with a row for each instruction, to read.
+    Table,
+    /// A JSON object on its own line for each instruction, for a visualizer to read.
+    Json,
+}
+
+/// Runs the program, writing the operand stack of the thread before and after each instruction in a range of
+/// pcs, so how an expression like `2+3*4` is evaluated on the stack can be followed.
+pub struct StackView {
+    pcs: Range<usize>,
+    format: StackFormat,
+}
+
+impl StackView {
+    pub fn new(pcs: Range<usize>, format: StackFormat) -> Self {
+        StackView { pcs, format }
+    }
+
+    /// Run the program until it is done, writing a row to out for each instruction executed in the range.
+    pub fn run(&self, mut rt: Runtime, mut out: impl Write) -> Result<Runtime> {
+        if self.format == StackFormat::Table {
+            writeln!(
+                out,
+                "{:>6}  {:>4}  {:<20}  {:<24}  after",
+                "thread", "pc", "instruction", "before"
+            )?;
+        }
+
+        while !rt.is_done() {
+            let thread_id = rt.current_thread.thread_id;
+            let pc = rt.current_thread.pc;
+            if !self.pcs.contains(&pc) {
+                rt = rt.step()?;
+                continue;
+            }
+
+            let before = stack_values(&rt);
+            rt = rt.step()?;
+
+            // the instruction didn't run if the thread was switched out before it
+            let thread = &rt.current_thread;
+            if thread.thread_id != thread_id || thread.pc == pc {
+                continue;
+            }
+
+            let instr = rt.instrs.get(pc).map(fmt_instr).unwrap_or_default();
+            let after = stack_values(&rt);
+            match self.format {
+                StackFormat::Table => writeln!(
+                    out,
+                    "{:>6}  {:>4}  {:<20}  {:<24}  {}",
+                    thread_id,
+                    pc,
+                    instr,
+                    fmt_stack(&before),
+                    fmt_stack(&after)
+                )?,
+                StackFormat::Json => {
+                    let row = serde_json::json!({
+                        "thread": thread_id,
+                        "pc": pc,
+                        "instr": instr,
+                        "before": before,
+                        "after": after,
+                    });
+                    writeln!(out, "{}", row)?
+                }
+            }
+        }
+
+        Ok(rt)
+    }
+}
+
+// The operand stack of the current thread, bottom first, with each value as println shows it
+fn stack_values(rt: &Runtime) -> Vec<String> {
+    rt.current_thread
+        .operand_stack
+        .iter()
+        .map(Value::to_string)
+        .collect()
+}
+
+fn fmt_stack(values: &[String]) -> String {
+    format!("[{}]", values.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use compiler::compiler::compile_from_string;
+
+    use super::*;
+
+    fn view(src: &str, range: &str, format: StackFormat) -> Result<String> {
+        let instrs = compile_from_string(src, true)?;
+        let pcs = range
+            .parse::<StackRange>()
+            .map_err(Error::msg)?
+            .pcs(&instrs)?;
+        let mut out = vec![];
+        StackView::new(pcs, format).run(Runtime::new(instrs), &mut out)?;
+        Ok(String::from_utf8(out)?)
+    }
+
+    #[test]
+    fn test_stack_view() -> Result<()> {
+        assert_eq!(
+            view("2+3*4", "0..5", StackFormat::Table)?,
+            "thread    pc  instruction           before                    after
+     1     0  LDC 2                 []                        [2]
+     1     1  LDC 3                 [2]                       [2, 3]
+     1     2  LDC 4                 [2, 3]                    [2, 3, 4]
+     1     3  BINOP *               [2, 3, 4]                 [2, 12]
+     1     4  BINOP +               [2, 12]                   [14]
+"
+        );
+
+        assert_eq!(
+            view("2+3", "2", StackFormat::Json)?,
+            "{\"after\":[\"5\"],\"before\":[\"2\",\"3\"],\"instr\":\"BINOP +\",\"pc\":2,\"thread\":1}\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_stack_view_fn() -> Result<()> {
+        let src = "fn sq(x: int) -> int { x * x } sq(3)";
+        let out = view(src, "sq", StackFormat::Table)?;
+        assert!(out.contains("LD x                  [3]                       [3, 3]\n"));
+        assert!(out.contains("BINOP *               [3, 3]                    [9]\n"));
+        assert!(!out.contains("CALL"));
+
+        assert!(view(src, "cube", StackFormat::Table).is_err());
+        assert_eq!(
+            "3..x".parse::<StackRange>(),
+            Err("Expected a range of pcs like 3..9 but got 3..x".to_string())
+        );
+        Ok(())
+    }
+}
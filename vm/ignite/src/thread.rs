@@ -1,12 +1,27 @@
-use std::{cell::RefCell, rc::Weak};
+use std::{
+    cell::RefCell,
+    rc::{Rc, Weak},
+    time::Instant,
+};
 
 use anyhow::Result;
-use bytecode::{weak_clone, Environment, StackFrame, Symbol, ThreadID, Value, W};
+use bytecode::{weak_clone, CallInfo, Environment, StackFrame, Symbol, ThreadID, Value, W};
 
 use crate::{Runtime, VmError};
 
 /// A thread of execution.
 /// Each thread has its own environment, operand stack, runtime stack, and program counter.
+///
+/// Threads share memory through their environments. A spawned thread starts in a frame whose parent is the
+/// environment of the spawn, so the names it reaches are the same bindings its parent sees, not copies, and
+/// a write by either is seen by the other. The frames a thread enters after that, for its calls and blocks,
+/// are its own. A thread spawned with `spawn isolate` instead starts in a deep copy of the environment of the
+/// spawn, and shares only the channels and semaphores reachable from it.
+///
+/// Every instruction runs to completion before another thread gets to run: preemption only happens between
+/// instructions, and a builtin that calls back into the program can't be preempted. So a single read (LD) or
+/// write (ASSIGN) of a binding is never torn or lost, but anything that takes more than one instruction, like
+/// `x = x + 1`, can interleave with other threads and needs a lock, semaphore or channel.
 #[derive(Debug, Default, Clone)]
 pub struct Thread {
     pub thread_id: ThreadID,
@@ -14,6 +29,8 @@ pub struct Thread {
     pub operand_stack: Vec<Value>,
     pub runtime_stack: Vec<StackFrame>,
     pub pc: usize,
+    /// When the thread gives up on the join_timeout it is waiting in, if it is waiting in one.
+    pub join_deadline: Option<Instant>,
 }
 
 impl Thread {
@@ -27,6 +44,14 @@ impl Thread {
         }
     }
 
+    /// The innermost call the thread is in, None at its top level.
+    pub fn call(&self) -> Option<Rc<CallInfo>> {
+        self.runtime_stack
+            .iter()
+            .rev()
+            .find_map(|frame| frame.call.clone())
+    }
+
     /// Create a new thread with the same environment as the current thread.
     /// But operand stack and runtime stack are empty.
     pub fn spawn_child(&self, thread_id: i64, pc: usize) -> Self {
@@ -36,6 +61,7 @@ impl Thread {
             operand_stack: Vec::new(),
             runtime_stack: Vec::new(),
             pc,
+            join_deadline: None,
         }
     }
 }
@@ -61,7 +87,7 @@ where
     let new_env = Environment::new_wrapped();
     new_env.borrow_mut().set_parent(env);
 
-    for (sym, val) in syms.into_iter().zip(vals.into_iter()) {
+    for (sym, val) in syms.into_iter().zip(vals) {
         new_env.borrow_mut().set(sym, val);
     }
 
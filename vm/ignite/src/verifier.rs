@@ -0,0 +1,302 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+use bytecode::{ByteCode, FrameType};
+
+use crate::VmError;
+
+/// Scope depths beyond this are not explored further. Well formed programs stay far below it,
+/// it only bounds the work done on programs that keep entering scopes without leaving them.
+const MAX_VERIFY_DEPTH: usize = 1024;
+
+/// Statically verify the bytecode before it is executed.
+///
+/// Every reachable path is walked while tracking how many scopes have been entered since the
+/// start of the enclosing function (or thread), and whether the path is inside a function body.
+/// Entry points are the start of the program, the address of every LDF (function bodies start with no
+/// scopes of their own) and the address of every SPAWN and SPAWNISO (child threads start with an empty runtime stack,
+/// so they are not inside a function even when spawned from one). The relative forms of jumps, LDF and the spawns
+/// are followed to the address they resolve to from where they are.
+///
+/// # Arguments
+///
+/// * `instrs` - The instructions to verify.
+///
+/// # Errors
+///
+/// * `VmError::ScopeUnderflow` if an EXITSCOPE (or a block frame RESET) can be reached with no matching ENTERSCOPE.
+/// * `VmError::ResetOutsideFunction` if a call frame RESET can be reached outside of a function body.
+pub fn verify(instrs: &[ByteCode]) -> Result<()> {
+    let mut worklist: Vec<(usize, usize, bool)> = vec![(0, 0, false)];
+    for (pc, instr) in instrs.iter().enumerate() {
+        let Some(addr) = instr.target(pc) else {
+            continue;
+        };
+
+        match instr {
+            ByteCode::LDF(..) | ByteCode::LDFR(..) => worklist.push((addr, 0, true)),
+            ByteCode::SPAWN(_)
+            | ByteCode::SPAWNISO(_)
+            | ByteCode::SPAWNR(_)
+            | ByteCode::SPAWNISOR(_) => worklist.push((addr, 0, false)),
+            _ => (),
+        }
+    }
+
+    let mut visited: HashSet<(usize, usize, bool)> = HashSet::new();
+
+    while let Some((pc, depth, in_fn)) = worklist.pop() {
+        if depth > MAX_VERIFY_DEPTH || !visited.insert((pc, depth, in_fn)) {
+            continue;
+        }
+
+        // Falling off the end or jumping out of bounds is reported at runtime
+        let Some(instr) = instrs.get(pc) else {
+            continue;
+        };
+
+        match instr {
+            ByteCode::ENTERSCOPE(_) => worklist.push((pc + 1, depth + 1, in_fn)),
+            ByteCode::EXITSCOPE | ByteCode::RESET(FrameType::BlockFrame) => {
+                if depth == 0 {
+                    return Err(VmError::ScopeUnderflow { pc }.into());
+                }
+                worklist.push((pc + 1, depth - 1, in_fn));
+            }
+            // Returning ends the path, the caller continues after its CALL
+            ByteCode::RESET(FrameType::CallFrame) => {
+                if !in_fn {
+                    return Err(VmError::ResetOutsideFunction { pc }.into());
+                }
+            }
+            ByteCode::GOTO(_) | ByteCode::GOTOR(_) => {
+                if let Some(addr) = instr.target(pc) {
+                    worklist.push((addr, depth, in_fn));
+                }
+            }
+            ByteCode::JOF(_) | ByteCode::JOFR(_) => {
+                if let Some(addr) = instr.target(pc) {
+                    worklist.push((addr, depth, in_fn));
+                }
+                worklist.push((pc + 1, depth, in_fn));
+            }
+            // The rest of the thread is unreachable from here
+            ByteCode::DONE => (),
+            ByteCode::ASSIGN(_)
+            | ByteCode::LD(_)
+            | ByteCode::LDC(_)
+            | ByteCode::POP
+            | ByteCode::BINOP(_)
+            | ByteCode::UNOP(_)
+            | ByteCode::LDF(..)
+            | ByteCode::CALL(_)
+            | ByteCode::LDFR(..)
+            | ByteCode::SPAWN(_)
+            | ByteCode::SPAWNISO(_)
+            | ByteCode::SPAWNR(_)
+            | ByteCode::SPAWNISOR(_)
+            | ByteCode::JOIN
+            | ByteCode::TRYJOIN
+            | ByteCode::YIELD
+            | ByteCode::SEMCREATE
+            | ByteCode::WAIT
+            | ByteCode::POST
+            | ByteCode::ARRAY(_)
+            | ByteCode::ARRAYFILL(_)
+            | ByteCode::LDIDX
+            | ByteCode::ASSIGNIDX
+            | ByteCode::SLICE
+            | ByteCode::LEN
+            | ByteCode::STRUCT(..)
+            | ByteCode::LDFIELD(_)
+            | ByteCode::ASSIGNFIELD(_)
+            | ByteCode::SEND
+            | ByteCode::RECV
+            | ByteCode::VARIANT(..)
+            | ByteCode::ISVARIANT(_)
+            | ByteCode::LDVARIANT(_)
+            | ByteCode::TUPLE(_)
+            | ByteCode::UNPACK(_)
+            | ByteCode::LDTUPLE(_)
+            | ByteCode::MAP(_) => worklist.push((pc + 1, depth, in_fn)),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use compiler::compiler::compile_from_string;
+
+    fn expect_scope_underflow(instrs: &[ByteCode], exp_pc: usize) {
+        let err = verify(instrs).expect_err("Should fail verification");
+        match err.downcast_ref::<VmError>() {
+            Some(VmError::ScopeUnderflow { pc }) => assert_eq!(*pc, exp_pc),
+            _ => panic!("Expected ScopeUnderflow, got: {err}"),
+        }
+    }
+
+    #[test]
+    fn test_verify_balanced() {
+        let instrs = vec![
+            ByteCode::enterscope(vec!["x"]),
+            ByteCode::enterscope(vec!["y"]),
+            ByteCode::EXITSCOPE,
+            ByteCode::EXITSCOPE,
+            ByteCode::DONE,
+        ];
+        verify(&instrs).expect("Should verify");
+    }
+
+    #[test]
+    fn test_verify_exit_without_enter() {
+        let instrs = vec![
+            ByteCode::enterscope(vec!["x"]),
+            ByteCode::EXITSCOPE,
+            ByteCode::EXITSCOPE,
+            ByteCode::DONE,
+        ];
+        expect_scope_underflow(&instrs, 2);
+    }
+
+    #[test]
+    fn test_verify_exit_on_one_branch() {
+        // Only the path that skips the ENTERSCOPE underflows
+        let instrs = vec![
+            ByteCode::ldc(true),
+            ByteCode::JOF(3),
+            ByteCode::enterscope(vec!["x"]),
+            ByteCode::EXITSCOPE,
+            ByteCode::DONE,
+        ];
+        expect_scope_underflow(&instrs, 3);
+    }
+
+    #[test]
+    fn test_verify_fn_body_has_own_scopes() {
+        // The caller's scope does not count for the callee
+        let instrs = vec![
+            ByteCode::enterscope(vec!["f"]),
+            ByteCode::ldf(3, Vec::<String>::new()),
+            ByteCode::GOTO(5),
+            ByteCode::EXITSCOPE,
+            ByteCode::reset(FrameType::CallFrame),
+            ByteCode::EXITSCOPE,
+            ByteCode::DONE,
+        ];
+        expect_scope_underflow(&instrs, 3);
+    }
+
+    #[test]
+    fn test_verify_spawned_thread_has_own_scopes() {
+        let instrs = vec![
+            ByteCode::enterscope(vec!["x"]),
+            ByteCode::SPAWN(4),
+            ByteCode::EXITSCOPE,
+            ByteCode::DONE,
+            ByteCode::EXITSCOPE,
+            ByteCode::DONE,
+        ];
+        expect_scope_underflow(&instrs, 4);
+    }
+
+    #[test]
+    fn test_verify_relative_forms() {
+        // As test_verify_exit_on_one_branch and test_verify_fn_body_has_own_scopes, with relative addresses
+        let instrs = vec![
+            ByteCode::ldc(true),
+            ByteCode::JOFR(2),
+            ByteCode::enterscope(vec!["x"]),
+            ByteCode::EXITSCOPE,
+            ByteCode::DONE,
+        ];
+        expect_scope_underflow(&instrs, 3);
+
+        let instrs = vec![
+            ByteCode::enterscope(vec!["f"]),
+            ByteCode::LDFR(2, vec![]),
+            ByteCode::GOTOR(3),
+            ByteCode::EXITSCOPE,
+            ByteCode::reset(FrameType::CallFrame),
+            ByteCode::EXITSCOPE,
+            ByteCode::DONE,
+        ];
+        expect_scope_underflow(&instrs, 3);
+
+        let instrs = vec![
+            ByteCode::ldf(3, Vec::<String>::new()),
+            ByteCode::CALL(0),
+            ByteCode::DONE,
+            ByteCode::SPAWNR(2),
+            ByteCode::reset(FrameType::CallFrame),
+            ByteCode::reset(FrameType::CallFrame),
+        ];
+        let err = verify(&instrs).expect_err("Should fail verification");
+        assert!(matches!(
+            err.downcast_ref::<VmError>(),
+            Some(VmError::ResetOutsideFunction { pc: 5 })
+        ));
+    }
+
+    #[test]
+    fn test_verify_reset_outside_fn() {
+        let instrs = vec![
+            ByteCode::enterscope(vec!["x"]),
+            ByteCode::reset(FrameType::CallFrame),
+            ByteCode::DONE,
+        ];
+        let err = verify(&instrs).expect_err("Should fail verification");
+        assert!(matches!(
+            err.downcast_ref::<VmError>(),
+            Some(VmError::ResetOutsideFunction { pc: 1 })
+        ));
+    }
+
+    #[test]
+    fn test_verify_reset_in_spawned_thread() {
+        // A thread spawned from inside a function body does not run inside that function
+        let instrs = vec![
+            ByteCode::ldf(3, Vec::<String>::new()),
+            ByteCode::CALL(0),
+            ByteCode::DONE,
+            ByteCode::SPAWN(5),
+            ByteCode::reset(FrameType::CallFrame),
+            ByteCode::reset(FrameType::CallFrame),
+        ];
+        let err = verify(&instrs).expect_err("Should fail verification");
+        assert!(matches!(
+            err.downcast_ref::<VmError>(),
+            Some(VmError::ResetOutsideFunction { pc: 5 })
+        ));
+    }
+
+    #[test]
+    fn test_verify_reset_block_frame() {
+        let instrs = vec![ByteCode::reset(FrameType::BlockFrame), ByteCode::DONE];
+        expect_scope_underflow(&instrs, 0);
+    }
+
+    #[test]
+    fn test_verify_unbounded_scopes_terminates() {
+        let instrs = vec![ByteCode::enterscope(vec!["x"]), ByteCode::GOTO(0)];
+        verify(&instrs).expect("Should verify");
+    }
+
+    #[test]
+    fn test_verify_compiled_programs() -> Result<()> {
+        let progs = [
+            "let x = 2; { let y = 3; x + y }",
+            "fn fac(n: int) -> int { if n == 0 { return 1; } n * fac(n - 1) } fac(5)",
+            "let x = 0; loop { let y = 1; if x > 3 { break; } x = x + y; } x",
+            "fn f() { let z = 1; } let t = spawn f(); join t;",
+        ];
+
+        for prog in progs {
+            verify(&compile_from_string(prog, true)?)?;
+        }
+
+        Ok(())
+    }
+}
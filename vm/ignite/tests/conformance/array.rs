@@ -0,0 +1,31 @@
+use bytecode::{ByteCode, Value};
+use ignite::VmError;
+
+use crate::{expect_vm_err, top_of};
+
+#[test]
+fn test_array_keeps_stack_order() {
+    let instrs = vec![
+        ByteCode::ldc(1),
+        ByteCode::ldc(2),
+        ByteCode::ldc(3),
+        ByteCode::ARRAY(3),
+        ByteCode::DONE,
+    ];
+    assert_eq!(
+        top_of(instrs),
+        vec![Value::Int(1), Value::Int(2), Value::Int(3)].into()
+    );
+}
+
+#[test]
+fn test_array_empty() {
+    let instrs = vec![ByteCode::ARRAY(0), ByteCode::DONE];
+    assert_eq!(top_of(instrs), Vec::new().into());
+}
+
+#[test]
+fn test_array_underflow() {
+    let instrs = vec![ByteCode::ldc(1), ByteCode::ARRAY(2), ByteCode::DONE];
+    expect_vm_err(instrs, |e| matches!(e, VmError::OperandStackUnderflow));
+}
@@ -0,0 +1,39 @@
+use bytecode::{ByteCode, Value};
+use ignite::VmError;
+
+use crate::{expect_vm_err, top_of};
+
+#[test]
+fn test_array_fill() {
+    let instrs = vec![ByteCode::ldc(7), ByteCode::ARRAYFILL(3), ByteCode::DONE];
+    assert_eq!(top_of(instrs), vec![Value::Int(7); 3].into());
+}
+
+#[test]
+fn test_array_fill_rows_are_independent() {
+    // let grid = [[0; 2]; 2]; grid[0][1] = 5; grid[1]
+    let instrs = vec![
+        ByteCode::enterscope(vec!["grid"]),
+        ByteCode::ldc(0),
+        ByteCode::ARRAYFILL(2),
+        ByteCode::ARRAYFILL(2),
+        ByteCode::assign("grid"),
+        ByteCode::ld("grid"),
+        ByteCode::ldc(0),
+        ByteCode::LDIDX,
+        ByteCode::ldc(1),
+        ByteCode::ldc(5),
+        ByteCode::ASSIGNIDX,
+        ByteCode::ld("grid"),
+        ByteCode::ldc(1),
+        ByteCode::LDIDX,
+        ByteCode::DONE,
+    ];
+    assert_eq!(top_of(instrs), vec![Value::Int(0); 2].into());
+}
+
+#[test]
+fn test_array_fill_underflow() {
+    let instrs = vec![ByteCode::ARRAYFILL(2), ByteCode::DONE];
+    expect_vm_err(instrs, |e| matches!(e, VmError::OperandStackUnderflow));
+}
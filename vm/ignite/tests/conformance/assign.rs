@@ -0,0 +1,49 @@
+use bytecode::{ByteCode, ByteCodeError, Value};
+use ignite::VmError;
+
+use crate::{expect_bytecode_err, expect_vm_err, top_of};
+
+#[test]
+fn test_assign_declared_symbol() {
+    let instrs = vec![
+        ByteCode::enterscope(vec!["x"]),
+        ByteCode::ldc(42),
+        ByteCode::assign("x"),
+        ByteCode::ld("x"),
+        ByteCode::DONE,
+    ];
+    assert_eq!(top_of(instrs), Value::Int(42));
+}
+
+#[test]
+fn test_assign_updates_enclosing_scope() {
+    let instrs = vec![
+        ByteCode::enterscope(vec!["x"]),
+        ByteCode::enterscope(vec!["y"]),
+        ByteCode::ldc(7),
+        ByteCode::assign("x"),
+        ByteCode::EXITSCOPE,
+        ByteCode::ld("x"),
+        ByteCode::DONE,
+    ];
+    assert_eq!(top_of(instrs), Value::Int(7));
+}
+
+#[test]
+fn test_assign_undeclared_symbol() {
+    let instrs = vec![ByteCode::ldc(1), ByteCode::assign("x"), ByteCode::DONE];
+    expect_bytecode_err(
+        instrs,
+        |e| matches!(e, ByteCodeError::UnboundedName { name } if name == "x"),
+    );
+}
+
+#[test]
+fn test_assign_underflow() {
+    let instrs = vec![
+        ByteCode::enterscope(vec!["x"]),
+        ByteCode::assign("x"),
+        ByteCode::DONE,
+    ];
+    expect_vm_err(instrs, |e| matches!(e, VmError::OperandStackUnderflow));
+}
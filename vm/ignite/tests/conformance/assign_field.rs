@@ -0,0 +1,45 @@
+use bytecode::{ByteCode, Value};
+use ignite::VmError;
+
+use crate::{expect_vm_err, top_of};
+
+#[test]
+fn test_assign_field_updates_every_reference() {
+    // let p = Point { x: 1 }; let q = p; q.x = 3; p.x
+    let instrs = vec![
+        ByteCode::enterscope(vec!["p", "q"]),
+        ByteCode::ldc(1),
+        ByteCode::STRUCT("Point".into(), vec!["x".into()]),
+        ByteCode::assign("p"),
+        ByteCode::ld("p"),
+        ByteCode::assign("q"),
+        ByteCode::ld("q"),
+        ByteCode::ldc(3),
+        ByteCode::ASSIGNFIELD("x".into()),
+        ByteCode::ld("p"),
+        ByteCode::LDFIELD("x".into()),
+        ByteCode::DONE,
+    ];
+    assert_eq!(top_of(instrs), Value::Int(3));
+}
+
+#[test]
+fn test_assign_field_not_found() {
+    let instrs = vec![
+        ByteCode::ldc(1),
+        ByteCode::STRUCT("Point".into(), vec!["x".into()]),
+        ByteCode::ldc(2),
+        ByteCode::ASSIGNFIELD("y".into()),
+        ByteCode::DONE,
+    ];
+    expect_vm_err(
+        instrs,
+        |e| matches!(e, VmError::FieldNotFound { field, pc: 3, .. } if field == "y"),
+    );
+}
+
+#[test]
+fn test_assign_field_underflow() {
+    let instrs = vec![ByteCode::ldc(1), ByteCode::ASSIGNFIELD("x".into())];
+    expect_vm_err(instrs, |e| matches!(e, VmError::OperandStackUnderflow));
+}
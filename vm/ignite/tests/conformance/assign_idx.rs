@@ -0,0 +1,52 @@
+use bytecode::{ByteCode, Value};
+use ignite::VmError;
+
+use crate::{expect_vm_err, top_of};
+
+#[test]
+fn test_assign_idx_updates_every_reference() {
+    // let xs = [1, 2]; let ys = xs; ys[0] = 3; xs
+    let instrs = vec![
+        ByteCode::enterscope(vec!["xs", "ys"]),
+        ByteCode::ldc(1),
+        ByteCode::ldc(2),
+        ByteCode::ARRAY(2),
+        ByteCode::assign("xs"),
+        ByteCode::ld("xs"),
+        ByteCode::assign("ys"),
+        ByteCode::ld("ys"),
+        ByteCode::ldc(0),
+        ByteCode::ldc(3),
+        ByteCode::ASSIGNIDX,
+        ByteCode::ld("xs"),
+        ByteCode::DONE,
+    ];
+    assert_eq!(top_of(instrs), vec![Value::Int(3), Value::Int(2)].into());
+}
+
+#[test]
+fn test_assign_idx_out_of_bounds() {
+    let instrs = vec![
+        ByteCode::ARRAY(0),
+        ByteCode::ldc(0),
+        ByteCode::ldc(1),
+        ByteCode::ASSIGNIDX,
+        ByteCode::DONE,
+    ];
+    expect_vm_err(instrs, |e| {
+        matches!(
+            e,
+            VmError::IndexOutOfBounds {
+                index: 0,
+                len: 0,
+                pc: 3
+            }
+        )
+    });
+}
+
+#[test]
+fn test_assign_idx_underflow() {
+    let instrs = vec![ByteCode::ldc(0), ByteCode::ldc(1), ByteCode::ASSIGNIDX];
+    expect_vm_err(instrs, |e| matches!(e, VmError::OperandStackUnderflow));
+}
@@ -0,0 +1,78 @@
+use bytecode::{BinOp, ByteCode, Value};
+use ignite::VmError;
+
+use crate::{expect_vm_err, top_of};
+
+fn eval(lhs: impl Into<Value>, rhs: impl Into<Value>, op: BinOp) -> Value {
+    top_of(vec![
+        ByteCode::ldc(lhs),
+        ByteCode::ldc(rhs),
+        ByteCode::BINOP(op),
+        ByteCode::DONE,
+    ])
+}
+
+#[test]
+fn test_binop_int_arithmetic() {
+    assert_eq!(eval(7, 3, BinOp::Add), Value::Int(10));
+    assert_eq!(eval(7, 3, BinOp::Sub), Value::Int(4));
+    assert_eq!(eval(7, 3, BinOp::Mul), Value::Int(21));
+    assert_eq!(eval(7, 3, BinOp::Div), Value::Int(2));
+    assert_eq!(eval(7, 3, BinOp::Mod), Value::Int(1));
+}
+
+#[test]
+fn test_binop_operand_order() {
+    // lhs is pushed first, so it is the deeper of the two operands
+    assert_eq!(eval(1, 2, BinOp::Sub), Value::Int(-1));
+    assert_eq!(eval(1, 2, BinOp::Lt), Value::Bool(true));
+    assert_eq!(eval(1, 2, BinOp::Gt), Value::Bool(false));
+}
+
+#[test]
+fn test_binop_float_bool_string() {
+    assert_eq!(eval(1.5, 2.5, BinOp::Add), Value::Float(4.0));
+    assert_eq!(eval(true, false, BinOp::And), Value::Bool(false));
+    assert_eq!(eval(true, false, BinOp::Or), Value::Bool(true));
+    assert_eq!(eval("ab", "cd", BinOp::Add), Value::String("abcd".into()));
+    assert_eq!(eval("ab", "ab", BinOp::Eq), Value::Bool(true));
+}
+
+#[test]
+fn test_binop_boundary_values() {
+    assert_eq!(eval(i64::MAX, 0, BinOp::Add), Value::Int(i64::MAX));
+    assert_eq!(eval(i64::MIN, i64::MIN, BinOp::Eq), Value::Bool(true));
+    assert_eq!(eval(i64::MIN, i64::MAX, BinOp::Lt), Value::Bool(true));
+}
+
+#[test]
+fn test_binop_type_mismatch() {
+    let instrs = vec![
+        ByteCode::ldc(1),
+        ByteCode::ldc(1.0),
+        ByteCode::BINOP(BinOp::Add),
+        ByteCode::DONE,
+    ];
+    expect_vm_err(instrs, |e| matches!(e, VmError::TypeMismatch { .. }));
+}
+
+#[test]
+fn test_binop_unsupported_operation() {
+    let instrs = vec![
+        ByteCode::ldc(true),
+        ByteCode::ldc(false),
+        ByteCode::BINOP(BinOp::Add),
+        ByteCode::DONE,
+    ];
+    expect_vm_err(instrs, |e| matches!(e, VmError::UnsupportedOperation(..)));
+}
+
+#[test]
+fn test_binop_underflow() {
+    let instrs = vec![
+        ByteCode::ldc(1),
+        ByteCode::BINOP(BinOp::Add),
+        ByteCode::DONE,
+    ];
+    expect_vm_err(instrs, |e| matches!(e, VmError::OperandStackUnderflow));
+}
@@ -0,0 +1,82 @@
+use bytecode::{BinOp, ByteCode, FrameType, Value};
+use ignite::VmError;
+
+use crate::{expect_vm_err, top_of};
+
+#[test]
+fn test_call_user_fn() {
+    let instrs = vec![
+        ByteCode::ldf(3, vec!["x"]),
+        ByteCode::ldc(41),
+        ByteCode::GOTO(7),
+        ByteCode::ld("x"),
+        ByteCode::ldc(1),
+        ByteCode::BINOP(BinOp::Add),
+        ByteCode::reset(FrameType::CallFrame),
+        ByteCode::CALL(1),
+        ByteCode::DONE,
+    ];
+    assert_eq!(top_of(instrs), Value::Int(42));
+}
+
+#[test]
+fn test_call_restores_caller_env() {
+    // y is in the caller's block scope, not in the env the closure was made in
+    let instrs = vec![
+        ByteCode::enterscope(vec!["y"]),
+        ByteCode::ldc(5),
+        ByteCode::assign("y"),
+        ByteCode::ldf(5, Vec::<&str>::new()),
+        ByteCode::GOTO(7),
+        ByteCode::ldc(1),
+        ByteCode::reset(FrameType::CallFrame),
+        ByteCode::CALL(0),
+        ByteCode::POP,
+        ByteCode::ld("y"),
+        ByteCode::DONE,
+    ];
+    assert_eq!(top_of(instrs), Value::Int(5));
+}
+
+#[test]
+fn test_call_builtin() {
+    let instrs = vec![
+        ByteCode::ld("abs"),
+        ByteCode::ldc(-5),
+        ByteCode::CALL(1),
+        ByteCode::DONE,
+    ];
+    assert_eq!(top_of(instrs), Value::Int(5));
+}
+
+#[test]
+fn test_call_non_closure() {
+    let instrs = vec![ByteCode::ldc(1), ByteCode::CALL(0), ByteCode::DONE];
+    expect_vm_err(instrs, |e| matches!(e, VmError::BadType { .. }));
+}
+
+#[test]
+fn test_call_arity_mismatch() {
+    let instrs = vec![
+        ByteCode::ldf(0, vec!["x", "y"]),
+        ByteCode::ldc(1),
+        ByteCode::CALL(1),
+        ByteCode::DONE,
+    ];
+    expect_vm_err(instrs, |e| {
+        matches!(
+            e,
+            VmError::ArityParamsMismatch {
+                arity: 1,
+                params: 2
+            }
+        )
+    });
+}
+
+#[test]
+fn test_call_underflow() {
+    // Arguments present but no closure below them
+    let instrs = vec![ByteCode::ldc(1), ByteCode::CALL(1), ByteCode::DONE];
+    expect_vm_err(instrs, |e| matches!(e, VmError::OperandStackUnderflow));
+}
@@ -0,0 +1,25 @@
+use bytecode::{ByteCode, Value};
+
+use crate::{run_instrs, top_of};
+
+#[test]
+fn test_done_stops_main_thread() {
+    // Instructions after DONE are never executed
+    let instrs = vec![ByteCode::ldc(1), ByteCode::DONE, ByteCode::ldc(2)];
+    assert_eq!(top_of(instrs), Value::Int(1));
+}
+
+#[test]
+fn test_done_child_becomes_zombie() {
+    let instrs = vec![
+        ByteCode::SPAWN(3),
+        ByteCode::YIELD,
+        ByteCode::DONE,
+        ByteCode::POP,
+        ByteCode::ldc(7),
+        ByteCode::DONE,
+    ];
+    let rt = run_instrs(instrs).expect("Program should run");
+    let zombie = rt.zombie_threads.get(&2).expect("Child should be a zombie");
+    assert_eq!(zombie.operand_stack.last(), Some(&Value::Int(7)));
+}
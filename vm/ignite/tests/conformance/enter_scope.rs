@@ -0,0 +1,41 @@
+use bytecode::{ByteCode, FrameType, Value};
+
+use crate::{run_instrs, top_of};
+
+#[test]
+fn test_enter_scope_declares_uninitialized() {
+    let instrs = vec![
+        ByteCode::enterscope(vec!["x"]),
+        ByteCode::ld("x"),
+        ByteCode::DONE,
+    ];
+    assert_eq!(top_of(instrs), Value::Unitialized);
+}
+
+#[test]
+fn test_enter_scope_pushes_block_frame() {
+    let instrs = vec![
+        ByteCode::enterscope(vec!["x"]),
+        ByteCode::enterscope(Vec::<String>::new()),
+        ByteCode::DONE,
+    ];
+    let rt = run_instrs(instrs).expect("Program should run");
+    let frames = &rt.current_thread.runtime_stack;
+    assert_eq!(frames.len(), 2);
+    assert!(frames.iter().all(|f| f.frame_type == FrameType::BlockFrame));
+}
+
+#[test]
+fn test_enter_scope_shadows() {
+    let instrs = vec![
+        ByteCode::enterscope(vec!["x"]),
+        ByteCode::ldc(1),
+        ByteCode::assign("x"),
+        ByteCode::enterscope(vec!["x"]),
+        ByteCode::ldc(2),
+        ByteCode::assign("x"),
+        ByteCode::ld("x"),
+        ByteCode::DONE,
+    ];
+    assert_eq!(top_of(instrs), Value::Int(2));
+}
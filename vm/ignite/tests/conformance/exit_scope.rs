@@ -0,0 +1,51 @@
+use bytecode::{ByteCode, Value};
+use ignite::VmError;
+
+use crate::{expect_vm_err, run_instrs, top_of};
+
+#[test]
+fn test_exit_scope_restores_env() {
+    let instrs = vec![
+        ByteCode::enterscope(vec!["x"]),
+        ByteCode::ldc(1),
+        ByteCode::assign("x"),
+        ByteCode::enterscope(vec!["x"]),
+        ByteCode::ldc(2),
+        ByteCode::assign("x"),
+        ByteCode::EXITSCOPE,
+        ByteCode::ld("x"),
+        ByteCode::DONE,
+    ];
+    assert_eq!(top_of(instrs), Value::Int(1));
+}
+
+#[test]
+fn test_exit_scope_pops_frame() {
+    let instrs = vec![
+        ByteCode::enterscope(vec!["x"]),
+        ByteCode::EXITSCOPE,
+        ByteCode::DONE,
+    ];
+    let rt = run_instrs(instrs).expect("Program should run");
+    assert!(rt.current_thread.runtime_stack.is_empty());
+}
+
+#[test]
+fn test_exit_scope_empty_runtime_stack() {
+    let instrs = vec![ByteCode::EXITSCOPE, ByteCode::DONE];
+    expect_vm_err(instrs, |e| matches!(e, VmError::ScopeUnderflow { pc: 0 }));
+}
+
+#[test]
+fn test_exit_scope_does_not_cross_call_frame() {
+    // The callee exits a scope it never entered, which must not unwind the caller's call frame
+    let instrs = vec![
+        ByteCode::enterscope(vec!["x"]),
+        ByteCode::ldf(4, Vec::<String>::new()),
+        ByteCode::CALL(0),
+        ByteCode::DONE,
+        ByteCode::EXITSCOPE,
+        ByteCode::DONE,
+    ];
+    expect_vm_err(instrs, |e| matches!(e, VmError::ScopeUnderflow { pc: 4 }));
+}
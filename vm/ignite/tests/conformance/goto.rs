@@ -0,0 +1,39 @@
+use bytecode::{ByteCode, Value};
+use ignite::VmError;
+
+use crate::{expect_vm_err, top_of};
+
+#[test]
+fn test_goto_skips_instructions() {
+    let instrs = vec![
+        ByteCode::GOTO(2),
+        ByteCode::ldc(1),
+        ByteCode::ldc(2),
+        ByteCode::DONE,
+    ];
+    assert_eq!(top_of(instrs), Value::Int(2));
+}
+
+#[test]
+fn test_goto_backwards() {
+    let instrs = vec![
+        ByteCode::GOTO(3),
+        ByteCode::ldc(1),
+        ByteCode::DONE,
+        ByteCode::GOTO(1),
+    ];
+    assert_eq!(top_of(instrs), Value::Int(1));
+}
+
+#[test]
+fn test_goto_out_of_bounds() {
+    let instrs = vec![ByteCode::GOTO(10), ByteCode::DONE];
+    expect_vm_err(instrs, |e| matches!(e, VmError::PcOutOfBounds(10)));
+}
+
+#[test]
+fn test_goto_end_of_program() {
+    // Jumping exactly one past the last instruction is also out of bounds
+    let instrs = vec![ByteCode::GOTO(2), ByteCode::DONE];
+    expect_vm_err(instrs, |e| matches!(e, VmError::PcOutOfBounds(2)));
+}
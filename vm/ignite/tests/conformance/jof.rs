@@ -0,0 +1,59 @@
+use bytecode::{ByteCode, ByteCodeError, Value};
+use ignite::VmError;
+
+use crate::{expect_bytecode_err, expect_vm_err, top_of};
+
+#[test]
+fn test_jof_jumps_on_false() {
+    let instrs = vec![
+        ByteCode::ldc(false),
+        ByteCode::JOF(3),
+        ByteCode::ldc(1),
+        ByteCode::ldc(2),
+        ByteCode::DONE,
+    ];
+    assert_eq!(top_of(instrs), Value::Int(2));
+}
+
+#[test]
+fn test_jof_falls_through_on_true() {
+    let instrs = vec![
+        ByteCode::ldc(true),
+        ByteCode::JOF(4),
+        ByteCode::ldc(1),
+        ByteCode::DONE,
+        ByteCode::ldc(2),
+        ByteCode::DONE,
+    ];
+    assert_eq!(top_of(instrs), Value::Int(1));
+}
+
+#[test]
+fn test_jof_target_out_of_bounds() {
+    let instrs = vec![ByteCode::ldc(false), ByteCode::JOF(100), ByteCode::DONE];
+    expect_vm_err(instrs, |e| matches!(e, VmError::PcOutOfBounds(100)));
+}
+
+#[test]
+fn test_jof_out_of_bounds_target_not_taken() {
+    // The target is only checked when the jump is taken
+    let instrs = vec![
+        ByteCode::ldc(true),
+        ByteCode::JOF(100),
+        ByteCode::ldc(1),
+        ByteCode::DONE,
+    ];
+    assert_eq!(top_of(instrs), Value::Int(1));
+}
+
+#[test]
+fn test_jof_non_bool() {
+    let instrs = vec![ByteCode::ldc(0), ByteCode::JOF(2), ByteCode::DONE];
+    expect_bytecode_err(instrs, |e| matches!(e, ByteCodeError::TypeMismatch { .. }));
+}
+
+#[test]
+fn test_jof_underflow() {
+    let instrs = vec![ByteCode::JOF(1), ByteCode::DONE];
+    expect_vm_err(instrs, |e| matches!(e, VmError::OperandStackUnderflow));
+}
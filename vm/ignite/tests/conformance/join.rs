@@ -0,0 +1,82 @@
+use bytecode::{ByteCode, ByteCodeError, Value};
+use ignite::VmError;
+
+use crate::{expect_bytecode_err, expect_vm_err, run_instrs, top_of};
+
+#[test]
+fn test_join_returns_child_result() {
+    let instrs = vec![
+        ByteCode::SPAWN(3),
+        ByteCode::JOIN,
+        ByteCode::DONE,
+        ByteCode::POP,
+        ByteCode::ldc(42),
+        ByteCode::DONE,
+    ];
+    assert_eq!(top_of(instrs), Value::Int(42));
+}
+
+#[test]
+fn test_join_removes_zombie() {
+    let instrs = vec![
+        ByteCode::SPAWN(3),
+        ByteCode::JOIN,
+        ByteCode::DONE,
+        ByteCode::POP,
+        ByteCode::ldc(42),
+        ByteCode::DONE,
+    ];
+    let rt = run_instrs(instrs).expect("Program should run");
+    assert!(rt.zombie_threads.is_empty());
+}
+
+#[test]
+fn test_join_non_int() {
+    let instrs = vec![ByteCode::ldc(true), ByteCode::JOIN, ByteCode::DONE];
+    expect_bytecode_err(instrs, |e| matches!(e, ByteCodeError::TypeMismatch { .. }));
+}
+
+#[test]
+fn test_join_underflow() {
+    let instrs = vec![ByteCode::JOIN, ByteCode::DONE];
+    expect_vm_err(instrs, |e| matches!(e, VmError::OperandStackUnderflow));
+}
+
+#[test]
+fn test_join_empty_zombie_stack() {
+    // A child that leaves nothing on its operand stack has no result to join on
+    let instrs = vec![
+        ByteCode::SPAWN(3),
+        ByteCode::JOIN,
+        ByteCode::DONE,
+        ByteCode::POP,
+        ByteCode::DONE,
+    ];
+    expect_vm_err(instrs, |e| matches!(e, VmError::OperandStackUnderflow));
+}
+
+#[test]
+fn test_tryjoin_finished_child() {
+    // The child runs to the end while the parent waits, and its result is dropped for the flag
+    let instrs = vec![
+        ByteCode::SPAWN(4),
+        ByteCode::ldc(1000),
+        ByteCode::TRYJOIN,
+        ByteCode::DONE,
+        ByteCode::POP,
+        ByteCode::ldc(42),
+        ByteCode::DONE,
+    ];
+    assert_eq!(top_of(instrs), Value::Bool(true));
+}
+
+#[test]
+fn test_tryjoin_non_int_timeout() {
+    let instrs = vec![
+        ByteCode::ldc(2),
+        ByteCode::ldc(true),
+        ByteCode::TRYJOIN,
+        ByteCode::DONE,
+    ];
+    expect_bytecode_err(instrs, |e| matches!(e, ByteCodeError::TypeMismatch { .. }));
+}
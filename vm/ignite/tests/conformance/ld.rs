@@ -0,0 +1,41 @@
+use bytecode::{ByteCode, ByteCodeError, Value};
+
+use crate::{expect_bytecode_err, top_of};
+
+#[test]
+fn test_ld_symbol() {
+    let instrs = vec![
+        ByteCode::enterscope(vec!["x"]),
+        ByteCode::ldc("hi"),
+        ByteCode::assign("x"),
+        ByteCode::ld("x"),
+        ByteCode::DONE,
+    ];
+    assert_eq!(top_of(instrs), Value::String("hi".into()));
+}
+
+#[test]
+fn test_ld_global_constant() {
+    let instrs = vec![ByteCode::ld("MAX_INT"), ByteCode::DONE];
+    assert_eq!(top_of(instrs), Value::Int(i64::MAX));
+}
+
+#[test]
+fn test_ld_unbound() {
+    let instrs = vec![ByteCode::ld("x"), ByteCode::DONE];
+    expect_bytecode_err(
+        instrs,
+        |e| matches!(e, ByteCodeError::UnboundedName { name } if name == "x"),
+    );
+}
+
+#[test]
+fn test_ld_out_of_scope() {
+    let instrs = vec![
+        ByteCode::enterscope(vec!["x"]),
+        ByteCode::EXITSCOPE,
+        ByteCode::ld("x"),
+        ByteCode::DONE,
+    ];
+    expect_bytecode_err(instrs, |e| matches!(e, ByteCodeError::UnboundedName { .. }));
+}
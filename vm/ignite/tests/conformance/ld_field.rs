@@ -0,0 +1,47 @@
+use bytecode::{ByteCode, Value};
+use ignite::VmError;
+
+use crate::{expect_vm_err, top_of};
+
+#[test]
+fn test_ld_field() {
+    let instrs = vec![
+        ByteCode::ldc(1),
+        ByteCode::ldc(2.0),
+        ByteCode::STRUCT("Point".into(), vec!["x".into(), "y".into()]),
+        ByteCode::LDFIELD("y".into()),
+        ByteCode::DONE,
+    ];
+    assert_eq!(top_of(instrs), Value::Float(2.0));
+}
+
+#[test]
+fn test_ld_field_not_found() {
+    let instrs = vec![
+        ByteCode::ldc(1),
+        ByteCode::STRUCT("Point".into(), vec!["x".into()]),
+        ByteCode::LDFIELD("z".into()),
+        ByteCode::DONE,
+    ];
+    expect_vm_err(
+        instrs,
+        |e| matches!(e, VmError::FieldNotFound { name, field, pc: 2 } if name == "Point" && field == "z"),
+    );
+}
+
+#[test]
+fn test_ld_field_underflow() {
+    expect_vm_err(vec![ByteCode::LDFIELD("x".into())], |e| {
+        matches!(e, VmError::OperandStackUnderflow)
+    });
+}
+
+#[test]
+fn test_ld_field_bad_type() {
+    let instrs = vec![
+        ByteCode::ldc(1),
+        ByteCode::LDFIELD("x".into()),
+        ByteCode::DONE,
+    ];
+    expect_vm_err(instrs, |e| matches!(e, VmError::BadType { .. }));
+}
@@ -0,0 +1,86 @@
+use bytecode::{ByteCode, Value};
+use ignite::VmError;
+
+use crate::{expect_vm_err, top_of};
+
+#[test]
+fn test_ld_idx() {
+    let instrs = vec![
+        ByteCode::ldc(1),
+        ByteCode::ldc(2),
+        ByteCode::ARRAY(2),
+        ByteCode::ldc(1),
+        ByteCode::LDIDX,
+        ByteCode::DONE,
+    ];
+    assert_eq!(top_of(instrs), Value::Int(2));
+}
+
+#[test]
+fn test_ld_idx_string() {
+    let instrs = vec![
+        ByteCode::ldc("abc"),
+        ByteCode::ldc(1),
+        ByteCode::LDIDX,
+        ByteCode::DONE,
+    ];
+    assert_eq!(top_of(instrs), Value::Char('b'));
+}
+
+#[test]
+fn test_ld_idx_out_of_bounds() {
+    let instrs = vec![
+        ByteCode::ldc(1),
+        ByteCode::ARRAY(1),
+        ByteCode::ldc(1),
+        ByteCode::LDIDX,
+        ByteCode::DONE,
+    ];
+    expect_vm_err(instrs, |e| {
+        matches!(
+            e,
+            VmError::IndexOutOfBounds {
+                index: 1,
+                len: 1,
+                pc: 3
+            }
+        )
+    });
+}
+
+#[test]
+fn test_ld_idx_negative() {
+    let instrs = vec![
+        ByteCode::ARRAY(0),
+        ByteCode::ldc(-1),
+        ByteCode::LDIDX,
+        ByteCode::DONE,
+    ];
+    expect_vm_err(instrs, |e| {
+        matches!(
+            e,
+            VmError::IndexOutOfBounds {
+                index: -1,
+                len: 0,
+                pc: 2
+            }
+        )
+    });
+}
+
+#[test]
+fn test_ld_idx_not_an_array() {
+    let instrs = vec![
+        ByteCode::ldc(true),
+        ByteCode::ldc(0),
+        ByteCode::LDIDX,
+        ByteCode::DONE,
+    ];
+    expect_vm_err(instrs, |e| matches!(e, VmError::BadType { .. }));
+}
+
+#[test]
+fn test_ld_idx_underflow() {
+    let instrs = vec![ByteCode::ldc(0), ByteCode::LDIDX, ByteCode::DONE];
+    expect_vm_err(instrs, |e| matches!(e, VmError::OperandStackUnderflow));
+}
@@ -0,0 +1,30 @@
+use bytecode::{ByteCode, Value};
+
+use crate::{run_instrs, top_of};
+
+#[test]
+fn test_ldc_constants() {
+    for val in [
+        Value::Unit,
+        Value::Int(i64::MIN),
+        Value::Int(i64::MAX),
+        Value::Float(f64::MIN_POSITIVE),
+        Value::Bool(false),
+        Value::String("".into()),
+    ] {
+        assert_eq!(
+            top_of(vec![ByteCode::LDC(val.clone()), ByteCode::DONE]),
+            val
+        );
+    }
+}
+
+#[test]
+fn test_ldc_pushes_in_order() {
+    let instrs = vec![ByteCode::ldc(1), ByteCode::ldc(2), ByteCode::DONE];
+    let rt = run_instrs(instrs).expect("Program should run");
+    assert_eq!(
+        rt.current_thread.operand_stack,
+        vec![Value::Int(1), Value::Int(2)]
+    );
+}
@@ -0,0 +1,37 @@
+use bytecode::{ByteCode, FnType, Value};
+
+use crate::{run_instrs, top_of};
+
+#[test]
+fn test_ldf_creates_closure() {
+    let instrs = vec![ByteCode::ldf(5, vec!["a", "b"]), ByteCode::DONE];
+    let Value::Closure {
+        fn_type,
+        prms,
+        addr,
+        ..
+    } = top_of(instrs)
+    else {
+        panic!("Expected closure");
+    };
+
+    assert_eq!(fn_type, FnType::User);
+    assert_eq!(*prms, ["a".to_string(), "b".to_string()]);
+    assert_eq!(addr, 5);
+}
+
+#[test]
+fn test_ldf_captures_current_env() {
+    let instrs = vec![
+        ByteCode::enterscope(vec!["x"]),
+        ByteCode::ldf(0, Vec::<String>::new()),
+        ByteCode::DONE,
+    ];
+    // Keep the runtime alive, it owns the environments
+    let rt = run_instrs(instrs).expect("Program should run");
+    let Some(Value::Closure { env, .. }) = rt.current_thread.operand_stack.last() else {
+        panic!("Expected closure");
+    };
+    let env = env.0.upgrade().expect("Env should be alive");
+    assert!(env.borrow().env.contains_key("x"));
+}
@@ -0,0 +1,45 @@
+use bytecode::{ByteCode, Value};
+use ignite::VmError;
+
+use crate::{expect_vm_err, top_of};
+
+#[test]
+fn test_len() {
+    let instrs = vec![
+        ByteCode::ldc(1),
+        ByteCode::ldc(2),
+        ByteCode::ARRAY(2),
+        ByteCode::LEN,
+        ByteCode::DONE,
+    ];
+    assert_eq!(top_of(instrs), Value::Int(2));
+}
+
+#[test]
+fn test_len_slice() {
+    let instrs = vec![
+        ByteCode::ldc(1),
+        ByteCode::ldc(2),
+        ByteCode::ldc(3),
+        ByteCode::ARRAY(3),
+        ByteCode::ldc(1),
+        ByteCode::ldc(2),
+        ByteCode::SLICE,
+        ByteCode::LEN,
+        ByteCode::DONE,
+    ];
+    assert_eq!(top_of(instrs), Value::Int(1));
+}
+
+#[test]
+fn test_len_underflow() {
+    expect_vm_err(vec![ByteCode::LEN, ByteCode::DONE], |e| {
+        matches!(e, VmError::OperandStackUnderflow)
+    });
+}
+
+#[test]
+fn test_len_bad_type() {
+    let instrs = vec![ByteCode::ldc("abc"), ByteCode::LEN, ByteCode::DONE];
+    expect_vm_err(instrs, |e| matches!(e, VmError::BadType { .. }));
+}
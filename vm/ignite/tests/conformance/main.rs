@@ -0,0 +1,72 @@
+//! Conformance suite for the VM: one module per opcode, exercising the public `ignite` API only.
+//! Each module covers the success path of its opcode, stack underflow and type mismatches where
+//! they apply, and boundary cases such as jump targets outside of the program.
+
+use anyhow::Result;
+use bytecode::{ByteCode, ByteCodeError, Value};
+use ignite::{run, Runtime, VmError};
+
+mod array;
+mod array_fill;
+mod assign;
+mod assign_field;
+mod assign_idx;
+mod binop;
+mod call;
+mod done;
+mod enter_scope;
+mod exit_scope;
+mod goto;
+mod jof;
+mod join;
+mod ld;
+mod ld_field;
+mod ld_idx;
+mod ldc;
+mod ldf;
+mod len;
+mod pop;
+mod post;
+mod recv;
+mod reset;
+mod sem_create;
+mod send;
+mod slice;
+mod spawn;
+mod struct_;
+mod unop;
+mod wait;
+mod yield_;
+
+/// Run the instructions to completion on a fresh runtime.
+pub fn run_instrs(instrs: Vec<ByteCode>) -> Result<Runtime> {
+    run(Runtime::new(instrs))
+}
+
+/// Run the instructions and return the value left on top of the operand stack.
+pub fn top_of(instrs: Vec<ByteCode>) -> Value {
+    let rt = run_instrs(instrs).expect("Program should run");
+    rt.current_thread
+        .operand_stack
+        .last()
+        .cloned()
+        .expect("Operand stack should not be empty")
+}
+
+/// Run the instructions and expect them to fail with a VmError matching the predicate.
+pub fn expect_vm_err(instrs: Vec<ByteCode>, pred: impl Fn(&VmError) -> bool) {
+    let err = run_instrs(instrs).err().expect("Program should fail");
+    let vm_err = err
+        .downcast_ref::<VmError>()
+        .unwrap_or_else(|| panic!("Expected VmError, got: {err}"));
+    assert!(pred(vm_err), "Unexpected VmError: {vm_err}");
+}
+
+/// Run the instructions and expect them to fail with a ByteCodeError matching the predicate.
+pub fn expect_bytecode_err(instrs: Vec<ByteCode>, pred: impl Fn(&ByteCodeError) -> bool) {
+    let err = run_instrs(instrs).err().expect("Program should fail");
+    let bc_err = err
+        .downcast_ref::<ByteCodeError>()
+        .unwrap_or_else(|| panic!("Expected ByteCodeError, got: {err}"));
+    assert!(pred(bc_err), "Unexpected ByteCodeError: {bc_err}");
+}
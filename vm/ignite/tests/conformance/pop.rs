@@ -0,0 +1,21 @@
+use bytecode::{ByteCode, Value};
+use ignite::VmError;
+
+use crate::{expect_vm_err, top_of};
+
+#[test]
+fn test_pop_removes_top() {
+    let instrs = vec![
+        ByteCode::ldc(1),
+        ByteCode::ldc(2),
+        ByteCode::POP,
+        ByteCode::DONE,
+    ];
+    assert_eq!(top_of(instrs), Value::Int(1));
+}
+
+#[test]
+fn test_pop_underflow() {
+    let instrs = vec![ByteCode::POP, ByteCode::DONE];
+    expect_vm_err(instrs, |e| matches!(e, VmError::OperandStackUnderflow));
+}
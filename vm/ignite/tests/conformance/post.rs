@@ -0,0 +1,47 @@
+use bytecode::{ByteCode, ByteCodeError};
+use ignite::VmError;
+
+use crate::{expect_bytecode_err, expect_vm_err, run_instrs};
+
+#[test]
+fn test_post_increments() {
+    let instrs = vec![ByteCode::SEMCREATE, ByteCode::POST, ByteCode::DONE];
+    run_instrs(instrs).expect("Program should run");
+}
+
+#[test]
+fn test_post_wakes_blocked_thread() {
+    // Main blocks on a semaphore with count 0 and the child posts it
+    let instrs = vec![
+        ByteCode::enterscope(vec!["s"]),
+        ByteCode::SEMCREATE,
+        ByteCode::assign("s"),
+        ByteCode::ld("s"),
+        ByteCode::WAIT,
+        ByteCode::SPAWN(10),
+        ByteCode::ld("s"),
+        ByteCode::WAIT,
+        ByteCode::ldc(1),
+        ByteCode::DONE,
+        // Child
+        ByteCode::POP,
+        ByteCode::ld("s"),
+        ByteCode::POST,
+        ByteCode::ldc(0),
+        ByteCode::DONE,
+    ];
+    let rt = run_instrs(instrs).expect("Program should run");
+    assert!(rt.blocked_queue.is_empty());
+}
+
+#[test]
+fn test_post_non_semaphore() {
+    let instrs = vec![ByteCode::ldc(1), ByteCode::POST, ByteCode::DONE];
+    expect_bytecode_err(instrs, |e| matches!(e, ByteCodeError::TypeMismatch { .. }));
+}
+
+#[test]
+fn test_post_underflow() {
+    let instrs = vec![ByteCode::POST, ByteCode::DONE];
+    expect_vm_err(instrs, |e| matches!(e, VmError::OperandStackUnderflow));
+}
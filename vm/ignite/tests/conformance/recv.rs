@@ -0,0 +1,28 @@
+use bytecode::{ByteCode, ByteCodeError};
+use ignite::VmError;
+
+use crate::{expect_bytecode_err, expect_vm_err};
+
+#[test]
+fn test_recv_empty_no_ready_threads() {
+    // Nothing else can send, so blocking has no thread to switch to
+    let instrs = vec![
+        ByteCode::ld("chan"),
+        ByteCode::CALL(0),
+        ByteCode::RECV,
+        ByteCode::DONE,
+    ];
+    expect_vm_err(instrs, |e| matches!(e, VmError::NoThreadsInReadyQueue));
+}
+
+#[test]
+fn test_recv_non_channel() {
+    let instrs = vec![ByteCode::ldc(1), ByteCode::RECV, ByteCode::DONE];
+    expect_bytecode_err(instrs, |e| matches!(e, ByteCodeError::TypeMismatch { .. }));
+}
+
+#[test]
+fn test_recv_underflow() {
+    let instrs = vec![ByteCode::RECV, ByteCode::DONE];
+    expect_vm_err(instrs, |e| matches!(e, VmError::OperandStackUnderflow));
+}
@@ -0,0 +1,68 @@
+use bytecode::{ByteCode, FrameType, Value};
+use ignite::VmError;
+
+use crate::{expect_vm_err, top_of};
+
+#[test]
+fn test_reset_returns_to_caller() {
+    let instrs = vec![
+        ByteCode::ldf(2, Vec::<String>::new()),
+        ByteCode::GOTO(4),
+        ByteCode::ldc(1),
+        ByteCode::reset(FrameType::CallFrame),
+        ByteCode::CALL(0),
+        ByteCode::DONE,
+    ];
+    assert_eq!(top_of(instrs), Value::Int(1));
+}
+
+#[test]
+fn test_reset_unwinds_block_frames() {
+    // Return from inside a nested block of the callee
+    let instrs = vec![
+        ByteCode::ldf(2, Vec::<String>::new()),
+        ByteCode::GOTO(6),
+        ByteCode::enterscope(vec!["x"]),
+        ByteCode::enterscope(vec!["y"]),
+        ByteCode::ldc(1),
+        ByteCode::reset(FrameType::CallFrame),
+        ByteCode::CALL(0),
+        ByteCode::DONE,
+    ];
+    assert_eq!(top_of(instrs), Value::Int(1));
+}
+
+#[test]
+fn test_reset_empty_runtime_stack() {
+    let instrs = vec![ByteCode::reset(FrameType::CallFrame), ByteCode::DONE];
+    expect_vm_err(instrs, |e| {
+        matches!(e, VmError::ResetUnderflow { pc: 0, .. })
+    });
+}
+
+#[test]
+fn test_reset_no_matching_frame() {
+    let instrs = vec![
+        ByteCode::enterscope(vec!["x"]),
+        ByteCode::reset(FrameType::CallFrame),
+        ByteCode::DONE,
+    ];
+    expect_vm_err(instrs, |e| {
+        matches!(e, VmError::ResetUnderflow { pc: 1, .. })
+    });
+}
+
+#[test]
+fn test_reset_block_frame_does_not_cross_call_frame() {
+    let instrs = vec![
+        ByteCode::enterscope(vec!["x"]),
+        ByteCode::ldf(4, Vec::<String>::new()),
+        ByteCode::CALL(0),
+        ByteCode::DONE,
+        ByteCode::reset(FrameType::BlockFrame),
+        ByteCode::DONE,
+    ];
+    expect_vm_err(instrs, |e| {
+        matches!(e, VmError::ResetFrameMismatch { pc: 4, .. })
+    });
+}
@@ -0,0 +1,22 @@
+use bytecode::{ByteCode, Value};
+
+use crate::top_of;
+
+#[test]
+fn test_sem_create() {
+    let Value::Semaphore(sem) = top_of(vec![ByteCode::SEMCREATE, ByteCode::DONE]) else {
+        panic!("Expected semaphore");
+    };
+    assert_eq!(*sem.lock().unwrap(), 1);
+}
+
+#[test]
+fn test_sem_create_distinct() {
+    let instrs = vec![
+        ByteCode::SEMCREATE,
+        ByteCode::SEMCREATE,
+        ByteCode::BINOP(bytecode::BinOp::Eq),
+        ByteCode::DONE,
+    ];
+    assert_eq!(top_of(instrs), Value::Bool(false));
+}
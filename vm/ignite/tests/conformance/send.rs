@@ -0,0 +1,65 @@
+use bytecode::{ByteCode, ByteCodeError, Value};
+use ignite::VmError;
+
+use crate::{expect_bytecode_err, expect_vm_err, run_instrs, top_of};
+
+#[test]
+fn test_send_then_recv() {
+    let instrs = vec![
+        ByteCode::enterscope(vec!["c"]),
+        ByteCode::ld("chan"),
+        ByteCode::CALL(0),
+        ByteCode::assign("c"),
+        ByteCode::ld("c"),
+        ByteCode::ldc(1),
+        ByteCode::SEND,
+        ByteCode::ld("c"),
+        ByteCode::ldc(2),
+        ByteCode::SEND,
+        ByteCode::ld("c"),
+        ByteCode::RECV,
+        ByteCode::DONE,
+    ];
+    assert_eq!(top_of(instrs), Value::Int(1));
+}
+
+#[test]
+fn test_send_wakes_blocked_thread() {
+    // Main blocks receiving on an empty channel and the child sends on it
+    let instrs = vec![
+        ByteCode::enterscope(vec!["c"]),
+        ByteCode::ld("chan"),
+        ByteCode::CALL(0),
+        ByteCode::assign("c"),
+        ByteCode::SPAWN(8),
+        ByteCode::ld("c"),
+        ByteCode::RECV,
+        ByteCode::DONE,
+        // Child
+        ByteCode::POP,
+        ByteCode::ld("c"),
+        ByteCode::ldc(42),
+        ByteCode::SEND,
+        ByteCode::ldc(0),
+        ByteCode::DONE,
+    ];
+    let rt = run_instrs(instrs).expect("Program should run");
+    assert!(rt.blocked_queue.is_empty());
+}
+
+#[test]
+fn test_send_non_channel() {
+    let instrs = vec![
+        ByteCode::ldc(1),
+        ByteCode::ldc(2),
+        ByteCode::SEND,
+        ByteCode::DONE,
+    ];
+    expect_bytecode_err(instrs, |e| matches!(e, ByteCodeError::TypeMismatch { .. }));
+}
+
+#[test]
+fn test_send_underflow() {
+    let instrs = vec![ByteCode::ldc(1), ByteCode::SEND, ByteCode::DONE];
+    expect_vm_err(instrs, |e| matches!(e, VmError::OperandStackUnderflow));
+}
@@ -0,0 +1,86 @@
+use bytecode::{ByteCode, Value};
+use ignite::VmError;
+
+use crate::{expect_vm_err, top_of};
+
+#[test]
+fn test_slice() {
+    let instrs = vec![
+        ByteCode::ldc(1),
+        ByteCode::ldc(2),
+        ByteCode::ldc(3),
+        ByteCode::ARRAY(3),
+        ByteCode::ldc(1),
+        ByteCode::ldc(3),
+        ByteCode::SLICE,
+        ByteCode::ldc(1),
+        ByteCode::LDIDX,
+        ByteCode::DONE,
+    ];
+    assert_eq!(top_of(instrs), Value::Int(3));
+}
+
+#[test]
+fn test_slice_to_end() {
+    let instrs = vec![
+        ByteCode::ldc(1),
+        ByteCode::ldc(2),
+        ByteCode::ARRAY(2),
+        ByteCode::ldc(2),
+        ByteCode::ldc(Value::Unit),
+        ByteCode::SLICE,
+        ByteCode::ldc(0),
+        ByteCode::ldc(Value::Unit),
+        ByteCode::SLICE,
+        ByteCode::DONE,
+    ];
+    let Value::Slice(slice) = top_of(instrs) else {
+        panic!("Expected a slice");
+    };
+    assert_eq!((slice.offset, slice.len), (2, 0));
+}
+
+#[test]
+fn test_slice_out_of_bounds() {
+    let instrs = vec![
+        ByteCode::ARRAY(0),
+        ByteCode::ldc(0),
+        ByteCode::ldc(1),
+        ByteCode::SLICE,
+        ByteCode::DONE,
+    ];
+    expect_vm_err(instrs, |e| {
+        matches!(
+            e,
+            VmError::SliceOutOfBounds {
+                start: 0,
+                end: 1,
+                len: 0,
+                pc: 3
+            }
+        )
+    });
+}
+
+#[test]
+fn test_slice_not_an_array() {
+    let instrs = vec![
+        ByteCode::ldc(1),
+        ByteCode::ldc(0),
+        ByteCode::ldc(Value::Unit),
+        ByteCode::SLICE,
+        ByteCode::DONE,
+    ];
+    expect_vm_err(instrs, |e| matches!(e, VmError::BadType { .. }));
+}
+
+#[test]
+fn test_slice_underflow() {
+    let instrs = vec![
+        ByteCode::ldc(0),
+        ByteCode::ldc(1),
+        ByteCode::SLICE,
+        ByteCode::DONE,
+    ];
+    expect_vm_err(instrs, |e| matches!(e, VmError::OperandStackUnderflow));
+}
@@ -0,0 +1,54 @@
+use bytecode::{ByteCode, Value};
+
+use crate::{run_instrs, top_of};
+
+#[test]
+fn test_spawn_pushes_child_id() {
+    let instrs = vec![ByteCode::SPAWN(2), ByteCode::DONE, ByteCode::DONE];
+    assert_eq!(top_of(instrs), Value::Int(2));
+}
+
+#[test]
+fn test_spawn_child_starts_at_addr() {
+    let instrs = vec![
+        ByteCode::SPAWN(3),
+        ByteCode::YIELD,
+        ByteCode::DONE,
+        ByteCode::POP,
+        ByteCode::ldc("child"),
+        ByteCode::DONE,
+    ];
+    let rt = run_instrs(instrs).expect("Program should run");
+    let child = rt
+        .zombie_threads
+        .get(&2)
+        .expect("Child should have finished");
+    assert_eq!(child.operand_stack, vec![Value::String("child".into())]);
+}
+
+#[test]
+fn test_spawn_child_receives_zero() {
+    let instrs = vec![
+        ByteCode::SPAWN(3),
+        ByteCode::JOIN,
+        ByteCode::DONE,
+        ByteCode::DONE,
+    ];
+    assert_eq!(top_of(instrs), Value::Int(0));
+}
+
+#[test]
+fn test_spawn_increments_ids() {
+    let instrs = vec![
+        ByteCode::SPAWN(3),
+        ByteCode::SPAWN(3),
+        ByteCode::DONE,
+        ByteCode::DONE,
+    ];
+    let rt = run_instrs(instrs).expect("Program should run");
+    assert_eq!(
+        rt.current_thread.operand_stack,
+        vec![Value::Int(2), Value::Int(3)]
+    );
+    assert_eq!(rt.thread_count, 3);
+}
@@ -0,0 +1,29 @@
+use bytecode::{ByteCode, Struct, Value};
+use ignite::VmError;
+
+use crate::{expect_vm_err, top_of};
+
+#[test]
+fn test_struct() {
+    let instrs = vec![
+        ByteCode::ldc(1),
+        ByteCode::ldc(2.0),
+        ByteCode::STRUCT("Point".into(), vec!["x".into(), "y".into()]),
+        ByteCode::DONE,
+    ];
+    let p = Struct::new(
+        "Point".into(),
+        vec![("x".into(), Value::Int(1)), ("y".into(), Value::Float(2.0))],
+    );
+    assert_eq!(top_of(instrs), p.into());
+}
+
+#[test]
+fn test_struct_underflow() {
+    let instrs = vec![
+        ByteCode::ldc(1),
+        ByteCode::STRUCT("Point".into(), vec!["x".into(), "y".into()]),
+        ByteCode::DONE,
+    ];
+    expect_vm_err(instrs, |e| matches!(e, VmError::OperandStackUnderflow));
+}
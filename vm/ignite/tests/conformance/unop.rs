@@ -0,0 +1,44 @@
+use bytecode::{ByteCode, UnOp, Value};
+use ignite::VmError;
+
+use crate::{expect_vm_err, top_of};
+
+fn eval(val: impl Into<Value>, op: UnOp) -> Value {
+    top_of(vec![ByteCode::ldc(val), ByteCode::UNOP(op), ByteCode::DONE])
+}
+
+#[test]
+fn test_unop() {
+    assert_eq!(eval(5, UnOp::Neg), Value::Int(-5));
+    assert_eq!(eval(2.5, UnOp::Neg), Value::Float(-2.5));
+    assert_eq!(eval(true, UnOp::Not), Value::Bool(false));
+}
+
+#[test]
+fn test_unop_boundary_values() {
+    assert_eq!(eval(i64::MAX, UnOp::Neg), Value::Int(-i64::MAX));
+    assert_eq!(eval(0, UnOp::Not), Value::Int(-1));
+}
+
+#[test]
+fn test_unop_unsupported() {
+    let instrs = vec![
+        ByteCode::ldc("s"),
+        ByteCode::UNOP(UnOp::Neg),
+        ByteCode::DONE,
+    ];
+    expect_vm_err(instrs, |e| matches!(e, VmError::UnsupportedOperation(..)));
+
+    let instrs = vec![
+        ByteCode::ldc(1.0),
+        ByteCode::UNOP(UnOp::Not),
+        ByteCode::DONE,
+    ];
+    expect_vm_err(instrs, |e| matches!(e, VmError::UnsupportedOperation(..)));
+}
+
+#[test]
+fn test_unop_underflow() {
+    let instrs = vec![ByteCode::UNOP(UnOp::Neg), ByteCode::DONE];
+    expect_vm_err(instrs, |e| matches!(e, VmError::OperandStackUnderflow));
+}
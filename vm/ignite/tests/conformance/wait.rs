@@ -0,0 +1,61 @@
+use bytecode::{ByteCode, ByteCodeError, Value};
+use ignite::VmError;
+
+use crate::{expect_bytecode_err, expect_vm_err, top_of};
+
+#[test]
+fn test_wait_decrements() {
+    let instrs = vec![
+        ByteCode::SEMCREATE,
+        ByteCode::WAIT,
+        ByteCode::ldc(1),
+        ByteCode::DONE,
+    ];
+    assert_eq!(top_of(instrs), Value::Int(1));
+}
+
+#[test]
+fn test_wait_blocks_current_thread() {
+    let instrs = vec![
+        ByteCode::enterscope(vec!["s"]),
+        ByteCode::SEMCREATE,
+        ByteCode::assign("s"),
+        ByteCode::ld("s"),
+        ByteCode::WAIT,
+        ByteCode::SPAWN(8),
+        ByteCode::ld("s"),
+        ByteCode::WAIT,
+        // Child: main stays blocked while it runs
+        ByteCode::DONE,
+    ];
+    // The child finishes with main still blocked and nothing left to run
+    expect_vm_err(instrs, |e| matches!(e, VmError::NoThreadsInReadyQueue));
+}
+
+#[test]
+fn test_wait_no_threads_to_run() {
+    // The second wait blocks main with no other thread to switch to
+    let instrs = vec![
+        ByteCode::enterscope(vec!["s"]),
+        ByteCode::SEMCREATE,
+        ByteCode::assign("s"),
+        ByteCode::ld("s"),
+        ByteCode::WAIT,
+        ByteCode::ld("s"),
+        ByteCode::WAIT,
+        ByteCode::DONE,
+    ];
+    expect_vm_err(instrs, |e| matches!(e, VmError::NoThreadsInReadyQueue));
+}
+
+#[test]
+fn test_wait_non_semaphore() {
+    let instrs = vec![ByteCode::ldc(1), ByteCode::WAIT, ByteCode::DONE];
+    expect_bytecode_err(instrs, |e| matches!(e, ByteCodeError::TypeMismatch { .. }));
+}
+
+#[test]
+fn test_wait_underflow() {
+    let instrs = vec![ByteCode::WAIT, ByteCode::DONE];
+    expect_vm_err(instrs, |e| matches!(e, VmError::OperandStackUnderflow));
+}
@@ -0,0 +1,26 @@
+use bytecode::{ByteCode, Value};
+
+use crate::{run_instrs, top_of};
+
+#[test]
+fn test_yield_alone_resumes_same_thread() {
+    let instrs = vec![ByteCode::YIELD, ByteCode::ldc(1), ByteCode::DONE];
+    assert_eq!(top_of(instrs), Value::Int(1));
+}
+
+#[test]
+fn test_yield_runs_ready_thread() {
+    // Main yields to the child, which finishes before main does
+    let instrs = vec![
+        ByteCode::SPAWN(4),
+        ByteCode::YIELD,
+        ByteCode::POP,
+        ByteCode::DONE,
+        ByteCode::POP,
+        ByteCode::ldc(2),
+        ByteCode::DONE,
+    ];
+    let rt = run_instrs(instrs).expect("Program should run");
+    assert!(rt.zombie_threads.contains_key(&2));
+    assert!(rt.ready_queue.is_empty());
+}
@@ -9,9 +9,10 @@ const OXIDATE_BINARY: &str = "oxidate";
 
 // Have to use random file name because tests run in parallel
 // With fixed filename we get errors due to race conditions
+// Files go in the temp dir, so one left behind by a failing test doesn't end up in the repo
 fn test_pass(inp: &str, exp: &str) -> Result<()> {
     let file_num = rand::random::<u128>().to_string();
-    let file_name = format!("./{file_num}.o2");
+    let file_name = std::env::temp_dir().join(format!("{file_num}.o2"));
 
     let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
     let comp = compile_from_string(inp, true)?;
@@ -36,13 +37,16 @@ fn test_pass(inp: &str, exp: &str) -> Result<()> {
 // file_name is expected to be prefix before .rst
 fn test_file(file_name: &str, exp: &str) -> Result<()> {
     let file_name_rst = format!("../../example/{file_name}.rst");
+    let out = std::env::temp_dir().join(format!("{file_name}-{}", rand::random::<u128>()));
 
     let mut cmd = Command::cargo_bin(OXIDATE_BINARY)?;
-    cmd.arg(file_name_rst.clone()).assert().success();
-
-    dbg!(format!("{file_name}.o2"));
+    cmd.arg(file_name_rst.clone())
+        .arg("-o")
+        .arg(&out)
+        .assert()
+        .success();
 
-    let file_name_o2 = format!("{file_name}.o2");
+    let file_name_o2 = out.with_extension("o2");
 
     let mut cmd_vm = Command::cargo_bin(IGNITE_BINARY)?;
 
@@ -53,11 +57,11 @@ fn test_file(file_name: &str, exp: &str) -> Result<()> {
     };
 
     cmd_vm
-        .arg(file_name_o2.clone())
+        .arg(&file_name_o2)
         .assert()
         .success()
         .stdout(predicate::eq(exp));
-    std::fs::remove_file(format!("./{file_name}.o2"))?;
+    std::fs::remove_file(file_name_o2)?;
 
     Ok(())
 }
@@ -73,6 +77,82 @@ fn test_e2e_example_folder() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_oxidate_doc() -> Result<()> {
+    let dir = std::env::temp_dir();
+    let file_num = rand::random::<u128>().to_string();
+    let file_name = dir.join(format!("{file_num}.rst"));
+    let t = r"
+    /// Doubles x.
+    fn double(x: int) -> int {
+        x * 2
+    }
+    double(2)
+    ";
+    std::fs::write(&file_name, t)?;
+
+    let mut cmd = Command::cargo_bin(OXIDATE_BINARY)?;
+    cmd.current_dir(&dir).arg(&file_name).arg("--doc");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(format!("{file_num}.md")));
+
+    let docs = std::fs::read_to_string(dir.join(format!("{file_num}.md")))?;
+    let exp =
+        format!("# {file_num}\n\n## Functions\n\n### `fn double(x: int) -> int`\n\nDoubles x.\n");
+    assert_eq!(docs, exp);
+
+    std::fs::remove_file(file_name)?;
+    std::fs::remove_file(dir.join(format!("{file_num}.md")))?;
+
+    Ok(())
+}
+
+#[test]
+fn test_oxidate_define() -> Result<()> {
+    let dir = std::env::temp_dir();
+    let file_num = rand::random::<u128>().to_string();
+    let file_name = dir.join(format!("{file_num}.rst"));
+    let t = r"
+    #[cfg(debug)]
+    fn log(x: int) {
+        println(x);
+    }
+
+    #[cfg(debug)]
+    let level = 3;
+
+    let x = 2;
+    if cfg(debug) {
+        println(x * 10);
+    }
+    x
+    ";
+    // log and level are only declared with the flag, so the program type checks either way
+    std::fs::write(&file_name, t)?;
+
+    for (defines, exp) in [(vec![], "2\n"), (vec!["--define", "debug"], "20\n2\n")] {
+        let mut cmd = Command::cargo_bin(OXIDATE_BINARY)?;
+        cmd.current_dir(&dir)
+            .arg(&file_name)
+            .args(defines)
+            .assert()
+            .success();
+
+        let mut cmd_vm = Command::cargo_bin(IGNITE_BINARY)?;
+        cmd_vm
+            .arg(dir.join(format!("{file_num}.o2")))
+            .assert()
+            .success()
+            .stdout(predicate::eq(exp));
+    }
+
+    std::fs::remove_file(file_name)?;
+    std::fs::remove_file(dir.join(format!("{file_num}.o2")))?;
+
+    Ok(())
+}
+
 #[test]
 fn test_e2e_simple() -> Result<()> {
     // int
@@ -142,7 +222,7 @@ fn test_e2e_blks_pop() -> Result<()> {
         "10",
     )?;
 
-    test_pass("let x = 2; { {2+2;} }", "()")?;
+    test_pass("let x = 2; { {2+2;} }", "")?;
     test_pass("let x = 2; { {2+2;} } {3}", "3")?;
     test_pass(
         r"
@@ -170,7 +250,7 @@ fn test_e2e_blks_pop() -> Result<()> {
     
     }
     ",
-        "()",
+        "",
     )?;
 
     Ok(())
@@ -525,6 +605,768 @@ fn test_e2e_loops() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_e2e_while() -> Result<()> {
+    let t = r"
+    let i = 0;
+    let sum = 0;
+    while i < 5 {
+        i = i + 1;
+        if i % 2 == 0 {
+            sum = sum + i;
+        }
+    }
+    sum
+    ";
+    test_pass(t, "6")?;
+
+    // break from inside nested scopes returns to the scope the loop is in
+    let t = r"
+    let x = 1;
+    while true {
+        let x = 2;
+        {
+            let x = 3;
+            break;
+        }
+    }
+    x
+    ";
+    test_pass(t, "1")?;
+
+    let t = r"
+    let n = 0;
+    while n < 3 {
+        let m = 0;
+        loop {
+            let k = m;
+            if k == n {
+                break;
+            }
+            m = m + 1;
+        }
+        n = n + 1;
+    }
+    n
+    ";
+    test_pass(t, "3")?;
+
+    Ok(())
+}
+
+#[test]
+fn test_e2e_for() -> Result<()> {
+    let t = r"
+    let sum = 0;
+    for i in 1..=4 {
+        sum = sum + i;
+    }
+    for i in 3..1 {
+        sum = sum + 100;
+    }
+    sum
+    ";
+    test_pass(t, "10")?;
+
+    // end is evaluated once and assigning to the loop var doesn't change the iteration
+    let t = r"
+    let n = 3;
+    let count = 0;
+    for i in 0..n {
+        n = n + 1;
+        i = i + 10;
+        count = count + 1;
+    }
+    count
+    ";
+    test_pass(t, "3")?;
+
+    // break from nested scopes leaves the loop var scope too
+    let t = r"
+    let i = 100;
+    for i in 0..10 {
+        let j = i;
+        {
+            let k = j;
+            if k == 2 {
+                break;
+            }
+        }
+    }
+    i
+    ";
+    test_pass(t, "100")?;
+
+    // arrays, slices and nested loops
+    let t = r"
+    let xs = [1, 2, 3, 4];
+    let total = 0;
+    for x in xs[1..] {
+        for y in xs {
+            total = total + x * y;
+        }
+    }
+    total
+    ";
+    test_pass(t, "90")?;
+
+    Ok(())
+}
+
+#[test]
+fn test_e2e_struct() -> Result<()> {
+    let t = r"
+    struct Point { x: int, y: float }
+    let p = Point { y: 2.5, x: 1 };
+    println(p);
+    p.x + 1
+    ";
+    test_pass(t, "Point { y: 2.5, x: 1 }\n2")?;
+
+    // structs are shared like arrays, including through fn params and array elements
+    let t = r"
+    struct Point { x: int, y: int }
+    fn shift(p: Point, dx: int) {
+        p.x = p.x + dx;
+    }
+    let p = Point { x: 1, y: 2 };
+    let q = p;
+    shift(q, 10);
+    let ps = [p, Point { x: 0, y: 0 }];
+    ps[1].y = 5;
+    p.x + ps[0].x + ps[1].y
+    ";
+    test_pass(t, "27")?;
+
+    // nested fields, and each element of a filled array is its own struct
+    let t = r"
+    struct Point { x: int, y: int }
+    struct Line { start: Point, end: Point }
+    let l = Line { start: Point { x: 0, y: 0 }, end: Point { x: 3, y: 4 } };
+    l.end.x = l.end.x * 2;
+    let ps = [Point { x: 1, y: 1 }; 2];
+    ps[0].x = 7;
+    l.end.x + l.end.y + ps[1].x
+    ";
+    test_pass(t, "11")?;
+
+    let t = r"
+    struct P { x: int }
+    P { x: 1 } == P { x: 1 } && !(P { x: 1 } == P { x: 2 })
+    ";
+    test_pass(t, "true")?;
+
+    Ok(())
+}
+
+#[test]
+fn test_e2e_macro() -> Result<()> {
+    let t = r"
+    macro swap($a, $b) { let t = $a; $a = $b; $b = t; }
+    macro max($a, $b) { if $a > $b { $a } else { $b } }
+    let x = 1;
+    let y = 2;
+    let t = 3;
+    swap!(x, y);
+    swap!(t, x);
+    println(x);
+    println(y);
+    println(t);
+    max!(x, max!(y, 10))
+    ";
+    test_pass(t, "3\n1\n2\n10")?;
+
+    // a fn declared in the body doesn't capture the caller's name for its param
+    let t = r"
+    macro m($a) { fn h(z: int) -> int { z + $a } println(h(1)); }
+    let z = 100;
+    m!(z);
+    ";
+    test_pass(t, "101")?;
+
+    // nor does a lambda
+    let t = r"
+    macro m($a) { let f = |t: int| t + $a; println(f(100)); }
+    let t = 1;
+    m!(t);
+    ";
+    test_pass(t, "101")?;
+
+    Ok(())
+}
+
+#[test]
+fn test_e2e_match() -> Result<()> {
+    let t = r"
+    fn describe(x: int) -> int {
+        match x {
+            0 => 100,
+            -1 => { return 200; }
+            _ => x * 2,
+        }
+    }
+    for i in [0, -1, 5] {
+        println(describe(i));
+    }
+    let total = 0;
+    for i in 0..10 {
+        match i % 3 == 0 {
+            true => { total = total + i; }
+            false => {}
+        }
+        match i {
+            7 => { break; }
+            _ => {}
+        }
+    }
+    total
+    ";
+    test_pass(t, "100\n200\n10\n9")?;
+
+    Ok(())
+}
+
+#[test]
+fn test_e2e_option_result() -> Result<()> {
+    // fallible builtins give a result to match on instead of stopping the program
+    let t = r#"
+    fn parse(s: str) -> option[int] {
+        match atoi(s) {
+            Ok(n) => Some(n),
+            Err(_) => None,
+        }
+    }
+    fn total(xs: [str; 3]) -> result[int, str] {
+        let sum = 0;
+        for x in xs {
+            match parse(x) {
+                Some(n) => { sum = sum + n; }
+                None => { return Err(format("bad number {}", x)); }
+            }
+        }
+        Ok(sum)
+    }
+    println(total(["1", "2", "x"]));
+    println(parse("7") == Some(7));
+
+    let c: chan[int] = chan();
+    println(try_recv(c));
+    send(c, 4);
+    let got = match try_recv(c) {
+        Some(n) => n,
+        None => 0,
+    };
+
+    match total(["10", "20", "30"]) {
+        Ok(n) => n + got,
+        Err(e) => {
+            println(e);
+            -1
+        }
+    }
+    "#;
+    test_pass(t, "Err(bad number x)\ntrue\nNone\n64")?;
+
+    Ok(())
+}
+
+#[test]
+fn test_e2e_enum() -> Result<()> {
+    let t = r"
+    enum Shape {
+        Circle(float),
+        Rect(float, float),
+        Empty,
+    }
+    fn area(s: Shape) -> float {
+        match s {
+            Circle(r) => 3.0 * r * r,
+            Rect(w, h) => w * h,
+            Empty => 0.0,
+        }
+    }
+    let shapes = [Circle(1.0), Rect(2.0, 3.0), Empty];
+    println(shapes[1]);
+    println(shapes[2] == Empty);
+    println(Rect(2.0, 3.0) == Rect(3.0, 2.0));
+
+    let total = 0.0;
+    for s in shapes {
+        total = total + area(s);
+    }
+    total
+    ";
+    test_pass(t, "Rect(2, 3)\ntrue\nfalse\n9")?;
+
+    Ok(())
+}
+
+#[test]
+fn test_e2e_match_or_range() -> Result<()> {
+    let t = r#"
+    fn grade(score: int) -> str {
+        match score {
+            100 => "perfect",
+            90..100 => "A",
+            75..=89 => "B",
+            0 | 1 | 2 => "barely there",
+            -100..0 => "negative",
+            _ => "C",
+        }
+    }
+    for s in [100, 95, 90, 89, 75, 74, 1, -3, 3] {
+        println(grade(s));
+    }
+    "#;
+    test_pass(t, "perfect\nA\nA\nB\nB\nC\nbarely there\nnegative\nC")?;
+
+    Ok(())
+}
+
+#[test]
+fn test_e2e_match_guard() -> Result<()> {
+    let t = r"
+    fn sign(x: option[int]) -> int {
+        match x {
+            Some(n) if n > 0 => 1,
+            Some(n) if n < 0 => -1,
+            Some(_) => 0,
+            None => 100,
+        }
+    }
+    let n = 5;
+    for x in [Some(3), Some(-2), Some(0), None] {
+        println(sign(x));
+    }
+    // the bind of a failed guard doesn't leak into the next arm
+    match Some(20) {
+        Some(n) if n > 50 => println(n),
+        _ => println(n),
+    }
+    ";
+    test_pass(t, "1\n-1\n0\n100\n5")?;
+
+    Ok(())
+}
+
+#[test]
+fn test_e2e_coalesce() -> Result<()> {
+    let t = r#"
+    let m = #{"a": 1, "b": 2};
+    let calls = 0;
+    fn fallback() -> int {
+        calls = calls + 1;
+        100
+    }
+    println(map_get(m, "a") ?? fallback());
+    println(map_get(m, "z") ?? fallback());
+    println(calls);
+
+    // right associative, and looser than arithmetic
+    let none: option[int] = None;
+    println(none ?? map_get(m, "b") ?? 0);
+    println(none ?? none ?? 3 * 2);
+
+    fn ok(r: result[int, str]) -> option[int] {
+        match r {
+            Ok(n) => Some(n),
+            Err(_) => None,
+        }
+    }
+    println(atoi("x") |> ok ?? -1);
+    "#;
+    test_pass(t, "1\n100\n1\n2\n6\n-1")?;
+
+    Ok(())
+}
+
+#[test]
+fn test_e2e_if_let_while_let() -> Result<()> {
+    let t = r#"
+    let m = #{"a": 1, "b": 2};
+    fn get(m: map[str, int], k: str) -> int {
+        if let Some(v) = map_get(m, k) { v } else { -1 }
+    }
+    println(get(m, "b"));
+    println(get(m, "z"));
+    if let None = map_get(m, "a") {
+        println("missing");
+    }
+
+    let c: chan[int] = chan();
+    for i in 1..=3 {
+        send(c, i * 10);
+    }
+    let total = 0;
+    while let Some(x) = try_recv(c) {
+        total = total + x;
+        if x == 20 {
+            break;
+        }
+    }
+    println(total);
+    // the loop ends when the pattern stops matching
+    while let Some(x) = try_recv(c) {
+        total = total + x;
+    }
+    total
+    "#;
+    test_pass(t, "2\n-1\n30\n60")?;
+
+    Ok(())
+}
+
+#[test]
+fn test_e2e_match_struct() -> Result<()> {
+    let t = r"
+    struct Point { x: int, y: int }
+    fn dist(p: Point) -> int {
+        match p {
+            Point { x, y: b } => x * x + b * b,
+        }
+    }
+    let p = Point { x: 3, y: 4 };
+    let x = 10;
+    println(match p { Point { x, .. } => x });
+    println(x);
+    dist(p)
+    ";
+    test_pass(t, "3\n10\n25")?;
+
+    Ok(())
+}
+
+#[test]
+fn test_e2e_tuple() -> Result<()> {
+    let t = r#"
+    fn div_rem(a: int, b: int) -> (int, int) {
+        (a / b, a % b)
+    }
+    let (q, r) = div_rem(17, 5);
+    println(q * 10 + r);
+
+    let t: (str, [int; 2], (bool,)) = ("xs", [1, 2], (true,));
+    println(t);
+    println(t.1[1]);
+    println(t.2.0);
+    println(div_rem(9, 3) == (3, 0));
+
+    // arrays in a tuple are shared, like arrays anywhere else
+    let (_, xs, _) = t;
+    xs[0] = 10;
+    t.1[0]
+    "#;
+    test_pass(t, "32\n(xs, [1, 2], (true,))\n2\ntrue\ntrue\n10")?;
+
+    Ok(())
+}
+
+#[test]
+fn test_e2e_int_division() -> Result<()> {
+    // / rounds towards zero and % takes the sign of the left side, like Rust and C
+    let t = r#"
+    println([7 / 2, -7 / 2, 7 / -2, -7 / -2]);
+    println([7 % 2, -7 % 2, 7 % -2, -7 % -2]);
+
+    // the euclidean versions round down, so the remainder is never negative
+    println([div_euclid(-7, 2), rem_euclid(-7, 2), rem_euclid(7, -2)]);
+    let hours = [0, 1, 2, 3];
+    println(hours[rem_euclid(0 - 1, 4)]);
+
+    let (q, r) = divmod(-17, 5);
+    println(q * 5 + r);
+    divmod(17, 5)
+    "#;
+    test_pass(
+        t,
+        "[3, -3, -3, 3]\n[1, -1, 1, -1]\n[-4, 1, 1]\n3\n-17\n(3, 2)",
+    )?;
+
+    Ok(())
+}
+
+#[test]
+fn test_e2e_map() -> Result<()> {
+    let t = r#"
+    let m = #{"b": 2, "a": 1};
+    map_insert(m, "c", 3);
+    println(m);
+    println(map_get(m, "a"));
+    println(map_remove(m, "b"));
+    println(map_get(m, "b"));
+    println(map_keys(m));
+
+    // maps are shared, like arrays
+    let alias = m;
+    map_insert(alias, "d", 4);
+    println(map_len(m));
+
+    let counts: map[int, int] = map_new();
+    for x in [1, 2, 1, 1] {
+        let n = match map_get(counts, x) {
+            Some(n) => n,
+            None => 0,
+        };
+        map_insert(counts, x, n + 1);
+    }
+    counts == #{1: 3, 2: 1}
+    "#;
+    test_pass(
+        t,
+        "#{a: 1, b: 2, c: 3}\nSome(1)\nSome(2)\nNone\n[a, c]\n3\ntrue",
+    )?;
+
+    // closures stored in a map keep the env they were made in
+    let t = r#"
+    fn make_ops() -> map[str, fn(int) -> int] {
+        let step = 10;
+        #{"inc": |x: int| x + step, "dbl": |x: int| x * 2}
+    }
+    let ops = make_ops();
+    let total = 0;
+    for name in map_keys(ops) {
+        total = total + match map_get(ops, name) {
+            Some(f) => f(1),
+            None => 0,
+        };
+    }
+    total
+    "#;
+    test_pass(t, "13")?;
+
+    // a map can hold the struct that holds it, and printing it stops where it comes back
+    let t = r#"
+    struct N { v: int, kids: map[str, N] }
+    let k: map[str, N] = #{};
+    let a = N { v: 1, kids: k };
+    map_insert(k, "self", a);
+    println(a);
+    format("{}", a)
+    "#;
+    test_pass(
+        t,
+        "N { v: 1, kids: #{self: <cycle>} }\nN { v: 1, kids: #{self: <cycle>} }",
+    )?;
+
+    Ok(())
+}
+
+#[test]
+fn test_e2e_channels() -> Result<()> {
+    // main blocks on recv before the producer has sent anything
+    let t = r"
+    fn produce(out: chan[int], n: int) {
+        for i in 1..=n {
+            send(out, i * 10);
+        }
+        send(out, -1);
+    }
+    fn sum(inp: chan[int], done: chan[int]) {
+        let total = 0;
+        loop {
+            let x = recv(inp);
+            if x < 0 {
+                break;
+            }
+            total = total + x;
+        }
+        send(done, total);
+    }
+    let nums : chan[int] = chan();
+    let done : chan[int] = chan();
+    spawn sum(nums, done);
+    spawn produce(nums, 3);
+    println(recv(done));
+    send(nums, 5);
+    recv(nums)
+    ";
+    test_pass(t, "60\n5")?;
+
+    Ok(())
+}
+
+#[test]
+fn test_e2e_lock() -> Result<()> {
+    // the mutex is released when the body ends, returns or breaks, so every later lock gets it
+    let t = r"
+    let m = mutex();
+    let count = 0;
+    fn work(n: int) {
+        for i in 0..n {
+            lock m {
+                let c = count;
+                yield;
+                count = c + 1;
+            }
+        }
+    }
+    fn first_over(limit: int) -> int {
+        lock m {
+            for i in 0..10 {
+                if i * i > limit {
+                    return i;
+                }
+            }
+            -1
+        }
+    }
+    let t1 = spawn work(5);
+    let t2 = spawn work(5);
+    join t1;
+    join t2;
+    println(count);
+    println(first_over(10));
+    loop {
+        lock m {
+            break;
+        }
+    }
+    lock m { count * 2 }
+    ";
+    test_pass(t, "10\n4\n20")?;
+
+    Ok(())
+}
+
+#[test]
+fn test_e2e_spawn_deep() -> Result<()> {
+    // the child starts with empty stacks in the env of the spawn, however deep the parent is
+    let t = r"
+    let res = 0;
+    fn work(x: int) {
+        res = x * 2;
+    }
+    fn down(n: int) {
+        if n == 0 {
+            let k = 21;
+            let t = spawn work(k);
+            join t;
+        } else {
+            down(n - 1);
+        }
+    }
+    down(500);
+    res
+    ";
+    test_pass(t, "42")?;
+
+    Ok(())
+}
+
+#[test]
+fn test_e2e_thread_env_semantics() -> Result<()> {
+    // args are evaluated at the spawn, other names are shared with the parent
+    let t = r"
+    let k = 21;
+    let res = 0;
+    let seen = 0;
+    fn work(x: int) {
+        res = x * 2;
+        seen = k;
+    }
+    let t = spawn work(k);
+    k = 5;
+    join t;
+    println(res);
+    seen
+    ";
+    test_pass(t, "42\n5")?;
+
+    // each instruction is atomic, but c = v + 1 after a read is not: both threads read 0
+    let t = r"
+    let c = 0;
+    fn inc() {
+        let v = c;
+        yield;
+        c = v + 1;
+    }
+    let t1 = spawn inc();
+    let t2 = spawn inc();
+    join t1;
+    join t2;
+    c
+    ";
+    test_pass(t, "1")?;
+
+    // bindings declared in a thread are its own
+    let t = r"
+    let out = [0, 0];
+    fn put(i: int) {
+        let v = i * 10 + 1;
+        yield;
+        out[i] = v;
+    }
+    let t1 = spawn put(0);
+    let t2 = spawn put(1);
+    join t1;
+    join t2;
+    println(out[0]);
+    out[1]
+    ";
+    test_pass(t, "1\n11")?;
+
+    Ok(())
+}
+
+#[test]
+fn test_e2e_spawn_isolate() -> Result<()> {
+    // the child works on copies, taken at the spawn, and only talks back through the channel
+    let t = r"
+    let xs = [1, 2, 3];
+    let k = 10;
+    let c: chan[int] = chan();
+    fn work(xs: [int; 3], c: chan[int]) {
+        xs[0] = 100;
+        send(c, xs[0] + k);
+    }
+    let t = spawn isolate work(xs, c);
+    k = 20;
+    let got = recv(c);
+    join t;
+    println(got);
+    xs[0]
+    ";
+    test_pass(t, "110\n1")?;
+
+    // arrays that were shared are still shared in the copy, and a closure sees the copied frame it was made in
+    let t = r"
+    let xs = [0];
+    let add = |n: int| xs[0] + n;
+    let c: chan[int] = chan();
+    fn work(ys: [int; 1], c: chan[int]) {
+        ys[0] = 7;
+        send(c, add(1));
+    }
+    let t = spawn isolate work(xs, c);
+    let got = recv(c);
+    join t;
+    println(got);
+    xs[0]
+    ";
+    test_pass(t, "8\n0")?;
+
+    Ok(())
+}
+
+#[test]
+fn test_e2e_pipeline() -> Result<()> {
+    // stages run left to right, each result is the first argument of the next stage
+    let t = r"
+    fn trace(x: int, tag: int) -> int {
+        println(tag);
+        x
+    }
+    fn sub(x: int, y: int) -> int {
+        x - y
+    }
+    let res = 10 |> trace(1) |> sub(trace(3, 2)) |> trace(3);
+    res
+    ";
+    test_pass(t, "1\n2\n3\n7")?;
+
+    Ok(())
+}
+
 #[test]
 fn test_e2e_fib() -> Result<()> {
     // loop-fib-01.rst
@@ -613,3 +1455,366 @@ fn test_e2e_fn_decl() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_e2e_lambda() -> Result<()> {
+    let t = r"
+    fn apply(f: fn(int) -> int, x: int) -> int {
+        f(x)
+    }
+    fn adder(k: int) -> fn(int) -> int {
+        |x: int| x + k
+    }
+    let add5 = adder(5);
+    let k = 100;
+    println(apply(add5, 1));
+    println(apply(|x: int| x * k, 3));
+    let sign = |x: int| -> int {
+        if x < 0 {
+            return -1;
+        }
+        1
+    };
+    println(sign(-4));
+    let curry = |x: int| |y: int| x - y;
+    let from10 = curry(10);
+    from10(4)
+    ";
+    test_pass(t, "6\n300\n-1\n6")?;
+
+    Ok(())
+}
+
+#[test]
+fn test_e2e_map_filter_fold() -> Result<()> {
+    let t = r"
+    fn square(x: int) -> int {
+        x * x
+    }
+    let xs = [1, 2, 3, 4, 5];
+    let squares = map(xs, square);
+    println(squares);
+    let offset = 10;
+    let odd = filter(xs, |x: int| x % 2 == 1);
+    println(map(odd, |x: int| x + offset));
+    let total = fold(squares, 0, |acc: int, x: int| acc + x);
+    println(total);
+    // callbacks can call back into builtins that take callbacks
+    let nested = map(xs[..2], |x: int| fold(xs, x, |acc: int, y: int| acc * y));
+    println(nested);
+    let calls = 0;
+    fold(filter(xs, |x: int| { calls = calls + 1; x > 10 }), calls, |acc: int, x: int| acc + x)
+    ";
+    test_pass(t, "[1, 4, 9, 16, 25]\n[11, 13, 15]\n55\n[120, 240]\n5")?;
+
+    Ok(())
+}
+
+#[test]
+fn test_e2e_nested_callbacks() -> Result<()> {
+    // callbacks of one builtin that call other builtins with callbacks of their own, and a function that recurses
+    // through a callback
+    let t = r"
+    let xs = map([1, 2, 3], |x: int| {
+        let sorted = sort_by([x, 0 - x, 2 * x], |a: int, b: int| a - b);
+        min_by(sorted, |a: int, b: int| b - a)
+    });
+    println(xs);
+
+    fn count(n: int) -> int {
+        if n == 0 {
+            0
+        } else {
+            fold(filter([n, 0], |x: int| x > 0), 1, |acc: int, x: int| acc + count(x - 1))
+        }
+    }
+    count(500)
+    ";
+    test_pass(t, "[2, 4, 6]\n500")?;
+
+    Ok(())
+}
+
+#[test]
+fn test_e2e_sort_by() -> Result<()> {
+    // pairs of a key and where they started, so the order of equal keys shows
+    let t = r"
+    fn by_key(a: [int; 2], b: [int; 2]) -> int {
+        a[0] - b[0]
+    }
+    let xs = [[2, 0], [1, 1], [2, 2], [1, 3], [0, 4]];
+    println(sort_by(xs, by_key));
+    println(xs[0]);
+    println(sort_by(xs[1..4], |a: [int; 2], b: [int; 2]| b[0] - a[0]));
+    println(min_by(xs, by_key));
+    max_by(xs, by_key)
+    ";
+    test_pass(
+        t,
+        "[[0, 4], [1, 1], [1, 3], [2, 0], [2, 2]]\n[2, 0]\n[[2, 2], [1, 1], [1, 3]]\n[0, 4]\n[2, 2]",
+    )?;
+
+    Ok(())
+}
+
+#[test]
+fn test_e2e_eval_order() -> Result<()> {
+    // every sub-expression runs left to right, seen through the order of the prints
+    let t = r"
+    fn t(x: int) -> int {
+        println(x);
+        x
+    }
+    fn sub(x: int, y: int) -> int {
+        x - y
+    }
+    println(t(1) - t(2) * t(3));
+    println(sub(t(4), sub(t(5), t(6))));
+    let xs = [t(7), t(8)];
+    xs[t(0)] = t(9);
+    struct P { x: int, y: int }
+    let p = P { y: t(10), x: t(11) };
+    let op = |x: int| x + 1;
+    fn change() -> int {
+        op = |x: int| x * 100;
+        2
+    }
+    // the callee is loaded before its args run
+    println(op(change()));
+    op(3)
+    ";
+    test_pass(t, "1\n2\n3\n-5\n4\n5\n6\n5\n7\n8\n0\n9\n10\n11\n3\n300")?;
+
+    Ok(())
+}
+
+#[test]
+fn test_e2e_bytes() -> Result<()> {
+    let path = std::env::temp_dir().join(format!("{}.bin", rand::random::<u128>()));
+    let path = path.display();
+
+    // a small header of a length and a checksum, written to a file and read back
+    let t = format!(
+        r#"
+    let data = bytes_from_string("héllo");
+    let len = bytes_len(data);
+    let header = bytes(2);
+    set_byte(header, 0, len);
+    let i = 0;
+    let sum = 0;
+    loop i < len {{
+        sum = (sum + get_byte(data, i)) % 256;
+        i = i + 1;
+    }}
+    set_byte(header, 1, sum);
+    write_bytes("{path}", header);
+
+    let read = match read_bytes("{path}") {{
+        Ok(b) => b,
+        Err(e) => {{
+            println(e);
+            bytes(2)
+        }}
+    }};
+    println(read);
+    println(string_from_bytes(data, "utf-8"));
+    get_byte(read, 0) * 1000 + get_byte(read, 1)
+    "#
+    );
+    test_pass(&t, "b\"\\x06\\x1b\"\nhéllo\n6027")?;
+
+    std::fs::remove_file(path.to_string())?;
+
+    Ok(())
+}
+
+#[test]
+fn test_e2e_detach() -> Result<()> {
+    // detached workers report back on a channel instead of being joined
+    let t = r"
+    fn work(out: chan[int], i: int) {
+        send(out, i);
+    }
+
+    let out: chan[int] = chan();
+    let i = 0;
+    loop i < 50 {
+        let t = spawn work(out, i);
+        detach(t);
+        i = i + 1;
+    }
+
+    let sum = 0;
+    let j = 0;
+    loop j < 50 {
+        sum = sum + recv(out);
+        j = j + 1;
+    }
+    sum
+    ";
+    test_pass(t, "1225")?;
+
+    Ok(())
+}
+
+#[test]
+fn test_e2e_try_join() -> Result<()> {
+    let t = r"
+    fn work(n: int) -> int {
+        sleep(n);
+        n
+    }
+
+    let fast = spawn work(0);
+    let slow = spawn work(10000);
+
+    let n = 0;
+    if join_timeout(fast, 5000) {
+        n = n + 1;
+    }
+    if !join_timeout(slow, 20) {
+        n = n + 10;
+    }
+    if !try_join(slow) {
+        n = n + 100;
+    }
+    detach(slow);
+    n
+    ";
+    test_pass(t, "111")?;
+
+    Ok(())
+}
+
+#[test]
+fn test_e2e_inspect() -> Result<()> {
+    let t = r#"
+    struct Node {
+        val: int,
+        next: [int; 2],
+    }
+
+    let node = Node { val: 1, next: [2, 3] };
+    println(inspect(node));
+    println(inspect_depth(node, 1));
+    inspect("done")
+    "#;
+    test_pass(
+        t,
+        "Node {\n  val: 1,\n  next: [\n    2,\n    3,\n  ],\n}\nNode {\n  val: 1,\n  next: [...],\n}\n\"done\"",
+    )?;
+
+    Ok(())
+}
+
+#[test]
+fn test_e2e_format() -> Result<()> {
+    let t = r#"
+    fn inc(x: int) -> int { x + 1 }
+    fn nothing() {}
+    let x = 2;
+    let name = "pt";
+    println(format("{name} = ({x}, {}) {} {} {} {{}}", 1.5, true, nothing(), inc));
+    let fmt = "{} + {} = {}";
+    format(fmt, x, x, inc(x) + 1)
+    "#;
+    test_pass(t, "pt = (2, 1.5) true () closure {}\n2 + 2 = 4")?;
+
+    Ok(())
+}
+
+#[test]
+fn test_e2e_string_lib() -> Result<()> {
+    let t = r#"
+    let line = "  name=Ada, lang=RustScript  ";
+    let fields = split(trim(line), ", ");
+    for field in fields {
+        let eq = find(field, "=");
+        let key = substring(field, 0, eq);
+        let val = substring(field, eq + 1, string_len(field) - eq - 1);
+        println(format("{} {}", to_upper(key), to_lower(val)));
+    }
+    if contains(line, "Rust") {
+        println(replace(line, "Rust", "Oxide"));
+    }
+    slice_len(fields)
+    "#;
+    test_pass(
+        t,
+        "NAME ada\nLANG rustscript\n  name=Ada, lang=OxideScript  \n2",
+    )?;
+
+    Ok(())
+}
+
+#[test]
+fn test_e2e_string_escapes() -> Result<()> {
+    let t = r###"
+    println("tab\there \"quoted\" back\\slash");
+    println("caf\u{e9}\nnext line");
+    let pattern = r"\d+\.\d+";
+    println(pattern);
+    println(string_len(r#"say "hi""#));
+    '\u{263A}'
+    "###;
+    test_pass(
+        t,
+        "tab\there \"quoted\" back\\slash\ncafé\nnext line\n\\d+\\.\\d+\n8\n☺",
+    )?;
+
+    Ok(())
+}
+
+#[test]
+fn test_e2e_char() -> Result<()> {
+    let t = r#"
+    let word = "Héllo";
+    let upper = 0;
+    for c in chars(word) {
+        if c < 'a' {
+            upper = upper + 1;
+        }
+    }
+    println(upper);
+    let first: char = word[0];
+    println(first);
+    println(word[1] == 'é');
+    println(chars(word));
+    // char_at gives None where word[i] would stop the program
+    println(char_at(word, 5));
+    println(char_at(word, 2));
+    println(char_at(word, 6));
+    word[3]
+    "#;
+    test_pass(t, "1\nH\ntrue\n[H, é, l, l, o]\nSome(o)\nNone\nNone\nl")?;
+
+    Ok(())
+}
+
+#[test]
+fn test_e2e_persist() -> Result<()> {
+    let t = r#"
+    let v: pvec[int] = pvec();
+    let i = 0;
+    loop i < 100 {
+        v = persist_push(v, i);
+        i = i + 1;
+    }
+
+    // the old versions are left as they were
+    let old = v;
+    v = persist_set(v, 50, -1);
+    println(persist_get(old, 50) + persist_get(v, 50));
+    println(persist_len(v));
+
+    let m: pmap[str, int] = pmap();
+    let with_a = persist_insert(m, "a", 1);
+    let with_b = persist_insert(with_a, "b", 2);
+    println(with_b);
+    println(persist_contains(persist_remove(with_b, "a"), "a"));
+    persist_len(with_a) + persist_len(m)
+    "#;
+    test_pass(t, "49\n100\n{a: 1, b: 2}\nfalse\n1")?;
+
+    Ok(())
+}
@@ -0,0 +1,164 @@
+use anyhow::Result;
+use assert_cmd::prelude::*;
+use compiler::compiler::compile_with_warnings;
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+const IGNITE_BINARY: &str = "ignite";
+const SPEC_DIR: &str = "../../spec";
+
+// What a spec program says about itself in its header, see spec/README.md
+struct Spec {
+    path: PathBuf,
+    rule: String,
+    expect: Vec<String>,
+    expect_error: Option<String>,
+}
+
+fn parse_spec(path: &Path) -> Result<Spec, String> {
+    let code = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+
+    let mut rule = None;
+    let mut expect = vec![];
+    let mut expect_error = None;
+
+    for line in code.lines().map_while(|line| line.strip_prefix("//")) {
+        let line = line.trim();
+        if let Some(name) = line.strip_prefix("rule:") {
+            rule = Some(name.trim().to_string());
+        } else if let Some(out) = line.strip_prefix("expect:") {
+            expect.push(out.trim().to_string());
+        } else if let Some(err) = line.strip_prefix("expect-error:") {
+            expect_error = Some(err.trim().to_string());
+        }
+    }
+
+    let Some(rule) = rule else {
+        return Err("missing '// rule:' line".to_string());
+    };
+
+    // the area of a rule is the directory it's in
+    let area = path
+        .parent()
+        .and_then(|dir| dir.file_name())
+        .and_then(|dir| dir.to_str())
+        .unwrap_or_default();
+    if !rule.starts_with(&format!("{}.", area)) {
+        return Err(format!("rule '{}' should be named '{}.<rule>'", rule, area));
+    }
+
+    if expect.is_empty() == expect_error.is_none() {
+        return Err("needs either '// expect:' lines or one '// expect-error:' line".to_string());
+    }
+
+    Ok(Spec {
+        path: path.to_path_buf(),
+        rule,
+        expect,
+        expect_error,
+    })
+}
+
+// Compile and run the program, returning what went wrong if it doesn't behave as its header says. Optimizing
+// must not change how a program behaves, so it is checked both ways
+fn check_spec(spec: &Spec) -> Result<(), String> {
+    check_spec_compiled(spec, false)?;
+    check_spec_compiled(spec, true).map_err(|e| format!("optimized, {}", e))
+}
+
+fn check_spec_compiled(spec: &Spec, optimize: bool) -> Result<(), String> {
+    let code = std::fs::read_to_string(&spec.path).map_err(|e| e.to_string())?;
+
+    let compiled = compile_with_warnings(&code, true, &HashSet::new(), optimize).map(|(bc, _)| bc);
+    let bytecode = match (compiled, &spec.expect_error) {
+        (Ok(_), Some(err)) => return Err(format!("compiled, but expected error '{}'", err)),
+        (Err(e), Some(err)) if e.to_string().contains(err) => return Ok(()),
+        (Err(e), _) => return Err(format!("failed to compile: {}", e)),
+        (Ok(bytecode), None) => bytecode,
+    };
+
+    let file_name = format!("./spec-{}.o2", rand::random::<u128>());
+    bytecode::write_to_file(&bytecode, &file_name).map_err(|e| e.to_string())?;
+    let output = Command::cargo_bin(IGNITE_BINARY)
+        .map_err(|e| e.to_string())?
+        .arg(&file_name)
+        .output();
+    std::fs::remove_file(&file_name).map_err(|e| e.to_string())?;
+
+    let output = output.map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(format!(
+            "failed to run: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    if lines != spec.expect {
+        return Err(format!(
+            "expected output {:?} but got {:?}",
+            spec.expect, lines
+        ));
+    }
+
+    Ok(())
+}
+
+fn spec_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            spec_files(&path, files)?;
+        } else if path.extension().is_some_and(|ext| ext == "rst") {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_spec() -> Result<()> {
+    let mut files = vec![];
+    spec_files(Path::new(SPEC_DIR), &mut files)?;
+    files.sort();
+    assert!(!files.is_empty(), "No spec programs found in {}", SPEC_DIR);
+
+    let mut rules: Vec<String> = vec![];
+    let mut failures: Vec<String> = vec![];
+
+    for path in files.iter() {
+        let name = path.strip_prefix("../../").unwrap_or(path).display();
+        let spec = match parse_spec(path) {
+            Ok(spec) => spec,
+            Err(e) => {
+                failures.push(format!("{}: {}", name, e));
+                continue;
+            }
+        };
+
+        if rules.contains(&spec.rule) {
+            failures.push(format!("[{}] {}: rule is already checked", spec.rule, name));
+            continue;
+        }
+        rules.push(spec.rule.to_owned());
+
+        if let Err(e) = check_spec(&spec) {
+            failures.push(format!("[{}] {}: {}", spec.rule, name, e));
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{} of {} spec rules broken:\n{}",
+        failures.len(),
+        files.len(),
+        failures.join("\n")
+    );
+
+    Ok(())
+}
@@ -1,6 +1,7 @@
 use anyhow::Result;
 use assert_cmd::prelude::*;
 use bytecode::ByteCode;
+use compiler::compiler::compile_from_string;
 use predicates::prelude::*;
 use std::process::Command;
 
@@ -52,3 +53,839 @@ fn run_simple_program() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn file_not_bytecode() -> Result<()> {
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+
+    std::fs::write("./not_bytecode.o2", "let x = 2;")?;
+
+    cmd.arg("./not_bytecode.o2");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "Not a bytecode file, the magic bytes are missing",
+    ));
+
+    std::fs::remove_file("./not_bytecode.o2")?;
+
+    Ok(())
+}
+
+#[test]
+fn disasm_program() -> Result<()> {
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+
+    let bytecode = vec![ByteCode::ldc(42), ByteCode::GOTO(2), ByteCode::DONE];
+    bytecode::write_to_file(&bytecode, "./disasm.o2")?;
+
+    // the program is printed, not run, so 42 is not printed as its result
+    cmd.arg("./disasm.o2").arg("--disasm");
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq(" 0: LDC 42\n 1: GOTO -> 2\n>2: DONE\n"));
+
+    std::fs::remove_file("./disasm.o2")?;
+
+    Ok(())
+}
+
+#[test]
+fn migrate_program() -> Result<()> {
+    // a program as the first version of the bytecode wrote it, with a 2 byte version and no builtin set hash
+    let bytecode = vec![
+        ByteCode::ld("println"),
+        ByteCode::ldc(42),
+        ByteCode::CALL(1),
+        ByteCode::DONE,
+    ];
+    let mut serialized = vec![];
+    bytecode::write_bytecode(&bytecode, &mut serialized)?;
+    let mut v1 = bytecode::BYTECODE_MAGIC.to_vec();
+    v1.extend(1u16.to_le_bytes());
+    v1.extend(&serialized[16..]);
+    std::fs::write("./migrate.o2", v1)?;
+
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+    cmd.arg("./migrate.o2");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Unsupported bytecode version 1.0"));
+
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+    cmd.arg("migrate").arg("./migrate.o2");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "Migrated ./migrate.o2 from bytecode version 1.0 to {}",
+            bytecode::BYTECODE_VERSION
+        )));
+
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+    cmd.arg("./migrate.o2");
+    cmd.assert().success().stdout(predicate::eq("42\n"));
+
+    std::fs::remove_file("./migrate.o2")?;
+
+    Ok(())
+}
+
+#[test]
+fn gc_keeps_closure_envs() -> Result<()> {
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+
+    // the env of mk's call is only reachable through the closure bound to add, across many collections
+    let program = r"
+    fn mk(x: int) -> fn(int) -> int {
+        |y: int| x + y
+    }
+    let add = mk(10);
+    let i = 0;
+    loop i < 100 {
+        i = i + 1;
+    }
+    println(add(1));
+    ";
+    let bytecode = compile_from_string(program, true)?;
+    bytecode::write_to_file(&bytecode, "./gc_closure.o2")?;
+
+    cmd.arg("./gc_closure.o2").arg("--gc-interval").arg("0");
+    cmd.assert().success().stdout(predicate::eq("11\n"));
+
+    std::fs::remove_file("./gc_closure.o2")?;
+
+    Ok(())
+}
+
+#[test]
+fn gc_incremental_keeps_closure_envs() -> Result<()> {
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+
+    // every block of the loop is garbage once it exits, and a collection starts while the last is still swept
+    let program = r"
+    fn mk(x: int) -> fn(int) -> int {
+        |y: int| x + y
+    }
+    let add = mk(10);
+    let i = 0;
+    loop i < 100 {
+        let j = i;
+        i = j + 1;
+    }
+    println(add(i));
+    ";
+    let bytecode = compile_from_string(program, true)?;
+    bytecode::write_to_file(&bytecode, "./gc_incremental.o2")?;
+
+    cmd.arg("./gc_incremental.o2")
+        .args(["--gc-threshold", "4", "--gc-incremental", "1"]);
+    cmd.assert().success().stdout(predicate::eq("110\n"));
+
+    std::fs::remove_file("./gc_incremental.o2")?;
+
+    Ok(())
+}
+
+#[test]
+fn gc_keeps_zombie_results() -> Result<()> {
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+
+    // the child is done long before it is joined, and its result is a closure whose env is reachable from
+    // nothing else
+    let program = r"
+    fn mk(x: int) -> fn(int) -> int {
+        |y: int| x + y
+    }
+    fn worker() -> fn(int) -> int {
+        mk(10)
+    }
+    let t = spawn worker();
+    let i = 0;
+    loop i < 100 {
+        i = i + 1;
+        yield;
+    }
+    let f = join t;
+    println(f(1));
+    ";
+    let bytecode = compile_from_string(program, true)?;
+    bytecode::write_to_file(&bytecode, "./gc_zombie.o2")?;
+
+    cmd.arg("./gc_zombie.o2").arg("--gc-interval").arg("0");
+    cmd.assert().success().stdout(predicate::eq("11\n"));
+
+    std::fs::remove_file("./gc_zombie.o2")?;
+
+    Ok(())
+}
+
+#[test]
+fn run_subcommand() -> Result<()> {
+    // a .rst file is compiled and run
+    std::fs::write("./run_sub.rst", "let x: int = 40; x + 2")?;
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+    cmd.arg("run")
+        .arg("./run_sub.rst")
+        .arg("--time-quantum")
+        .arg("5");
+    cmd.assert().success().stdout(predicate::eq("42\n"));
+
+    // the type check can be turned off
+    std::fs::write("./run_sub.rst", "let x: int = true; x")?;
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+    cmd.arg("run").arg("./run_sub.rst");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("[TypeError T002]"));
+
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+    cmd.arg("run").arg("./run_sub.rst").arg("--no-type-check");
+    cmd.assert().success().stdout(predicate::eq("true\n"));
+
+    std::fs::remove_file("./run_sub.rst")?;
+
+    Ok(())
+}
+
+#[test]
+fn quiet_flag() -> Result<()> {
+    // the value of the last expression is printed, unless it is ()
+    std::fs::write("./quiet.rst", "fn f() { println(1); } f()")?;
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+    cmd.arg("run").arg("./quiet.rst");
+    cmd.assert().success().stdout(predicate::eq("1\n"));
+
+    std::fs::write("./quiet.rst", "println(1); 40 + 2")?;
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+    cmd.arg("run").arg("./quiet.rst");
+    cmd.assert().success().stdout(predicate::eq("1\n42\n"));
+
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+    cmd.arg("run").arg("./quiet.rst").arg("--quiet");
+    cmd.assert().success().stdout(predicate::eq("1\n"));
+
+    std::fs::remove_file("./quiet.rst")?;
+
+    Ok(())
+}
+
+#[test]
+fn compile_and_disasm_subcommands() -> Result<()> {
+    std::fs::write("./compile_sub.rst", "42")?;
+
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+    cmd.arg("compile")
+        .arg("./compile_sub.rst")
+        .arg("-o")
+        .arg("./compiled_sub.o2");
+    cmd.assert().success().stdout(predicate::str::contains(
+        "Compiled successfully to ./compiled_sub.o2",
+    ));
+
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+    cmd.arg("disasm").arg("./compiled_sub.o2");
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq(" 0: LDC 42\n 1: DONE\n"));
+
+    // a .o2 file is run as it is
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+    cmd.arg("run").arg("./compiled_sub.o2");
+    cmd.assert().success().stdout(predicate::eq("42\n"));
+
+    // only source is compiled
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+    cmd.arg("compile").arg("./compiled_sub.o2");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("File is not a .rst file"));
+
+    std::fs::remove_file("./compile_sub.rst")?;
+    std::fs::remove_file("./compiled_sub.o2")?;
+
+    Ok(())
+}
+
+#[test]
+fn repl_subcommand() -> Result<()> {
+    // names declared on a line are still bound on the next, even after a line that failed
+    let mut cmd = assert_cmd::Command::cargo_bin(IGNITE_BINARY)?;
+    cmd.arg("repl").write_stdin(
+        "let x = 2;\nx + 1\nlet y: int = true;\nfn sq(n: int) -> int { n * n }\nsq(x)\n/exit\n",
+    );
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("3\n"))
+        .stdout(predicate::str::contains("[TypeError T002]"))
+        .stdout(predicate::str::contains("4\nSee you again!\n"));
+
+    Ok(())
+}
+
+#[test]
+fn allow_net_flag() -> Result<()> {
+    std::fs::write(
+        "./allow_net.rst",
+        r#"match udp_bind("127.0.0.1:0") { Ok(sock) => { close(sock); 42 } Err(_) => 0 }"#,
+    )?;
+
+    // the network is only used if allowed
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+    cmd.arg("run").arg("./allow_net.rst");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("--allow-net"));
+
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+    cmd.arg("run").arg("./allow_net.rst").arg("--allow-net");
+    cmd.assert().success().stdout(predicate::eq("42\n"));
+
+    std::fs::remove_file("./allow_net.rst")?;
+
+    Ok(())
+}
+
+#[test]
+fn allow_run_flag() -> Result<()> {
+    std::fs::write(
+        "./allow_run.rst",
+        r#"match run_command("sh", ["-c", "echo hi; exit 3"], 5000) { Ok(res) => { println(res.stdout); res.code } Err(_) => 0 }"#,
+    )?;
+
+    // other programs are only run if allowed
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+    cmd.arg("run").arg("./allow_run.rst");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("--allow-run"));
+
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+    cmd.arg("run").arg("./allow_run.rst").arg("--allow-run");
+    cmd.assert().success().stdout(predicate::eq("hi\n\n3\n"));
+
+    std::fs::remove_file("./allow_run.rst")?;
+
+    Ok(())
+}
+
+#[test]
+fn step_flag() -> Result<()> {
+    std::fs::write("./step.rst", "let x = 2; x + 1")?;
+
+    // stops at the breakpoint and prints the names there, then runs to the end
+    let mut cmd = assert_cmd::Command::cargo_bin(IGNITE_BINARY)?;
+    cmd.arg("run")
+        .arg("./step.rst")
+        .arg("--step")
+        .write_stdin("break 5\ncontinue\nprint x\ncontinue\n");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "Thread 1, PC 0: ENTERSCOPE [x]\n",
+        ))
+        .stdout(predicate::str::contains("Breakpoint at 5\nThread 1, PC 5"))
+        .stdout(predicate::str::contains("x = 2\n"))
+        .stdout(predicate::str::ends_with("3\n"));
+
+    std::fs::remove_file("./step.rst")?;
+
+    Ok(())
+}
+
+#[test]
+fn trace_flag() -> Result<()> {
+    std::fs::write("./trace.rst", "let x = 2; x")?;
+
+    // without a file the trace goes to stderr, apart from the result
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+    cmd.arg("run").arg("./trace.rst").arg("--trace");
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("2\n"))
+        .stderr(predicate::str::starts_with(
+            "thread 1 pc 0 stack 0 frames 0: ENTERSCOPE [x]\n",
+        ))
+        .stderr(predicate::str::ends_with(
+            "thread 1 pc 7 stack 1 frames 0: DONE\n",
+        ));
+
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+    cmd.arg("run")
+        .arg("./trace.rst")
+        .arg("--trace")
+        .arg("./trace.log");
+    cmd.assert().success().stderr(predicate::str::is_empty());
+    let trace = std::fs::read_to_string("./trace.log")?;
+    assert_eq!(trace.lines().count(), 8);
+
+    std::fs::remove_file("./trace.rst")?;
+    std::fs::remove_file("./trace.log")?;
+
+    Ok(())
+}
+
+#[test]
+fn quantum_instrs_flag() -> Result<()> {
+    std::fs::write(
+        "./quantum_instrs.rst",
+        r"let count = 0;
+fn increment(times: int) {
+  let i = 0;
+  loop i < times {
+    count = count + 1;
+    i = i + 1;
+  }
+}
+let t1 = spawn increment(100);
+let t2 = spawn increment(100);
+join t1;
+join t2;
+count",
+    )?;
+
+    // the threads race on count, but lose the same updates on every run
+    let output = |args: &[&str]| -> Result<String> {
+        let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+        cmd.arg("run").arg("./quantum_instrs.rst").args(args);
+        let out = cmd.assert().success().get_output().stdout.clone();
+        Ok(String::from_utf8(out)?)
+    };
+
+    for args in [
+        ["--quantum-instrs", "7", "--seed", "7"].as_slice(),
+        &["--quantum-instrs", "7"],
+    ] {
+        let first = output(args)?;
+        assert_eq!(first, output(args)?);
+        assert_ne!(first, "200\n");
+    }
+
+    // the seed only picks threads when they are switched after a number of instructions
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+    cmd.arg("run")
+        .arg("./quantum_instrs.rst")
+        .arg("--seed")
+        .arg("7");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("--quantum-instrs"));
+
+    std::fs::remove_file("./quantum_instrs.rst")?;
+
+    Ok(())
+}
+
+#[test]
+fn explain_flag() -> Result<()> {
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+    cmd.arg("--explain").arg("t012");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "T012: Misused thread or mutex\n",
+        ))
+        .stdout(predicate::str::contains("let m = mutex();"));
+
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+    cmd.arg("--explain").arg("T999");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("No error has the code T999"));
+
+    // runtime errors are shown with the code to explain them by
+    std::fs::write("./explain_flag.rst", "let d = 0; 10 / d")?;
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+    cmd.arg("run").arg("./explain_flag.rst");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "[RuntimeError R001]: Division by zero",
+    ));
+
+    std::fs::remove_file("./explain_flag.rst")?;
+
+    Ok(())
+}
+
+#[test]
+fn max_heap_flag() -> Result<()> {
+    // every closure kept alive keeps the environment it was made in
+    std::fs::write(
+        "./max_heap.rst",
+        "let fs: pvec[fn() -> int] = pvec();
+loop {
+    let f = || 1;
+    fs = persist_push(fs, f);
+}",
+    )?;
+
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+    cmd.arg("run")
+        .arg("./max_heap.rst")
+        .arg("--max-heap")
+        .arg("1000");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "[RuntimeError R013]: Out of memory: 1001 environments are live, more than the limit of 1000",
+    ));
+
+    std::fs::remove_file("./max_heap.rst")?;
+
+    Ok(())
+}
+
+#[test]
+fn messages_flag() -> Result<()> {
+    std::fs::write(
+        "./messages_flag.txt",
+        "# German
+[T002] '{}' has declared type {} but assigned type {}
+= '{0}' hat den Typ {1}, aber der zugewiesene Wert hat den Typ {2}
+
+[R001] Division by zero at pc {}
+= Division durch null bei pc {}
+",
+    )?;
+    std::fs::write("./messages_flag.rst", "let x: int = true;")?;
+    std::fs::write("./messages_flag_div.rst", "let d = 0; 10 / d")?;
+
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+    cmd.arg("run")
+        .arg("./messages_flag.rst")
+        .arg("--messages")
+        .arg("./messages_flag.txt");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "[TypeError T002]: 'x' hat den Typ int, aber der zugewiesene Wert hat den Typ bool",
+    ));
+
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+    cmd.arg("run")
+        .arg("./messages_flag_div.rst")
+        .arg("--messages")
+        .arg("./messages_flag.txt");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "[RuntimeError R001]: Division durch null bei pc",
+    ));
+
+    // a catalog that can't be read says where
+    std::fs::write("./messages_flag.txt", "[T002] no translation")?;
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+    cmd.arg("run")
+        .arg("./messages_flag.rst")
+        .arg("--messages")
+        .arg("./messages_flag.txt");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "Bad catalog at line 1: template without a translation after it",
+    ));
+
+    std::fs::remove_file("./messages_flag.txt")?;
+    std::fs::remove_file("./messages_flag.rst")?;
+    std::fs::remove_file("./messages_flag_div.rst")?;
+
+    Ok(())
+}
+
+#[test]
+fn max_call_depth_flag() -> Result<()> {
+    std::fs::write(
+        "./max_call_depth.rst",
+        "fn count(n: int) -> int {
+    1 + count(n + 1)
+}
+count(0)",
+    )?;
+
+    // unbounded recursion stops at the default limit
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+    cmd.arg("run").arg("./max_call_depth.rst");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "[RuntimeError R014]: Stack overflow in thread 1 at pc",
+    ));
+
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+    cmd.arg("run")
+        .arg("./max_call_depth.rst")
+        .arg("--max-call-depth")
+        .arg("50");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "more than 50 frames on the runtime stack",
+    ));
+
+    std::fs::remove_file("./max_call_depth.rst")?;
+
+    Ok(())
+}
+
+#[test]
+fn backtrace() -> Result<()> {
+    std::fs::write(
+        "./backtrace.rst",
+        "fn f(x: int) -> int {
+    10 / x
+}
+fn g(x: int) -> int {
+    f(x - 1) + 1
+}
+g(1)",
+    )?;
+
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+    cmd.arg("run").arg("./backtrace.rst");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "[RuntimeError R001]: Division by zero at pc 5\nstack backtrace of thread 1:",
+        ))
+        .stderr(predicate::str::contains(
+            "   1: fn at line 5, pc 12\n             at line 5, pc 16",
+        ))
+        .stderr(predicate::str::contains(
+            "   2: <top level>\n             at line 7, pc 25",
+        ));
+
+    std::fs::remove_file("./backtrace.rst")?;
+
+    Ok(())
+}
+
+#[test]
+fn test_command() -> Result<()> {
+    std::fs::write(
+        "./test_command.rst",
+        r#"#[deprecated("use double")]
+fn twice(x: int) -> int { x * 2 }
+fn double(x: int) -> int { x + x }
+
+#[test]
+fn test_double() {
+    assert(double(2) == 4, "double(2) should be 4");
+}
+
+#[test]
+fn test_twice() {
+    assert(twice(3) == 7, "twice(3) should be 7");
+}
+
+double(1)"#,
+    )?;
+
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+    cmd.arg("test").arg("./test_command.rst");
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains("running 2 tests"))
+        .stdout(predicate::str::contains("test test_double ... ok"))
+        .stdout(predicate::str::contains("test test_twice ... FAILED"))
+        .stdout(predicate::str::contains(
+            "[RuntimeError R015]: Assertion failed: twice(3) should be 7",
+        ))
+        .stdout(predicate::str::contains(
+            "test result: FAILED. 1 passed; 1 failed",
+        ))
+        .stderr(predicate::str::contains(
+            "[Warning] - 'twice' is deprecated: use double at line 12",
+        ));
+
+    // only the tests run, not the last expression
+    std::fs::write(
+        "./test_command.rst",
+        "#[test]\nfn t() { assert(true, \"t\"); }\nprintln(\"unreachable\");\n1 + 1",
+    )?;
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+    cmd.arg("test").arg("./test_command.rst");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("unreachable"))
+        .stdout(predicate::str::contains(
+            "test result: ok. 1 passed; 0 failed",
+        ))
+        .stdout(predicate::str::contains("2").not());
+
+    std::fs::remove_file("./test_command.rst")?;
+
+    Ok(())
+}
+
+#[test]
+fn strict_flag() -> Result<()> {
+    std::fs::write(
+        "./strict_flag.rst",
+        "let n = 4;
+if n > 2 {
+    let n = 8;
+    println(n);
+}",
+    )?;
+
+    // shadowing is fine outside of strict mode
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+    cmd.arg("run").arg("./strict_flag.rst");
+    cmd.assert().success().stdout(predicate::str::contains("8"));
+
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+    cmd.arg("run").arg("./strict_flag.rst").arg("--strict");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "[CompileError C004] -  'n' shadows a name declared before it at line 3, column 5, and --strict makes warnings errors",
+    ));
+
+    // strict mode relies on the type checker
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+    cmd.arg("compile")
+        .arg("./strict_flag.rst")
+        .arg("--strict")
+        .arg("--no-type-check");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+
+    std::fs::remove_file("./strict_flag.rst")?;
+
+    Ok(())
+}
+
+#[test]
+fn explain_run_flag() -> Result<()> {
+    std::fs::write(
+        "./explain_run.rst",
+        "let x = 4;
+let y = x * 2;
+println(y);",
+    )?;
+
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+    cmd.arg("run").arg("./explain_run.rst").arg("--explain-run");
+    cmd.assert().success().stdout(predicate::str::contains(
+        "line 1: let x = 4;
+  x = 4
+line 2: let y = x * 2;
+  4 * 2 is 8
+  y = 8
+line 3: println(y);
+8
+",
+    ));
+
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+    cmd.arg("run")
+        .arg("./explain_run.rst")
+        .arg("--explain-run=1");
+    cmd.assert().success().stdout(predicate::str::contains(
+        "line 1: let x = 4;
+  x = 4
+... explained 1 statements, the rest runs without explanation
+8
+",
+    ));
+
+    std::fs::remove_file("./explain_run.rst")?;
+
+    Ok(())
+}
+
+#[test]
+fn show_stack_flag() -> Result<()> {
+    std::fs::write(
+        "./show_stack.rst",
+        "fn sq(x: int) -> int { x * x }
+let y = sq(3);
+println(y);",
+    )?;
+
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+    cmd.arg("run")
+        .arg("./show_stack.rst")
+        .arg("--show-stack")
+        .arg("sq");
+    cmd.assert()
+        .success()
+        .stdout("9\n")
+        .stderr(predicate::str::contains(
+            "BINOP *               [3, 3]                    [9]",
+        ));
+
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+    cmd.arg("run")
+        .arg("./show_stack.rst")
+        .arg("--show-stack")
+        .arg("sq")
+        .arg("--stack-format")
+        .arg("json");
+    cmd.assert().success().stderr(predicate::str::contains(
+        "\"after\":[\"9\"],\"before\":[\"3\",\"3\"],\"instr\":\"BINOP *\"",
+    ));
+
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+    cmd.arg("run")
+        .arg("./show_stack.rst")
+        .arg("--show-stack")
+        .arg("cube");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("No fn named cube in the program"));
+
+    std::fs::remove_file("./show_stack.rst")?;
+
+    Ok(())
+}
+
+#[test]
+fn program_args() -> Result<()> {
+    std::fs::write(
+        "./program_args.rst",
+        r#"let argv = args();
+println(slice_len(argv));
+println(argv[1]);
+env_var("IGNITE_TEST_GREETING")"#,
+    )?;
+
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+    cmd.arg("run")
+        .arg("./program_args.rst")
+        .arg("--")
+        .arg("in.txt")
+        .arg("--verbose")
+        .env("IGNITE_TEST_GREETING", "hello");
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("2\n--verbose\nhello\n"));
+
+    std::fs::remove_file("./program_args.rst")?;
+
+    Ok(())
+}
+
+#[test]
+fn on_main_exit_flag() -> Result<()> {
+    std::fs::write(
+        "./on_main_exit.rst",
+        "fn work() {
+    sleep(50);
+    println(1);
+}
+spawn work();",
+    )?;
+
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+    cmd.arg("run").arg("./on_main_exit.rst");
+    cmd.assert()
+        .success()
+        .stdout("")
+        .stderr(predicate::str::contains(
+            "threads 2 were still running when the main thread finished",
+        ));
+
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+    cmd.arg("run")
+        .arg("./on_main_exit.rst")
+        .arg("--on-main-exit")
+        .arg("join-all");
+    cmd.assert().success().stdout("1\n").stderr("");
+
+    let mut cmd = Command::cargo_bin(IGNITE_BINARY)?;
+    cmd.arg("run")
+        .arg("./on_main_exit.rst")
+        .arg("--on-main-exit")
+        .arg("error");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("[RuntimeError R010]"));
+
+    std::fs::remove_file("./on_main_exit.rst")?;
+
+    Ok(())
+}